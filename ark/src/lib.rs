@@ -0,0 +1,626 @@
+//! Conversions between the Groth16 types of bellman and those of
+//! [`ark-groth16`], over BLS12-381.
+//!
+//! Both libraries reduce a circuit to the same QAP: variables are numbered
+//! alike, `ONE` first, then the public inputs and the auxiliary variables,
+//! and each public input is bound by a constraint of its own after those of
+//! the circuit. Keys generated by either library prove and verify with the
+//! other once converted:
+//!
+//! - [`proof_to_ark`] and [`proof_from_ark`] convert proofs, and
+//!   [`verifying_key_to_ark`] and [`verifying_key_from_ark`] verifying keys.
+//! - [`parameters_to_ark`] converts [`Parameters`] into a `ProvingKey`. The
+//!   `A` and `B` queries of [`Parameters`] leave out the variables that do
+//!   not appear in them, so the circuit is synthesized again to put the
+//!   other points back in place. [`parameters_from_ark`] drops the points at
+//!   infinity of a `ProvingKey` instead.
+//! - [`ArkConstraintSystem`] synthesizes a [`Circuit`] into a
+//!   `ConstraintSystemRef` of arkworks, to check it with
+//!   `ConstraintSystem::is_satisfied` against the circuits of arkworks, and
+//!   [`ArkCircuit`] hands it to the parameter generator and prover of
+//!   arkworks as a `ConstraintSynthesizer`.
+//!
+//! Points from arkworks are decoded with the checks of [`Parameters::read`],
+//! so a conversion fails with a [`StructureError`] on a point outside the
+//! prime-order subgroup, or on a point at infinity where bellman never
+//! has one. Parameters over a mixed-radix domain have no counterpart in
+//! arkworks, and fail the length check of `h`.
+//!
+//! arkworks needs a more recent Rust than bellman supports, so these
+//! conversions are a crate of their own, outside of the workspace of
+//! bellman.
+//!
+//! [`ark-groth16`]: https://docs.rs/ark-groth16
+
+use ark_bls12_381::{Bls12_381, Fq, Fq2, Fr};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField as ArkPrimeField};
+use ark_relations::r1cs::{self, ConstraintSynthesizer, ConstraintSystemRef};
+use bls12_381::{Bls12, G1Affine, G2Affine, Scalar};
+use ff::PrimeField;
+use group::prime::PrimeCurveAffine;
+use std::sync::Arc;
+
+use bellman::groth16::exporter::RawCircuit;
+use bellman::groth16::structure::{check_length, query_lengths, StructureError};
+use bellman::groth16::{Parameters, Proof, VerifyingKey};
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The size of an encoded coordinate of the base field.
+const FQ_SIZE: usize = 48;
+
+/// Converts a scalar to arkworks.
+pub fn scalar_to_ark(scalar: &Scalar) -> Fr {
+    Fr::from_le_bytes_mod_order(&scalar.to_repr())
+}
+
+/// Converts a scalar from arkworks.
+pub fn scalar_from_ark(scalar: &Fr) -> Scalar {
+    let mut repr = [0; 32];
+    repr.copy_from_slice(&scalar.into_bigint().to_bytes_le());
+    Scalar::from_repr(repr).expect("scalars of arkworks are reduced")
+}
+
+fn fq_from_be(bytes: &[u8]) -> Fq {
+    Fq::from_be_bytes_mod_order(bytes)
+}
+
+fn fq_to_be(fq: &Fq) -> Vec<u8> {
+    fq.into_bigint().to_bytes_be()
+}
+
+/// Converts a point of G1 to arkworks.
+pub fn g1_to_ark(point: &G1Affine) -> ark_bls12_381::G1Affine {
+    if bool::from(point.is_identity()) {
+        return ark_bls12_381::G1Affine::zero();
+    }
+    // x and y, big-endian, with no flags set for a finite point.
+    let bytes = point.to_uncompressed();
+    ark_bls12_381::G1Affine::new_unchecked(
+        fq_from_be(&bytes[..FQ_SIZE]),
+        fq_from_be(&bytes[FQ_SIZE..]),
+    )
+}
+
+/// Converts a point of G1 from arkworks, or returns `None` if it is not in
+/// the prime-order subgroup.
+pub fn g1_from_ark(point: &ark_bls12_381::G1Affine) -> Option<G1Affine> {
+    let mut bytes = [0; 2 * FQ_SIZE];
+    if point.is_zero() {
+        // The infinity flag.
+        bytes[0] = 0x40;
+    } else {
+        bytes[..FQ_SIZE].copy_from_slice(&fq_to_be(&point.x));
+        bytes[FQ_SIZE..].copy_from_slice(&fq_to_be(&point.y));
+    }
+    G1Affine::from_uncompressed(&bytes).into()
+}
+
+/// Converts a point of G2 to arkworks.
+pub fn g2_to_ark(point: &G2Affine) -> ark_bls12_381::G2Affine {
+    if bool::from(point.is_identity()) {
+        return ark_bls12_381::G2Affine::zero();
+    }
+    // x.c1, x.c0, y.c1 and y.c0, big-endian.
+    let bytes = point.to_uncompressed();
+    let fq = |i: usize| fq_from_be(&bytes[i * FQ_SIZE..(i + 1) * FQ_SIZE]);
+    ark_bls12_381::G2Affine::new_unchecked(Fq2::new(fq(1), fq(0)), Fq2::new(fq(3), fq(2)))
+}
+
+/// Converts a point of G2 from arkworks, or returns `None` if it is not in
+/// the prime-order subgroup.
+pub fn g2_from_ark(point: &ark_bls12_381::G2Affine) -> Option<G2Affine> {
+    let mut bytes = [0; 4 * FQ_SIZE];
+    if point.is_zero() {
+        bytes[0] = 0x40;
+    } else {
+        let coordinates = [&point.x.c1, &point.x.c0, &point.y.c1, &point.y.c0];
+        for (chunk, fq) in bytes.chunks_mut(FQ_SIZE).zip(coordinates.iter()) {
+            chunk.copy_from_slice(&fq_to_be(fq));
+        }
+    }
+    G2Affine::from_uncompressed(&bytes).into()
+}
+
+/// Checks that a point converted from arkworks decoded, and is not the point
+/// at infinity.
+fn checked<G, F>(point: Option<G>, element: F) -> Result<G, StructureError>
+where
+    G: PrimeCurveAffine,
+    F: FnOnce() -> String,
+{
+    match point {
+        Some(point) if bool::from(point.is_identity()) => {
+            Err(StructureError::Identity { element: element() })
+        }
+        Some(point) => Ok(point),
+        None => Err(StructureError::NotInSubgroup { element: element() }),
+    }
+}
+
+/// Converts the points of `query` from arkworks, none of which may be the
+/// point at infinity.
+fn checked_query<A, G>(
+    query: &str,
+    points: &[A],
+    convert: fn(&A) -> Option<G>,
+) -> Result<Vec<G>, StructureError>
+where
+    G: PrimeCurveAffine,
+{
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| checked(convert(point), || format!("{}[{}]", query, i)))
+        .collect()
+}
+
+/// Converts the points of `query` from arkworks, dropping those at infinity
+/// as parameter generation does for the `A` and `B` queries.
+fn dense_query<A, G>(
+    query: &str,
+    points: &[A],
+    convert: fn(&A) -> Option<G>,
+) -> Result<Vec<G>, StructureError>
+where
+    A: AffineRepr,
+    G: PrimeCurveAffine,
+{
+    points
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| !point.is_zero())
+        .map(|(i, point)| checked(convert(point), || format!("{}[{}]", query, i)))
+        .collect()
+}
+
+/// Puts the points of a query of [`Parameters`] back in place of the
+/// variables that appear in it, leaving the point at infinity for the
+/// others.
+fn sparse_query<G, A, I>(points: &[G], used: I, convert: fn(&G) -> A) -> Vec<A>
+where
+    A: AffineRepr,
+    I: Iterator<Item = bool>,
+{
+    let mut points = points.iter();
+    used.map(|used| {
+        if used {
+            convert(points.next().expect("query lengths were checked"))
+        } else {
+            A::zero()
+        }
+    })
+    .collect()
+}
+
+/// Converts a proof to arkworks.
+pub fn proof_to_ark(proof: &Proof<Bls12>) -> ark_groth16::Proof<Bls12_381> {
+    ark_groth16::Proof {
+        a: g1_to_ark(&proof.a),
+        b: g2_to_ark(&proof.b),
+        c: g1_to_ark(&proof.c),
+    }
+}
+
+/// Converts a proof from arkworks, checking its points as [`Proof::read`]
+/// does.
+pub fn proof_from_ark(
+    proof: &ark_groth16::Proof<Bls12_381>,
+) -> Result<Proof<Bls12>, StructureError> {
+    Ok(Proof {
+        a: checked(g1_from_ark(&proof.a), || "a".to_string())?,
+        b: checked(g2_from_ark(&proof.b), || "b".to_string())?,
+        c: checked(g1_from_ark(&proof.c), || "c".to_string())?,
+    })
+}
+
+/// Converts a verifying key to arkworks, which leaves out `beta_g1` and
+/// `delta_g1`.
+pub fn verifying_key_to_ark(vk: &VerifyingKey<Bls12>) -> ark_groth16::VerifyingKey<Bls12_381> {
+    ark_groth16::VerifyingKey {
+        alpha_g1: g1_to_ark(&vk.alpha_g1),
+        beta_g2: g2_to_ark(&vk.beta_g2),
+        gamma_g2: g2_to_ark(&vk.gamma_g2),
+        delta_g2: g2_to_ark(&vk.delta_g2),
+        gamma_abc_g1: vk.ic.iter().map(g1_to_ark).collect(),
+    }
+}
+
+/// Converts a verifying key from arkworks.
+///
+/// The verifying key of arkworks has no `beta_g1` or `delta_g1`, which the
+/// verifier does not use, so they are left as the point at infinity: the
+/// key verifies proofs, but is not [`VerifyingKey::is_well_formed`], and its
+/// [`VerifyingKey::hash`] differs from that of the key of the parameters.
+/// [`parameters_from_ark`] converts the whole key.
+pub fn verifying_key_from_ark(
+    vk: &ark_groth16::VerifyingKey<Bls12_381>,
+) -> Result<VerifyingKey<Bls12>, StructureError> {
+    Ok(VerifyingKey {
+        alpha_g1: checked(g1_from_ark(&vk.alpha_g1), || "alpha_g1".to_string())?,
+        beta_g1: G1Affine::identity(),
+        beta_g2: checked(g2_from_ark(&vk.beta_g2), || "beta_g2".to_string())?,
+        gamma_g2: checked(g2_from_ark(&vk.gamma_g2), || "gamma_g2".to_string())?,
+        delta_g1: G1Affine::identity(),
+        delta_g2: checked(g2_from_ark(&vk.delta_g2), || "delta_g2".to_string())?,
+        ic: checked_query("ic", &vk.gamma_abc_g1, g1_from_ark)?,
+    })
+}
+
+/// Converts parameters generated for `circuit` into a proving key of
+/// arkworks.
+pub fn parameters_to_ark<C: Circuit<Scalar>>(
+    params: &Parameters<Bls12>,
+    circuit: C,
+) -> Result<ark_groth16::ProvingKey<Bls12_381>, StructureError> {
+    let circuit = RawCircuit::synthesize(circuit)?;
+    let lengths = query_lengths(&circuit);
+    for &(query, actual) in [
+        ("ic", params.vk.ic.len()),
+        ("l", params.l.len()),
+        ("h", params.h.len()),
+        ("a", params.a.len()),
+        ("b_g1", params.b_g1.len()),
+        ("b_g2", params.b_g2.len()),
+    ]
+    .iter()
+    {
+        check_length(&lengths, query, actual)?;
+    }
+
+    // Every input appears in A through the constraint that binds it.
+    let a_used = || {
+        circuit
+            .at_inputs
+            .iter()
+            .map(|_| true)
+            .chain(circuit.at_aux.iter().map(|c| !c.is_empty()))
+    };
+    let b_used = || {
+        circuit
+            .bt_inputs
+            .iter()
+            .chain(&circuit.bt_aux)
+            .map(|c| !c.is_empty())
+    };
+
+    Ok(ark_groth16::ProvingKey {
+        vk: verifying_key_to_ark(&params.vk),
+        beta_g1: g1_to_ark(&params.vk.beta_g1),
+        delta_g1: g1_to_ark(&params.vk.delta_g1),
+        a_query: sparse_query(&params.a, a_used(), g1_to_ark),
+        b_g1_query: sparse_query(&params.b_g1, b_used(), g1_to_ark),
+        b_g2_query: sparse_query(&params.b_g2, b_used(), g2_to_ark),
+        h_query: params.h.iter().map(g1_to_ark).collect(),
+        l_query: params.l.iter().map(g1_to_ark).collect(),
+    })
+}
+
+/// Converts a proving key of arkworks into parameters, without circuit
+/// statistics.
+pub fn parameters_from_ark(
+    pk: &ark_groth16::ProvingKey<Bls12_381>,
+) -> Result<Parameters<Bls12>, StructureError> {
+    let vk = VerifyingKey {
+        beta_g1: checked(g1_from_ark(&pk.beta_g1), || "beta_g1".to_string())?,
+        delta_g1: checked(g1_from_ark(&pk.delta_g1), || "delta_g1".to_string())?,
+        ..verifying_key_from_ark(&pk.vk)?
+    };
+
+    Ok(Parameters {
+        vk,
+        h: Arc::new(checked_query("h", &pk.h_query, g1_from_ark)?),
+        l: Arc::new(checked_query("l", &pk.l_query, g1_from_ark)?),
+        a: Arc::new(dense_query("a", &pk.a_query, g1_from_ark)?),
+        b_g1: Arc::new(dense_query("b_g1", &pk.b_g1_query, g1_from_ark)?),
+        b_g2: Arc::new(dense_query("b_g2", &pk.b_g2_query, g2_from_ark)?),
+    })
+}
+
+/// Converts an error of arkworks raised while synthesizing.
+fn from_ark_error(e: r1cs::SynthesisError) -> SynthesisError {
+    match e {
+        r1cs::SynthesisError::AssignmentMissing => SynthesisError::AssignmentMissing,
+        r1cs::SynthesisError::DivisionByZero => SynthesisError::DivisionByZero,
+        r1cs::SynthesisError::Unsatisfiable => SynthesisError::Unsatisfiable,
+        r1cs::SynthesisError::PolynomialDegreeTooLarge => SynthesisError::PolynomialDegreeTooLarge,
+        r1cs::SynthesisError::UnexpectedIdentity => SynthesisError::UnexpectedIdentity,
+        r1cs::SynthesisError::UnconstrainedVariable => SynthesisError::UnconstrainedVariable,
+        // The remaining errors concern keys or a missing constraint system,
+        // which the adapter never synthesizes into.
+        _ => SynthesisError::Unsatisfiable,
+    }
+}
+
+/// Converts an error of a circuit for arkworks.
+fn to_ark_error(e: SynthesisError) -> r1cs::SynthesisError {
+    match e {
+        SynthesisError::AssignmentMissing => r1cs::SynthesisError::AssignmentMissing,
+        SynthesisError::DivisionByZero => r1cs::SynthesisError::DivisionByZero,
+        SynthesisError::Unsatisfiable => r1cs::SynthesisError::Unsatisfiable,
+        SynthesisError::PolynomialDegreeTooLarge => r1cs::SynthesisError::PolynomialDegreeTooLarge,
+        SynthesisError::UnexpectedIdentity => r1cs::SynthesisError::UnexpectedIdentity,
+        SynthesisError::UnconstrainedVariable => r1cs::SynthesisError::UnconstrainedVariable,
+        // arkworks has no errors for I/O or budgets: the witness could not
+        // be computed.
+        _ => r1cs::SynthesisError::AssignmentMissing,
+    }
+}
+
+/// A [`ConstraintSystem`] that synthesizes a circuit into a
+/// `ConstraintSystemRef` of arkworks.
+///
+/// The inputs of the circuit become instance variables and its auxiliary
+/// variables witness variables, after those already in the constraint
+/// system, and `ONE` is the constant of arkworks. Whether assignments are
+/// computed depends on the mode of the constraint system. Namespaces are not
+/// kept.
+pub struct ArkConstraintSystem {
+    cs: ConstraintSystemRef<Fr>,
+    instance_offset: usize,
+    witness_offset: usize,
+}
+
+impl ArkConstraintSystem {
+    /// Returns a constraint system adding to `cs`, or `None` if `cs` is
+    /// `ConstraintSystemRef::None`.
+    pub fn new(cs: ConstraintSystemRef<Fr>) -> Option<Self> {
+        if cs.is_none() {
+            return None;
+        }
+        Some(ArkConstraintSystem {
+            instance_offset: cs.num_instance_variables(),
+            witness_offset: cs.num_witness_variables(),
+            cs,
+        })
+    }
+
+    /// Returns the constraint system of arkworks.
+    pub fn into_inner(self) -> ConstraintSystemRef<Fr> {
+        self.cs
+    }
+
+    fn variable(&self, variable: Variable) -> r1cs::Variable {
+        match variable.get_unchecked() {
+            Index::Input(0) => r1cs::Variable::One,
+            Index::Input(i) => r1cs::Variable::Instance(self.instance_offset + i - 1),
+            Index::Aux(i) => r1cs::Variable::Witness(self.witness_offset + i),
+        }
+    }
+
+    fn lc(&self, lc: LinearCombination<Scalar>) -> r1cs::LinearCombination<Fr> {
+        r1cs::LinearCombination(
+            lc.as_ref()
+                .iter()
+                .map(|(var, coeff)| (scalar_to_ark(coeff), self.variable(*var)))
+                .collect(),
+        )
+    }
+
+    /// Allocates an instance or witness variable of arkworks, keeping the
+    /// error of the circuit if its assignment fails.
+    fn allocate<F>(&self, input: bool, f: F) -> Result<r1cs::Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+    {
+        let mut error = None;
+        let value = || {
+            f().map(|value| scalar_to_ark(&value)).map_err(|e| {
+                error = Some(e);
+                r1cs::SynthesisError::AssignmentMissing
+            })
+        };
+        let variable = if input {
+            self.cs.new_input_variable(value)
+        } else {
+            self.cs.new_witness_variable(value)
+        };
+        match error {
+            Some(e) => Err(e),
+            None => variable.map_err(from_ark_error),
+        }
+    }
+}
+
+impl ConstraintSystem<Scalar> for ArkConstraintSystem {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        match self.allocate(false, f)? {
+            r1cs::Variable::Witness(i) => {
+                Ok(Variable::new_unchecked(Index::Aux(i - self.witness_offset)))
+            }
+            _ => unreachable!("arkworks allocates witness variables"),
+        }
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        match self.allocate(true, f)? {
+            r1cs::Variable::Instance(i) => Ok(Variable::new_unchecked(Index::Input(
+                i - self.instance_offset + 1,
+            ))),
+            _ => unreachable!("arkworks allocates instance variables"),
+        }
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let a = self.lc(a(LinearCombination::zero()));
+        let b = self.lc(b(LinearCombination::zero()));
+        let c = self.lc(c(LinearCombination::zero()));
+        self.cs
+            .enforce_constraint(a, b, c)
+            .expect("the constraint system is not None");
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; arkworks names constraints by tracing spans instead.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; arkworks names constraints by tracing spans instead.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// A [`Circuit`] as a `ConstraintSynthesizer` of arkworks, so that
+/// arkworks generates parameters and proofs for it.
+pub struct ArkCircuit<C>(pub C);
+
+impl<C: Circuit<Scalar>> ConstraintSynthesizer<Fr> for ArkCircuit<C> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> r1cs::Result<()> {
+        let mut cs = ArkConstraintSystem::new(cs).ok_or(r1cs::SynthesisError::MissingCS)?;
+        self.0.synthesize(&mut cs).map_err(to_ark_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_groth16::Groth16;
+    use ark_relations::r1cs::ConstraintSystem as ArkCs;
+    use ark_snark::SNARK;
+    use bellman::groth16::exporter::ReplayCircuit;
+    use bellman::groth16::fuzz::{random_circuit, CircuitConfig};
+    use bellman::groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use ff::Field;
+    use group::{Curve, Group};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    #[test]
+    fn points_round_trip() {
+        let mut rng = rng();
+        for _ in 0..10 {
+            let scalar = Scalar::random(&mut rng);
+            assert_eq!(scalar_from_ark(&scalar_to_ark(&scalar)), scalar);
+
+            let g1 = bls12_381::G1Projective::random(&mut rng).to_affine();
+            assert_eq!(g1_from_ark(&g1_to_ark(&g1)), Some(g1));
+            let g2 = bls12_381::G2Projective::random(&mut rng).to_affine();
+            assert_eq!(g2_from_ark(&g2_to_ark(&g2)), Some(g2));
+        }
+
+        assert!(g1_to_ark(&G1Affine::identity()).is_zero());
+        assert_eq!(
+            g2_from_ark(&ark_bls12_381::G2Affine::zero()),
+            Some(G2Affine::identity())
+        );
+        assert_eq!(scalar_to_ark(&Scalar::one().double()), Fr::from(2u64));
+        assert_eq!(
+            g1_to_ark(&G1Affine::generator()),
+            ark_bls12_381::G1Affine::generator()
+        );
+        assert_eq!(
+            g2_to_ark(&G2Affine::generator()),
+            ark_bls12_381::G2Affine::generator()
+        );
+    }
+
+    #[test]
+    fn cross_prove() {
+        let mut rng = rng();
+        let (circuit, assignment) =
+            random_circuit::<Scalar, _>(&CircuitConfig::default(), &mut rng);
+        let replay = |assignment: Option<_>| ReplayCircuit {
+            circuit: circuit.clone(),
+            assignment,
+        };
+        let inputs = &assignment.inputs[1..];
+        let ark_inputs = inputs.iter().map(scalar_to_ark).collect::<Vec<_>>();
+
+        // The circuit synthesizes into arkworks with the same assignment.
+        let cs = ArkCs::<Fr>::new_ref();
+        replay(Some(assignment.clone()))
+            .synthesize(&mut ArkConstraintSystem::new(cs.clone()).unwrap())
+            .unwrap();
+        assert_eq!(cs.num_instance_variables(), circuit.num_inputs);
+        assert_eq!(cs.num_witness_variables(), circuit.num_aux);
+        assert_eq!(cs.num_constraints(), circuit.num_constraints);
+        assert!(cs.is_satisfied().unwrap());
+
+        let params = generate_random_parameters::<Bls12, _, _>(replay(None), &mut rng).unwrap();
+        let pk = parameters_to_ark(&params, replay(None)).unwrap();
+        assert!(parameters_from_ark(&pk).unwrap() == params);
+
+        // A proof of bellman verifies with arkworks.
+        let proof =
+            create_random_proof(replay(Some(assignment.clone())), &params, &mut rng).unwrap();
+        let ark_pvk = Groth16::<Bls12_381>::process_vk(&pk.vk).unwrap();
+        assert!(
+            Groth16::<Bls12_381>::verify_proof(&ark_pvk, &proof_to_ark(&proof), &ark_inputs)
+                .unwrap()
+        );
+
+        // A proof of arkworks verifies with bellman, with the key of
+        // arkworks.
+        let ark_proof = Groth16::<Bls12_381>::create_random_proof_with_reduction(
+            ArkCircuit(replay(Some(assignment.clone()))),
+            &pk,
+            &mut ark_std::test_rng(),
+        )
+        .unwrap();
+        let proof = proof_from_ark(&ark_proof).unwrap();
+        let vk = verifying_key_from_ark(&pk.vk).unwrap();
+        assert!(verify_proof(&prepare_verifying_key(&vk), &proof, inputs).is_ok());
+        assert!(!vk.is_well_formed());
+
+        // Parameters for another circuit do not convert.
+        let (other, _) = random_circuit::<Scalar, _>(
+            &CircuitConfig {
+                num_aux: CircuitConfig::default().num_aux + 1,
+                ..CircuitConfig::default()
+            },
+            &mut rng,
+        );
+        assert!(matches!(
+            parameters_to_ark(
+                &params,
+                ReplayCircuit {
+                    circuit: other,
+                    assignment: None,
+                }
+            ),
+            Err(StructureError::Length { query: "l", .. })
+        ));
+
+        let mut tampered = proof_to_ark(&proof);
+        tampered.c = ark_bls12_381::G1Affine::zero();
+        assert_eq!(
+            proof_from_ark(&tampered).err().unwrap().to_string(),
+            "c is the point at infinity"
+        );
+    }
+}
@@ -0,0 +1,393 @@
+//! A command-line interface to the Groth16 prover over BLS12-381.
+//!
+//! `keygen`, `prove` and `verify` can use another proof system of the crate,
+//! named by `--system` before the command.
+//!
+//! Circuits are exchanged as files written with [`RawCircuit::write`], and
+//! witnesses as files written with [`Assignment::write`], so that a circuit
+//! author can export them from Rust once and the rest of the flow can run
+//! from scripts or CI pipelines.
+//!
+//! [`RawCircuit::write`]: bellman::groth16::exporter::RawCircuit::write
+//! [`Assignment::write`]: bellman::groth16::exporter::Assignment::write
+
+use bellman::groth16::ceremony::{verify_transcript, Contribution};
+use bellman::groth16::exporter::{
+    Assignment, Convention, Form, Negatives, R1CSExport, RawCircuit, ReplayCircuit,
+};
+use bellman::groth16::lint::LintInputs;
+use bellman::groth16::stream::{prove_stream, StreamConfig};
+use bellman::groth16::vectors::{generate, write_vectors};
+use bellman::groth16::Parameters;
+use bellman::proof_system::Backend;
+use bellman::{Index, LinearCombination, SynthesisError};
+use bls12_381::{Bls12, Scalar};
+use rand_core::OsRng;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process;
+use std::sync::{Arc, Mutex};
+
+const USAGE: &str = "\
+usage: bellman-cli [--system <system>] <command> [<args>]
+
+systems:
+    groth16 (the default), sonic
+
+commands:
+    info    <circuit>                                print the size of a circuit
+    export  [--json] <circuit> [<out>]               print the constraints of a circuit, or
+                                                     write them as JSON
+    check   <circuit> <witness>                      check that a witness satisfies a circuit
+    lint    <params> <circuit> <witness>             check that parameters, a circuit and a witness
+                                                     belong together
+    keygen  [--montgomery] [--sign-bit] <circuit> <params> <vk>
+                                                     generate parameters and a verifying key, for
+                                                     a circuit whose coefficients are in Montgomery
+                                                     form or have negatives as a sign bit
+    prove   <params> <circuit> <witness> <proof> <public>
+                                                     create a proof and write its public inputs
+    prove-batch <params> <circuit> <memory-mb> <witness>...
+                                                     prove many witnesses, writing <witness>.proof
+                                                     and <witness>.public for each
+    verify  <vk> <proof> <public>                    verify a proof against its public inputs
+    vectors <seed> <out>                             write the conformance test vectors for a seed
+    ceremony <circuit> <initial> <final> [<contribution>...]
+                                                     verify a phase-2 ceremony transcript
+";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let (system, args) = match args.as_slice() {
+        ["--system", name, rest @ ..] => match Backend::from_name(name) {
+            Some(system) => (system, rest.to_vec()),
+            None => {
+                eprintln!("error: unsupported proof system {}", name);
+                process::exit(2);
+            }
+        },
+        _ => (Backend::Groth16, args),
+    };
+
+    let result = match args.as_slice() {
+        ["info", circuit] => info(circuit),
+        ["export", "--json", circuit] => export(circuit, None, true),
+        ["export", "--json", circuit, out] => export(circuit, Some(out), true),
+        ["export", circuit] => export(circuit, None, false),
+        ["export", circuit, out] => export(circuit, Some(out), false),
+        ["check", circuit, witness] => check(circuit, witness),
+        ["lint", params, circuit, witness] => lint(params, circuit, witness),
+        ["keygen", rest @ ..] => match coefficient_flags(rest) {
+            (convention, [circuit, params, vk]) => keygen(system, convention, circuit, params, vk),
+            _ => usage(),
+        },
+        ["prove", params, circuit, witness, proof, public] => {
+            prove(system, params, circuit, witness, proof, public)
+        }
+        ["prove-batch", params, circuit, memory, witnesses @ ..] if !witnesses.is_empty() => {
+            prove_batch(system, params, circuit, memory, witnesses)
+        }
+        ["verify", vk, proof, public] => verify(system, vk, proof, public),
+        ["vectors", seed, out] => vectors(seed, out),
+        ["ceremony", circuit, initial, params, contributions @ ..] => {
+            ceremony(circuit, initial, params, contributions)
+        }
+        _ => usage(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprint!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Strips the leading flags that name the coefficient convention of a
+/// circuit file.
+fn coefficient_flags<'a>(mut args: &'a [&'a str]) -> (Convention, &'a [&'a str]) {
+    let mut convention = Convention::default();
+    loop {
+        match args {
+            ["--montgomery", rest @ ..] => {
+                convention.form = Form::Montgomery;
+                args = rest;
+            }
+            ["--sign-bit", rest @ ..] => {
+                convention.negatives = Negatives::SignBit;
+                args = rest;
+            }
+            _ => return (convention, args),
+        }
+    }
+}
+
+fn error<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn synthesis_error(e: SynthesisError) -> io::Error {
+    bellman::error::Error::from(e).into()
+}
+
+fn open(path: &str) -> io::Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn create(path: &str) -> io::Result<BufWriter<File>> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn read(path: &str) -> io::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn write(path: &str, bytes: &[u8]) -> io::Result<()> {
+    let mut writer = create(path)?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+fn read_circuit(path: &str) -> io::Result<RawCircuit<Scalar>> {
+    RawCircuit::read(open(path)?)
+}
+
+fn read_witness(path: &str) -> io::Result<Assignment<Scalar>> {
+    Assignment::read(open(path)?)
+}
+
+fn info(circuit: &str) -> io::Result<()> {
+    let circuit = read_circuit(circuit)?;
+    println!("inputs: {}", circuit.num_inputs);
+    println!("aux: {}", circuit.num_aux);
+    println!("constraints: {}", circuit.num_constraints);
+
+    Ok(())
+}
+
+fn export(circuit: &str, out: Option<&str>, json: bool) -> io::Result<()> {
+    let circuit = read_circuit(circuit)?;
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    if json {
+        R1CSExport::new(&circuit).write_json(&mut writer)?;
+        return writer.flush();
+    }
+
+    let format = |lc: &LinearCombination<Scalar>| {
+        let terms = lc
+            .as_ref()
+            .iter()
+            .map(|(var, coeff)| match var.get_unchecked() {
+                Index::Input(i) => format!("{:?}*input{}", coeff, i),
+                Index::Aux(i) => format!("{:?}*aux{}", coeff, i),
+            })
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            "0".to_string()
+        } else {
+            terms.join(" + ")
+        }
+    };
+    for [a, b, c] in circuit.constraints().iter() {
+        writeln!(
+            writer,
+            "({}) * ({}) = ({})",
+            format(a),
+            format(b),
+            format(c)
+        )?;
+    }
+
+    writer.flush()
+}
+
+fn check(circuit: &str, witness: &str) -> io::Result<()> {
+    let circuit = read_circuit(circuit)?;
+    let witness = read_witness(witness)?;
+
+    if witness.is_satisfied(&circuit) {
+        println!("satisfied");
+        Ok(())
+    } else {
+        Err(error("the witness does not satisfy the circuit"))
+    }
+}
+
+fn lint(params: &str, circuit: &str, witness: &str) -> io::Result<()> {
+    let read = |path: &str| {
+        fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+    };
+    let (params, circuit, witness) = (read(params)?, read(circuit)?, read(witness)?);
+
+    let report = bellman::groth16::lint::lint::<Bls12>(&LintInputs {
+        parameters: Some(&params),
+        circuit: Some(&circuit),
+        witness: Some(&witness),
+        ..LintInputs::default()
+    });
+    print!("{}", report);
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(error("the files do not belong together"))
+    }
+}
+
+fn keygen(
+    system: Backend,
+    convention: Convention,
+    circuit: &str,
+    params: &str,
+    vk: &str,
+) -> io::Result<()> {
+    let circuit = ReplayCircuit {
+        circuit: RawCircuit::read_with(open(circuit)?, &convention)?,
+        assignment: None,
+    };
+
+    let (pk, vk_bytes) = system.setup::<Bls12, _, _>(circuit, &mut OsRng)?;
+    write(params, &pk)?;
+    write(vk, &vk_bytes)
+}
+
+fn prove(
+    system: Backend,
+    params: &str,
+    circuit: &str,
+    witness: &str,
+    proof: &str,
+    public: &str,
+) -> io::Result<()> {
+    let pk = read(params)?;
+    let witness = read_witness(witness)?;
+    let circuit = ReplayCircuit {
+        circuit: read_circuit(circuit)?,
+        assignment: Some(witness.clone()),
+    };
+
+    let created = system.prove::<Bls12, _, _>(&pk, circuit, &mut OsRng)?;
+    write(proof, &created)?;
+    write_public(public, &witness)
+}
+
+fn write_public(path: &str, witness: &Assignment<Scalar>) -> io::Result<()> {
+    let public_inputs = Assignment {
+        inputs: witness.inputs.clone(),
+        aux: vec![],
+    };
+    let mut writer = create(path)?;
+    public_inputs.write(&mut writer)?;
+    writer.flush()
+}
+
+fn prove_batch(
+    system: Backend,
+    params: &str,
+    circuit: &str,
+    memory: &str,
+    witnesses: &[&str],
+) -> io::Result<()> {
+    if system != Backend::Groth16 {
+        return Err(error("prove-batch only supports groth16"));
+    }
+    let memory = memory
+        .parse::<usize>()
+        .map_err(|_| error(format!("invalid memory budget {}", memory)))?;
+    let params = Parameters::<Bls12>::read(open(params)?, true)?;
+    let circuit = read_circuit(circuit)?;
+    let config = StreamConfig {
+        memory_budget: memory << 20,
+        ..StreamConfig::default()
+    };
+
+    // Witnesses are read as the stream asks for them, and the first that
+    // cannot be read ends it.
+    let failure = Arc::new(Mutex::new(None));
+    let paths = witnesses.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+    let reader_failure = failure.clone();
+    let input = paths
+        .clone()
+        .into_iter()
+        .scan((), move |_, path| match read_witness(&path) {
+            Ok(witness) => Some(witness),
+            Err(e) => {
+                *reader_failure.lock().unwrap() = Some(e);
+                None
+            }
+        });
+
+    let proofs = prove_stream(Arc::new(params), Arc::new(circuit), input, OsRng, &config);
+    for (path, proof) in paths.iter().zip(proofs) {
+        let proof = proof.map_err(|e| {
+            let e = synthesis_error(e);
+            io::Error::new(e.kind(), format!("{}: {}", path, e))
+        })?;
+        let mut writer = create(&format!("{}.proof", path))?;
+        proof.write(&mut writer)?;
+        writer.flush()?;
+        write_public(&format!("{}.public", path), &read_witness(path)?)?;
+    }
+
+    let failure = failure.lock().unwrap().take();
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn verify(system: Backend, vk: &str, proof: &str, public: &str) -> io::Result<()> {
+    let vk = read(vk)?;
+    let proof = read(proof)?;
+    let public = read_witness(public)?;
+
+    // The public input file includes ONE, which the verifier adds itself.
+    if public.inputs.is_empty() {
+        return Err(error("public input file is empty"));
+    }
+
+    match system.verify::<Bls12>(&vk, &proof, &public.inputs[1..]) {
+        Ok(()) => {
+            println!("valid");
+            Ok(())
+        }
+        Err(e) => Err(error(e)),
+    }
+}
+
+fn vectors(seed: &str, out: &str) -> io::Result<()> {
+    let vectors = generate::<Bls12>(seed.as_bytes()).map_err(synthesis_error)?;
+
+    let mut writer = create(out)?;
+    write_vectors(&vectors, &mut writer)?;
+    writer.flush()
+}
+
+fn ceremony(circuit: &str, initial: &str, params: &str, contributions: &[&str]) -> io::Result<()> {
+    let circuit = read_circuit(circuit)?;
+    let initial = Parameters::<Bls12>::read(open(initial)?, true)?;
+    let params = Parameters::<Bls12>::read(open(params)?, true)?;
+    let contributions = contributions
+        .iter()
+        .map(|path| Contribution::read(open(path)?))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let report = verify_transcript(&circuit, &initial, &contributions, &params, &mut OsRng);
+    print!("{}", report);
+    if report.is_valid() {
+        Ok(())
+    } else {
+        Err(error("the transcript is invalid"))
+    }
+}
@@ -0,0 +1,682 @@
+//! The BN254 pairing-friendly curve, also known as alt_bn128 or BN256, whose
+//! pairing the EVM exposes through the precompiles of EIP-196 and EIP-197.
+//!
+//! [`Bn254`] implements [`Engine`] and [`MultiMillerLoop`], so that the
+//! generic protocols of this crate, such as [`groth16`](crate::groth16), can
+//! prove and verify on it. Its points and scalars match those of Ethereum:
+//! G1 is generated by `(1, 2)` and G2 by the generator of EIP-197, and
+//! [`EvmEngine`](crate::groth16::solidity::EvmEngine) encodes them as the
+//! precompiles expect.
+//!
+//! The pairing is the optimal ate pairing, with a Miller loop over `6u + 2`
+//! for the parameter `u = 4965661367192848881` of the curve. The arithmetic
+//! is written for correctness rather than speed: it has no assembly and no
+//! dedicated cyclotomic squarings, and it is not audited for constant time
+//! beyond the scalar multiplications of the groups.
+
+use group::Group;
+use pairing::{Engine, MillerLoopResult as _, MultiMillerLoop, PairingCurveAffine};
+use rand_core::RngCore;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use subtle::{Choice, ConstantTimeEq};
+
+macro_rules! impl_binop {
+    ($lhs:ty, $rhs:ty, $output:ty, $method:path, $op:ident, $op_fn:ident) => {
+        impl<'a, 'b> $op<&'b $rhs> for &'a $lhs {
+            type Output = $output;
+
+            fn $op_fn(self, rhs: &'b $rhs) -> $output {
+                $method(self, rhs)
+            }
+        }
+
+        impl<'b> $op<&'b $rhs> for $lhs {
+            type Output = $output;
+
+            fn $op_fn(self, rhs: &'b $rhs) -> $output {
+                $method(&self, rhs)
+            }
+        }
+
+        impl<'a> $op<$rhs> for &'a $lhs {
+            type Output = $output;
+
+            fn $op_fn(self, rhs: $rhs) -> $output {
+                $method(self, &rhs)
+            }
+        }
+
+        impl $op<$rhs> for $lhs {
+            type Output = $output;
+
+            fn $op_fn(self, rhs: $rhs) -> $output {
+                $method(&self, &rhs)
+            }
+        }
+    };
+}
+
+macro_rules! impl_assign {
+    ($lhs:ty, $rhs:ty, $method:path, $op:ident, $op_fn:ident) => {
+        impl<'b> $op<&'b $rhs> for $lhs {
+            fn $op_fn(&mut self, rhs: &'b $rhs) {
+                *self = $method(self, rhs);
+            }
+        }
+
+        impl $op<$rhs> for $lhs {
+            fn $op_fn(&mut self, rhs: $rhs) {
+                *self = $method(self, &rhs);
+            }
+        }
+    };
+}
+
+mod curves;
+mod fields;
+mod tower;
+
+pub use self::curves::{
+    G1Affine, G1Compressed, G1Projective, G1Uncompressed, G2Affine, G2Compressed, G2Projective,
+    G2Uncompressed,
+};
+pub use self::fields::{Fp, Fr};
+pub use self::tower::{Fp12, Fp2, Fp6};
+
+/// `6u + 2`, the length of the Miller loop.
+const ATE_LOOP_COUNT: u128 = 29_793_968_203_157_093_288;
+
+/// `ξ^((p - 1) / 3)` and `ξ^((p - 1) / 2)`, which map the Frobenius
+/// endomorphism of the curve to the twist.
+const FROBENIUS_X: Fp2 = Fp2 {
+    c0: Fp::from_raw([
+        0x99e3_9557_176f_553d,
+        0xb78c_c310_c2c3_330c,
+        0x4c0b_ec3c_f559_b143,
+        0x2fb3_4798_4f79_11f7,
+    ]),
+    c1: Fp::from_raw([
+        0x1665_d51c_640f_cba2,
+        0x32ae_2a1d_0b7c_9dce,
+        0x4ba4_cc8b_d75a_0794,
+        0x16c9_e550_61eb_ae20,
+    ]),
+};
+
+const FROBENIUS_Y: Fp2 = Fp2 {
+    c0: Fp::from_raw([
+        0xdc54_0146_71a0_135a,
+        0xdbaa_e0ed_a9c9_5998,
+        0xdc5e_c698_b6e2_f9b9,
+        0x063c_f305_489a_f5dc,
+    ]),
+    c1: Fp::from_raw([
+        0x82d3_7f63_2623_b0e3,
+        0x2180_7dc9_8fa2_5bd2,
+        0x0704_b5a7_ec79_6f2b,
+        0x07c0_3cbc_ac41_049a,
+    ]),
+};
+
+/// `ξ^((p^2 - 1) / 3)`, in `Fp`; `ξ^((p^2 - 1) / 2)` is `-1`.
+const FROBENIUS2_X: Fp = Fp::from_raw([
+    0xe4bd_44e5_607c_fd48,
+    0xc28f_069f_bb96_6e3d,
+    0x5e6d_d9e7_e0ac_ccb0,
+    0x3064_4e72_e131_a029,
+]);
+
+/// `(p^6 + 1) / r`, the exponent of the final exponentiation after the
+/// easy part `p^6 - 1`.
+const FINAL_EXPONENT: [u64; 20] = [
+    0x5250_a540_36e3_f812,
+    0xa563_5f15_9678_9051,
+    0xd113_8bf5_4d5b_d1d4,
+    0xa8ce_2533_be36_c7a2,
+    0x94f6_9f6b_84e0_9bf6,
+    0x42ad_1f5e_50ef_3644,
+    0x0fcc_420e_48c3_454c,
+    0x758e_4408_ecc9_952c,
+    0xc901_bf18_87c6_042c,
+    0xa733_cd65_b14b_b3b5,
+    0xdf6d_76bd_cf51_b0d8,
+    0xca64_c0fd_82eb_59e1,
+    0x1d2e_5726_e392_76a1,
+    0xc2d1_ea74_a391_cae9,
+    0x0740_9206_c82d_647e,
+    0x051c_6d1a_a5af_dd17,
+    0xb37f_6019_1966_7af5,
+    0x150e_578c_5084_015b,
+    0xfbde_a556_c239_98e4,
+    0x000f_d14c_c52f_5b83,
+];
+
+/// The pairing-friendly curve BN254.
+#[derive(Clone, Debug)]
+pub struct Bn254;
+
+/// An element of the target group, a subgroup of order `r` of the
+/// multiplicative group of `Fp12`, written additively.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gt(pub(crate) Fp12);
+
+impl Gt {
+    fn add(&self, rhs: &Gt) -> Gt {
+        Gt(self.0 * rhs.0)
+    }
+
+    fn sub(&self, rhs: &Gt) -> Gt {
+        Gt(self.0 * rhs.0.conjugate())
+    }
+
+    fn multiply(&self, by: &Fr) -> Gt {
+        Gt(self.0.pow_vartime(&by.to_raw()))
+    }
+
+    /// Encodes the element as the 32-byte big-endian encodings of the twelve
+    /// coefficients of its `Fp12`, in the order of the tower: `c0.c0.c0`,
+    /// `c0.c0.c1`, `c0.c1.c0`, and so on up to `c1.c2.c1`.
+    pub fn to_bytes_be(&self) -> [u8; 384] {
+        let f = &self.0;
+        let mut bytes = [0; 384];
+        for (chunk, c) in bytes
+            .chunks_mut(64)
+            .zip(&[f.c0.c0, f.c0.c1, f.c0.c2, f.c1.c0, f.c1.c1, f.c1.c2])
+        {
+            chunk[..32].copy_from_slice(&c.c0.to_bytes_be());
+            chunk[32..].copy_from_slice(&c.c1.to_bytes_be());
+        }
+        bytes
+    }
+
+    /// Decodes an element encoded by [`Gt::to_bytes_be`], or returns `None`
+    /// if a coefficient is not canonical or the element is not in the target
+    /// group.
+    pub fn from_bytes_be(bytes: &[u8; 384]) -> Option<Gt> {
+        let mut coefficients = [Fp::zero(); 12];
+        for (c, chunk) in coefficients.iter_mut().zip(bytes.chunks(32)) {
+            let mut repr = [0; 32];
+            repr.copy_from_slice(chunk);
+            *c = Option::from(Fp::from_bytes_be(&repr))?;
+        }
+        let fp2 = |i: usize| Fp2 {
+            c0: coefficients[2 * i],
+            c1: coefficients[2 * i + 1],
+        };
+        let f = Fp12 {
+            c0: Fp6 {
+                c0: fp2(0),
+                c1: fp2(1),
+                c2: fp2(2),
+            },
+            c1: Fp6 {
+                c0: fp2(3),
+                c1: fp2(4),
+                c2: fp2(5),
+            },
+        };
+
+        // The target group is the only subgroup of order `r`, so it holds
+        // every element whose order divides `r`, and zero is not one of them.
+        if f.pow_vartime(&Fr::MODULUS) == Fp12::one() {
+            Some(Gt(f))
+        } else {
+            None
+        }
+    }
+}
+
+impl ConstantTimeEq for Gt {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl Neg for &Gt {
+    type Output = Gt;
+
+    fn neg(self) -> Gt {
+        // Elements of order dividing `p^6 + 1` are inverted by conjugation.
+        Gt(self.0.conjugate())
+    }
+}
+
+impl Neg for Gt {
+    type Output = Gt;
+
+    fn neg(self) -> Gt {
+        -&self
+    }
+}
+
+impl_binop!(Gt, Gt, Gt, Gt::add, Add, add);
+impl_binop!(Gt, Gt, Gt, Gt::sub, Sub, sub);
+impl_binop!(Gt, Fr, Gt, Gt::multiply, Mul, mul);
+impl_assign!(Gt, Gt, Gt::add, AddAssign, add_assign);
+impl_assign!(Gt, Gt, Gt::sub, SubAssign, sub_assign);
+impl_assign!(Gt, Fr, Gt::multiply, MulAssign, mul_assign);
+
+impl<T> Sum<T> for Gt
+where
+    T: std::borrow::Borrow<Gt>,
+{
+    fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.fold(Self::identity(), |acc, item| acc + item.borrow())
+    }
+}
+
+impl Group for Gt {
+    type Scalar = Fr;
+
+    fn random(mut rng: impl RngCore) -> Self {
+        Self::generator() * Fr::random(&mut rng)
+    }
+
+    fn identity() -> Self {
+        Gt(Fp12::one())
+    }
+
+    /// The pairing of the generators of G1 and G2.
+    fn generator() -> Self {
+        Bn254::pairing(&G1Affine::generator(), &G2Affine::generator())
+    }
+
+    fn is_identity(&self) -> Choice {
+        self.ct_eq(&Self::identity())
+    }
+
+    fn double(&self) -> Self {
+        Gt(self.0.square())
+    }
+}
+
+/// A G2 point with the lines of its Miller loop, which do not depend on the
+/// G1 point it is paired with.
+#[derive(Clone, Debug)]
+pub struct G2Prepared {
+    /// The slope `λ` of each line through `T` and the constant
+    /// `λ x_T - y_T`, in the order of the loop.
+    lines: Vec<(Fp2, Fp2)>,
+}
+
+/// Moves `t` to `t + s` along the line of slope `lambda` through them (the
+/// tangent at `t` if `s = t`), given the x-coordinate of `s`, and returns
+/// the line.
+fn line_step(t: &mut (Fp2, Fp2), lambda: Fp2, x_s: &Fp2) -> (Fp2, Fp2) {
+    let (x, y) = *t;
+    let x3 = lambda.square() - x - x_s;
+    *t = (x3, lambda * (x - x3) - y);
+    (lambda, lambda * x - y)
+}
+
+/// Runs the loop of the ate pairing from `q`, taking the slope of each
+/// line from `slope`, which is given `T`, and the point added to it or
+/// `None` when doubling. Stops at the first slope that is `None`.
+fn prepare<F>(q: (Fp2, Fp2), mut slope: F) -> Option<Vec<(Fp2, Fp2)>>
+where
+    F: FnMut(&(Fp2, Fp2), Option<&(Fp2, Fp2)>) -> Option<Fp2>,
+{
+    let mut lines = vec![];
+    let mut t = q;
+    let mut add = |t: &mut (Fp2, Fp2), s: Option<&(Fp2, Fp2)>| {
+        let lambda = slope(t, s)?;
+        let x_s = s.map_or(t.0, |s| s.0);
+        lines.push(line_step(t, lambda, &x_s));
+        Some(())
+    };
+    for i in (0..64).rev() {
+        add(&mut t, None)?;
+        if (ATE_LOOP_COUNT >> i) & 1 == 1 {
+            add(&mut t, Some(&q))?;
+        }
+    }
+
+    // The ate pairing adds π(Q) and -π²(Q), for the Frobenius π.
+    let q1 = (q.0.conjugate() * FROBENIUS_X, q.1.conjugate() * FROBENIUS_Y);
+    let q2 = (q.0.mul_by_fp(&FROBENIUS2_X), q.1);
+    add(&mut t, Some(&q1))?;
+    add(&mut t, Some(&q2))?;
+
+    Some(lines)
+}
+
+impl From<G2Affine> for G2Prepared {
+    fn from(q: G2Affine) -> G2Prepared {
+        let q = match q.coordinates() {
+            Some(q) => q,
+            None => return G2Prepared { lines: vec![] },
+        };
+        let lines = prepare(q, |(x, y), s| match s {
+            // The points have odd order, so `y` is not zero.
+            None => {
+                let x2 = x.square();
+                Some((x2.double() + x2) * y.double().invert().unwrap())
+            }
+            // The multiples of `Q` in the loop are below `r` and never `±Q`.
+            Some(s) => Some((s.1 - y) * (s.0 - x).invert().unwrap()),
+        });
+
+        G2Prepared {
+            lines: lines.expect("the slopes are computed"),
+        }
+    }
+}
+
+impl G2Prepared {
+    /// The number of lines of a point other than the identity: a doubling
+    /// for each bit of `6u + 2` below the top one, an addition for each of
+    /// those that is set, and the two additions of the Frobenius.
+    pub const LINES: usize = 64 + (ATE_LOOP_COUNT as u64).count_ones() as usize + 2;
+
+    /// Returns the slope of each line, which [`G2Prepared::from_slopes`]
+    /// takes as hints.
+    pub fn slopes(&self) -> Vec<Fp2> {
+        self.lines.iter().map(|(lambda, _)| *lambda).collect()
+    }
+
+    /// Prepares `q` from the slopes of its lines, as returned by
+    /// [`G2Prepared::slopes`] for the same point.
+    ///
+    /// Computing a slope takes an inversion in `Fp2`, and they are most of
+    /// the cost of preparing a point. Checking one takes a multiplication:
+    /// `λ 2y = 3x²` for a tangent, and `λ (x_S - x) = y_S - y` for the line
+    /// through `T` and `S`, each of which has a single solution. Returns
+    /// `None` if a slope is wrong, or there is not one for each line.
+    pub fn from_slopes(q: &G2Affine, slopes: &[Fp2]) -> Option<G2Prepared> {
+        let q = match q.coordinates() {
+            Some(q) => q,
+            None if slopes.is_empty() => return Some(G2Prepared { lines: vec![] }),
+            None => return None,
+        };
+        let mut slopes = slopes.iter();
+        let lines = prepare(q, |(x, y), s| {
+            let lambda = *slopes.next()?;
+            let valid = match s {
+                None => {
+                    let x2 = x.square();
+                    !bool::from(y.is_zero()) && lambda * y.double() == x2.double() + x2
+                }
+                Some(s) => !bool::from((s.0 - x).is_zero()) && lambda * (s.0 - x) == s.1 - y,
+            };
+            if valid {
+                Some(lambda)
+            } else {
+                None
+            }
+        })?;
+        if slopes.next().is_some() {
+            return None;
+        }
+
+        Some(G2Prepared { lines })
+    }
+}
+
+/// Evaluates the line `y - λ x + (λ x_T - y_T)` of the twist, untwisted, at
+/// the G1 point `(x, y)`: `y - λ x w + (λ x_T - y_T) w^3`.
+fn evaluate((lambda, constant): &(Fp2, Fp2), (x, y): &(Fp, Fp)) -> Fp12 {
+    Fp12 {
+        c0: Fp6 {
+            c0: Fp2 {
+                c0: *y,
+                c1: Fp::zero(),
+            },
+            c1: Fp2::zero(),
+            c2: Fp2::zero(),
+        },
+        c1: Fp6 {
+            c0: lambda.mul_by_fp(&-x),
+            c1: *constant,
+            c2: Fp2::zero(),
+        },
+    }
+}
+
+/// The output of the Miller loop, before the final exponentiation.
+#[derive(Clone, Copy, Debug)]
+pub struct MillerLoopResult(pub(crate) Fp12);
+
+impl pairing::MillerLoopResult for MillerLoopResult {
+    type Gt = Gt;
+
+    fn final_exponentiation(&self) -> Gt {
+        // The loop result is never zero: it is a product of lines evaluated
+        // away from their zeros.
+        let easy = self.0.conjugate() * self.0.invert().unwrap();
+        Gt(easy.pow_vartime(&FINAL_EXPONENT))
+    }
+}
+
+impl MultiMillerLoop for Bn254 {
+    type G2Prepared = G2Prepared;
+    type Result = MillerLoopResult;
+
+    fn multi_miller_loop(terms: &[(&G1Affine, &G2Prepared)]) -> MillerLoopResult {
+        // Pairings with the identity are one, and are skipped.
+        let terms: Vec<_> = terms
+            .iter()
+            .filter_map(|(p, q)| {
+                p.coordinates()
+                    .filter(|_| !q.lines.is_empty())
+                    .map(|p| (p, *q))
+            })
+            .collect();
+
+        let mut f = Fp12::one();
+        let mut line = 0;
+        let mut step = |f: &mut Fp12| {
+            for (p, q) in &terms {
+                *f *= evaluate(&q.lines[line], p);
+            }
+            line += 1;
+        };
+        for i in (0..64).rev() {
+            f = f.square();
+            step(&mut f);
+            if (ATE_LOOP_COUNT >> i) & 1 == 1 {
+                step(&mut f);
+            }
+        }
+        step(&mut f);
+        step(&mut f);
+
+        MillerLoopResult(f)
+    }
+}
+
+impl Engine for Bn254 {
+    type Fr = Fr;
+    type G1 = G1Projective;
+    type G1Affine = G1Affine;
+    type G2 = G2Projective;
+    type G2Affine = G2Affine;
+    type Gt = Gt;
+
+    fn pairing(p: &G1Affine, q: &G2Affine) -> Gt {
+        Self::multi_miller_loop(&[(p, &(*q).into())]).final_exponentiation()
+    }
+}
+
+impl PairingCurveAffine for G1Affine {
+    type Pair = G2Affine;
+    type PairingResult = Gt;
+
+    fn pairing_with(&self, other: &G2Affine) -> Gt {
+        Bn254::pairing(self, other)
+    }
+}
+
+impl PairingCurveAffine for G2Affine {
+    type Pair = G1Affine;
+    type PairingResult = Gt;
+
+    fn pairing_with(&self, other: &G1Affine) -> Gt {
+        Bn254::pairing(other, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::{rng, Fixture};
+    use crate::groth16::verify_proof;
+    use group::Curve;
+    use hex_literal::hex;
+
+    #[test]
+    fn pairing() {
+        let mut rng = rng();
+        let a = Fr::random(&mut rng);
+        let b = Fr::random(&mut rng);
+        let g = Gt::generator();
+        assert!(!bool::from(g.is_identity()));
+        assert!(bool::from((g * -Fr::one() + g).is_identity()));
+        assert_eq!(-g, g * -Fr::one());
+
+        let p = (G1Projective::generator() * a).to_affine();
+        let q = (G2Projective::generator() * b).to_affine();
+        assert_eq!(Bn254::pairing(&p, &q), g * (a * b));
+        assert_eq!(
+            Bn254::pairing(&p, &G2Affine::generator()),
+            Bn254::pairing(
+                &G1Affine::generator(),
+                &(G2Projective::generator() * a).to_affine()
+            )
+        );
+        assert_eq!(Bn254::pairing(&G1Affine::identity(), &q), Gt::identity());
+        assert_eq!(Bn254::pairing(&p, &G2Affine::identity()), Gt::identity());
+
+        // e(P, Q) e(-P, Q) = 1, in one loop.
+        let prepared = G2Prepared::from(q);
+        assert_eq!(
+            Bn254::multi_miller_loop(&[(&p, &prepared), (&-p, &prepared)]).final_exponentiation(),
+            Gt::identity()
+        );
+    }
+
+    #[test]
+    fn gt_encoding() {
+        let mut rng = rng();
+        let mut one = [0; 384];
+        one[31] = 1;
+        assert_eq!(&Gt::identity().to_bytes_be()[..], &one[..]);
+
+        let g = Gt::random(&mut rng);
+        let bytes = g.to_bytes_be();
+        assert_eq!(Gt::from_bytes_be(&bytes), Some(g));
+        assert_eq!(Gt::from_bytes_be(&one), Some(Gt::identity()));
+
+        // Elements of `Fp12` outside of the target group.
+        let mut tampered = bytes;
+        tampered[383] ^= 1;
+        assert_eq!(Gt::from_bytes_be(&tampered), None);
+        assert_eq!(Gt::from_bytes_be(&[0; 384]), None);
+        // A coefficient that is not reduced.
+        let mut unreduced = one;
+        unreduced[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(Gt::from_bytes_be(&unreduced), None);
+    }
+
+    #[test]
+    fn line_hints() {
+        let mut rng = rng();
+        let p = G1Projective::random(&mut rng).to_affine();
+        let q = G2Projective::random(&mut rng).to_affine();
+        let prepared = G2Prepared::from(q);
+        let slopes = prepared.slopes();
+
+        let hinted = G2Prepared::from_slopes(&q, &slopes).unwrap();
+        assert_eq!(hinted.lines, prepared.lines);
+        assert_eq!(slopes.len(), G2Prepared::LINES);
+        assert_eq!(
+            Bn254::multi_miller_loop(&[(&p, &hinted)]).final_exponentiation(),
+            Bn254::pairing(&p, &q)
+        );
+
+        // Each slope is checked, and there is one for each line.
+        for i in [0, 1, slopes.len() - 1].iter() {
+            let mut wrong = slopes.clone();
+            wrong[*i] += Fp2::one();
+            assert!(G2Prepared::from_slopes(&q, &wrong).is_none());
+        }
+        assert!(G2Prepared::from_slopes(&q, &slopes[1..]).is_none());
+        let mut long = slopes.clone();
+        long.push(Fp2::one());
+        assert!(G2Prepared::from_slopes(&q, &long).is_none());
+        assert!(G2Prepared::from_slopes(&(-q), &slopes).is_none());
+
+        assert!(G2Prepared::from_slopes(&G2Affine::identity(), &[]).is_some());
+        assert!(G2Prepared::from_slopes(&G2Affine::identity(), &slopes).is_none());
+    }
+
+    /// A vector of the `ecPairing` precompile of EIP-197, from the Ethereum
+    /// tests, with two pairs whose product is one.
+    #[test]
+    fn eip197() {
+        let p = [
+            hex!(
+                "1c76476f4def4bb94541d57ebba1193381ffa7aa76ada664dd31c16024c43f59
+                 3034dd2920f673e204fee2811c678745fc819b55d3e9d294e45c9b03a76aef41"
+            ),
+            hex!(
+                "111e129f1cf1097710d41c4ac70fcdfa5ba2023c6ff1cbeac322de49d1b6df7c
+                 2032c61a830e3c17286de9462bf242fca2883585b93870a73853face6a6bf411"
+            ),
+        ];
+        let q = [
+            hex!(
+                "209dd15ebff5d46c4bd888e51a93cf99a7329636c63514396b4a452003a35bf7
+                 04bf11ca01483bfa8b34b43561848d28905960114c8ac04049af4b6315a41678
+                 2bb8324af6cfc93537a2ad1a445cfd0ca2a71acd7ac41fadbf933c2a51be344d
+                 120a2a4cf30c1bf9845f20c6fe39e07ea2cce61f0c9bb048165fe5e4de877550"
+            ),
+            hex!(
+                "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2
+                 1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed
+                 090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b
+                 12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa"
+            ),
+        ];
+        // The second point of G2 is the generator of EIP-197.
+        assert_eq!(G2Affine::generator().to_uncompressed()[..], q[1][..]);
+
+        let p = p
+            .iter()
+            .map(|p| G1Affine::from_uncompressed(p).unwrap())
+            .collect::<Vec<_>>();
+        let q = q
+            .iter()
+            .map(|q| G2Prepared::from(G2Affine::from_uncompressed(q).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Bn254::multi_miller_loop(&[(&p[0], &q[0]), (&p[1], &q[1])]).final_exponentiation(),
+            Gt::identity()
+        );
+        // Either pair alone, or with the first point negated, is not.
+        assert_ne!(
+            Bn254::multi_miller_loop(&[(&p[0], &q[0])]).final_exponentiation(),
+            Gt::identity()
+        );
+        assert_ne!(
+            Bn254::multi_miller_loop(&[(&-p[0], &q[0]), (&p[1], &q[1])]).final_exponentiation(),
+            Gt::identity()
+        );
+        // The empty product is one.
+        assert_eq!(
+            Bn254::multi_miller_loop(&[]).final_exponentiation(),
+            Gt::identity()
+        );
+    }
+
+    #[test]
+    fn groth16() {
+        let mut f = Fixture::<Bn254>::new();
+        let proof = f.proof();
+        assert!(verify_proof(&f.pvk, &proof, f.inputs()).is_ok());
+
+        let mut wrong = f.inputs().to_vec();
+        wrong[0] += Fr::one();
+        assert!(verify_proof(&f.pvk, &proof, &wrong).is_err());
+    }
+}
@@ -0,0 +1,1024 @@
+//! The groups G1, on `y^2 = x^3 + 3` over `Fp`, and G2, on the sextic twist
+//! `y^2 = x^3 + 3 / ξ` over `Fp2`.
+//!
+//! Points are added with the complete formulas of
+//! <https://eprint.iacr.org/2015/1060> in homogeneous projective
+//! coordinates, and multiplied by scalars with double-and-add-always.
+//!
+//! The encodings are those of gnark: the big-endian `x`-coordinate, with
+//! the imaginary part first in G2, followed by `y` in the uncompressed
+//! encoding. The two top bits of the first byte are `00` for an
+//! uncompressed point, `10` and `11` for a compressed point whose `y` is
+//! the smaller or the larger of `±y`, and `01` for the identity, whose
+//! other bits are zero.
+
+use ff::PrimeField;
+use group::{
+    prime::{PrimeCurve, PrimeCurveAffine, PrimeGroup},
+    Curve, Group, GroupEncoding, UncompressedEncoding, WnafGroup,
+};
+use rand_core::RngCore;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use super::fields::{Fp, Fr};
+use super::tower::Fp2;
+
+const UNCOMPRESSED: u8 = 0b00 << 6;
+const IDENTITY: u8 = 0b01 << 6;
+const COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const COMPRESSED_LARGEST: u8 = 0b11 << 6;
+const FLAGS: u8 = 0b11 << 6;
+
+macro_rules! encoding {
+    ($name:ident, $len:expr) => {
+        #[derive(Clone, Copy)]
+        pub struct $name(pub [u8; $len]);
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name([0; $len])
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl AsMut<[u8]> for $name {
+            fn as_mut(&mut self) -> &mut [u8] {
+                &mut self.0
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0[..].fmt(f)
+            }
+        }
+    };
+}
+
+macro_rules! curve {
+    (
+        $(#[$affine_attr:meta])*
+        $affine:ident,
+        $(#[$projective_attr:meta])*
+        $projective:ident,
+        compressed: $compressed:ident,
+        uncompressed: $uncompressed:ident,
+        base: $base:ident,
+        base_len: $len:expr,
+        b: $b:expr,
+        b3: $b3:expr,
+        generator: ($gx:expr, $gy:expr),
+        wnaf: $wnaf:expr
+    ) => {
+        encoding!($compressed, $len);
+        encoding!($uncompressed, 2 * $len);
+
+        $(#[$affine_attr])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $affine {
+            pub(crate) x: $base,
+            pub(crate) y: $base,
+            pub(crate) infinity: Choice,
+        }
+
+        impl $affine {
+            pub fn identity() -> Self {
+                $affine {
+                    x: $base::zero(),
+                    y: $base::one(),
+                    infinity: Choice::from(1),
+                }
+            }
+
+            pub fn generator() -> Self {
+                $affine {
+                    x: $gx,
+                    y: $gy,
+                    infinity: Choice::from(0),
+                }
+            }
+
+            pub fn is_identity(&self) -> Choice {
+                self.infinity
+            }
+
+            /// Returns the coordinates, or `None` for the identity.
+            pub fn coordinates(&self) -> Option<($base, $base)> {
+                if bool::from(self.infinity) {
+                    None
+                } else {
+                    Some((self.x, self.y))
+                }
+            }
+
+            /// Returns whether the point is on the curve, or is the identity.
+            pub fn is_on_curve(&self) -> Choice {
+                (self.y.square() - self.x.square() * self.x).ct_eq(&$b) | self.infinity
+            }
+
+            pub fn to_compressed(&self) -> [u8; $len] {
+                let mut bytes = $base::conditional_select(&self.x, &$base::zero(), self.infinity)
+                    .to_bytes_be();
+                bytes[0] |= if bool::from(self.infinity) {
+                    IDENTITY
+                } else if bool::from(self.y.lexicographically_largest()) {
+                    COMPRESSED_LARGEST
+                } else {
+                    COMPRESSED_SMALLEST
+                };
+                bytes
+            }
+
+            pub fn to_uncompressed(&self) -> [u8; 2 * $len] {
+                let mut bytes = [0; 2 * $len];
+                if bool::from(self.infinity) {
+                    bytes[0] = IDENTITY;
+                } else {
+                    bytes[..$len].copy_from_slice(&self.x.to_bytes_be());
+                    bytes[$len..].copy_from_slice(&self.y.to_bytes_be());
+                }
+                bytes
+            }
+
+            /// Decodes a compressed point without checking that it is in
+            /// the prime-order subgroup.
+            pub fn from_compressed_unchecked(bytes: &[u8; $len]) -> CtOption<Self> {
+                let flags = bytes[0] & FLAGS;
+                let mut x = *bytes;
+                x[0] &= !FLAGS;
+                if flags == IDENTITY {
+                    let zero = x.iter().all(|&b| b == 0);
+                    return CtOption::new(Self::identity(), Choice::from(zero as u8));
+                }
+                let compressed = Choice::from((flags & COMPRESSED_SMALLEST != 0) as u8);
+                let largest = Choice::from((flags == COMPRESSED_LARGEST) as u8);
+                $base::from_bytes_be(&x).and_then(|x| {
+                    (x.square() * x + $b).sqrt().and_then(|y| {
+                        let y = $base::conditional_select(
+                            &y,
+                            &-y,
+                            y.lexicographically_largest() ^ largest,
+                        );
+                        CtOption::new(
+                            $affine {
+                                x,
+                                y,
+                                infinity: Choice::from(0),
+                            },
+                            compressed,
+                        )
+                    })
+                })
+            }
+
+            /// Decodes an uncompressed point, checking that it is on the
+            /// curve but not that it is in the prime-order subgroup.
+            pub fn from_uncompressed_unchecked(bytes: &[u8; 2 * $len]) -> CtOption<Self> {
+                let flags = bytes[0] & FLAGS;
+                let mut x = [0; $len];
+                let mut y = [0; $len];
+                x.copy_from_slice(&bytes[..$len]);
+                y.copy_from_slice(&bytes[$len..]);
+                x[0] &= !FLAGS;
+                if flags == IDENTITY {
+                    let zero = x.iter().chain(y.iter()).all(|&b| b == 0);
+                    return CtOption::new(Self::identity(), Choice::from(zero as u8));
+                }
+                let uncompressed = Choice::from((flags == UNCOMPRESSED) as u8);
+                $base::from_bytes_be(&x).and_then(|x| {
+                    $base::from_bytes_be(&y).and_then(|y| {
+                        let point = $affine {
+                            x,
+                            y,
+                            infinity: Choice::from(0),
+                        };
+                        CtOption::new(point, uncompressed & point.is_on_curve())
+                    })
+                })
+            }
+
+            pub fn from_compressed(bytes: &[u8; $len]) -> CtOption<Self> {
+                Self::from_compressed_unchecked(bytes)
+                    .and_then(|p| CtOption::new(p, p.is_torsion_free()))
+            }
+
+            pub fn from_uncompressed(bytes: &[u8; 2 * $len]) -> CtOption<Self> {
+                Self::from_uncompressed_unchecked(bytes)
+                    .and_then(|p| CtOption::new(p, p.is_torsion_free()))
+            }
+        }
+
+        impl Default for $affine {
+            fn default() -> Self {
+                Self::identity()
+            }
+        }
+
+        impl ConstantTimeEq for $affine {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                (self.infinity & other.infinity)
+                    | (!self.infinity
+                        & !other.infinity
+                        & self.x.ct_eq(&other.x)
+                        & self.y.ct_eq(&other.y))
+            }
+        }
+
+        impl PartialEq for $affine {
+            fn eq(&self, other: &Self) -> bool {
+                bool::from(self.ct_eq(other))
+            }
+        }
+
+        impl Eq for $affine {}
+
+        impl ConditionallySelectable for $affine {
+            fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+                $affine {
+                    x: $base::conditional_select(&a.x, &b.x, choice),
+                    y: $base::conditional_select(&a.y, &b.y, choice),
+                    infinity: Choice::conditional_select(&a.infinity, &b.infinity, choice),
+                }
+            }
+        }
+
+        impl<'a> Neg for &'a $affine {
+            type Output = $affine;
+
+            fn neg(self) -> $affine {
+                $affine {
+                    x: self.x,
+                    y: $base::conditional_select(&-self.y, &$base::one(), self.infinity),
+                    infinity: self.infinity,
+                }
+            }
+        }
+
+        impl Neg for $affine {
+            type Output = $affine;
+
+            fn neg(self) -> $affine {
+                -&self
+            }
+        }
+
+        impl<'a> From<&'a $projective> for $affine {
+            fn from(p: &'a $projective) -> $affine {
+                let zinv = p.z.invert().unwrap_or($base::zero());
+                let point = $affine {
+                    x: p.x * zinv,
+                    y: p.y * zinv,
+                    infinity: Choice::from(0),
+                };
+                $affine::conditional_select(&point, &$affine::identity(), zinv.is_zero())
+            }
+        }
+
+        impl From<$projective> for $affine {
+            fn from(p: $projective) -> $affine {
+                $affine::from(&p)
+            }
+        }
+
+        $(#[$projective_attr])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $projective {
+            pub(crate) x: $base,
+            pub(crate) y: $base,
+            pub(crate) z: $base,
+        }
+
+        impl $projective {
+            pub fn identity() -> Self {
+                $projective {
+                    x: $base::zero(),
+                    y: $base::one(),
+                    z: $base::zero(),
+                }
+            }
+
+            pub fn generator() -> Self {
+                $affine::generator().into()
+            }
+
+            pub fn is_identity(&self) -> Choice {
+                self.z.is_zero()
+            }
+
+            pub fn is_on_curve(&self) -> Choice {
+                // Y^2 Z = X^3 + b Z^3
+                (self.y.square() * self.z)
+                    .ct_eq(&(self.x.square() * self.x + self.z.square() * self.z * $b))
+                    | self.z.is_zero()
+            }
+
+            /// Algorithm 9 of <https://eprint.iacr.org/2015/1060>.
+            pub fn double(&self) -> Self {
+                let t0 = self.y.square();
+                let z3 = t0.double().double().double();
+                let t1 = self.y * self.z;
+                let t2 = self.z.square() * $b3;
+                let x3 = t2 * z3;
+                let y3 = t0 + t2;
+                let z3 = t1 * z3;
+                let t2 = t2.double() + t2;
+                let t0 = t0 - t2;
+                let y3 = t0 * y3 + x3;
+                let x3 = (t0 * (self.x * self.y)).double();
+
+                let tmp = $projective {
+                    x: x3,
+                    y: y3,
+                    z: z3,
+                };
+                $projective::conditional_select(&tmp, &$projective::identity(), self.is_identity())
+            }
+
+            /// Algorithm 7 of <https://eprint.iacr.org/2015/1060>.
+            pub fn add(&self, rhs: &Self) -> Self {
+                let t0 = self.x * rhs.x;
+                let t1 = self.y * rhs.y;
+                let t2 = self.z * rhs.z;
+                let t3 = (self.x + self.y) * (rhs.x + rhs.y) - (t0 + t1);
+                let t4 = (self.y + self.z) * (rhs.y + rhs.z) - (t1 + t2);
+                let y3 = (self.x + self.z) * (rhs.x + rhs.z) - (t0 + t2);
+                let t0 = t0.double() + t0;
+                let t2 = t2 * $b3;
+                let z3 = t1 + t2;
+                let t1 = t1 - t2;
+                let y3 = y3 * $b3;
+                let x3 = t3 * t1 - t4 * y3;
+                let y3 = t1 * z3 + y3 * t0;
+                let z3 = z3 * t4 + t0 * t3;
+
+                $projective {
+                    x: x3,
+                    y: y3,
+                    z: z3,
+                }
+            }
+
+            /// Algorithm 8 of <https://eprint.iacr.org/2015/1060>.
+            pub fn add_mixed(&self, rhs: &$affine) -> Self {
+                let t0 = self.x * rhs.x;
+                let t1 = self.y * rhs.y;
+                let t3 = (rhs.x + rhs.y) * (self.x + self.y) - (t0 + t1);
+                let t4 = rhs.y * self.z + self.y;
+                let y3 = rhs.x * self.z + self.x;
+                let t0 = t0.double() + t0;
+                let t2 = self.z * $b3;
+                let z3 = t1 + t2;
+                let t1 = t1 - t2;
+                let y3 = y3 * $b3;
+                let x3 = t3 * t1 - t4 * y3;
+                let y3 = t1 * z3 + y3 * t0;
+                let z3 = z3 * t4 + t0 * t3;
+
+                let tmp = $projective {
+                    x: x3,
+                    y: y3,
+                    z: z3,
+                };
+                $projective::conditional_select(&tmp, self, rhs.is_identity())
+            }
+
+            pub fn sub(&self, rhs: &Self) -> Self {
+                self.add(&-rhs)
+            }
+
+            pub fn sub_mixed(&self, rhs: &$affine) -> Self {
+                self.add_mixed(&-rhs)
+            }
+
+            /// Multiplies by the little-endian bytes of a scalar.
+            fn multiply(&self, by: &[u8; 32]) -> Self {
+                let mut acc = $projective::identity();
+                for bit in by
+                    .iter()
+                    .rev()
+                    .flat_map(|byte| (0..8).rev().map(move |i| Choice::from((byte >> i) & 1)))
+                {
+                    acc = acc.double();
+                    acc = $projective::conditional_select(&acc, &(acc + self), bit);
+                }
+                acc
+            }
+
+            fn multiply_scalar(&self, by: &Fr) -> Self {
+                self.multiply(&by.to_repr())
+            }
+
+            /// Converts to affine coordinates with one inversion.
+            pub fn batch_normalize(p: &[Self], q: &mut [$affine]) {
+                assert_eq!(p.len(), q.len());
+
+                let mut acc = $base::one();
+                for (p, q) in p.iter().zip(q.iter_mut()) {
+                    // The `x` of `q` holds the product of the previous `z`.
+                    q.x = acc;
+                    acc = $base::conditional_select(&(acc * p.z), &acc, p.is_identity());
+                }
+
+                acc = acc.invert().unwrap();
+
+                for (p, q) in p.iter().rev().zip(q.iter_mut().rev()) {
+                    let skip = p.is_identity();
+                    let zinv = q.x * acc;
+                    acc = $base::conditional_select(&(acc * p.z), &acc, skip);
+                    q.x = p.x * zinv;
+                    q.y = p.y * zinv;
+                    q.infinity = Choice::from(0);
+                    *q = $affine::conditional_select(q, &$affine::identity(), skip);
+                }
+            }
+        }
+
+        impl<'a> From<&'a $affine> for $projective {
+            fn from(p: &'a $affine) -> $projective {
+                $projective {
+                    x: p.x,
+                    y: p.y,
+                    z: $base::conditional_select(&$base::one(), &$base::zero(), p.infinity),
+                }
+            }
+        }
+
+        impl From<$affine> for $projective {
+            fn from(p: $affine) -> $projective {
+                $projective::from(&p)
+            }
+        }
+
+        impl ConstantTimeEq for $projective {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                let (a, b) = (self.is_identity(), other.is_identity());
+                (a & b)
+                    | (!a
+                        & !b
+                        & (self.x * other.z).ct_eq(&(other.x * self.z))
+                        & (self.y * other.z).ct_eq(&(other.y * self.z)))
+            }
+        }
+
+        impl PartialEq for $projective {
+            fn eq(&self, other: &Self) -> bool {
+                bool::from(self.ct_eq(other))
+            }
+        }
+
+        impl Eq for $projective {}
+
+        impl ConditionallySelectable for $projective {
+            fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+                $projective {
+                    x: $base::conditional_select(&a.x, &b.x, choice),
+                    y: $base::conditional_select(&a.y, &b.y, choice),
+                    z: $base::conditional_select(&a.z, &b.z, choice),
+                }
+            }
+        }
+
+        impl<'a> Neg for &'a $projective {
+            type Output = $projective;
+
+            fn neg(self) -> $projective {
+                $projective {
+                    x: self.x,
+                    y: -self.y,
+                    z: self.z,
+                }
+            }
+        }
+
+        impl Neg for $projective {
+            type Output = $projective;
+
+            fn neg(self) -> $projective {
+                -&self
+            }
+        }
+
+        impl_binop!($projective, $projective, $projective, $projective::add, Add, add);
+        impl_binop!($projective, $projective, $projective, $projective::sub, Sub, sub);
+        impl_binop!($projective, $affine, $projective, $projective::add_mixed, Add, add);
+        impl_binop!($projective, $affine, $projective, $projective::sub_mixed, Sub, sub);
+        impl_binop!($projective, Fr, $projective, $projective::multiply_scalar, Mul, mul);
+        impl_assign!($projective, $projective, $projective::add, AddAssign, add_assign);
+        impl_assign!($projective, $projective, $projective::sub, SubAssign, sub_assign);
+        impl_assign!($projective, $affine, $projective::add_mixed, AddAssign, add_assign);
+        impl_assign!($projective, $affine, $projective::sub_mixed, SubAssign, sub_assign);
+        impl_assign!($projective, Fr, $projective::multiply_scalar, MulAssign, mul_assign);
+
+        impl<'a, 'b> Mul<&'b Fr> for &'a $affine {
+            type Output = $projective;
+
+            fn mul(self, rhs: &'b Fr) -> $projective {
+                $projective::from(self).multiply_scalar(rhs)
+            }
+        }
+
+        impl<'b> Mul<&'b Fr> for $affine {
+            type Output = $projective;
+
+            fn mul(self, rhs: &'b Fr) -> $projective {
+                &self * rhs
+            }
+        }
+
+        impl<'a> Mul<Fr> for &'a $affine {
+            type Output = $projective;
+
+            fn mul(self, rhs: Fr) -> $projective {
+                self * &rhs
+            }
+        }
+
+        impl Mul<Fr> for $affine {
+            type Output = $projective;
+
+            fn mul(self, rhs: Fr) -> $projective {
+                &self * &rhs
+            }
+        }
+
+        impl<T> Sum<T> for $projective
+        where
+            T: std::borrow::Borrow<$projective>,
+        {
+            fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+                iter.fold(Self::identity(), |acc, item| acc + item.borrow())
+            }
+        }
+
+        impl Group for $projective {
+            type Scalar = Fr;
+
+            /// Tries random `x`-coordinates until one is on the curve,
+            /// and clears the cofactor of the point.
+            fn random(mut rng: impl RngCore) -> Self {
+                loop {
+                    let x = $base::random(&mut rng);
+                    let flip = Choice::from((rng.next_u32() & 1) as u8);
+                    let point = (x.square() * x + $b).sqrt().map(|y| $affine {
+                        x,
+                        y: $base::conditional_select(&y, &-y, flip),
+                        infinity: Choice::from(0),
+                    });
+                    if bool::from(point.is_some()) {
+                        let point = $projective::from(point.unwrap()).clear_cofactor();
+                        if !bool::from(point.is_identity()) {
+                            return point;
+                        }
+                    }
+                }
+            }
+
+            fn identity() -> Self {
+                Self::identity()
+            }
+
+            fn generator() -> Self {
+                Self::generator()
+            }
+
+            fn is_identity(&self) -> Choice {
+                self.is_identity()
+            }
+
+            fn double(&self) -> Self {
+                self.double()
+            }
+        }
+
+        impl WnafGroup for $projective {
+            fn recommended_wnaf_for_num_scalars(num_scalars: usize) -> usize {
+                const RECOMMENDATIONS: &[usize] = &$wnaf;
+
+                let mut ret = 4;
+                for r in RECOMMENDATIONS {
+                    if num_scalars > *r {
+                        ret += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                ret
+            }
+        }
+
+        impl PrimeGroup for $projective {}
+
+        impl Curve for $projective {
+            type AffineRepr = $affine;
+
+            fn batch_normalize(p: &[Self], q: &mut [Self::AffineRepr]) {
+                Self::batch_normalize(p, q);
+            }
+
+            fn to_affine(&self) -> $affine {
+                self.into()
+            }
+        }
+
+        impl PrimeCurve for $projective {
+            type Affine = $affine;
+        }
+
+        impl PrimeCurveAffine for $affine {
+            type Scalar = Fr;
+            type Curve = $projective;
+
+            fn identity() -> Self {
+                Self::identity()
+            }
+
+            fn generator() -> Self {
+                Self::generator()
+            }
+
+            fn is_identity(&self) -> Choice {
+                self.is_identity()
+            }
+
+            fn to_curve(&self) -> $projective {
+                self.into()
+            }
+        }
+
+        impl GroupEncoding for $projective {
+            type Repr = $compressed;
+
+            fn from_bytes(bytes: &$compressed) -> CtOption<Self> {
+                $affine::from_bytes(bytes).map(Self::from)
+            }
+
+            fn from_bytes_unchecked(bytes: &$compressed) -> CtOption<Self> {
+                $affine::from_bytes_unchecked(bytes).map(Self::from)
+            }
+
+            fn to_bytes(&self) -> $compressed {
+                $affine::from(self).to_bytes()
+            }
+        }
+
+        impl GroupEncoding for $affine {
+            type Repr = $compressed;
+
+            fn from_bytes(bytes: &$compressed) -> CtOption<Self> {
+                Self::from_compressed(&bytes.0)
+            }
+
+            fn from_bytes_unchecked(bytes: &$compressed) -> CtOption<Self> {
+                Self::from_compressed_unchecked(&bytes.0)
+            }
+
+            fn to_bytes(&self) -> $compressed {
+                $compressed(self.to_compressed())
+            }
+        }
+
+        impl UncompressedEncoding for $affine {
+            type Uncompressed = $uncompressed;
+
+            fn from_uncompressed(bytes: &$uncompressed) -> CtOption<Self> {
+                Self::from_uncompressed(&bytes.0)
+            }
+
+            fn from_uncompressed_unchecked(bytes: &$uncompressed) -> CtOption<Self> {
+                Self::from_uncompressed_unchecked(&bytes.0)
+            }
+
+            fn to_uncompressed(&self) -> $uncompressed {
+                $uncompressed(self.to_uncompressed())
+            }
+        }
+    };
+}
+
+curve!(
+    /// A point of G1 in affine coordinates.
+    G1Affine,
+    /// A point of G1 in projective coordinates.
+    G1Projective,
+    compressed: G1Compressed,
+    uncompressed: G1Uncompressed,
+    base: Fp,
+    base_len: 32,
+    b: Fp::from_raw([3, 0, 0, 0]),
+    b3: Fp::from_raw([9, 0, 0, 0]),
+    generator: (Fp::from_raw([1, 0, 0, 0]), Fp::from_raw([2, 0, 0, 0])),
+    wnaf: [1, 3, 7, 20, 43, 120, 273, 563, 1630, 3128, 7933, 62569]
+);
+
+curve!(
+    /// A point of G2 in affine coordinates.
+    G2Affine,
+    /// A point of G2 in projective coordinates.
+    G2Projective,
+    compressed: G2Compressed,
+    uncompressed: G2Uncompressed,
+    base: Fp2,
+    base_len: 64,
+    b: B2,
+    b3: B2_3,
+    generator: (G2_X, G2_Y),
+    wnaf: [1, 3, 8, 20, 47, 126, 260, 826, 1501, 4555, 84071]
+);
+
+/// `3 / ξ`.
+const B2: Fp2 = Fp2 {
+    c0: Fp::from_raw([
+        0x3267_e6dc_24a1_38e5,
+        0xb5b4_c5e5_59db_efa3,
+        0x81be_1899_1be0_6ac3,
+        0x2b14_9d40_ceb8_aaae,
+    ]),
+    c1: Fp::from_raw([
+        0xe4a2_bd06_85c3_15d2,
+        0xa74f_a084_e52d_1852,
+        0xcd2c_afad_eed8_fdf4,
+        0x0097_13b0_3af0_fed4,
+    ]),
+};
+
+/// `9 / ξ`.
+const B2_3: Fp2 = Fp2 {
+    c0: Fp::from_raw([
+        0x1ef6_9c66_bce9_b021,
+        0xf21b_7c8d_3cb0_39cf,
+        0x1499_be5e_509e_8f8f,
+        0x2075_3adc_a9c6_bfb8,
+    ]),
+    c1: Fp::from_raw([
+        0xade8_3713_9149_4176,
+        0xf5ee_e18e_af87_48f8,
+        0x6786_0f09_cc8a_f9dd,
+        0x01c5_3b10_b0d2_fc7e,
+    ]),
+};
+
+/// The generator of G2 of EIP-197.
+const G2_X: Fp2 = Fp2 {
+    c0: Fp::from_raw([
+        0x46de_bd5c_d992_f6ed,
+        0x6743_22d4_f75e_dadd,
+        0x426a_0066_5e5c_4479,
+        0x1800_deef_121f_1e76,
+    ]),
+    c1: Fp::from_raw([
+        0x97e4_85b7_aef3_12c2,
+        0xf1aa_4933_35a9_e712,
+        0x7260_bfb7_31fb_5d25,
+        0x198e_9393_920d_483a,
+    ]),
+};
+
+const G2_Y: Fp2 = Fp2 {
+    c0: Fp::from_raw([
+        0x4ce6_cc01_66fa_7daa,
+        0xe3d1_e769_0c43_d37b,
+        0x4aab_7180_8dcb_408f,
+        0x12c8_5ea5_db8c_6deb,
+    ]),
+    c1: Fp::from_raw([
+        0x55ac_dadc_d122_975b,
+        0xbc4b_3133_70b3_8ef3,
+        0xec9e_99ad_690c_3395,
+        0x0906_89d0_585f_f075,
+    ]),
+};
+
+impl G1Affine {
+    /// Every point of the curve is in G1, whose cofactor is 1.
+    pub fn is_torsion_free(&self) -> Choice {
+        Choice::from(1)
+    }
+}
+
+impl G1Projective {
+    fn clear_cofactor(&self) -> Self {
+        *self
+    }
+}
+
+impl G2Projective {
+    /// Multiplies by the cofactor `2p - r`, which maps the twist to G2.
+    fn clear_cofactor(&self) -> Self {
+        // 2p - r, in little-endian limbs.
+        const COFACTOR: [u64; 4] = [
+            0x345f_2299_c0f9_fa8d,
+            0x06ce_ecda_572a_2489,
+            0xb850_45b6_8181_585e,
+            0x3064_4e72_e131_a029,
+        ];
+        let mut acc = G2Projective::identity();
+        for limb in COFACTOR.iter().rev() {
+            for i in (0..64).rev() {
+                acc = acc.double();
+                if (limb >> i) & 1 == 1 {
+                    acc += self;
+                }
+            }
+        }
+        acc
+    }
+}
+
+impl G2Affine {
+    /// Returns whether `[r] Q` is the identity. The twist has `2p - r`
+    /// times more points than G2.
+    pub fn is_torsion_free(&self) -> Choice {
+        // r, read from the most significant bit.
+        let mut acc = G2Projective::identity();
+        let point = G2Projective::from(self);
+        for limb in Fr::MODULUS.iter().rev() {
+            for i in (0..64).rev() {
+                acc = acc.double();
+                if (limb >> i) & 1 == 1 {
+                    acc += point;
+                }
+            }
+        }
+        acc.is_identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn groups() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        assert!(bool::from(G1Affine::generator().is_on_curve()));
+        assert!(bool::from(G2Affine::generator().is_on_curve()));
+        assert!(bool::from(G2Affine::generator().is_torsion_free()));
+        assert_eq!(
+            G1Projective::generator() * -Fr::one(),
+            -G1Projective::generator()
+        );
+        assert!(bool::from(
+            (G1Projective::generator() * (-Fr::one()) + G1Projective::generator()).is_identity()
+        ));
+
+        let a = Fr::random(&mut rng);
+        let b = Fr::random(&mut rng);
+        let p = G1Projective::generator() * a;
+        let q = G2Projective::generator() * a;
+        assert_eq!(p * b + p, p * (b + Fr::one()));
+        assert_eq!(q.double(), q + q);
+        assert_eq!(q + G2Affine::generator() - G2Affine::generator(), q);
+        assert!(bool::from(q.is_on_curve()));
+
+        let mut affine = [G1Affine::identity(); 3];
+        G1Projective::batch_normalize(&[p, G1Projective::identity(), p.double()], &mut affine);
+        assert_eq!(
+            affine,
+            [p.to_affine(), G1Affine::identity(), p.double().to_affine()]
+        );
+
+        for point in &[p.to_affine(), (-p).to_affine(), G1Affine::identity()] {
+            assert_eq!(G1Affine::from_bytes(&point.to_bytes()).unwrap(), *point);
+            assert_eq!(
+                G1Affine::from_uncompressed(&point.to_uncompressed()).unwrap(),
+                *point
+            );
+        }
+        for point in &[q.to_affine(), (-q).to_affine(), G2Affine::identity()] {
+            assert_eq!(G2Affine::from_bytes(&point.to_bytes()).unwrap(), *point);
+            assert_eq!(
+                G2Affine::from_uncompressed(&point.to_uncompressed()).unwrap(),
+                *point
+            );
+        }
+
+        // The generator of G1 is (1, 2), with the smaller `y`.
+        let mut one = [0; 32];
+        one[31] = 1;
+        let mut compressed = one;
+        compressed[0] |= COMPRESSED_SMALLEST;
+        assert_eq!(G1Affine::generator().to_compressed(), compressed);
+        let mut uncompressed = [0; 64];
+        uncompressed[31] = 1;
+        uncompressed[63] = 2;
+        assert_eq!(
+            G1Affine::generator().to_uncompressed()[..],
+            uncompressed[..]
+        );
+        // An uncompressed encoding without flags is rejected as compressed.
+        assert!(bool::from(G1Affine::from_compressed(&one).is_none()));
+        uncompressed[63] = 3;
+        assert!(bool::from(
+            G1Affine::from_uncompressed(&uncompressed).is_none()
+        ));
+
+        // A point of the twist outside G2.
+        let (x, y) = (1..)
+            .map(|i| Fp2::one().mul_by_fp(&Fp::from(i)))
+            .find_map(|x| Option::from((x.square() * x + B2).sqrt()).map(|y| (x, y)))
+            .unwrap();
+        let outside = G2Affine {
+            x,
+            y,
+            infinity: Choice::from(0),
+        };
+        assert!(bool::from(outside.is_on_curve()));
+        assert!(!bool::from(outside.is_torsion_free()));
+        assert!(bool::from(
+            G2Affine::from_uncompressed_unchecked(&outside.to_uncompressed()).is_some()
+        ));
+        assert!(bool::from(
+            G2Affine::from_uncompressed(&outside.to_uncompressed()).is_none()
+        ));
+        let cleared = G2Projective::from(outside).clear_cofactor().to_affine();
+        assert!(!bool::from(cleared.is_identity()));
+        assert!(bool::from(cleared.is_torsion_free()));
+
+        // Random points are on the curve and in the subgroup.
+        let p = G1Projective::random(&mut rng).to_affine();
+        let q = G2Projective::random(&mut rng).to_affine();
+        assert!(bool::from(p.is_on_curve()));
+        assert!(bool::from(q.is_on_curve()));
+        assert!(bool::from(q.is_torsion_free()));
+        assert_ne!(G2Projective::random(&mut rng).to_affine(), q);
+    }
+
+    /// The vectors of the `ecAdd` and `ecMul` precompiles of EIP-196, from
+    /// the Ethereum tests, whose encoding is the uncompressed one.
+    #[test]
+    fn eip196() {
+        let point = |bytes: [u8; 64]| G1Affine::from_uncompressed(&bytes).unwrap();
+
+        let a = point(hex!(
+            "18b18acfb4c2c30276db5411368e7185b311dd124691610c5d3b74034e093dc9
+             063c909c4720840cb5134cb9f59fa749755796819658d32efc0d288198f37266"
+        ));
+        let b = point(hex!(
+            "07c2b7f58a84bd6145f00c9c2bc0bb1a187f20ff2c92963a88019e7c6a014eed
+             06614e20c147e940f2d70da3f74c9a17df361706a4485c742bd6788478fa17d7"
+        ));
+        let sum = hex!(
+            "2243525c5efd4b9c3d3c45ac0ca3fe4dd85e830a4ce6b65fa1eeaee202839703
+             301d1d33be6da8e509df21cc35964723180eed7532537db9ae5e7d48f195c915"
+        );
+        assert_eq!(
+            (G1Projective::from(a) + b).to_affine().to_uncompressed()[..],
+            sum[..]
+        );
+
+        let double = hex!(
+            "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3
+             15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4"
+        );
+        assert_eq!(
+            G1Projective::generator()
+                .double()
+                .to_affine()
+                .to_uncompressed()[..],
+            double[..]
+        );
+        assert_eq!(G1Projective::from(a) - a, G1Projective::identity());
+
+        let c = point(hex!(
+            "2bd3e6d0f3b142924f5ca7b49ce5b9d54c4703d7ae5648e61d02268b1a0a9fb7
+             21611ce0a6af85915e2f1d70300909ce2e49dfad4a4619c8390cae66cefdb204"
+        ));
+        let product = hex!(
+            "070a8d6a982153cae4be29d434e8faef8a47b274a053f5a4ee2a6c9c13c31e5c
+             031b8ce914eba3a9ffb989f9cdd5b0f01943074bf4f0f315690ec3cec6981afc"
+        );
+        assert_eq!(
+            (c * Fr::from(0x1113_8ce7_50fa_15c2))
+                .to_affine()
+                .to_uncompressed()[..],
+            product[..]
+        );
+
+        // A scalar of 2^256 - 1, which the precompile reduces modulo r.
+        let d = point(hex!(
+            "1a87b0584ce92f4593d161480614f2989035225609f08058ccfa3d0f940febe3
+             1a2f3c951f6dadcc7ee9007dff81504b0fcd6d7cf59996efdc33d92bf7f9f8f6"
+        ));
+        let product = hex!(
+            "2cde5879ba6f13c0b5aa4ef627f159a3347df9722efce88a9afbb20b763b4c41
+             1aa7e43076f6aee272755a7f9b84832e71559ba0d2e0b17d5f9f01755e5b0d11"
+        );
+        let scalar = Fr::from_u512([!0, !0, !0, !0, 0, 0, 0, 0]);
+        assert_eq!((d * scalar).to_affine().to_uncompressed()[..], product[..]);
+    }
+}
@@ -0,0 +1,516 @@
+//! The base field `Fp` and the scalar field `Fr` of BN254, in Montgomery
+//! form with four 64-bit limbs.
+
+use ff::{Field, PrimeField};
+use rand_core::RngCore;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+const fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+const fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = (a as u128).wrapping_sub(b as u128 + (borrow >> 63) as u128);
+    (t as u64, (t >> 64) as u64)
+}
+
+const fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + (b as u128 * c as u128) + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+/// Subtracts `m` from `a`, with `a < 2m`, if `a >= m`.
+const fn reduce_once(a: [u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let (d0, borrow) = sbb(a[0], m[0], 0);
+    let (d1, borrow) = sbb(a[1], m[1], borrow);
+    let (d2, borrow) = sbb(a[2], m[2], borrow);
+    let (d3, borrow) = sbb(a[3], m[3], borrow);
+    // The borrow is all ones when `a < m`.
+    [
+        (a[0] & borrow) | (d0 & !borrow),
+        (a[1] & borrow) | (d1 & !borrow),
+        (a[2] & borrow) | (d2 & !borrow),
+        (a[3] & borrow) | (d3 & !borrow),
+    ]
+}
+
+const fn add_mod(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    // Both moduli are below 2^254, so the sum does not overflow.
+    let (s0, carry) = adc(a[0], b[0], 0);
+    let (s1, carry) = adc(a[1], b[1], carry);
+    let (s2, carry) = adc(a[2], b[2], carry);
+    let (s3, _) = adc(a[3], b[3], carry);
+    reduce_once([s0, s1, s2, s3], m)
+}
+
+const fn sub_mod(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let (d0, borrow) = sbb(a[0], b[0], 0);
+    let (d1, borrow) = sbb(a[1], b[1], borrow);
+    let (d2, borrow) = sbb(a[2], b[2], borrow);
+    let (d3, borrow) = sbb(a[3], b[3], borrow);
+    // Adds the modulus back if the subtraction underflowed.
+    let (d0, carry) = adc(d0, m[0] & borrow, 0);
+    let (d1, carry) = adc(d1, m[1] & borrow, carry);
+    let (d2, carry) = adc(d2, m[2] & borrow, carry);
+    let (d3, _) = adc(d3, m[3] & borrow, carry);
+    [d0, d1, d2, d3]
+}
+
+/// Adds `a * b` to the accumulator `t` of [`mont_mul`], and divides it by
+/// `2^64` modulo `m`.
+const fn mont_round(t: [u64; 5], a: &[u64; 4], b: u64, m: &[u64; 4], inv: u64) -> [u64; 5] {
+    let (t0, carry) = mac(t[0], a[0], b, 0);
+    let (t1, carry) = mac(t[1], a[1], b, carry);
+    let (t2, carry) = mac(t[2], a[2], b, carry);
+    let (t3, carry) = mac(t[3], a[3], b, carry);
+    let (t4, t5) = adc(t[4], carry, 0);
+
+    let k = t0.wrapping_mul(inv);
+    let (_, carry) = mac(t0, k, m[0], 0);
+    let (t0, carry) = mac(t1, k, m[1], carry);
+    let (t1, carry) = mac(t2, k, m[2], carry);
+    let (t2, carry) = mac(t3, k, m[3], carry);
+    let (t3, carry) = adc(t4, carry, 0);
+    [t0, t1, t2, t3, t5 + carry]
+}
+
+/// Computes `a * b / 2^256 mod m`, for any `a < 2^256` and `b < m`.
+///
+/// The rounds are unrolled, as loops are not allowed in a `const fn` in the
+/// oldest Rust we support.
+const fn mont_mul(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4], inv: u64) -> [u64; 4] {
+    let t = mont_round([0; 5], a, b[0], m, inv);
+    let t = mont_round(t, a, b[1], m, inv);
+    let t = mont_round(t, a, b[2], m, inv);
+    let t = mont_round(t, a, b[3], m, inv);
+    reduce_once([t[0], t[1], t[2], t[3]], m)
+}
+
+fn lt(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    let mut i = 4;
+    while i > 0 {
+        i -= 1;
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+macro_rules! field_element {
+    (
+        $(#[$attr:meta])*
+        $name:ident,
+        modulus: $modulus:expr,
+        r: $r:expr,
+        r2: $r2:expr,
+        r3: $r3:expr,
+        inv: $inv:expr
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Default)]
+        pub struct $name(pub(crate) [u64; 4]);
+
+        impl $name {
+            pub(crate) const MODULUS: [u64; 4] = $modulus;
+            const R: [u64; 4] = $r;
+            const R2: [u64; 4] = $r2;
+            const R3: [u64; 4] = $r3;
+            const INV: u64 = $inv;
+
+            pub const fn zero() -> Self {
+                $name([0; 4])
+            }
+
+            pub const fn one() -> Self {
+                $name(Self::R)
+            }
+
+            /// Converts the canonical little-endian limbs of an element below
+            /// the modulus.
+            pub const fn from_raw(limbs: [u64; 4]) -> Self {
+                $name(mont_mul(&limbs, &Self::R2, &Self::MODULUS, Self::INV))
+            }
+
+            /// Returns the canonical little-endian limbs of the element.
+            pub const fn to_raw(&self) -> [u64; 4] {
+                mont_mul(&self.0, &[1, 0, 0, 0], &Self::MODULUS, Self::INV)
+            }
+
+            /// Reduces a 512-bit little-endian integer.
+            pub fn from_u512(limbs: [u64; 8]) -> Self {
+                let lo = [limbs[0], limbs[1], limbs[2], limbs[3]];
+                let hi = [limbs[4], limbs[5], limbs[6], limbs[7]];
+                $name(add_mod(
+                    &mont_mul(&lo, &Self::R2, &Self::MODULUS, Self::INV),
+                    &mont_mul(&hi, &Self::R3, &Self::MODULUS, Self::INV),
+                    &Self::MODULUS,
+                ))
+            }
+
+            /// Reads 32 big-endian bytes, which must be below the modulus.
+            pub fn from_bytes_be(bytes: &[u8; 32]) -> CtOption<Self> {
+                let mut limbs = [0; 4];
+                for (limb, chunk) in limbs.iter_mut().rev().zip(bytes.chunks(8)) {
+                    let mut word = [0; 8];
+                    word.copy_from_slice(chunk);
+                    *limb = u64::from_be_bytes(word);
+                }
+                let canonical = Choice::from(lt(&limbs, &Self::MODULUS) as u8);
+                CtOption::new(Self::from_raw(limbs), canonical)
+            }
+
+            pub fn to_bytes_be(&self) -> [u8; 32] {
+                let mut bytes = [0; 32];
+                for (chunk, limb) in bytes.chunks_mut(8).zip(self.to_raw().iter().rev()) {
+                    chunk.copy_from_slice(&limb.to_be_bytes());
+                }
+                bytes
+            }
+
+            pub fn random(mut rng: impl RngCore) -> Self {
+                let mut limbs = [0; 8];
+                for limb in limbs.iter_mut() {
+                    *limb = rng.next_u64();
+                }
+                Self::from_u512(limbs)
+            }
+
+            pub fn is_zero(&self) -> Choice {
+                self.ct_eq(&Self::zero())
+            }
+
+            /// Returns whether the element is above `(modulus - 1) / 2`, the
+            /// larger of it and its negation.
+            pub fn lexicographically_largest(&self) -> Choice {
+                let raw = self.to_raw();
+                let negated = (-*self).to_raw();
+                Choice::from(lt(&negated, &raw) as u8)
+            }
+
+            pub const fn add(&self, rhs: &Self) -> Self {
+                $name(add_mod(&self.0, &rhs.0, &Self::MODULUS))
+            }
+
+            pub const fn sub(&self, rhs: &Self) -> Self {
+                $name(sub_mod(&self.0, &rhs.0, &Self::MODULUS))
+            }
+
+            pub const fn neg(&self) -> Self {
+                $name(sub_mod(&[0; 4], &self.0, &Self::MODULUS))
+            }
+
+            pub const fn mul(&self, rhs: &Self) -> Self {
+                $name(mont_mul(&self.0, &rhs.0, &Self::MODULUS, Self::INV))
+            }
+
+            pub const fn square(&self) -> Self {
+                self.mul(self)
+            }
+
+            pub const fn double(&self) -> Self {
+                self.add(self)
+            }
+
+            pub fn pow_vartime(&self, exp: &[u64]) -> Self {
+                let mut res = Self::one();
+                for e in exp.iter().rev() {
+                    for i in (0..64).rev() {
+                        res = res.square();
+                        if ((*e >> i) & 1) == 1 {
+                            res = res.mul(self);
+                        }
+                    }
+                }
+                res
+            }
+
+            /// Inverts the element by Fermat's little theorem.
+            pub fn invert(&self) -> CtOption<Self> {
+                let (m0, _) = sbb(Self::MODULUS[0], 2, 0);
+                let exp = [m0, Self::MODULUS[1], Self::MODULUS[2], Self::MODULUS[3]];
+                CtOption::new(self.pow_vartime(&exp), !self.is_zero())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "0x")?;
+                for byte in self.to_bytes_be().iter() {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl ConstantTimeEq for $name {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                self.0[0].ct_eq(&other.0[0])
+                    & self.0[1].ct_eq(&other.0[1])
+                    & self.0[2].ct_eq(&other.0[2])
+                    & self.0[3].ct_eq(&other.0[3])
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                bool::from(self.ct_eq(other))
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl ConditionallySelectable for $name {
+            fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+                $name([
+                    u64::conditional_select(&a.0[0], &b.0[0], choice),
+                    u64::conditional_select(&a.0[1], &b.0[1], choice),
+                    u64::conditional_select(&a.0[2], &b.0[2], choice),
+                    u64::conditional_select(&a.0[3], &b.0[3], choice),
+                ])
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                Self::from_raw([value, 0, 0, 0])
+            }
+        }
+
+        impl<'a> Neg for &'a $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name::neg(self)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name::neg(&self)
+            }
+        }
+
+        impl_binop!($name, $name, $name, $name::add, Add, add);
+        impl_binop!($name, $name, $name, $name::sub, Sub, sub);
+        impl_binop!($name, $name, $name, $name::mul, Mul, mul);
+        impl_assign!($name, $name, $name::add, AddAssign, add_assign);
+        impl_assign!($name, $name, $name::sub, SubAssign, sub_assign);
+        impl_assign!($name, $name, $name::mul, MulAssign, mul_assign);
+    };
+}
+
+field_element!(
+    /// An element of the base field of BN254, of order
+    /// `p = 21888242871839275222246405745257275088696311157297823662689037894645226208583`.
+    Fp,
+    modulus: [0x3c20_8c16_d87c_fd47, 0x9781_6a91_6871_ca8d, 0xb850_45b6_8181_585d, 0x3064_4e72_e131_a029],
+    r: [0xd35d_438d_c58f_0d9d, 0x0a78_eb28_f5c7_0b3d, 0x666e_a36f_7879_462c, 0x0e0a_77c1_9a07_df2f],
+    r2: [0xf32c_fc5b_538a_fa89, 0xb5e7_1911_d445_01fb, 0x47ab_1eff_0a41_7ff6, 0x06d8_9f71_cab8_351f],
+    r3: [0xb1cd_6daf_da15_30df, 0x62f2_10e6_a728_3db6, 0xef7f_0b0c_0ada_0afb, 0x20fd_6e90_2d59_2544],
+    inv: 0x87d2_0782_e486_6389
+);
+
+field_element!(
+    /// An element of the scalar field of BN254, of order
+    /// `r = 21888242871839275222246405745257275088548364400416034343698204186575808495617`.
+    Fr,
+    modulus: [0x43e1_f593_f000_0001, 0x2833_e848_79b9_7091, 0xb850_45b6_8181_585d, 0x3064_4e72_e131_a029],
+    r: [0xac96_341c_4fff_fffb, 0x36fc_7695_9f60_cd29, 0x666e_a36f_7879_462e, 0x0e0a_77c1_9a07_df2f],
+    r2: [0x1bb8_e645_ae21_6da7, 0x53fe_3ab1_e35c_59e3, 0x8c49_833d_53bb_8085, 0x0216_d0b1_7f4e_44a5],
+    r3: [0x5e94_d8e1_b4bf_0040, 0x2a48_9cbe_1cfb_b6b8, 0x893c_c664_a19f_cfed, 0x0cf8_594b_7fcc_657c],
+    inv: 0xc2e1_f593_efff_ffff
+);
+
+impl Fp {
+    /// Computes the square root with `a^((p + 1) / 4)`, as `p = 3 mod 4`.
+    pub fn sqrt(&self) -> CtOption<Self> {
+        let root = self.pow_vartime(&[
+            0x4f08_2305_b61f_3f52,
+            0x65e0_5aa4_5a1c_72a3,
+            0x6e14_116d_a060_5617,
+            0x0c19_139c_b84c_680a,
+        ]);
+        CtOption::new(root, root.square().ct_eq(self))
+    }
+}
+
+/// `5^t` for `r - 1 = 2^28 * t`, a primitive `2^28`-th root of unity.
+const ROOT_OF_UNITY: Fr = Fr::from_raw([
+    0x9bd6_1b6e_725b_19f0,
+    0x402d_111e_4111_2ed4,
+    0x00e0_a7eb_8ef6_2abc,
+    0x2a3c_09f0_a58a_7e85,
+]);
+
+impl Field for Fr {
+    fn random(rng: impl RngCore) -> Self {
+        Fr::random(rng)
+    }
+
+    fn zero() -> Self {
+        Fr::zero()
+    }
+
+    fn one() -> Self {
+        Fr::one()
+    }
+
+    fn is_zero(&self) -> bool {
+        bool::from(Fr::is_zero(self))
+    }
+
+    fn square(&self) -> Self {
+        Fr::square(self)
+    }
+
+    fn double(&self) -> Self {
+        Fr::double(self)
+    }
+
+    fn invert(&self) -> CtOption<Self> {
+        Fr::invert(self)
+    }
+
+    /// Tonelli-Shanks, with the roots of unity of order `2^28`.
+    fn sqrt(&self) -> CtOption<Self> {
+        // (t - 1) / 2
+        let w = self.pow_vartime(&[
+            0xcdcb_848a_1f0f_ac9f,
+            0x0c0a_c2e9_419f_4243,
+            0x098d_014d_c282_2db4,
+            0x0000_0001_8322_7397,
+        ]);
+
+        let mut v = Self::S;
+        let mut x = *self * w;
+        let mut b = x * w;
+        let mut z = ROOT_OF_UNITY;
+
+        for max_v in (1..=Self::S).rev() {
+            let mut k = 1;
+            let mut tmp = b.square();
+            let mut j_less_than_v = Choice::from(1);
+
+            for j in 2..max_v {
+                let tmp_is_one = tmp.ct_eq(&Fr::one());
+                let squared = Fr::conditional_select(&tmp, &z, tmp_is_one).square();
+                tmp = Fr::conditional_select(&squared, &tmp, tmp_is_one);
+                let new_z = Fr::conditional_select(&z, &squared, tmp_is_one);
+                j_less_than_v &= !j.ct_eq(&v);
+                k = u32::conditional_select(&j, &k, tmp_is_one);
+                z = Fr::conditional_select(&z, &new_z, j_less_than_v);
+            }
+
+            let result = x * z;
+            x = Fr::conditional_select(&result, &x, b.ct_eq(&Fr::one()));
+            z = z.square();
+            b *= z;
+            v = k;
+        }
+
+        CtOption::new(x, x.square().ct_eq(self))
+    }
+}
+
+impl From<Fr> for [u8; 32] {
+    fn from(value: Fr) -> [u8; 32] {
+        value.to_repr()
+    }
+}
+
+impl<'a> From<&'a Fr> for [u8; 32] {
+    fn from(value: &'a Fr) -> [u8; 32] {
+        value.to_repr()
+    }
+}
+
+impl PrimeField for Fr {
+    /// The canonical little-endian bytes.
+    type Repr = [u8; 32];
+    type ReprBits = [u64; 4];
+
+    fn from_repr(repr: [u8; 32]) -> Option<Self> {
+        let mut bytes = repr;
+        bytes.reverse();
+        Fr::from_bytes_be(&bytes).into()
+    }
+
+    fn to_repr(&self) -> [u8; 32] {
+        let mut bytes = self.to_bytes_be();
+        bytes.reverse();
+        bytes
+    }
+
+    fn to_le_bits(&self) -> bitvec::array::BitArray<bitvec::order::Lsb0, [u64; 4]> {
+        bitvec::array::BitArray::new(self.to_raw())
+    }
+
+    fn is_odd(&self) -> bool {
+        self.to_raw()[0] & 1 == 1
+    }
+
+    fn char_le_bits() -> bitvec::array::BitArray<bitvec::order::Lsb0, [u64; 4]> {
+        bitvec::array::BitArray::new(Self::MODULUS)
+    }
+
+    const NUM_BITS: u32 = 254;
+    const CAPACITY: u32 = 253;
+    const S: u32 = 28;
+
+    fn multiplicative_generator() -> Self {
+        Fr::from(5)
+    }
+
+    fn root_of_unity() -> Self {
+        ROOT_OF_UNITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn arithmetic() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        assert_eq!(Fp::from(6) * Fp::from(7), Fp::from(42));
+        assert_eq!(-Fp::one() + Fp::from(2), Fp::one());
+        assert_eq!(Fp::from(3) - Fp::from(5), -Fp::from(2));
+        assert_eq!(Fp::from(9).sqrt().unwrap().square(), Fp::from(9));
+        // -1 is not a square modulo p = 3 mod 4.
+        assert!(bool::from(Fp::one().neg().sqrt().is_none()));
+
+        for _ in 0..20 {
+            let a = Fp::random(&mut rng);
+            assert_eq!(a * a.invert().unwrap(), Fp::one());
+            assert_eq!(Fp::from_bytes_be(&a.to_bytes_be()).unwrap(), a);
+
+            let s = <Fr as Field>::random(&mut rng);
+            assert_eq!(s * Field::invert(&s).unwrap(), Fr::one());
+            assert_eq!(Fr::from_repr(s.to_repr()), Some(s));
+            let square = Field::square(&s);
+            let root = Field::sqrt(&square).unwrap();
+            assert!(root == s || root == -s);
+        }
+        assert!(bool::from(Fr::from_bytes_be(&[0xff; 32]).is_none()));
+        // The modulus itself is not a canonical encoding.
+        let mut modulus = (-Fr::one()).to_repr();
+        modulus[0] += 1;
+        assert_eq!(Fr::from_repr(modulus), None);
+
+        assert_eq!(Fr::root_of_unity().pow_vartime(&[1 << 28]), Fr::one());
+        assert_ne!(Fr::root_of_unity().pow_vartime(&[1 << 27]), Fr::one());
+        assert_eq!(Fr::from_str("42"), Some(Fr::from(42)));
+    }
+}
@@ -0,0 +1,486 @@
+//! The tower of extensions of the base field that the pairing maps into:
+//! `Fp2 = Fp[u] / (u^2 + 1)`, `Fp6 = Fp2[v] / (v^3 - ξ)` with `ξ = 9 + u`,
+//! and `Fp12 = Fp6[w] / (w^2 - v)`.
+
+use rand_core::RngCore;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use super::fields::Fp;
+
+/// `c0 + c1 * u`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fp2 {
+    pub c0: Fp,
+    pub c1: Fp,
+}
+
+impl Fp2 {
+    pub const fn zero() -> Self {
+        Fp2 {
+            c0: Fp::zero(),
+            c1: Fp::zero(),
+        }
+    }
+
+    pub const fn one() -> Self {
+        Fp2 {
+            c0: Fp::one(),
+            c1: Fp::zero(),
+        }
+    }
+
+    pub fn is_zero(&self) -> Choice {
+        self.c0.is_zero() & self.c1.is_zero()
+    }
+
+    pub fn random(mut rng: impl RngCore) -> Self {
+        Fp2 {
+            c0: Fp::random(&mut rng),
+            c1: Fp::random(&mut rng),
+        }
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        Fp2 {
+            c0: self.c0.add(&rhs.c0),
+            c1: self.c1.add(&rhs.c1),
+        }
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        Fp2 {
+            c0: self.c0.sub(&rhs.c0),
+            c1: self.c1.sub(&rhs.c1),
+        }
+    }
+
+    pub fn neg(&self) -> Self {
+        Fp2 {
+            c0: self.c0.neg(),
+            c1: self.c1.neg(),
+        }
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        // Karatsuba: (a0 + a1 u)(b0 + b1 u) = a0 b0 - a1 b1 + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) u.
+        let t0 = self.c0.mul(&rhs.c0);
+        let t1 = self.c1.mul(&rhs.c1);
+        let t2 = self.c0.add(&self.c1).mul(&rhs.c0.add(&rhs.c1));
+        Fp2 {
+            c0: t0.sub(&t1),
+            c1: t2.sub(&t0).sub(&t1),
+        }
+    }
+
+    pub fn square(&self) -> Self {
+        // (a0 + a1 u)^2 = (a0 + a1)(a0 - a1) + 2 a0 a1 u.
+        Fp2 {
+            c0: self.c0.add(&self.c1).mul(&self.c0.sub(&self.c1)),
+            c1: self.c0.mul(&self.c1).double(),
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        self.add(self)
+    }
+
+    pub fn mul_by_fp(&self, rhs: &Fp) -> Self {
+        Fp2 {
+            c0: self.c0.mul(rhs),
+            c1: self.c1.mul(rhs),
+        }
+    }
+
+    /// Multiplies by `ξ = 9 + u`.
+    pub fn mul_by_nonresidue(&self) -> Self {
+        let nine_c0 = self.c0.double().double().double().add(&self.c0);
+        let nine_c1 = self.c1.double().double().double().add(&self.c1);
+        Fp2 {
+            c0: nine_c0.sub(&self.c1),
+            c1: nine_c1.add(&self.c0),
+        }
+    }
+
+    /// The conjugate, which is also the Frobenius map `x -> x^p`.
+    pub fn conjugate(&self) -> Self {
+        Fp2 {
+            c0: self.c0,
+            c1: self.c1.neg(),
+        }
+    }
+
+    pub fn invert(&self) -> CtOption<Self> {
+        (self.c0.square() + self.c1.square()).invert().map(|t| Fp2 {
+            c0: self.c0 * t,
+            c1: -(self.c1 * t),
+        })
+    }
+
+    pub fn pow_vartime(&self, exp: &[u64]) -> Self {
+        let mut res = Self::one();
+        for e in exp.iter().rev() {
+            for i in (0..64).rev() {
+                res = res.square();
+                if ((*e >> i) & 1) == 1 {
+                    res = res.mul(self);
+                }
+            }
+        }
+        res
+    }
+
+    /// Computes the square root with Algorithm 9 of
+    /// <https://eprint.iacr.org/2012/685>, as `p = 3 mod 4`.
+    pub fn sqrt(&self) -> CtOption<Self> {
+        // (p - 3) / 4
+        let a1 = self.pow_vartime(&[
+            0x4f08_2305_b61f_3f51,
+            0x65e0_5aa4_5a1c_72a3,
+            0x6e14_116d_a060_5617,
+            0x0c19_139c_b84c_680a,
+        ]);
+        let alpha = a1.square() * self;
+        let x0 = a1 * self;
+        let root = Fp2::conditional_select(
+            // (p - 1) / 2
+            &((alpha + Fp2::one()).pow_vartime(&[
+                0x9e10_460b_6c3e_7ea3,
+                0xcbc0_b548_b438_e546,
+                0xdc28_22db_40c0_ac2e,
+                0x1832_2739_7098_d014,
+            ]) * x0),
+            // alpha = -1, so the root is u x0.
+            &Fp2 {
+                c0: -x0.c1,
+                c1: x0.c0,
+            },
+            alpha.ct_eq(&-Fp2::one()),
+        );
+        CtOption::new(root, root.square().ct_eq(self))
+    }
+
+    /// Compares the imaginary parts, then the real parts.
+    pub fn lexicographically_largest(&self) -> Choice {
+        self.c1.lexicographically_largest()
+            | (self.c1.is_zero() & self.c0.lexicographically_largest())
+    }
+
+    /// Reads the imaginary part then the real part, as 32 big-endian bytes
+    /// each, both below the modulus.
+    pub fn from_bytes_be(bytes: &[u8; 64]) -> CtOption<Self> {
+        let mut c1 = [0; 32];
+        let mut c0 = [0; 32];
+        c1.copy_from_slice(&bytes[..32]);
+        c0.copy_from_slice(&bytes[32..]);
+        Fp::from_bytes_be(&c1).and_then(|c1| Fp::from_bytes_be(&c0).map(|c0| Fp2 { c0, c1 }))
+    }
+
+    pub fn to_bytes_be(&self) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&self.c1.to_bytes_be());
+        bytes[32..].copy_from_slice(&self.c0.to_bytes_be());
+        bytes
+    }
+}
+
+impl ConstantTimeEq for Fp2 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.c0.ct_eq(&other.c0) & self.c1.ct_eq(&other.c1)
+    }
+}
+
+impl ConditionallySelectable for Fp2 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Fp2 {
+            c0: Fp::conditional_select(&a.c0, &b.c0, choice),
+            c1: Fp::conditional_select(&a.c1, &b.c1, choice),
+        }
+    }
+}
+
+impl Neg for Fp2 {
+    type Output = Fp2;
+
+    fn neg(self) -> Fp2 {
+        Fp2::neg(&self)
+    }
+}
+
+impl_binop!(Fp2, Fp2, Fp2, Fp2::add, Add, add);
+impl_binop!(Fp2, Fp2, Fp2, Fp2::sub, Sub, sub);
+impl_binop!(Fp2, Fp2, Fp2, Fp2::mul, Mul, mul);
+impl_assign!(Fp2, Fp2, Fp2::add, AddAssign, add_assign);
+impl_assign!(Fp2, Fp2, Fp2::sub, SubAssign, sub_assign);
+impl_assign!(Fp2, Fp2, Fp2::mul, MulAssign, mul_assign);
+
+/// `c0 + c1 * v + c2 * v^2`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fp6 {
+    pub c0: Fp2,
+    pub c1: Fp2,
+    pub c2: Fp2,
+}
+
+impl Fp6 {
+    pub const fn zero() -> Self {
+        Fp6 {
+            c0: Fp2::zero(),
+            c1: Fp2::zero(),
+            c2: Fp2::zero(),
+        }
+    }
+
+    pub const fn one() -> Self {
+        Fp6 {
+            c0: Fp2::one(),
+            c1: Fp2::zero(),
+            c2: Fp2::zero(),
+        }
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        Fp6 {
+            c0: self.c0.add(&rhs.c0),
+            c1: self.c1.add(&rhs.c1),
+            c2: self.c2.add(&rhs.c2),
+        }
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        Fp6 {
+            c0: self.c0.sub(&rhs.c0),
+            c1: self.c1.sub(&rhs.c1),
+            c2: self.c2.sub(&rhs.c2),
+        }
+    }
+
+    pub fn neg(&self) -> Self {
+        Fp6 {
+            c0: self.c0.neg(),
+            c1: self.c1.neg(),
+            c2: self.c2.neg(),
+        }
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        let (a, b) = (self, rhs);
+        Fp6 {
+            c0: a
+                .c0
+                .mul(&b.c0)
+                .add(&a.c1.mul(&b.c2).add(&a.c2.mul(&b.c1)).mul_by_nonresidue()),
+            c1: a
+                .c0
+                .mul(&b.c1)
+                .add(&a.c1.mul(&b.c0))
+                .add(&a.c2.mul(&b.c2).mul_by_nonresidue()),
+            c2: a.c0.mul(&b.c2).add(&a.c1.mul(&b.c1)).add(&a.c2.mul(&b.c0)),
+        }
+    }
+
+    pub fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    /// Multiplies by `v`, with `v^3 = ξ`.
+    pub fn mul_by_nonresidue(&self) -> Self {
+        Fp6 {
+            c0: self.c2.mul_by_nonresidue(),
+            c1: self.c0,
+            c2: self.c1,
+        }
+    }
+
+    pub fn invert(&self) -> CtOption<Self> {
+        let t0 = self.c0.square() - (self.c1 * self.c2).mul_by_nonresidue();
+        let t1 = self.c2.square().mul_by_nonresidue() - self.c0 * self.c1;
+        let t2 = self.c1.square() - self.c0 * self.c2;
+        let norm = self.c0 * t0 + (self.c2 * t1 + self.c1 * t2).mul_by_nonresidue();
+        norm.invert().map(|t| Fp6 {
+            c0: t0 * t,
+            c1: t1 * t,
+            c2: t2 * t,
+        })
+    }
+}
+
+impl_binop!(Fp6, Fp6, Fp6, Fp6::add, Add, add);
+impl_binop!(Fp6, Fp6, Fp6, Fp6::sub, Sub, sub);
+impl_binop!(Fp6, Fp6, Fp6, Fp6::mul, Mul, mul);
+
+impl ConstantTimeEq for Fp6 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.c0.ct_eq(&other.c0) & self.c1.ct_eq(&other.c1) & self.c2.ct_eq(&other.c2)
+    }
+}
+
+impl ConditionallySelectable for Fp6 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Fp6 {
+            c0: Fp2::conditional_select(&a.c0, &b.c0, choice),
+            c1: Fp2::conditional_select(&a.c1, &b.c1, choice),
+            c2: Fp2::conditional_select(&a.c2, &b.c2, choice),
+        }
+    }
+}
+
+/// `c0 + c1 * w`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fp12 {
+    pub c0: Fp6,
+    pub c1: Fp6,
+}
+
+impl Fp12 {
+    pub const fn one() -> Self {
+        Fp12 {
+            c0: Fp6::one(),
+            c1: Fp6::zero(),
+        }
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        let (a, b) = (self, rhs);
+        Fp12 {
+            c0: a.c0.mul(&b.c0).add(&a.c1.mul(&b.c1).mul_by_nonresidue()),
+            c1: a.c0.mul(&b.c1).add(&a.c1.mul(&b.c0)),
+        }
+    }
+
+    pub fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    /// The conjugate, which is also the Frobenius map `x -> x^(p^6)`, and
+    /// the inverse of elements of order dividing `p^6 + 1`.
+    pub fn conjugate(&self) -> Self {
+        Fp12 {
+            c0: self.c0,
+            c1: self.c1.neg(),
+        }
+    }
+
+    pub fn invert(&self) -> CtOption<Self> {
+        (self.c0.square() - self.c1.square().mul_by_nonresidue())
+            .invert()
+            .map(|t| Fp12 {
+                c0: self.c0.mul(&t),
+                c1: self.c1.mul(&t).neg(),
+            })
+    }
+
+    pub fn pow_vartime(&self, exp: &[u64]) -> Self {
+        let mut res = Self::one();
+        for e in exp.iter().rev() {
+            for i in (0..64).rev() {
+                res = res.square();
+                if ((*e >> i) & 1) == 1 {
+                    res = res.mul(self);
+                }
+            }
+        }
+        res
+    }
+}
+
+impl ConstantTimeEq for Fp12 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.c0.ct_eq(&other.c0) & self.c1.ct_eq(&other.c1)
+    }
+}
+
+impl ConditionallySelectable for Fp12 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Fp12 {
+            c0: Fp6::conditional_select(&a.c0, &b.c0, choice),
+            c1: Fp6::conditional_select(&a.c1, &b.c1, choice),
+        }
+    }
+}
+
+impl_binop!(Fp12, Fp12, Fp12, Fp12::mul, Mul, mul);
+impl_assign!(Fp12, Fp12, Fp12::mul, MulAssign, mul_assign);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn tower() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut fp2 = || Fp2 {
+            c0: Fp::random(&mut rng),
+            c1: Fp::random(&mut rng),
+        };
+        let u = Fp2 {
+            c0: Fp::zero(),
+            c1: Fp::one(),
+        };
+        assert_eq!(u.square(), -Fp2::one());
+
+        for _ in 0..10 {
+            let (a, b) = (fp2(), fp2());
+            assert_eq!(a * b, b * a);
+            assert_eq!(a.square(), a * a);
+            assert_eq!(a * a.invert().unwrap(), Fp2::one());
+            assert_eq!(
+                a.mul_by_nonresidue(),
+                a * Fp2 {
+                    c0: Fp::from(9),
+                    c1: Fp::one()
+                }
+            );
+            assert_eq!(Fp2::from_bytes_be(&a.to_bytes_be()).unwrap(), a);
+            let root = a.square().sqrt().unwrap();
+            assert!(root == a || root == -a);
+
+            let c = Fp6 {
+                c0: a,
+                c1: b,
+                c2: fp2(),
+            };
+            assert_eq!(c.mul(&c.invert().unwrap()), Fp6::one());
+            let d = Fp12 {
+                c0: c,
+                c1: Fp6 {
+                    c0: fp2(),
+                    c1: fp2(),
+                    c2: fp2(),
+                },
+            };
+            assert_eq!(d * d.invert().unwrap(), Fp12::one());
+        }
+
+        // v^3 = ξ and w^2 = v.
+        let v = Fp6 {
+            c0: Fp2::zero(),
+            c1: Fp2::one(),
+            c2: Fp2::zero(),
+        };
+        assert_eq!(
+            v.square().mul(&v),
+            Fp6 {
+                c0: Fp2::one().mul_by_nonresidue(),
+                c1: Fp2::zero(),
+                c2: Fp2::zero()
+            }
+        );
+        let w = Fp12 {
+            c0: Fp6::zero(),
+            c1: Fp6::one(),
+        };
+        assert_eq!(
+            w.square(),
+            Fp12 {
+                c0: v,
+                c1: Fp6::zero()
+            }
+        );
+    }
+}
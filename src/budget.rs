@@ -0,0 +1,339 @@
+//! Per-job limits on the threads, memory and time of proving.
+//!
+//! A service that proves for several tenants at once gives each job a
+//! [`ProvingBudget`], and proves it with [`create_proof_with_budget`], or on
+//! a worker made by [`Worker::with_budget`]. The worker has at most
+//! `max_threads` threads, and the FFTs and multiexponentiations that run on
+//! it check the budget as they go: once the job has run for `max_time`, or
+//! would hold more than `max_memory` bytes, they stop early and proving
+//! fails with [`BudgetError::Exceeded`], leaving the threads and the memory
+//! to the other jobs.
+//!
+//! The functions that return a [`SynthesisError`] carry the exceeded limit
+//! as a [`BudgetExceeded`] in a [`SynthesisError::IoError`], from which
+//! [`BudgetError::from`] takes it back out.
+//!
+//! Memory is counted by the prover as it allocates its large buffers: the
+//! witness and quotient polynomials as estimated by the [planner], the
+//! scratch space of the FFTs, and the buckets of the multiexponentiations.
+//! It bounds those buffers rather than the memory of the process, which the
+//! circuit and the parameters add to.
+//!
+//! [planner]: crate::groth16::planner
+//! [`create_proof_with_budget`]: crate::groth16::create_proof_with_budget
+//! [`Worker::with_budget`]: crate::multicore::Worker::with_budget
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::SynthesisError;
+
+/// The resources one proving job may use. Each limit is unbounded when it
+/// is `None`, as in the default budget.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProvingBudget {
+    /// The number of threads of the worker, at most the number of CPUs.
+    pub max_threads: Option<usize>,
+    /// The bytes of the buffers of proving held at once.
+    pub max_memory: Option<usize>,
+    /// The wall time from the creation of the worker.
+    pub max_time: Option<Duration>,
+}
+
+/// A limit of a [`ProvingBudget`] that a job ran into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    Memory,
+    Time,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Resource::Memory => "memory",
+            Resource::Time => "time",
+        })
+    }
+}
+
+/// The limit a job ran into, as the source of a [`SynthesisError::IoError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded(pub Resource);
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exceeded the {} budget of proving", self.0)
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+impl From<BudgetExceeded> for SynthesisError {
+    fn from(e: BudgetExceeded) -> SynthesisError {
+        SynthesisError::IoError(io::Error::new(io::ErrorKind::Other, e))
+    }
+}
+
+/// An error of proving on a [`ProvingBudget`].
+#[derive(Debug)]
+pub enum BudgetError {
+    /// The job ran out of a limit of its budget.
+    Exceeded(Resource),
+    /// The job failed within its budget.
+    Synthesis(SynthesisError),
+}
+
+impl From<SynthesisError> for BudgetError {
+    /// Takes the limit out of the errors that carry a [`BudgetExceeded`].
+    fn from(e: SynthesisError) -> BudgetError {
+        let exceeded = match &e {
+            SynthesisError::IoError(io) => io
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<BudgetExceeded>())
+                .copied(),
+            _ => None,
+        };
+        match exceeded {
+            Some(BudgetExceeded(resource)) => BudgetError::Exceeded(resource),
+            None => BudgetError::Synthesis(e),
+        }
+    }
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::Exceeded(resource) => BudgetExceeded(*resource).fmt(f),
+            BudgetError::Synthesis(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for BudgetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BudgetError::Exceeded(_) => None,
+            BudgetError::Synthesis(e) => Some(e),
+        }
+    }
+}
+
+const WITHIN: u8 = 0;
+const MEMORY: u8 = 1;
+const TIME: u8 = 2;
+
+struct State {
+    deadline: Option<Instant>,
+    max_memory: Option<usize>,
+    used: AtomicUsize,
+    /// The first limit that was exceeded, so that every thread of the job
+    /// stops once one of them runs out.
+    exceeded: AtomicU8,
+}
+
+/// The budget of a worker, shared by the threads it runs on. A worker
+/// without a budget has no state to check.
+#[derive(Clone, Default)]
+pub(crate) struct Budget(Option<Arc<State>>);
+
+impl Budget {
+    pub(crate) fn new(budget: &ProvingBudget) -> Self {
+        Budget(Some(Arc::new(State {
+            deadline: budget.max_time.map(|time| Instant::now() + time),
+            max_memory: budget.max_memory,
+            used: AtomicUsize::new(0),
+            exceeded: AtomicU8::new(WITHIN),
+        })))
+    }
+
+    fn exceed(state: &State, resource: Resource) -> SynthesisError {
+        let code = match resource {
+            Resource::Memory => MEMORY,
+            Resource::Time => TIME,
+        };
+        let _ = state
+            .exceeded
+            .compare_exchange(WITHIN, code, Ordering::Relaxed, Ordering::Relaxed);
+        BudgetExceeded(resource).into()
+    }
+
+    /// Fails if a limit was exceeded, or the time has run out.
+    pub(crate) fn check(&self) -> Result<(), SynthesisError> {
+        let state = match &self.0 {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        match state.exceeded.load(Ordering::Relaxed) {
+            MEMORY => return Err(BudgetExceeded(Resource::Memory).into()),
+            TIME => return Err(BudgetExceeded(Resource::Time).into()),
+            _ => {}
+        }
+        if state
+            .deadline
+            .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            return Err(Self::exceed(state, Resource::Time));
+        }
+        Ok(())
+    }
+
+    /// Returns whether the job should stop, for the computations that cannot
+    /// fail themselves.
+    pub(crate) fn exceeded(&self) -> bool {
+        self.check().is_err()
+    }
+
+    /// Accounts for a buffer of `bytes` until the reservation is dropped, or
+    /// fails if it does not fit in the memory left.
+    pub(crate) fn reserve(&self, bytes: usize) -> Result<Reservation, SynthesisError> {
+        self.check()?;
+        let state = match &self.0 {
+            Some(state) => state,
+            None => return Ok(Reservation(None, 0)),
+        };
+        let max = state.max_memory.unwrap_or(usize::MAX);
+        let mut used = state.used.load(Ordering::Relaxed);
+        loop {
+            let next = match used.checked_add(bytes).filter(|&next| next <= max) {
+                Some(next) => next,
+                None => return Err(Self::exceed(state, Resource::Memory)),
+            };
+            match state
+                .used
+                .compare_exchange_weak(used, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => used = current,
+            }
+        }
+        Ok(Reservation(Some(state.clone()), bytes))
+    }
+
+    /// Returns the bytes left to reserve, if the memory is limited.
+    #[cfg_attr(not(feature = "groth16"), allow(dead_code))]
+    pub(crate) fn remaining_memory(&self) -> Option<usize> {
+        let state = self.0.as_ref()?;
+        let max = state.max_memory?;
+        Some(max.saturating_sub(state.used.load(Ordering::Relaxed)))
+    }
+}
+
+/// Memory counted against a budget, released when dropped.
+pub(crate) struct Reservation(Option<Arc<State>>, usize);
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(state) = &self.0 {
+            state.used.fetch_sub(self.1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservations() {
+        let budget = Budget::new(&ProvingBudget {
+            max_memory: Some(100),
+            ..ProvingBudget::default()
+        });
+        let a = budget.reserve(60).unwrap();
+        assert_eq!(budget.remaining_memory(), Some(40));
+        drop(a);
+        let _b = budget.reserve(100).unwrap();
+        assert_eq!(budget.remaining_memory(), Some(0));
+        assert!(matches!(
+            budget.reserve(1).map_err(BudgetError::from),
+            Err(BudgetError::Exceeded(Resource::Memory))
+        ));
+        // Once exceeded, the budget stays exceeded for the whole job.
+        assert!(budget.exceeded());
+
+        let budget = Budget::new(&ProvingBudget {
+            max_time: Some(Duration::from_secs(0)),
+            ..ProvingBudget::default()
+        });
+        assert!(matches!(
+            budget.check().map_err(BudgetError::from),
+            Err(BudgetError::Exceeded(Resource::Time))
+        ));
+        assert!(matches!(
+            BudgetError::from(SynthesisError::Unsatisfiable),
+            BudgetError::Synthesis(SynthesisError::Unsatisfiable)
+        ));
+
+        let unlimited = Budget::default();
+        assert!(unlimited.reserve(usize::MAX).is_ok());
+        assert_eq!(unlimited.remaining_memory(), None);
+    }
+
+    #[cfg(feature = "groth16")]
+    #[test]
+    fn budgeted_proofs() {
+        use crate::groth16::planner::{plan, Plan};
+        use crate::groth16::tests::fixture::Fixture;
+        use crate::groth16::{
+            create_proof_on, create_proof_planned, create_proof_with_budget, verify_proof,
+        };
+        use crate::multicore::Worker;
+        use bls12_381::{Bls12, Scalar};
+        use ff::Field;
+
+        let f = Fixture::<Bls12>::new();
+        let (params, pvk, replay) = (&f.params, &f.pvk, || f.circuit());
+        let mut rng = f.rng.clone();
+        let (r, s) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let expected = create_proof_on(&Worker::new(), replay(), params, r, s).unwrap();
+
+        let ample = Worker::with_budget(&ProvingBudget {
+            max_threads: Some(2),
+            max_memory: Some(1 << 30),
+            max_time: Some(Duration::from_secs(3600)),
+        });
+        let proof = create_proof_on(&ample, replay(), params, r, s).unwrap();
+        assert!(proof == expected);
+        assert!(verify_proof(pvk, &proof, f.inputs()).is_ok());
+        // Every reservation is released with the job.
+        assert_eq!(ample.budget.remaining_memory(), Some(1 << 30));
+
+        let out_of_time = ProvingBudget {
+            max_time: Some(Duration::from_secs(0)),
+            ..ProvingBudget::default()
+        };
+        assert!(matches!(
+            create_proof_with_budget(&out_of_time, replay(), params, r, s),
+            Err(BudgetError::Exceeded(Resource::Time))
+        ));
+
+        let no_memory = Worker::with_budget(&ProvingBudget {
+            max_memory: Some(64),
+            ..ProvingBudget::default()
+        });
+        assert!(matches!(
+            create_proof_on(&no_memory, replay(), params, r, s).map_err(BudgetError::from),
+            Err(BudgetError::Exceeded(Resource::Memory))
+        ));
+        // Without the witness counted, the FFTs or the multiexponentiations
+        // run out instead.
+        let no_memory = Worker::with_budget(&ProvingBudget {
+            max_memory: Some(64),
+            ..ProvingBudget::default()
+        });
+        assert!(matches!(
+            create_proof_planned(&no_memory, replay(), params, r, s, |stats, capabilities| {
+                Plan {
+                    estimated_memory: 0,
+                    ..plan(stats, capabilities)
+                }
+            })
+            .map_err(BudgetError::from),
+            Err(BudgetError::Exceeded(Resource::Memory))
+        ));
+    }
+}
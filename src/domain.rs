@@ -8,19 +8,150 @@
 //! field. This allows us to perform polynomial operations in O(n) by performing
 //! an O(n log n) FFT over such a domain.
 //!
+//! Fields whose multiplicative group also has a subgroup of order 3 or 9
+//! support mixed-radix domains of 2<sup>a</sup>3<sup>b</sup> points, which
+//! fit polynomials just over a power of two more tightly, and extend the
+//! domains of fields with few two-adic roots of unity. [`Radix`] chooses
+//! between the two, and [`EvaluationDomain::from_coeffs`] falls back to a
+//! mixed-radix domain when a power of two is too large for the field.
+//!
+//!
+//! The [`polynomial`] module builds general polynomial arithmetic on top of it.
+//!
 //! [`EvaluationDomain`]: crate::domain::EvaluationDomain
 //! [Groth16]: https://eprint.iacr.org/2016/260
 
 use ff::PrimeField;
 use group::cofactor::CofactorCurve;
 
+use super::budget::Budget;
 use super::SynthesisError;
 
 use super::multicore::Worker;
 
+pub mod polynomial;
+
+/// The sizes of evaluation domains to choose from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radix {
+    /// Powers of two, or the mixed-radix sizes when the field has no large
+    /// enough power of two. The default.
+    Two,
+    /// The smallest product of powers of two and three that the field
+    /// supports.
+    Mixed,
+}
+
+impl Default for Radix {
+    fn default() -> Self {
+        Radix::Two
+    }
+}
+
+/// Returns the largest `b` such that `3^b` divides the order of the
+/// multiplicative group of `S`, up to `3^b` fitting in a `usize`, along
+/// with a `3^b` primitive root of unity. Fields whose representation is not
+/// the little- or big-endian bytes of the canonical integer are assumed to
+/// have none.
+fn three_adic_root<S: PrimeField>() -> (u32, S) {
+    let one = S::one().to_repr();
+    let one = one.as_ref();
+    let big_endian = match one.iter().position(|b| *b != 0) {
+        Some(0) if one[0] == 1 && one[1..].iter().all(|b| *b == 0) => false,
+        Some(i) if i == one.len() - 1 && one[i] == 1 => true,
+        _ => return (0, S::one()),
+    };
+
+    // The little-endian limbs of p - 1.
+    let mut bytes = (-S::one()).to_repr().as_ref().to_vec();
+    if big_endian {
+        bytes.reverse();
+    }
+    let mut limbs = bytes
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .rev()
+                .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte))
+        })
+        .collect::<Vec<_>>();
+
+    // Divide p - 1 by 3 for as long as it is a multiple of it.
+    let mut b = 0;
+    while 3usize.checked_pow(b + 1).is_some() {
+        let mut quotient = limbs.clone();
+        let mut remainder = 0u128;
+        for limb in quotient.iter_mut().rev() {
+            let acc = (remainder << 64) | u128::from(*limb);
+            *limb = (acc / 3) as u64;
+            remainder = acc % 3;
+        }
+        if remainder != 0 {
+            break;
+        }
+        limbs = quotient;
+        b += 1;
+    }
+
+    // The generator raised to (p - 1) / 3^b has order 3^b, or less if it
+    // does not generate the whole group.
+    let root = S::multiplicative_generator().pow_vartime(&limbs);
+    let mut order = 0;
+    let mut power = root;
+    while power != S::one() {
+        if order == b {
+            return (0, S::one());
+        }
+        power = power.square() * power;
+        order += 1;
+    }
+    (order, root)
+}
+
+/// Returns the number of points of the smallest domain of `radix` that fits
+/// `len` coefficients, or fails with
+/// [`SynthesisError::PolynomialDegreeTooLarge`] if the field has none.
+pub fn domain_size<S: PrimeField>(len: usize, radix: Radix) -> Result<usize, SynthesisError> {
+    let len = std::cmp::max(len, 1);
+    let two = len
+        .checked_next_power_of_two()
+        .filter(|m| m.trailing_zeros() < S::S);
+    match (radix, two) {
+        (Radix::Two, Some(m)) => return Ok(m),
+        (Radix::Mixed, _) | (Radix::Two, None) => {}
+    }
+
+    let (b, _) = three_adic_root::<S>();
+    let mut best = None;
+    let mut three = 1usize;
+    for _ in 0..=b {
+        // The smallest power of two that makes `three` big enough.
+        let mut m = three;
+        let mut exp = 0;
+        while m < len {
+            m = match m.checked_mul(2) {
+                Some(m) => m,
+                None => break,
+            };
+            exp += 1;
+        }
+        if m >= len && exp < S::S && best.map_or(true, |best| m < best) {
+            best = Some(m);
+        }
+        three = match three.checked_mul(3) {
+            Some(three) => three,
+            None => break,
+        };
+    }
+    best.ok_or(SynthesisError::PolynomialDegreeTooLarge)
+}
+
 pub struct EvaluationDomain<S: PrimeField, G: Group<S>> {
     coeffs: Vec<G>,
+    /// The domain has 2^exp 3^exp3 points.
     exp: u32,
+    exp3: u32,
     omega: S,
     omegainv: S,
     geninv: S,
@@ -44,46 +175,84 @@ impl<S: PrimeField, G: Group<S>> EvaluationDomain<S, G> {
         self.coeffs
     }
 
-    pub fn from_coeffs(mut coeffs: Vec<G>) -> Result<EvaluationDomain<S, G>, SynthesisError> {
-        // Compute the size of our evaluation domain
-        let mut m = 1;
-        let mut exp = 0;
-        while m < coeffs.len() {
-            m *= 2;
-            exp += 1;
+    /// Pads `coeffs` to a domain of a power of two points, or of
+    /// 2<sup>a</sup>3<sup>b</sup> points if the field has no large enough
+    /// power of two.
+    pub fn from_coeffs(coeffs: Vec<G>) -> Result<EvaluationDomain<S, G>, SynthesisError> {
+        let size = domain_size::<S>(coeffs.len(), Radix::Two)?;
+        Self::with_size(coeffs, size)
+    }
 
-            // The pairing-friendly curve may not be able to support
-            // large enough (radix2) evaluation domains.
-            if exp >= S::S {
-                return Err(SynthesisError::PolynomialDegreeTooLarge);
-            }
+    /// Pads `coeffs` to the smallest mixed-radix domain that fits them.
+    pub fn from_coeffs_mixed(coeffs: Vec<G>) -> Result<EvaluationDomain<S, G>, SynthesisError> {
+        let size = domain_size::<S>(coeffs.len(), Radix::Mixed)?;
+        Self::with_size(coeffs, size)
+    }
+
+    /// Pads `coeffs` to a domain of `size` points, which must be
+    /// 2<sup>a</sup>3<sup>b</sup> for a field with such roots of unity, and
+    /// at least the number of coefficients.
+    pub fn with_size(
+        mut coeffs: Vec<G>,
+        size: usize,
+    ) -> Result<EvaluationDomain<S, G>, SynthesisError> {
+        if size < coeffs.len() || size == 0 {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+        let exp = size.trailing_zeros();
+        let mut odd = size >> exp;
+        let mut exp3 = 0;
+        while odd % 3 == 0 {
+            odd /= 3;
+            exp3 += 1;
         }
 
-        // Compute omega, the 2^exp primitive root of unity
+        // The pairing-friendly curve may not be able to support
+        // large enough (radix2) evaluation domains.
+        if odd != 1 || exp >= S::S {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        // Compute omega, the 2^exp 3^exp3 primitive root of unity
         let mut omega = S::root_of_unity();
         for _ in exp..S::S {
             omega = omega.square();
         }
+        if exp3 > 0 {
+            let (b, root) = three_adic_root::<S>();
+            if exp3 > b {
+                return Err(SynthesisError::PolynomialDegreeTooLarge);
+            }
+            let root = (exp3..b).fold(root, |root, _| root.square() * root);
+            omega.mul_assign(&root);
+        }
 
         // Extend the coeffs vector with zeroes if necessary
-        coeffs.resize(m, G::group_zero());
+        coeffs.resize(size, G::group_zero());
 
         Ok(EvaluationDomain {
             coeffs,
             exp,
+            exp3,
             omega,
             omegainv: omega.invert().unwrap(),
             geninv: S::multiplicative_generator().invert().unwrap(),
-            minv: S::from_str(&format!("{}", m)).unwrap().invert().unwrap(),
+            minv: S::from_str(&format!("{}", size)).unwrap().invert().unwrap(),
         })
     }
 
     pub fn fft(&mut self, worker: &Worker) {
-        best_fft(&mut self.coeffs, worker, &self.omega, self.exp);
+        best_mixed_fft(&mut self.coeffs, worker, &self.omega, self.exp, self.exp3);
     }
 
     pub fn ifft(&mut self, worker: &Worker) {
-        best_fft(&mut self.coeffs, worker, &self.omegainv, self.exp);
+        best_mixed_fft(
+            &mut self.coeffs,
+            worker,
+            &self.omegainv,
+            self.exp,
+            self.exp3,
+        );
 
         worker.scope(self.coeffs.len(), |scope, chunk| {
             let minv = self.minv;
@@ -102,7 +271,7 @@ impl<S: PrimeField, G: Group<S>> EvaluationDomain<S, G> {
         worker.scope(self.coeffs.len(), |scope, chunk| {
             for (i, v) in self.coeffs.chunks_mut(chunk).enumerate() {
                 scope.spawn(move |_scope| {
-                    let mut u = g.pow_vartime(&[(i * chunk) as u64]);
+                    let mut u = g.pow_vartime([(i * chunk) as u64]);
                     for v in v.iter_mut() {
                         v.group_mul_assign(&u);
                         u.mul_assign(&g);
@@ -125,9 +294,9 @@ impl<S: PrimeField, G: Group<S>> EvaluationDomain<S, G> {
     }
 
     /// This evaluates t(tau) for this domain, which is
-    /// tau^m - 1 for these radix-2 domains.
+    /// tau^m - 1 for these domains of the m-th roots of unity.
     pub fn z(&self, tau: &S) -> S {
-        let mut tmp = tau.pow_vartime(&[self.coeffs.len() as u64]);
+        let mut tmp = tau.pow_vartime([self.coeffs.len() as u64]);
         tmp.sub_assign(&S::one());
 
         tmp
@@ -189,7 +358,7 @@ impl<S: PrimeField, G: Group<S>> EvaluationDomain<S, G> {
     }
 }
 
-pub trait Group<Scalar: PrimeField>: Sized + Copy + Clone + Send + Sync {
+pub trait Group<Scalar: PrimeField>: Sized + Copy + Clone + Send + Sync + 'static {
     fn group_zero() -> Self;
     fn group_mul_assign(&mut self, by: &Scalar);
     fn group_add_assign(&mut self, other: &Self);
@@ -259,16 +428,34 @@ impl<S: PrimeField> Group<S> for Scalar<S> {
 }
 
 fn best_fft<S: PrimeField, T: Group<S>>(a: &mut [T], worker: &Worker, omega: &S, log_n: u32) {
+    let mut span = crate::trace::span("fft");
+    span.record("size", a.len());
+
+    if worker.budget.exceeded() {
+        return;
+    }
+
+    #[cfg(feature = "gpu")]
+    {
+        if worker
+            .devices()
+            .map_or(false, |devices| devices.fft(a, omega, log_n))
+        {
+            return;
+        }
+    }
+
     let log_cpus = worker.log_num_cpus();
 
     if log_n <= log_cpus {
-        serial_fft(a, omega, log_n);
+        serial_fft(a, omega, log_n, &worker.budget);
     } else {
         parallel_fft(a, worker, omega, log_n, log_cpus);
     }
 }
 
-fn serial_fft<S: PrimeField, T: Group<S>>(a: &mut [T], omega: &S, log_n: u32) {
+/// Stops between rounds once `budget` is exceeded.
+fn serial_fft<S: PrimeField, T: Group<S>>(a: &mut [T], omega: &S, log_n: u32, budget: &Budget) {
     fn bitreverse(mut n: u32, l: u32) -> u32 {
         let mut r = 0;
         for _ in 0..l {
@@ -290,7 +477,10 @@ fn serial_fft<S: PrimeField, T: Group<S>>(a: &mut [T], omega: &S, log_n: u32) {
 
     let mut m = 1;
     for _ in 0..log_n {
-        let w_m = omega.pow_vartime(&[u64::from(n / (2 * m))]);
+        if budget.exceeded() {
+            return;
+        }
+        let w_m = omega.pow_vartime([u64::from(n / (2 * m))]);
 
         let mut k = 0;
         while k < n {
@@ -323,8 +513,12 @@ fn parallel_fft<S: PrimeField, T: Group<S>>(
 
     let num_cpus = 1 << log_cpus;
     let log_new_n = log_n - log_cpus;
+    let _scratch = match worker.budget.reserve(std::mem::size_of_val(a)) {
+        Ok(reservation) => reservation,
+        Err(_) => return,
+    };
     let mut tmp = vec![vec![T::group_zero(); 1 << log_new_n]; num_cpus];
-    let new_omega = omega.pow_vartime(&[num_cpus as u64]);
+    let new_omega = omega.pow_vartime([num_cpus as u64]);
 
     worker.scope(0, |scope, _| {
         let a = &*a;
@@ -332,8 +526,8 @@ fn parallel_fft<S: PrimeField, T: Group<S>>(
         for (j, tmp) in tmp.iter_mut().enumerate() {
             scope.spawn(move |_scope| {
                 // Shuffle into a sub-FFT
-                let omega_j = omega.pow_vartime(&[j as u64]);
-                let omega_step = omega.pow_vartime(&[(j as u64) << log_new_n]);
+                let omega_j = omega.pow_vartime([j as u64]);
+                let omega_step = omega.pow_vartime([(j as u64) << log_new_n]);
 
                 let mut elt = S::one();
                 for (i, tmp) in tmp.iter_mut().enumerate() {
@@ -348,7 +542,7 @@ fn parallel_fft<S: PrimeField, T: Group<S>>(
                 }
 
                 // Perform sub-FFT
-                serial_fft(tmp, &new_omega, log_new_n);
+                serial_fft(tmp, &new_omega, log_new_n, &worker.budget);
             });
         }
     });
@@ -370,6 +564,174 @@ fn parallel_fft<S: PrimeField, T: Group<S>>(
     });
 }
 
+fn best_mixed_fft<S: PrimeField, T: Group<S>>(
+    a: &mut [T],
+    worker: &Worker,
+    omega: &S,
+    log_n: u32,
+    log3_n: u32,
+) {
+    if log3_n == 0 {
+        best_fft(a, worker, omega, log_n);
+    } else {
+        mixed_fft(a, worker, omega, log_n, log3_n);
+    }
+}
+
+/// Transforms the 2^log_n 3^log3_n points of `a` as 3^log3_n transforms of
+/// 2^log_n points, of the sums of the points that are 2^log_n apart:
+///
+/// X[k1 + n1 k2] = Σ_j2 ω^(n1 j2 k2) ω^(j2 k1) Σ_j1 x[n2 j1 + j2] ω^(n2 j1 k1)
+///
+/// where n1 = 3^log3_n and n2 = 2^log_n. The inner sums are transforms of
+/// n1 points, which are small for the fields with such roots of unity.
+fn mixed_fft<S: PrimeField, T: Group<S>>(
+    a: &mut [T],
+    worker: &Worker,
+    omega: &S,
+    log_n: u32,
+    log3_n: u32,
+) {
+    let mut span = crate::trace::span("fft");
+    span.record("size", a.len());
+
+    let n1 = 3usize.pow(log3_n);
+    let n2 = 1usize << log_n;
+    assert_eq!(a.len(), n1 * n2);
+    if worker.budget.exceeded() {
+        return;
+    }
+    let _scratch = match worker.budget.reserve(std::mem::size_of_val(a)) {
+        Ok(reservation) => reservation,
+        Err(_) => return,
+    };
+
+    let omega_n1 = omega.pow_vartime([n2 as u64]);
+    let omega_n2 = omega.pow_vartime([n1 as u64]);
+    let mut rows = vec![vec![T::group_zero(); n2]; n1];
+
+    // The rows split into the columns each thread computes.
+    let chunk = std::cmp::max(n2 >> worker.log_num_cpus(), 1);
+    let mut parts = (0..(n2 + chunk - 1) / chunk)
+        .map(|_| vec![])
+        .collect::<Vec<_>>();
+    for row in rows.iter_mut() {
+        for (part, row) in parts.iter_mut().zip(row.chunks_mut(chunk)) {
+            part.push(row);
+        }
+    }
+    worker.scope(0, |scope, _| {
+        let a = &*a;
+        let budget = &worker.budget;
+
+        for (i, mut part) in parts.into_iter().enumerate() {
+            scope.spawn(move |_scope| {
+                let mut column = vec![T::group_zero(); n1];
+                let mut twiddle = omega.pow_vartime([(i * chunk) as u64]);
+                for j2 in 0..part[0].len() {
+                    for (j1, c) in column.iter_mut().enumerate() {
+                        *c = a[n2 * j1 + i * chunk + j2];
+                    }
+                    serial_fft3(&mut column, &omega_n1, log3_n, budget);
+
+                    let mut w = S::one();
+                    for (row, c) in part.iter_mut().zip(column.iter_mut()) {
+                        c.group_mul_assign(&w);
+                        row[j2] = *c;
+                        w.mul_assign(&twiddle);
+                    }
+                    twiddle.mul_assign(omega);
+                }
+            });
+        }
+    });
+
+    for row in rows.iter_mut() {
+        best_fft(row, worker, &omega_n2, log_n);
+    }
+
+    worker.scope(a.len(), |scope, chunk| {
+        let rows = &rows;
+
+        for (idx, a) in a.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_scope| {
+                for (idx, a) in (idx * chunk..).zip(a) {
+                    *a = rows[idx % n1][idx / n1];
+                }
+            });
+        }
+    });
+}
+
+/// Transforms the 3^log_n points of `a` radix-3, stopping between rounds
+/// once `budget` is exceeded.
+fn serial_fft3<S: PrimeField, T: Group<S>>(a: &mut [T], omega: &S, log_n: u32, budget: &Budget) {
+    fn digitreverse(mut n: usize, l: u32) -> usize {
+        let mut r = 0;
+        for _ in 0..l {
+            r = r * 3 + n % 3;
+            n /= 3;
+        }
+        r
+    }
+
+    let n = a.len();
+    assert_eq!(n, 3usize.pow(log_n));
+    if log_n == 0 {
+        return;
+    }
+
+    for k in 0..n {
+        let rk = digitreverse(k, log_n);
+        if k < rk {
+            a.swap(rk, k);
+        }
+    }
+
+    // A primitive cube root of unity, whose square is -1 - w3.
+    let w3 = omega.pow_vartime([(n / 3) as u64]);
+    let mut m = 1;
+    for _ in 0..log_n {
+        if budget.exceeded() {
+            return;
+        }
+        let w_m = omega.pow_vartime([(n / (3 * m)) as u64]);
+
+        for k in (0..n).step_by(3 * m) {
+            let mut w = S::one();
+            for j in 0..m {
+                let x0 = a[k + j];
+                let mut x1 = a[k + j + m];
+                x1.group_mul_assign(&w);
+                let mut x2 = a[k + j + 2 * m];
+                x2.group_mul_assign(&w.square());
+
+                // d = w3 (x1 - x2)
+                let mut d = x1;
+                d.group_sub_assign(&x2);
+                d.group_mul_assign(&w3);
+
+                let mut y0 = x0;
+                y0.group_add_assign(&x1);
+                y0.group_add_assign(&x2);
+                let mut y1 = x0;
+                y1.group_sub_assign(&x2);
+                y1.group_add_assign(&d);
+                let mut y2 = x0;
+                y2.group_sub_assign(&x1);
+                y2.group_sub_assign(&d);
+
+                a[k + j] = y0;
+                a[k + j + m] = y1;
+                a[k + j + 2 * m] = y2;
+                w.mul_assign(&w_m);
+            }
+        }
+
+        m *= 3;
+    }
+}
+
 // Test multiplying various (low degree) polynomials together and
 // comparing with naive evaluations.
 #[cfg(feature = "pairing")]
@@ -483,7 +845,7 @@ fn parallel_fft_consistency() {
 
                 for log_cpus in log_d..min(log_d + 1, 3) {
                     parallel_fft(&mut v1.coeffs, &worker, &v1.omega, log_d, log_cpus);
-                    serial_fft(&mut v2.coeffs, &v2.omega, log_d);
+                    serial_fft(&mut v2.coeffs, &v2.omega, log_d, &worker.budget);
 
                     assert!(v1.coeffs == v2.coeffs);
                 }
@@ -495,3 +857,54 @@ fn parallel_fft_consistency() {
 
     test_consistency::<Fr, _>(rng);
 }
+
+#[cfg(feature = "pairing")]
+#[test]
+fn mixed_radix_fft() {
+    use bls12_381::Scalar as Fr;
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    // The order of the group of BLS12-381 is a multiple of 3, but not 9.
+    assert_eq!(three_adic_root::<Fr>().0, 1);
+    assert_eq!(domain_size::<Fr>(5, Radix::Two).unwrap(), 8);
+    assert_eq!(domain_size::<Fr>(5, Radix::Mixed).unwrap(), 6);
+    assert_eq!(domain_size::<Fr>(13, Radix::Mixed).unwrap(), 16);
+    assert_eq!(domain_size::<Fr>(1025, Radix::Mixed).unwrap(), 1536);
+    assert!(EvaluationDomain::<Fr, Scalar<Fr>>::with_size(vec![], 9).is_err());
+    assert!(EvaluationDomain::<Fr, Scalar<Fr>>::with_size(vec![Scalar(Fr::one()); 7], 6).is_err());
+
+    for worker in &[Worker::new(), Worker::with_threads(1)] {
+        for &len in &[3, 6, 12, 48, 90, 1025] {
+            let v = (0..len)
+                .map(|_| Scalar(Fr::random(&mut rng)))
+                .collect::<Vec<_>>();
+            let mut domain = EvaluationDomain::from_coeffs_mixed(v.clone()).unwrap();
+            assert_eq!(domain.exp3, 1);
+            let m = domain.coeffs.len();
+            assert!(domain.omega.pow_vartime(&[m as u64, 0, 0, 0]) == Fr::one());
+
+            domain.fft(worker);
+            // A few of the evaluations at the powers of omega.
+            for i in (0..m).step_by(m / 3 + 1).chain(Some(m - 1)) {
+                let point = domain.omega.pow_vartime(&[i as u64, 0, 0, 0]);
+                let expected = v.iter().rev().fold(Fr::zero(), |acc, c| acc * point + c.0);
+                assert!(domain.coeffs[i].0 == expected);
+            }
+
+            domain.ifft(worker);
+            assert!(domain.coeffs[..len] == v[..]);
+            domain.coset_fft(worker);
+            domain.icoset_fft(worker);
+            assert!(domain.coeffs[..len] == v[..]);
+            let omega = domain.omega;
+            assert!(domain.z(&omega) == Fr::zero());
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Arithmetic on polynomials in coefficient form, on top of
+//! [`EvaluationDomain`].
+//!
+//! Polynomials are slices of coefficients, from the constant term up, and
+//! may have trailing zeros. The polynomials returned have none, so that the
+//! zero polynomial is empty.
+//!
+//! Products are computed with FFTs over an [`EvaluationDomain`] once both
+//! factors have more than a few coefficients, and so are quotients, with a
+//! Newton iteration for the inverse of the divisor. [`evaluate_many`] and
+//! [`interpolate`] reduce and combine along a tree of products of `x - x_i`,
+//! in `O(n log² n)` rather than the `O(n²)` of evaluating or combining one
+//! point at a time.
+
+use ff::PrimeField;
+
+use super::{EvaluationDomain, Scalar};
+use crate::multicore::Worker;
+use crate::SynthesisError;
+
+/// Below this many coefficients in the smaller operand, products and
+/// quotients are computed directly, which is faster than the FFTs.
+const DIRECT: usize = 32;
+
+/// Removes the trailing zeros of `poly`.
+pub fn trim<S: PrimeField>(poly: &mut Vec<S>) {
+    while poly.last().map_or(false, |c| c.is_zero()) {
+        poly.pop();
+    }
+}
+
+/// Returns the degree of `poly`, or `None` for the zero polynomial.
+pub fn degree<S: PrimeField>(poly: &[S]) -> Option<usize> {
+    poly.iter().rposition(|c| !c.is_zero())
+}
+
+/// Returns `a + b`.
+pub fn add<S: PrimeField>(a: &[S], b: &[S]) -> Vec<S> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = long.to_vec();
+    for (s, c) in sum.iter_mut().zip(short) {
+        *s += c;
+    }
+    trim(&mut sum);
+    sum
+}
+
+/// Returns `a - b`.
+pub fn sub<S: PrimeField>(a: &[S], b: &[S]) -> Vec<S> {
+    let mut difference = a.to_vec();
+    if difference.len() < b.len() {
+        difference.resize(b.len(), S::zero());
+    }
+    for (d, c) in difference.iter_mut().zip(b) {
+        *d -= c;
+    }
+    trim(&mut difference);
+    difference
+}
+
+/// Returns `a * b`.
+pub fn mul<S: PrimeField>(worker: &Worker, a: &[S], b: &[S]) -> Result<Vec<S>, SynthesisError> {
+    let (a, b) = match (degree(a), degree(b)) {
+        (Some(da), Some(db)) => (&a[..=da], &b[..=db]),
+        _ => return Ok(vec![]),
+    };
+    let len = a.len() + b.len() - 1;
+
+    if a.len().min(b.len()) <= DIRECT {
+        let mut product = vec![S::zero(); len];
+        for (i, a) in a.iter().enumerate() {
+            for (p, b) in product[i..].iter_mut().zip(b) {
+                *p += *a * b;
+            }
+        }
+        return Ok(product);
+    }
+
+    let domain = |coeffs: &[S]| {
+        let mut coeffs = coeffs.iter().map(|c| Scalar(*c)).collect::<Vec<_>>();
+        coeffs.resize(len, Scalar(S::zero()));
+        let mut domain = EvaluationDomain::from_coeffs(coeffs)?;
+        domain.fft(worker);
+        Ok::<_, SynthesisError>(domain)
+    };
+    let mut product = domain(a)?;
+    product.mul_assign(worker, &domain(b)?);
+    product.ifft(worker);
+
+    let mut product = product
+        .into_coeffs()
+        .into_iter()
+        .take(len)
+        .map(|c| c.0)
+        .collect();
+    trim(&mut product);
+    Ok(product)
+}
+
+/// Returns the first `n` coefficients of the inverse of `f` as a power
+/// series, whose constant term must not be zero.
+fn inverse_series<S: PrimeField>(
+    worker: &Worker,
+    f: &[S],
+    n: usize,
+) -> Result<Vec<S>, SynthesisError> {
+    let c = Option::<S>::from(f[0].invert()).ok_or(SynthesisError::DivisionByZero)?;
+    let mut g = vec![c];
+    let mut k = 1;
+    while k < n {
+        k = std::cmp::min(2 * k, n);
+        // g <- g (2 - f g) mod x^k
+        let fg = mul(worker, &f[..std::cmp::min(k, f.len())], &g)?;
+        let mut correction = fg.into_iter().take(k).map(|c| -c).collect::<Vec<_>>();
+        if correction.is_empty() {
+            correction.push(S::zero());
+        }
+        correction[0] += S::one() + S::one();
+        g = mul(worker, &g, &correction)?;
+        g.truncate(k);
+    }
+    Ok(g)
+}
+
+/// Returns the quotient and the remainder of the division of `a` by `b`,
+/// the remainder having a lower degree than `b`. Fails with
+/// [`SynthesisError::DivisionByZero`] if `b` is zero.
+pub fn div_rem<S: PrimeField>(
+    worker: &Worker,
+    a: &[S],
+    b: &[S],
+) -> Result<(Vec<S>, Vec<S>), SynthesisError> {
+    let db = degree(b).ok_or(SynthesisError::DivisionByZero)?;
+    let b = &b[..=db];
+    let da = match degree(a) {
+        Some(da) if da >= db => da,
+        _ => {
+            let mut remainder = a.to_vec();
+            trim(&mut remainder);
+            return Ok((vec![], remainder));
+        }
+    };
+    let a = &a[..=da];
+    let len = da - db + 1;
+
+    if len.min(b.len()) <= DIRECT {
+        let lead = Option::<S>::from(b[db].invert()).ok_or(SynthesisError::DivisionByZero)?;
+        let mut remainder = a.to_vec();
+        let mut quotient = vec![S::zero(); len];
+        for i in (0..len).rev() {
+            let q = remainder[i + db] * lead;
+            for (r, b) in remainder[i..=i + db].iter_mut().zip(b) {
+                *r -= q * b;
+            }
+            quotient[i] = q;
+        }
+        remainder.truncate(db);
+        trim(&mut remainder);
+        return Ok((quotient, remainder));
+    }
+
+    // The quotient reversed is the reversed dividend divided by the
+    // reversed divisor, as power series, to `len` terms.
+    let reversed = |poly: &[S]| poly.iter().rev().copied().collect::<Vec<_>>();
+    let inverse = inverse_series(worker, &reversed(b), len)?;
+    let ra = reversed(a);
+    let mut quotient = mul(worker, &ra[..len], &inverse)?;
+    quotient.resize(len, S::zero());
+    quotient.reverse();
+    trim(&mut quotient);
+
+    let remainder = sub(a, &mul(worker, b, &quotient)?);
+    Ok((quotient, remainder))
+}
+
+/// Returns `poly(point)`.
+pub fn evaluate<S: PrimeField>(poly: &[S], point: &S) -> S {
+    poly.iter()
+        .rev()
+        .fold(S::zero(), |acc, coeff| acc * point + coeff)
+}
+
+/// The products of `x - x_i` over ranges of the points, each level halving
+/// the number of products of the one below, the last product of a level
+/// with an odd number of them being carried up as it is.
+struct SubproductTree<S: PrimeField> {
+    levels: Vec<Vec<Vec<S>>>,
+}
+
+impl<S: PrimeField> SubproductTree<S> {
+    fn new(worker: &Worker, points: &[S]) -> Result<Self, SynthesisError> {
+        let mut levels = vec![points
+            .iter()
+            .map(|x| vec![-*x, S::one()])
+            .collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => mul(worker, left, right),
+                    [single] => Ok(single.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<_, _>>()?;
+            levels.push(next);
+        }
+        Ok(SubproductTree { levels })
+    }
+
+    fn root(&self) -> &[S] {
+        &self.levels.last().unwrap()[0]
+    }
+
+    /// Returns `poly` modulo each `x - x_i`, which is `poly(x_i)`.
+    fn evaluate(&self, worker: &Worker, poly: &[S]) -> Result<Vec<S>, SynthesisError> {
+        let mut remainders = vec![div_rem(worker, poly, self.root())?.1];
+        for level in self.levels.iter().rev().skip(1) {
+            remainders = level
+                .iter()
+                .enumerate()
+                .map(|(i, node)| Ok(div_rem(worker, &remainders[i / 2], node)?.1))
+                .collect::<Result<_, SynthesisError>>()?;
+        }
+        Ok(remainders
+            .into_iter()
+            .map(|r| r.first().copied().unwrap_or_else(S::zero))
+            .collect())
+    }
+
+    /// Returns the sum of `weights[i]` times the product of `x - x_j` over
+    /// the points other than `x_i`.
+    fn combine(&self, worker: &Worker, weights: &[S]) -> Result<Vec<S>, SynthesisError> {
+        let mut sums = weights.iter().map(|w| vec![*w]).collect::<Vec<_>>();
+        for level in &self.levels[..self.levels.len() - 1] {
+            sums = sums
+                .chunks(2)
+                .zip(level.chunks(2))
+                .map(|pair| match pair {
+                    ([left, right], [left_node, right_node]) => Ok(add(
+                        &mul(worker, left, right_node)?,
+                        &mul(worker, right, left_node)?,
+                    )),
+                    ([single], [_]) => Ok(single.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<_, SynthesisError>>()?;
+        }
+        let mut sum = sums.pop().unwrap_or_default();
+        trim(&mut sum);
+        Ok(sum)
+    }
+}
+
+/// Returns `poly(x)` for each of `points`.
+pub fn evaluate_many<S: PrimeField>(
+    worker: &Worker,
+    poly: &[S],
+    points: &[S],
+) -> Result<Vec<S>, SynthesisError> {
+    if points.len() <= DIRECT || poly.len() <= DIRECT {
+        let mut values = vec![S::zero(); points.len()];
+        worker.scope(points.len(), |scope, chunk| {
+            for (values, points) in values.chunks_mut(chunk).zip(points.chunks(chunk)) {
+                scope.spawn(move |_| {
+                    for (value, point) in values.iter_mut().zip(points) {
+                        *value = evaluate(poly, point);
+                    }
+                });
+            }
+        });
+        return Ok(values);
+    }
+
+    SubproductTree::new(worker, points)?.evaluate(worker, poly)
+}
+
+/// Returns the polynomial of degree less than the number of `points` that
+/// takes `values[i]` at `points[i]`. Fails with
+/// [`SynthesisError::DivisionByZero`] if two of the points are equal.
+///
+/// # Panics
+///
+/// Panics if there are not as many values as points.
+pub fn interpolate<S: PrimeField>(
+    worker: &Worker,
+    points: &[S],
+    values: &[S],
+) -> Result<Vec<S>, SynthesisError> {
+    assert_eq!(points.len(), values.len());
+    if points.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // The Lagrange basis polynomial of x_i is the product of x - x_j over
+    // the other points, divided by its value at x_i, which is the
+    // derivative of the product over all of them at x_i.
+    let tree = SubproductTree::new(worker, points)?;
+    let derivative = tree
+        .root()
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| S::from(i as u64) * c)
+        .collect::<Vec<_>>();
+    let mut weights = tree.evaluate(worker, &derivative)?;
+
+    // Invert the denominators at once.
+    let mut acc = S::one();
+    let prefixes = weights
+        .iter()
+        .map(|w| {
+            let prefix = acc;
+            acc *= w;
+            prefix
+        })
+        .collect::<Vec<_>>();
+    let mut inverse = Option::<S>::from(acc.invert()).ok_or(SynthesisError::DivisionByZero)?;
+    for (w, prefix) in weights.iter_mut().zip(prefixes).rev() {
+        let next = inverse * *w;
+        *w = inverse * prefix;
+        inverse = next;
+    }
+    for (w, value) in weights.iter_mut().zip(values) {
+        *w *= value;
+    }
+
+    tree.combine(worker, &weights)
+}
+
+#[cfg(all(test, feature = "pairing"))]
+mod tests {
+    use super::*;
+    use bls12_381::Scalar as Fr;
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    fn random(rng: &mut XorShiftRng, len: usize) -> Vec<Fr> {
+        let mut poly = (0..len).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+        trim(&mut poly);
+        poly
+    }
+
+    #[test]
+    fn products_and_quotients() {
+        let mut rng = rng();
+        let worker = Worker::new();
+
+        for &(len_a, len_b) in &[(0, 3), (1, 1), (5, 7), (40, 33), (100, 70), (300, 41)] {
+            let a = random(&mut rng, len_a);
+            let b = random(&mut rng, len_b);
+            let product = mul(&worker, &a, &b).unwrap();
+            assert_eq!(
+                product.len(),
+                if len_a == 0 { 0 } else { len_a + len_b - 1 }
+            );
+            let x = Fr::random(&mut rng);
+            assert_eq!(evaluate(&product, &x), evaluate(&a, &x) * evaluate(&b, &x));
+
+            // Dividing the product plus a remainder recovers both.
+            let r = random(&mut rng, len_b - 1);
+            let (q, remainder) = div_rem(&worker, &add(&product, &r), &b).unwrap();
+            assert_eq!(q, a);
+            assert_eq!(remainder, r);
+        }
+
+        // Quotients of polynomials of any degree satisfy a = b q + r.
+        let a = random(&mut rng, 500);
+        for &len_b in &[1, 2, 33, 64, 200, 499, 500] {
+            let b = random(&mut rng, len_b);
+            let (q, r) = div_rem(&worker, &a, &b).unwrap();
+            assert!(degree(&r) < degree(&b));
+            assert_eq!(add(&mul(&worker, &b, &q).unwrap(), &r), a);
+        }
+        let (q, r) = div_rem(&worker, &a[..10], &random(&mut rng, 20)).unwrap();
+        assert!(q.is_empty());
+        assert_eq!(r[..], a[..10]);
+
+        assert!(matches!(
+            div_rem(&worker, &a, &[Fr::zero()]),
+            Err(SynthesisError::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn evaluation_and_interpolation() {
+        let mut rng = rng();
+        let worker = Worker::new();
+
+        for &(len, count) in &[(0, 4), (10, 50), (200, 100), (100, 300)] {
+            let poly = random(&mut rng, len);
+            let points = random(&mut rng, count);
+            let values = evaluate_many(&worker, &poly, &points).unwrap();
+            for (point, value) in points.iter().zip(&values) {
+                assert_eq!(evaluate(&poly, point), *value);
+            }
+
+            if len <= count {
+                assert_eq!(interpolate(&worker, &points, &values).unwrap(), poly);
+            }
+        }
+
+        let points = [Fr::one(), Fr::from(2), Fr::one()];
+        assert!(matches!(
+            interpolate(&worker, &points, &[Fr::one(); 3]),
+            Err(SynthesisError::DivisionByZero)
+        ));
+        assert!(interpolate::<Fr>(&worker, &[], &[]).unwrap().is_empty());
+    }
+}
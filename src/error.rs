@@ -0,0 +1,323 @@
+//! Structured errors, with the context in which they occurred.
+//!
+//! [`SynthesisError`] and [`VerificationError`] describe what went wrong in
+//! a circuit or a proof, and reading parameters or proofs fails with an
+//! [`io::Error`]. [`Error`] brings these together for services that need to
+//! react to failures programmatically: it has an [`ErrorKind`] to match on,
+//! the chain of [`Context`] it was raised in, such as the section of a
+//! parameter file and the offset of the element that could not be read, and
+//! the underlying error as its [`source`].
+//!
+//! Readers in this crate attach context by wrapping an [`Error`] in the
+//! [`io::Error`] they return, so it is recovered by converting that error
+//! with [`Error::from`].
+//!
+//! [`source`]: std::error::Error::source
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+#[cfg(feature = "groth16")]
+use std::io::Read;
+
+use crate::{SynthesisError, VerificationError};
+
+/// The kinds of failure an [`Error`] can describe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A circuit could not be synthesized.
+    Synthesis,
+    /// An I/O operation failed.
+    Io,
+    /// Parameters or a verifying key are truncated or invalid.
+    MalformedParameters,
+    /// A proof is truncated or invalid.
+    MalformedProof,
+    /// Public inputs are not canonical, or do not match the circuit.
+    MalformedInputs,
+    /// A proof did not verify.
+    VerificationFailed,
+}
+
+impl ErrorKind {
+    fn description(self) -> &'static str {
+        match self {
+            ErrorKind::Synthesis => "synthesis failed",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::MalformedParameters => "malformed parameters",
+            ErrorKind::MalformedProof => "malformed proof",
+            ErrorKind::MalformedInputs => "malformed public inputs",
+            ErrorKind::VerificationFailed => "verification failed",
+        }
+    }
+}
+
+/// Where an error occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Context {
+    /// A named section of a serialized object, such as the `h` query of
+    /// parameters.
+    Section(&'static str),
+    /// The index of an element within a section.
+    Element(usize),
+    /// The offset in bytes, from the start of the object being read, of the
+    /// element that could not be read.
+    Offset(u64),
+    /// The path of a namespace in a circuit.
+    Namespace(String),
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Context::Section(section) => write!(f, "in section `{}`", section),
+            Context::Element(index) => write!(f, "at element {}", index),
+            Context::Offset(offset) => write!(f, "at offset {}", offset),
+            Context::Namespace(path) => write!(f, "in namespace `{}`", path),
+        }
+    }
+}
+
+/// An error, with its kind, context and cause.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    // Innermost first.
+    context: Vec<Context>,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
+}
+
+impl Error {
+    pub fn new<E>(kind: ErrorKind, source: E) -> Self
+    where
+        E: Into<Box<dyn StdError + Send + Sync + 'static>>,
+    {
+        Error {
+            kind,
+            context: vec![],
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the context of the error, from the outermost to the
+    /// innermost.
+    pub fn context(&self) -> impl Iterator<Item = &Context> {
+        self.context.iter().rev()
+    }
+
+    /// Adds context around the error.
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context.push(context);
+        self
+    }
+
+    /// Returns the innermost section the error occurred in, if any.
+    pub fn section(&self) -> Option<&'static str> {
+        self.context.iter().find_map(|c| match c {
+            Context::Section(section) => Some(*section),
+            _ => None,
+        })
+    }
+
+    /// Returns the offset of the element that could not be read, if any.
+    pub fn offset(&self) -> Option<u64> {
+        self.context.iter().find_map(|c| match c {
+            Context::Offset(offset) => Some(*offset),
+            _ => None,
+        })
+    }
+
+    /// Returns the first error of type `T` in the chain of sources.
+    pub fn find_source<T: StdError + 'static>(&self) -> Option<&T> {
+        let mut source = self.source();
+        while let Some(error) = source {
+            if let Some(error) = error.downcast_ref::<T>() {
+                return Some(error);
+            }
+            source = error.source();
+        }
+        None
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.description())?;
+        for (i, context) in self.context().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, context)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn StdError + 'static))
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Recovers the [`Error`] carried by `e`, if it has one.
+    fn from(e: io::Error) -> Self {
+        if e.get_ref().map_or(false, |inner| inner.is::<Error>()) {
+            *e.into_inner().unwrap().downcast::<Error>().unwrap()
+        } else {
+            Error::new(ErrorKind::Io, e)
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e.find_source::<io::Error>() {
+            Some(source) => source.kind(),
+            None => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+impl From<SynthesisError> for Error {
+    fn from(e: SynthesisError) -> Self {
+        match e {
+            SynthesisError::IoError(e) => Error::from(e),
+            e => Error::new(ErrorKind::Synthesis, e),
+        }
+    }
+}
+
+impl From<VerificationError> for Error {
+    fn from(e: VerificationError) -> Self {
+        match e {
+            VerificationError::InvalidVerifyingKey => Error::new(ErrorKind::MalformedParameters, e),
+            VerificationError::InvalidProof => Error::new(ErrorKind::VerificationFailed, e),
+        }
+    }
+}
+
+/// A reader that keeps track of its offset, to attach context to the errors
+/// of the reads made through it.
+#[cfg(feature = "groth16")]
+pub(crate) struct Tracked<R> {
+    reader: R,
+    offset: u64,
+}
+
+#[cfg(feature = "groth16")]
+impl<R: Read> Tracked<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Tracked { reader, offset: 0 }
+    }
+
+    /// Tracks `reader`, which starts `offset` bytes into the object being
+    /// read.
+    pub(crate) fn at(reader: R, offset: u64) -> Self {
+        Tracked { reader, offset }
+    }
+
+    /// Runs `f`, attributing its errors to `context`. Errors that do not
+    /// have an offset yet get the offset at which `f` started, and data
+    /// errors are reported as `kind`.
+    pub(crate) fn within<T, F>(&mut self, kind: ErrorKind, context: Context, f: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut Self) -> io::Result<T>,
+    {
+        let start = self.offset;
+        f(self).map_err(|e| attribute(e, kind, start, context))
+    }
+
+    /// Returns the number of bytes read so far.
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+/// Attributes `e` to `context`, as [`Tracked::within`] does for an error
+/// raised at offset `start`.
+#[cfg(feature = "groth16")]
+pub(crate) fn attribute(e: io::Error, kind: ErrorKind, start: u64, context: Context) -> io::Error {
+    let malformed = matches!(
+        e.kind(),
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+    );
+
+    let mut e = Error::from(e);
+    if e.kind == ErrorKind::Io && malformed {
+        e.kind = kind;
+    }
+    if e.offset().is_none() {
+        e.context.push(Context::Offset(start));
+    }
+    e.with_context(context).into()
+}
+
+#[cfg(feature = "groth16")]
+impl<R: Read> Read for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.offset += len as u64;
+        Ok(len)
+    }
+}
+
+#[cfg(all(test, feature = "groth16"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_chaining() {
+        let data = [0u8; 6];
+        let mut reader = Tracked::new(&data[..]);
+        let e = reader
+            .within(ErrorKind::MalformedProof, Context::Section("proof"), |r| {
+                r.read_exact(&mut [0; 4])?;
+                r.within(ErrorKind::MalformedProof, Context::Element(1), |r| {
+                    r.read_exact(&mut [0; 4])
+                })
+            })
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        let e = Error::from(e);
+        assert_eq!(e.kind(), ErrorKind::MalformedProof);
+        assert_eq!(e.section(), Some("proof"));
+        assert_eq!(e.offset(), Some(4));
+        assert_eq!(
+            e.context().collect::<Vec<_>>(),
+            vec![
+                &Context::Section("proof"),
+                &Context::Element(1),
+                &Context::Offset(4)
+            ]
+        );
+        assert_eq!(
+            e.find_source::<io::Error>().unwrap().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert!(e
+            .to_string()
+            .starts_with("malformed proof in section `proof`, at element 1, at offset 4: "));
+
+        let e = Error::from(SynthesisError::IoError(e.into()));
+        assert_eq!(e.kind(), ErrorKind::MalformedProof);
+        let e = Error::from(SynthesisError::AssignmentMissing);
+        assert_eq!(e.kind(), ErrorKind::Synthesis);
+        assert!(matches!(
+            e.find_source::<SynthesisError>(),
+            Some(SynthesisError::AssignmentMissing)
+        ));
+        assert_eq!(
+            Error::from(VerificationError::InvalidProof).to_string(),
+            "verification failed: proof verification failed"
+        );
+    }
+}
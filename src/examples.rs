@@ -0,0 +1,71 @@
+//! Complete application circuits, proven end to end with Groth16.
+//!
+//! Each module is a reference implementation of a common statement, with
+//! the native code that produces its witness and public inputs, and a type
+//! that holds its parameters and proves and verifies it:
+//!
+//! - [`preimage`]: knowledge of a preimage of a Poseidon digest;
+//! - [`merkle`]: membership of a private leaf in a Poseidon Merkle tree
+//!   whose root is public;
+//! - [`transfer`]: a token transfer between accounts whose balances are
+//!   hidden in commitments, with range checks against overdrafts and
+//!   overflows.
+//!
+//! They are built from the gadgets of the crate like any other circuit, and
+//! are meant to be depended on, or extended, rather than copied.
+//!
+//! ```ignore
+//! let preimage = Preimage::<Bls12>::setup(2, &mut rng)?;
+//! let (digest, proof) = preimage.prove(&[x, y], &mut rng)?;
+//! preimage.verify(&digest, &proof)?;
+//! ```
+
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+
+use crate::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    Parameters, PreparedVerifyingKey, Proof,
+};
+use crate::{Circuit, SynthesisError, VerificationError};
+
+pub mod merkle;
+pub mod preimage;
+pub mod transfer;
+
+/// The parameters of a circuit, and its prepared verifying key.
+pub struct Keys<E: MultiMillerLoop> {
+    pub params: Parameters<E>,
+    pub pvk: PreparedVerifyingKey<E>,
+}
+
+impl<E: MultiMillerLoop> Keys<E> {
+    /// Generates parameters for `circuit`, which need not have a witness.
+    pub fn generate<C: Circuit<E::Fr>, R: RngCore>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Self, SynthesisError>
+    where
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+    {
+        let params = generate_random_parameters(circuit, rng)?;
+        let pvk = prepare_verifying_key(&params.vk);
+        Ok(Keys { params, pvk })
+    }
+
+    /// Proves `circuit` with the parameters.
+    pub fn prove<C: Circuit<E::Fr>, R: RngCore>(
+        &self,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Proof<E>, SynthesisError> {
+        create_random_proof(circuit, &self.params, rng)
+    }
+
+    /// Verifies `proof` against the public inputs.
+    pub fn verify(&self, proof: &Proof<E>, inputs: &[E::Fr]) -> Result<(), VerificationError> {
+        verify_proof(&self.pvk, proof, inputs)
+    }
+}
@@ -0,0 +1,262 @@
+//! Membership in a Poseidon Merkle tree.
+//!
+//! A [`MerkleTree`] of depth `d` holds up to `2^d` leaves, hashed with
+//! [`mmr::hash_leaf`] and combined with [`mmr::hash_node`], and the empty
+//! slots are zero leaves. A [`MembershipCircuit`] proves that a private leaf
+//! is in the tree whose root is the public input, without revealing the
+//! leaf or its position, as an allow list or the note commitments of a
+//! shielded pool would.
+
+use ff::PrimeField;
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+
+use super::Keys;
+use crate::gadgets::boolean::{AllocatedBit, Boolean};
+use crate::gadgets::mmr as gadgets;
+use crate::gadgets::num::AllocatedNum;
+use crate::groth16::Proof;
+use crate::mmr;
+use crate::poseidon::PoseidonParams;
+use crate::{Circuit, ConstraintSystem, SynthesisError, VerificationError};
+
+/// The width of the permutation, which absorbs two elements a call.
+const WIDTH: usize = 3;
+
+/// A Merkle tree of fixed depth, with every node it has built.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<S: PrimeField> {
+    params: PoseidonParams<S>,
+    // The nodes of each height, from the leaves to the root.
+    levels: Vec<Vec<S>>,
+}
+
+impl<S: PrimeField> MerkleTree<S> {
+    /// Builds the tree of depth `depth` over `leaves`, or returns `None` if
+    /// they do not fit.
+    pub fn new(params: PoseidonParams<S>, depth: usize, leaves: &[S]) -> Option<Self> {
+        assert!(depth < 64, "unsupported depth {}", depth);
+        if leaves.len() as u64 > 1 << depth {
+            return None;
+        }
+
+        let mut level = leaves
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(S::zero()).take((1 << depth) - leaves.len()))
+            .map(|leaf| mmr::hash_leaf(&params, leaf))
+            .collect::<Vec<_>>();
+        let mut levels = vec![];
+        while level.len() > 1 {
+            let next = level
+                .chunks(2)
+                .map(|pair| mmr::hash_node(&params, pair[0], pair[1]))
+                .collect();
+            levels.push(level);
+            level = next;
+        }
+        levels.push(level);
+        Some(MerkleTree { params, levels })
+    }
+
+    pub fn params(&self) -> &PoseidonParams<S> {
+        &self.params
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn root(&self) -> S {
+        self.levels[self.depth()][0]
+    }
+
+    /// Returns the path of the leaf at `index`, or `None` if it is out of
+    /// the tree.
+    pub fn path(&self, index: u64) -> Option<MerklePath<S>> {
+        if index >= self.levels[0].len() as u64 {
+            return None;
+        }
+        let siblings = self.levels[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(height, level)| level[((index >> height) ^ 1) as usize])
+            .collect();
+        Some(MerklePath { siblings, index })
+    }
+}
+
+/// The path from a leaf to the root of a [`MerkleTree`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath<S: PrimeField> {
+    /// The sibling of the node at each height, from the leaves up.
+    pub siblings: Vec<S>,
+    /// The position of the leaf.
+    pub index: u64,
+}
+
+impl<S: PrimeField> MerklePath<S> {
+    /// Returns whether the node at `height` is a right child.
+    pub fn is_right(&self, height: usize) -> bool {
+        (self.index >> height) & 1 == 1
+    }
+
+    /// Returns the root of the tree in which `leaf` has this path.
+    pub fn root(&self, params: &PoseidonParams<S>, leaf: S) -> S {
+        self.siblings.iter().enumerate().fold(
+            mmr::hash_leaf(params, leaf),
+            |node, (height, &sibling)| {
+                if self.is_right(height) {
+                    mmr::hash_node(params, sibling, node)
+                } else {
+                    mmr::hash_node(params, node, sibling)
+                }
+            },
+        )
+    }
+}
+
+/// Proves that a private leaf is in a tree of depth `depth` whose root is
+/// the public input.
+pub struct MembershipCircuit<'a, S: PrimeField> {
+    pub params: &'a PoseidonParams<S>,
+    pub depth: usize,
+    /// The leaf and its path, when proving.
+    pub witness: Option<(S, &'a MerklePath<S>)>,
+}
+
+impl<'a, S: PrimeField> Circuit<S> for MembershipCircuit<'a, S> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let path = self.witness.map(|(_, path)| path);
+        if path.map_or(false, |path| path.siblings.len() != self.depth) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || {
+            self.witness
+                .map(|(leaf, _)| leaf)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let mut node = gadgets::hash_leaf(cs.namespace(|| "hash leaf"), self.params, &leaf)?;
+        for height in 0..self.depth {
+            let mut cs = cs.namespace(|| format!("height {}", height));
+            let sibling = AllocatedNum::alloc(cs.namespace(|| "sibling"), || {
+                path.map(|path| path.siblings[height])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let is_right = Boolean::from(AllocatedBit::alloc(
+                cs.namespace(|| "is right"),
+                path.map(|path| path.is_right(height)),
+            )?);
+            let (left, right) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "order"),
+                &node,
+                &sibling,
+                &is_right,
+            )?;
+            node = gadgets::hash_node(cs.namespace(|| "parent"), self.params, &left, &right)?;
+        }
+        node.inputize(cs.namespace(|| "root"))
+    }
+}
+
+/// The parameters for membership in trees of a depth.
+pub struct Membership<E: MultiMillerLoop> {
+    params: PoseidonParams<E::Fr>,
+    depth: usize,
+    keys: Keys<E>,
+}
+
+impl<E: MultiMillerLoop> Membership<E> {
+    /// Generates the parameters for trees of depth `depth`.
+    pub fn setup<R: RngCore>(depth: usize, rng: &mut R) -> Result<Self, SynthesisError>
+    where
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+    {
+        let params = PoseidonParams::for_width(WIDTH);
+        let keys = Keys::generate(
+            MembershipCircuit {
+                params: &params,
+                depth,
+                witness: None,
+            },
+            rng,
+        )?;
+        Ok(Membership {
+            params,
+            depth,
+            keys,
+        })
+    }
+
+    pub fn keys(&self) -> &Keys<E> {
+        &self.keys
+    }
+
+    /// Builds the tree over `leaves`, with the hash of these parameters, or
+    /// returns `None` if they do not fit.
+    pub fn tree(&self, leaves: &[E::Fr]) -> Option<MerkleTree<E::Fr>> {
+        MerkleTree::new(self.params.clone(), self.depth, leaves)
+    }
+
+    /// Proves that `leaf`, with path `path`, is in the tree.
+    pub fn prove<R: RngCore>(
+        &self,
+        leaf: E::Fr,
+        path: &MerklePath<E::Fr>,
+        rng: &mut R,
+    ) -> Result<Proof<E>, SynthesisError> {
+        self.keys.prove(
+            MembershipCircuit {
+                params: &self.params,
+                depth: self.depth,
+                witness: Some((leaf, path)),
+            },
+            rng,
+        )
+    }
+
+    /// Verifies a proof of membership in the tree with root `root`.
+    pub fn verify(&self, root: &E::Fr, proof: &Proof<E>) -> Result<(), VerificationError> {
+        self.keys.verify(proof, &[*root])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn membership() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let membership = Membership::<Bls12>::setup(3, &mut rng).unwrap();
+        let leaves = (10..15).map(Scalar::from).collect::<Vec<_>>();
+        let tree = membership.tree(&leaves).unwrap();
+        assert!(membership.tree(&[Scalar::zero(); 9]).is_none());
+        assert!(tree.path(8).is_none());
+
+        for (index, &leaf) in leaves.iter().enumerate() {
+            let path = tree.path(index as u64).unwrap();
+            assert_eq!(path.root(tree.params(), leaf), tree.root());
+            assert_ne!(path.root(tree.params(), leaf + Scalar::one()), tree.root());
+        }
+
+        let path = tree.path(3).unwrap();
+        let proof = membership.prove(leaves[3], &path, &mut rng).unwrap();
+        assert!(membership.verify(&tree.root(), &proof).is_ok());
+        let other = membership.tree(&leaves[1..]).unwrap();
+        assert!(membership.verify(&other.root(), &proof).is_err());
+
+        // A leaf outside the tree only proves membership in another one.
+        let proof = membership.prove(Scalar::from(99), &path, &mut rng).unwrap();
+        assert!(membership.verify(&tree.root(), &proof).is_err());
+    }
+}
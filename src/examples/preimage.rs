@@ -0,0 +1,138 @@
+//! Knowledge of a preimage of a Poseidon digest.
+//!
+//! A [`PreimageCircuit`] proves the knowledge of a message of a fixed
+//! number of field elements whose [Poseidon hash](PoseidonParams::hash) is
+//! the public digest, as a password check or the opening of a commitment
+//! would.
+
+use ff::PrimeField;
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+
+use super::Keys;
+use crate::gadgets::num::AllocatedNum;
+use crate::gadgets::poseidon;
+use crate::groth16::Proof;
+use crate::poseidon::PoseidonParams;
+use crate::{Circuit, ConstraintSystem, SynthesisError, VerificationError};
+
+/// The width of the permutation, which absorbs two elements a call.
+const WIDTH: usize = 3;
+
+/// Proves that the hash of a message of `len` elements is the public input.
+pub struct PreimageCircuit<'a, S: PrimeField> {
+    pub params: &'a PoseidonParams<S>,
+    pub len: usize,
+    /// The message, when proving.
+    pub preimage: Option<&'a [S]>,
+}
+
+impl<'a, S: PrimeField> Circuit<S> for PreimageCircuit<'a, S> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        if self
+            .preimage
+            .map_or(false, |preimage| preimage.len() != self.len)
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let message = (0..self.len)
+            .map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("message {}", i)), || {
+                    self.preimage
+                        .map(|preimage| preimage[i])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let digest = poseidon::hash(cs.namespace(|| "hash"), self.params, &message)?;
+        digest.inputize(cs.namespace(|| "digest"))
+    }
+}
+
+/// The parameters for preimages of a length.
+pub struct Preimage<E: MultiMillerLoop> {
+    params: PoseidonParams<E::Fr>,
+    len: usize,
+    keys: Keys<E>,
+}
+
+impl<E: MultiMillerLoop> Preimage<E> {
+    /// Generates the parameters for preimages of `len` elements.
+    pub fn setup<R: RngCore>(len: usize, rng: &mut R) -> Result<Self, SynthesisError>
+    where
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+    {
+        let params = PoseidonParams::for_width(WIDTH);
+        let keys = Keys::generate(
+            PreimageCircuit {
+                params: &params,
+                len,
+                preimage: None,
+            },
+            rng,
+        )?;
+        Ok(Preimage { params, len, keys })
+    }
+
+    pub fn keys(&self) -> &Keys<E> {
+        &self.keys
+    }
+
+    /// Returns the digest of `preimage`.
+    pub fn digest(&self, preimage: &[E::Fr]) -> E::Fr {
+        self.params.hash(preimage)
+    }
+
+    /// Proves the knowledge of `preimage`, and returns its digest with the
+    /// proof.
+    pub fn prove<R: RngCore>(
+        &self,
+        preimage: &[E::Fr],
+        rng: &mut R,
+    ) -> Result<(E::Fr, Proof<E>), SynthesisError> {
+        let proof = self.keys.prove(
+            PreimageCircuit {
+                params: &self.params,
+                len: self.len,
+                preimage: Some(preimage),
+            },
+            rng,
+        )?;
+        Ok((self.digest(preimage), proof))
+    }
+
+    /// Verifies a proof of the knowledge of a preimage of `digest`.
+    pub fn verify(&self, digest: &E::Fr, proof: &Proof<E>) -> Result<(), VerificationError> {
+        self.keys.verify(proof, &[*digest])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn preimage() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let preimage = Preimage::<Bls12>::setup(3, &mut rng).unwrap();
+
+        let message = [Scalar::from(1), Scalar::from(2), Scalar::from(3)];
+        let (digest, proof) = preimage.prove(&message, &mut rng).unwrap();
+        assert!(preimage.verify(&digest, &proof).is_ok());
+        assert!(preimage.verify(&(digest + Scalar::one()), &proof).is_err());
+
+        assert!(matches!(
+            preimage.prove(&message[..2], &mut rng),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+}
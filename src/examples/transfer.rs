@@ -0,0 +1,294 @@
+//! Token transfers between accounts with hidden balances.
+//!
+//! An [`Account`] is public only through the [commitment](Account::commitment)
+//! to its balance and a random salt. A [`TransferCircuit`] proves that the
+//! commitments to the accounts of the sender and the receiver after a
+//! transfer follow from those before it, for a private amount: the sender's
+//! balance falls by the amount and the receiver's rises by it. Every balance
+//! and the amount are checked to fit in 64 bits, so that a transfer cannot
+//! overdraw the sender, which would wrap its balance around the field, or
+//! overflow the receiver.
+
+use ff::PrimeField;
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+
+use super::Keys;
+use crate::gadgets::num::AllocatedNum;
+use crate::gadgets::poseidon;
+use crate::gadgets::range::RangeChecks;
+use crate::gadgets::Assignment;
+use crate::groth16::Proof;
+use crate::poseidon::PoseidonParams;
+use crate::{Circuit, ConstraintSystem, SynthesisError, VerificationError};
+
+/// The width of the permutation, which absorbs two elements a call.
+const WIDTH: usize = 3;
+
+/// The bits of a balance.
+const BALANCE_BITS: usize = 64;
+
+/// The balance of an account, with the salt that hides it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Account<S: PrimeField> {
+    pub balance: u64,
+    pub salt: S,
+}
+
+impl<S: PrimeField> Account<S> {
+    /// Creates an account with a random salt.
+    pub fn random<R: RngCore>(balance: u64, rng: &mut R) -> Self {
+        Account {
+            balance,
+            salt: S::random(rng),
+        }
+    }
+
+    /// Returns the commitment to the account.
+    pub fn commitment(&self, params: &PoseidonParams<S>) -> S {
+        params.hash(&[S::from(self.balance), self.salt])
+    }
+}
+
+/// A transfer, with the accounts before and after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transfer<S: PrimeField> {
+    pub sender: Account<S>,
+    pub receiver: Account<S>,
+    pub amount: u64,
+    pub new_sender: Account<S>,
+    pub new_receiver: Account<S>,
+}
+
+impl<S: PrimeField> Transfer<S> {
+    /// Transfers `amount` from `sender` to `receiver`, with fresh salts for
+    /// the new accounts, or returns `None` if the sender cannot afford it or
+    /// the receiver's balance would overflow.
+    pub fn new<R: RngCore>(
+        sender: Account<S>,
+        receiver: Account<S>,
+        amount: u64,
+        rng: &mut R,
+    ) -> Option<Self> {
+        let new_sender = Account::random(sender.balance.checked_sub(amount)?, rng);
+        let new_receiver = Account::random(receiver.balance.checked_add(amount)?, rng);
+        Some(Transfer {
+            sender,
+            receiver,
+            amount,
+            new_sender,
+            new_receiver,
+        })
+    }
+
+    /// Returns the commitments to the accounts, the public inputs of a
+    /// [`TransferCircuit`]: the sender and the receiver before the transfer,
+    /// then after it.
+    pub fn commitments(&self, params: &PoseidonParams<S>) -> [S; 4] {
+        [
+            self.sender.commitment(params),
+            self.receiver.commitment(params),
+            self.new_sender.commitment(params),
+            self.new_receiver.commitment(params),
+        ]
+    }
+}
+
+/// Allocates an account and exposes its commitment.
+fn account<S, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<S>,
+    balance: Option<S>,
+    salt: Option<S>,
+) -> Result<AllocatedNum<S>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    let balance = AllocatedNum::alloc(cs.namespace(|| "balance"), || Ok(*balance.get()?))?;
+    let salt = AllocatedNum::alloc(cs.namespace(|| "salt"), || Ok(*salt.get()?))?;
+    let commitment = poseidon::hash(
+        cs.namespace(|| "commitment"),
+        params,
+        &[balance.clone(), salt],
+    )?;
+    commitment.inputize(cs.namespace(|| "input"))?;
+    Ok(balance)
+}
+
+/// Proves a transfer between the accounts committed to by the public
+/// inputs, in the order of [`Transfer::commitments`].
+pub struct TransferCircuit<'a, S: PrimeField> {
+    pub params: &'a PoseidonParams<S>,
+    /// The transfer, when proving.
+    pub transfer: Option<&'a Transfer<S>>,
+}
+
+impl<'a, S: PrimeField> Circuit<S> for TransferCircuit<'a, S> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let accounts = self.transfer.map(|transfer| {
+            [
+                transfer.sender,
+                transfer.receiver,
+                transfer.new_sender,
+                transfer.new_receiver,
+            ]
+        });
+        let mut balances = vec![];
+        for (i, name) in ["sender", "receiver", "new sender", "new receiver"]
+            .iter()
+            .enumerate()
+        {
+            let account_i = accounts.map(|accounts| accounts[i]);
+            balances.push(account(
+                cs.namespace(|| *name),
+                self.params,
+                account_i.map(|account| S::from(account.balance)),
+                account_i.map(|account| account.salt),
+            )?);
+        }
+        let amount = AllocatedNum::alloc(cs.namespace(|| "amount"), || {
+            self.transfer
+                .map(|transfer| S::from(transfer.amount))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let (sender, receiver, new_sender, new_receiver) =
+            (&balances[0], &balances[1], &balances[2], &balances[3]);
+        cs.enforce(
+            || "sender pays",
+            |lc| lc + sender.get_variable() - amount.get_variable() - new_sender.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc,
+        );
+        cs.enforce(
+            || "receiver is paid",
+            |lc| lc + receiver.get_variable() + amount.get_variable() - new_receiver.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc,
+        );
+
+        let mut ranges = RangeChecks::new();
+        for balance in balances.iter().chain(Some(&amount)) {
+            ranges.check_num(balance, BALANCE_BITS);
+        }
+        ranges.enforce(cs.namespace(|| "ranges"))
+    }
+}
+
+/// The parameters for transfers.
+pub struct Token<E: MultiMillerLoop> {
+    params: PoseidonParams<E::Fr>,
+    keys: Keys<E>,
+}
+
+impl<E: MultiMillerLoop> Token<E> {
+    /// Generates the parameters for transfers.
+    pub fn setup<R: RngCore>(rng: &mut R) -> Result<Self, SynthesisError>
+    where
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+    {
+        let params = PoseidonParams::for_width(WIDTH);
+        let keys = Keys::generate(
+            TransferCircuit {
+                params: &params,
+                transfer: None,
+            },
+            rng,
+        )?;
+        Ok(Token { params, keys })
+    }
+
+    pub fn params(&self) -> &PoseidonParams<E::Fr> {
+        &self.params
+    }
+
+    pub fn keys(&self) -> &Keys<E> {
+        &self.keys
+    }
+
+    /// Proves `transfer`, and returns its commitments with the proof.
+    pub fn prove<R: RngCore>(
+        &self,
+        transfer: &Transfer<E::Fr>,
+        rng: &mut R,
+    ) -> Result<([E::Fr; 4], Proof<E>), SynthesisError> {
+        let proof = self.keys.prove(
+            TransferCircuit {
+                params: &self.params,
+                transfer: Some(transfer),
+            },
+            rng,
+        )?;
+        Ok((transfer.commitments(&self.params), proof))
+    }
+
+    /// Verifies a proof of a transfer between the accounts of
+    /// `commitments`.
+    pub fn verify(
+        &self,
+        commitments: &[E::Fr; 4],
+        proof: &Proof<E>,
+    ) -> Result<(), VerificationError> {
+        self.keys.verify(proof, commitments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn transfer() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let token = Token::<Bls12>::setup(&mut rng).unwrap();
+        let alice = Account::random(100, &mut rng);
+        let bob = Account::random(u64::MAX - 50, &mut rng);
+
+        let transfer = Transfer::new(alice, bob, 30, &mut rng).unwrap();
+        assert_eq!(transfer.new_sender.balance, 70);
+        let (commitments, proof) = token.prove(&transfer, &mut rng).unwrap();
+        assert!(token.verify(&commitments, &proof).is_ok());
+        let mut swapped = commitments;
+        swapped.swap(2, 3);
+        assert!(token.verify(&swapped, &proof).is_err());
+
+        assert!(Transfer::new(alice, bob, 101, &mut rng).is_none());
+        assert!(Transfer::new(alice, bob, 51, &mut rng).is_none());
+
+        // An amount over the balance wraps the sender's around the field,
+        // where only the range checks catch it.
+        let mut cs = TestConstraintSystem::new();
+        TransferCircuit {
+            params: token.params(),
+            transfer: Some(&transfer),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert!(cs.is_satisfied());
+        let amount = Scalar::from(130);
+        cs.set("amount/num", amount);
+        cs.set(
+            "new sender/balance/num",
+            Scalar::from(alice.balance) - amount,
+        );
+        cs.set(
+            "new receiver/balance/num",
+            Scalar::from(bob.balance) + amount,
+        );
+        let unsatisfied = cs.unsatisfied();
+        assert!(unsatisfied.iter().any(|u| u.path.starts_with("ranges/")));
+        assert!(!unsatisfied
+            .iter()
+            .any(|u| u.path.ends_with(" paid") || u.path.ends_with(" pays")));
+    }
+}
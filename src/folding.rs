@@ -0,0 +1,808 @@
+//! Folding of relaxed R1CS instances, following [Nova].
+//!
+//! A relaxed R1CS instance for matrices `(A, B, C)` is satisfied by an
+//! assignment `z = (u, x, W)` and an error vector `E` when
+//! `(A z) ∘ (B z) = u (C z) + E`. An ordinary R1CS instance is the special
+//! case `u = 1, E = 0`. Instances are committed to with Pedersen vector
+//! commitments to `W` and `E`, and two committed instances can be *folded*
+//! into one, whose satisfiability implies that of both, at the cost of a
+//! single commitment sent by the prover.
+//!
+//! This lets a long iterative computation be proven step by step: each step
+//! is synthesized from the same [`Circuit`] and folded into an
+//! [`Accumulator`], and only the final accumulated instance needs to be
+//! checked. The matrices are those of [`RawCircuit`]; commitments use the
+//! generators of an [`ipa::Params`], so the group's scalar field must be the
+//! circuit's field. `W` is committed to over the first `num_aux` generators
+//! and `E` over the next `num_constraints`, so that `comm_w + comm_e` is a
+//! commitment to `(W, E)`.
+//!
+//! The final instance is compressed into a single Groth16 proof by
+//! [`compress`]: a circuit checks the relaxed relation for public `u` and `x`,
+//! and commits to `W` and `E` with the LegoGroth16 construction of
+//! [`commitment`], whose link proof shows that they open `comm_w + comm_e`.
+//! [`verify_compressed`] then checks the running instance without its
+//! witness, in a constant number of pairings.
+//!
+//! The verifier still folds every step itself, with
+//! [`FoldingParams::fold_instances`], so it needs the instance and folding
+//! proof of each step. Nova removes that by expressing the folding verifier
+//! in an augmented step circuit over a cycle of curves, where the
+//! commitments of one curve are native arithmetic in the circuit of the
+//! other. No such cycle is available here (the base field of BLS12-381 G1
+//! is not the scalar field of any curve this crate supports), so
+//! incrementally verifiable computation is out of scope of this module.
+//!
+//! [Nova]: https://eprint.iacr.org/2021/370
+//! [`Circuit`]: crate::Circuit
+//! [`Accumulator`]: crate::folding::Accumulator
+//! [`RawCircuit`]: crate::groth16::exporter::RawCircuit
+//! [`ipa::Params`]: crate::ipa::Params
+//! [`compress`]: crate::folding::compress
+//! [`commitment`]: crate::groth16::commitment
+//! [`verify_compressed`]: crate::folding::verify_compressed
+//! [`FoldingParams::fold_instances`]: crate::folding::FoldingParams::fold_instances
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurve, Curve, WnafGroup};
+use pairing::{Engine, MultiMillerLoop};
+use rand_core::RngCore;
+use std::ops::AddAssign;
+
+use crate::groth16::commitment::{
+    self, CommitmentKey, CommittedProof, LinkVerifyingKey, PedersenKey,
+};
+use crate::groth16::exporter::{Assignment, RawCircuit};
+use crate::groth16::{Parameters, PreparedVerifyingKey};
+use crate::ipa;
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+use crate::transcript::{Blake2sTranscript, Transcript};
+use crate::{
+    Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, VerificationError,
+};
+
+/// The products `(A z, B z, C z)`.
+type Products<S> = (Vec<S>, Vec<S>, Vec<S>);
+
+/// Public parameters for folding instances of one step circuit.
+#[derive(Clone)]
+pub struct FoldingParams<G: PrimeCurve> {
+    shape: RawCircuit<G::Scalar>,
+    generators: ipa::Params<G>,
+    digest: [u8; 32],
+}
+
+/// A committed R1CS instance produced by a single step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance<G: PrimeCurve> {
+    pub comm_w: G,
+    /// The public inputs, excluding `ONE`.
+    pub x: Vec<G::Scalar>,
+}
+
+/// The witness of an [`Instance`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Witness<S: PrimeField> {
+    pub w: Vec<S>,
+    pub r_w: S,
+}
+
+/// A committed relaxed R1CS instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelaxedInstance<G: PrimeCurve> {
+    pub comm_w: G,
+    pub comm_e: G,
+    pub u: G::Scalar,
+    pub x: Vec<G::Scalar>,
+}
+
+/// The witness of a [`RelaxedInstance`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelaxedWitness<S: PrimeField> {
+    pub w: Vec<S>,
+    pub e: Vec<S>,
+    pub r_w: S,
+    pub r_e: S,
+}
+
+/// The prover's message when folding: a commitment to the cross term.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoldingProof<G: PrimeCurve> {
+    pub comm_t: G,
+}
+
+impl<G: PrimeCurve> From<Instance<G>> for RelaxedInstance<G> {
+    fn from(instance: Instance<G>) -> Self {
+        RelaxedInstance {
+            comm_w: instance.comm_w,
+            comm_e: G::identity(),
+            u: G::Scalar::one(),
+            x: instance.x,
+        }
+    }
+}
+
+impl<S: PrimeField> RelaxedWitness<S> {
+    fn from_witness(witness: Witness<S>, num_constraints: usize) -> Self {
+        RelaxedWitness {
+            w: witness.w,
+            e: vec![S::zero(); num_constraints],
+            r_w: witness.r_w,
+            r_e: S::zero(),
+        }
+    }
+}
+
+impl<G: PrimeCurve> FoldingParams<G> {
+    /// Creates parameters for the step circuit `circuit`, deriving the
+    /// commitment generators from `seed`.
+    pub fn new<C: Circuit<G::Scalar>>(circuit: C, seed: &[u8]) -> Result<Self, SynthesisError> {
+        let shape = RawCircuit::synthesize(circuit)?;
+
+        let size = std::cmp::max(shape.num_aux + shape.num_constraints, 1);
+        let mut k = 0;
+        while (1 << k) < size {
+            k += 1;
+        }
+        let generators = ipa::Params::new(k, seed);
+
+        let digest = shape_digest(&shape, seed);
+
+        Ok(FoldingParams {
+            shape,
+            generators,
+            digest,
+        })
+    }
+
+    /// Returns the constraint matrices of the step circuit.
+    pub fn shape(&self) -> &RawCircuit<G::Scalar> {
+        &self.shape
+    }
+
+    /// Synthesizes one step of the computation and commits to its witness.
+    pub fn synthesize<C, R>(
+        &self,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Instance<G>, Witness<G::Scalar>), SynthesisError>
+    where
+        C: Circuit<G::Scalar>,
+        R: RngCore,
+    {
+        let assignment = Assignment::synthesize(circuit)?;
+
+        if assignment.inputs.len() != self.shape.num_inputs
+            || assignment.aux.len() != self.shape.num_aux
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let r_w = G::Scalar::random(&mut *rng);
+        let comm_w = self.commit(0, &assignment.aux, r_w)?;
+
+        Ok((
+            Instance {
+                comm_w,
+                x: assignment.inputs[1..].to_vec(),
+            },
+            Witness {
+                w: assignment.aux,
+                r_w,
+            },
+        ))
+    }
+
+    /// Folds a step into a running accumulator, returning the folded
+    /// accumulator together with the proof that the verifier needs to fold
+    /// the instances itself.
+    pub fn fold<R: RngCore>(
+        &self,
+        running: &Accumulator<G>,
+        step: (&Instance<G>, &Witness<G::Scalar>),
+        rng: &mut R,
+    ) -> Result<(Accumulator<G>, FoldingProof<G>), SynthesisError> {
+        let (u1, w1) = (&running.instance, &running.witness);
+        let (u2, w2) = step;
+        self.check_sizes(&u1.x, &w1.w, &w1.e)?;
+        self.check_sizes(&u2.x, &w2.w, &w1.e)?;
+
+        let (a1, b1, c1) = self.multiply(u1.u, &u1.x, &w1.w);
+        let (a2, b2, c2) = self.multiply(G::Scalar::one(), &u2.x, &w2.w);
+
+        // The cross term T = Az1 ∘ Bz2 + Az2 ∘ Bz1 - u1 Cz2 - u2 Cz1, with u2 = 1.
+        let t = (0..self.shape.num_constraints)
+            .map(|i| a1[i] * &b2[i] + &(a2[i] * &b1[i]) - &(u1.u * &c2[i]) - &c1[i])
+            .collect::<Vec<_>>();
+        let r_t = G::Scalar::random(&mut *rng);
+        let proof = FoldingProof {
+            comm_t: self.commit(self.shape.num_aux, &t, r_t)?,
+        };
+
+        let r = self.challenge(u1, u2, &proof);
+        let instance = self.fold_instances(u1, u2, &proof);
+        let witness = RelaxedWitness {
+            w: w1
+                .w
+                .iter()
+                .zip(w2.w.iter())
+                .map(|(a, b)| *a + &(r * b))
+                .collect(),
+            e: w1
+                .e
+                .iter()
+                .zip(t.iter())
+                .map(|(e, t)| *e + &(r * t))
+                .collect(),
+            r_w: w1.r_w + &(r * &w2.r_w),
+            r_e: w1.r_e + &(r * &r_t),
+        };
+
+        Ok((Accumulator { instance, witness }, proof))
+    }
+
+    /// Folds a step's instance into a running instance, as the verifier does.
+    pub fn fold_instances(
+        &self,
+        running: &RelaxedInstance<G>,
+        step: &Instance<G>,
+        proof: &FoldingProof<G>,
+    ) -> RelaxedInstance<G> {
+        let r = self.challenge(running, step, proof);
+
+        RelaxedInstance {
+            comm_w: running.comm_w + step.comm_w * r,
+            comm_e: running.comm_e + proof.comm_t * r,
+            u: running.u + &r,
+            x: running
+                .x
+                .iter()
+                .zip(step.x.iter())
+                .map(|(a, b)| *a + &(r * b))
+                .collect(),
+        }
+    }
+
+    /// Checks that `witness` opens the commitments in `instance` and
+    /// satisfies the relaxed R1CS relation.
+    pub fn is_satisfied(
+        &self,
+        instance: &RelaxedInstance<G>,
+        witness: &RelaxedWitness<G::Scalar>,
+    ) -> Result<(), SynthesisError> {
+        self.check_sizes(&instance.x, &witness.w, &witness.e)?;
+
+        if self.commit(0, &witness.w, witness.r_w)? != instance.comm_w
+            || self.commit(self.shape.num_aux, &witness.e, witness.r_e)? != instance.comm_e
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let (a, b, c) = self.multiply(instance.u, &instance.x, &witness.w);
+        for i in 0..self.shape.num_constraints {
+            if a[i] * &b[i] != instance.u * &c[i] + &witness.e[i] {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_sizes(
+        &self,
+        x: &[G::Scalar],
+        w: &[G::Scalar],
+        e: &[G::Scalar],
+    ) -> Result<(), SynthesisError> {
+        if x.len() + 1 != self.shape.num_inputs
+            || w.len() != self.shape.num_aux
+            || e.len() != self.shape.num_constraints
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        Ok(())
+    }
+
+    /// Commits to `values` over the generators from `offset`.
+    fn commit(
+        &self,
+        offset: usize,
+        values: &[G::Scalar],
+        blind: G::Scalar,
+    ) -> Result<G, SynthesisError> {
+        let bases = &self.generators.g()[offset..offset + values.len()];
+        let mut acc = dense_multiexp::<G>(&Worker::new(), bases, values)?;
+        acc.add_assign(&(*self.generators.h() * blind));
+
+        Ok(acc)
+    }
+
+    /// Evaluates the matrices on `z = (u, x, w)`.
+    fn multiply(&self, u: G::Scalar, x: &[G::Scalar], w: &[G::Scalar]) -> Products<G::Scalar> {
+        let mut inputs = Vec::with_capacity(x.len() + 1);
+        inputs.push(u);
+        inputs.extend_from_slice(x);
+
+        self.shape.evaluate(&inputs, w)
+    }
+
+    /// Derives the folding challenge from the parameters and both instances.
+    fn challenge(
+        &self,
+        running: &RelaxedInstance<G>,
+        step: &Instance<G>,
+        proof: &FoldingProof<G>,
+    ) -> G::Scalar {
+        let mut transcript = Blake2sTranscript::new(b"bellman-nifs");
+        transcript.absorb_bytes(b"params", &self.digest);
+
+        transcript.absorb_point(b"running.comm_w", &running.comm_w);
+        transcript.absorb_point(b"running.comm_e", &running.comm_e);
+        transcript.absorb_scalar(b"running.u", &running.u);
+        for x in running.x.iter() {
+            transcript.absorb_scalar(b"running.x", x);
+        }
+
+        transcript.absorb_point(b"step.comm_w", &step.comm_w);
+        for x in step.x.iter() {
+            transcript.absorb_scalar(b"step.x", x);
+        }
+
+        transcript.absorb_point(b"comm_t", &proof.comm_t);
+        transcript.squeeze_challenge(b"r")
+    }
+}
+
+/// The running state of an incremental computation: a relaxed instance
+/// covering every step folded so far, and its witness.
+#[derive(Clone, Debug)]
+pub struct Accumulator<G: PrimeCurve> {
+    pub instance: RelaxedInstance<G>,
+    pub witness: RelaxedWitness<G::Scalar>,
+}
+
+impl<G: PrimeCurve> Accumulator<G> {
+    /// Starts an accumulator from the first step of the computation.
+    pub fn new<C, R>(
+        params: &FoldingParams<G>,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Self, Instance<G>), SynthesisError>
+    where
+        C: Circuit<G::Scalar>,
+        R: RngCore,
+    {
+        let (instance, witness) = params.synthesize(circuit, rng)?;
+
+        Ok((
+            Accumulator {
+                instance: instance.clone().into(),
+                witness: RelaxedWitness::from_witness(witness, params.shape.num_constraints),
+            },
+            instance,
+        ))
+    }
+
+    /// Synthesizes the next step and folds it into the accumulator,
+    /// returning the step's instance and the folding proof, with which a
+    /// verifier can update its copy of the running instance.
+    pub fn fold_step<C, R>(
+        &mut self,
+        params: &FoldingParams<G>,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Instance<G>, FoldingProof<G>), SynthesisError>
+    where
+        C: Circuit<G::Scalar>,
+        R: RngCore,
+    {
+        let (instance, witness) = params.synthesize(circuit, rng)?;
+        let (folded, proof) = params.fold(self, (&instance, &witness), rng)?;
+        *self = folded;
+
+        Ok((instance, proof))
+    }
+
+    /// Checks the accumulated instance, which holds only if every folded
+    /// step was satisfied.
+    pub fn decide(&self, params: &FoldingParams<G>) -> Result<(), SynthesisError> {
+        params.is_satisfied(&self.instance, &self.witness)
+    }
+}
+
+/// The circuit of [`compress`]: the relaxed R1CS relation of the step
+/// circuit, for `u` and `x` as public inputs and `W` and `E` allocated
+/// under the namespaces `w` and `e`, which the proof commits to.
+struct Decider<'a, S: PrimeField> {
+    shape: &'a RawCircuit<S>,
+    instance: Option<(S, &'a [S])>,
+    witness: Option<&'a RelaxedWitness<S>>,
+}
+
+impl<'a, S: PrimeField> Circuit<S> for Decider<'a, S> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let shape = self.shape;
+        let instance = self.instance;
+        let witness = self.witness;
+        // The products `(A z, B z, C z)`, when proving.
+        let products = match (instance, witness) {
+            (Some((u, x)), Some(witness)) => {
+                if x.len() + 1 != shape.num_inputs
+                    || witness.w.len() != shape.num_aux
+                    || witness.e.len() != shape.num_constraints
+                {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+                let mut inputs = vec![u];
+                inputs.extend_from_slice(x);
+                Some(shape.evaluate(&inputs, &witness.w))
+            }
+            _ => None,
+        };
+
+        // The column of `ONE` in the step circuit is that of `u`.
+        let mut inputs = vec![cs.alloc_input(
+            || "u",
+            || {
+                instance
+                    .map(|(u, _)| u)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            },
+        )?];
+        for i in 1..shape.num_inputs {
+            inputs.push(cs.alloc_input(
+                || format!("x {}", i),
+                || {
+                    instance
+                        .map(|(_, x)| x[i - 1])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?);
+        }
+        let mut aux = vec![];
+        {
+            let cs = &mut cs.namespace(|| "w");
+            for i in 0..shape.num_aux {
+                aux.push(cs.alloc(
+                    || format!("{}", i),
+                    || {
+                        witness
+                            .map(|w| w.w[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )?);
+            }
+        }
+        let mut e = vec![];
+        {
+            let cs = &mut cs.namespace(|| "e");
+            for i in 0..shape.num_constraints {
+                e.push(cs.alloc(
+                    || format!("{}", i),
+                    || {
+                        witness
+                            .map(|w| w.e[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )?);
+            }
+        }
+
+        let map = |lc: &LinearCombination<S>| {
+            lc.as_ref()
+                .iter()
+                .fold(LinearCombination::zero(), |acc, (var, coeff)| {
+                    acc + (
+                        *coeff,
+                        match var.get_unchecked() {
+                            Index::Input(i) => inputs[i],
+                            Index::Aux(i) => aux[i],
+                        },
+                    )
+                })
+        };
+        for (i, [a, b, c]) in shape.constraints().iter().enumerate() {
+            let uc = cs.alloc(
+                || format!("u c {}", i),
+                || {
+                    let u = instance.map(|(u, _)| u);
+                    match (u, &products) {
+                        (Some(u), Some((_, _, c))) => Ok(u * &c[i]),
+                        _ => Err(SynthesisError::AssignmentMissing),
+                    }
+                },
+            )?;
+            cs.enforce(
+                || format!("u c {}", i),
+                |lc| lc + inputs[0],
+                |_| map(c),
+                |lc| lc + uc,
+            );
+            cs.enforce(
+                || format!("constraint {}", i),
+                |_| map(a),
+                |_| map(b),
+                |lc| lc + uc + e[i],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Generates the parameters of [`compress`] for the step circuit of
+/// `params`, with a commitment key linked to the generators of `W` and `E`.
+pub fn generate_compression_parameters<E, R>(
+    params: &FoldingParams<E::G1>,
+    rng: &mut R,
+) -> Result<(Parameters<E>, CommitmentKey<E>), SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    R: RngCore,
+{
+    let len = params.shape.num_aux + params.shape.num_constraints;
+    let pedersen = PedersenKey {
+        bases: params.generators.g()[..len].to_vec(),
+        blinding: *params.generators.h(),
+    };
+    let decider = Decider {
+        shape: &params.shape,
+        instance: None,
+        witness: None,
+    };
+
+    commitment::generate_random_parameters(decider, &["w", "e"], Some(&pedersen), rng)
+}
+
+/// Proves with a single Groth16 proof that the instance of `accumulator`
+/// is satisfied, with parameters of [`generate_compression_parameters`].
+pub fn compress<E, R>(
+    params: &FoldingParams<E::G1>,
+    groth_params: &Parameters<E>,
+    key: &CommitmentKey<E>,
+    accumulator: &Accumulator<E::G1>,
+    rng: &mut R,
+) -> Result<CommittedProof<E>, SynthesisError>
+where
+    E: Engine,
+    R: RngCore,
+{
+    let instance = &accumulator.instance;
+    let witness = &accumulator.witness;
+    let decider = Decider {
+        shape: &params.shape,
+        instance: Some((instance.u, &instance.x)),
+        witness: Some(witness),
+    };
+    // `comm_w + comm_e` is blinded by `r_w + r_e`.
+    let blindings = [E::Fr::random(&mut *rng), witness.r_w + &witness.r_e];
+
+    commitment::create_random_proof_with_committed_witness(
+        decider,
+        groth_params,
+        key,
+        &blindings,
+        rng,
+    )
+}
+
+/// Checks a proof of [`compress`] for the running instance `instance`, as
+/// folded by the verifier.
+pub fn verify_compressed<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    vk: &LinkVerifyingKey<E>,
+    instance: &RelaxedInstance<E::G1>,
+    proof: &CommittedProof<E>,
+) -> Result<(), VerificationError> {
+    let mut external = instance.comm_w;
+    AddAssign::<&E::G1>::add_assign(&mut external, &instance.comm_e);
+    let mut inputs = vec![instance.u];
+    inputs.extend_from_slice(&instance.x);
+
+    commitment::verify_proof_with_commitment(pvk, vk, proof, Some(&external.to_affine()), &inputs)
+}
+
+/// Hashes the constraint matrices and generator seed, so that folding
+/// challenges are bound to the circuit being folded.
+fn shape_digest<S: PrimeField>(shape: &RawCircuit<S>, seed: &[u8]) -> [u8; 32] {
+    let mut h = Blake2sParams::new().personal(b"bellNIFS").to_state();
+    h.update(&(seed.len() as u64).to_le_bytes());
+    h.update(seed);
+    for n in [shape.num_inputs, shape.num_aux, shape.num_constraints].iter() {
+        h.update(&(*n as u64).to_le_bytes());
+    }
+    for matrix in [
+        &shape.at_inputs,
+        &shape.bt_inputs,
+        &shape.ct_inputs,
+        &shape.at_aux,
+        &shape.bt_aux,
+        &shape.ct_aux,
+    ]
+    .iter()
+    {
+        for column in matrix.iter() {
+            h.update(&(column.len() as u64).to_le_bytes());
+            for (coeff, constraint) in column {
+                h.update(coeff.to_repr().as_ref());
+                h.update(&(*constraint as u64).to_le_bytes());
+            }
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(h.finalize().as_bytes());
+    digest
+}
+
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::prepare_verifying_key;
+    use bls12_381::{Bls12, G1Projective, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// One step of iterated squaring: proves `y = x^2` for public `x`, `y`.
+    struct SquareStep {
+        x: Option<Scalar>,
+        y: Option<Scalar>,
+    }
+
+    impl Circuit<Scalar> for SquareStep {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc_input(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let w = cs.alloc(|| "w", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(|| "y", || self.y.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "w = x", |lc| lc + w, |lc| lc + CS::one(), |lc| lc + x);
+            cs.enforce(|| "y = w^2", |lc| lc + w, |lc| lc + w, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    fn step(x: Scalar) -> SquareStep {
+        SquareStep {
+            x: Some(x),
+            y: Some(x.square()),
+        }
+    }
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    fn params() -> FoldingParams<G1Projective> {
+        FoldingParams::new(SquareStep { x: None, y: None }, b"test").unwrap()
+    }
+
+    #[test]
+    fn fold_many_steps() {
+        let mut rng = rng();
+        let params = params();
+
+        let mut x = Scalar::from(3);
+        let (mut acc, first) = Accumulator::new(&params, step(x), &mut rng).unwrap();
+        let mut verifier: RelaxedInstance<G1Projective> = first.into();
+        assert!(acc.decide(&params).is_ok());
+
+        for _ in 0..4 {
+            x = x.square();
+            let (instance, proof) = acc.fold_step(&params, step(x), &mut rng).unwrap();
+            verifier = params.fold_instances(&verifier, &instance, &proof);
+            assert_eq!(verifier, acc.instance);
+        }
+        assert!(acc.decide(&params).is_ok());
+        assert!(acc.witness.e.iter().any(|e| !e.is_zero()));
+    }
+
+    #[test]
+    fn unsatisfied_step_is_caught() {
+        let mut rng = rng();
+        let params = params();
+
+        let (mut acc, _) = Accumulator::new(&params, step(Scalar::from(3)), &mut rng).unwrap();
+        let bad = SquareStep {
+            x: Some(Scalar::from(5)),
+            y: Some(Scalar::from(24)),
+        };
+        acc.fold_step(&params, bad, &mut rng).unwrap();
+        acc.fold_step(&params, step(Scalar::from(7)), &mut rng)
+            .unwrap();
+
+        assert!(matches!(
+            acc.decide(&params),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    #[test]
+    fn tampered_instance_is_rejected() {
+        let mut rng = rng();
+        let params = params();
+
+        let (mut acc, _) = Accumulator::new(&params, step(Scalar::from(3)), &mut rng).unwrap();
+        acc.fold_step(&params, step(Scalar::from(4)), &mut rng)
+            .unwrap();
+
+        let mut tampered = acc.clone();
+        tampered.instance.x[0] += Scalar::one();
+        assert!(tampered.decide(&params).is_err());
+
+        let mut tampered = acc.clone();
+        tampered.witness.w[0] += Scalar::one();
+        assert!(tampered.decide(&params).is_err());
+
+        // A step from a circuit of a different shape cannot be folded.
+        let (instance, witness) = params.synthesize(step(Scalar::from(2)), &mut rng).unwrap();
+        let mut short = witness.clone();
+        short.w.pop();
+        assert!(params.fold(&acc, (&instance, &short), &mut rng).is_err());
+    }
+
+    #[test]
+    fn compressed_accumulator_verifies() {
+        let mut rng = rng();
+        let params = params();
+        let (groth_params, key) =
+            generate_compression_parameters::<Bls12, _>(&params, &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&groth_params.vk);
+
+        let mut x = Scalar::from(3);
+        let (mut acc, first) = Accumulator::new(&params, step(x), &mut rng).unwrap();
+        let mut verifier: RelaxedInstance<G1Projective> = first.into();
+        for _ in 0..3 {
+            x = x.square();
+            let (instance, proof) = acc.fold_step(&params, step(x), &mut rng).unwrap();
+            verifier = params.fold_instances(&verifier, &instance, &proof);
+        }
+
+        let proof = compress(&params, &groth_params, &key, &acc, &mut rng).unwrap();
+        assert!(verify_compressed(&pvk, &key.vk, &verifier, &proof).is_ok());
+
+        let mut tampered = verifier.clone();
+        tampered.x[0] += Scalar::one();
+        assert!(verify_compressed(&pvk, &key.vk, &tampered, &proof).is_err());
+
+        let mut tampered = verifier.clone();
+        tampered.u += Scalar::one();
+        assert!(verify_compressed(&pvk, &key.vk, &tampered, &proof).is_err());
+
+        // The proof is bound to the committed error vector, not only to `W`.
+        let mut tampered = verifier.clone();
+        tampered.comm_e = G1Projective::identity();
+        assert!(verify_compressed(&pvk, &key.vk, &tampered, &proof).is_err());
+    }
+
+    #[test]
+    fn unsatisfied_accumulator_does_not_compress() {
+        let mut rng = rng();
+        let params = params();
+        let (groth_params, key) =
+            generate_compression_parameters::<Bls12, _>(&params, &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&groth_params.vk);
+
+        let (mut acc, _) = Accumulator::new(&params, step(Scalar::from(3)), &mut rng).unwrap();
+        let bad = SquareStep {
+            x: Some(Scalar::from(5)),
+            y: Some(Scalar::from(24)),
+        };
+        acc.fold_step(&params, bad, &mut rng).unwrap();
+
+        let proof = compress(&params, &groth_params, &key, &acc, &mut rng).unwrap();
+        assert!(verify_compressed(&pvk, &key.vk, &acc.instance, &proof).is_err());
+    }
+}
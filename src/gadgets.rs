@@ -1,15 +1,26 @@
-//! Self-contained sub-circuit implementations for various primitives.
+//! Self-contained sub-circuit implementations for various primitives, with
+//! the `gadgets` feature: booleans and field elements, [`u32`] and [`u64`]
+//! words, and hashes such as SHA-256 and BLAKE2s built from them.
 
 pub mod test;
 
 pub mod blake2s;
 pub mod boolean;
+pub mod ecc;
+pub mod inputs;
+pub mod io;
 pub mod lookup;
+pub mod mmr;
 pub mod multieq;
 pub mod multipack;
 pub mod num;
+pub mod poseidon;
+pub mod profiler;
+pub mod range;
 pub mod sha256;
+pub mod trace;
 pub mod uint32;
+pub mod uint64;
 
 use crate::SynthesisError;
 
@@ -453,7 +453,7 @@ mod test {
             .collect();
         blake2s(&mut cs, &input_bits, b"12345678").unwrap();
         assert!(cs.is_satisfied());
-        assert_eq!(cs.num_constraints(), 21518);
+        assert_eq!(cs.num_constraints(), 21514);
     }
 
     #[test]
@@ -476,7 +476,7 @@ mod test {
             .collect();
         blake2s(&mut cs, &input_bits, b"12345678").unwrap();
         assert!(cs.is_satisfied());
-        assert_eq!(cs.num_constraints(), 21518);
+        assert_eq!(cs.num_constraints(), 21514);
     }
 
     #[test]
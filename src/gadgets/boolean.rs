@@ -375,6 +375,15 @@ impl Boolean {
         }
     }
 
+    /// Returns the value of the boolean if it is a constant, which gadgets
+    /// can compute with natively rather than with constraints.
+    pub fn as_constant(&self) -> Option<bool> {
+        match *self {
+            Boolean::Constant(b) => Some(b),
+            _ => None,
+        }
+    }
+
     pub fn enforce_equal<Scalar, CS>(mut cs: CS, a: &Self, b: &Self) -> Result<(), SynthesisError>
     where
         Scalar: PrimeField,
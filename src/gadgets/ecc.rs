@@ -0,0 +1,619 @@
+//! Gadgets for twisted Edwards curves embedded in the scalar field.
+//!
+//! An [`EmbeddedCurve`] is a twisted Edwards curve `a·x² + y² = 1 + d·x²·y²`
+//! whose base field is the scalar field of the constraint system, such as
+//! Jubjub and Bandersnatch over the scalar field of BLS12-381, or Baby
+//! Jubjub over the scalar field of BN254. The gadgets of this module are
+//! generic over the curve, so supporting a new curve only takes its
+//! parameters.
+//!
+//! The addition formulas have no exceptional cases when `a` is a square and
+//! `d` is not, as for Jubjub and Baby Jubjub. Otherwise, as for Bandersnatch,
+//! they have none on the prime-order subgroup, and synthesis fails with
+//! [`SynthesisError::DivisionByZero`] if it is left.
+
+use ff::PrimeField;
+use std::marker::PhantomData;
+
+use super::boolean::Boolean;
+use super::lookup::lookup3_xy;
+use super::num::AllocatedNum;
+use super::Assignment;
+use crate::{ConstraintSystem, SynthesisError};
+
+/// The parameters of a twisted Edwards curve over `Scalar`.
+pub trait EmbeddedCurve<Scalar: PrimeField> {
+    /// The coefficient `a` of the curve equation.
+    fn a() -> Scalar;
+
+    /// The coefficient `d` of the curve equation.
+    fn d() -> Scalar;
+
+    /// The cofactor of the prime-order subgroup.
+    fn cofactor() -> u64;
+
+    /// A generator of the prime-order subgroup.
+    fn generator() -> (Scalar, Scalar);
+
+    /// Adds two points.
+    fn add(p: (Scalar, Scalar), q: (Scalar, Scalar)) -> (Scalar, Scalar) {
+        let t = Self::d() * p.0 * q.0 * p.1 * q.1;
+        let x = (p.0 * q.1 + p.1 * q.0) * (Scalar::one() + t).invert().unwrap();
+        let y = (p.1 * q.1 - Self::a() * p.0 * q.0) * (Scalar::one() - t).invert().unwrap();
+        (x, y)
+    }
+
+    /// Multiplies `p` by the scalar with the given little-endian bits.
+    fn mul(p: (Scalar, Scalar), bits: &[bool]) -> (Scalar, Scalar) {
+        let mut acc = (Scalar::zero(), Scalar::one());
+        for bit in bits.iter().rev() {
+            acc = Self::add(acc, acc);
+            if *bit {
+                acc = Self::add(acc, p);
+            }
+        }
+        acc
+    }
+
+    /// Returns `j · 8^i · base` for `j` in `0..8`, for each of the first
+    /// `windows` windows `i`, as used by [`EdwardsPoint::fixed_base_mul`].
+    /// Curves may override this to return precomputed tables for their
+    /// generators.
+    fn window_table(base: (Scalar, Scalar), windows: usize) -> Vec<Vec<(Scalar, Scalar)>> {
+        let mut table = Vec::with_capacity(windows);
+        let mut base = base;
+        for _ in 0..windows {
+            let mut window = vec![(Scalar::zero(), Scalar::one())];
+            for j in 1..8 {
+                window.push(Self::add(window[j - 1], base));
+            }
+            base = Self::add(window[7], base);
+            table.push(window);
+        }
+        table
+    }
+}
+
+/// The identity of a twisted Edwards curve.
+fn identity<Scalar: PrimeField>() -> (Scalar, Scalar) {
+    (Scalar::zero(), Scalar::one())
+}
+
+/// A point on an [`EmbeddedCurve`] in the constraint system.
+pub struct EdwardsPoint<Scalar: PrimeField, C: EmbeddedCurve<Scalar>> {
+    x: AllocatedNum<Scalar>,
+    y: AllocatedNum<Scalar>,
+    _curve: PhantomData<C>,
+}
+
+impl<Scalar: PrimeField, C: EmbeddedCurve<Scalar>> Clone for EdwardsPoint<Scalar, C> {
+    fn clone(&self) -> Self {
+        EdwardsPoint {
+            x: self.x.clone(),
+            y: self.y.clone(),
+            _curve: PhantomData,
+        }
+    }
+}
+
+impl<Scalar: PrimeField, C: EmbeddedCurve<Scalar>> EdwardsPoint<Scalar, C> {
+    pub fn x(&self) -> &AllocatedNum<Scalar> {
+        &self.x
+    }
+
+    pub fn y(&self) -> &AllocatedNum<Scalar> {
+        &self.y
+    }
+
+    pub fn get_value(&self) -> Option<(Scalar, Scalar)> {
+        match (self.x.get_value(), self.y.get_value()) {
+            (Some(x), Some(y)) => Some((x, y)),
+            _ => None,
+        }
+    }
+
+    /// Allocates a point, and enforces that it is on the curve.
+    pub fn witness<CS>(mut cs: CS, value: Option<(Scalar, Scalar)>) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(value.get()?.0))?;
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(value.get()?.1))?;
+
+        let x2 = x.square(cs.namespace(|| "x^2"))?;
+        let y2 = y.square(cs.namespace(|| "y^2"))?;
+
+        // x^2 * (a - d * y^2) = 1 - y^2
+        cs.enforce(
+            || "on curve",
+            |lc| lc + x2.get_variable(),
+            |lc| lc + (C::a(), CS::one()) - (C::d(), y2.get_variable()),
+            |lc| lc + CS::one() - y2.get_variable(),
+        );
+
+        Ok(EdwardsPoint {
+            x,
+            y,
+            _curve: PhantomData,
+        })
+    }
+
+    /// Allocates a point with a known value, fixed by two linear
+    /// constraints. The value must be on the curve, since the curve equation
+    /// is not enforced.
+    pub fn constant<CS>(mut cs: CS, value: (Scalar, Scalar)) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(value.0))?;
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(value.1))?;
+
+        cs.enforce(
+            || "x is constant",
+            |lc| lc + x.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (value.0, CS::one()),
+        );
+        cs.enforce(
+            || "y is constant",
+            |lc| lc + y.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (value.1, CS::one()),
+        );
+
+        Ok(EdwardsPoint {
+            x,
+            y,
+            _curve: PhantomData,
+        })
+    }
+
+    /// Adds two points.
+    pub fn add<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // u = (x1 + y1) * (x2 + y2)
+        let u = AllocatedNum::alloc(cs.namespace(|| "u"), || {
+            Ok((*self.x.get_value().get()? + self.y.get_value().get()?)
+                * (*other.x.get_value().get()? + other.y.get_value().get()?))
+        })?;
+        cs.enforce(
+            || "u computation",
+            |lc| lc + self.x.get_variable() + self.y.get_variable(),
+            |lc| lc + other.x.get_variable() + other.y.get_variable(),
+            |lc| lc + u.get_variable(),
+        );
+
+        // p = x1 * x2, q = y1 * y2
+        let p = self.x.mul(cs.namespace(|| "p"), &other.x)?;
+        let q = self.y.mul(cs.namespace(|| "q"), &other.y)?;
+
+        // c = d * p * q = d * x1 * x2 * y1 * y2
+        let c = AllocatedNum::alloc(cs.namespace(|| "c"), || {
+            Ok(C::d() * p.get_value().get()? * q.get_value().get()?)
+        })?;
+        cs.enforce(
+            || "c computation",
+            |lc| lc + (C::d(), p.get_variable()),
+            |lc| lc + q.get_variable(),
+            |lc| lc + c.get_variable(),
+        );
+
+        // x3 * (1 + c) = u - p - q = x1 * y2 + y1 * x2
+        let x3 = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+            let t = *u.get_value().get()? - p.get_value().get()? - q.get_value().get()?;
+            let denominator = Scalar::one() + c.get_value().get()?;
+            let inverse: Option<Scalar> = denominator.invert().into();
+            Ok(t * inverse.ok_or(SynthesisError::DivisionByZero)?)
+        })?;
+        cs.enforce(
+            || "x3 computation",
+            |lc| lc + x3.get_variable(),
+            |lc| lc + CS::one() + c.get_variable(),
+            |lc| lc + u.get_variable() - p.get_variable() - q.get_variable(),
+        );
+
+        // y3 * (1 - c) = q - a * p = y1 * y2 - a * x1 * x2
+        let y3 = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+            let t = *q.get_value().get()? - C::a() * p.get_value().get()?;
+            let denominator = Scalar::one() - c.get_value().get()?;
+            let inverse: Option<Scalar> = denominator.invert().into();
+            Ok(t * inverse.ok_or(SynthesisError::DivisionByZero)?)
+        })?;
+        cs.enforce(
+            || "y3 computation",
+            |lc| lc + y3.get_variable(),
+            |lc| lc + CS::one() - c.get_variable(),
+            |lc| lc + q.get_variable() - (C::a(), p.get_variable()),
+        );
+
+        Ok(EdwardsPoint {
+            x: x3,
+            y: y3,
+            _curve: PhantomData,
+        })
+    }
+
+    pub fn double<CS>(&self, cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        self.add(cs, self)
+    }
+
+    /// Returns the point if `condition` is true, and the identity
+    /// otherwise.
+    pub fn conditionally_select<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let selected =
+            |value: Option<Scalar>, identity: Scalar| -> Result<Scalar, SynthesisError> {
+                if *condition.get_value().get()? {
+                    Ok(*value.get()?)
+                } else {
+                    Ok(identity)
+                }
+            };
+
+        // x' = x * condition
+        let x = AllocatedNum::alloc(cs.namespace(|| "x'"), || {
+            selected(self.x.get_value(), Scalar::zero())
+        })?;
+        cs.enforce(
+            || "x' computation",
+            |lc| lc + self.x.get_variable(),
+            |_| condition.lc(CS::one(), Scalar::one()),
+            |lc| lc + x.get_variable(),
+        );
+
+        // y' - 1 = (y - 1) * condition
+        let y = AllocatedNum::alloc(cs.namespace(|| "y'"), || {
+            selected(self.y.get_value(), Scalar::one())
+        })?;
+        cs.enforce(
+            || "y' computation",
+            |lc| lc + self.y.get_variable() - CS::one(),
+            |_| condition.lc(CS::one(), Scalar::one()),
+            |lc| lc + y.get_variable() - CS::one(),
+        );
+
+        Ok(EdwardsPoint {
+            x,
+            y,
+            _curve: PhantomData,
+        })
+    }
+
+    /// Multiplies the point by the scalar with the given little-endian bits.
+    /// Constant bits are not selected, and the point is only doubled up to
+    /// the last bit that is not a constant `false`.
+    pub fn mul<CS>(&self, mut cs: CS, bits: &[Boolean]) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        assert!(!bits.is_empty());
+
+        let len = match bits.iter().rposition(|b| b.as_constant() != Some(false)) {
+            Some(last) => last + 1,
+            None => return Self::constant(cs.namespace(|| "identity"), identity()),
+        };
+
+        let mut base = self.clone();
+        let mut acc: Option<Self> = None;
+        for (i, bit) in bits[..len].iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            if i > 0 {
+                base = base.double(cs.namespace(|| "double"))?;
+            }
+            let term = match bit.as_constant() {
+                Some(false) => None,
+                Some(true) => Some(base.clone()),
+                None if i == 0 => Some(base.conditionally_select(&mut cs, bit)?),
+                None => Some(base.conditionally_select(cs.namespace(|| "select"), bit)?),
+            };
+            acc = match (acc, term) {
+                (Some(acc), Some(term)) => Some(acc.add(cs.namespace(|| "add"), &term)?),
+                (acc, term) => acc.or(term),
+            };
+        }
+
+        Ok(acc.expect("the last bit is not a constant false"))
+    }
+
+    /// Multiplies a fixed base by the scalar with the given little-endian
+    /// bits, with one table lookup per window of three bits. `table` comes
+    /// from [`EmbeddedCurve::window_table`], and must have at least as many
+    /// windows as `bits` has windows. The points of windows of constant bits
+    /// are summed natively, and added at once.
+    pub fn fixed_base_mul<CS>(
+        mut cs: CS,
+        table: &[Vec<(Scalar, Scalar)>],
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        assert!(!bits.is_empty());
+        assert!(table.len() * 3 >= bits.len());
+
+        let mut acc: Option<Self> = None;
+        let mut constant: Option<(Scalar, Scalar)> = None;
+        for (i, (window, points)) in bits.chunks(3).zip(table).enumerate() {
+            let mut window = window.to_vec();
+            window.resize(3, Boolean::constant(false));
+
+            let index = window.iter().rev().try_fold(0, |index, bit| {
+                bit.as_constant().map(|b| (index << 1) | usize::from(b))
+            });
+            if let Some(index) = index {
+                // The first point of each window is the identity.
+                if index != 0 {
+                    constant = Some(match constant {
+                        Some(sum) => C::add(sum, points[index]),
+                        None => points[index],
+                    });
+                }
+                continue;
+            }
+
+            let mut cs = cs.namespace(|| format!("window {}", i));
+            let (x, y) = lookup3_xy(cs.namespace(|| "lookup"), &window, points)?;
+            let point = EdwardsPoint {
+                x,
+                y,
+                _curve: PhantomData,
+            };
+            acc = Some(match acc {
+                Some(acc) => acc.add(cs.namespace(|| "add"), &point)?,
+                None => point,
+            });
+        }
+
+        match (acc, constant) {
+            (Some(acc), Some(constant)) => {
+                let mut cs = cs.namespace(|| "constant windows");
+                let point = Self::constant(cs.namespace(|| "sum"), constant)?;
+                acc.add(cs.namespace(|| "add"), &point)
+            }
+            (Some(acc), None) => Ok(acc),
+            (None, constant) => Self::constant(
+                cs.namespace(|| "constant windows"),
+                constant.unwrap_or_else(identity),
+            ),
+        }
+    }
+
+    /// Enforces that the point is not of small order, that is, that it does
+    /// not become the identity when multiplied by the cofactor, which must
+    /// be a power of two.
+    pub fn assert_not_small_order<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        assert!(C::cofactor().is_power_of_two());
+
+        // Points of order 2 have x = 0, so if the point multiplied by half
+        // of the cofactor has a nonzero x, the cofactor does not send it to
+        // the identity.
+        let mut point = self.clone();
+        for i in 1..C::cofactor().trailing_zeros() {
+            point = point.double(cs.namespace(|| format!("double {}", i)))?;
+        }
+        point.x.assert_nonzero(cs.namespace(|| "nonzero x"))
+    }
+
+    /// Exposes the coordinates of the point as two public inputs, `x` then
+    /// `y`, as encoded by [`inputs::edwards_point`](super::inputs::edwards_point).
+    pub fn inputize<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        self.x.inputize(cs.namespace(|| "x"))?;
+        self.y.inputize(cs.namespace(|| "y"))
+    }
+}
+
+/// The Jubjub curve, `-x² + y² = 1 - (10240/10241)·x²·y²` over the scalar
+/// field of BLS12-381.
+#[cfg(any(test, feature = "bls12_381"))]
+pub struct Jubjub;
+
+#[cfg(any(test, feature = "bls12_381"))]
+impl EmbeddedCurve<bls12_381::Scalar> for Jubjub {
+    fn a() -> bls12_381::Scalar {
+        -bls12_381::Scalar::one()
+    }
+
+    fn d() -> bls12_381::Scalar {
+        -(bls12_381::Scalar::from(10240) * bls12_381::Scalar::from(10241).invert().unwrap())
+    }
+
+    fn cofactor() -> u64 {
+        8
+    }
+
+    /// The cofactor times the point with `y = 11` and an even `x`.
+    fn generator() -> (bls12_381::Scalar, bls12_381::Scalar) {
+        let y = bls12_381::Scalar::from(11);
+        let x2 = (bls12_381::Scalar::one() - y.square())
+            * (Self::a() - Self::d() * y.square()).invert().unwrap();
+        let x = x2.sqrt().unwrap();
+        let x = if x.is_odd() { -x } else { x };
+
+        Self::mul((x, y), &[false, false, false, true])
+    }
+}
+
+/// The Bandersnatch curve, `-5·x² + y² = 1 + d·x²·y²` over the scalar field
+/// of BLS12-381.
+#[cfg(any(test, feature = "bls12_381"))]
+pub struct Bandersnatch;
+
+#[cfg(any(test, feature = "bls12_381"))]
+impl EmbeddedCurve<bls12_381::Scalar> for Bandersnatch {
+    fn a() -> bls12_381::Scalar {
+        -bls12_381::Scalar::from(5)
+    }
+
+    fn d() -> bls12_381::Scalar {
+        bls12_381::Scalar::from_str(
+            "45022363124591815672509500913686876175488063829319466900776701791074614335719",
+        )
+        .unwrap()
+    }
+
+    fn cofactor() -> u64 {
+        4
+    }
+
+    fn generator() -> (bls12_381::Scalar, bls12_381::Scalar) {
+        (
+            bls12_381::Scalar::from_str(
+                "18886178867200960497001835917649091219057080094937609519140440539760939937304",
+            )
+            .unwrap(),
+            bls12_381::Scalar::from_str(
+                "19188667384257783945677642223292697773471335439753913231509108946878080696678",
+            )
+            .unwrap(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bls12_381::Scalar;
+    use ff::PrimeField;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::{Bandersnatch, EdwardsPoint, EmbeddedCurve, Jubjub};
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::test::*;
+    use crate::ConstraintSystem;
+
+    fn is_on_curve<C: EmbeddedCurve<Scalar>>((x, y): (Scalar, Scalar)) -> bool {
+        C::a() * x.square() + y.square() == Scalar::one() + C::d() * x.square() * y.square()
+    }
+
+    fn check_curve<C: EmbeddedCurve<Scalar>>(order: &str) {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let g = C::generator();
+        assert!(is_on_curve::<C>(g));
+        let order = Scalar::from_str(order).unwrap().to_le_bits();
+        let order = order.iter().copied().collect::<Vec<_>>();
+        assert_eq!(C::mul(g, &order), (Scalar::zero(), Scalar::one()));
+
+        let bits = (0..20).map(|_| rng.next_u32() & 1 == 1).collect::<Vec<_>>();
+        let h = C::mul(g, &[true, false, true]);
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let p = EdwardsPoint::<Scalar, C>::witness(cs.namespace(|| "g"), Some(g)).unwrap();
+        let q = EdwardsPoint::<Scalar, C>::witness(cs.namespace(|| "h"), Some(h)).unwrap();
+        assert_eq!(cs.num_constraints(), 6);
+
+        let sum = p.add(cs.namespace(|| "g + h"), &q).unwrap();
+        assert_eq!(cs.num_constraints(), 12);
+        assert_eq!(sum.get_value(), Some(C::add(g, h)));
+        p.assert_not_small_order(cs.namespace(|| "g is not small"))
+            .unwrap();
+
+        let allocated = bits
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let product = p.mul(cs.namespace(|| "variable base"), &allocated).unwrap();
+        assert_eq!(product.get_value(), Some(C::mul(g, &bits)));
+        let table = C::window_table(g, 7);
+        let fixed = EdwardsPoint::<Scalar, C>::fixed_base_mul(
+            cs.namespace(|| "fixed base"),
+            &table,
+            &allocated,
+        )
+        .unwrap();
+        assert_eq!(fixed.get_value(), Some(C::mul(g, &bits)));
+        assert!(cs.is_satisfied());
+
+        // Constant bits are folded: a constant scalar only costs the
+        // doublings up to its last set bit and the additions of the others.
+        let constants = bits
+            .iter()
+            .map(|&b| Boolean::constant(b))
+            .collect::<Vec<_>>();
+        let before = cs.num_constraints();
+        let product = p
+            .mul(cs.namespace(|| "constant scalar"), &constants)
+            .unwrap();
+        assert_eq!(product.get_value(), Some(C::mul(g, &bits)));
+        let doublings = bits.iter().rposition(|&b| b).unwrap();
+        let additions = bits.iter().filter(|&&b| b).count() - 1;
+        assert_eq!(cs.num_constraints() - before, 6 * (doublings + additions));
+
+        let before = cs.num_constraints();
+        let fixed = EdwardsPoint::<Scalar, C>::fixed_base_mul(
+            cs.namespace(|| "constant fixed base"),
+            &table,
+            &constants,
+        )
+        .unwrap();
+        assert_eq!(fixed.get_value(), Some(C::mul(g, &bits)));
+        assert_eq!(cs.num_constraints() - before, 2);
+
+        let mut mixed = allocated.clone();
+        mixed[..9].clone_from_slice(&constants[..9]);
+        mixed[15] = Boolean::constant(bits[15]);
+        let product = p.mul(cs.namespace(|| "mixed scalar"), &mixed).unwrap();
+        assert_eq!(product.get_value(), Some(C::mul(g, &bits)));
+        let fixed =
+            EdwardsPoint::<Scalar, C>::fixed_base_mul(cs.namespace(|| "mixed"), &table, &mixed)
+                .unwrap();
+        assert_eq!(fixed.get_value(), Some(C::mul(g, &bits)));
+
+        let zero = vec![Boolean::constant(false); 4];
+        let identity = p.mul(cs.namespace(|| "zero"), &zero).unwrap();
+        assert_eq!(identity.get_value(), Some((Scalar::zero(), Scalar::one())));
+        assert!(cs.is_satisfied());
+
+        // A point off the curve.
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        EdwardsPoint::<Scalar, C>::witness(&mut cs, Some((g.0, g.1 + Scalar::one()))).unwrap();
+        assert!(!cs.is_satisfied());
+
+        // The point of order 2.
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let p = EdwardsPoint::<Scalar, C>::witness(
+            cs.namespace(|| "p"),
+            Some((Scalar::zero(), -Scalar::one())),
+        )
+        .unwrap();
+        assert!(p.assert_not_small_order(cs.namespace(|| "check")).is_err());
+    }
+
+    #[test]
+    fn test_jubjub() {
+        check_curve::<Jubjub>(
+            "6554484396890773809930967563523245729705921265872317281365359162392183254199",
+        );
+    }
+
+    #[test]
+    fn test_bandersnatch() {
+        check_curve::<Bandersnatch>(
+            "13108968793781547619861935127046491459309155893440570251786403306729687672801",
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Native encodings of the values that gadgets expose as public inputs.
+//!
+//! A verifier has to supply public inputs in exactly the order and packing
+//! that the circuit allocated them in. Each function here computes, outside
+//! of any constraint system, the inputs that the matching gadget allocates
+//! for a value, so that provers and verifiers compute them the same way:
+//!
+//! | Gadget                                         | Encoder              |
+//! |------------------------------------------------|----------------------|
+//! | [`AllocatedNum::inputize`]                     | [`num`]              |
+//! | [`multipack::pack_into_inputs`]                | [`bits`]             |
+//! | `pack_into_inputs` of a [`sha256`] digest      | [`sha256_digest`]    |
+//! | `pack_into_inputs` of a [`blake2s`] digest     | [`blake2s_digest`]   |
+//! | [`EdwardsPoint::inputize`]                     | [`edwards_point`]    |
+//!
+//! [`AllocatedNum::inputize`]: super::num::AllocatedNum::inputize
+//! [`multipack::pack_into_inputs`]: super::multipack::pack_into_inputs
+//! [`sha256`]: super::sha256::sha256
+//! [`blake2s`]: super::blake2s::blake2s
+//! [`EdwardsPoint::inputize`]: super::ecc::EdwardsPoint::inputize
+
+use ff::PrimeField;
+
+use super::multipack::{bytes_to_bits, bytes_to_bits_le, compute_multipacking};
+
+/// The input of a number exposed with `inputize`.
+pub fn num<S: PrimeField>(value: S) -> Vec<S> {
+    vec![value]
+}
+
+/// The inputs of bits exposed with `pack_into_inputs`, `CAPACITY` bits at a
+/// time from the first.
+pub fn bits<S: PrimeField>(bits: &[bool]) -> Vec<S> {
+    compute_multipacking(bits)
+}
+
+/// The inputs of a SHA-256 digest whose gadget output is packed with
+/// `pack_into_inputs`. The gadget outputs the bits of each byte from the
+/// most significant down.
+pub fn sha256_digest<S: PrimeField>(digest: &[u8]) -> Vec<S> {
+    bits(&bytes_to_bits(digest))
+}
+
+/// The inputs of a BLAKE2s digest whose gadget output is packed with
+/// `pack_into_inputs`. The gadget outputs the bits of each byte from the
+/// least significant up.
+pub fn blake2s_digest<S: PrimeField>(digest: &[u8]) -> Vec<S> {
+    bits(&bytes_to_bits_le(digest))
+}
+
+/// The inputs of a point exposed with `EdwardsPoint::inputize`.
+pub fn edwards_point<S: PrimeField>(point: (S, S)) -> Vec<S> {
+    vec![point.0, point.1]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::blake2s::blake2s;
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::ecc::{EdwardsPoint, EmbeddedCurve, Jubjub};
+    use crate::gadgets::multipack::pack_into_inputs;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::sha256::sha256;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::ConstraintSystem;
+    use blake2s_simd::Params as Blake2sParams;
+    use bls12_381::Scalar;
+    use sha2::{Digest, Sha256};
+
+    fn alloc_bytes(cs: &mut TestConstraintSystem<Scalar>, bytes: &[u8]) -> Vec<Boolean> {
+        bytes_to_bits(bytes)
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b))
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_encoders_match_gadgets() {
+        let preimage = b"public inputs must agree";
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let mut expected = vec![];
+
+        let value = Scalar::from(0xdead_beef);
+        AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(value))
+            .unwrap()
+            .inputize(cs.namespace(|| "num input"))
+            .unwrap();
+        expected.extend(num(value));
+
+        let input = alloc_bytes(&mut cs, &preimage[..]);
+        let digest = sha256(cs.namespace(|| "sha256"), &input).unwrap();
+        pack_into_inputs(cs.namespace(|| "sha256 inputs"), &digest).unwrap();
+        expected.extend(sha256_digest::<Scalar>(&Sha256::digest(&preimage[..])));
+
+        // The BLAKE2s gadget reads its input bits least significant first.
+        let input = bytes_to_bits_le(&preimage[..])
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("blake2s bit {}", i)), Some(b))
+                        .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let digest = blake2s(cs.namespace(|| "blake2s"), &input, b"12345678").unwrap();
+        pack_into_inputs(cs.namespace(|| "blake2s inputs"), &digest).unwrap();
+        let native = Blake2sParams::new()
+            .hash_length(32)
+            .personal(b"12345678")
+            .hash(&preimage[..]);
+        expected.extend(blake2s_digest::<Scalar>(native.as_bytes()));
+
+        let point = Jubjub::mul(Jubjub::generator(), &[true, false, true]);
+        EdwardsPoint::<Scalar, Jubjub>::witness(cs.namespace(|| "point"), Some(point))
+            .unwrap()
+            .inputize(cs.namespace(|| "point inputs"))
+            .unwrap();
+        expected.extend(edwards_point(point));
+
+        assert!(cs.is_satisfied());
+        assert_eq!(expected.len(), 1 + 2 + 2 + 2);
+        assert!(cs.verify(&expected));
+
+        let mut wrong = expected.clone();
+        wrong.swap(5, 6);
+        assert!(!cs.verify(&wrong));
+    }
+}
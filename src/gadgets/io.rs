@@ -0,0 +1,383 @@
+//! Typed declarations of the inputs of a circuit.
+//!
+//! The prover allocates the public inputs of a circuit in some order and
+//! packing, and the verifier has to encode the same values in the same order
+//! and packing, usually in another crate. [`circuit_io!`](crate::circuit_io) declares a struct
+//! of values once, and generates from it both the allocation of its fields
+//! in a circuit and their native encoding as public inputs, so that the two
+//! cannot drift apart:
+//!
+//! ```
+//! use bellman::circuit_io;
+//! use bellman::gadgets::io::Element;
+//!
+//! circuit_io! {
+//!     /// The public statement of a transfer.
+//!     #[derive(Clone)]
+//!     pub struct Transfer<S> as AllocatedTransfer {
+//!         pub root: Element<S>,
+//!         pub nullifier: [bool; 64],
+//!         pub amount: u32,
+//!     }
+//! }
+//! ```
+//!
+//! [`CircuitIo::alloc_inputs`] allocates the fields as public inputs in the
+//! order of their declaration, each under a namespace named after it, and
+//! returns them as an `AllocatedTransfer`, whose fields have the same names.
+//! [`CircuitIo::encode`] returns the public inputs of a value, with the
+//! encoders of [`inputs`]. [`CircuitIo::alloc`] allocates the
+//! same fields as private witnesses instead.
+//!
+//! A field can be of any type that implements [`CircuitValue`]: a field
+//! element, as an [`Element<S>`], `bool`, `u32`, or `[bool; N]` for `N` up to 32
+//! and the lengths of common digests up to 512. A struct whose fields
+//! do not mention `S` is declared without it, as in `pub struct Flags as
+//! AllocatedFlags { .. }`, and is then an input of circuits over any field.
+
+use ff::PrimeField;
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::inputs;
+use super::multipack::pack_into_inputs;
+use super::num::AllocatedNum;
+use super::uint32::UInt32;
+use crate::{ConstraintSystem, SynthesisError};
+
+#[doc(hidden)]
+pub use ff::PrimeField as __PrimeField;
+
+/// A type of value that can be a field of a
+/// [`circuit_io!`](crate::circuit_io) struct.
+pub trait CircuitValue<S: PrimeField> {
+    /// The gadget that holds the value in a circuit.
+    type Allocated;
+
+    /// Allocates `value` as a private witness.
+    fn alloc<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError>;
+
+    /// Allocates `value` and exposes it as public inputs.
+    fn alloc_input<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError>;
+
+    /// Appends the public inputs that `alloc_input` allocates for the value.
+    fn encode(&self, inputs: &mut Vec<S>);
+}
+
+/// A struct of values declared with [`circuit_io!`](crate::circuit_io).
+pub trait CircuitIo<S: PrimeField>: Sized {
+    /// The struct of the gadgets of the fields.
+    type Allocated;
+
+    /// Allocates the fields of `value` as private witnesses.
+    fn alloc<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError>;
+
+    /// Allocates the fields of `value` as public inputs.
+    fn alloc_inputs<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError>;
+
+    /// Returns the public inputs that `alloc_inputs` allocates for the value.
+    fn encode(&self) -> Vec<S>;
+}
+
+/// A field element, as a field of a [`circuit_io!`](crate::circuit_io)
+/// struct.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Element<S>(pub S);
+
+impl<S: PrimeField> From<S> for Element<S> {
+    fn from(value: S) -> Self {
+        Element(value)
+    }
+}
+
+impl<S: PrimeField> CircuitValue<S> for Element<S> {
+    type Allocated = AllocatedNum<S>;
+
+    fn alloc<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError> {
+        AllocatedNum::alloc(cs, || {
+            value
+                .map(|value| value.0)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })
+    }
+
+    fn alloc_input<CS: ConstraintSystem<S>>(
+        mut cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError> {
+        let num = Self::alloc(cs.namespace(|| "value"), value)?;
+        num.inputize(cs.namespace(|| "input"))?;
+        Ok(num)
+    }
+
+    fn encode(&self, out: &mut Vec<S>) {
+        out.extend(inputs::num(self.0));
+    }
+}
+
+impl<S: PrimeField> CircuitValue<S> for bool {
+    type Allocated = Boolean;
+
+    fn alloc<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError> {
+        AllocatedBit::alloc(cs, value.copied()).map(Boolean::from)
+    }
+
+    fn alloc_input<CS: ConstraintSystem<S>>(
+        mut cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError> {
+        let bit = <Self as CircuitValue<S>>::alloc(cs.namespace(|| "value"), value)?;
+        pack_into_inputs(cs.namespace(|| "input"), std::slice::from_ref(&bit))?;
+        Ok(bit)
+    }
+
+    fn encode(&self, out: &mut Vec<S>) {
+        out.extend(inputs::bits::<S>(&[*self]));
+    }
+}
+
+/// The bits of a `u32` as `UInt32` holds them, from the least significant.
+fn u32_bits(value: u32) -> Vec<bool> {
+    (0..32).map(|i| (value >> i) & 1 == 1).collect()
+}
+
+impl<S: PrimeField> CircuitValue<S> for u32 {
+    type Allocated = UInt32;
+
+    fn alloc<CS: ConstraintSystem<S>>(
+        cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError> {
+        UInt32::alloc(cs, value.copied())
+    }
+
+    fn alloc_input<CS: ConstraintSystem<S>>(
+        mut cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Allocated, SynthesisError> {
+        let num = <Self as CircuitValue<S>>::alloc(cs.namespace(|| "value"), value)?;
+        pack_into_inputs(cs.namespace(|| "input"), &num.clone().into_bits())?;
+        Ok(num)
+    }
+
+    fn encode(&self, out: &mut Vec<S>) {
+        out.extend(inputs::bits::<S>(&u32_bits(*self)));
+    }
+}
+
+/// Implements [`CircuitValue`] for arrays of bits of the given lengths.
+macro_rules! impl_bit_arrays {
+    ($($n:expr),*) => {
+        $(
+            impl<S: PrimeField> CircuitValue<S> for [bool; $n] {
+                type Allocated = Vec<Boolean>;
+
+                fn alloc<CS: ConstraintSystem<S>>(
+                    mut cs: CS,
+                    value: Option<&Self>,
+                ) -> Result<Self::Allocated, SynthesisError> {
+                    (0..$n)
+                        .map(|i| {
+                            AllocatedBit::alloc(
+                                cs.namespace(|| format!("bit {}", i)),
+                                value.map(|v| v[i]),
+                            )
+                            .map(Boolean::from)
+                        })
+                        .collect()
+                }
+
+                fn alloc_input<CS: ConstraintSystem<S>>(
+                    mut cs: CS,
+                    value: Option<&Self>,
+                ) -> Result<Self::Allocated, SynthesisError> {
+                    let bits = <Self as CircuitValue<S>>::alloc(cs.namespace(|| "value"), value)?;
+                    pack_into_inputs(cs.namespace(|| "input"), &bits)?;
+                    Ok(bits)
+                }
+
+                fn encode(&self, out: &mut Vec<S>) {
+                    out.extend(inputs::bits::<S>(&self[..]));
+                }
+            }
+        )*
+    };
+}
+
+// Arrays of any length need const generics, which are more recent than the
+// minimum supported Rust, so the lengths of common digests and keys are
+// listed instead.
+impl_bit_arrays!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 48, 64, 96, 128, 160, 192, 224, 256, 384, 512
+);
+
+/// Declares a struct of circuit values, and implements [`CircuitIo`] for it.
+/// See the [module documentation](crate::gadgets::io).
+#[macro_export]
+macro_rules! circuit_io {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident<$s:ident> as $allocated:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name<$s> {
+            $($(#[$field_attr])* $field_vis $field: $ty,)*
+        }
+
+        $crate::circuit_io!(@impl $vis ($name<$s>) $s $allocated { $($field_vis $field: $ty,)* });
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident as $allocated:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($(#[$field_attr])* $field_vis $field: $ty,)*
+        }
+
+        $crate::circuit_io!(@impl $vis ($name) S $allocated { $($field_vis $field: $ty,)* });
+    };
+    (@impl $vis:vis ($self_ty:ty) $s:ident $allocated:ident {
+        $($field_vis:vis $field:ident : $ty:ty,)*
+    }) => {
+        /// The gadgets of the fields of the declared struct.
+        $vis struct $allocated<$s: $crate::gadgets::io::__PrimeField> {
+            $($field_vis $field: <$ty as $crate::gadgets::io::CircuitValue<$s>>::Allocated,)*
+        }
+
+        impl<$s: $crate::gadgets::io::__PrimeField> $crate::gadgets::io::CircuitIo<$s> for $self_ty {
+            type Allocated = $allocated<$s>;
+
+            fn alloc<CS: $crate::ConstraintSystem<$s>>(
+                mut cs: CS,
+                value: ::std::option::Option<&Self>,
+            ) -> ::std::result::Result<Self::Allocated, $crate::SynthesisError> {
+                ::std::result::Result::Ok($allocated {
+                    $($field: <$ty as $crate::gadgets::io::CircuitValue<$s>>::alloc(
+                        $crate::ConstraintSystem::namespace(&mut cs, || stringify!($field)),
+                        value.map(|value| &value.$field),
+                    )?,)*
+                })
+            }
+
+            fn alloc_inputs<CS: $crate::ConstraintSystem<$s>>(
+                mut cs: CS,
+                value: ::std::option::Option<&Self>,
+            ) -> ::std::result::Result<Self::Allocated, $crate::SynthesisError> {
+                ::std::result::Result::Ok($allocated {
+                    $($field: <$ty as $crate::gadgets::io::CircuitValue<$s>>::alloc_input(
+                        $crate::ConstraintSystem::namespace(&mut cs, || stringify!($field)),
+                        value.map(|value| &value.$field),
+                    )?,)*
+                })
+            }
+
+            fn encode(&self) -> ::std::vec::Vec<$s> {
+                let mut inputs = ::std::vec::Vec::new();
+                $(<$ty as $crate::gadgets::io::CircuitValue<$s>>::encode(&self.$field, &mut inputs);)*
+                inputs
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+
+    circuit_io! {
+        #[derive(Clone)]
+        pub struct Statement<S> as AllocatedStatement {
+            pub root: Element<S>,
+            pub flag: bool,
+            pub nullifier: [bool; 384],
+            pub amount: u32,
+        }
+    }
+
+    circuit_io! {
+        struct Flags as AllocatedFlags {
+            first: bool,
+            second: bool,
+        }
+    }
+
+    #[test]
+    fn test_circuit_io() {
+        let mut nullifier = [false; 384];
+        for (i, bit) in nullifier.iter_mut().enumerate() {
+            *bit = i % 3 == 0;
+        }
+        let statement = Statement {
+            root: Scalar::from(12345).into(),
+            flag: true,
+            nullifier,
+            amount: 0xdead_beef,
+        };
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let allocated =
+            Statement::alloc_inputs(cs.namespace(|| "statement"), Some(&statement)).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(allocated.root.get_value(), Some(statement.root.0));
+        assert_eq!(allocated.flag.get_value(), Some(true));
+        assert_eq!(allocated.nullifier.len(), 384);
+        assert_eq!(allocated.amount.into_bits().len(), 32);
+
+        // The inputs are in the order of the fields, the nullifier taking
+        // two of them.
+        let inputs = statement.encode();
+        assert_eq!(inputs.len(), 1 + 1 + 2 + 1);
+        assert_eq!(cs.num_inputs(), 1 + inputs.len());
+        assert!(cs.verify(&inputs));
+
+        let mut other = statement.clone();
+        other.amount += 1;
+        assert!(!cs.verify(&other.encode()));
+
+        // As witnesses, the same fields allocate no input.
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let allocated = Statement::alloc(cs.namespace(|| "statement"), Some(&statement)).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 1);
+        assert_eq!(allocated.root.get_value(), Some(statement.root.0));
+
+        let flags = Flags {
+            first: false,
+            second: true,
+        };
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let allocated = Flags::alloc_inputs(&mut cs, Some(&flags)).unwrap();
+        assert_eq!(allocated.first.get_value(), Some(false));
+        assert_eq!(allocated.second.get_value(), Some(true));
+        assert!(cs.verify(&CircuitIo::<Scalar>::encode(&flags)));
+        assert_eq!(
+            CircuitIo::<Scalar>::encode(&flags),
+            vec![Scalar::zero(), Scalar::one()]
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! Circuits for the Merkle mountain ranges of [`crate::mmr`].
+//!
+//! An [`MmrState`] holds the number of leaves and the peaks of a range of
+//! fixed depth. [`MmrState::append`] computes the state after appending a
+//! leaf, and [`MmrState::enforce_membership`] checks the path of a leaf to
+//! one of the peaks, so that a circuit can take the commitment of one epoch
+//! as input and expose the commitment of the next.
+
+use ff::PrimeField;
+
+use crate::mmr::{Mmr, MmrProof};
+use crate::poseidon::PoseidonParams;
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::{AllocatedBit, Boolean};
+use super::num::AllocatedNum;
+use super::poseidon;
+use super::Assignment;
+
+/// The state of a Merkle mountain range in a circuit.
+#[derive(Clone)]
+pub struct MmrState<S: PrimeField> {
+    /// The number of leaves.
+    pub len: AllocatedNum<S>,
+    /// The peak of each height, or zero where there is none.
+    pub peaks: Vec<AllocatedNum<S>>,
+}
+
+/// Returns `value`, or `zero` if `condition` is false.
+fn select<S, CS>(
+    mut cs: CS,
+    condition: &Boolean,
+    value: &AllocatedNum<S>,
+) -> Result<AllocatedNum<S>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    let result = AllocatedNum::alloc(cs.namespace(|| "selection"), || {
+        if *condition.get_value().get()? {
+            Ok(*value.get_value().get()?)
+        } else {
+            Ok(S::zero())
+        }
+    })?;
+    cs.enforce(
+        || "selection constraint",
+        |_| condition.lc(CS::one(), S::one()),
+        |lc| lc + value.get_variable(),
+        |lc| lc + result.get_variable(),
+    );
+    Ok(result)
+}
+
+/// Hashes the leaf `leaf`, as [`crate::mmr::hash_leaf`] does.
+pub fn hash_leaf<S, CS>(
+    cs: CS,
+    params: &PoseidonParams<S>,
+    leaf: &AllocatedNum<S>,
+) -> Result<AllocatedNum<S>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    poseidon::hash(cs, params, std::slice::from_ref(leaf))
+}
+
+/// Hashes the node with children `left` and `right`, as
+/// [`crate::mmr::hash_node`] does.
+pub fn hash_node<S, CS>(
+    cs: CS,
+    params: &PoseidonParams<S>,
+    left: &AllocatedNum<S>,
+    right: &AllocatedNum<S>,
+) -> Result<AllocatedNum<S>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    poseidon::hash(cs, params, &[left.clone(), right.clone()])
+}
+
+impl<S: PrimeField> MmrState<S> {
+    /// Allocates the state of a range of the given depth, with the value of
+    /// `mmr` if it is known.
+    pub fn alloc<CS: ConstraintSystem<S>>(
+        mut cs: CS,
+        depth: usize,
+        mmr: Option<&Mmr<S>>,
+    ) -> Result<Self, SynthesisError> {
+        if let Some(mmr) = mmr {
+            assert_eq!(mmr.depth(), depth);
+        }
+        let peaks = mmr.map(|mmr| mmr.peaks());
+
+        let len = AllocatedNum::alloc(cs.namespace(|| "len"), || Ok(S::from(mmr.get()?.len())))?;
+        let peaks = (0..depth)
+            .map(|height| {
+                AllocatedNum::alloc(cs.namespace(|| format!("peak {}", height)), || {
+                    Ok(peaks.get()?[height])
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(MmrState { len, peaks })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.peaks.len()
+    }
+
+    /// Returns the commitment to the state, as [`Mmr::commitment`] does.
+    pub fn commitment<CS: ConstraintSystem<S>>(
+        &self,
+        cs: CS,
+        params: &PoseidonParams<S>,
+    ) -> Result<AllocatedNum<S>, SynthesisError> {
+        let mut message = vec![self.len.clone()];
+        message.extend(self.peaks.iter().cloned());
+        poseidon::hash(cs, params, &message)
+    }
+
+    /// Decomposes the number of leaves into `depth` bits, which also shows
+    /// that it is below `2^depth`.
+    fn len_bits<CS: ConstraintSystem<S>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        let len = self.len.get_value().map(|len| len.to_repr());
+        let bits = (0..self.depth())
+            .map(|i| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("len bit {}", i)),
+                    len.as_ref()
+                        .map(|repr| (repr.as_ref()[i / 8] >> (i % 8)) & 1 == 1),
+                )
+                .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut coeff = S::one();
+        let mut sum = LinearCombination::zero();
+        for bit in &bits {
+            sum = sum + &bit.lc(CS::one(), coeff);
+            coeff = coeff.double();
+        }
+        cs.enforce(
+            || "len decomposition",
+            |_| sum,
+            |lc| lc + CS::one(),
+            |lc| lc + self.len.get_variable(),
+        );
+        Ok(bits)
+    }
+
+    /// Returns the state after appending `leaf`. The circuit is not
+    /// satisfied if the range is full.
+    pub fn append<CS: ConstraintSystem<S>>(
+        &self,
+        mut cs: CS,
+        params: &PoseidonParams<S>,
+        leaf: &AllocatedNum<S>,
+    ) -> Result<Self, SynthesisError> {
+        let bits = self.len_bits(cs.namespace(|| "len bits"))?;
+
+        // The new leaf is carried up through the peaks, as one is through
+        // the bits of the number of leaves: it merges with the peaks of the
+        // heights whose bit is set, and takes the place of the first empty
+        // one.
+        let mut carry = hash_leaf(cs.namespace(|| "leaf"), params, leaf)?;
+        let mut active = Boolean::constant(true);
+        let mut peaks = vec![];
+        for (height, (peak, bit)) in self.peaks.iter().zip(bits.iter()).enumerate() {
+            let mut cs = cs.namespace(|| format!("height {}", height));
+
+            let merge = Boolean::and(cs.namespace(|| "merge"), &active, bit)?;
+            let place = Boolean::and(cs.namespace(|| "place"), &active, &bit.not())?;
+
+            // The peak is kept if the carry has stopped, taken by the carry
+            // if it is placed here, and emptied if it merges with it.
+            let kept = select(cs.namespace(|| "kept"), &active.not(), peak)?;
+            let placed = select(cs.namespace(|| "placed"), &place, &carry)?;
+            let new_peak = AllocatedNum::alloc(cs.namespace(|| "peak"), || {
+                Ok(*kept.get_value().get()? + placed.get_value().get()?)
+            })?;
+            cs.enforce(
+                || "peak constraint",
+                |lc| lc + kept.get_variable() + placed.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + new_peak.get_variable(),
+            );
+            peaks.push(new_peak);
+
+            let merged = hash_node(cs.namespace(|| "node"), params, peak, &carry)?;
+            let (next, _) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "carry"),
+                &carry,
+                &merged,
+                &merge,
+            )?;
+            carry = next;
+            active = merge;
+        }
+        Boolean::enforce_equal(
+            cs.namespace(|| "capacity"),
+            &active,
+            &Boolean::constant(false),
+        )?;
+
+        let len = AllocatedNum::alloc(cs.namespace(|| "len"), || {
+            Ok(*self.len.get_value().get()? + S::one())
+        })?;
+        cs.enforce(
+            || "len constraint",
+            |lc| lc + self.len.get_variable() + CS::one(),
+            |lc| lc + CS::one(),
+            |lc| lc + len.get_variable(),
+        );
+
+        Ok(MmrState { len, peaks })
+    }
+
+    /// Enforces that `leaf` is in the range, with the path of `proof` if it
+    /// is known. The path is padded to the height of the highest peak, so
+    /// that the circuit does not depend on the height of the leaf's peak.
+    pub fn enforce_membership<CS: ConstraintSystem<S>>(
+        &self,
+        mut cs: CS,
+        params: &PoseidonParams<S>,
+        leaf: &AllocatedNum<S>,
+        proof: Option<&MmrProof<S>>,
+    ) -> Result<(), SynthesisError> {
+        let len_bits = self.len_bits(cs.namespace(|| "len bits"))?;
+
+        // The height of the peak, as one selector per height.
+        let selectors = (0..self.depth())
+            .map(|height| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("height {} selector", height)),
+                    proof.map(|proof| proof.height() == height),
+                )
+                .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        cs.enforce(
+            || "one height",
+            |_| {
+                selectors.iter().fold(LinearCombination::zero(), |lc, s| {
+                    lc + &s.lc(CS::one(), S::one())
+                })
+            },
+            |lc| lc + CS::one(),
+            |lc| lc + CS::one(),
+        );
+
+        let mut node = hash_leaf(cs.namespace(|| "leaf"), params, leaf)?;
+        for (height, ((peak, selector), len_bit)) in self
+            .peaks
+            .iter()
+            .zip(selectors.iter())
+            .zip(len_bits.iter())
+            .enumerate()
+        {
+            let mut cs = cs.namespace(|| format!("height {}", height));
+
+            // At the height of the peak, the peak exists and is the node.
+            cs.enforce(
+                || "peak exists",
+                |_| selector.lc(CS::one(), S::one()),
+                |_| len_bit.not().lc(CS::one(), S::one()),
+                |lc| lc,
+            );
+            cs.enforce(
+                || "peak matches",
+                |_| selector.lc(CS::one(), S::one()),
+                |lc| lc + node.get_variable() - peak.get_variable(),
+                |lc| lc,
+            );
+
+            if height + 1 == self.depth() {
+                break;
+            }
+
+            let sibling = AllocatedNum::alloc(cs.namespace(|| "sibling"), || {
+                let proof = proof.get()?;
+                Ok(proof.siblings.get(height).copied().unwrap_or_else(S::zero))
+            })?;
+            let is_right = Boolean::from(AllocatedBit::alloc(
+                cs.namespace(|| "is right"),
+                proof.map(|proof| proof.is_right(height)),
+            )?);
+            let (left, right) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "order"),
+                &node,
+                &sibling,
+                &is_right,
+            )?;
+            node = hash_node(cs.namespace(|| "node"), params, &left, &right)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+
+    #[test]
+    fn test_mmr_append() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        let mut mmr = Mmr::new(params.clone(), 3);
+
+        // One epoch per append, each circuit going from the commitment of
+        // the previous epoch to the next.
+        for i in 0..8u64 {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let state = MmrState::alloc(cs.namespace(|| "state"), 3, Some(&mmr)).unwrap();
+            let before = state
+                .commitment(cs.namespace(|| "before"), &params)
+                .unwrap();
+            assert_eq!(before.get_value(), Some(mmr.commitment()));
+
+            let leaf = Scalar::from(100 + i);
+            let num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+            let next = state
+                .append(cs.namespace(|| "append"), &params, &num)
+                .unwrap();
+            let after = next.commitment(cs.namespace(|| "after"), &params).unwrap();
+
+            if mmr.append(leaf).is_some() {
+                assert!(cs.is_satisfied());
+                assert_eq!(after.get_value(), Some(mmr.commitment()));
+            } else {
+                // The range is full.
+                assert_eq!(i, 7);
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("append/capacity/enforce equal to zero")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_mmr_membership() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        let mut mmr = Mmr::new(params.clone(), 3);
+        for i in 0..6u64 {
+            mmr.append(Scalar::from(i));
+        }
+
+        for i in 0..6u64 {
+            for leaf in [Scalar::from(i), Scalar::from(i + 6)].iter() {
+                let mut cs = TestConstraintSystem::<Scalar>::new();
+                let state = MmrState::alloc(cs.namespace(|| "state"), 3, Some(&mmr)).unwrap();
+                let num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(*leaf)).unwrap();
+                let proof = mmr.proof(i).unwrap();
+                state
+                    .enforce_membership(cs.namespace(|| "membership"), &params, &num, Some(&proof))
+                    .unwrap();
+                assert_eq!(cs.is_satisfied(), *leaf == Scalar::from(i));
+            }
+        }
+
+        // A path to an empty peak.
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let state = MmrState::alloc(cs.namespace(|| "state"), 3, Some(&mmr)).unwrap();
+        let num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(Scalar::zero())).unwrap();
+        let proof = MmrProof {
+            index: 0,
+            siblings: vec![],
+        };
+        state
+            .enforce_membership(cs.namespace(|| "membership"), &params, &num, Some(&proof))
+            .unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
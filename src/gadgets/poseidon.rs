@@ -0,0 +1,408 @@
+//! Circuits for the [Poseidon] permutation and hash of [`crate::poseidon`].
+//!
+//! Within a permutation only the S-boxes are allocated: the round constants
+//! and the MDS matrix are folded into linear combinations of the S-box
+//! outputs, so a full round costs `width` S-boxes and a partial round one.
+//! The outputs are allocated once the permutation is done.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458
+
+use ff::PrimeField;
+
+use crate::poseidon::PoseidonParams;
+use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+use super::num::AllocatedNum;
+
+/// A state element, as a linear combination without repeated variables.
+#[derive(Clone)]
+struct Element<S: PrimeField> {
+    terms: Vec<(Variable, S)>,
+    value: Option<S>,
+}
+
+impl<S: PrimeField> Element<S> {
+    fn from_num(num: &AllocatedNum<S>) -> Self {
+        Element {
+            terms: vec![(num.get_variable(), S::one())],
+            value: num.get_value(),
+        }
+    }
+
+    fn add_term(&mut self, var: Variable, coeff: S) {
+        match self
+            .terms
+            .iter_mut()
+            .find(|(other, _)| other.get_unchecked() == var.get_unchecked())
+        {
+            Some((_, c)) => *c += coeff,
+            None => self.terms.push((var, coeff)),
+        }
+    }
+
+    fn add_constant<CS: ConstraintSystem<S>>(&mut self, constant: S) {
+        self.add_term(CS::one(), constant);
+        self.value = self.value.map(|v| v + constant);
+    }
+
+    fn add_scaled(&mut self, other: &Self, coeff: S) {
+        for &(var, c) in &other.terms {
+            self.add_term(var, c * coeff);
+        }
+        self.value = match (self.value, other.value) {
+            (Some(v), Some(o)) => Some(v + o * coeff),
+            _ => None,
+        };
+    }
+
+    fn lc(&self) -> LinearCombination<S> {
+        self.terms
+            .iter()
+            .fold(LinearCombination::zero(), |lc, &(var, coeff)| {
+                lc + (coeff, var)
+            })
+    }
+
+    fn mul<CS: ConstraintSystem<S>>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a * b),
+            _ => None,
+        };
+        let product = cs.alloc(
+            || "product",
+            || value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(
+            || "multiplication",
+            |_| self.lc(),
+            |_| other.lc(),
+            |lc| lc + product,
+        );
+        Ok(Element {
+            terms: vec![(product, S::one())],
+            value,
+        })
+    }
+
+    /// Raises the element to `alpha` by square-and-multiply.
+    fn pow<CS: ConstraintSystem<S>>(&self, mut cs: CS, alpha: u64) -> Result<Self, SynthesisError> {
+        let bits = 64 - alpha.leading_zeros();
+        let mut acc = self.clone();
+        for (i, bit) in (0..bits - 1)
+            .rev()
+            .map(|i| (alpha >> i) & 1 == 1)
+            .enumerate()
+        {
+            acc = acc.mul(cs.namespace(|| format!("square {}", i)), &acc)?;
+            if bit {
+                acc = acc.mul(cs.namespace(|| format!("multiply {}", i)), self)?;
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Allocates a number constrained to equal the element.
+    fn alloc<CS: ConstraintSystem<S>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<AllocatedNum<S>, SynthesisError> {
+        let num = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+            self.value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "allocation",
+            |_| self.lc(),
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+        Ok(num)
+    }
+}
+
+fn permute_elements<S, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<S>,
+    state: &mut [Element<S>],
+) -> Result<(), SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    assert_eq!(state.len(), params.width());
+
+    for (round, constants) in params.round_constants().iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+        for (s, c) in state.iter_mut().zip(constants.iter()) {
+            s.add_constant::<CS>(*c);
+        }
+
+        let sboxes = if params.is_full_round(round) {
+            state.len()
+        } else {
+            1
+        };
+        for (i, s) in state.iter_mut().enumerate().take(sboxes) {
+            *s = s.pow(cs.namespace(|| format!("s-box {}", i)), params.alpha())?;
+        }
+
+        let mixed = params
+            .mds()
+            .iter()
+            .map(|row| {
+                let mut acc = Element {
+                    terms: vec![],
+                    value: Some(S::zero()),
+                };
+                for (m, s) in row.iter().zip(state.iter()) {
+                    acc.add_scaled(s, *m);
+                }
+                acc
+            })
+            .collect::<Vec<_>>();
+        state.clone_from_slice(&mixed);
+    }
+
+    Ok(())
+}
+
+/// Applies the permutation to `state`, as [`PoseidonParams::permute`] does.
+pub fn permute<S, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<S>,
+    state: &[AllocatedNum<S>],
+) -> Result<Vec<AllocatedNum<S>>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    let mut elements = state.iter().map(Element::from_num).collect::<Vec<_>>();
+    permute_elements(cs.namespace(|| "permutation"), params, &mut elements)?;
+    elements
+        .iter()
+        .enumerate()
+        .map(|(i, e)| e.alloc(cs.namespace(|| format!("output {}", i))))
+        .collect()
+}
+
+/// Hashes a fixed-length message, as [`PoseidonParams::hash`] does.
+pub fn hash<S, CS>(
+    mut cs: CS,
+    params: &PoseidonParams<S>,
+    message: &[AllocatedNum<S>],
+) -> Result<AllocatedNum<S>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    let zero = Element {
+        terms: vec![],
+        value: Some(S::zero()),
+    };
+    let mut state = vec![zero; params.width()];
+    state[0].add_constant::<CS>(S::from(message.len() as u64));
+
+    let rate = params.width() - 1;
+    for (i, chunk) in message.chunks(rate).enumerate() {
+        if i > 0 {
+            permute_elements(cs.namespace(|| format!("absorb {}", i)), params, &mut state)?;
+        }
+        for (s, m) in state[1..].iter_mut().zip(chunk) {
+            s.add_scaled(&Element::from_num(m), S::one());
+        }
+    }
+    permute_elements(cs.namespace(|| "squeeze"), params, &mut state)?;
+
+    state[1].alloc(cs.namespace(|| "digest"))
+}
+
+/// A duplex sponge in the circuit, as [`crate::poseidon::Sponge`] is
+/// natively: it absorbs and squeezes the same values in the same order.
+pub struct Sponge<S: PrimeField> {
+    params: PoseidonParams<S>,
+    state: Vec<Element<S>>,
+    absorbed: usize,
+    squeezed: Option<usize>,
+    permutations: usize,
+}
+
+impl<S: PrimeField> Sponge<S> {
+    /// Creates a sponge whose capacity element is the constant `domain`.
+    pub fn new<CS: ConstraintSystem<S>>(params: PoseidonParams<S>, domain: S) -> Self {
+        let zero = Element {
+            terms: vec![],
+            value: Some(S::zero()),
+        };
+        let mut state = vec![zero; params.width()];
+        state[0].add_constant::<CS>(domain);
+
+        Sponge {
+            params,
+            state,
+            absorbed: 0,
+            squeezed: None,
+            permutations: 0,
+        }
+    }
+
+    fn rate(&self) -> usize {
+        self.params.width() - 1
+    }
+
+    fn permute<CS: ConstraintSystem<S>>(&mut self, mut cs: CS) -> Result<(), SynthesisError> {
+        let permutation = self.permutations;
+        permute_elements(
+            cs.namespace(|| format!("permutation {}", permutation)),
+            &self.params,
+            &mut self.state,
+        )?;
+        self.permutations += 1;
+        self.absorbed = 0;
+        Ok(())
+    }
+
+    /// Absorbs `value`, permuting first if the rate is full or the sponge
+    /// was squeezed.
+    pub fn absorb<CS: ConstraintSystem<S>>(
+        &mut self,
+        cs: CS,
+        value: &AllocatedNum<S>,
+    ) -> Result<(), SynthesisError> {
+        if self.absorbed == self.rate() || self.squeezed.is_some() {
+            self.permute(cs)?;
+            self.squeezed = None;
+        }
+
+        self.state[1 + self.absorbed].add_scaled(&Element::from_num(value), S::one());
+        self.absorbed += 1;
+        Ok(())
+    }
+
+    /// Squeezes an element, permuting first unless the previous permutation
+    /// still has a squeezed element left.
+    pub fn squeeze<CS: ConstraintSystem<S>>(
+        &mut self,
+        mut cs: CS,
+    ) -> Result<AllocatedNum<S>, SynthesisError> {
+        let position = match self.squeezed {
+            Some(position) if position < self.rate() => position,
+            _ => {
+                self.permute(cs.namespace(|| "permute"))?;
+                0
+            }
+        };
+
+        self.squeezed = Some(position + 1);
+        let output = self.state[1 + position].alloc(cs.namespace(|| "output"))?;
+        // Later permutations use the output rather than its combination.
+        self.state[1 + position] = Element::from_num(&output);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+    use ff::Field;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_poseidon_matches_native() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = PoseidonParams::<Scalar>::for_width(3);
+
+        for len in 0..6usize {
+            let message = (0..len)
+                .map(|_| Scalar::random(&mut rng))
+                .collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let nums = message
+                .iter()
+                .enumerate()
+                .map(|(i, m)| AllocatedNum::alloc(cs.namespace(|| format!("m {}", i)), || Ok(*m)))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let digest = hash(cs.namespace(|| "hash"), &params, &nums).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(digest.get_value(), Some(params.hash(&message)));
+
+            // Three constraints per S-box, for the rounds of each of the
+            // permutations, and one for the digest.
+            let permutations = std::cmp::max(1, (len + 1) / 2);
+            let sboxes = 8 * 3 + 57;
+            assert_eq!(cs.num_constraints(), permutations * sboxes * 3 + 1);
+        }
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let mut state = vec![Scalar::one(), Scalar::zero(), Scalar::random(&mut rng)];
+        let nums = state
+            .iter()
+            .enumerate()
+            .map(|(i, s)| AllocatedNum::alloc(cs.namespace(|| format!("s {}", i)), || Ok(*s)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let permuted = permute(cs.namespace(|| "permute"), &params, &nums).unwrap();
+        params.permute(&mut state);
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            permuted.iter().map(|n| n.get_value()).collect::<Vec<_>>(),
+            state.into_iter().map(Some).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_poseidon_sponge_matches_native() {
+        use crate::poseidon;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        let domain = Scalar::from(7);
+
+        let mut native = poseidon::Sponge::new(params.clone(), domain);
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let mut sponge = Sponge::new::<TestConstraintSystem<Scalar>>(params, domain);
+
+        // Absorbing three elements fills the rate of two once, and squeezing
+        // three takes two more permutations.
+        for i in 0..3 {
+            let value = Scalar::random(&mut rng);
+            native.absorb(value);
+            let num =
+                AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(value)).unwrap();
+            sponge
+                .absorb(cs.namespace(|| format!("absorb {}", i)), &num)
+                .unwrap();
+        }
+        for i in 0..3 {
+            let output = sponge
+                .squeeze(cs.namespace(|| format!("squeeze {}", i)))
+                .unwrap();
+            assert_eq!(output.get_value(), Some(native.squeeze()));
+        }
+        let value = Scalar::random(&mut rng);
+        native.absorb(value);
+        let num = AllocatedNum::alloc(cs.namespace(|| "input 3"), || Ok(value)).unwrap();
+        sponge.absorb(cs.namespace(|| "absorb 3"), &num).unwrap();
+        let output = sponge.squeeze(cs.namespace(|| "squeeze 3")).unwrap();
+        assert_eq!(output.get_value(), Some(native.squeeze()));
+
+        assert!(cs.is_satisfied());
+        // Two more permutations to absorb after squeezing and squeeze again,
+        // each of three constraints per S-box, and one for each output.
+        let sboxes = 8 * 3 + 57;
+        assert_eq!(cs.num_constraints(), 5 * sboxes * 3 + 4);
+    }
+}
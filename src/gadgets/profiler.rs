@@ -0,0 +1,412 @@
+//! Where the constraints of a circuit come from.
+//!
+//! A [`ConstraintProfiler`] is a constraint system that only counts: for
+//! each namespace, the constraints enforced, the variables allocated and the
+//! terms of the linear combinations, without evaluating the witness. The
+//! resulting [`Profile`] is a tree of namespaces, where namespaces of the
+//! same name under the same parent are merged, and the costs of each node
+//! include those of its children.
+//!
+//! [`Profile`] displays as a table of the namespaces, the most expensive
+//! first, and [`Profile::write_json`] writes the tree in the `name`, `value`
+//! and `children` format of flame graph viewers such as d3-flame-graph, with
+//! the number of constraints as the value.
+
+use ff::PrimeField;
+use std::fmt;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::AddAssign;
+
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// What a part of a circuit costs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Costs {
+    pub constraints: usize,
+    pub inputs: usize,
+    pub aux: usize,
+    /// The number of terms in the `A`, `B` and `C` linear combinations of
+    /// the constraints.
+    pub terms: usize,
+}
+
+impl AddAssign for Costs {
+    fn add_assign(&mut self, other: Costs) {
+        self.constraints += other.constraints;
+        self.inputs += other.inputs;
+        self.aux += other.aux;
+        self.terms += other.terms;
+    }
+}
+
+/// The costs of a namespace, and of the namespaces within it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// The name of the namespace, empty for the root.
+    pub name: String,
+    /// The costs of the namespace itself, outside of its children.
+    pub own: Costs,
+    /// The namespaces within this one, in the order they were first
+    /// entered.
+    pub children: Vec<Profile>,
+}
+
+impl Profile {
+    fn new(name: String) -> Self {
+        Profile {
+            name,
+            own: Costs::default(),
+            children: vec![],
+        }
+    }
+
+    /// Returns the costs of the namespace, including its children.
+    pub fn total(&self) -> Costs {
+        let mut total = self.own;
+        for child in &self.children {
+            total += child.total();
+        }
+        total
+    }
+
+    /// Returns the profile of the namespace at `path`, relative to this
+    /// one, with names separated by `/`.
+    pub fn get(&self, path: &str) -> Option<&Profile> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |profile, name| {
+                profile.children.iter().find(|child| child.name == name)
+            })
+    }
+
+    /// Writes the tree as JSON, with an object for each namespace:
+    ///
+    /// ```text
+    /// {"name": "sha256", "value": 25840, "constraints": 25840, "inputs": 0,
+    ///  "aux": 25840, "terms": 120452, "children": [...]}
+    /// ```
+    ///
+    /// where `value` and the costs are totals, including the children. The
+    /// root is named `"root"`.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.write_json_node(&mut writer)?;
+        writeln!(writer)
+    }
+
+    fn write_json_node<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let total = self.total();
+        let name = if self.name.is_empty() {
+            "root"
+        } else {
+            &self.name
+        };
+        write!(
+            writer,
+            "{{\"name\": {}, \"value\": {}, \"constraints\": {}, \"inputs\": {}, \"aux\": {}, \"terms\": {}, \"children\": [",
+            json_string(name),
+            total.constraints,
+            total.constraints,
+            total.inputs,
+            total.aux,
+            total.terms
+        )?;
+        for (i, child) in self.children.iter().enumerate() {
+            if i > 0 {
+                write!(writer, ", ")?;
+            }
+            child.write_json_node(writer)?;
+        }
+        write!(writer, "]}}")
+    }
+
+    fn fmt_rows(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let total = self.total();
+        let name = if self.name.is_empty() {
+            "<root>"
+        } else {
+            &self.name
+        };
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>8} {:>12}  {:indent$}{}",
+            total.constraints,
+            total.aux,
+            total.inputs,
+            total.terms,
+            "",
+            name,
+            indent = 2 * depth
+        )?;
+
+        let mut children = self.children.iter().collect::<Vec<_>>();
+        children.sort_by_key(|child| std::cmp::Reverse(child.total().constraints));
+        for child in children {
+            child.fmt_rows(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Profile {
+    /// Formats the tree as a table, with the children of each namespace
+    /// below it, indented, the most constrained first.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>8} {:>12}  namespace",
+            "constraints", "aux", "inputs", "terms"
+        )?;
+        self.fmt_rows(f, 0)
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// A constraint system that records the [`Profile`] of a circuit.
+pub struct ConstraintProfiler<Scalar: PrimeField> {
+    root: Profile,
+    // The indices of the children from the root to the current namespace.
+    current: Vec<usize>,
+    num_inputs: usize,
+    num_aux: usize,
+    _marker: PhantomData<Scalar>,
+}
+
+impl<Scalar: PrimeField> ConstraintProfiler<Scalar> {
+    pub fn new() -> Self {
+        ConstraintProfiler {
+            root: Profile::new(String::new()),
+            current: vec![],
+            // The "one" input, which is not allocated by the circuit.
+            num_inputs: 1,
+            num_aux: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the profile recorded so far.
+    pub fn profile(&self) -> &Profile {
+        &self.root
+    }
+
+    pub fn into_profile(self) -> Profile {
+        self.root
+    }
+
+    fn current(&mut self) -> &mut Profile {
+        let mut profile = &mut self.root;
+        for &i in &self.current {
+            profile = &mut profile.children[i];
+        }
+        profile
+    }
+}
+
+impl<Scalar: PrimeField> Default for ConstraintProfiler<Scalar> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Scalar: PrimeField> ConstraintSystem<Scalar> for ConstraintProfiler<Scalar> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.current().own.aux += 1;
+        self.num_aux += 1;
+        Ok(Variable::new_unchecked(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.current().own.inputs += 1;
+        self.num_inputs += 1;
+        Ok(Variable::new_unchecked(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let terms = a(LinearCombination::zero()).as_ref().len()
+            + b(LinearCombination::zero()).as_ref().len()
+            + c(LinearCombination::zero()).as_ref().len();
+        let own = &mut self.current().own;
+        own.constraints += 1;
+        own.terms += terms;
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name = name_fn().into();
+        let parent = self.current();
+        let index = match parent.children.iter().position(|child| child.name == name) {
+            Some(index) => index,
+            None => {
+                parent.children.push(Profile::new(name));
+                parent.children.len() - 1
+            }
+        };
+        self.current.push(index);
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Synthesizes `circuit` and returns its profile.
+pub fn profile<Scalar, C>(circuit: C) -> Result<Profile, SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+{
+    let mut profiler = ConstraintProfiler::new();
+    circuit.synthesize(&mut profiler)?;
+    Ok(profiler.into_profile())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::sha256::sha256;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+
+    struct Hashes;
+
+    impl Circuit<Scalar> for Hashes {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc_input(|| "x", || Ok(Scalar::one()))?;
+            cs.enforce(
+                || "x is one",
+                |lc| lc + x,
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+
+            for i in 0..2 {
+                let mut cs = cs.namespace(|| format!("hash {}", i));
+                let bits = (0..8)
+                    .map(|j| {
+                        AllocatedBit::alloc(cs.namespace(|| format!("bit {}", j)), Some(false))
+                            .map(Boolean::from)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                sha256(cs.namespace(|| "sha256"), &bits)?;
+            }
+
+            let mut cs = cs.namespace(|| "squares");
+            for i in 0..3 {
+                cs.alloc(|| format!("{}", i), || Ok(Scalar::zero()))?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn constraint_profile() {
+        let profile = profile(Hashes).unwrap();
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        Hashes.synthesize(&mut cs).unwrap();
+        let total = profile.total();
+        assert_eq!(total.constraints, cs.num_constraints());
+        assert_eq!(total.inputs, cs.num_inputs() - 1);
+        assert_eq!(profile.own.constraints, 1);
+        assert_eq!(profile.own.terms, 3);
+
+        let names = profile
+            .children
+            .iter()
+            .map(|child| child.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["hash 0", "hash 1", "squares"]);
+        assert_eq!(profile.get("squares").unwrap().own.aux, 3);
+        let sha = profile.get("hash 1/sha256").unwrap().total();
+        assert_eq!(sha, profile.get("hash 0/sha256").unwrap().total());
+        assert_eq!(
+            profile.get("hash 1").unwrap().total().constraints,
+            sha.constraints + 8
+        );
+        assert!(profile.get("hash 2").is_none());
+
+        let report = profile.to_string();
+        let lines = report.lines().collect::<Vec<_>>();
+        assert!(lines[0].ends_with("namespace"));
+        assert!(lines[1].ends_with("<root>"));
+        assert!(lines[2].ends_with("  hash 0"));
+        assert!(lines.last().unwrap().ends_with("  squares"));
+
+        let mut json = vec![];
+        profile.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with(&format!(
+            "{{\"name\": \"root\", \"value\": {}, ",
+            total.constraints
+        )));
+        assert!(json.contains(
+            "{\"name\": \"squares\", \"value\": 0, \"constraints\": 0, \"inputs\": 0, \"aux\": 3, \"terms\": 0, \"children\": []}"
+        ));
+        assert_eq!(json_string("a\"b\\\n"), "\"a\\\"b\\\\\\u000a\"");
+    }
+
+    #[test]
+    fn merged_namespaces() {
+        // Entering a namespace again adds to it.
+        let mut profiler = ConstraintProfiler::<Scalar>::new();
+        for _ in 0..2 {
+            let mut cs = profiler.namespace(|| "loop");
+            cs.alloc(|| "x", || Ok(Scalar::zero())).unwrap();
+            cs.enforce(|| "x is zero", |lc| lc, |lc| lc, |lc| lc);
+        }
+        let profile = profiler.profile();
+        assert_eq!(profile.children.len(), 1);
+        assert_eq!(
+            profile.get("loop").unwrap().own,
+            Costs {
+                constraints: 2,
+                inputs: 0,
+                aux: 2,
+                terms: 0,
+            }
+        );
+    }
+}
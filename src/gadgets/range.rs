@@ -0,0 +1,222 @@
+//! Range checks collected during synthesis and enforced together.
+//!
+//! A circuit with thousands of range checks usually enforces each one where
+//! it is needed, by decomposing the value into `n` bits and constraining
+//! their sum to be the value, at `n + 1` constraints a check. [`RangeChecks`]
+//! instead records each check, and [`RangeChecks::enforce`] proves them all
+//! at the end of synthesis:
+//!
+//! - checks of the same linear combination are merged into the tightest
+//!   one, so that gadgets which each bound their operands do not pay twice
+//!   for a shared operand;
+//! - each remaining check of `n` bits allocates only its lower `n - 1` bits,
+//!   and constrains the rest of the value, which is a linear combination of
+//!   them, to be a bit, at `n` constraints a check.
+//!
+//! Lookup arguments, which share one table between all the checks, need a
+//! challenge drawn after the witness is fixed, which a Groth16 circuit
+//! cannot get; the checks here are sound on their own.
+
+use ff::PrimeField;
+use std::collections::btree_map::{BTreeMap, Entry};
+
+use super::boolean::AllocatedBit;
+use super::num::{AllocatedNum, Num};
+use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError};
+
+/// Identifies a linear combination, up to the order of its terms.
+type Key = Vec<(bool, usize, Vec<u8>)>;
+
+fn key<S: PrimeField>(lc: &LinearCombination<S>) -> Key {
+    let mut terms = BTreeMap::<(bool, usize), S>::new();
+    for (var, coeff) in lc.as_ref() {
+        let index = match var.get_unchecked() {
+            Index::Input(i) => (false, i),
+            Index::Aux(i) => (true, i),
+        };
+        *terms.entry(index).or_insert_with(S::zero) += coeff;
+    }
+    terms
+        .into_iter()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .map(|((aux, i), coeff)| (aux, i, coeff.to_repr().as_ref().to_vec()))
+        .collect()
+}
+
+struct Check<S: PrimeField> {
+    value: Option<S>,
+    lc: LinearCombination<S>,
+    bits: usize,
+}
+
+/// The range checks of a circuit, to be enforced together.
+pub struct RangeChecks<S: PrimeField> {
+    checks: Vec<Check<S>>,
+    index: BTreeMap<Key, usize>,
+}
+
+impl<S: PrimeField> Default for RangeChecks<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: PrimeField> RangeChecks<S> {
+    pub fn new() -> Self {
+        RangeChecks {
+            checks: vec![],
+            index: BTreeMap::new(),
+        }
+    }
+
+    /// Records that `num` must be less than `2^bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is more than the capacity of the field, as the check
+    /// would not bound anything.
+    pub fn check(&mut self, num: Num<S>, bits: usize) {
+        assert!(bits <= S::CAPACITY as usize);
+        let lc = num.lc(S::one());
+        match self.index.entry(key(&lc)) {
+            Entry::Occupied(entry) => {
+                let check = &mut self.checks[*entry.get()];
+                check.bits = std::cmp::min(check.bits, bits);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(self.checks.len());
+                self.checks.push(Check {
+                    value: num.get_value(),
+                    lc,
+                    bits,
+                });
+            }
+        }
+    }
+
+    /// Records that `num` must be less than `2^bits`.
+    pub fn check_num(&mut self, num: &AllocatedNum<S>, bits: usize) {
+        self.check(num.clone().into(), bits);
+    }
+
+    /// Returns the number of distinct checks recorded.
+    pub fn len(&self) -> usize {
+        self.checks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// Returns the number of constraints that [`enforce`](Self::enforce)
+    /// adds.
+    pub fn num_constraints(&self) -> usize {
+        self.checks
+            .iter()
+            .map(|check| std::cmp::max(check.bits, 1))
+            .sum()
+    }
+
+    /// Enforces every recorded check.
+    pub fn enforce<CS: ConstraintSystem<S>>(self, mut cs: CS) -> Result<(), SynthesisError> {
+        for (i, check) in self.checks.into_iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("range check {}", i));
+            let Check { value, lc, bits } = check;
+
+            if bits == 0 {
+                cs.enforce(|| "zero", |_| lc, |lc| lc + CS::one(), |lc| lc);
+                continue;
+            }
+
+            // The rest of the value above the lower bits, divided by the
+            // weight of the top bit, must itself be a bit.
+            let mut rest = lc;
+            let mut coeff = S::one();
+            let values =
+                value.map(|value| value.to_le_bits().into_iter().cloned().collect::<Vec<_>>());
+            for j in 0..bits - 1 {
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", j)),
+                    values.as_ref().map(|values| values[j]),
+                )?;
+                rest = rest - (coeff, bit.get_variable());
+                coeff = coeff.double();
+            }
+            let scale = coeff.invert().unwrap();
+            let top = LinearCombination::zero() + (scale, &rest);
+            cs.enforce(
+                || "top bit",
+                |lc| lc + &top,
+                |lc| lc + &top - CS::one(),
+                |lc| lc,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_range_checks() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let mut checks = RangeChecks::new();
+
+        let nums = (0..1000)
+            .map(|i| {
+                let value = Scalar::from(rng.next_u32() as u64 & 0xff);
+                AllocatedNum::alloc(cs.namespace(|| format!("num {}", i)), || Ok(value)).unwrap()
+            })
+            .collect::<Vec<_>>();
+        for num in &nums {
+            checks.check_num(num, 8);
+        }
+        // Checks of the same value merge into the tightest one.
+        checks.check_num(&nums[0], 16);
+        checks.check(Num::from(nums[1].clone()), 8);
+        assert_eq!(checks.len(), 1000);
+        assert_eq!(checks.num_constraints(), 8 * 1000);
+
+        checks.enforce(cs.namespace(|| "range checks")).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 8 * 1000);
+    }
+
+    #[test]
+    fn test_range_check_bounds() {
+        for &(bits, value, satisfied) in &[
+            (0, 0, true),
+            (0, 1, false),
+            (1, 1, true),
+            (1, 2, false),
+            (8, 255, true),
+            (8, 256, false),
+        ] {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let num =
+                AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Scalar::from(value))).unwrap();
+            let mut checks = RangeChecks::new();
+            checks.check_num(&num, bits);
+            checks.enforce(cs.namespace(|| "range checks")).unwrap();
+            assert_eq!(cs.is_satisfied(), satisfied, "{} < 2^{}", value, bits);
+        }
+
+        // A negative value is out of every range.
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(-Scalar::one())).unwrap();
+        let mut checks = RangeChecks::new();
+        checks.check_num(&num, Scalar::CAPACITY as usize);
+        checks.enforce(cs.namespace(|| "range checks")).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
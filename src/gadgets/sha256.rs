@@ -77,6 +77,51 @@ fn get_sha256_iv() -> Vec<UInt32> {
     IV.iter().map(|&v| UInt32::constant(v)).collect()
 }
 
+/// Compresses a block of constants into a constant state.
+fn compress(state: &mut [u32], block: &[u32]) {
+    let mut w = block.to_vec();
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w.push(
+            w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1),
+        );
+    }
+
+    let mut v = [0u32; 8];
+    v.copy_from_slice(state);
+    for i in 0..64 {
+        let [a, b, c, d, e, f, g, h] = v;
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(ROUND_CONSTANTS[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+        v = [
+            temp1.wrapping_add(temp2),
+            a,
+            b,
+            c,
+            d.wrapping_add(temp1),
+            e,
+            f,
+            g,
+        ];
+    }
+
+    for (s, v) in state.iter_mut().zip(v.iter()) {
+        *s = s.wrapping_add(*v);
+    }
+}
+
 fn sha256_compression_function<Scalar, CS>(
     cs: CS,
     input: &[Boolean],
@@ -94,6 +139,20 @@ where
         .map(|e| UInt32::from_bits_be(e))
         .collect::<Vec<_>>();
 
+    // Blocks of constants, such as fixed prefixes, are compressed natively.
+    let block = w
+        .iter()
+        .map(UInt32::as_constant)
+        .collect::<Option<Vec<_>>>();
+    let state = current_hash_value
+        .iter()
+        .map(UInt32::as_constant)
+        .collect::<Option<Vec<_>>>();
+    if let (Some(block), Some(mut state)) = (block, state) {
+        compress(&mut state, &block);
+        return Ok(state.into_iter().map(UInt32::constant).collect());
+    }
+
     // We can save some constraints by combining some of
     // the constraints in different u32 additions
     let mut cs = MultiEq::new(cs);
@@ -328,7 +387,53 @@ mod test {
         sha256_compression_function(cs.namespace(|| "sha256"), &input_bits, &iv).unwrap();
 
         assert!(cs.is_satisfied());
-        assert_eq!(cs.num_constraints() - 512, 25840);
+        assert_eq!(cs.num_constraints() - 512, 25833);
+    }
+
+    #[test]
+    fn test_constant_prefix() {
+        use sha2::{Digest, Sha256};
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let data: Vec<u8> = (0..96).map(|_| rng.next_u32() as u8).collect();
+        let expected = Sha256::digest(&data);
+
+        // The first block is a constant, and the rest is allocated.
+        let synthesize = |constant: usize| {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let input = data
+                .iter()
+                .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                .enumerate()
+                .map(|(i, b)| {
+                    if i < constant {
+                        Boolean::constant(b)
+                    } else {
+                        AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b))
+                            .unwrap()
+                            .into()
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let r = sha256(&mut cs, &input).unwrap();
+            assert!(cs.is_satisfied());
+            let r = r.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>();
+            let expected = expected
+                .iter()
+                .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                .collect::<Vec<_>>();
+            assert_eq!(r, expected);
+            cs.num_constraints() - (input.len() - constant)
+        };
+
+        let allocated = synthesize(0);
+        let prefixed = synthesize(512);
+        assert!(prefixed + 25000 < allocated);
+        assert_eq!(synthesize(data.len() * 8), 0);
     }
 
     #[test]
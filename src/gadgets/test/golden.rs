@@ -0,0 +1,370 @@
+//! Golden files of the synthesis of circuits.
+//!
+//! A refactor of gadget code should not change the circuit it synthesizes,
+//! nor the witness it computes. [`GoldenCs`] wraps another constraint system
+//! and records, in order, the path and value of every allocation and the
+//! path and hash of every constraint, into a [`Golden`]. A test writes it
+//! once to a file, and compares every later synthesis to it with
+//! [`assert_golden`], which reports the first allocation or constraint that
+//! differs.
+//!
+//! Golden files are text, one record per line, so that a reviewer can see
+//! what a change to a golden file changes:
+//!
+//! ```text
+//! golden v1
+//! input <value> <path>
+//! aux <value> <path>
+//! constraint <hash> <path>
+//! ```
+//!
+//! Values are the hexadecimal bytes of their canonical representation, or
+//! `-` when the wrapped constraint system did not compute them. Hashes are
+//! the first 16 bytes of a BLAKE2s hash of the constraint, normalized as in
+//! [`TestConstraintSystem::hash`](super::TestConstraintSystem::hash).
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::PrimeField;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use super::hash_lc;
+use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+const HEADER: &str = "golden v1";
+
+/// What a record of a [`Golden`] is of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Input,
+    Aux,
+    Constraint,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Input => "input",
+            Kind::Aux => "aux",
+            Kind::Constraint => "constraint",
+        }
+    }
+}
+
+/// An allocation or a constraint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub kind: Kind,
+    /// The value of an allocation, or the hash of a constraint.
+    pub digest: String,
+    pub path: String,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.kind.name(), self.digest, self.path)
+    }
+}
+
+/// The first difference between two syntheses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub index: usize,
+    /// The record of the golden file, if it has that many.
+    pub expected: Option<Record>,
+    /// The record of the new synthesis, if it has that many.
+    pub actual: Option<Record>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |record: &Option<Record>| match record {
+            Some(record) => record.to_string(),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "record {} differs: expected {}, got {}",
+            self.index,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// The records of a synthesis, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Golden {
+    pub records: Vec<Record>,
+}
+
+impl Golden {
+    /// Returns the first record at which `actual` differs from `self`.
+    pub fn diff(&self, actual: &Golden) -> Option<Mismatch> {
+        let len = std::cmp::max(self.records.len(), actual.records.len());
+        (0..len)
+            .find(|&i| self.records.get(i) != actual.records.get(i))
+            .map(|index| Mismatch {
+                index,
+                expected: self.records.get(index).cloned(),
+                actual: actual.records.get(index).cloned(),
+            })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        for record in &self.records {
+            writeln!(writer, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid("not a golden file"));
+        }
+
+        let records = lines
+            .map(|line| {
+                let line = line?;
+                let mut parts = line.splitn(3, ' ');
+                let kind = match parts.next() {
+                    Some("input") => Kind::Input,
+                    Some("aux") => Kind::Aux,
+                    Some("constraint") => Kind::Constraint,
+                    _ => return Err(invalid("unknown record")),
+                };
+                match (parts.next(), parts.next()) {
+                    (Some(digest), Some(path)) => Ok(Record {
+                        kind,
+                        digest: digest.to_string(),
+                        path: path.to_string(),
+                    }),
+                    _ => Err(invalid("truncated record")),
+                }
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Golden { records })
+    }
+}
+
+/// Compares `golden` to the golden file at `path`, and panics at the first
+/// difference. If the file does not exist, or the `BELLMAN_BLESS`
+/// environment variable is set, writes `golden` to it instead.
+pub fn assert_golden<P: AsRef<Path>>(path: P, golden: &Golden) {
+    let path = path.as_ref();
+    if std::env::var_os("BELLMAN_BLESS").is_some() || !path.exists() {
+        let mut bytes = vec![];
+        golden.write(&mut bytes).unwrap();
+        fs::write(path, bytes).unwrap();
+        return;
+    }
+
+    let expected = Golden::read(io::BufReader::new(fs::File::open(path).unwrap())).unwrap();
+    if let Some(mismatch) = expected.diff(golden) {
+        panic!("{}: {}", path.display(), mismatch);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A constraint system that records the synthesis of the one it wraps.
+pub struct GoldenCs<CS> {
+    inner: CS,
+    namespace: Vec<String>,
+    golden: Golden,
+}
+
+impl<CS> GoldenCs<CS> {
+    pub fn new(inner: CS) -> Self {
+        GoldenCs {
+            inner,
+            namespace: vec![],
+            golden: Golden::default(),
+        }
+    }
+
+    pub fn golden(&self) -> &Golden {
+        &self.golden
+    }
+
+    pub fn inner(&self) -> &CS {
+        &self.inner
+    }
+
+    /// Returns the wrapped constraint system and the records.
+    pub fn into_parts(self) -> (CS, Golden) {
+        (self.inner, self.golden)
+    }
+
+    fn record(&mut self, kind: Kind, digest: String, annotation: &str) {
+        let mut path = self.namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(annotation);
+        self.golden.records.push(Record { kind, digest, path });
+    }
+}
+
+impl<S: PrimeField, CS: ConstraintSystem<S>> ConstraintSystem<S> for GoldenCs<CS> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let annotation = annotation().into();
+        let mut value = None;
+        let var = self.inner.alloc(
+            || annotation.clone(),
+            || {
+                let v = f()?;
+                value = Some(hex(v.to_repr().as_ref()));
+                Ok(v)
+            },
+        )?;
+        self.record(Kind::Aux, value.unwrap_or_else(|| "-".into()), &annotation);
+        Ok(var)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let annotation = annotation().into();
+        let mut value = None;
+        let var = self.inner.alloc_input(
+            || annotation.clone(),
+            || {
+                let v = f()?;
+                value = Some(hex(v.to_repr().as_ref()));
+                Ok(v)
+            },
+        )?;
+        self.record(
+            Kind::Input,
+            value.unwrap_or_else(|| "-".into()),
+            &annotation,
+        );
+        Ok(var)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        let annotation = annotation().into();
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        let mut h = Blake2sParams::new().hash_length(16).to_state();
+        for lc in [&a, &b, &c].iter() {
+            hash_lc::<S>(lc.as_ref(), &mut h);
+        }
+        self.record(Kind::Constraint, hex(h.finalize().as_ref()), &annotation);
+
+        self.inner
+            .enforce(|| annotation, |lc| lc + &a, |lc| lc + &b, |lc| lc + &c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name = name_fn().into();
+        self.inner.get_root().push_namespace(|| name.clone());
+        self.namespace.push(name);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.inner.get_root().pop_namespace();
+        self.namespace.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+
+    fn synthesize(x: u64) -> (TestConstraintSystem<Scalar>, Golden) {
+        let mut cs = GoldenCs::new(TestConstraintSystem::<Scalar>::new());
+        let num = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Scalar::from(x))).unwrap();
+        let square = num.square(cs.namespace(|| "square")).unwrap();
+        square.inputize(cs.namespace(|| "out")).unwrap();
+        cs.into_parts()
+    }
+
+    #[test]
+    fn test_golden_cs() {
+        let (mut cs, golden) = synthesize(3);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.get("square/squared num"), Scalar::from(9));
+        assert_eq!(
+            golden
+                .records
+                .iter()
+                .map(|r| (r.kind, r.path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Kind::Aux, "x/num"),
+                (Kind::Aux, "square/squared num"),
+                (Kind::Constraint, "square/squaring constraint"),
+                (Kind::Input, "out/input variable"),
+                (Kind::Constraint, "out/enforce input is correct"),
+            ]
+        );
+
+        let mut bytes = vec![];
+        golden.write(&mut bytes).unwrap();
+        assert_eq!(Golden::read(&bytes[..]).unwrap(), golden);
+        assert_eq!(golden.diff(&synthesize(3).1), None);
+
+        // Another witness changes the values, but not the constraints.
+        let mismatch = golden.diff(&synthesize(4).1).unwrap();
+        assert_eq!(mismatch.index, 0);
+        assert_eq!(mismatch.expected.unwrap().path, "x/num");
+        let other = synthesize(4).1;
+        for (expected, actual) in golden.records.iter().zip(other.records.iter()) {
+            assert_eq!(
+                expected.kind == Kind::Constraint,
+                expected.digest == actual.digest
+            );
+        }
+
+        let mut truncated = golden.clone();
+        truncated.records.pop();
+        let mismatch = truncated.diff(&golden).unwrap();
+        assert_eq!((mismatch.index, mismatch.expected), (4, None));
+
+        let path = std::env::temp_dir().join(format!("bellman-golden-{}", std::process::id()));
+        assert_golden(&path, &golden);
+        assert_golden(&path, &synthesize(3).1);
+        let result = std::panic::catch_unwind(|| assert_golden(&path, &synthesize(5).1));
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -5,7 +5,8 @@ use ff::PrimeField;
 use crate::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 
 use std::collections::HashMap;
-use std::fmt::Write;
+use std::fmt::{self, Write};
+use std::panic::Location;
 
 use byteorder::{BigEndian, ByteOrder};
 use std::cmp::Ordering;
@@ -13,6 +14,8 @@ use std::collections::BTreeMap;
 
 use blake2s_simd::{Params as Blake2sParams, State as Blake2sState};
 
+pub mod golden;
+
 #[derive(Debug)]
 enum NamedObject {
     Constraint(usize),
@@ -32,6 +35,48 @@ pub struct TestConstraintSystem<Scalar: PrimeField> {
     )>,
     inputs: Vec<(Scalar, String)>,
     aux: Vec<(Scalar, String)>,
+    /// The calls that allocated the variables and enforced the constraints,
+    /// by path, with the `locations` feature.
+    locations: HashMap<String, &'static Location<'static>>,
+}
+
+/// A constraint that the assignment of a [`TestConstraintSystem`] does not
+/// satisfy, with the values its linear combinations evaluate to.
+#[derive(Clone, Debug)]
+pub struct Unsatisfied<Scalar: PrimeField> {
+    /// The namespace path of the constraint.
+    pub path: String,
+    /// The call that enforced the constraint, with the `locations` feature.
+    pub location: Option<&'static Location<'static>>,
+    pub a: Scalar,
+    pub b: Scalar,
+    pub c: Scalar,
+}
+
+impl<Scalar: PrimeField> PartialEq for Unsatisfied<Scalar> {
+    fn eq(&self, other: &Self) -> bool {
+        // `Location` has no `PartialEq` in the oldest Rust we support.
+        let location = |u: &Self| u.location.map(|l| (l.file(), l.line(), l.column()));
+        self.path == other.path
+            && location(self) == location(other)
+            && self.a == other.a
+            && self.b == other.b
+            && self.c == other.c
+    }
+}
+
+impl<Scalar: PrimeField> fmt::Display for Unsatisfied<Scalar> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:?} * {:?} != {:?}",
+            self.path, self.a, self.b, self.c
+        )?;
+        if let Some(location) = self.location {
+            write!(f, " (enforced at {})", location)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -149,6 +194,7 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
             constraints: vec![],
             inputs: vec![(Scalar::one(), "ONE".into())],
             aux: vec![],
+            locations: HashMap::new(),
         }
     }
 
@@ -158,7 +204,7 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         let negone = Scalar::one().neg();
 
         let powers_of_two = (0..Scalar::NUM_BITS)
-            .map(|i| Scalar::from_str("2").unwrap().pow_vartime(&[u64::from(i)]))
+            .map(|i| Scalar::from_str("2").unwrap().pow_vartime([u64::from(i)]))
             .collect::<Vec<_>>();
 
         let pp = |s: &mut String, lc: &LinearCombination<Scalar>| {
@@ -240,20 +286,63 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         s
     }
 
+    /// Returns the values of `A`, `B` and `C` of the constraint at `index`.
+    fn eval_constraint(&self, index: usize) -> (Scalar, Scalar, Scalar) {
+        let (a, b, c, _) = &self.constraints[index];
+        (
+            eval_lc::<Scalar>(a.as_ref(), &self.inputs, &self.aux),
+            eval_lc::<Scalar>(b.as_ref(), &self.inputs, &self.aux),
+            eval_lc::<Scalar>(c.as_ref(), &self.inputs, &self.aux),
+        )
+    }
+
+    fn unsatisfied_at(&self, index: usize) -> Option<Unsatisfied<Scalar>> {
+        let (a, b, c) = self.eval_constraint(index);
+        if a * b == c {
+            return None;
+        }
+        let path = self.constraints[index].3.clone();
+        Some(Unsatisfied {
+            location: self.location(&path),
+            path,
+            a,
+            b,
+            c,
+        })
+    }
+
     pub fn which_is_unsatisfied(&self) -> Option<&str> {
-        for &(ref a, ref b, ref c, ref path) in &self.constraints {
-            let mut a = eval_lc::<Scalar>(a.as_ref(), &self.inputs, &self.aux);
-            let b = eval_lc::<Scalar>(b.as_ref(), &self.inputs, &self.aux);
-            let c = eval_lc::<Scalar>(c.as_ref(), &self.inputs, &self.aux);
+        (0..self.constraints.len())
+            .find(|&index| self.unsatisfied_at(index).is_some())
+            .map(|index| &*self.constraints[index].3)
+    }
 
-            a.mul_assign(&b);
+    /// Returns every constraint that the assignment does not satisfy, in the
+    /// order they were enforced.
+    pub fn unsatisfied(&self) -> Vec<Unsatisfied<Scalar>> {
+        (0..self.constraints.len())
+            .filter_map(|index| self.unsatisfied_at(index))
+            .collect()
+    }
 
-            if a != c {
-                return Some(&*path);
-            }
+    /// Returns the values of `A`, `B` and `C` of the constraint at `path`
+    /// under the current assignment.
+    pub fn evaluate(&self, path: &str) -> (Scalar, Scalar, Scalar) {
+        match self.named_objects.get(path) {
+            Some(&NamedObject::Constraint(index)) => self.eval_constraint(index),
+            Some(e) => panic!(
+                "tried to evaluate path `{}`, but `{:?}` exists there (not a constraint)",
+                path, e
+            ),
+            _ => panic!("no constraint exists at path: {}", path),
         }
+    }
 
-        None
+    /// Returns the call that allocated the variable or enforced the
+    /// constraint at `path`. Locations are only captured with the
+    /// `locations` feature; without it, this returns `None`.
+    pub fn location(&self, path: &str) -> Option<&'static Location<'static>> {
+        self.locations.get(path).copied()
     }
 
     pub fn is_satisfied(&self) -> bool {
@@ -316,6 +405,12 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         }
     }
 
+    fn record_location(&mut self, path: &str, location: Option<&'static Location<'static>>) {
+        if let Some(location) = location {
+            self.locations.insert(path.to_string(), location);
+        }
+    }
+
     fn set_named_obj(&mut self, path: String, to: NamedObject) {
         if self.named_objects.contains_key(&path) {
             panic!("tried to create object at existing path: {}", path);
@@ -356,6 +451,7 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for TestConstraintSystem<Scala
     {
         let index = self.aux.len();
         let path = compute_path(&self.current_namespace, annotation().into());
+        self.record_location(&path, crate::caller_location());
         self.aux.push((f()?, path.clone()));
         let var = Variable::new_unchecked(Index::Aux(index));
         self.set_named_obj(path, NamedObject::Var(var));
@@ -371,6 +467,7 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for TestConstraintSystem<Scala
     {
         let index = self.inputs.len();
         let path = compute_path(&self.current_namespace, annotation().into());
+        self.record_location(&path, crate::caller_location());
         self.inputs.push((f()?, path.clone()));
         let var = Variable::new_unchecked(Index::Input(index));
         self.set_named_obj(path, NamedObject::Var(var));
@@ -388,6 +485,7 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for TestConstraintSystem<Scala
     {
         let path = compute_path(&self.current_namespace, annotation().into());
         let index = self.constraints.len();
+        self.record_location(&path, crate::caller_location());
         self.set_named_obj(path.clone(), NamedObject::Constraint(index));
 
         let a = a(LinearCombination::zero());
@@ -448,6 +546,25 @@ fn test_cs() {
 
     assert!(!cs.is_satisfied());
     assert!(cs.which_is_unsatisfied() == Some("mult"));
+    assert_eq!(
+        cs.unsatisfied(),
+        vec![Unsatisfied {
+            path: "mult".to_string(),
+            location: cs.location("mult"),
+            a: Scalar::from_str("4").unwrap(),
+            b: Scalar::from_str("4").unwrap(),
+            c: Scalar::from_str("40").unwrap(),
+        }]
+    );
+    assert!(cs.unsatisfied()[0].to_string().starts_with("mult: "));
+    assert!(
+        cs.evaluate("eq")
+            == (
+                Scalar::from_str("4").unwrap(),
+                Scalar::one(),
+                Scalar::from_str("4").unwrap()
+            )
+    );
 
     assert!(cs.get("product") == Scalar::from_str("40").unwrap());
 
@@ -462,3 +579,41 @@ fn test_cs() {
 
     assert!(cs.get("test1/test2/hehe") == Scalar::one());
 }
+
+#[cfg(feature = "locations")]
+#[test]
+fn test_locations() {
+    use crate::gadgets::boolean::AllocatedBit;
+    use bls12_381::Scalar;
+
+    let mut cs = TestConstraintSystem::<Scalar>::new();
+    let a = {
+        let mut cs = cs.namespace(|| "a");
+        let line = line!() + 1;
+        let a = cs.alloc(|| "var", || Ok(Scalar::one())).unwrap();
+        let location = cs.get_root().location("a/var").unwrap();
+        assert_eq!((location.file(), location.line()), (file!(), line));
+        a
+    };
+
+    // A constraint enforced by a gadget is located in the gadget.
+    AllocatedBit::alloc(cs.namespace(|| "bit"), Some(true)).unwrap();
+    let location = cs.location("bit/boolean").unwrap();
+    assert!(location.file().ends_with("boolean.rs"));
+
+    let line = {
+        let mut cs = cs.namespace(|| "outer");
+        let mut cs = cs.namespace(|| "inner");
+        let line = line!() + 1;
+        cs.enforce(|| "zero", |lc| lc + a, |lc| lc + a, |lc| lc);
+        line
+    };
+    let unsatisfied = cs.unsatisfied();
+    assert_eq!(unsatisfied.len(), 1);
+    let location = unsatisfied[0].location.unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+    assert!(unsatisfied[0]
+        .to_string()
+        .ends_with(&format!(" (enforced at {})", location)));
+    assert!(cs.location("ONE").is_none());
+}
@@ -0,0 +1,511 @@
+//! Circuits replayed from execution traces.
+//!
+//! A virtual machine that records what its interpreter did as a [`Trace`]
+//! can prove the execution with a [`TraceCircuit`] instead of a hand-written
+//! `synthesize`. Each [`Op`] of the trace allocates an input or a witness,
+//! or calls a gadget by name on the values of earlier operations, and the
+//! circuit synthesizes the constraints and the witness of every gadget in
+//! turn.
+//!
+//! Gadgets are looked up in a [`Gadgets`] registry. [`Builtins`] provides
+//! arithmetic on numbers, bitwise operations and SHA-256, and a pair of
+//! registries looks names up in the first and then in the second, so that a
+//! machine can add its own instructions to the built-in ones.
+//!
+//! A trace that calls a gadget the registry does not have, or on values of
+//! the wrong kind, fails to synthesize with
+//! [`SynthesisError::Unsatisfiable`].
+
+use ff::PrimeField;
+
+use crate::{Circuit, ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::Boolean;
+use super::num::AllocatedNum;
+use super::sha256::sha256;
+use super::Assignment;
+
+/// A value computed by an operation of a trace.
+#[derive(Clone)]
+pub enum Value<S: PrimeField> {
+    Num(AllocatedNum<S>),
+    /// Bits, in little-endian order.
+    Bits(Vec<Boolean>),
+}
+
+impl<S: PrimeField> Value<S> {
+    pub fn as_num(&self) -> Result<&AllocatedNum<S>, SynthesisError> {
+        match self {
+            Value::Num(num) => Ok(num),
+            Value::Bits(_) => Err(SynthesisError::Unsatisfiable),
+        }
+    }
+
+    pub fn as_bits(&self) -> Result<&[Boolean], SynthesisError> {
+        match self {
+            Value::Bits(bits) => Ok(bits),
+            Value::Num(_) => Err(SynthesisError::Unsatisfiable),
+        }
+    }
+}
+
+/// An operation of a trace. The arguments of a call are the indices of the
+/// earlier operations whose results it takes.
+#[derive(Clone, Debug)]
+pub enum Op<S: PrimeField> {
+    /// Allocates a public input.
+    Input(Option<S>),
+    /// Allocates a private witness.
+    Witness(Option<S>),
+    /// Calls the gadget of the given name.
+    Call { gadget: String, args: Vec<usize> },
+}
+
+/// A recorded execution, as a sequence of operations.
+#[derive(Clone, Debug)]
+pub struct Trace<S: PrimeField> {
+    ops: Vec<Op<S>>,
+}
+
+impl<S: PrimeField> Default for Trace<S> {
+    fn default() -> Self {
+        Trace { ops: vec![] }
+    }
+}
+
+impl<S: PrimeField> Trace<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ops(&self) -> &[Op<S>] {
+        &self.ops
+    }
+
+    /// Appends `op`, and returns its index.
+    pub fn push(&mut self, op: Op<S>) -> usize {
+        self.ops.push(op);
+        self.ops.len() - 1
+    }
+
+    pub fn input(&mut self, value: Option<S>) -> usize {
+        self.push(Op::Input(value))
+    }
+
+    pub fn witness(&mut self, value: Option<S>) -> usize {
+        self.push(Op::Witness(value))
+    }
+
+    pub fn call(&mut self, gadget: &str, args: &[usize]) -> usize {
+        self.push(Op::Call {
+            gadget: gadget.to_string(),
+            args: args.to_vec(),
+        })
+    }
+}
+
+/// A registry of gadgets that traces can call by name.
+pub trait Gadgets<S: PrimeField> {
+    /// Returns whether the registry has a gadget of the given name.
+    fn has(&self, name: &str) -> bool;
+
+    /// Synthesizes the gadget `name` on `args`, and returns its result, if
+    /// it has one.
+    fn call<CS: ConstraintSystem<S>>(
+        &self,
+        cs: CS,
+        name: &str,
+        args: &[Value<S>],
+    ) -> Result<Option<Value<S>>, SynthesisError>;
+}
+
+impl<S: PrimeField, A: Gadgets<S>, B: Gadgets<S>> Gadgets<S> for (A, B) {
+    fn has(&self, name: &str) -> bool {
+        self.0.has(name) || self.1.has(name)
+    }
+
+    fn call<CS: ConstraintSystem<S>>(
+        &self,
+        cs: CS,
+        name: &str,
+        args: &[Value<S>],
+    ) -> Result<Option<Value<S>>, SynthesisError> {
+        if self.0.has(name) {
+            self.0.call(cs, name, args)
+        } else {
+            self.1.call(cs, name, args)
+        }
+    }
+}
+
+/// The built-in gadgets:
+///
+/// - `add`, `sub`, `mul` and `square` on numbers;
+/// - `assert_eq` of two numbers, which has no result;
+/// - `expose`, which makes a number a public input and has no result;
+/// - `bits`, the strict little-endian bits of a number, and `pack`, the
+///   number of at most `CAPACITY` bits;
+/// - `and`, `xor` and `not` on bits of equal length;
+/// - `sha256` of bits, padded with zeros to whole bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Builtins;
+
+const BUILTINS: &[&str] = &[
+    "add",
+    "sub",
+    "mul",
+    "square",
+    "assert_eq",
+    "expose",
+    "bits",
+    "pack",
+    "and",
+    "xor",
+    "not",
+    "sha256",
+];
+
+fn linear<S, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<S>,
+    b: &AllocatedNum<S>,
+    negate: bool,
+) -> Result<AllocatedNum<S>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+{
+    let sign = if negate { -S::one() } else { S::one() };
+    let result = AllocatedNum::alloc(cs.namespace(|| "result"), || {
+        Ok(*a.get_value().get()? + *b.get_value().get()? * sign)
+    })?;
+    cs.enforce(
+        || "linear",
+        |lc| lc + a.get_variable() + (sign, b.get_variable()),
+        |lc| lc + CS::one(),
+        |lc| lc + result.get_variable(),
+    );
+    Ok(result)
+}
+
+fn bitwise<S, CS, F>(
+    mut cs: CS,
+    a: &[Boolean],
+    b: &[Boolean],
+    mut f: F,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    S: PrimeField,
+    CS: ConstraintSystem<S>,
+    F: FnMut(&mut CS, usize, &Boolean, &Boolean) -> Result<Boolean, SynthesisError>,
+{
+    if a.len() != b.len() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    a.iter()
+        .zip(b.iter())
+        .enumerate()
+        .map(|(i, (a, b))| f(&mut cs, i, a, b))
+        .collect()
+}
+
+impl<S: PrimeField> Gadgets<S> for Builtins {
+    fn has(&self, name: &str) -> bool {
+        BUILTINS.contains(&name)
+    }
+
+    fn call<CS: ConstraintSystem<S>>(
+        &self,
+        mut cs: CS,
+        name: &str,
+        args: &[Value<S>],
+    ) -> Result<Option<Value<S>>, SynthesisError> {
+        let arity = match name {
+            "add" | "sub" | "mul" | "assert_eq" | "and" | "xor" => 2,
+            _ => 1,
+        };
+        if args.len() != arity {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let result = match name {
+            "add" | "sub" => Value::Num(linear(
+                cs,
+                args[0].as_num()?,
+                args[1].as_num()?,
+                name == "sub",
+            )?),
+            "mul" => Value::Num(args[0].as_num()?.mul(cs, args[1].as_num()?)?),
+            "square" => Value::Num(args[0].as_num()?.square(cs)?),
+            "assert_eq" => {
+                let (a, b) = (args[0].as_num()?, args[1].as_num()?);
+                cs.enforce(
+                    || "equality",
+                    |lc| lc + a.get_variable(),
+                    |lc| lc + CS::one(),
+                    |lc| lc + b.get_variable(),
+                );
+                return Ok(None);
+            }
+            "expose" => {
+                args[0].as_num()?.inputize(cs)?;
+                return Ok(None);
+            }
+            "bits" => Value::Bits(args[0].as_num()?.to_bits_le_strict(cs)?),
+            "pack" => {
+                let bits = args[0].as_bits()?;
+                if bits.len() > S::CAPACITY as usize {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+                let mut lc = LinearCombination::zero();
+                let mut value = Some(S::zero());
+                let mut coeff = S::one();
+                for bit in bits {
+                    lc = lc + &bit.lc(CS::one(), coeff);
+                    value = match (value, bit.get_value()) {
+                        (Some(v), Some(true)) => Some(v + coeff),
+                        (v, Some(false)) => v,
+                        _ => None,
+                    };
+                    coeff = coeff.double();
+                }
+                let num = AllocatedNum::alloc(cs.namespace(|| "packed"), || {
+                    value.ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                cs.enforce(
+                    || "packing",
+                    |_| lc,
+                    |lc| lc + CS::one(),
+                    |lc| lc + num.get_variable(),
+                );
+                Value::Num(num)
+            }
+            "and" => Value::Bits(bitwise(
+                cs,
+                args[0].as_bits()?,
+                args[1].as_bits()?,
+                |cs, i, a, b| Boolean::and(cs.namespace(|| format!("bit {}", i)), a, b),
+            )?),
+            "xor" => Value::Bits(bitwise(
+                cs,
+                args[0].as_bits()?,
+                args[1].as_bits()?,
+                |cs, i, a, b| Boolean::xor(cs.namespace(|| format!("bit {}", i)), a, b),
+            )?),
+            "not" => Value::Bits(args[0].as_bits()?.iter().map(Boolean::not).collect()),
+            "sha256" => {
+                let mut bits = args[0].as_bits()?.to_vec();
+                bits.resize((bits.len() + 7) / 8 * 8, Boolean::constant(false));
+                // The gadget takes the bits of each byte from the most
+                // significant down.
+                let input = bits
+                    .chunks(8)
+                    .flat_map(|byte| byte.iter().rev().cloned())
+                    .collect::<Vec<_>>();
+                let digest = sha256(cs, &input)?;
+                Value::Bits(
+                    digest
+                        .chunks(8)
+                        .flat_map(|byte| byte.iter().rev().cloned())
+                        .collect(),
+                )
+            }
+            _ => return Err(SynthesisError::Unsatisfiable),
+        };
+        Ok(Some(result))
+    }
+}
+
+/// A circuit that replays a trace with the gadgets of a registry.
+pub struct TraceCircuit<S: PrimeField, G = Builtins> {
+    trace: Trace<S>,
+    gadgets: G,
+}
+
+impl<S: PrimeField> TraceCircuit<S> {
+    /// Replays `trace` with the built-in gadgets.
+    pub fn new(trace: Trace<S>) -> Self {
+        Self::with_gadgets(trace, Builtins)
+    }
+}
+
+impl<S: PrimeField, G: Gadgets<S>> TraceCircuit<S, G> {
+    pub fn with_gadgets(trace: Trace<S>, gadgets: G) -> Self {
+        TraceCircuit { trace, gadgets }
+    }
+
+    /// Synthesizes the trace, and returns the result of each operation.
+    pub fn replay<CS: ConstraintSystem<S>>(
+        &self,
+        cs: &mut CS,
+    ) -> Result<Vec<Option<Value<S>>>, SynthesisError> {
+        let mut results: Vec<Option<Value<S>>> = Vec::with_capacity(self.trace.ops.len());
+        for (i, op) in self.trace.ops.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("op {}", i));
+            let result = match op {
+                Op::Input(value) => {
+                    let num = AllocatedNum::alloc(cs.namespace(|| "num"), || {
+                        value.ok_or(SynthesisError::AssignmentMissing)
+                    })?;
+                    num.inputize(cs.namespace(|| "input"))?;
+                    Some(Value::Num(num))
+                }
+                Op::Witness(value) => Some(Value::Num(AllocatedNum::alloc(
+                    cs.namespace(|| "witness"),
+                    || value.ok_or(SynthesisError::AssignmentMissing),
+                )?)),
+                Op::Call { gadget, args } => {
+                    if !self.gadgets.has(gadget) {
+                        return Err(SynthesisError::Unsatisfiable);
+                    }
+                    let args = args
+                        .iter()
+                        .map(|&arg| match results.get(arg) {
+                            Some(Some(value)) => Ok(value.clone()),
+                            _ => Err(SynthesisError::Unsatisfiable),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.gadgets
+                        .call(cs.namespace(|| gadget.clone()), gadget, &args)?
+                }
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
+
+impl<S: PrimeField, G: Gadgets<S>> Circuit<S> for TraceCircuit<S, G> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.replay(cs).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+    use sha2::{Digest, Sha256};
+
+    /// An instruction of a machine that is not built in.
+    struct Cube;
+
+    impl Gadgets<Scalar> for Cube {
+        fn has(&self, name: &str) -> bool {
+            name == "cube"
+        }
+
+        fn call<CS: ConstraintSystem<Scalar>>(
+            &self,
+            mut cs: CS,
+            _name: &str,
+            args: &[Value<Scalar>],
+        ) -> Result<Option<Value<Scalar>>, SynthesisError> {
+            let x = args[0].as_num()?;
+            let square = x.square(cs.namespace(|| "square"))?;
+            Ok(Some(Value::Num(square.mul(cs.namespace(|| "cube"), x)?)))
+        }
+    }
+
+    #[test]
+    fn test_trace_circuit() {
+        // Proves knowledge of x such that x^3 + x - y is the public input.
+        let mut trace = Trace::new();
+        let x = trace.witness(Some(Scalar::from(3)));
+        let y = trace.witness(Some(Scalar::from(5)));
+        let cube = trace.call("cube", &[x]);
+        let sum = trace.call("add", &[cube, x]);
+        let result = trace.call("sub", &[sum, y]);
+        let expected = trace.input(Some(Scalar::from(25)));
+        trace.call("assert_eq", &[result, expected]);
+
+        let circuit = TraceCircuit::with_gadgets(trace.clone(), (Builtins, Cube));
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let results = circuit.replay(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 2);
+        assert_eq!(
+            results[result]
+                .as_ref()
+                .unwrap()
+                .as_num()
+                .unwrap()
+                .get_value(),
+            Some(Scalar::from(25))
+        );
+        assert!(results[6].is_none());
+
+        // The built-in gadgets alone do not know the instruction.
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        assert!(matches!(
+            TraceCircuit::new(trace).synthesize(&mut cs),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+
+        // A wrong output leaves the constraints unsatisfied.
+        let mut trace = Trace::new();
+        let x = trace.witness(Some(Scalar::from(3)));
+        let square = trace.call("square", &[x]);
+        let y = trace.input(Some(Scalar::from(10)));
+        trace.call("assert_eq", &[square, y]);
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        TraceCircuit::new(trace).synthesize(&mut cs).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("op 3/assert_eq/equality"));
+    }
+
+    #[test]
+    fn test_trace_bits() {
+        let mut trace = Trace::new();
+        let a = trace.witness(Some(Scalar::from(0xab)));
+        let b = trace.witness(Some(Scalar::from(0x3c)));
+        let a_bits = trace.call("bits", &[a]);
+        let b_bits = trace.call("bits", &[b]);
+        let xor = trace.call("xor", &[a_bits, b_bits]);
+        let digest = trace.call("sha256", &[a_bits]);
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let results = TraceCircuit::new(trace).replay(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+
+        // A number has more bits than can be packed back into one.
+        let xor = results[xor].clone().unwrap();
+        assert!(matches!(
+            Builtins.call(
+                cs.namespace(|| "pack all"),
+                "pack",
+                std::slice::from_ref(&xor)
+            ),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        let low = Value::Bits(xor.as_bits().unwrap()[..8].to_vec());
+        let packed = Builtins
+            .call(cs.namespace(|| "pack"), "pack", &[low])
+            .unwrap()
+            .unwrap();
+        Builtins
+            .call(cs.namespace(|| "expose"), "expose", &[packed])
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            cs.get_input(1, "expose/input variable"),
+            Scalar::from(0xab ^ 0x3c)
+        );
+
+        // The bits of a number are those of its little-endian encoding.
+        let mut preimage = [0u8; 32];
+        preimage[0] = 0xab;
+        let expected = Sha256::digest(&preimage[..]);
+        let bits = results[digest].as_ref().unwrap().as_bits().unwrap();
+        let bytes = bits
+            .chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .enumerate()
+                    .map(|(i, b)| (b.get_value().unwrap() as u8) << i)
+                    .sum::<u8>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(&bytes[..], &expected[..]);
+    }
+}
@@ -76,6 +76,14 @@ impl UInt32 {
         Ok(UInt32 { bits, value })
     }
 
+    /// Returns the value if every bit is a constant, so that operations on
+    /// it can be computed natively.
+    pub fn as_constant(&self) -> Option<u32> {
+        self.bits.iter().rev().try_fold(0u32, |acc, bit| {
+            bit.as_constant().map(|b| (acc << 1) | u32::from(b))
+        })
+    }
+
     pub fn into_bits_be(self) -> Vec<Boolean> {
         let mut ret = self.bits;
         ret.reverse();
@@ -305,21 +313,45 @@ impl UInt32 {
         assert!(operands.len() >= 2); // Weird trivial cases that should never happen
         assert!(operands.len() <= 10);
 
+        // Constant operands are summed natively, and only their sum modulo
+        // 2^32 is added to the variable ones
+        let mut constant = 0u32;
+        let mut variables = vec![];
+        for op in operands {
+            match op.as_constant() {
+                Some(value) => constant = constant.wrapping_add(value),
+                None => variables.push(op),
+            }
+        }
+
+        if variables.is_empty() {
+            // We can just return a constant, rather than
+            // unpacking the result into allocated bits.
+
+            return Ok(UInt32::constant(constant));
+        }
+
+        if variables.len() == 1 && constant == 0 {
+            return Ok(variables[0].clone());
+        }
+
         // Compute the maximum value of the sum so we allocate enough bits for
         // the result
-        let mut max_value = (operands.len() as u64) * (u64::from(u32::max_value()));
+        let mut max_value =
+            (variables.len() as u64) * u64::from(u32::max_value()) + u64::from(constant);
 
         // Keep track of the resulting value
-        let mut result_value = Some(0u64);
+        let mut result_value = Some(u64::from(constant));
 
         // This is a linear combination that we will enforce to equal the
         // output
         let mut lc = LinearCombination::zero();
-
-        let mut all_constants = true;
+        if constant != 0 {
+            lc = lc + (Scalar::from(u64::from(constant)), CS::one());
+        }
 
         // Iterate over the operands
-        for op in operands {
+        for op in variables {
             // Accumulate the value
             match op.value {
                 Some(val) => {
@@ -338,8 +370,6 @@ impl UInt32 {
             for bit in &op.bits {
                 lc = lc + &bit.lc(CS::one(), coeff);
 
-                all_constants &= bit.is_constant();
-
                 coeff = coeff.double();
             }
         }
@@ -347,13 +377,6 @@ impl UInt32 {
         // The value of the actual result is modulo 2^32
         let modular_value = result_value.map(|v| v as u32);
 
-        if all_constants && modular_value.is_some() {
-            // We can just return a constant, rather than
-            // unpacking the result into allocated bits.
-
-            return Ok(UInt32::constant(modular_value.unwrap()));
-        }
-
         // Storage area for the resulting bits
         let mut result_bits = vec![];
 
@@ -621,6 +644,57 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_uint32_addmany_folds_constants() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+
+            let a = rng.next_u32();
+            let b = rng.next_u32();
+            let c = rng.next_u32();
+
+            let a_bit = UInt32::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            assert_eq!(a_bit.as_constant(), None);
+            assert_eq!(UInt32::constant(b).as_constant(), Some(b));
+            let allocated = cs.num_constraints();
+
+            // Constants that cancel out modulo 2^32 cost nothing.
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt32::addmany(
+                    cs.namespace(|| "cancelled"),
+                    &[
+                        UInt32::constant(b),
+                        a_bit.clone(),
+                        UInt32::constant(b.wrapping_neg()),
+                    ],
+                )
+                .unwrap()
+            };
+            assert_eq!(r.value, Some(a));
+            assert_eq!(cs.num_constraints(), allocated);
+
+            // Otherwise, their sum only takes as many result bits as adding
+            // a single operand.
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt32::addmany(
+                    cs.namespace(|| "addition"),
+                    &[UInt32::constant(b), a_bit, UInt32::constant(c)],
+                )
+                .unwrap()
+            };
+            assert!(cs.is_satisfied());
+            assert_eq!(r.value, Some(a.wrapping_add(b).wrapping_add(c)));
+            assert!(cs.num_constraints() - allocated <= 34);
+        }
+    }
+
     #[test]
     fn test_uint32_rotr() {
         let mut rng = XorShiftRng::from_seed([
@@ -0,0 +1,387 @@
+//! Circuit representation of a [`u64`], for the 64-bit words of hashes such
+//! as SHA-512 and BLAKE2b.
+
+use ff::PrimeField;
+
+use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+
+use super::boolean::{AllocatedBit, Boolean};
+
+use super::multieq::MultiEq;
+
+/// Represents an interpretation of 64 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt64 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u64>,
+}
+
+impl UInt64 {
+    /// Construct a constant `UInt64` from a `u64`
+    pub fn constant(value: u64) -> Self {
+        let bits = (0..64)
+            .map(|i| Boolean::constant((value >> i) & 1 == 1))
+            .collect();
+
+        UInt64 {
+            bits,
+            value: Some(value),
+        }
+    }
+
+    /// Allocate a `UInt64` in the constraint system
+    pub fn alloc<Scalar, CS>(mut cs: CS, value: Option<u64>) -> Result<Self, SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        let bits = (0..64)
+            .map(|i| {
+                Ok(Boolean::from(AllocatedBit::alloc(
+                    cs.namespace(|| format!("allocated bit {}", i)),
+                    value.map(|v| (v >> i) & 1 == 1),
+                )?))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt64 { bits, value })
+    }
+
+    /// Returns the value if every bit is a constant, so that operations on
+    /// it can be computed natively.
+    pub fn as_constant(&self) -> Option<u64> {
+        self.bits.iter().rev().try_fold(0u64, |acc, bit| {
+            bit.as_constant().map(|b| (acc << 1) | u64::from(b))
+        })
+    }
+
+    /// Returns the value, if it is known.
+    pub fn get_value(&self) -> Option<u64> {
+        self.value
+    }
+
+    pub fn into_bits_be(self) -> Vec<Boolean> {
+        let mut ret = self.bits;
+        ret.reverse();
+        ret
+    }
+
+    pub fn from_bits_be(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 64);
+
+        let mut bits = bits.to_vec();
+        bits.reverse();
+        Self::from_bits(&bits)
+    }
+
+    /// Turns this `UInt64` into its little-endian byte order representation.
+    pub fn into_bits(self) -> Vec<Boolean> {
+        self.bits
+    }
+
+    /// Converts a little-endian byte order representation of bits into a
+    /// `UInt64`.
+    pub fn from_bits(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 64);
+
+        let value = bits.iter().rev().try_fold(0u64, |acc, bit| {
+            bit.get_value().map(|b| (acc << 1) | u64::from(b))
+        });
+
+        UInt64 {
+            value,
+            bits: bits.to_vec(),
+        }
+    }
+
+    pub fn rotr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let new_bits = self
+            .bits
+            .iter()
+            .skip(by)
+            .chain(self.bits.iter())
+            .take(64)
+            .cloned()
+            .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v.rotate_right(by as u32)),
+        }
+    }
+
+    pub fn shr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let fill = Boolean::constant(false);
+
+        let new_bits = self
+            .bits
+            .iter() // The bits are least significant first
+            .skip(by) // Skip the bits that will be lost during the shift
+            .chain(Some(&fill).into_iter().cycle()) // Rest will be zeros
+            .take(64)
+            .cloned()
+            .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v >> by as u32),
+        }
+    }
+
+    /// XOR this `UInt64` with another `UInt64`
+    pub fn xor<Scalar, CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a ^ b),
+            _ => None,
+        };
+
+        let bits = self
+            .bits
+            .iter()
+            .zip(other.bits.iter())
+            .enumerate()
+            .map(|(i, (a, b))| Boolean::xor(cs.namespace(|| format!("xor of bit {}", i)), a, b))
+            .collect::<Result<_, _>>()?;
+
+        Ok(UInt64 {
+            bits,
+            value: new_value,
+        })
+    }
+
+    /// Perform modular addition of several `UInt64` objects.
+    pub fn addmany<Scalar, CS, M>(mut cs: M, operands: &[Self]) -> Result<Self, SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+        M: ConstraintSystem<Scalar, Root = MultiEq<Scalar, CS>>,
+    {
+        // Make some arbitrary bounds for ourselves to avoid overflows
+        // in the scalar field
+        assert!(Scalar::NUM_BITS >= 128);
+        assert!(operands.len() >= 2); // Weird trivial cases that should never happen
+        assert!(operands.len() <= 10);
+
+        // Constant operands are summed natively, and only their sum modulo
+        // 2^64 is added to the variable ones
+        let mut constant = 0u64;
+        let mut variables = vec![];
+        for op in operands {
+            match op.as_constant() {
+                Some(value) => constant = constant.wrapping_add(value),
+                None => variables.push(op),
+            }
+        }
+
+        if variables.is_empty() {
+            return Ok(UInt64::constant(constant));
+        }
+
+        if variables.len() == 1 && constant == 0 {
+            return Ok(variables[0].clone());
+        }
+
+        // Compute the maximum value of the sum so we allocate enough bits for
+        // the result
+        let mut max_value = (variables.len() as u128) * u128::from(u64::MAX) + u128::from(constant);
+
+        // Keep track of the resulting value
+        let mut result_value = Some(u128::from(constant));
+
+        // This is a linear combination that we will enforce to equal the
+        // output
+        let mut lc = LinearCombination::zero();
+        if constant != 0 {
+            lc = lc + (Scalar::from(constant), CS::one());
+        }
+
+        for op in variables {
+            result_value = match (result_value, op.value) {
+                (Some(v), Some(val)) => Some(v + u128::from(val)),
+                _ => None,
+            };
+
+            let mut coeff = Scalar::one();
+            for bit in &op.bits {
+                lc = lc + &bit.lc(CS::one(), coeff);
+
+                coeff = coeff.double();
+            }
+        }
+
+        // The value of the actual result is modulo 2^64
+        let modular_value = result_value.map(|v| v as u64);
+
+        let mut result_bits = vec![];
+        let mut result_lc = LinearCombination::zero();
+
+        // Allocate each bit of the result
+        let mut coeff = Scalar::one();
+        let mut i = 0;
+        while max_value != 0 {
+            let b = AllocatedBit::alloc(
+                cs.namespace(|| format!("result bit {}", i)),
+                result_value.map(|v| (v >> i) & 1 == 1),
+            )?;
+
+            result_lc = result_lc + (coeff, b.get_variable());
+
+            result_bits.push(b.into());
+
+            max_value >>= 1;
+            i += 1;
+            coeff = coeff.double();
+        }
+
+        // Enforce equality between the sum and result
+        cs.get_root().enforce_equal(i, &lc, &result_lc);
+
+        // Discard carry bits that we don't care about
+        result_bits.truncate(64);
+
+        Ok(UInt64 {
+            bits: result_bits,
+            value: modular_value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UInt64;
+    use crate::gadgets::multieq::MultiEq;
+    use crate::gadgets::test::*;
+    use crate::ConstraintSystem;
+    use bls12_381::Scalar;
+    use ff::Field;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn check_bits(r: &UInt64, mut expected: u64) {
+        assert_eq!(r.get_value(), Some(expected));
+        for b in r.bits.iter() {
+            assert_eq!(b.get_value(), Some(expected & 1 == 1));
+            expected >>= 1;
+        }
+    }
+
+    #[test]
+    fn test_uint64_bits() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let num = rng.next_u64();
+            let a = UInt64::constant(num);
+            assert_eq!(a.as_constant(), Some(num));
+
+            let b = UInt64::from_bits(&a.clone().into_bits());
+            check_bits(&b, num);
+            let c = UInt64::from_bits_be(&a.into_bits_be());
+            check_bits(&c, num);
+
+            for i in 0..64 {
+                check_bits(&UInt64::constant(num).rotr(i), num.rotate_right(i as u32));
+                check_bits(&UInt64::constant(num).shr(i), num >> i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_xor() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+            assert_eq!(a_bit.as_constant(), None);
+
+            let r = a_bit.xor(cs.namespace(|| "first xor"), &b_bit).unwrap();
+            let r = r.xor(cs.namespace(|| "second xor"), &c_bit).unwrap();
+
+            assert!(cs.is_satisfied());
+            // 64 booleanity constraints for each allocated operand, and one
+            // for each bit of the xor of two variables.
+            assert_eq!(cs.num_constraints(), 64 * 3);
+            check_bits(&r, a ^ b ^ c);
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+
+            let a = rng.next_u64();
+            let b = rng.next_u64();
+            let c = rng.next_u64();
+            let d = rng.next_u64();
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::constant(c);
+            let d_bit = UInt64::alloc(cs.namespace(|| "d_bit"), Some(d)).unwrap();
+            let allocated = cs.num_constraints();
+
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &[r, c_bit, d_bit]).unwrap()
+            };
+
+            assert!(cs.is_satisfied());
+            check_bits(&r, (a ^ b).wrapping_add(c).wrapping_add(d));
+            // The xor with a constant is free; the sum of two variables and
+            // a constant fits in 66 bits, each a booleanity constraint, and
+            // the sum itself is one more.
+            assert_eq!(cs.num_constraints() - allocated, 66 + 1);
+
+            // Flip a bit and see if the addition constraint still works
+            if cs.get("addition/result bit 0/boolean").is_zero() {
+                cs.set("addition/result bit 0/boolean", Field::one());
+            } else {
+                cs.set("addition/result bit 0/boolean", Field::zero());
+            }
+
+            assert!(!cs.is_satisfied());
+        }
+
+        let r = {
+            let mut cs = TestConstraintSystem::<Scalar>::new();
+            let mut cs = MultiEq::new(&mut cs);
+            UInt64::addmany(
+                cs.namespace(|| "constants"),
+                &[UInt64::constant(u64::MAX), UInt64::constant(2)],
+            )
+            .unwrap()
+        };
+        assert_eq!(r.as_constant(), Some(1));
+    }
+}
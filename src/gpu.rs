@@ -0,0 +1,430 @@
+//! A pluggable interface for accelerators of multiexponentiations and FFTs.
+//!
+//! With the `gpu` feature enabled, a [`Worker`] can carry a set of
+//! [`Devices`]: kernels, registered per curve or field, to which
+//! [`multiexp`] and the FFTs of [`EvaluationDomain`] dispatch instead of
+//! running on the CPU. The `opencl` feature adds a backend, in [`opencl`],
+//! with kernels for BLS12-381 that run on any OpenCL device, including
+//! NVIDIA GPUs through the OpenCL driver of CUDA. Other backends implement
+//! [`MultiexpKernel`] or [`FftKernel`] for the types they support, and
+//! register them with [`Devices::register_multiexp`] and
+//! [`Devices::register_fft`]. Each should test its kernels against the CPU,
+//! as the tests of this module do for the dispatch with reference kernels.
+//!
+//! A computation falls back to the CPU when no kernel is registered for its
+//! types, when it is smaller than the kernel's [`min_size`], or when the
+//! kernel fails. A kernel that fails must report it rather than return a
+//! wrong result, which bellman cannot detect. Since the devices belong to
+//! the worker, offloading is selected per call: [`create_proof_on`]
+//! offloads when given a worker from [`Worker::with_devices`], and runs on
+//! the CPU when given [`Worker::on_cpu`].
+//!
+//! [`create_proof_on`]: crate::groth16::create_proof_on
+//! [`multiexp`]: crate::multiexp::multiexp
+//! [`EvaluationDomain`]: crate::domain::EvaluationDomain
+//! [`min_size`]: MultiexpKernel::min_size
+
+use bitvec::{array::BitArray, order::Lsb0};
+use ff::PrimeField;
+use group::prime::PrimeCurve;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::domain::Group;
+use crate::multicore::Worker;
+
+#[cfg(all(feature = "opencl", unix))]
+pub mod opencl;
+
+/// The little-endian bits of a scalar, as [`multiexp`] takes them.
+///
+/// [`multiexp`]: crate::multiexp::multiexp
+pub type Exponent<S> = BitArray<Lsb0, <S as PrimeField>::ReprBits>;
+
+/// A failure of an accelerator, such as a device running out of memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KernelError(pub String);
+
+impl fmt::Display for KernelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "accelerator failed: {}", self.0)
+    }
+}
+
+impl Error for KernelError {}
+
+/// A multiexponentiation kernel for the curve `G`.
+pub trait MultiexpKernel<G: PrimeCurve>: Send + Sync {
+    /// The smallest number of bases worth offloading. Smaller
+    /// multiexponentiations run on the CPU.
+    fn min_size(&self) -> usize {
+        1 << 16
+    }
+
+    /// Computes `sum(bases[i] * exponents[i])`. The slices have the same
+    /// length, and no base is the identity.
+    fn multiexp(
+        &self,
+        bases: &[G::Affine],
+        exponents: &[&Exponent<G::Scalar>],
+    ) -> Result<G, KernelError>;
+}
+
+/// A radix-2 FFT kernel over the elements `T` of a group with scalars `S`.
+pub trait FftKernel<S: PrimeField, T: Group<S>>: Send + Sync {
+    /// The smallest domain worth offloading. Smaller FFTs run on the CPU.
+    fn min_size(&self) -> usize {
+        1 << 16
+    }
+
+    /// Replaces `a`, of length `2^log_n`, by its evaluations at the powers
+    /// of `omega`, in order. On failure, `a` must be left unchanged so that
+    /// the FFT can be run on the CPU instead.
+    fn fft(&self, a: &mut [T], omega: &S, log_n: u32) -> Result<(), KernelError>;
+}
+
+/// The kernels a [`Worker`] offloads to.
+#[derive(Default)]
+pub struct Devices {
+    multiexp: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    fft: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    offloaded: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl fmt::Debug for Devices {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Devices")
+            .field("multiexp_kernels", &self.multiexp.len())
+            .field("fft_kernels", &self.fft.len())
+            .field("offloaded", &self.offloaded())
+            .field("failures", &self.failures())
+            .finish()
+    }
+}
+
+impl Devices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the multiexponentiation kernel for `G`, replacing any
+    /// previous one.
+    pub fn register_multiexp<G, K>(&mut self, kernel: K) -> &mut Self
+    where
+        G: PrimeCurve,
+        K: MultiexpKernel<G> + 'static,
+    {
+        let kernel: Arc<dyn MultiexpKernel<G>> = Arc::new(kernel);
+        self.multiexp.insert(TypeId::of::<G>(), Box::new(kernel));
+        self
+    }
+
+    /// Registers the FFT kernel over `T`, replacing any previous one.
+    pub fn register_fft<S, T, K>(&mut self, kernel: K) -> &mut Self
+    where
+        S: PrimeField,
+        T: Group<S>,
+        K: FftKernel<S, T> + 'static,
+    {
+        let kernel: Arc<dyn FftKernel<S, T>> = Arc::new(kernel);
+        self.fft.insert(TypeId::of::<(S, T)>(), Box::new(kernel));
+        self
+    }
+
+    /// Returns the number of computations that ran on a kernel.
+    pub fn offloaded(&self) -> usize {
+        self.offloaded.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of computations that fell back to the CPU because
+    /// their kernel failed.
+    pub fn failures(&self) -> usize {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    fn multiexp_kernel<G: PrimeCurve>(&self) -> Option<&Arc<dyn MultiexpKernel<G>>> {
+        self.multiexp.get(&TypeId::of::<G>())?.downcast_ref()
+    }
+
+    fn fft_kernel<S: PrimeField, T: Group<S>>(&self) -> Option<&Arc<dyn FftKernel<S, T>>> {
+        self.fft.get(&TypeId::of::<(S, T)>())?.downcast_ref()
+    }
+
+    /// Runs `f` on a kernel, and counts whether it succeeded.
+    fn run<R>(&self, f: impl FnOnce() -> Result<R, KernelError>) -> Option<R> {
+        match f() {
+            Ok(result) => {
+                self.offloaded.fetch_add(1, Ordering::Relaxed);
+                Some(result)
+            }
+            Err(_) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Offloads a multiexponentiation, or returns `None` if it must run on
+    /// the CPU.
+    pub(crate) fn multiexp<G: PrimeCurve>(
+        &self,
+        bases: &[G::Affine],
+        exponents: &[&Exponent<G::Scalar>],
+    ) -> Option<G> {
+        let kernel = self.multiexp_kernel::<G>()?;
+        if bases.len() < kernel.min_size() {
+            return None;
+        }
+
+        let mut span = crate::trace::span("offloaded_multiexp");
+        span.record("size", bases.len());
+        self.run(|| kernel.multiexp(bases, exponents))
+    }
+
+    /// Offloads an FFT, or returns `false` if it must run on the CPU.
+    pub(crate) fn fft<S: PrimeField, T: Group<S>>(
+        &self,
+        a: &mut [T],
+        omega: &S,
+        log_n: u32,
+    ) -> bool {
+        let kernel = match self.fft_kernel::<S, T>() {
+            Some(kernel) if a.len() >= kernel.min_size() => kernel,
+            _ => return false,
+        };
+
+        let mut span = crate::trace::span("offloaded_fft");
+        span.record("size", a.len());
+        self.run(|| kernel.fft(a, omega, log_n)).is_some()
+    }
+}
+
+impl Worker {
+    /// Returns a worker on the same threads that offloads to `devices`.
+    pub fn with_devices(&self, devices: Arc<Devices>) -> Worker {
+        let mut worker = self.clone();
+        worker.devices = Some(devices);
+        worker
+    }
+
+    /// Returns a worker on the same threads that runs everything on the
+    /// CPU.
+    pub fn on_cpu(&self) -> Worker {
+        let mut worker = self.clone();
+        worker.devices = None;
+        worker
+    }
+
+    /// Returns the devices this worker offloads to, if any.
+    pub fn devices(&self) -> Option<&Devices> {
+        self.devices.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EvaluationDomain, Scalar};
+    use crate::multiexp::{multiexp, FullDensity};
+    use bls12_381::{Bls12, G1Affine, G1Projective};
+    use ff::Field;
+    use futures::Future;
+    use group::{Curve, Group as _};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::ops::AddAssign;
+
+    /// Double-and-add, as a stand-in for a device.
+    struct NaiveMultiexp {
+        fail: bool,
+    }
+
+    impl MultiexpKernel<G1Projective> for NaiveMultiexp {
+        fn min_size(&self) -> usize {
+            8
+        }
+
+        fn multiexp(
+            &self,
+            bases: &[G1Affine],
+            exponents: &[&Exponent<bls12_381::Scalar>],
+        ) -> Result<G1Projective, KernelError> {
+            if self.fail {
+                return Err(KernelError("out of memory".into()));
+            }
+            let mut acc = G1Projective::identity();
+            for (base, exp) in bases.iter().zip(exponents) {
+                let mut term = G1Projective::identity();
+                for bit in exp.iter().rev() {
+                    term = term.double();
+                    if *bit {
+                        term.add_assign(base);
+                    }
+                }
+                acc += term;
+            }
+            Ok(acc)
+        }
+    }
+
+    /// The quadratic DFT.
+    struct NaiveFft;
+
+    impl FftKernel<bls12_381::Scalar, Scalar<bls12_381::Scalar>> for NaiveFft {
+        fn min_size(&self) -> usize {
+            0
+        }
+
+        fn fft(
+            &self,
+            a: &mut [Scalar<bls12_381::Scalar>],
+            omega: &bls12_381::Scalar,
+            _: u32,
+        ) -> Result<(), KernelError> {
+            let coeffs = a.to_vec();
+            let mut point = bls12_381::Scalar::one();
+            for value in a.iter_mut() {
+                let mut power = bls12_381::Scalar::one();
+                value.0 = bls12_381::Scalar::zero();
+                for c in &coeffs {
+                    value.0 += c.0 * power;
+                    power *= point;
+                }
+                point *= omega;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn offloading() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut devices = Devices::new();
+        devices
+            .register_multiexp(NaiveMultiexp { fail: false })
+            .register_fft(NaiveFft);
+        let devices = Arc::new(devices);
+        let cpu = Worker::new();
+        let offloading = cpu.with_devices(devices.clone());
+
+        let bases = Arc::new(
+            (0..16)
+                .map(|_| G1Projective::random(&mut rng).to_affine())
+                .collect::<Vec<_>>(),
+        );
+        let exponents = Arc::new(
+            (0..16)
+                .map(|_| bls12_381::Scalar::random(&mut rng).to_le_bits())
+                .collect::<Vec<_>>(),
+        );
+        let run = |worker: &Worker, n: usize| -> G1Projective {
+            multiexp(
+                worker,
+                (bases.clone(), 0),
+                FullDensity,
+                Arc::new(exponents[..n].to_vec()),
+            )
+            .wait()
+            .unwrap()
+        };
+
+        // Small multiexponentiations stay on the CPU.
+        assert_eq!(run(&offloading, 4), run(&cpu, 4));
+        assert_eq!(devices.offloaded(), 0);
+        assert_eq!(run(&offloading, 16), run(&cpu, 16));
+        assert_eq!(devices.offloaded(), 1);
+        assert_eq!(run(&offloading.on_cpu(), 16), run(&cpu, 16));
+        assert_eq!(devices.offloaded(), 1);
+
+        let coeffs = (0..8)
+            .map(|_| Scalar(bls12_381::Scalar::random(&mut rng)))
+            .collect::<Vec<_>>();
+        let mut on_cpu = EvaluationDomain::from_coeffs(coeffs.clone()).unwrap();
+        let mut offloaded = EvaluationDomain::from_coeffs(coeffs).unwrap();
+        on_cpu.fft(&cpu);
+        offloaded.fft(&offloading);
+        assert!(on_cpu.as_ref() == offloaded.as_ref());
+        assert_eq!(devices.offloaded(), 2);
+
+        // A failing kernel falls back to the CPU.
+        let mut failing = Devices::new();
+        failing.register_multiexp(NaiveMultiexp { fail: true });
+        let failing = Arc::new(failing);
+        assert_eq!(run(&cpu.with_devices(failing.clone()), 16), run(&cpu, 16));
+        assert_eq!((failing.offloaded(), failing.failures()), (0, 1));
+    }
+
+    #[test]
+    fn offloaded_proof() {
+        use crate::groth16::{
+            create_proof_on, generate_random_parameters, prepare_verifying_key, verify_proof,
+        };
+        use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+        struct Cube(Option<bls12_381::Scalar>);
+
+        impl Circuit<bls12_381::Scalar> for Cube {
+            fn synthesize<CS: ConstraintSystem<bls12_381::Scalar>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let mut x = cs.alloc(|| "x", || self.0.ok_or(SynthesisError::AssignmentMissing))?;
+                let mut value = self.0;
+                for i in 0..12 {
+                    let next = value.map(|v| v * v);
+                    let y = cs.alloc(
+                        || format!("square {}", i),
+                        || next.ok_or(SynthesisError::AssignmentMissing),
+                    )?;
+                    cs.enforce(
+                        || format!("squaring {}", i),
+                        |lc| lc + x,
+                        |lc| lc + x,
+                        |lc| lc + y,
+                    );
+                    x = y;
+                    value = next;
+                }
+                let out =
+                    cs.alloc_input(|| "out", || value.ok_or(SynthesisError::AssignmentMissing))?;
+                cs.enforce(|| "out", |lc| lc + x, |lc| lc + CS::one(), |lc| lc + out);
+                Ok(())
+            }
+        }
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = generate_random_parameters::<Bls12, _, _>(Cube(None), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let mut devices = Devices::new();
+        devices
+            .register_multiexp(NaiveMultiexp { fail: false })
+            .register_fft(NaiveFft);
+        let devices = Arc::new(devices);
+        let worker = Worker::new().with_devices(devices.clone());
+
+        let x = bls12_381::Scalar::from(3);
+        let (r, s) = (
+            bls12_381::Scalar::random(&mut rng),
+            bls12_381::Scalar::random(&mut rng),
+        );
+        let proof = create_proof_on(&worker, Cube(Some(x)), &params, r, s).unwrap();
+        let cpu_proof = create_proof_on(&worker.on_cpu(), Cube(Some(x)), &params, r, s).unwrap();
+        assert!(proof == cpu_proof);
+        assert!(devices.offloaded() > 0);
+
+        let out = (0..12).fold(x, |v, _| v * v);
+        assert!(verify_proof(&pvk, &proof, &[out]).is_ok());
+    }
+}
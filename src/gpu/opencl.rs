@@ -0,0 +1,891 @@
+//! An OpenCL backend for the FFTs over the scalars of BLS12-381 and the
+//! multiexponentiations in its G1.
+//!
+//! The OpenCL library is loaded when [`Device::all`] or [`Device::gpus`] is
+//! first called rather than linked, so that building with the `opencl`
+//! feature needs no OpenCL SDK, and a machine without one finds no devices
+//! and proves on the CPU. CUDA devices are used through the OpenCL driver
+//! that NVIDIA ships with CUDA.
+//!
+//! [`Device::register`] registers the kernels of a device with a set of
+//! [`Devices`]:
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! use bellman::gpu::{opencl, Devices};
+//! use bellman::multicore::Worker;
+//!
+//! let mut devices = Devices::new();
+//! if let Some(device) = opencl::Device::gpus().unwrap_or_default().pop() {
+//!     device.register(&mut devices);
+//! }
+//! let worker = Worker::new().with_devices(Arc::new(devices));
+//! ```
+//!
+//! The kernels compute in Montgomery form on 64-bit limbs, and the FFT is
+//! the radix-2 one of the CPU, with a launch per round. The
+//! multiexponentiation sums buckets of 8-bit windows over chunks of the
+//! bases, a work item per window and chunk, and the device adds the sums
+//! up, so that a single point is read back. That point is checked to be in
+//! G1 before it is returned, and a computation whose result is not is
+//! reported as failed. The G2 multiexponentiations of proving stay on the
+//! CPU.
+
+use bitvec::field::BitField;
+use bls12_381::{G1Affine, G1Projective};
+use ff::Field;
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use super::{Devices, Exponent, FftKernel, KernelError, MultiexpKernel};
+use crate::domain::Scalar;
+
+type Fr = bls12_381::Scalar;
+
+const COMMON: &str = include_str!("opencl/common.cl");
+const FIELD: &str = include_str!("opencl/field.cl");
+const FFT: &str = include_str!("opencl/fft.cl");
+const CURVE: &str = include_str!("opencl/curve.cl");
+const MULTIEXP: &str = include_str!("opencl/multiexp.cl");
+
+/// The modulus of the scalar field, the inverse of its negation modulo
+/// 2^64, and 2^256 and 2^512 modulo it.
+const FR_MODULUS: [u64; 4] = [
+    0xffff_ffff_0000_0001,
+    0x53bd_a402_fffe_5bfe,
+    0x3339_d808_09a1_d805,
+    0x73ed_a753_299d_7d48,
+];
+const FR_INV: u64 = 0xffff_fffe_ffff_ffff;
+const FR_R: [u64; 4] = [
+    0x0000_0001_ffff_fffe,
+    0x5884_b7fa_0003_4802,
+    0x998c_4fef_ecbc_4ff5,
+    0x1824_b159_acc5_056f,
+];
+const FR_R2: [u64; 4] = [
+    0xc999_e990_f3f2_9c6d,
+    0x2b6c_edcb_8792_5c23,
+    0x05d3_1496_7254_398f,
+    0x0748_d9d9_9f59_ff11,
+];
+
+/// The same for the base field, with 2^384 and 2^768.
+const FQ_MODULUS: [u64; 6] = [
+    0xb9fe_ffff_ffff_aaab,
+    0x1eab_fffe_b153_ffff,
+    0x6730_d2a0_f6b0_f624,
+    0x6477_4b84_f385_12bf,
+    0x4b1b_a7b6_434b_acd7,
+    0x1a01_11ea_397f_e69a,
+];
+const FQ_INV: u64 = 0x89f3_fffc_fffc_fffd;
+const FQ_R: [u64; 6] = [
+    0x7609_0000_0002_fffd,
+    0xebf4_000b_c40c_0002,
+    0x5f48_9857_53c7_58ba,
+    0x77ce_5853_7052_5745,
+    0x5c07_1a97_a256_ec6d,
+    0x15f6_5ec3_fa80_e493,
+];
+const FQ_R2: [u64; 6] = [
+    0xf4df_1f34_1c34_1746,
+    0x0a76_e6a6_09d1_04f1,
+    0x8de5_476c_4c95_b6d5,
+    0x67eb_88a9_939d_83c0,
+    0x9a79_3e85_b519_952d,
+    0x1198_8fe5_92ca_e3aa,
+];
+
+/// The bits of the windows of the multiexponentiation.
+const WINDOW_BITS: usize = 8;
+const WINDOWS: usize = (256 + WINDOW_BITS - 1) / WINDOW_BITS;
+const BUCKETS: usize = (1 << WINDOW_BITS) - 1;
+
+/// The bases a chunk of the multiexponentiation has at least, unless there
+/// are fewer, and the most chunks, which bound the memory of the buckets.
+const MIN_CHUNK: usize = 1 << 10;
+const MAX_CHUNKS: usize = 128;
+
+/// The size of a point of G1 on the device, in limbs.
+const G1_LIMBS: usize = 18;
+
+/// Returns the source of the kernels.
+fn source() -> String {
+    let mut source = format!(
+        "#define SCALAR_LIMBS 4\n#define WINDOW_BITS {}\n",
+        WINDOW_BITS
+    );
+    source.push_str(COMMON);
+    source.push_str(&field("fr", &FR_MODULUS, FR_INV, &FR_R, &FR_R2));
+    source.push_str(&field("fq", &FQ_MODULUS, FQ_INV, &FQ_R, &FQ_R2));
+    source.push_str(FFT);
+    source.push_str(CURVE);
+    source.push_str(MULTIEXP);
+    source
+}
+
+/// Instantiates the field arithmetic for a modulus.
+fn field(name: &str, modulus: &[u64], inv: u64, r: &[u64], r2: &[u64]) -> String {
+    let array = |suffix: &str, limbs: &[u64]| {
+        let limbs = limbs
+            .iter()
+            .map(|limb| format!("{:#x}UL", limb))
+            .collect::<Vec<_>>();
+        format!(
+            "__constant ulong {}_{}[{}] = {{{}}};\n",
+            name,
+            suffix,
+            limbs.len(),
+            limbs.join(", ")
+        )
+    };
+
+    let mut source = format!(
+        "#define {0}_LIMBS {1}\n#define {0}_INV {2:#x}UL\n",
+        name,
+        modulus.len(),
+        inv
+    );
+    source.push_str(&array("P", modulus));
+    source.push_str(&array("R", r));
+    source.push_str(&array("R2", r2));
+    source.push_str(&FIELD.replace("FIELD", name));
+    source
+}
+
+type Handle = *mut c_void;
+
+const CL_SUCCESS: i32 = 0;
+const CL_DEVICE_TYPE_GPU: u64 = 1 << 2;
+const CL_DEVICE_TYPE_ALL: u64 = 0xffff_ffff;
+const CL_DEVICE_NAME: u32 = 0x102b;
+const CL_PROGRAM_BUILD_LOG: u32 = 0x1183;
+const CL_MEM_READ_WRITE: u64 = 1;
+const CL_TRUE: u32 = 1;
+
+#[cfg(target_os = "macos")]
+const LIBRARIES: &[&str] = &["/System/Library/Frameworks/OpenCL.framework/OpenCL"];
+#[cfg(not(target_os = "macos"))]
+const LIBRARIES: &[&str] = &["libOpenCL.so.1", "libOpenCL.so"];
+
+/// The functions of the OpenCL library.
+struct Api {
+    get_platform_ids: unsafe extern "system" fn(u32, *mut Handle, *mut u32) -> i32,
+    get_device_ids: unsafe extern "system" fn(Handle, u64, u32, *mut Handle, *mut u32) -> i32,
+    get_device_info: unsafe extern "system" fn(Handle, u32, usize, *mut c_void, *mut usize) -> i32,
+    create_context: unsafe extern "system" fn(
+        *const isize,
+        u32,
+        *const Handle,
+        Option<unsafe extern "system" fn(*const c_char, *const c_void, usize, *mut c_void)>,
+        *mut c_void,
+        *mut i32,
+    ) -> Handle,
+    create_command_queue: unsafe extern "system" fn(Handle, Handle, u64, *mut i32) -> Handle,
+    create_program_with_source: unsafe extern "system" fn(
+        Handle,
+        u32,
+        *const *const c_char,
+        *const usize,
+        *mut i32,
+    ) -> Handle,
+    build_program: unsafe extern "system" fn(
+        Handle,
+        u32,
+        *const Handle,
+        *const c_char,
+        Option<unsafe extern "system" fn(Handle, *mut c_void)>,
+        *mut c_void,
+    ) -> i32,
+    get_program_build_info:
+        unsafe extern "system" fn(Handle, Handle, u32, usize, *mut c_void, *mut usize) -> i32,
+    create_kernel: unsafe extern "system" fn(Handle, *const c_char, *mut i32) -> Handle,
+    set_kernel_arg: unsafe extern "system" fn(Handle, u32, usize, *const c_void) -> i32,
+    create_buffer: unsafe extern "system" fn(Handle, u64, usize, *mut c_void, *mut i32) -> Handle,
+    enqueue_write_buffer: unsafe extern "system" fn(
+        Handle,
+        Handle,
+        u32,
+        usize,
+        usize,
+        *const c_void,
+        u32,
+        *const Handle,
+        *mut Handle,
+    ) -> i32,
+    enqueue_read_buffer: unsafe extern "system" fn(
+        Handle,
+        Handle,
+        u32,
+        usize,
+        usize,
+        *mut c_void,
+        u32,
+        *const Handle,
+        *mut Handle,
+    ) -> i32,
+    enqueue_nd_range_kernel: unsafe extern "system" fn(
+        Handle,
+        Handle,
+        u32,
+        *const usize,
+        *const usize,
+        *const usize,
+        u32,
+        *const Handle,
+        *mut Handle,
+    ) -> i32,
+    finish: unsafe extern "system" fn(Handle) -> i32,
+    release_mem_object: unsafe extern "system" fn(Handle) -> i32,
+    release_kernel: unsafe extern "system" fn(Handle) -> i32,
+    release_program: unsafe extern "system" fn(Handle) -> i32,
+    release_command_queue: unsafe extern "system" fn(Handle) -> i32,
+    release_context: unsafe extern "system" fn(Handle) -> i32,
+}
+
+impl Api {
+    /// Loads the OpenCL library, or returns `None` if there is none. The
+    /// library is never unloaded.
+    #[allow(clippy::missing_transmute_annotations)]
+    fn load() -> Option<Api> {
+        let library = LIBRARIES.iter().find_map(|name| {
+            let name = CString::new(*name).unwrap();
+            let library = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+            if library.is_null() {
+                None
+            } else {
+                Some(library)
+            }
+        })?;
+
+        macro_rules! symbol {
+            ($name:expr) => {{
+                let symbol =
+                    unsafe { libc::dlsym(library, concat!($name, "\0").as_ptr() as *const c_char) };
+                if symbol.is_null() {
+                    return None;
+                }
+                // The signatures are those of the OpenCL 1.2 headers.
+                unsafe { mem::transmute(symbol) }
+            }};
+        }
+
+        Some(Api {
+            get_platform_ids: symbol!("clGetPlatformIDs"),
+            get_device_ids: symbol!("clGetDeviceIDs"),
+            get_device_info: symbol!("clGetDeviceInfo"),
+            create_context: symbol!("clCreateContext"),
+            create_command_queue: symbol!("clCreateCommandQueue"),
+            create_program_with_source: symbol!("clCreateProgramWithSource"),
+            build_program: symbol!("clBuildProgram"),
+            get_program_build_info: symbol!("clGetProgramBuildInfo"),
+            create_kernel: symbol!("clCreateKernel"),
+            set_kernel_arg: symbol!("clSetKernelArg"),
+            create_buffer: symbol!("clCreateBuffer"),
+            enqueue_write_buffer: symbol!("clEnqueueWriteBuffer"),
+            enqueue_read_buffer: symbol!("clEnqueueReadBuffer"),
+            enqueue_nd_range_kernel: symbol!("clEnqueueNDRangeKernel"),
+            finish: symbol!("clFinish"),
+            release_mem_object: symbol!("clReleaseMemObject"),
+            release_kernel: symbol!("clReleaseKernel"),
+            release_program: symbol!("clReleaseProgram"),
+            release_command_queue: symbol!("clReleaseCommandQueue"),
+            release_context: symbol!("clReleaseContext"),
+        })
+    }
+}
+
+/// Turns the status of an OpenCL call into a result.
+fn check(status: i32, call: &str) -> Result<(), KernelError> {
+    if status == CL_SUCCESS {
+        Ok(())
+    } else {
+        Err(KernelError(format!(
+            "{} failed with error {}",
+            call, status
+        )))
+    }
+}
+
+/// Returns the items of an OpenCL list, such as the platforms.
+fn list(
+    get: impl Fn(u32, *mut Handle, *mut u32) -> i32,
+    call: &str,
+) -> Result<Vec<Handle>, KernelError> {
+    let mut len = 0;
+    check(get(0, ptr::null_mut(), &mut len), call)?;
+    let mut items = vec![ptr::null_mut(); len as usize];
+    if len > 0 {
+        check(get(len, items.as_mut_ptr(), ptr::null_mut()), call)?;
+    }
+    Ok(items)
+}
+
+/// Returns a string property, such as the name of a device or the build
+/// log of a program.
+fn string(
+    get: impl Fn(usize, *mut c_void, *mut usize) -> i32,
+    call: &str,
+) -> Result<String, KernelError> {
+    let mut len = 0;
+    check(get(0, ptr::null_mut(), &mut len), call)?;
+    let mut bytes = vec![0u8; len];
+    check(
+        get(len, bytes.as_mut_ptr() as *mut c_void, ptr::null_mut()),
+        call,
+    )?;
+    // Without the terminating nul.
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+    bytes.truncate(end);
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// An OpenCL device, with the kernels of this module built for it.
+pub struct Device {
+    api: Arc<Api>,
+    name: String,
+    context: Handle,
+    queue: Handle,
+    program: Handle,
+    // Serializes the computations, which share the queue.
+    lock: Mutex<()>,
+}
+
+// The handles of OpenCL objects may be used from any thread, and the
+// command queue is only used under the lock.
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe {
+            (self.api.release_program)(self.program);
+            (self.api.release_command_queue)(self.queue);
+            (self.api.release_context)(self.context);
+        }
+    }
+}
+
+impl Device {
+    /// Returns every OpenCL device, including implementations of OpenCL on
+    /// the CPU, which are rarely faster than proving on the CPU directly.
+    /// Returns no device if there is no OpenCL library.
+    pub fn all() -> Result<Vec<Device>, KernelError> {
+        Self::of_type(CL_DEVICE_TYPE_ALL)
+    }
+
+    /// Returns the OpenCL GPUs.
+    pub fn gpus() -> Result<Vec<Device>, KernelError> {
+        Self::of_type(CL_DEVICE_TYPE_GPU)
+    }
+
+    fn of_type(device_type: u64) -> Result<Vec<Device>, KernelError> {
+        let api = match Api::load() {
+            Some(api) => Arc::new(api),
+            None => return Ok(vec![]),
+        };
+
+        let platforms = list(
+            |n, platforms, len| unsafe { (api.get_platform_ids)(n, platforms, len) },
+            "clGetPlatformIDs",
+        )?;
+        let mut devices = vec![];
+        for platform in platforms {
+            let ids = list(
+                |n, devices, len| unsafe {
+                    (api.get_device_ids)(platform, device_type, n, devices, len)
+                },
+                "clGetDeviceIDs",
+            );
+            // A platform without devices of the type reports an error.
+            for id in ids.unwrap_or_default() {
+                devices.push(Device::new(api.clone(), id)?);
+            }
+        }
+        Ok(devices)
+    }
+
+    fn new(api: Arc<Api>, id: Handle) -> Result<Device, KernelError> {
+        let name = string(
+            |n, value, len| unsafe { (api.get_device_info)(id, CL_DEVICE_NAME, n, value, len) },
+            "clGetDeviceInfo",
+        )?;
+
+        let mut status = CL_SUCCESS;
+        let context = unsafe {
+            (api.create_context)(ptr::null(), 1, &id, None, ptr::null_mut(), &mut status)
+        };
+        check(status, "clCreateContext")?;
+        let queue = unsafe { (api.create_command_queue)(context, id, 0, &mut status) };
+        if let Err(e) = check(status, "clCreateCommandQueue") {
+            unsafe { (api.release_context)(context) };
+            return Err(e);
+        }
+
+        let source = source();
+        let (text, len) = (source.as_ptr() as *const c_char, source.len());
+        let program =
+            unsafe { (api.create_program_with_source)(context, 1, &text, &len, &mut status) };
+        // Built from here on, so that the objects are released on failure.
+        let device = Device {
+            api,
+            name,
+            context,
+            queue,
+            program,
+            lock: Mutex::new(()),
+        };
+        check(status, "clCreateProgramWithSource")?;
+
+        let options = CString::new("").unwrap();
+        let status = unsafe {
+            (device.api.build_program)(program, 1, &id, options.as_ptr(), None, ptr::null_mut())
+        };
+        if status != CL_SUCCESS {
+            let log = string(
+                |n, value, len| unsafe {
+                    (device.api.get_program_build_info)(
+                        program,
+                        id,
+                        CL_PROGRAM_BUILD_LOG,
+                        n,
+                        value,
+                        len,
+                    )
+                },
+                "clGetProgramBuildInfo",
+            )?;
+            return Err(KernelError(format!(
+                "building the kernels for {} failed: {}",
+                device.name, log
+            )));
+        }
+
+        Ok(device)
+    }
+
+    /// Returns the name of the device.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Registers the kernels of the device with `devices`, for the FFTs over
+    /// the scalars of BLS12-381 and the multiexponentiations in its G1.
+    pub fn register(self, devices: &mut Devices) {
+        let device = Arc::new(self);
+        devices
+            .register_fft::<Fr, Scalar<Fr>, _>(Kernels(device.clone()))
+            .register_multiexp::<G1Projective, _>(Kernels(device));
+    }
+
+    /// Allocates a buffer of `len` limbs, initialized with `data` if given.
+    fn buffer(&self, len: usize, data: Option<&[u64]>) -> Result<Buffer<'_>, KernelError> {
+        let mut status = CL_SUCCESS;
+        let mem = unsafe {
+            (self.api.create_buffer)(
+                self.context,
+                CL_MEM_READ_WRITE,
+                len * 8,
+                ptr::null_mut(),
+                &mut status,
+            )
+        };
+        check(status, "clCreateBuffer")?;
+        let buffer = Buffer { device: self, mem };
+
+        if let Some(data) = data {
+            assert_eq!(data.len(), len);
+            check(
+                unsafe {
+                    (self.api.enqueue_write_buffer)(
+                        self.queue,
+                        mem,
+                        CL_TRUE,
+                        0,
+                        len * 8,
+                        data.as_ptr() as *const c_void,
+                        0,
+                        ptr::null(),
+                        ptr::null_mut(),
+                    )
+                },
+                "clEnqueueWriteBuffer",
+            )?;
+        }
+        Ok(buffer)
+    }
+
+    /// Runs the kernel `name` on `size` work items, with the given
+    /// arguments.
+    fn run(&self, name: &str, size: usize, args: &[Arg]) -> Result<(), KernelError> {
+        let name = CString::new(name).unwrap();
+        let mut status = CL_SUCCESS;
+        let kernel = unsafe { (self.api.create_kernel)(self.program, name.as_ptr(), &mut status) };
+        check(status, "clCreateKernel")?;
+
+        let result = (|| {
+            for (i, arg) in args.iter().enumerate() {
+                let (size, value) = match arg {
+                    Arg::Buffer(buffer) => (
+                        mem::size_of::<Handle>(),
+                        &buffer.mem as *const Handle as *const c_void,
+                    ),
+                    Arg::Uint(value) => {
+                        (mem::size_of::<u32>(), value as *const u32 as *const c_void)
+                    }
+                };
+                check(
+                    unsafe { (self.api.set_kernel_arg)(kernel, i as u32, size, value) },
+                    "clSetKernelArg",
+                )?;
+            }
+            check(
+                unsafe {
+                    (self.api.enqueue_nd_range_kernel)(
+                        self.queue,
+                        kernel,
+                        1,
+                        ptr::null(),
+                        &size,
+                        ptr::null(),
+                        0,
+                        ptr::null(),
+                        ptr::null_mut(),
+                    )
+                },
+                "clEnqueueNDRangeKernel",
+            )?;
+            check(unsafe { (self.api.finish)(self.queue) }, "clFinish")
+        })();
+
+        unsafe { (self.api.release_kernel)(kernel) };
+        result
+    }
+}
+
+/// A buffer on a device.
+struct Buffer<'a> {
+    device: &'a Device,
+    mem: Handle,
+}
+
+impl<'a> Buffer<'a> {
+    fn read(&self, data: &mut [u64]) -> Result<(), KernelError> {
+        check(
+            unsafe {
+                (self.device.api.enqueue_read_buffer)(
+                    self.device.queue,
+                    self.mem,
+                    CL_TRUE,
+                    0,
+                    data.len() * 8,
+                    data.as_mut_ptr() as *mut c_void,
+                    0,
+                    ptr::null(),
+                    ptr::null_mut(),
+                )
+            },
+            "clEnqueueReadBuffer",
+        )
+    }
+}
+
+impl<'a> Drop for Buffer<'a> {
+    fn drop(&mut self) {
+        unsafe { (self.device.api.release_mem_object)(self.mem) };
+    }
+}
+
+enum Arg<'a, 'b> {
+    Buffer(&'a Buffer<'b>),
+    Uint(u32),
+}
+
+/// The kernels of a device, as registered with [`Devices`].
+struct Kernels(Arc<Device>);
+
+impl FftKernel<Fr, Scalar<Fr>> for Kernels {
+    fn fft(&self, a: &mut [Scalar<Fr>], omega: &Fr, log_n: u32) -> Result<(), KernelError> {
+        let n = a.len();
+        if n < 2 {
+            return Ok(());
+        }
+        assert!(log_n < 32);
+        let device = &self.0;
+        let _lock = device.lock.lock().unwrap();
+
+        // The twiddles are in Montgomery form, as integers times 2^256.
+        let mut twiddle = Field::pow_vartime(&Fr::from(2), [256]);
+        let mut twiddles = Vec::with_capacity(2 * n);
+        for _ in 0..n / 2 {
+            twiddles.extend_from_slice(&scalar_limbs(&twiddle));
+            twiddle *= omega;
+        }
+        let mut limbs = Vec::with_capacity(4 * n);
+        for value in a.iter() {
+            limbs.extend_from_slice(&scalar_limbs(&value.0));
+        }
+
+        let values = device.buffer(limbs.len(), Some(&limbs))?;
+        let twiddles = device.buffer(twiddles.len(), Some(&twiddles))?;
+        device.run(
+            "fr_bit_reverse",
+            n,
+            &[Arg::Buffer(&values), Arg::Uint(log_n)],
+        )?;
+        for round in 0..log_n {
+            let half = 1 << round;
+            device.run(
+                "fr_butterflies",
+                n / 2,
+                &[
+                    Arg::Buffer(&values),
+                    Arg::Buffer(&twiddles),
+                    Arg::Uint(half as u32),
+                    Arg::Uint((n / (2 * half)) as u32),
+                ],
+            )?;
+        }
+        values.read(&mut limbs)?;
+
+        // `a` is left unchanged unless every element is valid.
+        let mut results = Vec::with_capacity(n);
+        for limbs in limbs.chunks(4) {
+            let mut bytes = [0; 32];
+            for (bytes, limb) in bytes.chunks_mut(8).zip(limbs) {
+                bytes.copy_from_slice(&limb.to_le_bytes());
+            }
+            let value: Option<Fr> = Fr::from_bytes(&bytes).into();
+            results.push(
+                value.ok_or_else(|| KernelError("the FFT returned unreduced elements".into()))?,
+            );
+        }
+        for (a, result) in a.iter_mut().zip(results) {
+            a.0 = result;
+        }
+        Ok(())
+    }
+}
+
+impl MultiexpKernel<G1Projective> for Kernels {
+    fn multiexp(
+        &self,
+        bases: &[G1Affine],
+        exponents: &[&Exponent<Fr>],
+    ) -> Result<G1Projective, KernelError> {
+        let n = bases.len();
+        if n == 0 {
+            return Ok(G1Projective::identity());
+        }
+        let device = &self.0;
+        let _lock = device.lock.lock().unwrap();
+
+        // The coordinates as integers, which the device converts.
+        let mut points = Vec::with_capacity(12 * n);
+        for base in bases {
+            let bytes = base.to_uncompressed();
+            for coordinate in bytes.chunks(48) {
+                for limb in coordinate.rchunks(8) {
+                    let mut be = [0; 8];
+                    be.copy_from_slice(limb);
+                    points.push(u64::from_be_bytes(be));
+                }
+            }
+        }
+        let mut limbs = Vec::with_capacity(4 * n);
+        for exponent in exponents {
+            limbs.extend(
+                exponent
+                    .as_bitslice()
+                    .chunks(64)
+                    .map(|bits| bits.load_le::<u64>()),
+            );
+        }
+
+        let chunks = ((n + MIN_CHUNK - 1) / MIN_CHUNK).min(MAX_CHUNKS);
+        let chunk_size = (n + chunks - 1) / chunks;
+        let chunks = (n + chunk_size - 1) / chunk_size;
+
+        let points = device.buffer(points.len(), Some(&points))?;
+        let exponents = device.buffer(limbs.len(), Some(&limbs))?;
+        let buckets = device.buffer(chunks * WINDOWS * BUCKETS * G1_LIMBS, None)?;
+        let sums = device.buffer(chunks * WINDOWS * G1_LIMBS, None)?;
+        let out = device.buffer(13, None)?;
+        device.run("g1_prepare_bases", n, &[Arg::Buffer(&points)])?;
+        device.run(
+            "g1_bucket_sums",
+            chunks * WINDOWS,
+            &[
+                Arg::Buffer(&points),
+                Arg::Buffer(&exponents),
+                Arg::Buffer(&buckets),
+                Arg::Buffer(&sums),
+                Arg::Uint(n as u32),
+                Arg::Uint(chunk_size as u32),
+            ],
+        )?;
+        device.run(
+            "g1_combine",
+            1,
+            &[
+                Arg::Buffer(&sums),
+                Arg::Buffer(&out),
+                Arg::Uint(chunks as u32),
+            ],
+        )?;
+        let mut result = [0; 13];
+        out.read(&mut result)?;
+
+        if result[12] == 1 {
+            return Ok(G1Projective::identity());
+        }
+        let mut bytes = [0; 96];
+        for (bytes, limb) in bytes
+            .chunks_mut(8)
+            .zip(result[..6].iter().rev().chain(result[6..12].iter().rev()))
+        {
+            bytes.copy_from_slice(&limb.to_be_bytes());
+        }
+        let point: Option<G1Affine> = G1Affine::from_uncompressed(&bytes).into();
+        point
+            .map(G1Projective::from)
+            .ok_or_else(|| KernelError("the multiexponentiation returned a point not in G1".into()))
+    }
+}
+
+/// The limbs of a scalar, as an integer.
+fn scalar_limbs(value: &Fr) -> [u64; 4] {
+    let bytes = value.to_bytes();
+    let mut limbs = [0; 4];
+    for (limb, bytes) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        let mut le = [0; 8];
+        le.copy_from_slice(bytes);
+        *limb = u64::from_le_bytes(le);
+    }
+    limbs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::EvaluationDomain;
+    use crate::multicore::Worker;
+    use crate::multiexp::{multiexp, FullDensity};
+    use ff::PrimeField;
+    use futures::Future;
+    use group::{Curve, Group};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// The first device, if the machine has one, with kernels offloading
+    /// the smallest computations.
+    fn device() -> Option<Arc<Device>> {
+        let device = Device::all().unwrap().pop()?;
+        Some(Arc::new(device))
+    }
+
+    struct Small(Arc<Device>);
+
+    impl FftKernel<Fr, Scalar<Fr>> for Small {
+        fn min_size(&self) -> usize {
+            0
+        }
+
+        fn fft(&self, a: &mut [Scalar<Fr>], omega: &Fr, log_n: u32) -> Result<(), KernelError> {
+            Kernels(self.0.clone()).fft(a, omega, log_n)
+        }
+    }
+
+    impl MultiexpKernel<G1Projective> for Small {
+        fn min_size(&self) -> usize {
+            0
+        }
+
+        fn multiexp(
+            &self,
+            bases: &[G1Affine],
+            exponents: &[&Exponent<Fr>],
+        ) -> Result<G1Projective, KernelError> {
+            Kernels(self.0.clone()).multiexp(bases, exponents)
+        }
+    }
+
+    #[test]
+    fn kernels_match_the_cpu() {
+        let device = match device() {
+            Some(device) => device,
+            // Without a device there is nothing to compare.
+            None => return,
+        };
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut devices = Devices::new();
+        devices
+            .register_fft::<Fr, Scalar<Fr>, _>(Small(device.clone()))
+            .register_multiexp::<G1Projective, _>(Small(device));
+        let devices = Arc::new(devices);
+        let cpu = Worker::new();
+        let offloading = cpu.with_devices(devices.clone());
+
+        for &log_n in &[1, 4, 10] {
+            let coeffs = (0..1 << log_n)
+                .map(|_| Scalar(Fr::random(&mut rng)))
+                .collect::<Vec<_>>();
+            let mut on_cpu = EvaluationDomain::from_coeffs(coeffs.clone()).unwrap();
+            let mut offloaded = EvaluationDomain::from_coeffs(coeffs).unwrap();
+            on_cpu.fft(&cpu);
+            offloaded.fft(&offloading);
+            assert!(on_cpu.as_ref() == offloaded.as_ref());
+        }
+
+        for &n in &[1, 100, 3000] {
+            let mut bases = (0..n)
+                .map(|_| G1Projective::random(&mut rng).to_affine())
+                .collect::<Vec<_>>();
+            let mut exponents = (0..n)
+                .map(|_| Fr::random(&mut rng).to_le_bits())
+                .collect::<Vec<_>>();
+            // Equal and opposite bases, and small and zero exponents.
+            if n > 4 {
+                bases[1] = bases[0];
+                exponents[1] = exponents[0];
+                bases[3] = -bases[2];
+                exponents[3] = exponents[2];
+                exponents[4] = Fr::from(3).to_le_bits();
+                exponents[5] = Fr::zero().to_le_bits();
+            }
+            let (bases, exponents) = (Arc::new(bases), Arc::new(exponents));
+            let run = |worker: &Worker| -> G1Projective {
+                multiexp(worker, (bases.clone(), 0), FullDensity, exponents.clone())
+                    .wait()
+                    .unwrap()
+            };
+            assert_eq!(run(&offloading), run(&cpu));
+        }
+        assert_eq!(devices.failures(), 0);
+        assert_eq!(devices.offloaded(), 6);
+    }
+
+    #[test]
+    fn source() {
+        // The kernels are built on the device, so their source is checked
+        // for the definitions that the host relies on.
+        let source = super::source();
+        for kernel in &[
+            "fr_bit_reverse",
+            "fr_butterflies",
+            "g1_prepare_bases",
+            "g1_bucket_sums",
+            "g1_combine",
+        ] {
+            assert!(source.contains(&format!("__kernel void {}(", kernel)));
+        }
+        assert!(!source.contains("FIELD"));
+    }
+}
@@ -0,0 +1,34 @@
+// Arithmetic on 64-bit limbs shared by the fields.
+
+// Returns the low limb of a + b * c + *carry, and sets *carry to the high
+// limb. The sum cannot overflow 128 bits.
+ulong mac(ulong a, ulong b, ulong c, ulong *carry) {
+  ulong lo = b * c;
+  ulong hi = mul_hi(b, c);
+  lo += a;
+  hi += lo < a;
+  lo += *carry;
+  hi += lo < *carry;
+  *carry = hi;
+  return lo;
+}
+
+// Returns a + b + *carry, and sets *carry to the carry out.
+ulong adc(ulong a, ulong b, ulong *carry) {
+  ulong sum = a + b;
+  ulong out = sum < a;
+  sum += *carry;
+  out += sum < *carry;
+  *carry = out;
+  return sum;
+}
+
+// Returns a - b - *borrow, and sets *borrow to the borrow out.
+ulong sbb(ulong a, ulong b, ulong *borrow) {
+  ulong diff = a - b;
+  ulong out = a < b;
+  out += diff < *borrow;
+  diff -= *borrow;
+  *borrow = out;
+  return diff;
+}
@@ -0,0 +1,110 @@
+// The group of points of y^2 = x^3 + b over fq, in Jacobian coordinates
+// (X : Y : Z) for (X / Z^2, Y / Z^3), with Z = 0 at infinity. The
+// coordinates are in Montgomery form.
+
+typedef struct {
+  fq x;
+  fq y;
+  fq z;
+} g1;
+
+// A point other than infinity, in affine coordinates.
+typedef struct {
+  fq x;
+  fq y;
+} g1_affine;
+
+g1 g1_identity() {
+  g1 r;
+  r.x = fq_zero();
+  r.y = fq_one();
+  r.z = fq_zero();
+  return r;
+}
+
+bool g1_is_identity(g1 p) {
+  return fq_is_zero(p.z);
+}
+
+// dbl-2009-l, for curves with a = 0.
+g1 g1_double(g1 p) {
+  if (g1_is_identity(p)) {
+    return p;
+  }
+
+  fq a = fq_sqr(p.x);
+  fq b = fq_sqr(p.y);
+  fq c = fq_sqr(b);
+  fq d = fq_double(fq_sub(fq_sub(fq_sqr(fq_add(p.x, b)), a), c));
+  fq e = fq_add(fq_double(a), a);
+  fq f = fq_sqr(e);
+
+  g1 r;
+  r.x = fq_sub(f, fq_double(d));
+  r.y = fq_sub(fq_mul(e, fq_sub(d, r.x)), fq_double(fq_double(fq_double(c))));
+  r.z = fq_double(fq_mul(p.y, p.z));
+  return r;
+}
+
+// madd-2007-bl.
+g1 g1_add_mixed(g1 p, g1_affine q) {
+  if (g1_is_identity(p)) {
+    g1 r;
+    r.x = q.x;
+    r.y = q.y;
+    r.z = fq_one();
+    return r;
+  }
+
+  fq z1z1 = fq_sqr(p.z);
+  fq u2 = fq_mul(q.x, z1z1);
+  fq s2 = fq_mul(fq_mul(q.y, p.z), z1z1);
+  fq h = fq_sub(u2, p.x);
+  fq r = fq_double(fq_sub(s2, p.y));
+  if (fq_is_zero(h)) {
+    return fq_is_zero(r) ? g1_double(p) : g1_identity();
+  }
+
+  fq hh = fq_sqr(h);
+  fq i = fq_double(fq_double(hh));
+  fq j = fq_mul(h, i);
+  fq v = fq_mul(p.x, i);
+
+  g1 sum;
+  sum.x = fq_sub(fq_sub(fq_sub(fq_sqr(r), j), v), v);
+  sum.y = fq_sub(fq_mul(r, fq_sub(v, sum.x)), fq_double(fq_mul(p.y, j)));
+  sum.z = fq_sub(fq_sub(fq_sqr(fq_add(p.z, h)), z1z1), hh);
+  return sum;
+}
+
+// add-2007-bl.
+g1 g1_add(g1 p, g1 q) {
+  if (g1_is_identity(p)) {
+    return q;
+  }
+  if (g1_is_identity(q)) {
+    return p;
+  }
+
+  fq z1z1 = fq_sqr(p.z);
+  fq z2z2 = fq_sqr(q.z);
+  fq u1 = fq_mul(p.x, z2z2);
+  fq u2 = fq_mul(q.x, z1z1);
+  fq s1 = fq_mul(fq_mul(p.y, q.z), z2z2);
+  fq s2 = fq_mul(fq_mul(q.y, p.z), z1z1);
+  fq h = fq_sub(u2, u1);
+  fq r = fq_double(fq_sub(s2, s1));
+  if (fq_is_zero(h)) {
+    return fq_is_zero(r) ? g1_double(p) : g1_identity();
+  }
+
+  fq i = fq_sqr(fq_double(h));
+  fq j = fq_mul(h, i);
+  fq v = fq_mul(u1, i);
+
+  g1 sum;
+  sum.x = fq_sub(fq_sub(fq_sub(fq_sqr(r), j), v), v);
+  sum.y = fq_sub(fq_mul(r, fq_sub(v, sum.x)), fq_double(fq_mul(s1, j)));
+  sum.z = fq_mul(fq_sub(fq_sub(fq_sqr(fq_add(p.z, q.z)), z1z1), z2z2), h);
+  return sum;
+}
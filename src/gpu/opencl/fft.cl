@@ -0,0 +1,36 @@
+// A radix-2 decimation in time FFT over fr, in place: the elements are
+// permuted to bit-reversed order, then combined by log_n rounds of
+// butterflies, with one kernel launch each.
+
+uint bit_reverse(uint i, uint log_n) {
+  uint r = 0;
+  for (uint b = 0; b < log_n; b++) {
+    r = (r << 1) | ((i >> b) & 1);
+  }
+  return r;
+}
+
+__kernel void fr_bit_reverse(__global fr *a, uint log_n) {
+  uint i = get_global_id(0);
+  uint j = bit_reverse(i, log_n);
+  if (i < j) {
+    fr t = a[i];
+    a[i] = a[j];
+    a[j] = t;
+  }
+}
+
+// One round, in which each butterfly combines elements `half` apart. The
+// elements are integers, and the twiddles omega^i, for i < n / 2, are in
+// Montgomery form, so that their products are integers again.
+__kernel void fr_butterflies(__global fr *a, __global const fr *twiddles,
+                             uint half, uint stride) {
+  uint k = get_global_id(0);
+  uint j = k % half;
+  uint i = (k / half) * 2 * half + j;
+
+  fr t = fr_mul(a[i + half], twiddles[j * stride]);
+  fr u = a[i];
+  a[i] = fr_add(u, t);
+  a[i + half] = fr_sub(u, t);
+}
@@ -0,0 +1,155 @@
+// Arithmetic modulo FIELD_P, of FIELD_LIMBS little-endian 64-bit limbs.
+// Elements are reduced, and multiplied in Montgomery form: FIELD_mul
+// returns a * b / R, with R = 2^(64 FIELD_LIMBS).
+
+typedef struct {
+  ulong v[FIELD_LIMBS];
+} FIELD;
+
+FIELD FIELD_zero() {
+  FIELD r;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r.v[i] = 0;
+  }
+  return r;
+}
+
+// The one of the Montgomery form, R mod FIELD_P.
+FIELD FIELD_one() {
+  FIELD r;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r.v[i] = FIELD_R[i];
+  }
+  return r;
+}
+
+bool FIELD_is_zero(FIELD a) {
+  ulong acc = 0;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    acc |= a.v[i];
+  }
+  return acc == 0;
+}
+
+bool FIELD_eq(FIELD a, FIELD b) {
+  ulong acc = 0;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    acc |= a.v[i] ^ b.v[i];
+  }
+  return acc == 0;
+}
+
+// Subtracts FIELD_P from a + carry * 2^(64 FIELD_LIMBS) if that is at
+// least FIELD_P.
+FIELD FIELD_reduce(FIELD a, ulong carry) {
+  FIELD r;
+  ulong borrow = 0;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r.v[i] = sbb(a.v[i], FIELD_P[i], &borrow);
+  }
+  return carry >= borrow ? r : a;
+}
+
+FIELD FIELD_add(FIELD a, FIELD b) {
+  FIELD r;
+  ulong carry = 0;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r.v[i] = adc(a.v[i], b.v[i], &carry);
+  }
+  return FIELD_reduce(r, carry);
+}
+
+FIELD FIELD_sub(FIELD a, FIELD b) {
+  FIELD r;
+  ulong borrow = 0;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r.v[i] = sbb(a.v[i], b.v[i], &borrow);
+  }
+  if (borrow) {
+    ulong carry = 0;
+    for (int i = 0; i < FIELD_LIMBS; i++) {
+      r.v[i] = adc(r.v[i], FIELD_P[i], &carry);
+    }
+  }
+  return r;
+}
+
+FIELD FIELD_double(FIELD a) {
+  return FIELD_add(a, a);
+}
+
+// The coarsely integrated operand scanning Montgomery multiplication.
+FIELD FIELD_mul(FIELD a, FIELD b) {
+  ulong t[FIELD_LIMBS + 2];
+  for (int i = 0; i < FIELD_LIMBS + 2; i++) {
+    t[i] = 0;
+  }
+
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    ulong carry = 0;
+    for (int j = 0; j < FIELD_LIMBS; j++) {
+      t[j] = mac(t[j], a.v[j], b.v[i], &carry);
+    }
+    ulong high = 0;
+    t[FIELD_LIMBS] = adc(t[FIELD_LIMBS], carry, &high);
+    t[FIELD_LIMBS + 1] = high;
+
+    // Adds the multiple of FIELD_P that clears the low limb, and shifts.
+    ulong m = t[0] * FIELD_INV;
+    carry = 0;
+    mac(t[0], m, FIELD_P[0], &carry);
+    for (int j = 1; j < FIELD_LIMBS; j++) {
+      t[j - 1] = mac(t[j], m, FIELD_P[j], &carry);
+    }
+    high = 0;
+    t[FIELD_LIMBS - 1] = adc(t[FIELD_LIMBS], carry, &high);
+    t[FIELD_LIMBS] = t[FIELD_LIMBS + 1] + high;
+  }
+
+  FIELD r;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r.v[i] = t[i];
+  }
+  return FIELD_reduce(r, t[FIELD_LIMBS]);
+}
+
+FIELD FIELD_sqr(FIELD a) {
+  return FIELD_mul(a, a);
+}
+
+// Converts a reduced integer to the Montgomery form.
+FIELD FIELD_to_montgomery(FIELD a) {
+  FIELD r2;
+  for (int i = 0; i < FIELD_LIMBS; i++) {
+    r2.v[i] = FIELD_R2[i];
+  }
+  return FIELD_mul(a, r2);
+}
+
+// Converts an element in Montgomery form back to an integer.
+FIELD FIELD_from_montgomery(FIELD a) {
+  FIELD one = FIELD_zero();
+  one.v[0] = 1;
+  return FIELD_mul(a, one);
+}
+
+// Returns the inverse of a non-zero a, as a^(FIELD_P - 2).
+FIELD FIELD_inverse(FIELD a) {
+  ulong e[FIELD_LIMBS];
+  ulong borrow = 0;
+  e[0] = sbb(FIELD_P[0], 2, &borrow);
+  for (int i = 1; i < FIELD_LIMBS; i++) {
+    e[i] = sbb(FIELD_P[i], 0, &borrow);
+  }
+
+  FIELD r = FIELD_one();
+  for (int i = FIELD_LIMBS - 1; i >= 0; i--) {
+    for (int j = 63; j >= 0; j--) {
+      r = FIELD_sqr(r);
+      if ((e[i] >> j) & 1) {
+        r = FIELD_mul(r, a);
+      }
+    }
+  }
+  return r;
+}
@@ -0,0 +1,88 @@
+// A multiexponentiation in G1 with the bucket method. The exponents, of
+// SCALAR_LIMBS limbs each, are cut into windows of WINDOW_BITS bits, and
+// the bases into chunks. Each work item sums the buckets of one window over
+// one chunk, which g1_combine then adds up.
+
+#define BUCKETS ((1 << WINDOW_BITS) - 1)
+#define WINDOWS ((SCALAR_LIMBS * 64 + WINDOW_BITS - 1) / WINDOW_BITS)
+
+// Converts the coordinates of the bases from integers to the Montgomery
+// form.
+__kernel void g1_prepare_bases(__global g1_affine *bases) {
+  uint i = get_global_id(0);
+  bases[i].x = fq_to_montgomery(bases[i].x);
+  bases[i].y = fq_to_montgomery(bases[i].y);
+}
+
+uint window(__global const ulong *exponent, uint w) {
+  uint bit = w * WINDOW_BITS;
+  uint limb = bit / 64;
+  uint shift = bit % 64;
+  ulong digit = exponent[limb] >> shift;
+  if (shift + WINDOW_BITS > 64 && limb + 1 < SCALAR_LIMBS) {
+    digit |= exponent[limb + 1] << (64 - shift);
+  }
+  return digit & BUCKETS;
+}
+
+// Work item `chunk * WINDOWS + w` sums the bases of the chunk into buckets
+// by their digit in window `w`, in `buckets`, and then the buckets, each
+// as many times as its digit, into `sums`.
+__kernel void g1_bucket_sums(__global const g1_affine *bases,
+                             __global const ulong *exponents,
+                             __global g1 *buckets, __global g1 *sums, uint n,
+                             uint chunk_size) {
+  uint id = get_global_id(0);
+  uint w = id % WINDOWS;
+  uint start = (id / WINDOWS) * chunk_size;
+  uint end = min(start + chunk_size, n);
+  __global g1 *own = buckets + id * BUCKETS;
+
+  for (uint i = 0; i < BUCKETS; i++) {
+    own[i] = g1_identity();
+  }
+  for (uint i = start; i < end; i++) {
+    uint digit = window(exponents + i * SCALAR_LIMBS, w);
+    if (digit != 0) {
+      own[digit - 1] = g1_add_mixed(own[digit - 1], bases[i]);
+    }
+  }
+
+  g1 running = g1_identity();
+  g1 sum = g1_identity();
+  for (int i = BUCKETS - 1; i >= 0; i--) {
+    running = g1_add(running, own[i]);
+    sum = g1_add(sum, running);
+  }
+  sums[id] = sum;
+}
+
+// Adds up the sums of `chunks` chunks by window, and writes the affine
+// coordinates of the result as integers to `out`, followed by 1 if it is
+// the point at infinity and 0 otherwise.
+__kernel void g1_combine(__global const g1 *sums, __global ulong *out,
+                         uint chunks) {
+  g1 acc = g1_identity();
+  for (int w = WINDOWS - 1; w >= 0; w--) {
+    for (int i = 0; i < WINDOW_BITS; i++) {
+      acc = g1_double(acc);
+    }
+    for (uint c = 0; c < chunks; c++) {
+      acc = g1_add(acc, sums[c * WINDOWS + w]);
+    }
+  }
+
+  fq x = fq_zero();
+  fq y = fq_zero();
+  if (!g1_is_identity(acc)) {
+    fq z_inv = fq_inverse(acc.z);
+    fq z_inv2 = fq_sqr(z_inv);
+    x = fq_from_montgomery(fq_mul(acc.x, z_inv2));
+    y = fq_from_montgomery(fq_mul(acc.y, fq_mul(z_inv2, z_inv)));
+  }
+  for (int i = 0; i < fq_LIMBS; i++) {
+    out[i] = x.v[i];
+    out[fq_LIMBS + i] = y.v[i];
+  }
+  out[2 * fq_LIMBS] = g1_is_identity(acc);
+}
@@ -0,0 +1,1194 @@
+//! Aggregation of Groth16 proofs into one logarithmic-size proof.
+//!
+//! [`verify_proofs_batch`](super::verify_proofs_batch) checks many proofs at
+//! once, but the verifier still needs every proof. [`aggregate_proofs`]
+//! instead turns `n` proofs of the same circuit into one [`AggregateProof`]
+//! of `O(log n)` group elements, which [`verify_aggregate_proof`] checks in
+//! `O(log n)` pairings plus a multiexponentiation over the public inputs,
+//! following [SnarkPack].
+//!
+//! With a random `r`, the proofs verify if and only if, except with
+//! negligible probability,
+//!
+//! ```text
+//! Σ r^i·e(A_i, B_i) = (Σ r^i)·e(α, β) + e(Σ r^i·S_i, γ) + e(Σ r^i·C_i, δ)
+//! ```
+//!
+//! where `S_i` is the combination of the IC points with the inputs of proof
+//! `i`. The prover commits to the vectors `A`, `B` and `C` with pairing
+//! commitments, derives `r` from the commitments, and proves the two sums
+//! on the left and the right of the equation that involve the proofs with
+//! two inner product arguments: TIPP for the pairing product of `A` and `B`,
+//! and MIPP for the multiexponentiation of `C`. Each halves its vectors at
+//! every round, and ends with KZG openings that show that the folded keys
+//! were folded from the [`AggregationKey`].
+//!
+//! The key holds powers of two secrets in both groups, which must be
+//! independent of the parameters of the circuit. The one made by
+//! [`AggregationKey::new`] is for tests; in production, the powers come from
+//! two existing powers-of-tau ceremonies, and one key serves every circuit
+//! over the same curve.
+//!
+//! Challenges are derived from a caller-provided [`Transcript`]. Elements of
+//! the target group have no canonical encoding in [`pairing`], so the engine
+//! provides one with [`GtEncoding`]; it is implemented for BLS12-381 and,
+//! with the `bn254` feature, for BN254.
+//!
+//! # Format
+//!
+//! [`AggregateProof::write`] emits `com_ab`, `com_c`, `z_ab` and `z_c`, then
+//! the number of rounds as a big-endian `u32`, the rounds of TIPP followed by
+//! its folded `A` and `w` and their openings, then its folded `B` and `v` and
+//! their openings, and finally the rounds of MIPP followed by its folded `C`,
+//! its folded `v` and their openings. Points are compressed, and elements of
+//! the target group are encoded by [`GtEncoding`]. Reading them back needs
+//! [`GtDecoding`], which `bls12_381` does not allow, as it does not expose
+//! the coefficients of its target group; aggregate proofs over BLS12-381 can
+//! be written but not read.
+//!
+//! [SnarkPack]: https://eprint.iacr.org/2021/529
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Curve, Group, GroupEncoding, GroupOps, GroupOpsOwned};
+use pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::ops::{AddAssign, Mul, Neg, SubAssign};
+
+use super::{PreparedVerifyingKey, Proof};
+use crate::metrics;
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+use crate::transcript::Transcript;
+use crate::{SynthesisError, VerificationError};
+
+/// An engine with a canonical encoding of the elements of its target group,
+/// which [`pairing`] does not define.
+pub trait GtEncoding: Engine {
+    /// The length of an encoded element.
+    const GT_BYTES: usize;
+
+    /// Encodes `gt` as the big-endian encodings of the twelve coefficients
+    /// of its `Fp12`, in the order of the tower: `c0.c0.c0`, `c0.c0.c1`,
+    /// `c0.c1.c0`, and so on.
+    fn gt_to_bytes(gt: &Self::Gt) -> Vec<u8>;
+}
+
+/// An engine that decodes the encodings of [`GtEncoding`].
+pub trait GtDecoding: GtEncoding {
+    /// Decodes an element, or returns `None` if `bytes` does not encode an
+    /// element of the target group.
+    fn gt_from_bytes(bytes: &[u8]) -> Option<Self::Gt>;
+}
+
+#[cfg(any(test, feature = "bls12_381"))]
+impl GtEncoding for bls12_381::Bls12 {
+    const GT_BYTES: usize = 576;
+
+    fn gt_to_bytes(gt: &bls12_381::Gt) -> Vec<u8> {
+        // The coefficients are private, but the `Debug` output of the element
+        // writes each of them as `0x` and its 48 big-endian bytes in
+        // hexadecimal, in the order of the tower.
+        let debug = format!("{:?}", gt);
+        let bytes = debug
+            .split("0x")
+            .skip(1)
+            .flat_map(|coefficient| {
+                (0..48).map(move |i| {
+                    u8::from_str_radix(&coefficient[2 * i..2 * i + 2], 16)
+                        .expect("coefficients are written in hexadecimal")
+                })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(bytes.len(), Self::GT_BYTES, "unexpected Gt format");
+        bytes
+    }
+}
+
+#[cfg(feature = "bn254")]
+impl GtEncoding for crate::bn254::Bn254 {
+    const GT_BYTES: usize = 384;
+
+    fn gt_to_bytes(gt: &crate::bn254::Gt) -> Vec<u8> {
+        gt.to_bytes_be().to_vec()
+    }
+}
+
+#[cfg(feature = "bn254")]
+impl GtDecoding for crate::bn254::Bn254 {
+    fn gt_from_bytes(bytes: &[u8]) -> Option<crate::bn254::Gt> {
+        if bytes.len() != Self::GT_BYTES {
+            return None;
+        }
+        let mut repr = [0; 384];
+        repr.copy_from_slice(bytes);
+        crate::bn254::Gt::from_bytes_be(&repr)
+    }
+}
+
+/// The structured reference string of the aggregate prover: `g^{a^i}` and
+/// `g^{b^i}` for `i < 2n`, and `h^{a^i}` and `h^{b^i}` for `i < n`, for
+/// aggregating up to `n` proofs.
+#[derive(Clone)]
+pub struct AggregationKey<E: Engine> {
+    g_alpha: Vec<E::G1Affine>,
+    g_beta: Vec<E::G1Affine>,
+    h_alpha: Vec<E::G2Affine>,
+    h_beta: Vec<E::G2Affine>,
+}
+
+/// The part of an [`AggregationKey`] that the aggregate verifier needs.
+#[derive(Clone)]
+pub struct AggregationVerifyingKey<E: Engine> {
+    n: usize,
+    g: E::G1Affine,
+    h: E::G2Affine,
+    g_alpha: E::G1Affine,
+    g_beta: E::G1Affine,
+    h_alpha: E::G2Affine,
+    h_beta: E::G2Affine,
+}
+
+impl<E: Engine> AggregationKey<E> {
+    /// Creates a key for up to `n` proofs, rounded up to a power of two, from
+    /// secrets drawn from `rng`.
+    pub fn new<R: RngCore>(n: usize, rng: &mut R) -> Self {
+        let n = n.next_power_of_two().max(2);
+        let g1 = |powers: Vec<E::Fr>| {
+            let g = E::G1Affine::generator();
+            powers.iter().map(|p| (g * p).to_affine()).collect()
+        };
+        let g2 = |powers: Vec<E::Fr>| {
+            let h = E::G2Affine::generator();
+            powers.iter().map(|p| (h * p).to_affine()).collect()
+        };
+
+        let (a, b) = (E::Fr::random(&mut *rng), E::Fr::random(&mut *rng));
+        AggregationKey {
+            g_alpha: g1(powers(a, 2 * n)),
+            g_beta: g1(powers(b, 2 * n)),
+            h_alpha: g2(powers(a, n)),
+            h_beta: g2(powers(b, n)),
+        }
+    }
+
+    /// Returns the number of proofs the key can aggregate.
+    pub fn max_proofs(&self) -> usize {
+        self.h_alpha.len()
+    }
+
+    pub fn verifying_key(&self) -> AggregationVerifyingKey<E> {
+        AggregationVerifyingKey {
+            n: self.max_proofs(),
+            g: self.g_alpha[0],
+            h: self.h_alpha[0],
+            g_alpha: self.g_alpha[1],
+            g_beta: self.g_beta[1],
+            h_alpha: self.h_alpha[1],
+            h_beta: self.h_beta[1],
+        }
+    }
+}
+
+/// A commitment to a vector under the `a` and `b` halves of a key.
+type Commitment<E> = (<E as Engine>::Gt, <E as Engine>::Gt);
+
+struct TippRound<E: Engine> {
+    z_l: E::Gt,
+    z_r: E::Gt,
+    com_l: Commitment<E>,
+    com_r: Commitment<E>,
+}
+
+struct MippRound<E: Engine> {
+    z_l: E::G1Affine,
+    z_r: E::G1Affine,
+    com_l: Commitment<E>,
+    com_r: Commitment<E>,
+}
+
+/// A proof that `Σ r^i·e(A_i, B_i) = z_ab`, with the folded vectors and
+/// keys, and the KZG openings of the keys.
+struct TippProof<E: Engine> {
+    rounds: Vec<TippRound<E>>,
+    a: E::G1Affine,
+    b: E::G2Affine,
+    v: (E::G2Affine, E::G2Affine),
+    w: (E::G1Affine, E::G1Affine),
+    v_openings: (E::G2Affine, E::G2Affine),
+    w_openings: (E::G1Affine, E::G1Affine),
+}
+
+/// A proof that `Σ r^i·C_i = z_c`, with the folded vector and key, and the
+/// KZG openings of the key.
+struct MippProof<E: Engine> {
+    rounds: Vec<MippRound<E>>,
+    c: E::G1Affine,
+    v: (E::G2Affine, E::G2Affine),
+    v_openings: (E::G2Affine, E::G2Affine),
+}
+
+/// Proofs of a circuit, aggregated by [`aggregate_proofs`].
+pub struct AggregateProof<E: Engine> {
+    com_ab: Commitment<E>,
+    com_c: Commitment<E>,
+    z_ab: E::Gt,
+    z_c: E::G1Affine,
+    tipp: TippProof<E>,
+    mipp: MippProof<E>,
+}
+
+impl<E: GtEncoding> AggregateProof<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let writer = &mut writer;
+        write_commitment::<E, _>(writer, &self.com_ab)?;
+        write_commitment::<E, _>(writer, &self.com_c)?;
+        writer.write_all(&E::gt_to_bytes(&self.z_ab))?;
+        writer.write_all(self.z_c.to_bytes().as_ref())?;
+
+        writer.write_u32::<BigEndian>(self.tipp.rounds.len() as u32)?;
+        for round in &self.tipp.rounds {
+            writer.write_all(&E::gt_to_bytes(&round.z_l))?;
+            writer.write_all(&E::gt_to_bytes(&round.z_r))?;
+            write_commitment::<E, _>(writer, &round.com_l)?;
+            write_commitment::<E, _>(writer, &round.com_r)?;
+        }
+        let tipp = &self.tipp;
+        for p in &[
+            tipp.a,
+            tipp.w.0,
+            tipp.w.1,
+            tipp.w_openings.0,
+            tipp.w_openings.1,
+        ] {
+            writer.write_all(p.to_bytes().as_ref())?;
+        }
+        for p in &[
+            tipp.b,
+            tipp.v.0,
+            tipp.v.1,
+            tipp.v_openings.0,
+            tipp.v_openings.1,
+        ] {
+            writer.write_all(p.to_bytes().as_ref())?;
+        }
+
+        for round in &self.mipp.rounds {
+            writer.write_all(round.z_l.to_bytes().as_ref())?;
+            writer.write_all(round.z_r.to_bytes().as_ref())?;
+            write_commitment::<E, _>(writer, &round.com_l)?;
+            write_commitment::<E, _>(writer, &round.com_r)?;
+        }
+        let mipp = &self.mipp;
+        writer.write_all(mipp.c.to_bytes().as_ref())?;
+        for p in &[mipp.v.0, mipp.v.1, mipp.v_openings.0, mipp.v_openings.1] {
+            writer.write_all(p.to_bytes().as_ref())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: GtDecoding> AggregateProof<E> {
+    /// Reads a proof written by [`AggregateProof::write`], checking that each
+    /// of its elements is in its group.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let reader = &mut reader;
+        let com_ab = read_commitment::<E, _>(reader)?;
+        let com_c = read_commitment::<E, _>(reader)?;
+        let z_ab = read_gt::<E, _>(reader)?;
+        let z_c = read_point(reader)?;
+
+        let rounds = reader.read_u32::<BigEndian>()?;
+        // Not preallocated, so that a corrupted count fails at the end of the
+        // data rather than on allocation.
+        let mut tipp_rounds = vec![];
+        for _ in 0..rounds {
+            tipp_rounds.push(TippRound {
+                z_l: read_gt::<E, _>(reader)?,
+                z_r: read_gt::<E, _>(reader)?,
+                com_l: read_commitment::<E, _>(reader)?,
+                com_r: read_commitment::<E, _>(reader)?,
+            });
+        }
+        let tipp = TippProof {
+            rounds: tipp_rounds,
+            a: read_point(reader)?,
+            w: (read_point(reader)?, read_point(reader)?),
+            w_openings: (read_point(reader)?, read_point(reader)?),
+            b: read_point(reader)?,
+            v: (read_point(reader)?, read_point(reader)?),
+            v_openings: (read_point(reader)?, read_point(reader)?),
+        };
+
+        let mut mipp_rounds = vec![];
+        for _ in 0..rounds {
+            mipp_rounds.push(MippRound {
+                z_l: read_point(reader)?,
+                z_r: read_point(reader)?,
+                com_l: read_commitment::<E, _>(reader)?,
+                com_r: read_commitment::<E, _>(reader)?,
+            });
+        }
+        let mipp = MippProof {
+            rounds: mipp_rounds,
+            c: read_point(reader)?,
+            v: (read_point(reader)?, read_point(reader)?),
+            v_openings: (read_point(reader)?, read_point(reader)?),
+        };
+
+        Ok(AggregateProof {
+            com_ab,
+            com_c,
+            z_ab,
+            z_c,
+            tipp,
+            mipp,
+        })
+    }
+}
+
+fn write_commitment<E: GtEncoding, W: Write>(
+    writer: &mut W,
+    com: &Commitment<E>,
+) -> io::Result<()> {
+    writer.write_all(&E::gt_to_bytes(&com.0))?;
+    writer.write_all(&E::gt_to_bytes(&com.1))
+}
+
+fn read_commitment<E: GtDecoding, R: Read>(reader: &mut R) -> io::Result<Commitment<E>> {
+    Ok((read_gt::<E, _>(reader)?, read_gt::<E, _>(reader)?))
+}
+
+fn read_gt<E: GtDecoding, R: Read>(reader: &mut R) -> io::Result<E::Gt> {
+    let mut bytes = vec![0; E::GT_BYTES];
+    reader.read_exact(&mut bytes)?;
+    E::gt_from_bytes(&bytes).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid Gt"))
+}
+
+fn read_point<G: GroupEncoding, R: Read>(reader: &mut R) -> io::Result<G> {
+    let mut repr = G::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+    Option::from(G::from_bytes(&repr))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid point"))
+}
+
+/// Aggregates `proofs`, each with its public inputs, into one proof. The
+/// proofs are padded to a power of two by repeating the last one.
+///
+/// Returns [`SynthesisError::PolynomialDegreeTooLarge`] if there are more
+/// proofs than the key supports, and [`SynthesisError::Unsatisfiable`] if
+/// there are none.
+pub fn aggregate_proofs<E, T>(
+    key: &AggregationKey<E>,
+    proofs: &[(&Proof<E>, &[E::Fr])],
+    transcript: &mut T,
+) -> Result<AggregateProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    let _span = crate::trace::span("aggregate_proofs");
+    if proofs.is_empty() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    if proofs.len() > key.max_proofs() {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+    let proofs = pad(proofs);
+    let (n, m) = (key.max_proofs(), proofs.len());
+    let worker = Worker::new();
+
+    let a = proofs.iter().map(|(p, _)| p.a).collect::<Vec<_>>();
+    let b = proofs.iter().map(|(p, _)| p.b).collect::<Vec<_>>();
+    let c = proofs.iter().map(|(p, _)| p.c).collect::<Vec<_>>();
+    let (v1, v2) = (&key.h_alpha[..m], &key.h_beta[..m]);
+    let (w1, w2) = (&key.g_alpha[n..n + m], &key.g_beta[n..n + m]);
+
+    let com_ab = (commit::<E>(&a, v1, w1, &b), commit::<E>(&a, v2, w2, &b));
+    let com_c = (pair::<E>(&c, v1), pair::<E>(&c, v2));
+    let r = derive_r::<E, _, _>(
+        transcript,
+        proofs.iter().map(|(_, inputs)| *inputs),
+        &com_ab,
+        &com_c,
+    )
+    .ok_or(SynthesisError::DivisionByZero)?;
+
+    let r_powers = powers(r, m);
+    let r_inv_powers = powers(r.invert().unwrap(), m);
+    let a = a
+        .iter()
+        .zip(&r_powers)
+        .map(|(a, r)| (*a * r).to_affine())
+        .collect::<Vec<_>>();
+    let z_ab = pair::<E>(&a, &b);
+    let z_c = dense_multiexp::<E::G1>(&worker, &c, &r_powers)?.to_affine();
+    absorb_gt::<E, _>(transcript, b"z_ab", &z_ab);
+    transcript.absorb_point(b"z_c", &z_c);
+
+    let rescale = |v: &[E::G2Affine]| {
+        v.iter()
+            .zip(&r_inv_powers)
+            .map(|(v, r)| (*v * r).to_affine())
+            .collect::<Vec<_>>()
+    };
+    let tipp = prove_tipp(
+        key,
+        &worker,
+        transcript,
+        (a, b),
+        (rescale(v1), rescale(v2)),
+        (w1.to_vec(), w2.to_vec()),
+        r,
+    )?;
+    let mipp = prove_mipp(
+        key,
+        &worker,
+        transcript,
+        c,
+        r_powers,
+        (v1.to_vec(), v2.to_vec()),
+    )?;
+
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        z_ab,
+        z_c,
+        tipp,
+        mipp,
+    })
+}
+
+/// Verifies an aggregate of proofs of the circuit of `pvk`, given the public
+/// inputs of each of the proofs, in order, and a transcript in the state the
+/// prover's was in.
+pub fn verify_aggregate_proof<E, T>(
+    vk: &AggregationVerifyingKey<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &AggregateProof<E>,
+    public_inputs: &[&[E::Fr]],
+    transcript: &mut T,
+) -> Result<(), VerificationError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    for _ in public_inputs {
+        metrics::increment("bellman_verifications_total", &[]);
+    }
+    let result = verify_inner(vk, pvk, proof, public_inputs, transcript);
+    if result.is_err() {
+        metrics::increment("bellman_verification_failures_total", &[]);
+    }
+    result
+}
+
+fn verify_inner<E, T>(
+    vk: &AggregationVerifyingKey<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &AggregateProof<E>,
+    public_inputs: &[&[E::Fr]],
+    transcript: &mut T,
+) -> Result<(), VerificationError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    if public_inputs
+        .iter()
+        .any(|inputs| inputs.len() + 1 != pvk.ic.len())
+    {
+        return Err(VerificationError::InvalidVerifyingKey);
+    }
+    if public_inputs.is_empty() || public_inputs.len() > vk.n {
+        return Err(VerificationError::InvalidProof);
+    }
+    let public_inputs = pad(public_inputs);
+    let m = public_inputs.len();
+    let rounds = m.trailing_zeros() as usize;
+    if proof.tipp.rounds.len() != rounds || proof.mipp.rounds.len() != rounds {
+        return Err(VerificationError::InvalidProof);
+    }
+
+    let r = derive_r::<E, _, _>(
+        transcript,
+        public_inputs.iter().copied(),
+        &proof.com_ab,
+        &proof.com_c,
+    )
+    .ok_or(VerificationError::InvalidProof)?;
+    absorb_gt::<E, _>(transcript, b"z_ab", &proof.z_ab);
+    transcript.absorb_point(b"z_c", &proof.z_c);
+
+    verify_tipp(vk, transcript, proof, r)?;
+    verify_mipp(vk, transcript, proof, r)?;
+
+    // The Groth16 equation, on the sums over the proofs.
+    let r_powers = powers(r, m);
+    let mut ic_factors = vec![E::Fr::zero(); pvk.ic.len()];
+    for (r, inputs) in r_powers.iter().zip(&public_inputs) {
+        ic_factors[0] += r;
+        for (factor, input) in ic_factors[1..].iter_mut().zip(inputs.iter()) {
+            *factor += &(*r * input);
+        }
+    }
+    let s = dense_multiexp::<E::G1>(&Worker::new(), &pvk.ic, &ic_factors)
+        .map_err(|_| VerificationError::InvalidVerifyingKey)?
+        .to_affine();
+    let rhs = E::multi_miller_loop(&[(&s, &pvk.neg_gamma_g2), (&proof.z_c, &pvk.neg_delta_g2)])
+        .final_exponentiation();
+    if proof.z_ab + &rhs == gt_mul::<E>(pvk.alpha_g1_beta_g2, ic_factors[0]) {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidProof)
+    }
+}
+
+fn prove_tipp<E, T>(
+    key: &AggregationKey<E>,
+    worker: &Worker,
+    transcript: &mut T,
+    (mut a, mut b): (Vec<E::G1Affine>, Vec<E::G2Affine>),
+    (mut v1, mut v2): (Vec<E::G2Affine>, Vec<E::G2Affine>),
+    (mut w1, mut w2): (Vec<E::G1Affine>, Vec<E::G1Affine>),
+    r: E::Fr,
+) -> Result<TippProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    transcript.domain_separate(b"tipp");
+    let mut rounds = vec![];
+    let mut challenges = vec![];
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (v1_l, v1_r) = v1.split_at(half);
+        let (v2_l, v2_r) = v2.split_at(half);
+        let (w1_l, w1_r) = w1.split_at(half);
+        let (w2_l, w2_r) = w2.split_at(half);
+        let round = TippRound::<E> {
+            z_l: pair::<E>(a_r, b_l),
+            z_r: pair::<E>(a_l, b_r),
+            com_l: (
+                commit::<E>(a_r, v1_l, w1_r, b_l),
+                commit::<E>(a_r, v2_l, w2_r, b_l),
+            ),
+            com_r: (
+                commit::<E>(a_l, v1_r, w1_l, b_r),
+                commit::<E>(a_l, v2_r, w2_l, b_r),
+            ),
+        };
+        let x = tipp_challenge(transcript, &round).ok_or(SynthesisError::DivisionByZero)?;
+        let x_inv = x.invert().unwrap();
+
+        a = fold(&a, x);
+        b = fold(&b, x_inv);
+        v1 = fold(&v1, x_inv);
+        v2 = fold(&v2, x_inv);
+        w1 = fold(&w1, x);
+        w2 = fold(&w2, x);
+        rounds.push(round);
+        challenges.push(x);
+    }
+
+    let (a, b, v, w) = (a[0], b[0], (v1[0], v2[0]), (w1[0], w2[0]));
+    let z = final_challenge::<E, _>(transcript, &[a], &[b], v, w);
+    let v_poly = expand(&tipp_v_factors(&challenges, r));
+    let mut w_poly = vec![E::Fr::zero(); key.max_proofs()];
+    w_poly.extend(expand(&challenges));
+
+    Ok(TippProof {
+        rounds,
+        a,
+        b,
+        v,
+        w,
+        v_openings: (
+            open::<E::G2>(worker, &key.h_alpha, &v_poly, z)?,
+            open::<E::G2>(worker, &key.h_beta, &v_poly, z)?,
+        ),
+        w_openings: (
+            open::<E::G1>(worker, &key.g_alpha, &w_poly, z)?,
+            open::<E::G1>(worker, &key.g_beta, &w_poly, z)?,
+        ),
+    })
+}
+
+fn verify_tipp<E, T>(
+    vk: &AggregationVerifyingKey<E>,
+    transcript: &mut T,
+    proof: &AggregateProof<E>,
+    r: E::Fr,
+) -> Result<(), VerificationError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    let tipp = &proof.tipp;
+    transcript.domain_separate(b"tipp");
+    let (mut t, mut u, mut z_ab) = (proof.com_ab.0, proof.com_ab.1, proof.z_ab);
+    let mut challenges = vec![];
+    for round in &tipp.rounds {
+        let x = tipp_challenge(transcript, round).ok_or(VerificationError::InvalidProof)?;
+        let x_inv = x.invert().unwrap();
+        t = t + &gt_mul::<E>(round.com_l.0, x) + &gt_mul::<E>(round.com_r.0, x_inv);
+        u = u + &gt_mul::<E>(round.com_l.1, x) + &gt_mul::<E>(round.com_r.1, x_inv);
+        z_ab = z_ab + &gt_mul::<E>(round.z_l, x) + &gt_mul::<E>(round.z_r, x_inv);
+        challenges.push(x);
+    }
+
+    let z = final_challenge::<E, _>(transcript, &[tipp.a], &[tipp.b], tipp.v, tipp.w);
+    let folded = t == commit::<E>(&[tipp.a], &[tipp.v.0], &[tipp.w.0], &[tipp.b])
+        && u == commit::<E>(&[tipp.a], &[tipp.v.1], &[tipp.w.1], &[tipp.b])
+        && z_ab == pair::<E>(&[tipp.a], &[tipp.b]);
+
+    let v_eval = evaluate(&tipp_v_factors(&challenges, r), z);
+    let w_eval = evaluate(&challenges, z) * &z.pow_vartime([vk.n as u64]);
+    let opened = check_g2_opening(vk, vk.g_alpha, tipp.v.0, tipp.v_openings.0, z, v_eval)
+        && check_g2_opening(vk, vk.g_beta, tipp.v.1, tipp.v_openings.1, z, v_eval)
+        && check_g1_opening(vk, vk.h_alpha, tipp.w.0, tipp.w_openings.0, z, w_eval)
+        && check_g1_opening(vk, vk.h_beta, tipp.w.1, tipp.w_openings.1, z, w_eval);
+
+    if folded && opened {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidProof)
+    }
+}
+
+fn prove_mipp<E, T>(
+    key: &AggregationKey<E>,
+    worker: &Worker,
+    transcript: &mut T,
+    mut c: Vec<E::G1Affine>,
+    mut s: Vec<E::Fr>,
+    (mut v1, mut v2): (Vec<E::G2Affine>, Vec<E::G2Affine>),
+) -> Result<MippProof<E>, SynthesisError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    transcript.domain_separate(b"mipp");
+    let mut rounds = vec![];
+    let mut challenges = vec![];
+    while c.len() > 1 {
+        let half = c.len() / 2;
+        let (c_l, c_r) = c.split_at(half);
+        let (s_l, s_r) = s.split_at(half);
+        let (v1_l, v1_r) = v1.split_at(half);
+        let (v2_l, v2_r) = v2.split_at(half);
+        let round = MippRound::<E> {
+            z_l: dense_multiexp::<E::G1>(worker, c_r, s_l)?.to_affine(),
+            z_r: dense_multiexp::<E::G1>(worker, c_l, s_r)?.to_affine(),
+            com_l: (pair::<E>(c_r, v1_l), pair::<E>(c_r, v2_l)),
+            com_r: (pair::<E>(c_l, v1_r), pair::<E>(c_l, v2_r)),
+        };
+        let x = mipp_challenge(transcript, &round).ok_or(SynthesisError::DivisionByZero)?;
+        let x_inv = x.invert().unwrap();
+
+        c = fold(&c, x);
+        s = s_l
+            .iter()
+            .zip(s_r)
+            .map(|(l, r)| *l + &(*r * &x_inv))
+            .collect();
+        v1 = fold(&v1, x_inv);
+        v2 = fold(&v2, x_inv);
+        rounds.push(round);
+        challenges.push(x_inv);
+    }
+
+    let (c, v) = (c[0], (v1[0], v2[0]));
+    let z = final_challenge::<E, _>(transcript, &[c], &[], v, (c, c));
+    let v_poly = expand(&challenges);
+
+    Ok(MippProof {
+        rounds,
+        c,
+        v,
+        v_openings: (
+            open::<E::G2>(worker, &key.h_alpha, &v_poly, z)?,
+            open::<E::G2>(worker, &key.h_beta, &v_poly, z)?,
+        ),
+    })
+}
+
+fn verify_mipp<E, T>(
+    vk: &AggregationVerifyingKey<E>,
+    transcript: &mut T,
+    proof: &AggregateProof<E>,
+    r: E::Fr,
+) -> Result<(), VerificationError>
+where
+    E: MultiMillerLoop + GtEncoding,
+    T: Transcript<E::Fr>,
+{
+    let mipp = &proof.mipp;
+    transcript.domain_separate(b"mipp");
+    let (mut t, mut u, mut z_c) = (proof.com_c.0, proof.com_c.1, proof.z_c.to_curve());
+    let mut challenges = vec![];
+    for round in &mipp.rounds {
+        let x = mipp_challenge(transcript, round).ok_or(VerificationError::InvalidProof)?;
+        let x_inv = x.invert().unwrap();
+        t = t + &gt_mul::<E>(round.com_l.0, x) + &gt_mul::<E>(round.com_r.0, x_inv);
+        u = u + &gt_mul::<E>(round.com_l.1, x) + &gt_mul::<E>(round.com_r.1, x_inv);
+        AddAssign::<&E::G1>::add_assign(&mut z_c, &(round.z_l * &x));
+        AddAssign::<&E::G1>::add_assign(&mut z_c, &(round.z_r * &x_inv));
+        challenges.push(x_inv);
+    }
+
+    let z = final_challenge::<E, _>(transcript, &[mipp.c], &[], mipp.v, (mipp.c, mipp.c));
+    // The scalars r^i fold like the key, so the folded scalar is the
+    // polynomial of the key evaluated at r.
+    let folded = t == pair::<E>(&[mipp.c], &[mipp.v.0])
+        && u == pair::<E>(&[mipp.c], &[mipp.v.1])
+        && z_c == mipp.c * &evaluate(&challenges, r);
+
+    let v_eval = evaluate(&challenges, z);
+    let opened = check_g2_opening(vk, vk.g_alpha, mipp.v.0, mipp.v_openings.0, z, v_eval)
+        && check_g2_opening(vk, vk.g_beta, mipp.v.1, mipp.v_openings.1, z, v_eval);
+
+    if folded && opened {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidProof)
+    }
+}
+
+/// Pads to a power of two by repeating the last element.
+fn pad<T: Copy>(items: &[T]) -> Vec<T> {
+    let mut items = items.to_vec();
+    let last = items[items.len() - 1];
+    items.resize(items.len().next_power_of_two(), last);
+    items
+}
+
+fn powers<S: Field>(base: S, len: usize) -> Vec<S> {
+    let mut power = S::one();
+    (0..len)
+        .map(|_| {
+            let current = power;
+            power *= base;
+            current
+        })
+        .collect()
+}
+
+/// Returns `Σ e(g1_i, g2_i)`.
+fn pair<E: MultiMillerLoop>(g1: &[E::G1Affine], g2: &[E::G2Affine]) -> E::Gt {
+    let g2 = g2
+        .iter()
+        .map(|p| E::G2Prepared::from(*p))
+        .collect::<Vec<_>>();
+    let terms = g1.iter().zip(g2.iter()).collect::<Vec<_>>();
+    E::multi_miller_loop(&terms).final_exponentiation()
+}
+
+/// Returns the commitment `Σ e(a_i, v_i) + e(w_i, b_i)` to `a` and `b`.
+fn commit<E: MultiMillerLoop>(
+    a: &[E::G1Affine],
+    v: &[E::G2Affine],
+    w: &[E::G1Affine],
+    b: &[E::G2Affine],
+) -> E::Gt {
+    let g1 = a.iter().chain(w).copied().collect::<Vec<_>>();
+    let g2 = v.iter().chain(b).copied().collect::<Vec<_>>();
+    pair::<E>(&g1, &g2)
+}
+
+/// Returns `gt^x`, through the bound older compilers resolve.
+fn gt_mul<E: Engine>(gt: E::Gt, x: E::Fr) -> E::Gt {
+    Mul::<E::Fr>::mul(gt, x)
+}
+
+/// Folds the halves `l` and `r` of a vector into `l + x·r`.
+// The bound is implied by `PrimeCurveAffine`, but older compilers do not see it.
+fn fold<G: PrimeCurveAffine>(v: &[G], x: G::Scalar) -> Vec<G>
+where
+    G::Curve: GroupOps<G> + GroupOpsOwned<G>,
+{
+    let (l, r) = v.split_at(v.len() / 2);
+    l.iter()
+        .zip(r)
+        .map(|(l, r)| (*r * x + *l).to_affine())
+        .collect()
+}
+
+/// Returns the coefficients of `Π (1 + f_j·X^{2^{k-1-j}})` for the `k`
+/// factors `f_j` of the rounds of an argument: the factors by which the
+/// elements of a vector of length `2^k` end up in the folded element.
+fn expand<S: Field>(factors: &[S]) -> Vec<S> {
+    let mut poly = vec![S::one()];
+    for factor in factors.iter().rev() {
+        let high = poly.iter().map(|c| *c * factor).collect::<Vec<_>>();
+        poly.extend(high);
+    }
+    poly
+}
+
+/// Evaluates the polynomial of [`expand`] at `x`, in `O(k)`.
+fn evaluate<S: Field>(factors: &[S], x: S) -> S {
+    let mut power = x;
+    let mut acc = S::one();
+    for factor in factors.iter().rev() {
+        acc *= S::one() + *factor * power;
+        power = power.square();
+    }
+    acc
+}
+
+/// The factors of the rescaled key `h^{(a/r)^i}` of TIPP, folded with the
+/// inverses of the challenges.
+fn tipp_v_factors<S: Field>(challenges: &[S], r: S) -> Vec<S> {
+    let r_inv = r.invert().unwrap();
+    let mut power = r_inv;
+    let mut factors = challenges
+        .iter()
+        .rev()
+        .map(|x| {
+            let factor = x.invert().unwrap() * power;
+            power = power.square();
+            factor
+        })
+        .collect::<Vec<_>>();
+    factors.reverse();
+    factors
+}
+
+/// Returns a KZG opening at `z` of the commitment to `poly` under `powers`.
+fn open<G: group::prime::PrimeCurve>(
+    worker: &Worker,
+    powers: &[G::Affine],
+    poly: &[G::Scalar],
+    z: G::Scalar,
+) -> Result<G::Affine, SynthesisError> {
+    // Divides by X - z from the highest coefficient down.
+    let mut quotient = vec![G::Scalar::zero(); poly.len() - 1];
+    let mut acc = G::Scalar::zero();
+    for (i, coeff) in poly.iter().enumerate().skip(1).rev() {
+        acc = acc * &z + coeff;
+        quotient[i - 1] = acc;
+    }
+    if quotient.is_empty() {
+        return Ok(G::Affine::identity());
+    }
+    Ok(dense_multiexp::<G>(worker, &powers[..quotient.len()], &quotient)?.to_affine())
+}
+
+/// Checks that `commitment = h^{p(s)}` with `p(z) = eval`, given the opening
+/// `pi` and `g_s = g^s`.
+fn check_g2_opening<E: MultiMillerLoop>(
+    vk: &AggregationVerifyingKey<E>,
+    g_s: E::G1Affine,
+    commitment: E::G2Affine,
+    pi: E::G2Affine,
+    z: E::Fr,
+    eval: E::Fr,
+) -> bool {
+    let mut lhs = g_s.to_curve();
+    SubAssign::<&E::G1>::sub_assign(&mut lhs, &(vk.g * &z));
+    let mut rhs = commitment.to_curve();
+    SubAssign::<&E::G2>::sub_assign(&mut rhs, &(vk.h * &eval));
+    let (lhs, rhs) = (lhs.to_affine(), rhs.to_affine());
+    let neg_g = vk.g.neg();
+    E::multi_miller_loop(&[(&lhs, &pi.into()), (&neg_g, &rhs.into())])
+        .final_exponentiation()
+        .is_identity()
+        .into()
+}
+
+/// Checks that `commitment = g^{p(s)}` with `p(z) = eval`, given the opening
+/// `pi` and `h_s = h^s`.
+fn check_g1_opening<E: MultiMillerLoop>(
+    vk: &AggregationVerifyingKey<E>,
+    h_s: E::G2Affine,
+    commitment: E::G1Affine,
+    pi: E::G1Affine,
+    z: E::Fr,
+    eval: E::Fr,
+) -> bool {
+    let mut lhs = commitment.to_curve();
+    SubAssign::<&E::G1>::sub_assign(&mut lhs, &(vk.g * &eval));
+    let mut rhs = h_s.to_curve();
+    SubAssign::<&E::G2>::sub_assign(&mut rhs, &(vk.h * &z));
+    let (lhs, rhs) = (lhs.to_affine(), rhs.to_affine());
+    let neg_pi = pi.neg();
+    E::multi_miller_loop(&[(&lhs, &vk.h.into()), (&neg_pi, &rhs.into())])
+        .final_exponentiation()
+        .is_identity()
+        .into()
+}
+
+fn absorb_gt<E: GtEncoding, T: Transcript<E::Fr>>(
+    transcript: &mut T,
+    label: &'static [u8],
+    gt: &E::Gt,
+) {
+    transcript.absorb_bytes(label, &E::gt_to_bytes(gt));
+}
+
+fn absorb_commitment<E: GtEncoding, T: Transcript<E::Fr>>(
+    transcript: &mut T,
+    label: &'static [u8],
+    com: &Commitment<E>,
+) {
+    absorb_gt::<E, _>(transcript, label, &com.0);
+    absorb_gt::<E, _>(transcript, label, &com.1);
+}
+
+/// Derives the random `r` of the aggregation from the inputs of the padded
+/// proofs and the commitments to them, or `None` if it is zero.
+fn derive_r<'a, E, T, I>(
+    transcript: &mut T,
+    inputs: I,
+    com_ab: &Commitment<E>,
+    com_c: &Commitment<E>,
+) -> Option<E::Fr>
+where
+    E: GtEncoding,
+    T: Transcript<E::Fr>,
+    I: ExactSizeIterator<Item = &'a [E::Fr]>,
+{
+    transcript.domain_separate(b"groth16-aggregate");
+    transcript.absorb_u64(b"proofs", inputs.len() as u64);
+    for inputs in inputs {
+        for input in inputs {
+            transcript.absorb_scalar(b"input", input);
+        }
+    }
+    absorb_commitment::<E, _>(transcript, b"com_ab", com_ab);
+    absorb_commitment::<E, _>(transcript, b"com_c", com_c);
+    nonzero(transcript.squeeze_challenge(b"r"))
+}
+
+fn tipp_challenge<E: GtEncoding, T: Transcript<E::Fr>>(
+    transcript: &mut T,
+    round: &TippRound<E>,
+) -> Option<E::Fr> {
+    absorb_gt::<E, _>(transcript, b"z_l", &round.z_l);
+    absorb_gt::<E, _>(transcript, b"z_r", &round.z_r);
+    absorb_commitment::<E, _>(transcript, b"com_l", &round.com_l);
+    absorb_commitment::<E, _>(transcript, b"com_r", &round.com_r);
+    nonzero(transcript.squeeze_challenge(b"x"))
+}
+
+fn mipp_challenge<E: GtEncoding, T: Transcript<E::Fr>>(
+    transcript: &mut T,
+    round: &MippRound<E>,
+) -> Option<E::Fr> {
+    transcript.absorb_point(b"z_l", &round.z_l);
+    transcript.absorb_point(b"z_r", &round.z_r);
+    absorb_commitment::<E, _>(transcript, b"com_l", &round.com_l);
+    absorb_commitment::<E, _>(transcript, b"com_r", &round.com_r);
+    nonzero(transcript.squeeze_challenge(b"x"))
+}
+
+/// Derives the point at which the folded keys are opened.
+fn final_challenge<E: Engine, T: Transcript<E::Fr>>(
+    transcript: &mut T,
+    g1: &[E::G1Affine],
+    g2: &[E::G2Affine],
+    v: (E::G2Affine, E::G2Affine),
+    w: (E::G1Affine, E::G1Affine),
+) -> E::Fr {
+    for p in g1.iter().chain(&[w.0, w.1]) {
+        transcript.absorb_point(b"final", p);
+    }
+    for p in g2.iter().chain(&[v.0, v.1]) {
+        transcript.absorb_point(b"final", p);
+    }
+    transcript.squeeze_challenge(b"z")
+}
+
+fn nonzero<S: Field>(x: S) -> Option<S> {
+    if x.is_zero() {
+        None
+    } else {
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::rng;
+    use crate::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key};
+    use crate::transcript::Blake2sTranscript;
+    use crate::{Circuit, ConstraintSystem};
+    use bls12_381::{Bls12, Scalar};
+    use ff::PrimeField;
+    use hex_literal::hex;
+
+    struct Square(Option<u64>);
+
+    impl<S: PrimeField> Circuit<S> for Square {
+        fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = self.0.map(S::from);
+            let a = cs.alloc(|| "x", || x.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc_input(
+                || "x^2",
+                || {
+                    x.map(|x| x.square())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?;
+            cs.enforce(|| "x * x = x^2", |lc| lc + a, |lc| lc + a, |lc| lc + b);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn polynomials() {
+        let factors = [Scalar::from(2), Scalar::from(3), Scalar::from(5)];
+        let poly = expand(&factors);
+        let x = Scalar::from(7);
+        assert_eq!(poly.len(), 8);
+        assert_eq!(crate::ipa::evaluate(&poly, x), evaluate(&factors, x));
+        // X^5 has the bits of rounds 0 and 2.
+        assert_eq!(poly[5], factors[0] * factors[2]);
+    }
+
+    /// The encodings of the target group, and so the challenges derived from
+    /// them, do not change.
+    #[test]
+    fn gt_encoding() {
+        let mut one = vec![0; 576];
+        one[47] = 1;
+        assert_eq!(Bls12::gt_to_bytes(&bls12_381::Gt::identity()), one);
+
+        let challenge = |g: &bls12_381::Gt| {
+            let mut transcript = Blake2sTranscript::new(b"gt encoding test");
+            absorb_gt::<Bls12, _>(&mut transcript, b"g", g);
+            transcript.squeeze_challenge(b"x")
+        };
+        let g = bls12_381::Gt::generator();
+        assert_eq!(
+            challenge(&g).to_repr(),
+            hex!("db3f61b025cc4d97486e6994ff3489e5d9409ff5772b44700dfa9b289f79cd3f")
+        );
+        assert!(challenge(&g) != challenge(&g.double()));
+    }
+
+    #[test]
+    fn aggregation() {
+        let mut rng = rng();
+        let params = generate_random_parameters::<Bls12, _, _>(Square(None), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let key = AggregationKey::<Bls12>::new(8, &mut rng);
+        assert_eq!(key.max_proofs(), 8);
+        let vk = key.verifying_key();
+
+        let proofs = (0..5)
+            .map(|i| create_random_proof(Square(Some(i)), &params, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let inputs = (0..5u64).map(|i| [Scalar::from(i * i)]).collect::<Vec<_>>();
+        let pairs = proofs
+            .iter()
+            .zip(&inputs)
+            .map(|(p, i)| (p, &i[..]))
+            .collect::<Vec<_>>();
+        let input_refs = inputs.iter().map(|i| &i[..]).collect::<Vec<_>>();
+        let transcript = || Blake2sTranscript::new(b"aggregation test");
+
+        let aggregate = aggregate_proofs(&key, &pairs, &mut transcript()).unwrap();
+        assert_eq!(aggregate.tipp.rounds.len(), 3);
+        assert!(
+            verify_aggregate_proof(&vk, &pvk, &aggregate, &input_refs, &mut transcript()).is_ok()
+        );
+
+        // Wrong inputs, or fewer of them, do not verify.
+        let mut wrong = input_refs.clone();
+        let four = [Scalar::from(4)];
+        wrong[3] = &four;
+        assert!(verify_aggregate_proof(&vk, &pvk, &aggregate, &wrong, &mut transcript()).is_err());
+        assert!(
+            verify_aggregate_proof(&vk, &pvk, &aggregate, &input_refs[..4], &mut transcript())
+                .is_err()
+        );
+
+        // Neither does an aggregate including an invalid proof.
+        let mut invalid = pairs.clone();
+        invalid[1] = (&proofs[2], &inputs[1][..]);
+        let aggregate = aggregate_proofs(&key, &invalid, &mut transcript()).unwrap();
+        assert_eq!(
+            verify_aggregate_proof(&vk, &pvk, &aggregate, &input_refs, &mut transcript()),
+            Err(VerificationError::InvalidProof)
+        );
+
+        // A single proof aggregates too, and too many do not.
+        let aggregate = aggregate_proofs(&key, &pairs[..1], &mut transcript()).unwrap();
+        assert!(
+            verify_aggregate_proof(&vk, &pvk, &aggregate, &input_refs[..1], &mut transcript())
+                .is_ok()
+        );
+        let many = pairs.iter().cycle().take(9).copied().collect::<Vec<_>>();
+        assert!(matches!(
+            aggregate_proofs(&key, &many, &mut transcript()),
+            Err(SynthesisError::PolynomialDegreeTooLarge)
+        ));
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn serialization() {
+        use crate::bn254::{Bn254, Fr};
+
+        let mut rng = rng();
+        let params = generate_random_parameters::<Bn254, _, _>(Square(None), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let key = AggregationKey::<Bn254>::new(4, &mut rng);
+        let proofs = (0..3)
+            .map(|i| create_random_proof(Square(Some(i)), &params, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let inputs = (0..3u64).map(|i| [Fr::from(i * i)]).collect::<Vec<_>>();
+        let pairs = proofs
+            .iter()
+            .zip(&inputs)
+            .map(|(p, i)| (p, &i[..]))
+            .collect::<Vec<_>>();
+        let input_refs = inputs.iter().map(|i| &i[..]).collect::<Vec<_>>();
+        let transcript = || Blake2sTranscript::new(b"serialization test");
+
+        let aggregate = aggregate_proofs(&key, &pairs, &mut transcript()).unwrap();
+        let mut v = vec![];
+        aggregate.write(&mut v).unwrap();
+        // The claims, the count, two rounds of each argument, and the folded
+        // vectors, keys and openings of TIPP and MIPP.
+        let (gt, g1, g2) = (384, 32, 64);
+        let rounds = 2 * (6 * gt) + 2 * (2 * g1 + 4 * gt);
+        assert_eq!(
+            v.len(),
+            5 * gt + g1 + 4 + rounds + 5 * (g1 + g2) + g1 + 4 * g2
+        );
+
+        let read = AggregateProof::<Bn254>::read(&v[..]).unwrap();
+        let mut written = vec![];
+        read.write(&mut written).unwrap();
+        assert_eq!(written, v);
+        assert!(verify_aggregate_proof(
+            &key.verifying_key(),
+            &pvk,
+            &read,
+            &input_refs,
+            &mut transcript()
+        )
+        .is_ok());
+
+        // An element of `Fp12` outside of the target group.
+        let mut tampered = v.clone();
+        tampered[383] ^= 1;
+        assert_eq!(
+            AggregateProof::<Bn254>::read(&tampered[..])
+                .err()
+                .unwrap()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(AggregateProof::<Bn254>::read(&v[..v.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Lowering of constraint systems to arithmetic circuits.
+//!
+//! MPC and FHE pipelines consume circuits as lists of gates rather than as
+//! R1CS. [`ArithmeticCircuit::lower`] turns the constraints of a
+//! [`RawCircuit`] into a circuit over the same field that, given the public
+//! inputs and the auxiliary variables, outputs `A·B - C` for each constraint,
+//! so that a witness is valid exactly when every output is zero.
+//!
+//! # Format
+//!
+//! [`ArithmeticCircuit::write`] emits a Bristol-style text gate list. Field
+//! elements are written as big-endian hexadecimal.
+//!
+//! ```text
+//! bellman-arithmetic 1
+//! modulus <hex>
+//! <gates> <wires>
+//! <public inputs> <private inputs>
+//! <outputs> <output wire>...
+//!
+//! 0 1 <out> CONST <hex>
+//! 1 1 <in> <out> SCALE <hex>
+//! 2 1 <left> <right> <out> ADD|SUB|MUL
+//! ```
+//!
+//! Wires `0..public` are the public inputs, not counting the constant `ONE`,
+//! the next `private` wires are the auxiliary variables, and every gate
+//! writes the next wire.
+
+use ff::PrimeField;
+use std::io::{self, Write};
+
+use super::exporter::RawCircuit;
+use crate::{Index, LinearCombination, Variable};
+
+/// A gate, writing the wire `out`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Gate<S: PrimeField> {
+    Const {
+        out: usize,
+        value: S,
+    },
+    Scale {
+        out: usize,
+        input: usize,
+        coeff: S,
+    },
+    Add {
+        out: usize,
+        left: usize,
+        right: usize,
+    },
+    Sub {
+        out: usize,
+        left: usize,
+        right: usize,
+    },
+    Mul {
+        out: usize,
+        left: usize,
+        right: usize,
+    },
+}
+
+/// A circuit of field operations, checking the constraints of a
+/// [`RawCircuit`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArithmeticCircuit<S: PrimeField> {
+    pub num_public: usize,
+    pub num_private: usize,
+    pub num_wires: usize,
+    pub gates: Vec<Gate<S>>,
+    /// The wire holding `A·B - C` for each constraint, in order.
+    pub outputs: Vec<usize>,
+}
+
+struct Lowering<S: PrimeField> {
+    circuit: ArithmeticCircuit<S>,
+    zero: Option<usize>,
+}
+
+impl<S: PrimeField> Lowering<S> {
+    fn push(&mut self, gate: impl FnOnce(usize) -> Gate<S>) -> usize {
+        let out = self.circuit.num_wires;
+        self.circuit.gates.push(gate(out));
+        self.circuit.num_wires += 1;
+        out
+    }
+
+    fn zero(&mut self) -> usize {
+        match self.zero {
+            Some(zero) => zero,
+            None => {
+                let zero = self.push(|out| Gate::Const {
+                    out,
+                    value: S::zero(),
+                });
+                self.zero = Some(zero);
+                zero
+            }
+        }
+    }
+
+    fn wire(&self, var: Variable) -> usize {
+        match var.get_unchecked() {
+            Index::Input(i) => i - 1,
+            Index::Aux(i) => self.circuit.num_public + i,
+        }
+    }
+
+    /// Sums the terms of `lc`, folding the multiples of `ONE` into a single
+    /// constant.
+    fn lower(&mut self, lc: &LinearCombination<S>) -> usize {
+        let mut constant = S::zero();
+        let mut terms = vec![];
+        for &(var, coeff) in lc.as_ref() {
+            if coeff.is_zero() {
+                continue;
+            }
+            if var.get_unchecked() == Index::Input(0) {
+                constant += coeff;
+                continue;
+            }
+
+            let input = self.wire(var);
+            terms.push(if coeff == S::one() {
+                input
+            } else {
+                self.push(|out| Gate::Scale { out, input, coeff })
+            });
+        }
+        if !constant.is_zero() {
+            terms.push(self.push(|out| Gate::Const {
+                out,
+                value: constant,
+            }));
+        }
+
+        let mut terms = terms.into_iter();
+        match terms.next() {
+            Some(first) => terms.fold(first, |left, right| {
+                self.push(|out| Gate::Add { out, left, right })
+            }),
+            None => self.zero(),
+        }
+    }
+}
+
+impl<S: PrimeField> ArithmeticCircuit<S> {
+    /// Lowers the constraints of `circuit`.
+    pub fn lower(circuit: &RawCircuit<S>) -> Self {
+        let num_public = circuit.num_inputs - 1;
+        let mut lowering = Lowering {
+            circuit: ArithmeticCircuit {
+                num_public,
+                num_private: circuit.num_aux,
+                num_wires: num_public + circuit.num_aux,
+                gates: vec![],
+                outputs: vec![],
+            },
+            zero: None,
+        };
+
+        for [a, b, c] in circuit.constraints() {
+            let a = lowering.lower(&a);
+            let b = lowering.lower(&b);
+            let c = lowering.lower(&c);
+            let ab = lowering.push(|out| Gate::Mul {
+                out,
+                left: a,
+                right: b,
+            });
+            let residual = lowering.push(|out| Gate::Sub {
+                out,
+                left: ab,
+                right: c,
+            });
+            lowering.circuit.outputs.push(residual);
+        }
+
+        lowering.circuit
+    }
+
+    /// Evaluates the circuit, returning its outputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of public or private inputs is wrong.
+    pub fn evaluate(&self, public: &[S], private: &[S]) -> Vec<S> {
+        assert_eq!(public.len(), self.num_public);
+        assert_eq!(private.len(), self.num_private);
+
+        let mut wires = public.to_vec();
+        wires.extend_from_slice(private);
+        for gate in &self.gates {
+            let value = match *gate {
+                Gate::Const { value, .. } => value,
+                Gate::Scale { input, coeff, .. } => wires[input] * coeff,
+                Gate::Add { left, right, .. } => wires[left] + wires[right],
+                Gate::Sub { left, right, .. } => wires[left] - wires[right],
+                Gate::Mul { left, right, .. } => wires[left] * wires[right],
+            };
+            wires.push(value);
+        }
+
+        self.outputs.iter().map(|&out| wires[out]).collect()
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "bellman-arithmetic 1")?;
+        writeln!(writer, "modulus {}", hex(S::char_le_bits().iter().copied()))?;
+        writeln!(writer, "{} {}", self.gates.len(), self.num_wires)?;
+        writeln!(writer, "{} {}", self.num_public, self.num_private)?;
+        write!(writer, "{}", self.outputs.len())?;
+        for out in &self.outputs {
+            write!(writer, " {}", out)?;
+        }
+        writeln!(writer, "\n")?;
+
+        for gate in &self.gates {
+            match gate {
+                Gate::Const { out, value } => {
+                    writeln!(writer, "0 1 {} CONST {}", out, value_hex(value))
+                }
+                Gate::Scale { out, input, coeff } => {
+                    writeln!(writer, "1 1 {} {} SCALE {}", input, out, value_hex(coeff))
+                }
+                Gate::Add { out, left, right } => {
+                    writeln!(writer, "2 1 {} {} {} ADD", left, right, out)
+                }
+                Gate::Sub { out, left, right } => {
+                    writeln!(writer, "2 1 {} {} {} SUB", left, right, out)
+                }
+                Gate::Mul { out, left, right } => {
+                    writeln!(writer, "2 1 {} {} {} MUL", left, right, out)
+                }
+            }?;
+        }
+        Ok(())
+    }
+}
+
+fn value_hex<S: PrimeField>(value: &S) -> String {
+    hex(value.to_le_bits().iter().copied())
+}
+
+/// Formats little-endian bits as big-endian hexadecimal.
+fn hex<I: Iterator<Item = bool>>(bits: I) -> String {
+    let bits = bits.collect::<Vec<_>>();
+    let digits = bits
+        .chunks(4)
+        .map(|nibble| {
+            nibble
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, bit)| acc | ((*bit as u32) << i))
+        })
+        .collect::<Vec<_>>();
+
+    let mut hex = digits
+        .iter()
+        .rev()
+        .skip_while(|d| **d == 0)
+        .map(|d| std::char::from_digit(*d, 16).unwrap())
+        .collect::<String>();
+    if hex.is_empty() {
+        hex.push('0');
+    }
+    format!("0x{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::Scalar;
+    use ff::Field;
+
+    #[test]
+    fn lowers_constraints() {
+        let mut rng = rng();
+        let (circuit, witness) = random_circuit::<Scalar, _>(&CircuitConfig::default(), &mut rng);
+        let lowered = ArithmeticCircuit::lower(&circuit);
+        assert_eq!(lowered.outputs.len(), circuit.num_constraints);
+
+        let outputs = lowered.evaluate(&witness.inputs[1..], &witness.aux);
+        assert!(outputs.iter().all(|o| o.is_zero()));
+
+        let mut aux = witness.aux.clone();
+        aux[0] += Scalar::one();
+        let outputs = lowered.evaluate(&witness.inputs[1..], &aux);
+        assert!(outputs.iter().any(|o| !o.is_zero()));
+
+        let mut text = vec![];
+        lowered.write(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("bellman-arithmetic 1"));
+        assert_eq!(
+            lines.next(),
+            Some("modulus 0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(&*format!("{} {}", lowered.gates.len(), lowered.num_wires))
+        );
+        assert_eq!(text.lines().skip(6).count(), lowered.gates.len());
+
+        assert_eq!(hex([false; 8].iter().copied()), "0x0");
+        assert_eq!(value_hex(&Scalar::from(0x1f2u64)), "0x1f2");
+    }
+}
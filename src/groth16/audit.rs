@@ -0,0 +1,353 @@
+//! Auditable generation of parameters by a single party.
+//!
+//! Test setups run by a single party, for instance in a secure enclave, are
+//! only as trustworthy as the evidence that the toxic waste was destroyed.
+//! [`generate_random_parameters_audited`] generates parameters like
+//! [`generate_random_parameters`], and records when each of `alpha`, `beta`,
+//! `gamma`, `delta` and `tau` is drawn and when its last copy is zeroized,
+//! followed by the hash of the resulting verifying key. Each event is passed
+//! to a callback as it happens, and collected in a [`KeygenLog`] that can be
+//! signed by a [`Signer`], such as the attestation key of an enclave.
+//!
+//! # Format
+//!
+//! [`KeygenLog::write`] emits the following, with integers in big-endian:
+//!
+//! ```text
+//! magic      "bellman-keygen-log"
+//! version    u32 (currently 1)
+//! entries    u32 count, then for each entry:
+//!   timestamp  u64, milliseconds since the Unix epoch
+//!   event      u8: 0 generated, 1 zeroized, 2 finished
+//!   subject    for 0 and 1, u8: 0 alpha, 1 beta, 2 gamma, 3 delta, 4 tau
+//!              for 2, 32 bytes, VerifyingKey::hash
+//! ```
+//!
+//! and [`SignedKeygenLog::write`] appends the key id, as a u32 length and
+//! UTF-8 bytes, and the signature, as a u32 length and bytes. Signatures
+//! are over [`SIGNATURE_DOMAIN`] followed by the log bytes.
+//!
+//! [`generate_random_parameters`]: super::generate_random_parameters
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::Field;
+use group::{Group, WnafGroup};
+use pairing::Engine;
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::envelope::{read_bytes, read_string, write_string, SignatureVerifier, Signer};
+use super::{generate_parameters, Parameters};
+use crate::zeroize::Secret;
+use crate::{Circuit, SynthesisError};
+
+pub(super) const MAGIC: &[u8] = b"bellman-keygen-log";
+const VERSION: u32 = 1;
+
+/// The prefix of the messages signed by [`KeygenLog::sign`].
+pub const SIGNATURE_DOMAIN: &[u8] = b"bellman-keygen-log-signature";
+
+/// One of the secrets of keygen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToxicWaste {
+    Alpha,
+    Beta,
+    Gamma,
+    Delta,
+    Tau,
+}
+
+const TOXIC_WASTE: [ToxicWaste; 5] = [
+    ToxicWaste::Alpha,
+    ToxicWaste::Beta,
+    ToxicWaste::Gamma,
+    ToxicWaste::Delta,
+    ToxicWaste::Tau,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeygenEvent {
+    /// The secret was drawn from the RNG.
+    Generated(ToxicWaste),
+    /// Every copy of the secret, and of the values derived from it, has been
+    /// overwritten.
+    Zeroized(ToxicWaste),
+    /// Keygen succeeded, with the verifying key of this hash.
+    Finished([u8; 32]),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub event: KeygenEvent,
+}
+
+/// The events of an audited keygen, in the order they happened.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeygenLog {
+    pub entries: Vec<LogEntry>,
+}
+
+impl KeygenLog {
+    /// Returns `true` if each secret was generated and then zeroized, and
+    /// keygen finished after all of them were.
+    pub fn is_complete(&self) -> bool {
+        let events = self.entries.iter().map(|e| e.event).collect::<Vec<_>>();
+        let position = |event| events.iter().position(|e| *e == event);
+        let finished = match events.last() {
+            Some(KeygenEvent::Finished(_)) => events.len() - 1,
+            _ => return false,
+        };
+
+        TOXIC_WASTE.iter().all(|&waste| {
+            match (
+                position(KeygenEvent::Generated(waste)),
+                position(KeygenEvent::Zeroized(waste)),
+            ) {
+                (Some(generated), Some(zeroized)) => generated < zeroized && zeroized < finished,
+                _ => false,
+            }
+        })
+    }
+
+    pub fn sign<S: Signer>(self, signer: &S) -> SignedKeygenLog {
+        let signature = signer.sign(&self.signed_message());
+        SignedKeygenLog {
+            log: self,
+            key_id: signer.key_id().to_string(),
+            signature,
+        }
+    }
+
+    fn signed_message(&self) -> Vec<u8> {
+        let mut message = SIGNATURE_DOMAIN.to_vec();
+        self.write(&mut message)
+            .expect("writing to a Vec does not fail");
+        message
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in &self.entries {
+            writer.write_u64::<BigEndian>(entry.timestamp)?;
+            match entry.event {
+                KeygenEvent::Generated(waste) => {
+                    writer.write_u8(0)?;
+                    writer.write_u8(waste as u8)?;
+                }
+                KeygenEvent::Zeroized(waste) => {
+                    writer.write_u8(1)?;
+                    writer.write_u8(waste as u8)?;
+                }
+                KeygenEvent::Finished(vk) => {
+                    writer.write_u8(2)?;
+                    writer.write_all(&vk)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a keygen log"));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(invalid("unsupported keygen log version"));
+        }
+
+        let count = reader.read_u32::<BigEndian>()?;
+        let mut entries = vec![];
+        for _ in 0..count {
+            let timestamp = reader.read_u64::<BigEndian>()?;
+            let waste = |reader: &mut R| {
+                TOXIC_WASTE
+                    .get(reader.read_u8()? as usize)
+                    .copied()
+                    .ok_or_else(|| invalid("unknown secret"))
+            };
+            let event = match reader.read_u8()? {
+                0 => KeygenEvent::Generated(waste(&mut reader)?),
+                1 => KeygenEvent::Zeroized(waste(&mut reader)?),
+                2 => {
+                    let mut vk = [0; 32];
+                    reader.read_exact(&mut vk)?;
+                    KeygenEvent::Finished(vk)
+                }
+                _ => return Err(invalid("unknown keygen event")),
+            };
+            entries.push(LogEntry { timestamp, event });
+        }
+
+        Ok(KeygenLog { entries })
+    }
+}
+
+/// A keygen log, signed by the party that ran keygen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedKeygenLog {
+    pub log: KeygenLog,
+    pub key_id: String,
+    pub signature: Vec<u8>,
+}
+
+impl SignedKeygenLog {
+    /// Returns the log if its signature is valid.
+    pub fn verify<V: SignatureVerifier>(&self, verifier: &V) -> Option<&KeygenLog> {
+        if verifier.verify(&self.key_id, &self.log.signed_message(), &self.signature) {
+            Some(&self.log)
+        } else {
+            None
+        }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.log.write(&mut writer)?;
+        write_string(&mut writer, &self.key_id)?;
+        writer.write_u32::<BigEndian>(self.signature.len() as u32)?;
+        writer.write_all(&self.signature)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let log = KeygenLog::read(&mut reader)?;
+        let key_id = read_string(&mut reader)?;
+        let signature = read_bytes(&mut reader)?;
+
+        Ok(SignedKeygenLog {
+            log,
+            key_id,
+            signature,
+        })
+    }
+}
+
+struct Recorder<'a> {
+    log: KeygenLog,
+    observer: &'a mut dyn FnMut(&LogEntry),
+}
+
+impl Recorder<'_> {
+    fn record(&mut self, event: KeygenEvent) {
+        let entry = LogEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            event,
+        };
+        (self.observer)(&entry);
+        self.log.entries.push(entry);
+    }
+}
+
+/// Generates parameters like [`generate_random_parameters`], passing each
+/// event of the lifetime of the toxic waste to `observer` as it happens, and
+/// returns them with the log of those events.
+///
+/// The secrets are drawn from `rng` in the same order as by
+/// [`generate_random_parameters`], so both produce the same parameters from
+/// the same RNG. If keygen fails, the secrets are still zeroized, and
+/// `observer` is told so, but no log is returned.
+///
+/// [`generate_random_parameters`]: super::generate_random_parameters
+pub fn generate_random_parameters_audited<E, C, R>(
+    circuit: C,
+    mut rng: &mut R,
+    observer: &mut dyn FnMut(&LogEntry),
+) -> Result<(Parameters<E>, KeygenLog), SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let mut recorder = Recorder {
+        log: KeygenLog::default(),
+        observer,
+    };
+
+    let g1 = E::G1::random(&mut rng);
+    let g2 = E::G2::random(&mut rng);
+    let mut waste = Vec::with_capacity(TOXIC_WASTE.len());
+    for &name in TOXIC_WASTE.iter() {
+        waste.push(Secret::new(E::Fr::random(&mut rng), E::Fr::zero()));
+        recorder.record(KeygenEvent::Generated(name));
+    }
+
+    // Keygen erases its own copies of the secrets, and everything derived
+    // from them, before it returns, so dropping these erases the last ones.
+    let params = generate_parameters::<E, C>(
+        circuit, g1, g2, *waste[0], *waste[1], *waste[2], *waste[3], *waste[4],
+    );
+    for (name, secret) in TOXIC_WASTE.iter().zip(waste.drain(..)) {
+        drop(secret);
+        recorder.record(KeygenEvent::Zeroized(*name));
+    }
+
+    let params = params?;
+    recorder.record(KeygenEvent::Finished(params.vk.hash()));
+    Ok((params, recorder.log))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::envelope::MacKey;
+    use crate::groth16::generate_random_parameters;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn audited_keygen() {
+        let f = Fixture::<Bls12>::new();
+
+        let mut observed = vec![];
+        let (params, log) = generate_random_parameters_audited::<Bls12, _, _>(
+            f.shape(),
+            &mut f.rng.clone(),
+            &mut |entry| observed.push(*entry),
+        )
+        .unwrap();
+        let expected =
+            generate_random_parameters::<Bls12, _, _>(f.shape(), &mut f.rng.clone()).unwrap();
+        assert!(params == expected);
+
+        assert_eq!(observed, log.entries);
+        assert_eq!(log.entries.len(), 11);
+        assert!(log.is_complete());
+        assert_eq!(
+            log.entries.last().unwrap().event,
+            KeygenEvent::Finished(params.vk.hash())
+        );
+        assert!(log
+            .entries
+            .windows(2)
+            .all(|w| w[0].timestamp <= w[1].timestamp));
+
+        let mut incomplete = log.clone();
+        incomplete.entries.remove(6);
+        assert!(!incomplete.is_complete());
+
+        let key = MacKey::new("enclave", [3; 32]);
+        let signed = log.clone().sign(&key);
+        let mut bytes = vec![];
+        signed.write(&mut bytes).unwrap();
+        let read = SignedKeygenLog::read(&bytes[..]).unwrap();
+        assert_eq!(read, signed);
+        assert_eq!(read.verify(&key), Some(&log));
+        assert!(read.verify(&MacKey::new("enclave", [4; 32])).is_none());
+
+        let mut tampered = read;
+        tampered.log.entries[6].timestamp += 1;
+        assert!(tampered.verify(&key).is_none());
+    }
+}
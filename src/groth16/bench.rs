@@ -0,0 +1,638 @@
+//! Differential benchmarks of gadgets.
+//!
+//! A [`Registry`] names gadgets, each given as a circuit exercising it once.
+//! [`Registry::run`] measures, for each of them, the size of its constraint
+//! system and witness, the time to synthesize its witness, and the time it
+//! adds to proving a reference circuit: a chain of squarings, of
+//! [`BenchConfig::reference_constraints`] constraints, which stands for the
+//! rest of an application so that the gadget is costed by its share of the
+//! FFTs and multiexponentiations rather than by their fixed overheads.
+//! Times are the median of [`BenchConfig::samples`] runs.
+//!
+//! A [`Report`] is written in a line-based text format that does not depend
+//! on the version of this crate, so that reports of several versions can be
+//! kept and laid side by side with [`compare`], and a new report checked
+//! against a baseline with [`regressions`]:
+//!
+//! ```text
+//! bellman-gadget-bench 1
+//! version <label>
+//! <gadget> <constraints> <inputs> <aux> <synthesis ns> <marginal proving ns>
+//! ```
+//!
+//! with one tab-separated line per gadget. [`Registry::standard`] holds the
+//! hash gadgets of this crate.
+
+use ff::PrimeField;
+use pairing::Engine;
+use rand_core::RngCore;
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use super::exporter::{Assignment, RawCircuit};
+use super::{create_random_proof, generate_random_parameters, Parameters};
+use crate::gadgets::boolean::{AllocatedBit, Boolean};
+use crate::gadgets::num::AllocatedNum;
+use crate::gadgets::{blake2s, poseidon, sha256};
+use crate::poseidon::PoseidonParams;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// How gadgets are measured.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// The number of constraints of the reference circuit the gadget is
+    /// proven in.
+    pub reference_constraints: usize,
+    /// The number of runs each time is the median of.
+    pub samples: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            reference_constraints: 1 << 12,
+            samples: 5,
+        }
+    }
+}
+
+/// The costs of one gadget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Measurement {
+    pub gadget: String,
+    pub num_constraints: usize,
+    /// The number of public inputs, not counting `ONE`.
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    /// The time to synthesize the witness of the gadget alone.
+    pub synthesis: Duration,
+    /// The time proving the reference circuit takes with the gadget, over
+    /// the time it takes without. Noise may make this zero for small
+    /// gadgets.
+    pub marginal_proving: Duration,
+}
+
+impl Measurement {
+    /// The metrics compared across reports, times in nanoseconds.
+    fn metrics(&self) -> [(&'static str, u128); 5] {
+        [
+            ("constraints", self.num_constraints as u128),
+            ("inputs", self.num_inputs as u128),
+            ("aux", self.num_aux as u128),
+            ("synthesis ns", self.synthesis.as_nanos()),
+            ("proving ns", self.marginal_proving.as_nanos()),
+        ]
+    }
+}
+
+/// The measurements of a registry of gadgets, with one version of the
+/// crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// A label for the code measured, by default the version of this crate.
+    pub version: String,
+    pub measurements: Vec<Measurement>,
+}
+
+const MAGIC: &str = "bellman-gadget-bench 1";
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Report {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let line = |s: &str| !s.contains(&['\t', '\n', '\r'][..]);
+        if !line(&self.version) || !self.measurements.iter().all(|m| line(&m.gadget)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "labels cannot contain tabs or line breaks",
+            ));
+        }
+
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(writer, "version {}", self.version)?;
+        for m in &self.measurements {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                m.gadget,
+                m.num_constraints,
+                m.num_inputs,
+                m.num_aux,
+                m.synthesis.as_nanos(),
+                m.marginal_proving.as_nanos()
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+            return Err(invalid("not a gadget benchmark report"));
+        }
+        let version = lines
+            .next()
+            .transpose()?
+            .filter(|line| line.starts_with("version "))
+            .map(|line| line["version ".len()..].to_string())
+            .ok_or_else(|| invalid("missing version"))?;
+
+        let measurements = lines
+            .map(|line| {
+                let line = line?;
+                let fields = line.split('\t').collect::<Vec<_>>();
+                if fields.len() != 6 {
+                    return Err(invalid("expected six fields per gadget"));
+                }
+                let number = |i: usize| -> io::Result<u64> {
+                    fields[i].parse().map_err(|_| invalid("invalid number"))
+                };
+                Ok(Measurement {
+                    gadget: fields[0].to_string(),
+                    num_constraints: number(1)? as usize,
+                    num_inputs: number(2)? as usize,
+                    num_aux: number(3)? as usize,
+                    synthesis: Duration::from_nanos(number(4)?),
+                    marginal_proving: Duration::from_nanos(number(5)?),
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Report {
+            version,
+            measurements,
+        })
+    }
+
+    fn get(&self, gadget: &str) -> Option<&Measurement> {
+        self.measurements.iter().find(|m| m.gadget == gadget)
+    }
+}
+
+/// Lays out reports side by side, with a row per gadget and metric, a
+/// column per report, and the change from the first report to the last.
+pub fn compare(reports: &[Report]) -> String {
+    let mut seen = HashSet::new();
+    let gadgets = reports
+        .iter()
+        .flat_map(|r| r.measurements.iter().map(|m| m.gadget.as_str()))
+        .filter(|g| seen.insert(*g))
+        .collect::<Vec<_>>();
+
+    let mut rows = vec![];
+    let mut header = vec!["gadget".to_string(), "metric".to_string()];
+    header.extend(reports.iter().map(|r| r.version.clone()));
+    header.push("change".to_string());
+    rows.push(header);
+
+    for gadget in gadgets {
+        let metrics = reports
+            .iter()
+            .map(|r| r.get(gadget).map(Measurement::metrics))
+            .collect::<Vec<_>>();
+        for i in 0..5 {
+            let values = metrics
+                .iter()
+                .map(|m| m.map(|m| m[i].1))
+                .collect::<Vec<_>>();
+            let name = metrics.iter().flatten().next().unwrap()[i].0;
+
+            let mut row = vec![gadget.to_string(), name.to_string()];
+            row.extend(
+                values
+                    .iter()
+                    .map(|v| v.map_or("-".to_string(), |v| v.to_string())),
+            );
+            row.push(match (values.first(), values.last()) {
+                (Some(Some(first)), Some(Some(last))) if *first > 0 => format!(
+                    "{:+.1}%",
+                    (*last as f64 - *first as f64) * 100.0 / *first as f64
+                ),
+                _ => "-".to_string(),
+            });
+            rows.push(row);
+        }
+    }
+
+    let widths = (0..rows[0].len())
+        .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap())
+        .collect::<Vec<_>>();
+    let mut table = String::new();
+    for row in rows {
+        let cells = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(c, (cell, width))| {
+                if c < 2 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// A metric of a gadget that got worse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regression {
+    pub gadget: String,
+    pub metric: &'static str,
+    pub baseline: u128,
+    pub current: u128,
+}
+
+/// Returns the metrics of the gadgets of `baseline` that grew in `current`:
+/// any growth of the sizes, which are deterministic, and growth of the times
+/// beyond the fraction `tolerance`, which absorbs noise. Gadgets missing from
+/// `current` are not reported.
+pub fn regressions(baseline: &Report, current: &Report, tolerance: f64) -> Vec<Regression> {
+    let mut regressions = vec![];
+    for old in &baseline.measurements {
+        let new = match current.get(&old.gadget) {
+            Some(new) => new,
+            None => continue,
+        };
+        for (i, ((metric, before), (_, after))) in
+            old.metrics().iter().zip(new.metrics().iter()).enumerate()
+        {
+            let limit = if i < 3 {
+                *before as f64
+            } else {
+                *before as f64 * (1.0 + tolerance)
+            };
+            if *after as f64 > limit {
+                regressions.push(Regression {
+                    gadget: old.gadget.clone(),
+                    metric,
+                    baseline: *before,
+                    current: *after,
+                });
+            }
+        }
+    }
+    regressions
+}
+
+/// A chain of squarings, followed by the gadget in its own namespace.
+struct WithReference<C> {
+    constraints: usize,
+    gadget: Option<C>,
+}
+
+impl<S: PrimeField, C: Circuit<S>> Circuit<S> for WithReference<C> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        {
+            let mut cs = cs.namespace(|| "reference");
+            let mut x =
+                AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(S::multiplicative_generator()))?;
+            for i in 0..self.constraints {
+                x = x.square(cs.namespace(|| format!("square {}", i)))?;
+            }
+        }
+        match self.gadget {
+            Some(gadget) => gadget.synthesize(&mut cs.namespace(|| "gadget")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A circuit without constraints, to prove the reference alone.
+struct Nothing;
+
+impl<S: PrimeField> Circuit<S> for Nothing {
+    fn synthesize<CS: ConstraintSystem<S>>(self, _: &mut CS) -> Result<(), SynthesisError> {
+        Ok(())
+    }
+}
+
+fn median(
+    mut f: impl FnMut() -> Result<Duration, SynthesisError>,
+    samples: usize,
+) -> Result<Duration, SynthesisError> {
+    let mut times = (0..samples.max(1))
+        .map(|_| f())
+        .collect::<Result<Vec<_>, _>>()?;
+    times.sort();
+    Ok(times[times.len() / 2])
+}
+
+/// Returns the median time to prove the reference circuit with the gadget
+/// built by `factory`, or alone.
+fn proving_time<E, C>(
+    config: &BenchConfig,
+    factory: Option<&dyn Fn() -> C>,
+    mut rng: &mut dyn RngCore,
+) -> Result<Duration, SynthesisError>
+where
+    E: Engine,
+    E::G1: group::WnafGroup,
+    E::G2: group::WnafGroup,
+    C: Circuit<E::Fr>,
+{
+    let circuit = || WithReference {
+        constraints: config.reference_constraints,
+        gadget: factory.map(|f| f()),
+    };
+    let params: Parameters<E> = generate_random_parameters(circuit(), &mut rng)?;
+    median(
+        || {
+            let circuit = circuit();
+            let start = Instant::now();
+            create_random_proof(circuit, &params, &mut rng)?;
+            Ok(start.elapsed())
+        },
+        config.samples,
+    )
+}
+
+type Bench =
+    Box<dyn Fn(&BenchConfig, Duration, &mut dyn RngCore) -> Result<Measurement, SynthesisError>>;
+
+/// A registry of gadgets to measure on the engine `E`.
+pub struct Registry<E: Engine> {
+    gadgets: Vec<(String, Bench)>,
+    _engine: std::marker::PhantomData<E>,
+}
+
+impl<E> Registry<E>
+where
+    E: Engine,
+    E::G1: group::WnafGroup,
+    E::G2: group::WnafGroup,
+{
+    pub fn new() -> Self {
+        Registry {
+            gadgets: vec![],
+            _engine: std::marker::PhantomData,
+        }
+    }
+
+    /// Registers the gadget `name`, exercised by the circuits `factory`
+    /// returns. The circuits must have a witness.
+    pub fn register<C, F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        C: Circuit<E::Fr> + 'static,
+        F: Fn() -> C + 'static,
+    {
+        let gadget = name.to_string();
+        let bench = move |config: &BenchConfig, reference: Duration, rng: &mut dyn RngCore| {
+            let shape = RawCircuit::synthesize(factory())?;
+            let synthesis = median(
+                || {
+                    let circuit = factory();
+                    let start = Instant::now();
+                    Assignment::synthesize(circuit)?;
+                    Ok(start.elapsed())
+                },
+                config.samples,
+            )?;
+            let proving = proving_time::<E, C>(config, Some(&factory), rng)?;
+
+            Ok(Measurement {
+                gadget: gadget.clone(),
+                num_constraints: shape.num_constraints,
+                num_inputs: shape.num_inputs - 1,
+                num_aux: shape.num_aux,
+                synthesis,
+                marginal_proving: proving.checked_sub(reference).unwrap_or_default(),
+            })
+        };
+        self.gadgets.push((name.to_string(), Box::new(bench)));
+        self
+    }
+
+    /// Returns the names of the registered gadgets, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.gadgets.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Measures every gadget.
+    pub fn run<R: RngCore>(
+        &self,
+        config: &BenchConfig,
+        rng: &mut R,
+    ) -> Result<Report, SynthesisError> {
+        let reference = proving_time::<E, Nothing>(config, None, rng)?;
+        let measurements = self
+            .gadgets
+            .iter()
+            .map(|(_, bench)| bench(config, reference, rng))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Report {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            measurements,
+        })
+    }
+
+    /// Returns a registry of the hash gadgets of this crate, each hashing
+    /// one block: SHA-256 compression, BLAKE2s, and Poseidon of width 3.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register("sha256", || Sha256Block)
+            .register("blake2s", || Blake2sBlock)
+            .register("poseidon", || PoseidonHash);
+        registry
+    }
+}
+
+impl<E> Default for Registry<E>
+where
+    E: Engine,
+    E::G1: group::WnafGroup,
+    E::G2: group::WnafGroup,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Allocates `n` bits of a fixed pattern.
+fn block<S: PrimeField, CS: ConstraintSystem<S>>(
+    mut cs: CS,
+    n: usize,
+) -> Result<Vec<Boolean>, SynthesisError> {
+    (0..n)
+        .map(|i| {
+            let bit = AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(i % 3 == 0))?;
+            Ok(Boolean::from(bit))
+        })
+        .collect()
+}
+
+struct Sha256Block;
+
+impl<S: PrimeField> Circuit<S> for Sha256Block {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let input = block(cs.namespace(|| "block"), 512)?;
+        sha256::sha256_block_no_padding(cs.namespace(|| "sha256"), &input)?;
+        Ok(())
+    }
+}
+
+struct Blake2sBlock;
+
+impl<S: PrimeField> Circuit<S> for Blake2sBlock {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let input = block(cs.namespace(|| "block"), 512)?;
+        blake2s::blake2s(cs.namespace(|| "blake2s"), &input, b"12345678")?;
+        Ok(())
+    }
+}
+
+struct PoseidonHash;
+
+impl<S: PrimeField> Circuit<S> for PoseidonHash {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let params = PoseidonParams::for_width(3);
+        let message = (0..2)
+            .map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("message {}", i)), || {
+                    Ok(S::from(i as u64 + 1))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        poseidon::hash(cs.namespace(|| "poseidon"), &params, &message)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::Bls12;
+
+    /// Raises a public input to the power `2^n`.
+    struct Squarings(usize);
+
+    impl<S: PrimeField> Circuit<S> for Squarings {
+        fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(S::from(3)))?;
+            for i in 0..self.0 {
+                x = x.square(cs.namespace(|| format!("square {}", i)))?;
+            }
+            x.inputize(cs.namespace(|| "out"))
+        }
+    }
+
+    #[test]
+    fn standard_gadgets() {
+        fn satisfied<C: Circuit<bls12_381::Scalar>>(circuit: C) -> bool {
+            let mut cs = TestConstraintSystem::new();
+            circuit.synthesize(&mut cs).unwrap();
+            cs.is_satisfied()
+        }
+        assert!(satisfied(Sha256Block));
+        assert!(satisfied(Blake2sBlock));
+        assert!(satisfied(PoseidonHash));
+    }
+
+    #[test]
+    fn gadget_benchmarks() {
+        let mut rng = rng();
+        let config = BenchConfig {
+            reference_constraints: 8,
+            samples: 1,
+        };
+        let mut registry = Registry::<Bls12>::new();
+        registry
+            .register("short", || Squarings(2))
+            .register("long", || Squarings(6));
+        assert_eq!(registry.names(), ["short", "long"]);
+        assert_eq!(
+            Registry::<Bls12>::standard().names(),
+            ["sha256", "blake2s", "poseidon"]
+        );
+
+        let report = registry.run(&config, &mut rng).unwrap();
+        assert_eq!(report.version, env!("CARGO_PKG_VERSION"));
+        let sizes = report
+            .measurements
+            .iter()
+            .map(|m| {
+                (
+                    m.gadget.as_str(),
+                    m.num_constraints,
+                    m.num_inputs,
+                    m.num_aux,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [("short", 3, 1, 3), ("long", 7, 1, 7)]);
+
+        let mut bytes = vec![];
+        report.write(&mut bytes).unwrap();
+        assert_eq!(Report::read(&bytes[..]).unwrap(), report);
+        assert!(Report::read(&b"bellman-gadget-bench 2\n"[..]).is_err());
+
+        // Two versions, where one gadget got bigger and another was added.
+        let measurement = |gadget: &str, size: usize, ns: u64| Measurement {
+            gadget: gadget.to_string(),
+            num_constraints: size,
+            num_inputs: 1,
+            num_aux: size,
+            synthesis: Duration::from_nanos(ns),
+            marginal_proving: Duration::from_nanos(10 * ns),
+        };
+        let report = Report {
+            version: "0.8.0".to_string(),
+            measurements: vec![measurement("short", 3, 100), measurement("long", 7, 200)],
+        };
+        let later = Report {
+            version: "0.8.1".to_string(),
+            measurements: vec![
+                measurement("short", 3, 105),
+                measurement("long", 14, 400),
+                measurement("new", 1, 50),
+            ],
+        };
+
+        assert_eq!(
+            compare(&[report.clone(), later.clone()]),
+            "\
+gadget  metric        0.8.0  0.8.1   change
+short   constraints       3      3    +0.0%
+short   inputs            1      1    +0.0%
+short   aux               3      3    +0.0%
+short   synthesis ns    100    105    +5.0%
+short   proving ns     1000   1050    +5.0%
+long    constraints       7     14  +100.0%
+long    inputs            1      1    +0.0%
+long    aux               7     14  +100.0%
+long    synthesis ns    200    400  +100.0%
+long    proving ns     2000   4000  +100.0%
+new     constraints       -      1        -
+new     inputs            -      1        -
+new     aux               -      1        -
+new     synthesis ns      -     50        -
+new     proving ns        -    500        -
+"
+        );
+
+        let found = regressions(&report, &later, 0.1)
+            .into_iter()
+            .map(|r| (r.gadget, r.metric, r.baseline, r.current))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("long".to_string(), "constraints", 7, 14),
+                ("long".to_string(), "aux", 7, 14),
+                ("long".to_string(), "synthesis ns", 200, 400),
+                ("long".to_string(), "proving ns", 2000, 4000),
+            ]
+        );
+        assert!(regressions(&later, &report, 0.1).is_empty());
+    }
+}
@@ -0,0 +1,375 @@
+//! Parameters and verifying keys of several circuits, in a single file.
+//!
+//! Applications often ship several related circuits, such as the spend,
+//! output and migration circuits of a payment protocol. A bundle holds the
+//! parameters, or only the verifying key, of each of them, under a name and
+//! with the fingerprint of its circuit. [`BundleBuilder`] writes a bundle,
+//! and [`Bundle`] reads its directory and then loads only the entries it is
+//! asked for, seeking past the others.
+//!
+//! # Format
+//!
+//! Integers are big-endian, and strings are a u32 length followed by UTF-8
+//! bytes.
+//!
+//! ```text
+//! magic      "bellman-bundle"
+//! version    u32 (currently 1)
+//! curve      string
+//! count      u32
+//! directory  count times:
+//!              name     string
+//!              kind     u8, 0 for parameters, 1 for a verifying key
+//!              circuit  32 bytes, the fingerprint of the circuit
+//!              vk       32 bytes, VerifyingKey::hash
+//!              offset   u64, from the end of the directory
+//!              length   u64
+//! entries    as written by Parameters::write or VerifyingKey::write
+//! ```
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use pairing::Engine;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use super::{Parameters, VerifyingKey};
+
+pub(super) const MAGIC: &[u8] = b"bellman-bundle";
+pub(super) const VERSION: u32 = 1;
+
+/// What an entry of a bundle holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Parameters,
+    VerifyingKey,
+}
+
+/// An entry in the directory of a bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub kind: EntryKind,
+    /// The fingerprint of the circuit.
+    pub circuit: [u8; 32],
+    /// The hash of the verifying key.
+    pub vk: [u8; 32],
+    offset: u64,
+    length: u64,
+}
+
+enum Item<'a, E: Engine> {
+    Parameters(&'a Parameters<E>),
+    VerifyingKey(&'a VerifyingKey<E>),
+}
+
+impl<'a, E: Engine> Item<'a, E> {
+    fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        match self {
+            Item::Parameters(params) => params.write(writer),
+            Item::VerifyingKey(vk) => vk.write(writer),
+        }
+    }
+}
+
+/// Counts the bytes written to it.
+struct Counter(u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_string<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(s.len() as u32)?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = reader.read_u32::<BigEndian>()? as u64;
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8"))
+}
+
+/// Collects the entries of a bundle, and writes it.
+pub struct BundleBuilder<'a, E: Engine> {
+    curve: String,
+    entries: Vec<(String, [u8; 32], Item<'a, E>)>,
+}
+
+impl<'a, E: Engine> BundleBuilder<'a, E> {
+    /// Starts a bundle of circuits over `curve`, such as `"bls12_381"`.
+    pub fn new(curve: &str) -> Self {
+        BundleBuilder {
+            curve: curve.to_string(),
+            entries: vec![],
+        }
+    }
+
+    /// Adds the parameters of the circuit with fingerprint `circuit`.
+    pub fn parameters(mut self, name: &str, circuit: [u8; 32], params: &'a Parameters<E>) -> Self {
+        self.entries
+            .push((name.to_string(), circuit, Item::Parameters(params)));
+        self
+    }
+
+    /// Adds the verifying key of the circuit with fingerprint `circuit`,
+    /// for bundles shipped to verifiers.
+    pub fn verifying_key(mut self, name: &str, circuit: [u8; 32], vk: &'a VerifyingKey<E>) -> Self {
+        self.entries
+            .push((name.to_string(), circuit, Item::VerifyingKey(vk)));
+        self
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (i, (name, _, _)) in self.entries.iter().enumerate() {
+            if self.entries[..i].iter().any(|(other, _, _)| other == name) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("duplicate entry `{}`", name),
+                ));
+            }
+        }
+
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        write_string(&mut writer, &self.curve)?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+
+        // The entries are serialized once to measure them, so that the
+        // directory can come first.
+        let mut offset = 0;
+        for (name, circuit, item) in &self.entries {
+            let mut counter = Counter(0);
+            item.write(&mut counter)?;
+            let (kind, vk) = match item {
+                Item::Parameters(params) => (0, params.vk.hash()),
+                Item::VerifyingKey(vk) => (1, vk.hash()),
+            };
+
+            write_string(&mut writer, name)?;
+            writer.write_u8(kind)?;
+            writer.write_all(circuit)?;
+            writer.write_all(&vk)?;
+            writer.write_u64::<BigEndian>(offset)?;
+            writer.write_u64::<BigEndian>(counter.0)?;
+            offset += counter.0;
+        }
+
+        for (_, _, item) in &self.entries {
+            item.write(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// A bundle whose directory has been read.
+pub struct Bundle<R> {
+    reader: R,
+    curve: String,
+    entries: Vec<Entry>,
+    // The position of the end of the directory in the reader.
+    start: u64,
+}
+
+impl<R: Read + Seek> Bundle<R> {
+    /// Reads the directory of a bundle.
+    pub fn read(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a bundle"));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported bundle version",
+            ));
+        }
+        let curve = read_string(&mut reader)?;
+
+        let count = reader.read_u32::<BigEndian>()?;
+        let mut entries = vec![];
+        for _ in 0..count {
+            let name = read_string(&mut reader)?;
+            let kind = match reader.read_u8()? {
+                0 => EntryKind::Parameters,
+                1 => EntryKind::VerifyingKey,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unknown entry kind",
+                    ))
+                }
+            };
+            let mut circuit = [0; 32];
+            reader.read_exact(&mut circuit)?;
+            let mut vk = [0; 32];
+            reader.read_exact(&mut vk)?;
+            let offset = reader.read_u64::<BigEndian>()?;
+            let length = reader.read_u64::<BigEndian>()?;
+
+            entries.push(Entry {
+                name,
+                kind,
+                circuit,
+                vk,
+                offset,
+                length,
+            });
+        }
+
+        let start = reader.seek(SeekFrom::Current(0))?;
+        Ok(Bundle {
+            reader,
+            curve,
+            entries,
+            start,
+        })
+    }
+
+    pub fn curve(&self) -> &str {
+        &self.curve
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the entry called `name`.
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Returns the entry for the circuit with fingerprint `circuit`.
+    pub fn find_circuit(&self, circuit: &[u8; 32]) -> Option<&Entry> {
+        self.entries.iter().find(|e| &e.circuit == circuit)
+    }
+
+    fn seek(&mut self, name: &str) -> io::Result<Entry> {
+        let entry = self.entry(name).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no entry `{}` in the bundle", name),
+            )
+        })?;
+        self.reader
+            .seek(SeekFrom::Start(self.start + entry.offset))?;
+        Ok(entry)
+    }
+
+    fn check_hash(entry: &Entry, hash: [u8; 32]) -> io::Result<()> {
+        if hash != entry.vk {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the verifying key of `{}` does not match its hash",
+                    entry.name
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Loads the parameters called `name`.
+    pub fn load_parameters<E: Engine>(
+        &mut self,
+        name: &str,
+        checked: bool,
+    ) -> io::Result<Parameters<E>> {
+        let entry = self.seek(name)?;
+        if entry.kind != EntryKind::Parameters {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{}` only holds a verifying key", name),
+            ));
+        }
+
+        let params = Parameters::read((&mut self.reader).take(entry.length), checked)?;
+        Self::check_hash(&entry, params.vk.hash())?;
+        Ok(params)
+    }
+
+    /// Loads the verifying key called `name`, from either kind of entry.
+    pub fn load_verifying_key<E: Engine>(&mut self, name: &str) -> io::Result<VerifyingKey<E>> {
+        let entry = self.seek(name)?;
+
+        // Parameters start with their verifying key.
+        let vk = VerifyingKey::read((&mut self.reader).take(entry.length))?;
+        Self::check_hash(&entry, vk.hash())?;
+        Ok(vk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::exporter::ReplayCircuit;
+    use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+    use crate::groth16::generate_random_parameters;
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::{Bls12, Scalar};
+    use std::io::Cursor;
+
+    #[test]
+    fn bundles() {
+        let mut rng = rng();
+        let mut circuits = vec![];
+        for _ in 0..3 {
+            let (circuit, _) = random_circuit::<Scalar, _>(&CircuitConfig::default(), &mut rng);
+            let params = generate_random_parameters::<Bls12, _, _>(
+                ReplayCircuit {
+                    circuit: circuit.clone(),
+                    assignment: None,
+                },
+                &mut rng,
+            )
+            .unwrap();
+            circuits.push((circuit.fingerprint(), params));
+        }
+
+        let mut bytes = vec![];
+        BundleBuilder::new("bls12_381")
+            .parameters("spend", circuits[0].0, &circuits[0].1)
+            .parameters("output", circuits[1].0, &circuits[1].1)
+            .verifying_key("migration", circuits[2].0, &circuits[2].1.vk)
+            .write(&mut bytes)
+            .unwrap();
+
+        let mut bundle = Bundle::read(Cursor::new(&bytes)).unwrap();
+        assert_eq!(bundle.curve(), "bls12_381");
+        assert_eq!(bundle.entries().len(), 3);
+        assert_eq!(bundle.find_circuit(&circuits[1].0).unwrap().name, "output");
+
+        // Entries load in any order.
+        let vk = bundle.load_verifying_key::<Bls12>("migration").unwrap();
+        assert!(vk == circuits[2].1.vk);
+        let output = bundle.load_parameters::<Bls12>("output", true).unwrap();
+        assert!(output == circuits[1].1);
+        let vk = bundle.load_verifying_key::<Bls12>("spend").unwrap();
+        assert!(vk == circuits[0].1.vk);
+        assert!(bundle.load_parameters::<Bls12>("migration", true).is_err());
+        assert!(bundle.load_parameters::<Bls12>("unknown", true).is_err());
+
+        // A corrupted entry is caught by its hash.
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let mut bundle = Bundle::read(Cursor::new(&bytes)).unwrap();
+        assert!(bundle.load_verifying_key::<Bls12>("migration").is_err());
+
+        assert!(BundleBuilder::new("bls12_381")
+            .verifying_key("spend", circuits[0].0, &circuits[0].1.vk)
+            .verifying_key("spend", circuits[1].0, &circuits[1].1.vk)
+            .write(vec![])
+            .is_err());
+    }
+}
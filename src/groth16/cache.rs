@@ -0,0 +1,374 @@
+//! A cache of parameters shared by proving jobs.
+//!
+//! A service that proves many circuits would otherwise have each worker
+//! read and check its own copy of the parameters of every circuit it runs.
+//! A [`ParamsCache`] keeps one copy of the parameters of each circuit, keyed
+//! by the [fingerprint] of the circuit, and hands
+//! out shared references to it. The parameters are read and their points
+//! checked once, by the first job that asks for them; jobs that ask while
+//! they are being read wait for that read rather than starting another.
+//!
+//! The cache holds up to a configured number of bytes of parameters. Once
+//! it is over that, it drops the least recently used parameters that no job
+//! holds any more, so that parameters in use are never loaded twice.
+//!
+//! [`ParamsCache::global`] returns a cache shared by the whole process, for
+//! each engine. Lookups are reported with
+//! [`metrics::record_cache_access`](crate::metrics) when the `metrics`
+//! feature is enabled.
+//!
+//! [fingerprint]: super::exporter::RawCircuit::fingerprint
+
+use pairing::Engine;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+use super::Parameters;
+use crate::multicore::Global;
+
+/// The key of parameters in a [`ParamsCache`].
+pub type Fingerprint = [u8; 32];
+
+/// The capacity of the [global](ParamsCache::global) caches, in bytes.
+pub const DEFAULT_CAPACITY: usize = 1 << 32;
+
+/// Returns the number of bytes that `params` take in memory.
+pub fn params_size<E: Engine>(params: &Parameters<E>) -> usize {
+    let g1 = params.h.len() + params.l.len() + params.a.len() + params.b_g1.len();
+    g1 * std::mem::size_of::<E::G1Affine>() + params.b_g2.len() * std::mem::size_of::<E::G2Affine>()
+}
+
+struct Entry<E: Engine> {
+    params: Arc<Parameters<E>>,
+    size: usize,
+    last_used: u64,
+}
+
+struct State<E: Engine> {
+    entries: HashMap<Fingerprint, Entry<E>>,
+    loading: HashSet<Fingerprint>,
+    size: usize,
+    clock: u64,
+}
+
+/// Parameters shared by concurrent proving jobs.
+pub struct ParamsCache<E: Engine> {
+    capacity: usize,
+    state: Mutex<State<E>>,
+    loaded: Condvar,
+}
+
+impl<E: Engine> ParamsCache<E> {
+    /// Creates an empty cache that holds up to `capacity` bytes of
+    /// parameters that are not in use.
+    pub fn new(capacity: usize) -> Self {
+        ParamsCache {
+            capacity,
+            state: Mutex::new(State {
+                entries: HashMap::new(),
+                loading: HashSet::new(),
+                size: 0,
+                clock: 0,
+            }),
+            loaded: Condvar::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of circuits whose parameters are cached.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bytes of cached parameters, in use or not.
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().size
+    }
+
+    /// Returns the number of jobs that hold the parameters of `fingerprint`,
+    /// not counting the cache itself.
+    pub fn users(&self, fingerprint: &Fingerprint) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .get(fingerprint)
+            .map_or(0, |entry| Arc::strong_count(&entry.params) - 1)
+    }
+
+    /// Returns the cached parameters of `fingerprint`, if any.
+    pub fn get(&self, fingerprint: &Fingerprint) -> Option<Arc<Parameters<E>>> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let params = state.entries.get_mut(fingerprint).map(|entry| {
+            entry.last_used = clock;
+            entry.params.clone()
+        });
+        record(params.is_some());
+        params
+    }
+
+    /// Returns the parameters of `fingerprint`, calling `load` for them if
+    /// they are not cached. If another thread is loading them, waits for it
+    /// instead, and only loads them itself if that thread failed.
+    pub fn get_or_load<F>(
+        &self,
+        fingerprint: Fingerprint,
+        load: F,
+    ) -> io::Result<Arc<Parameters<E>>>
+    where
+        F: FnOnce() -> io::Result<Parameters<E>>,
+    {
+        let mut state = self.state.lock().unwrap();
+        let mut hit = true;
+        loop {
+            state.clock += 1;
+            let clock = state.clock;
+            if let Some(entry) = state.entries.get_mut(&fingerprint) {
+                entry.last_used = clock;
+                record(hit);
+                return Ok(entry.params.clone());
+            }
+            if !state.loading.contains(&fingerprint) {
+                break;
+            }
+            hit = false;
+            state = self.loaded.wait(state).unwrap();
+        }
+        record(false);
+        state.loading.insert(fingerprint);
+        drop(state);
+
+        let loaded = load();
+
+        let mut state = self.state.lock().unwrap();
+        state.loading.remove(&fingerprint);
+        self.loaded.notify_all();
+        let params = Arc::new(loaded?);
+        self.insert_locked(&mut state, fingerprint, params.clone());
+        Ok(params)
+    }
+
+    /// Returns the parameters of `fingerprint`, reading and checking them
+    /// from the file at `path` if they are not cached.
+    pub fn open<P: AsRef<Path>>(
+        &self,
+        fingerprint: Fingerprint,
+        path: P,
+    ) -> io::Result<Arc<Parameters<E>>> {
+        self.get_or_load(fingerprint, || {
+            Parameters::read(BufReader::new(File::open(path)?), true)
+        })
+    }
+
+    /// Caches `params` under `fingerprint`, replacing any previous ones, and
+    /// returns a shared reference to them.
+    pub fn insert(&self, fingerprint: Fingerprint, params: Parameters<E>) -> Arc<Parameters<E>> {
+        let params = Arc::new(params);
+        let mut state = self.state.lock().unwrap();
+        self.insert_locked(&mut state, fingerprint, params.clone());
+        params
+    }
+
+    /// Removes the parameters of `fingerprint` from the cache. Jobs that
+    /// hold them keep them until they are done.
+    pub fn remove(&self, fingerprint: &Fingerprint) -> Option<Arc<Parameters<E>>> {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entries.remove(fingerprint)?;
+        state.size -= entry.size;
+        Some(entry.params)
+    }
+
+    /// Drops the least recently used parameters that are not in use until
+    /// the cache is within its capacity, and returns the fingerprints of
+    /// those it dropped.
+    pub fn evict(&self) -> Vec<Fingerprint> {
+        let mut state = self.state.lock().unwrap();
+        self.evict_locked(&mut state)
+    }
+
+    fn insert_locked(
+        &self,
+        state: &mut State<E>,
+        fingerprint: Fingerprint,
+        params: Arc<Parameters<E>>,
+    ) {
+        state.clock += 1;
+        let entry = Entry {
+            size: params_size(&params),
+            params,
+            last_used: state.clock,
+        };
+        state.size += entry.size;
+        if let Some(previous) = state.entries.insert(fingerprint, entry) {
+            state.size -= previous.size;
+        }
+        self.evict_locked(state);
+    }
+
+    fn evict_locked(&self, state: &mut State<E>) -> Vec<Fingerprint> {
+        let mut evicted = vec![];
+        while state.size > self.capacity {
+            let unused = state
+                .entries
+                .iter()
+                .filter(|(_, entry)| Arc::strong_count(&entry.params) == 1)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(fingerprint, _)| *fingerprint);
+            match unused {
+                Some(fingerprint) => {
+                    let entry = state.entries.remove(&fingerprint).unwrap();
+                    state.size -= entry.size;
+                    evicted.push(fingerprint);
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+}
+
+impl<E> ParamsCache<E>
+where
+    E: Engine,
+    Parameters<E>: Send + Sync,
+{
+    /// Returns the cache of the process for the engine `E`, which holds up
+    /// to [`DEFAULT_CAPACITY`] bytes.
+    pub fn global() -> Arc<Self> {
+        static CACHES: Global<Mutex<Vec<Box<dyn Any + Send + Sync>>>> = Global::new();
+
+        let mut caches = CACHES.get().lock().unwrap();
+        if let Some(cache) = caches.iter().find_map(|c| c.downcast_ref::<Arc<Self>>()) {
+            return cache.clone();
+        }
+        let cache = Arc::new(Self::new(DEFAULT_CAPACITY));
+        caches.push(Box::new(cache.clone()));
+        cache
+    }
+}
+
+#[allow(unused_variables)]
+fn record(hit: bool) {
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_cache_access(hit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::exporter::ReplayCircuit;
+    use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+    use crate::groth16::generate_random_parameters;
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::{Bls12, Scalar};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn params_cache() {
+        let mut rng = rng();
+        let mut circuits = vec![];
+        for _ in 0..3 {
+            let (circuit, _) = random_circuit::<Scalar, _>(&CircuitConfig::default(), &mut rng);
+            let params = generate_random_parameters::<Bls12, _, _>(
+                ReplayCircuit {
+                    circuit: circuit.clone(),
+                    assignment: None,
+                },
+                &mut rng,
+            )
+            .unwrap();
+            circuits.push((circuit.fingerprint(), params));
+        }
+        // Room for any two of the circuits, but not for all three.
+        let sizes = circuits
+            .iter()
+            .map(|(_, params)| params_size(params))
+            .collect::<Vec<_>>();
+        let capacity = sizes.iter().sum::<usize>() - sizes.iter().min().unwrap();
+
+        // Concurrent jobs share a single load.
+        let cache = Arc::new(ParamsCache::<Bls12>::new(capacity));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let jobs = (0..4)
+            .map(|_| {
+                let (cache, loads) = (cache.clone(), loads.clone());
+                let (fingerprint, params) = (circuits[0].0, circuits[0].1.clone());
+                thread::spawn(move || {
+                    cache
+                        .get_or_load(fingerprint, || {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            thread::sleep(std::time::Duration::from_millis(50));
+                            Ok(params)
+                        })
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        let held = jobs
+            .into_iter()
+            .map(|job| job.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(held.iter().all(|p| Arc::ptr_eq(p, &held[0])));
+        assert_eq!(cache.users(&circuits[0].0), 4);
+
+        // Parameters in use are kept over capacity, and the least recently
+        // used of the others are dropped.
+        cache.insert(circuits[1].0, circuits[1].1.clone());
+        assert!(cache.get(&circuits[0].0).is_some());
+        cache.insert(circuits[2].0, circuits[2].1.clone());
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&circuits[1].0).is_none());
+        assert_eq!(
+            cache.size(),
+            params_size(&circuits[0].1) + params_size(&circuits[2].1)
+        );
+
+        drop(held);
+        assert_eq!(cache.users(&circuits[0].0), 0);
+        assert!(cache.evict().is_empty());
+        cache.insert(circuits[1].0, circuits[1].1.clone());
+        assert!(cache.get(&circuits[0].0).is_none());
+
+        // A failed load is not cached.
+        let missing = [0xff; 32];
+        assert!(cache
+            .get_or_load(missing, || Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "gone"
+            )))
+            .is_err());
+        assert!(cache.get(&missing).is_none());
+
+        // Parameters are read from disk once.
+        let path = std::env::temp_dir().join("bellman-params-cache-test");
+        let mut file = File::create(&path).unwrap();
+        circuits[0].1.write(&mut file).unwrap();
+        let from_file = cache.open(circuits[0].0, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(from_file == Arc::new(circuits[0].1.clone()));
+        assert!(Arc::ptr_eq(
+            &cache.open(circuits[0].0, &path).unwrap(),
+            &from_file
+        ));
+
+        assert!(Arc::ptr_eq(
+            &ParamsCache::<Bls12>::global(),
+            &ParamsCache::<Bls12>::global()
+        ));
+    }
+}
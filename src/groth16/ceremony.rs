@@ -0,0 +1,425 @@
+//! Verification of phase-2 ceremony transcripts.
+//!
+//! In the second phase of a Groth16 trusted setup, parameters generated for
+//! a circuit are passed from participant to participant. Each one picks a
+//! secret `d`, multiplies `delta` by it and divides the `H` and `L` queries
+//! by it with [`contribute`], and publishes a [`Contribution`]: the new
+//! `delta`, and a proof of knowledge of `d`. As long as one participant
+//! destroys their `d`, nobody can forge proofs with the final parameters.
+//!
+//! Contributions form a chain: each one names the transcript it extends,
+//! which is the hash of the initial parameters for the first contribution,
+//! and the [`Contribution::hash`] of the previous one after that.
+//! [`verify_transcript`] checks the whole chain against the circuit, the
+//! initial parameters and the final parameters, and returns a [`Report`]
+//! listing every check it made.
+//!
+//! The initial parameters are trusted: this module does not check how they
+//! were derived from the first phase, only that the final parameters differ
+//! from them in the contributions alone.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Curve, Group, GroupEncoding, UncompressedEncoding};
+use pairing::Engine;
+use rand_core::RngCore;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use super::exporter::RawCircuit;
+use super::structure::query_lengths;
+use super::vectors::SeededRng;
+use super::Parameters;
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+use crate::zeroize::Secret;
+
+fn hasher() -> blake2s_simd::State {
+    Blake2sParams::new().personal(b"bellCrmy").to_state()
+}
+
+/// Returns the transcript that the first contribution to `params` extends.
+pub fn initial_transcript<E: Engine>(params: &Parameters<E>) -> [u8; 32] {
+    let mut bytes = vec![];
+    params
+        .write(&mut bytes)
+        .expect("writing to a Vec does not fail");
+
+    let mut transcript = [0; 32];
+    transcript.copy_from_slice(hasher().update(&bytes).finalize().as_bytes());
+    transcript
+}
+
+/// One participant's contribution to the ceremony.
+#[derive(Clone)]
+pub struct Contribution<E: Engine> {
+    /// `delta` in G1 after the contribution.
+    pub delta_after: E::G1Affine,
+    /// A random element `s`, and `s * d`.
+    pub s: E::G1Affine,
+    pub s_delta: E::G1Affine,
+    /// `r * d`, where `r` is derived from the transcript, `s` and `s * d`.
+    pub r_delta: E::G2Affine,
+    /// The transcript this contribution extends.
+    pub transcript: [u8; 32],
+}
+
+/// Derives the G2 element `r` of a proof of knowledge.
+fn hash_to_g2<E: Engine>(transcript: &[u8; 32], s: &E::G1Affine, s_delta: &E::G1Affine) -> E::G2 {
+    let mut seed = transcript.to_vec();
+    seed.extend_from_slice(s.to_bytes().as_ref());
+    seed.extend_from_slice(s_delta.to_bytes().as_ref());
+
+    E::G2::random(SeededRng::new(&seed, "proof-of-knowledge"))
+}
+
+/// Returns `true` if `g1.1 / g1.0 = g2.1 / g2.0`, in the exponent.
+pub(super) fn same_ratio<E: Engine>(
+    g1: (E::G1Affine, E::G1Affine),
+    g2: (E::G2Affine, E::G2Affine),
+) -> bool {
+    E::pairing(&g1.0, &g2.1) == E::pairing(&g1.1, &g2.0)
+}
+
+impl<E: Engine> PartialEq for Contribution<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta_after == other.delta_after
+            && self.s == other.s
+            && self.s_delta == other.s_delta
+            && self.r_delta == other.r_delta
+            && self.transcript == other.transcript
+    }
+}
+
+impl<E: Engine> Contribution<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.delta_after.to_uncompressed().as_ref())?;
+        writer.write_all(self.s.to_uncompressed().as_ref())?;
+        writer.write_all(self.s_delta.to_uncompressed().as_ref())?;
+        writer.write_all(self.r_delta.to_uncompressed().as_ref())?;
+        writer.write_all(&self.transcript)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        fn read_point<G: UncompressedEncoding, R: Read>(reader: &mut R) -> io::Result<G> {
+            let mut repr = G::Uncompressed::default();
+            reader.read_exact(repr.as_mut())?;
+            Option::from(G::from_uncompressed(&repr)).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid point in contribution")
+            })
+        }
+
+        let delta_after = read_point(&mut reader)?;
+        let s = read_point(&mut reader)?;
+        let s_delta = read_point(&mut reader)?;
+        let r_delta = read_point(&mut reader)?;
+        let mut transcript = [0; 32];
+        reader.read_exact(&mut transcript)?;
+
+        Ok(Contribution {
+            delta_after,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        })
+    }
+
+    /// Returns the transcript that the next contribution extends.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut bytes = vec![];
+        self.write(&mut bytes)
+            .expect("writing to a Vec does not fail");
+
+        let mut hash = [0; 32];
+        hash.copy_from_slice(hasher().update(&bytes).finalize().as_bytes());
+        hash
+    }
+
+    /// Returns `true` if the proof of knowledge is valid, and the
+    /// contribution moves `delta` from `delta_before` to `delta_after`.
+    fn is_valid(&self, delta_before: E::G1Affine) -> bool {
+        if bool::from(self.s.is_identity() | self.delta_after.is_identity()) {
+            return false;
+        }
+        let r = hash_to_g2::<E>(&self.transcript, &self.s, &self.s_delta).to_affine();
+
+        same_ratio::<E>((self.s, self.s_delta), (r, self.r_delta))
+            && same_ratio::<E>((delta_before, self.delta_after), (r, self.r_delta))
+    }
+}
+
+/// Contributes a random secret to `params`, extending `transcript`, and
+/// returns the contribution to publish. The secret is erased before this
+/// returns.
+pub fn contribute<E: Engine, R: RngCore>(
+    params: &mut Parameters<E>,
+    transcript: &[u8; 32],
+    rng: &mut R,
+) -> Contribution<E> {
+    let d = loop {
+        let d = Secret::new(E::Fr::random(&mut *rng), E::Fr::zero());
+        if !d.is_zero() {
+            break d;
+        }
+    };
+    let d_inverse = Secret::new(d.invert().unwrap(), E::Fr::zero());
+
+    let s = E::G1::random(&mut *rng).to_affine();
+    let s_delta = (s * &*d).to_affine();
+    let r = hash_to_g2::<E>(transcript, &s, &s_delta);
+    let r_delta = (r * &*d).to_affine();
+
+    let scale = |points: &[E::G1Affine]| {
+        let projective = points.iter().map(|p| *p * &*d_inverse).collect::<Vec<_>>();
+        let mut affine = vec![E::G1Affine::identity(); projective.len()];
+        E::G1::batch_normalize(&projective, &mut affine);
+        Arc::new(affine)
+    };
+    params.h = scale(&params.h);
+    params.l = scale(&params.l);
+    params.vk.delta_g1 = (params.vk.delta_g1 * &*d).to_affine();
+    params.vk.delta_g2 = (params.vk.delta_g2 * &*d).to_affine();
+
+    Contribution {
+        delta_after: params.vk.delta_g1,
+        s,
+        s_delta,
+        r_delta,
+        transcript: *transcript,
+    }
+}
+
+/// A check made while verifying a transcript.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    pub description: String,
+    pub passed: bool,
+}
+
+/// The result of verifying a transcript.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// The transcript of the initial parameters.
+    pub initial: [u8; 32],
+    /// The hash of each contribution, in order.
+    pub contributions: Vec<[u8; 32]>,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Returns `true` if every check passed.
+    pub fn is_valid(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub(super) fn check(&mut self, passed: bool, description: String) {
+        self.checks.push(Check {
+            description,
+            passed,
+        });
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "initial parameters: {}", hex(&self.initial))?;
+        for (i, hash) in self.contributions.iter().enumerate() {
+            writeln!(f, "contribution {}: {}", i + 1, hex(hash))?;
+        }
+        for check in self.checks.iter() {
+            let status = if check.passed { "ok" } else { "FAILED" };
+            writeln!(f, "{:<6} {}", status, check.description)?;
+        }
+        let result = if self.is_valid() { "valid" } else { "INVALID" };
+        writeln!(f, "transcript is {}", result)
+    }
+}
+
+/// Verifies that `params` results from applying `contributions`, in order,
+/// to `initial`, which matches `circuit`. `rng` is used to batch the checks
+/// of the `H` and `L` queries.
+pub fn verify_transcript<E: Engine, R: RngCore>(
+    circuit: &RawCircuit<E::Fr>,
+    initial: &Parameters<E>,
+    contributions: &[Contribution<E>],
+    params: &Parameters<E>,
+    rng: &mut R,
+) -> Report {
+    let mut report = Report {
+        initial: initial_transcript(initial),
+        contributions: contributions.iter().map(|c| c.hash()).collect(),
+        checks: vec![],
+    };
+
+    let lengths = [
+        initial.vk.ic.len(),
+        initial.l.len(),
+        initial.h.len(),
+        initial.a.len(),
+        initial.b_g1.len(),
+        initial.b_g2.len(),
+    ];
+    report.check(
+        query_lengths(circuit)
+            .iter()
+            .map(|(_, len)| *len)
+            .eq(lengths.iter().copied()),
+        "initial parameters match the shape of the circuit".to_string(),
+    );
+
+    verify_chain(&mut report, initial, contributions, params, rng);
+    report
+}
+
+/// Checks that `params` results from applying `contributions`, in order,
+/// to `initial`, the first contribution extending `report.initial`.
+pub(super) fn verify_chain<E: Engine, R: RngCore>(
+    report: &mut Report,
+    initial: &Parameters<E>,
+    contributions: &[Contribution<E>],
+    params: &Parameters<E>,
+    rng: &mut R,
+) {
+    let mut transcript = report.initial;
+    let mut delta = initial.vk.delta_g1;
+    for (i, contribution) in contributions.iter().enumerate() {
+        report.check(
+            contribution.transcript == transcript,
+            format!("contribution {} extends the transcript", i + 1),
+        );
+        report.check(
+            contribution.is_valid(delta),
+            format!("contribution {} proves knowledge of its secret", i + 1),
+        );
+        transcript = contribution.hash();
+        delta = contribution.delta_after;
+    }
+
+    report.check(
+        params.vk.delta_g1 == delta,
+        "final delta in G1 is the last contribution's".to_string(),
+    );
+    report.check(
+        same_ratio::<E>(
+            (initial.vk.delta_g1, params.vk.delta_g1),
+            (initial.vk.delta_g2, params.vk.delta_g2),
+        ),
+        "final delta in G2 matches delta in G1".to_string(),
+    );
+
+    // Every element of H and L must be scaled by the inverse of the same
+    // factor as delta, which a random linear combination checks at once.
+    let worker = Worker::new();
+    let mut scaled = |before: &[E::G1Affine], after: &[E::G1Affine]| {
+        if before.len() != after.len() {
+            return false;
+        }
+        let coeffs = (0..before.len())
+            .map(|_| E::Fr::random(&mut *rng))
+            .collect::<Vec<_>>();
+        match (
+            dense_multiexp::<E::G1>(&worker, before, &coeffs),
+            dense_multiexp::<E::G1>(&worker, after, &coeffs),
+        ) {
+            (Ok(before), Ok(after)) => same_ratio::<E>(
+                (after.to_affine(), before.to_affine()),
+                (initial.vk.delta_g2, params.vk.delta_g2),
+            ),
+            _ => false,
+        }
+    };
+    let h = scaled(&initial.h, &params.h);
+    report.check(h, "H query is scaled by the contributions".to_string());
+    let l = scaled(&initial.l, &params.l);
+    report.check(l, "L query is scaled by the contributions".to_string());
+
+    report.check(
+        params.vk.alpha_g1 == initial.vk.alpha_g1
+            && params.vk.beta_g1 == initial.vk.beta_g1
+            && params.vk.beta_g2 == initial.vk.beta_g2
+            && params.vk.gamma_g2 == initial.vk.gamma_g2
+            && params.vk.ic == initial.vk.ic
+            && params.a == initial.a
+            && params.b_g1 == initial.b_g1
+            && params.b_g2 == initial.b_g2,
+        "final parameters are otherwise unchanged".to_string(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{create_random_proof, prepare_verifying_key, verify_proof};
+    use bls12_381::{Bls12, Scalar};
+
+    #[test]
+    fn transcript_verification() {
+        let f = Fixture::<Bls12>::new();
+        let (initial, circuit, mut rng) = (&f.params, &f.circuit, f.rng.clone());
+
+        let mut params = initial.clone();
+        let mut contributions = vec![];
+        let mut transcript = initial_transcript(initial);
+        for _ in 0..3 {
+            let contribution = contribute(&mut params, &transcript, &mut rng);
+            transcript = contribution.hash();
+
+            let mut bytes = vec![];
+            contribution.write(&mut bytes).unwrap();
+            assert!(Contribution::read(&bytes[..]).unwrap() == contribution);
+            contributions.push(contribution);
+        }
+
+        let report = verify_transcript(circuit, initial, &contributions, &params, &mut rng);
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(report.contributions.len(), 3);
+        assert_eq!(report.contributions[2], transcript);
+
+        let proof = create_random_proof(f.circuit(), &params, &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, f.inputs()).is_ok());
+
+        // Contributions out of order.
+        let mut swapped = contributions.clone();
+        swapped.swap(0, 1);
+        let report = verify_transcript(circuit, initial, &swapped, &params, &mut rng);
+        assert!(!report.is_valid());
+
+        // A contribution that is left out.
+        let report = verify_transcript(circuit, initial, &contributions[1..], &params, &mut rng);
+        assert!(!report.is_valid());
+
+        // Final parameters with an L query that was not scaled.
+        let mut tampered = params.clone();
+        let mut l = (*tampered.l).clone();
+        l[0] = (l[0] * Scalar::from(2)).to_affine();
+        tampered.l = Arc::new(l);
+        let report = verify_transcript(circuit, initial, &contributions, &tampered, &mut rng);
+        let failed = report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.description.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(failed, vec!["L query is scaled by the contributions"]);
+
+        // Parameters for a different circuit.
+        let (other, _) = random_circuit::<Scalar, _>(
+            &CircuitConfig {
+                num_inputs: 3,
+                ..CircuitConfig::default()
+            },
+            &mut rng,
+        );
+        let report = verify_transcript(&other, initial, &contributions, &params, &mut rng);
+        assert!(!report.is_valid());
+    }
+}
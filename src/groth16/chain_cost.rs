@@ -0,0 +1,212 @@
+//! Estimates of the cost of verifying Groth16 proofs on chain.
+//!
+//! A verifier contract or program spends most of its budget in the
+//! precompiles or syscalls of the curve operations, whose prices are fixed
+//! by the chain: a [`Target`] lists them, and [`estimate`] counts the
+//! operations that verifying proofs with a given number of public inputs
+//! takes, so that circuit designs can be compared by their verification cost
+//! before being deployed. The targets provided are:
+//!
+//! - [`EVM_BN254`], with the prices of EIP-1108 for the BN254 precompiles
+//!   and of EIP-2028 for calldata;
+//! - [`EVM_BLS12_381`], with the prices of EIP-2537 for the BLS12-381
+//!   precompiles, each multiplication costing a `G1MSM` of one point, and of
+//!   EIP-2028 for calldata;
+//! - [`SOLANA_BN254`], with the compute units of the `alt_bn128` syscalls,
+//!   within the compute and size limits of a transaction.
+//!
+//! Verifying one proof takes a multiplication and an addition per public
+//! input, to combine the IC points, and a product of four pairings. A batch
+//! of `k` proofs for the same key is verified with a random linear
+//! combination instead: `k - 1` multiplications for the `A` points and as
+//! many for the `C` points, `n + 1` for the IC points, each scaled by the sum
+//! of the random factors times the inputs, and a product of `k + 3`
+//! pairings. The cost of the interpreter around the precompiles, of the field
+//! arithmetic and of deriving the random factors is not counted, beyond the
+//! fixed `overhead` of the target.
+
+use pairing::Engine;
+
+use super::VerifyingKey;
+
+/// The prices of a chain on which proofs are verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub name: &'static str,
+    /// The unit in which costs are counted.
+    pub unit: &'static str,
+    /// The length of an encoded G1 point, G2 point and scalar.
+    pub g1_len: usize,
+    pub g2_len: usize,
+    pub scalar_len: usize,
+    pub g1_add: u64,
+    pub g1_mul: u64,
+    /// The price of a product of `k` pairings is `pairing_base + k *
+    /// pairing_per_pair`.
+    pub pairing_base: u64,
+    pub pairing_per_pair: u64,
+    /// The price of each byte of proofs and inputs sent to the verifier.
+    pub per_byte: u64,
+    /// The price of a transaction, whatever it does.
+    pub overhead: u64,
+    /// The largest cost of a transaction, if the chain has one.
+    pub max_cost: Option<u64>,
+    /// The largest size of a transaction, if the chain has one.
+    pub max_bytes: Option<usize>,
+}
+
+/// Ethereum, with the BN254 precompiles of EIP-1108.
+pub const EVM_BN254: Target = Target {
+    name: "EVM (BN254)",
+    unit: "gas",
+    g1_len: 64,
+    g2_len: 128,
+    scalar_len: 32,
+    g1_add: 150,
+    g1_mul: 6_000,
+    pairing_base: 45_000,
+    pairing_per_pair: 34_000,
+    per_byte: 16,
+    overhead: 21_000,
+    max_cost: None,
+    max_bytes: None,
+};
+
+/// Ethereum, with the BLS12-381 precompiles of EIP-2537.
+pub const EVM_BLS12_381: Target = Target {
+    name: "EVM (BLS12-381)",
+    unit: "gas",
+    g1_len: 128,
+    g2_len: 256,
+    scalar_len: 32,
+    g1_add: 375,
+    g1_mul: 12_000,
+    pairing_base: 37_700,
+    pairing_per_pair: 32_600,
+    per_byte: 16,
+    overhead: 21_000,
+    max_cost: None,
+    max_bytes: None,
+};
+
+/// Solana, with the `alt_bn128` syscalls. The first pairing costs 36,364
+/// compute units, and every other 12,121.
+pub const SOLANA_BN254: Target = Target {
+    name: "Solana (BN254)",
+    unit: "compute units",
+    g1_len: 64,
+    g2_len: 128,
+    scalar_len: 32,
+    g1_add: 334,
+    g1_mul: 3_840,
+    pairing_base: 36_364 - 12_121,
+    pairing_per_pair: 12_121,
+    per_byte: 0,
+    overhead: 0,
+    max_cost: Some(1_400_000),
+    max_bytes: Some(1_232),
+};
+
+/// The operations and cost of verifying proofs on a target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostReport {
+    pub target: &'static str,
+    pub unit: &'static str,
+    pub proofs: usize,
+    pub g1_adds: u64,
+    pub g1_muls: u64,
+    pub pairings: u64,
+    /// The size of the proofs and their inputs.
+    pub bytes: usize,
+    /// The cost of the whole verification.
+    pub cost: u64,
+    /// Whether the verification fits in one transaction.
+    pub fits: bool,
+}
+
+impl CostReport {
+    /// Returns the cost of verification per proof, rounded up.
+    pub fn cost_per_proof(&self) -> u64 {
+        (self.cost + self.proofs as u64 - 1) / self.proofs as u64
+    }
+}
+
+/// Estimates the cost of verifying `proofs` proofs with `num_inputs` public
+/// inputs each, not counting `ONE`, on `target`. More than one proof is
+/// verified as a batch.
+///
+/// # Panics
+///
+/// Panics if `proofs` is zero.
+pub fn estimate(target: &Target, num_inputs: usize, proofs: usize) -> CostReport {
+    assert!(proofs > 0);
+    let (n, k) = (num_inputs as u64, proofs as u64);
+    let (g1_muls, g1_adds, pairings) = if proofs == 1 {
+        (n, n, 4)
+    } else {
+        (2 * (k - 1) + n + 1, 2 * (k - 1) + n, k + 3)
+    };
+
+    let bytes = proofs * (2 * target.g1_len + target.g2_len + num_inputs * target.scalar_len);
+    let cost = target.overhead
+        + g1_muls * target.g1_mul
+        + g1_adds * target.g1_add
+        + target.pairing_base
+        + pairings * target.pairing_per_pair
+        + bytes as u64 * target.per_byte;
+    let fits = target.max_cost.map_or(true, |max| cost <= max)
+        && target.max_bytes.map_or(true, |max| bytes <= max);
+
+    CostReport {
+        target: target.name,
+        unit: target.unit,
+        proofs,
+        g1_adds,
+        g1_muls,
+        pairings,
+        bytes,
+        cost,
+        fits,
+    }
+}
+
+/// Estimates the cost of verifying `proofs` proofs for `vk` on `target`.
+pub fn estimate_for_key<E: Engine>(
+    target: &Target,
+    vk: &VerifyingKey<E>,
+    proofs: usize,
+) -> CostReport {
+    estimate(target, vk.ic.len() - 1, proofs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verification_costs() {
+        // The cost of a verifier with one public input, on its own.
+        let single = estimate(&EVM_BN254, 1, 1);
+        assert_eq!((single.g1_muls, single.g1_adds, single.pairings), (1, 1, 4));
+        assert_eq!(single.bytes, 256 + 32);
+        assert_eq!(
+            single.cost,
+            21_000 + 6_000 + 150 + 45_000 + 4 * 34_000 + 288 * 16
+        );
+        assert!(single.fits);
+
+        // Batches share the pairings of the key.
+        let batch = estimate(&EVM_BN254, 1, 8);
+        assert_eq!(batch.pairings, 11);
+        assert!(batch.cost_per_proof() < single.cost_per_proof());
+
+        let bls = estimate(&EVM_BLS12_381, 1, 1);
+        assert!(bls.bytes > single.bytes);
+
+        // A Solana transaction only fits a few proofs.
+        assert!(estimate(&SOLANA_BN254, 4, 1).fits);
+        let too_large = estimate(&SOLANA_BN254, 4, 8);
+        assert!(too_large.bytes > 1_232);
+        assert!(!too_large.fits);
+    }
+}
@@ -0,0 +1,337 @@
+//! Checkpoints, so that parameter generation and proving can resume after
+//! a crash.
+//!
+//! Most of the time spent in [`generate_parameters`] goes into exponentiating
+//! the generators to compute the queries. [`generate_parameters_checkpointed`]
+//! does this in windows of [`Checkpoint::window`] elements, and stores each
+//! completed window in the checkpoint directory. If keygen is interrupted,
+//! calling it again with the same circuit, toxic waste and directory loads
+//! the completed windows instead of recomputing them.
+//!
+//! Windows are bound to the circuit and toxic waste they were computed with,
+//! and to their position, by a BLAKE2s tag; windows that do not match, such
+//! as those of another run or those torn by a crash, are recomputed. The
+//! windows only hold group elements that end up in the parameters, but the
+//! toxic waste itself has to be kept until keygen completes, so that it can
+//! be supplied again on resume.
+//!
+//! Similarly, [`create_random_proof_checkpointed`] stores each phase of a
+//! proof in a [`ProofCheckpoint`] as it completes: the evaluated witness,
+//! the coefficients of `h`, and the result of each multiexponentiation.
+//! These reveal the witness, so they are encrypted with the [`Key`] of the
+//! checkpoint, and phases that do not decrypt are recomputed.
+//!
+//! [`generate_parameters`]: super::generate_parameters
+//! [`generate_parameters_checkpointed`]: super::generate_parameters_checkpointed
+//! [`create_random_proof_checkpointed`]: super::create_random_proof_checkpointed
+
+use blake2s_simd::Params as Blake2sParams;
+use rand_core::RngCore;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::encrypted::{EncryptedReader, EncryptedWriter, Key};
+
+/// The number of elements per window, by default.
+pub const DEFAULT_WINDOW: usize = 1 << 16;
+
+const EXTENSION: &str = "chk";
+
+/// A directory holding the completed windows of a keygen run.
+pub struct Checkpoint {
+    dir: PathBuf,
+    window: usize,
+    resumed: AtomicUsize,
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint in `dir`, creating the directory if needed.
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Checkpoint {
+            dir: dir.as_ref().to_path_buf(),
+            window: DEFAULT_WINDOW,
+            resumed: AtomicUsize::new(0),
+        })
+    }
+
+    /// Sets the number of elements per window. Smaller windows lose less
+    /// work to a crash, at the cost of more files.
+    pub fn with_window(mut self, window: usize) -> Self {
+        assert!(window > 0);
+        self.window = window;
+        self
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the number of windows loaded rather than computed, since the
+    /// checkpoint was opened.
+    pub fn resumed(&self) -> usize {
+        self.resumed.load(Ordering::Relaxed)
+    }
+
+    /// Removes the stored windows, once keygen has completed.
+    pub fn clear(&self) -> io::Result<()> {
+        clear(&self.dir)
+    }
+
+    fn tag(binding: &[u8; 32], name: &str, payload: &[u8]) -> blake2s_simd::Hash {
+        Blake2sParams::new()
+            .personal(b"bellChkp")
+            .to_state()
+            .update(binding)
+            .update(&(name.len() as u64).to_be_bytes())
+            .update(name.as_bytes())
+            .update(payload)
+            .finalize()
+    }
+
+    /// Returns the payload of window `name`, if it was stored for `binding`.
+    pub(crate) fn load(&self, binding: &[u8; 32], name: &str) -> Option<Vec<u8>> {
+        let mut bytes = fs::read(path(&self.dir, name)).ok()?;
+        if bytes.len() < 32 {
+            return None;
+        }
+        let tag = bytes.split_off(bytes.len() - 32);
+        if Self::tag(binding, name, &bytes) != tag[..] {
+            return None;
+        }
+        self.resumed.fetch_add(1, Ordering::Relaxed);
+        Some(bytes)
+    }
+
+    /// Stores the payload of window `name`.
+    pub(crate) fn store(&self, binding: &[u8; 32], name: &str, payload: &[u8]) -> io::Result<()> {
+        let mut bytes = payload.to_vec();
+        bytes.extend_from_slice(Self::tag(binding, name, payload).as_bytes());
+
+        atomically(&path(&self.dir, name), |mut file| {
+            file.write_all(&bytes)?;
+            Ok(file)
+        })
+    }
+}
+
+/// A directory holding the completed phases of a proof, encrypted.
+pub struct ProofCheckpoint {
+    dir: PathBuf,
+    key: Key,
+    resumed: AtomicUsize,
+}
+
+impl ProofCheckpoint {
+    /// Opens the checkpoint in `dir`, creating the directory if needed.
+    /// Phases are encrypted with `key`.
+    pub fn new<P: AsRef<Path>>(dir: P, key: Key) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(ProofCheckpoint {
+            dir: dir.as_ref().to_path_buf(),
+            key,
+            resumed: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the number of phases loaded rather than computed, since the
+    /// checkpoint was opened.
+    pub fn resumed(&self) -> usize {
+        self.resumed.load(Ordering::Relaxed)
+    }
+
+    /// Removes the stored phases, once the proof has been created.
+    pub fn clear(&self) -> io::Result<()> {
+        clear(&self.dir)
+    }
+
+    /// Decrypts phase `name` and reads it with `f`. Returns `None` if the
+    /// phase is missing, does not decrypt, or is not read to its end by `f`.
+    pub(crate) fn load<T, F>(&self, name: &str, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut dyn Read) -> io::Result<T>,
+    {
+        let file = fs::File::open(path(&self.dir, name)).ok()?;
+        let mut reader = EncryptedReader::new(BufReader::new(file), &self.key).ok()?;
+        let value = f(&mut reader).ok()?;
+
+        // Reading up to the last chunk authenticates the end of the phase.
+        if reader.read(&mut [0]).ok()? != 0 {
+            return None;
+        }
+
+        self.resumed.fetch_add(1, Ordering::Relaxed);
+        Some(value)
+    }
+
+    /// Encrypts phase `name`, as written by `f`.
+    pub(crate) fn store<F>(&self, name: &str, mut rng: &mut dyn RngCore, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()>,
+    {
+        atomically(&path(&self.dir, name), |file| {
+            let mut writer = EncryptedWriter::new(BufWriter::new(file), &self.key, &mut rng)?;
+            f(&mut writer)?;
+            Ok(writer.finish()?.into_inner()?)
+        })
+    }
+}
+
+fn path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.{}", name, EXTENSION))
+}
+
+fn clear(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |e| e == EXTENSION) {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `path` with `f`, to a temporary file first, so that a crash
+/// cannot leave it half written.
+fn atomically<F>(path: &Path, f: F) -> io::Result<()>
+where
+    F: FnOnce(fs::File) -> io::Result<fs::File>,
+{
+    let tmp = path.with_extension("tmp");
+    f(fs::File::create(&tmp)?)?.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{
+        create_random_proof_checkpointed, generate_parameters, generate_parameters_checkpointed,
+        generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use bls12_381::{Bls12, G1Projective, G2Projective, Scalar};
+    use ff::Field;
+    use group::Group;
+
+    #[test]
+    fn resumes_keygen() {
+        let mut f = Fixture::<Bls12>::new();
+        let g1 = G1Projective::random(&mut f.rng);
+        let g2 = G2Projective::random(&mut f.rng);
+        let waste = (0..5)
+            .map(|_| Scalar::random(&mut f.rng))
+            .collect::<Vec<_>>();
+        let (alpha, beta, gamma, delta, tau) = (waste[0], waste[1], waste[2], waste[3], waste[4]);
+
+        let expected =
+            generate_parameters::<Bls12, _>(f.shape(), g1, g2, alpha, beta, gamma, delta, tau)
+                .unwrap();
+
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("bellman-checkpoint-{}", std::process::id()));
+        let run = |tau: Scalar| {
+            let checkpoint = Checkpoint::new(&dir).unwrap().with_window(3);
+            let params = generate_parameters_checkpointed::<Bls12, _>(
+                f.shape(),
+                g1,
+                g2,
+                alpha,
+                beta,
+                gamma,
+                delta,
+                tau,
+                &checkpoint,
+            )
+            .unwrap();
+            (params, checkpoint.resumed())
+        };
+
+        let (params, resumed) = run(tau);
+        assert!(params == expected);
+        assert_eq!(resumed, 0);
+        let mut windows = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect::<Vec<_>>();
+        windows.sort();
+        assert!(windows.len() > 2);
+
+        // Resuming loads the windows that are intact, and recomputes the
+        // missing and torn ones.
+        fs::remove_file(&windows[0]).unwrap();
+        let torn = fs::read(&windows[1]).unwrap();
+        fs::write(&windows[1], &torn[..torn.len() / 2]).unwrap();
+        let (params, resumed) = run(tau);
+        assert!(params == expected);
+        assert_eq!(resumed, windows.len() - 2);
+
+        // Windows of another run are not used.
+        let (params, resumed) = run(tau.double());
+        assert!(params != expected);
+        assert_eq!(resumed, 0);
+
+        Checkpoint::new(&dir).unwrap().clear().unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn resumes_proving() {
+        let mut f = Fixture::<Bls12>::new();
+        let inputs = &f.witness.inputs[1..];
+
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("bellman-proof-checkpoint-{}", std::process::id()));
+        let key = || Key::new([7; 32]);
+
+        let checkpoint = ProofCheckpoint::new(&dir, key()).unwrap();
+        let proof =
+            create_random_proof_checkpointed(f.circuit(), &f.params, &mut f.rng, &checkpoint)
+                .unwrap();
+        assert!(verify_proof(&f.pvk, &proof, inputs).is_ok());
+        assert_eq!(checkpoint.resumed(), 0);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 10);
+
+        // Once the witness is stored, the circuit is not synthesized again,
+        // so a circuit without an assignment still yields a valid proof.
+        fs::remove_file(dir.join("a_aux.chk")).unwrap();
+        let torn = fs::read(dir.join("h.chk")).unwrap();
+        fs::write(dir.join("h.chk"), &torn[..torn.len() / 2]).unwrap();
+        let checkpoint = ProofCheckpoint::new(&dir, key()).unwrap();
+        let proof = create_random_proof_checkpointed(f.shape(), &f.params, &mut f.rng, &checkpoint)
+            .unwrap();
+        assert!(verify_proof(&f.pvk, &proof, inputs).is_ok());
+        assert_eq!(checkpoint.resumed(), 8);
+
+        // The multiexponentiations of other parameters are recomputed.
+        let other = generate_random_parameters::<Bls12, _, _>(f.shape(), &mut f.rng).unwrap();
+        let checkpoint = ProofCheckpoint::new(&dir, key()).unwrap();
+        let proof =
+            create_random_proof_checkpointed(f.shape(), &other, &mut f.rng, &checkpoint).unwrap();
+        assert!(verify_proof(&prepare_verifying_key(&other.vk), &proof, inputs).is_ok());
+        assert_eq!(checkpoint.resumed(), 2);
+
+        // Phases encrypted with another key are not used.
+        let checkpoint = ProofCheckpoint::new(&dir, Key::new([8; 32])).unwrap();
+        assert!(
+            create_random_proof_checkpointed(f.shape(), &f.params, &mut f.rng, &checkpoint)
+                .is_err()
+        );
+        assert_eq!(checkpoint.resumed(), 0);
+
+        checkpoint.clear().unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,503 @@
+//! Collaborative Groth16 proving, with a witness that is additively
+//! secret-shared among several parties.
+//!
+//! Each party holds a [`WitnessShare`]: the public inputs, and a share of
+//! every auxiliary variable, such that the shares of all parties sum to the
+//! witness. No party needs to know the witness itself. Everything the
+//! Groth16 prover computes from the witness is linear in it, except for the
+//! product `A(x) * B(x)` in the quotient polynomial and the product `r * s`
+//! of the blinding factors. Parties compute the linear parts, including the
+//! FFTs and multiexponentiations, on their own shares, and the products with
+//! [Beaver triples] handed out in advance by a [`Triple`] dealer.
+//!
+//! A proof is created in three rounds, in each of which every party sends a
+//! message to a coordinator, who sums them and sends the sum back:
+//!
+//! 1. [`Party::new`] evaluates the party's share of the constraints, and
+//!    [`Party::opening`] returns it masked by the triples.
+//! 2. [`Party::commit`] takes the summed [`Opening`], and returns the
+//!    party's share of the `A` and `B` elements of the proof.
+//! 3. [`Party::finish`] takes the summed [`Commitment`], and returns the
+//!    party's share of the `C` element; [`Commitment::assemble`] sums those
+//!    into the proof.
+//!
+//! The openings are masked by the uniformly random triples, and `A` and `B`
+//! are part of the proof, so the messages reveal nothing beyond the proof
+//! as long as the parties follow the protocol and the dealer is honest.
+//! [`prove`] runs the protocol for all parties in this process.
+//!
+//! [Beaver triples]: https://doi.org/10.1007/3-540-46766-1_34
+
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve};
+use pairing::Engine;
+use rand_core::RngCore;
+use std::ops::{AddAssign, SubAssign};
+use std::sync::Arc;
+
+use futures::Future;
+
+use super::exporter::{Assignment, RawCircuit};
+use super::{ParameterSource, Proof};
+use crate::domain::{EvaluationDomain, Scalar};
+use crate::multicore::Worker;
+use crate::multiexp::{multiexp, DensityTracker, FullDensity};
+use crate::SynthesisError;
+
+/// One party's share of a witness.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WitnessShare<S: PrimeField> {
+    /// The index of the party, from 0.
+    pub party: usize,
+    /// The public inputs, starting with `ONE`, which every party knows.
+    pub inputs: Vec<S>,
+    /// The party's share of each auxiliary variable.
+    pub aux: Vec<S>,
+}
+
+/// Splits an assignment into additive shares for `parties` parties.
+pub fn share<S: PrimeField, R: RngCore>(
+    assignment: &Assignment<S>,
+    parties: usize,
+    rng: &mut R,
+) -> Vec<WitnessShare<S>> {
+    assert!(parties > 0);
+
+    let mut shares = (0..parties)
+        .map(|party| WitnessShare {
+            party,
+            inputs: assignment.inputs.clone(),
+            aux: vec![S::zero(); assignment.aux.len()],
+        })
+        .collect::<Vec<_>>();
+    for (i, value) in assignment.aux.iter().enumerate() {
+        let mut last = *value;
+        for share in shares[1..].iter_mut() {
+            share.aux[i] = S::random(&mut *rng);
+            last -= share.aux[i];
+        }
+        shares[0].aux[i] = last;
+    }
+
+    shares
+}
+
+/// One party's share of a Beaver triple: random `u` and `v`, and `w = u * v`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triple<S: PrimeField> {
+    pub u: S,
+    pub v: S,
+    pub w: S,
+}
+
+impl<S: PrimeField> Triple<S> {
+    /// Deals `count` triples to each of `parties` parties. The dealer learns
+    /// nothing about the witness, but must not collude with the parties.
+    pub fn deal<R: RngCore>(parties: usize, count: usize, rng: &mut R) -> Vec<Vec<Triple<S>>> {
+        assert!(parties > 0);
+
+        let mut dealt = vec![Vec::with_capacity(count); parties];
+        for _ in 0..count {
+            let u = S::random(&mut *rng);
+            let v = S::random(&mut *rng);
+            let mut rest = (u, v, u * v);
+            for triples in dealt[1..].iter_mut() {
+                let triple = Triple {
+                    u: S::random(&mut *rng),
+                    v: S::random(&mut *rng),
+                    w: S::random(&mut *rng),
+                };
+                rest = (rest.0 - triple.u, rest.1 - triple.v, rest.2 - triple.w);
+                triples.push(triple);
+            }
+            dealt[0].push(Triple {
+                u: rest.0,
+                v: rest.1,
+                w: rest.2,
+            });
+        }
+
+        dealt
+    }
+
+    /// Returns the number of triples each party needs to prove `circuit`.
+    pub fn needed(circuit: &RawCircuit<S>) -> usize {
+        // One per point of the evaluation domain, and one for r * s.
+        (circuit.num_constraints + circuit.num_inputs).next_power_of_two() + 1
+    }
+}
+
+/// The values `x - u` and `y - v` that a Beaver multiplication of `x` and
+/// `y` opens, for each multiplication.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opening<S: PrimeField> {
+    pub d: Vec<S>,
+    pub e: Vec<S>,
+}
+
+impl<S: PrimeField> Opening<S> {
+    /// Sums the openings of all parties.
+    pub fn combine(openings: &[Opening<S>]) -> Self {
+        let mut sum = openings[0].clone();
+        for opening in openings[1..].iter() {
+            for (acc, d) in sum.d.iter_mut().zip(opening.d.iter()) {
+                *acc += d;
+            }
+            for (acc, e) in sum.e.iter_mut().zip(opening.e.iter()) {
+                *acc += e;
+            }
+        }
+
+        sum
+    }
+}
+
+/// A party's share of the `A` and `B` elements of the proof, with `B` in
+/// both groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commitment<E: Engine> {
+    pub a: E::G1,
+    pub b: E::G2,
+    pub b_g1: E::G1,
+}
+
+impl<E: Engine> Commitment<E> {
+    /// Sums the commitments of all parties.
+    pub fn combine(commitments: &[Commitment<E>]) -> Self {
+        let mut sum = commitments[0].clone();
+        for commitment in commitments[1..].iter() {
+            AddAssign::<&E::G1>::add_assign(&mut sum.a, &commitment.a);
+            AddAssign::<&E::G2>::add_assign(&mut sum.b, &commitment.b);
+            AddAssign::<&E::G1>::add_assign(&mut sum.b_g1, &commitment.b_g1);
+        }
+
+        sum
+    }
+
+    /// Assembles the proof from the summed commitment and each party's
+    /// share of `C`.
+    pub fn assemble(&self, c: &[E::G1]) -> Proof<E> {
+        let c = c.iter().sum::<E::G1>();
+
+        Proof {
+            a: self.a.to_affine(),
+            b: self.b.to_affine(),
+            c: c.to_affine(),
+        }
+    }
+}
+
+/// Multiplies shares of `x` and `y` with a triple, given the opened
+/// `d = x - u` and `e = y - v`.
+fn beaver<S: PrimeField>(first: bool, triple: &Triple<S>, d: S, e: S) -> S {
+    let mut product = triple.w + d * triple.v + e * triple.u;
+    if first {
+        product += d * e;
+    }
+
+    product
+}
+
+/// The state of one party during the protocol.
+pub struct Party<E: Engine> {
+    index: usize,
+    inputs: Vec<E::Fr>,
+    aux: Vec<E::Fr>,
+    triples: Vec<Triple<E::Fr>>,
+    r: E::Fr,
+    s: E::Fr,
+    a_aux_density: Arc<DensityTracker>,
+    b_input_density: Arc<DensityTracker>,
+    b_aux_density: Arc<DensityTracker>,
+    // Shares of the A, B and C polynomials on the coset.
+    a: EvaluationDomain<E::Fr, Scalar<E::Fr>>,
+    b: EvaluationDomain<E::Fr, Scalar<E::Fr>>,
+    c: EvaluationDomain<E::Fr, Scalar<E::Fr>>,
+    // Set by `commit`.
+    partial_c: Option<(E::G1, E::Fr, E::G1Affine)>,
+}
+
+impl<E: Engine> Party<E> {
+    /// Starts the protocol for the party holding `share`, with its own
+    /// blinding factors drawn from `rng`.
+    pub fn new<R: RngCore>(
+        circuit: &RawCircuit<E::Fr>,
+        share: WitnessShare<E::Fr>,
+        triples: Vec<Triple<E::Fr>>,
+        rng: &mut R,
+    ) -> Result<Self, SynthesisError> {
+        assert_eq!(triples.len(), Triple::needed(circuit));
+
+        // Public values are added by the first party only.
+        let first = share.party == 0;
+        let inputs = if first {
+            share.inputs
+        } else {
+            vec![E::Fr::zero(); share.inputs.len()]
+        };
+        let aux = share.aux;
+        let (mut a, mut b, mut c) = circuit.evaluate(&inputs, &aux);
+
+        // The prover adds the constraints input * 0 = 0.
+        a.extend_from_slice(&inputs);
+        b.resize(a.len(), E::Fr::zero());
+        c.resize(a.len(), E::Fr::zero());
+
+        let worker = Worker::new();
+        let to_coset = |values: Vec<E::Fr>| -> Result<_, SynthesisError> {
+            let mut domain =
+                EvaluationDomain::from_coeffs(values.into_iter().map(Scalar).collect())?;
+            domain.ifft(&worker);
+            domain.coset_fft(&worker);
+            Ok(domain)
+        };
+
+        let density = |columns: &[Vec<(E::Fr, usize)>]| {
+            let mut density = DensityTracker::new();
+            for (i, column) in columns.iter().enumerate() {
+                density.add_element();
+                if !column.is_empty() {
+                    density.inc(i);
+                }
+            }
+            Arc::new(density)
+        };
+
+        Ok(Party {
+            index: share.party,
+            inputs,
+            aux,
+            triples,
+            r: E::Fr::random(&mut *rng),
+            s: E::Fr::random(&mut *rng),
+            a_aux_density: density(&circuit.at_aux),
+            b_input_density: density(&circuit.bt_inputs),
+            b_aux_density: density(&circuit.bt_aux),
+            a: to_coset(a)?,
+            b: to_coset(b)?,
+            c: to_coset(c)?,
+            partial_c: None,
+        })
+    }
+
+    /// Returns this party's share of the first round's openings.
+    pub fn opening(&self) -> Opening<E::Fr> {
+        let a = self.a.as_ref().iter().map(|x| x.0).chain(Some(self.r));
+        let b = self.b.as_ref().iter().map(|y| y.0).chain(Some(self.s));
+
+        Opening {
+            d: a.zip(self.triples.iter()).map(|(x, t)| x - &t.u).collect(),
+            e: b.zip(self.triples.iter()).map(|(y, t)| y - &t.v).collect(),
+        }
+    }
+
+    /// Computes this party's share of the quotient polynomial from the
+    /// summed openings, and returns its share of `A` and `B`.
+    pub fn commit<P: ParameterSource<E>>(
+        &mut self,
+        opened: &Opening<E::Fr>,
+        mut params: P,
+    ) -> Result<Commitment<E>, SynthesisError> {
+        let first = self.index == 0;
+        let worker = Worker::new();
+        let vk = params.get_vk(self.inputs.len())?;
+        if bool::from(vk.delta_g1.is_identity() | vk.delta_g2.is_identity()) {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+
+        let mut products = opened
+            .d
+            .iter()
+            .zip(opened.e.iter())
+            .zip(self.triples.iter())
+            .map(|((d, e), triple)| beaver(first, triple, *d, *e))
+            .collect::<Vec<_>>();
+        let rs = products.pop().unwrap();
+
+        let h = {
+            let mut h = EvaluationDomain::from_coeffs(products.into_iter().map(Scalar).collect())?;
+            h.sub_assign(&worker, &self.c);
+            h.divide_by_z_on_coset(&worker);
+            h.icoset_fft(&worker);
+            let mut h = h.into_coeffs();
+            h.truncate(h.len() - 1);
+            Arc::new(h.into_iter().map(|s| s.0.to_le_bits()).collect::<Vec<_>>())
+        };
+        let input_assignment = Arc::new(
+            self.inputs
+                .iter()
+                .map(|s| s.to_le_bits())
+                .collect::<Vec<_>>(),
+        );
+        let aux_assignment = Arc::new(self.aux.iter().map(|s| s.to_le_bits()).collect::<Vec<_>>());
+
+        let h = multiexp::<_, _, E::G1, _>(&worker, params.get_h(h.len())?, FullDensity, h);
+        let l = multiexp::<_, _, E::G1, _>(
+            &worker,
+            params.get_l(aux_assignment.len())?,
+            FullDensity,
+            aux_assignment.clone(),
+        );
+
+        let (a_inputs_source, a_aux_source) = params.get_a(
+            input_assignment.len(),
+            self.a_aux_density.get_total_density(),
+        )?;
+        let a_inputs = multiexp::<_, _, E::G1, _>(
+            &worker,
+            a_inputs_source,
+            FullDensity,
+            input_assignment.clone(),
+        );
+        let a_aux = multiexp::<_, _, E::G1, _>(
+            &worker,
+            a_aux_source,
+            self.a_aux_density.clone(),
+            aux_assignment.clone(),
+        );
+
+        let b_input_total = self.b_input_density.get_total_density();
+        let b_aux_total = self.b_aux_density.get_total_density();
+        let (b_g1_inputs_source, b_g1_aux_source) = params.get_b_g1(b_input_total, b_aux_total)?;
+        let b_g1_inputs = multiexp::<_, _, E::G1, _>(
+            &worker,
+            b_g1_inputs_source,
+            self.b_input_density.clone(),
+            input_assignment.clone(),
+        );
+        let b_g1_aux = multiexp::<_, _, E::G1, _>(
+            &worker,
+            b_g1_aux_source,
+            self.b_aux_density.clone(),
+            aux_assignment.clone(),
+        );
+        let (b_g2_inputs_source, b_g2_aux_source) = params.get_b_g2(b_input_total, b_aux_total)?;
+        let b_g2_inputs = multiexp::<_, _, E::G2, _>(
+            &worker,
+            b_g2_inputs_source,
+            self.b_input_density.clone(),
+            input_assignment,
+        );
+        let b_g2_aux = multiexp::<_, _, E::G2, _>(
+            &worker,
+            b_g2_aux_source,
+            self.b_aux_density.clone(),
+            aux_assignment,
+        );
+
+        let mut a = vk.delta_g1 * &self.r;
+        AddAssign::<&E::G1>::add_assign(&mut a, &a_inputs.wait()?);
+        AddAssign::<&E::G1>::add_assign(&mut a, &a_aux.wait()?);
+        let mut b = vk.delta_g2 * &self.s;
+        AddAssign::<&E::G2>::add_assign(&mut b, &b_g2_inputs.wait()?);
+        AddAssign::<&E::G2>::add_assign(&mut b, &b_g2_aux.wait()?);
+        let mut b_g1 = vk.delta_g1 * &self.s;
+        AddAssign::<&E::G1>::add_assign(&mut b_g1, &b_g1_inputs.wait()?);
+        AddAssign::<&E::G1>::add_assign(&mut b_g1, &b_g1_aux.wait()?);
+        if first {
+            a += &vk.alpha_g1;
+            b += &vk.beta_g2;
+            b_g1 += &vk.beta_g1;
+        }
+
+        let mut partial = h.wait()?;
+        AddAssign::<&E::G1>::add_assign(&mut partial, &l.wait()?);
+        self.partial_c = Some((partial, rs, vk.delta_g1));
+
+        Ok(Commitment { a, b, b_g1 })
+    }
+
+    /// Returns this party's share of `C`, given the summed commitments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`Party::commit`] has not been called.
+    pub fn finish(self, commitment: &Commitment<E>) -> E::G1 {
+        let (partial, rs, delta_g1) = self.partial_c.expect("commit must be called first");
+
+        let mut c = partial;
+        AddAssign::<&E::G1>::add_assign(&mut c, &(commitment.a * &self.s));
+        AddAssign::<&E::G1>::add_assign(&mut c, &(commitment.b_g1 * &self.r));
+        SubAssign::<&E::G1>::sub_assign(&mut c, &(delta_g1 * &rs));
+        c
+    }
+}
+
+/// Runs the protocol for every share in this process, and returns the
+/// proof. Each party's blinding factors are drawn from `rng`.
+pub fn prove<E, P, R>(
+    circuit: &RawCircuit<E::Fr>,
+    params: P,
+    shares: Vec<WitnessShare<E::Fr>>,
+    triples: Vec<Vec<Triple<E::Fr>>>,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    P: ParameterSource<E> + Clone,
+    R: RngCore,
+{
+    assert_eq!(shares.len(), triples.len());
+
+    let mut parties = shares
+        .into_iter()
+        .zip(triples)
+        .map(|(share, triples)| Party::<E>::new(circuit, share, triples, rng))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let opened = Opening::combine(&parties.iter().map(Party::opening).collect::<Vec<_>>());
+    let commitments = parties
+        .iter_mut()
+        .map(|party| party.commit(&opened, params.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let commitment = Commitment::combine(&commitments);
+    let c = parties
+        .into_iter()
+        .map(|party| party.finish(&commitment))
+        .collect::<Vec<_>>();
+
+    Ok(commitment.assemble(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::fuzz::CircuitConfig;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::verify_proof;
+    use bls12_381::{Bls12, Scalar as Fr};
+
+    #[test]
+    fn shared_witness_proves() {
+        let Fixture {
+            mut rng,
+            circuit,
+            witness,
+            params,
+            pvk,
+        } = Fixture::<Bls12>::with_config(&CircuitConfig {
+            num_inputs: 2,
+            num_aux: 6,
+            num_constraints: 9,
+            terms: 3,
+        });
+
+        for parties in 1..4 {
+            let shares = share(&witness, parties, &mut rng);
+            if parties > 1 {
+                assert!(shares.iter().all(|share| share.aux != witness.aux));
+            }
+            let triples = Triple::deal(parties, Triple::needed(&circuit), &mut rng);
+
+            let proof =
+                prove(&circuit, &params, shares.clone(), triples.clone(), &mut rng).unwrap();
+            assert!(verify_proof(&pvk, &proof, &witness.inputs[1..]).is_ok());
+
+            // A wrong share gives a proof for a different witness.
+            let mut bad = shares;
+            bad[parties - 1].aux[0] += Fr::one();
+            let proof = prove(&circuit, &params, bad, triples, &mut rng).unwrap();
+            assert!(verify_proof(&pvk, &proof, &witness.inputs[1..]).is_err());
+        }
+    }
+}
@@ -0,0 +1,964 @@
+//! Proofs linked to a Pedersen commitment to part of the witness.
+//!
+//! A statement proven with Groth16 often has to agree with one made
+//! elsewhere: that the balance in a range proof is the one committed to in
+//! a credential, or the vote the one encrypted for the tally. Following
+//! LegoGroth16, from LegoSNARK (Campanelli, Fiore and Querol, 2019), the
+//! auxiliary variables allocated under a list of namespaces are committed
+//! to in a Pedersen commitment `D` that is published with the proof, and
+//! which an external sigma protocol can then reason about.
+//!
+//! The committed variables are laid out as inputs, so that their terms
+//! `(beta u_i(tau) + alpha v_i(tau) + w_i(tau)) / gamma` leave the IC
+//! points of the verifying key and become the bases of the
+//! [`CommitmentKey`]. For values `v_i` and a blinding factor `o`,
+//!
+//! ```text
+//! D  = sum v_i K_i + o (eta / gamma) G
+//! C' = C - o (eta / delta) G
+//! ```
+//!
+//! where `C` is the element of a proof with these values as inputs, and
+//! [`verify_proof_with_commitment`] adds `D` to the combination of the
+//! public inputs. The terms in `eta` cancel in the pairings, and hide the
+//! values for a random `o`.
+//!
+//! The pairings alone would accept any `D`: a prover could fold part of the
+//! public inputs into it, and prove another statement. The proof therefore
+//! carries the link proof of LegoSNARK, `CP_link`, a quasi-adaptive NIZK of
+//! Kiltz and Wee (2015) that `D` opens over the bases of the key, and, if
+//! the key was generated for a [`PedersenKey`], that a commitment under
+//! that key opens to the same values. For the matrix `M` whose rows are
+//! the bases of each commitment, and whose columns are the values and the
+//! blinding factor of each commitment, the key holds `P = M^T K` in G1, and
+//! the verifier `K (a, 1)^T` and `a` in G2, for random `K` and `a`. The
+//! link proof of the opening `w` is `w^T P`, and is checked against the
+//! commitments `x = M w` by
+//!
+//! ```text
+//! sum e(x_r, [K_r (a, 1)^T]) = e(pi_0, [a]) e(pi_1, [1])
+//! ```
+//!
+//! A namespace covers the variables below it like those of
+//! [`Disclosure`](super::disclosure::Disclosure): `a/b` covers `a/b/x` and
+//! `a/b/c/y`, but not `a/bc/z`.
+//!
+//! # Format
+//!
+//! [`CommitmentKey::write`] emits the following, with integers in
+//! big-endian and points uncompressed:
+//!
+//! ```text
+//! namespaces  u32 count, then each as a u32 length and UTF-8 bytes
+//! bases       u32 count, then the bases in the order of the values
+//! blinding    u32 2, then (eta / gamma) G and (eta / delta) G
+//! link        u32 count, then the two points of P for each value, and
+//!             for each blinding factor
+//! link key    the key of [`LinkVerifyingKey::write`]
+//! ```
+//!
+//! [`LinkVerifyingKey::write`] emits a `u32` count, then `[a]` and the
+//! `[K_r (a, 1)^T]` of each commitment in G2, and
+//! [`CommittedProof::write`] the proof of [`Proof::write`], then `D` and the
+//! two points of the link proof compressed.
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField};
+use group::{
+    prime::PrimeCurveAffine, Curve, Group, GroupEncoding, UncompressedEncoding, WnafGroup,
+};
+use pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::ops::{AddAssign, Neg, SubAssign};
+
+use super::disclosure::{covers, read_path, write_path};
+use super::verifier::{check_equation, input_sum};
+use super::{
+    create_proof, generate_parameters, read_points, Parameters, PreparedVerifyingKey, Proof,
+};
+use crate::error::{Context, ErrorKind, Tracked};
+use crate::metrics;
+use crate::multicore::Worker;
+use crate::zeroize::{zeroize, Secret};
+use crate::{
+    Circuit, ConstraintSystem, LinearCombination, SynthesisError, Variable, VerificationError,
+};
+
+/// The bases of a Pedersen commitment made outside the proof, to the
+/// values of the committed variables, which the link proof ties to `D`.
+#[derive(Clone)]
+pub struct PedersenKey<E: Engine> {
+    /// The base of each value.
+    pub bases: Vec<E::G1Affine>,
+    /// The base of the blinding factor.
+    pub blinding: E::G1Affine,
+}
+
+impl<E: Engine> PedersenKey<E> {
+    /// Commits to `values` with the blinding factor `blinding`.
+    ///
+    /// Panics if the number of values does not match the key.
+    pub fn commit(&self, values: &[E::Fr], blinding: &E::Fr) -> E::G1Affine {
+        commit::<E>(&self.bases, &self.blinding, values, blinding)
+    }
+}
+
+/// The bases of the commitment to the witness, for parameters generated by
+/// [`generate_random_parameters`].
+#[derive(Clone)]
+pub struct CommitmentKey<E: Engine> {
+    /// The namespaces whose auxiliary variables are committed to.
+    pub namespaces: Vec<String>,
+    /// The base of each committed variable, in the order they are allocated.
+    pub bases: Vec<E::G1Affine>,
+    /// `(eta / gamma) G`, the base of the blinding factor.
+    pub blinding: E::G1Affine,
+    /// `(eta / delta) G`, which takes the blinding factor out of `C`.
+    pub blinding_delta: E::G1Affine,
+    /// The columns of `P` for each value, then for the blinding factor of
+    /// `D` and, if the key links a [`PedersenKey`], of its commitment.
+    pub link: Vec<[E::G1Affine; 2]>,
+    /// The key that checks the link proofs.
+    pub vk: LinkVerifyingKey<E>,
+}
+
+impl<E: Engine> PartialEq for CommitmentKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.namespaces == other.namespaces
+            && self.bases == other.bases
+            && self.blinding == other.blinding
+            && self.blinding_delta == other.blinding_delta
+            && self.link == other.link
+            && self.vk == other.vk
+    }
+}
+
+impl<E: Engine> CommitmentKey<E> {
+    /// Returns the number of committed values.
+    pub fn len(&self) -> usize {
+        self.bases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    /// Commits to `values` with the blinding factor `blinding`.
+    ///
+    /// Panics if the number of values does not match the key.
+    pub fn commit(&self, values: &[E::Fr], blinding: &E::Fr) -> E::G1Affine {
+        commit::<E>(&self.bases, &self.blinding, values, blinding)
+    }
+
+    /// Returns the link proof of the opening of the commitments to
+    /// `values`, with a blinding factor for each of them.
+    ///
+    /// Panics if the number of values or blinding factors does not match
+    /// the key.
+    pub fn prove_link(&self, values: &[E::Fr], blindings: &[E::Fr]) -> [E::G1Affine; 2] {
+        assert_eq!(values.len(), self.bases.len());
+        assert_eq!(self.bases.len() + blindings.len(), self.link.len());
+        let mut pi = [E::G1::identity(), E::G1::identity()];
+        for (column, w) in self.link.iter().zip(values.iter().chain(blindings)) {
+            for (pi, p) in pi.iter_mut().zip(column.iter()) {
+                AddAssign::<&E::G1>::add_assign(pi, &(*p * w));
+            }
+        }
+        [pi[0].to_affine(), pi[1].to_affine()]
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.namespaces.len() as u32)?;
+        for namespace in &self.namespaces {
+            write_path(&mut writer, namespace)?;
+        }
+
+        writer.write_u32::<BigEndian>(self.bases.len() as u32)?;
+        for base in &self.bases {
+            writer.write_all(base.to_uncompressed().as_ref())?;
+        }
+
+        writer.write_u32::<BigEndian>(2)?;
+        writer.write_all(self.blinding.to_uncompressed().as_ref())?;
+        writer.write_all(self.blinding_delta.to_uncompressed().as_ref())?;
+
+        writer.write_u32::<BigEndian>(2 * self.link.len() as u32)?;
+        for p in self.link.iter().flat_map(|column| column.iter()) {
+            writer.write_all(p.to_uncompressed().as_ref())?;
+        }
+
+        self.vk.write(writer)
+    }
+
+    /// Reads a key written by [`CommitmentKey::write`]. If `checked`, the
+    /// points are checked to be in the prime-order subgroup.
+    pub fn read<R: Read>(reader: R, checked: bool) -> io::Result<Self> {
+        let worker = Worker::new();
+        let mut reader = Tracked::new(reader);
+        let kind = ErrorKind::MalformedParameters;
+
+        let namespaces = reader.within(kind, Context::Section("namespaces"), |r| {
+            let len = r.read_u32::<BigEndian>()?;
+            (0..len).map(|_| read_path(r)).collect::<io::Result<_>>()
+        })?;
+        let bases = reader.within(kind, Context::Section("bases"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let blinding = reader.within(kind, Context::Section("blinding"), |r| {
+            read_points::<E::G1Affine, _>(r, &worker, kind, checked, "invalid G1")
+        })?;
+        if blinding.len() != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected two blinding bases",
+            ));
+        }
+        let link = reader.within(kind, Context::Section("link"), |r| {
+            read_points::<E::G1Affine, _>(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let vk = reader.within(kind, Context::Section("link key"), |r| {
+            LinkVerifyingKey::read_tracked(r, &worker, checked)
+        })?;
+        // Each commitment has a blinding factor, and a row in the key.
+        if link.len() % 2 != 0 || link.len() / 2 != bases.len() + vk.c.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the link key does not match the bases",
+            ));
+        }
+
+        Ok(CommitmentKey {
+            namespaces,
+            bases,
+            blinding: blinding[0],
+            blinding_delta: blinding[1],
+            link: link.chunks(2).map(|p| [p[0], p[1]]).collect(),
+            vk,
+        })
+    }
+}
+
+/// The key that checks the link proof of a [`CommittedProof`], from
+/// [`CommitmentKey::vk`].
+#[derive(Clone)]
+pub struct LinkVerifyingKey<E: Engine> {
+    /// `[a]` in G2.
+    pub a: E::G2Affine,
+    /// `[K_r (a, 1)^T]` in G2 for `D`, then for the commitment under the
+    /// [`PedersenKey`] of the key, if any.
+    pub c: Vec<E::G2Affine>,
+}
+
+impl<E: Engine> PartialEq for LinkVerifyingKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.a == other.a && self.c == other.c
+    }
+}
+
+impl<E: Engine> LinkVerifyingKey<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(1 + self.c.len() as u32)?;
+        writer.write_all(self.a.to_uncompressed().as_ref())?;
+        for c in &self.c {
+            writer.write_all(c.to_uncompressed().as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a key written by [`LinkVerifyingKey::write`]. If `checked`,
+    /// the points are checked to be in the prime-order subgroup.
+    pub fn read<R: Read>(reader: R, checked: bool) -> io::Result<Self> {
+        let worker = Worker::new();
+        let mut reader = Tracked::new(reader);
+        reader.within(
+            ErrorKind::MalformedParameters,
+            Context::Section("link key"),
+            |r| Self::read_tracked(r, &worker, checked),
+        )
+    }
+
+    fn read_tracked<R: Read>(
+        reader: &mut Tracked<R>,
+        worker: &Worker,
+        checked: bool,
+    ) -> io::Result<Self> {
+        let kind = ErrorKind::MalformedParameters;
+        let points = read_points::<E::G2Affine, _>(reader, worker, kind, checked, "invalid G2")?;
+        // `D` always has a row.
+        if points.len() < 2 || points.len() > 3 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a link key for one or two commitments",
+            ));
+        }
+        Ok(LinkVerifyingKey {
+            a: points[0],
+            c: points[1..].to_vec(),
+        })
+    }
+}
+
+/// A proof of [`create_proof_with_committed_witness`], with the commitment
+/// `D` to the committed values and the link proof of its opening.
+#[derive(Clone)]
+pub struct CommittedProof<E: Engine> {
+    pub proof: Proof<E>,
+    /// The commitment `D`.
+    pub commitment: E::G1Affine,
+    /// The link proof `w^T P`.
+    pub link: [E::G1Affine; 2],
+}
+
+impl<E: Engine> PartialEq for CommittedProof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof && self.commitment == other.commitment && self.link == other.link
+    }
+}
+
+impl<E: Engine> CommittedProof<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.proof.write(&mut writer)?;
+        writer.write_all(self.commitment.to_bytes().as_ref())?;
+        writer.write_all(self.link[0].to_bytes().as_ref())?;
+        writer.write_all(self.link[1].to_bytes().as_ref())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let proof = Proof::read(&mut reader)?;
+        let mut read_g1 = || -> io::Result<E::G1Affine> {
+            let mut repr = <E::G1Affine as GroupEncoding>::Repr::default();
+            reader.read_exact(repr.as_mut())?;
+            let point = E::G1Affine::from_bytes(&repr);
+            if bool::from(point.is_some()) {
+                Ok(point.unwrap())
+            } else {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "invalid G1"))
+            }
+        };
+        let commitment = read_g1()?;
+        let link = [read_g1()?, read_g1()?];
+        Ok(CommittedProof {
+            proof,
+            commitment,
+            link,
+        })
+    }
+}
+
+fn commit<E: Engine>(
+    bases: &[E::G1Affine],
+    blinding_base: &E::G1Affine,
+    values: &[E::Fr],
+    blinding: &E::Fr,
+) -> E::G1Affine {
+    assert_eq!(values.len(), bases.len());
+    let mut acc = *blinding_base * blinding;
+    for (base, value) in bases.iter().zip(values) {
+        AddAssign::<&E::G1>::add_assign(&mut acc, &(*base * value));
+    }
+    acc.to_affine()
+}
+
+/// Synthesizes a circuit with the auxiliary variables under `namespaces`
+/// allocated as inputs.
+struct Committing<'a, S: PrimeField, C> {
+    circuit: C,
+    namespaces: &'a [String],
+    /// The committed values, in the order they are allocated, when proving.
+    values: &'a mut Vec<S>,
+    /// Whether each input but `ONE` is committed.
+    committed: &'a mut Vec<bool>,
+}
+
+impl<'a, S: PrimeField, C: Circuit<S>> Circuit<S> for Committing<'a, S, C> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.circuit.synthesize(&mut CommittingCs {
+            cs,
+            namespaces: self.namespaces,
+            namespace: vec![],
+            values: self.values,
+            committed: self.committed,
+        })
+    }
+}
+
+struct CommittingCs<'a, S: PrimeField, CS> {
+    cs: &'a mut CS,
+    namespaces: &'a [String],
+    namespace: Vec<String>,
+    values: &'a mut Vec<S>,
+    committed: &'a mut Vec<bool>,
+}
+
+impl<'a, S: PrimeField, CS: ConstraintSystem<S>> ConstraintSystem<S> for CommittingCs<'a, S, CS> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let annotation = annotation().into();
+        let mut path = self.namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&annotation);
+
+        if !self.namespaces.iter().any(|ns| covers(ns, &path)) {
+            return self.cs.alloc(|| annotation, f);
+        }
+        self.committed.push(true);
+        let values = &mut *self.values;
+        self.cs.alloc_input(
+            || annotation,
+            || {
+                let value = f()?;
+                values.push(value);
+                Ok(value)
+            },
+        )
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.committed.push(false);
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        self.cs.enforce(annotation, a, b, c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name = name_fn().into();
+        self.namespace.push(name.clone());
+        self.cs.get_root().push_namespace(|| name);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.namespace.pop();
+        self.cs.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Generates random parameters for `circuit` like
+/// [`generate_random_parameters`](super::generate_random_parameters), and
+/// returns them with a [`CommitmentKey`] for the auxiliary variables
+/// allocated under `namespaces`. If `external` is given, the link proofs
+/// also show that a commitment under it opens to the committed values.
+///
+/// The verifying key only has IC points for the public inputs, so proofs
+/// of these parameters verify with [`verify_proof_with_commitment`] rather
+/// than [`verify_proof`]. Fails with [`SynthesisError::IoError`] if
+/// `external` has another number of bases than there are committed values.
+///
+/// [`verify_proof`]: super::verify_proof
+pub fn generate_random_parameters<E, C, R>(
+    circuit: C,
+    namespaces: &[&str],
+    external: Option<&PedersenKey<E>>,
+    mut rng: &mut R,
+) -> Result<(Parameters<E>, CommitmentKey<E>), SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let g1 = E::G1::random(&mut rng);
+    let g2 = E::G2::random(&mut rng);
+    let alpha = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let beta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let gamma = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let delta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let tau = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let eta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    let namespaces = namespaces
+        .iter()
+        .map(|ns| ns.to_string())
+        .collect::<Vec<_>>();
+    let mut committed = vec![];
+    let mut params = generate_parameters::<E, _>(
+        Committing {
+            circuit,
+            namespaces: &namespaces,
+            values: &mut vec![],
+            committed: &mut committed,
+        },
+        g1,
+        g2,
+        *alpha,
+        *beta,
+        *gamma,
+        *delta,
+        *tau,
+    )?;
+
+    let inverse = |x: &E::Fr| {
+        let inverse = x.invert();
+        if bool::from(inverse.is_some()) {
+            Ok(Secret::new(inverse.unwrap(), E::Fr::zero()))
+        } else {
+            Err(SynthesisError::UnexpectedIdentity)
+        }
+    };
+    let eta_gamma = Secret::new(*eta * &*inverse(&gamma)?, E::Fr::zero());
+    let eta_delta = Secret::new(*eta * &*inverse(&delta)?, E::Fr::zero());
+
+    let mut ic = vec![params.vk.ic[0]];
+    let mut bases = vec![];
+    for (point, committed) in params.vk.ic[1..].iter().zip(committed) {
+        if committed {
+            bases.push(*point);
+        } else {
+            ic.push(*point);
+        }
+    }
+    params.vk.ic = ic;
+    let blinding = (g1 * *eta_gamma).to_affine();
+
+    // The columns of `M`, with the identity where a commitment does not
+    // depend on a value or blinding factor.
+    let mut columns = bases.iter().map(|base| vec![*base]).collect::<Vec<_>>();
+    columns.push(vec![blinding]);
+    if let Some(external) = external {
+        if external.bases.len() != bases.len() {
+            return Err(SynthesisError::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the external key commits to another number of values",
+            )));
+        }
+        for (column, base) in columns.iter_mut().zip(&external.bases) {
+            column.push(*base);
+        }
+        columns[bases.len()].push(E::G1Affine::identity());
+        columns.push(vec![E::G1Affine::identity(), external.blinding]);
+    }
+    let rows = columns[0].len();
+
+    let a = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let k = (0..rows)
+        .map(|_| {
+            [
+                Secret::new(E::Fr::random(&mut rng), E::Fr::zero()),
+                Secret::new(E::Fr::random(&mut rng), E::Fr::zero()),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let link = columns
+        .iter()
+        .map(|column| {
+            let mut p = [E::G1::identity(), E::G1::identity()];
+            for (m, k) in column.iter().zip(&k) {
+                for (p, k) in p.iter_mut().zip(k.iter()) {
+                    AddAssign::<&E::G1>::add_assign(p, &(*m * &**k));
+                }
+            }
+            [p[0].to_affine(), p[1].to_affine()]
+        })
+        .collect();
+    let generator = E::G2Affine::generator();
+    let vk = LinkVerifyingKey {
+        a: (generator * &*a).to_affine(),
+        c: k.iter()
+            .map(|k| (generator * &(*k[0] * &*a + &*k[1])).to_affine())
+            .collect(),
+    };
+
+    let key = CommitmentKey {
+        namespaces,
+        bases,
+        blinding,
+        blinding_delta: (g1 * *eta_delta).to_affine(),
+        link,
+        vk,
+    };
+    Ok((params, key))
+}
+
+/// Creates a proof of `circuit` with the parameters and the key of
+/// [`generate_random_parameters`], committing to the values of the
+/// committed variables under the first of `blindings`. The others are the
+/// blinding factors of the commitment under the [`PedersenKey`] the key
+/// links, if any.
+///
+/// Fails with [`SynthesisError::IoError`] if there is not a blinding factor
+/// for each commitment, and with [`SynthesisError::Unsatisfiable`] if the
+/// circuit commits to another number of values than the key was generated
+/// for.
+pub fn create_proof_with_committed_witness<E, C>(
+    circuit: C,
+    params: &Parameters<E>,
+    key: &CommitmentKey<E>,
+    r: E::Fr,
+    s: E::Fr,
+    blindings: &[E::Fr],
+) -> Result<CommittedProof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+{
+    if blindings.len() != key.vk.c.len() {
+        return Err(SynthesisError::IoError(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "expected a blinding factor for each commitment",
+        )));
+    }
+    let blinding = Secret::new(blindings[0], E::Fr::zero());
+
+    let mut values = vec![];
+    let proof = create_proof(
+        Committing {
+            circuit,
+            namespaces: &key.namespaces,
+            values: &mut values,
+            committed: &mut vec![],
+        },
+        params,
+        r,
+        s,
+    );
+    let result = proof.and_then(|mut proof| {
+        if values.len() != key.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let commitment = key.commit(&values, &blinding);
+        let link = key.prove_link(&values, blindings);
+        let mut c = proof.c.to_curve();
+        SubAssign::<&E::G1>::sub_assign(&mut c, &(key.blinding_delta * &*blinding));
+        proof.c = c.to_affine();
+        Ok(CommittedProof {
+            proof,
+            commitment,
+            link,
+        })
+    });
+    zeroize(&mut values, E::Fr::zero());
+    result
+}
+
+/// Creates a proof like [`create_proof_with_committed_witness`], with
+/// random factors from `rng`. The blinding factors are left to the caller,
+/// which needs them to open the commitments, and should be random for the
+/// commitments to hide the values.
+pub fn create_random_proof_with_committed_witness<E, C, R>(
+    circuit: C,
+    params: &Parameters<E>,
+    key: &CommitmentKey<E>,
+    blindings: &[E::Fr],
+    mut rng: &mut R,
+) -> Result<CommittedProof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let r = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let s = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    create_proof_with_committed_witness(circuit, params, key, *r, *s, blindings)
+}
+
+/// Verifies a proof made by [`create_proof_with_committed_witness`] like
+/// [`verify_proof`](super::verify_proof), with its commitment `D` standing
+/// for the committed part of the witness, and checks its link proof. If
+/// the key links a [`PedersenKey`], `external` is the commitment under it
+/// that has to open to the same values.
+pub fn verify_proof_with_commitment<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    vk: &LinkVerifyingKey<E>,
+    proof: &CommittedProof<E>,
+    external: Option<&E::G1Affine>,
+    public_inputs: &[E::Fr],
+) -> Result<(), VerificationError> {
+    metrics::increment("bellman_verifications_total", &[]);
+
+    if (public_inputs.len() + 1) != pvk.ic.len() || vk.c.len() != 1 + external.iter().count() {
+        metrics::increment("bellman_verification_failures_total", &[]);
+        return Err(VerificationError::InvalidVerifyingKey);
+    }
+
+    let commitments = Some(&proof.commitment).into_iter().chain(external);
+    let c =
+        vk.c.iter()
+            .map(|c| (*c).into())
+            .collect::<Vec<E::G2Prepared>>();
+    let a = vk.a.neg().into();
+    let one = E::G2Affine::generator().neg().into();
+    let mut terms = commitments.zip(&c).collect::<Vec<_>>();
+    terms.push((&proof.link[0], &a));
+    terms.push((&proof.link[1], &one));
+    if !bool::from(
+        E::multi_miller_loop(&terms)
+            .final_exponentiation()
+            .is_identity(),
+    ) {
+        metrics::increment("bellman_verification_failures_total", &[]);
+        return Err(VerificationError::InvalidProof);
+    }
+
+    let mut acc = input_sum(pvk, public_inputs);
+    AddAssign::<&E::G1Affine>::add_assign(&mut acc, &pvk.ic[0]);
+    AddAssign::<&E::G1Affine>::add_assign(&mut acc, &proof.commitment);
+
+    check_equation(pvk, &proof.proof, &acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::gadgets::Assignment;
+    use crate::groth16::tests::fixture::rng;
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters as generate, prepare_verifying_key,
+        verify_proof,
+    };
+    use bls12_381::{Bls12, G1Projective, Scalar};
+
+    /// Proves that `a * b + x` is the public input, committing to `a` and
+    /// `b`.
+    struct Balances {
+        witness: Option<[Scalar; 3]>,
+    }
+
+    impl Circuit<Scalar> for Balances {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let value = |i: usize| {
+                self.witness
+                    .map(|w| w[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            };
+            let (a, b) = {
+                let mut cs = cs.namespace(|| "balances");
+                let a = AllocatedNum::alloc(cs.namespace(|| "a"), || value(0))?;
+                let b = AllocatedNum::alloc(cs.namespace(|| "b"), || value(1))?;
+                (a, b)
+            };
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || value(2))?;
+            let product = a.mul(cs.namespace(|| "product"), &b)?;
+            let sum = AllocatedNum::alloc(cs.namespace(|| "sum"), || {
+                Ok(*product.get_value().get()? + x.get_value().get()?)
+            })?;
+            cs.enforce(
+                || "sum",
+                |lc| lc + product.get_variable() + x.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + sum.get_variable(),
+            );
+            sum.inputize(cs.namespace(|| "input"))
+        }
+    }
+
+    #[test]
+    fn committed_witness() {
+        let mut rng = rng();
+        let (params, key): (Parameters<Bls12>, _) =
+            generate_random_parameters(Balances { witness: None }, &["balances"], None, &mut rng)
+                .unwrap();
+        assert_eq!(key.len(), 2);
+        assert_eq!(params.vk.ic.len(), 2);
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let (a, b, x) = (Scalar::from(3), Scalar::from(5), Scalar::from(7));
+        let witness = Balances {
+            witness: Some([a, b, x]),
+        };
+        let blinding = Scalar::random(&mut rng);
+        let proof = create_random_proof_with_committed_witness(
+            witness,
+            &params,
+            &key,
+            &[blinding],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(proof.commitment == key.commit(&[a, b], &blinding));
+        let sum = Scalar::from(22);
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, None, &[sum]).is_ok());
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, None, &[sum + x]).is_err());
+        let other = CommittedProof {
+            commitment: key.commit(&[b, a], &blinding),
+            ..proof.clone()
+        };
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &other, None, &[sum]).is_err());
+        assert!(verify_proof(&pvk, &proof.proof, &[sum]).is_err());
+
+        let mut bytes = vec![];
+        proof.write(&mut bytes).unwrap();
+        assert!(CommittedProof::<Bls12>::read(&bytes[..]).unwrap() == proof);
+
+        // The commitment hides the values behind the blinding factor.
+        let again = create_proof_with_committed_witness(
+            Balances {
+                witness: Some([a, b, x]),
+            },
+            &params,
+            &key,
+            Scalar::one(),
+            Scalar::one(),
+            &[blinding + Scalar::one()],
+        )
+        .unwrap();
+        assert!(again.commitment != proof.commitment);
+        assert!(matches!(
+            create_proof_with_committed_witness(
+                Balances {
+                    witness: Some([a, b, x])
+                },
+                &params,
+                &key,
+                Scalar::one(),
+                Scalar::one(),
+                &[],
+            ),
+            Err(SynthesisError::IoError(_))
+        ));
+
+        let mut bytes = vec![];
+        key.write(&mut bytes).unwrap();
+        assert!(CommitmentKey::<Bls12>::read(&bytes[..], true).unwrap() == key);
+        assert!(CommitmentKey::<Bls12>::read(&bytes[..bytes.len() - 1], true).is_err());
+        let mut bytes = vec![];
+        key.vk.write(&mut bytes).unwrap();
+        assert!(LinkVerifyingKey::<Bls12>::read(&bytes[..], true).unwrap() == key.vk);
+
+        // The parameters without the key prove the circuit as usual.
+        let plain: Parameters<Bls12> = generate(Balances { witness: None }, &mut rng).unwrap();
+        let proof = create_random_proof(
+            Balances {
+                witness: Some([a, b, x]),
+            },
+            &plain,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verify_proof(&prepare_verifying_key(&plain.vk), &proof, &[sum]).is_ok());
+    }
+
+    #[test]
+    fn forged_commitment() {
+        let mut rng = rng();
+        let (params, key): (Parameters<Bls12>, _) =
+            generate_random_parameters(Balances { witness: None }, &["balances"], None, &mut rng)
+                .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let (a, b, x) = (Scalar::from(3), Scalar::from(5), Scalar::from(7));
+        let proof = create_random_proof_with_committed_witness(
+            Balances {
+                witness: Some([a, b, x]),
+            },
+            &params,
+            &key,
+            &[Scalar::random(&mut rng)],
+            &mut rng,
+        )
+        .unwrap();
+        let sum = Scalar::from(22);
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, None, &[sum]).is_ok());
+
+        // Folding the difference of the public inputs into `D` satisfies the
+        // pairings of the proof for another sum, but `D` no longer opens
+        // over the bases of the key.
+        let mut forged = proof.clone();
+        let mut d = forged.commitment.to_curve();
+        AddAssign::<&<Bls12 as Engine>::G1>::add_assign(&mut d, &(params.vk.ic[1] * &x));
+        forged.commitment = d.to_affine();
+        let lie = sum - x;
+        let mut acc = params.vk.ic[0].to_curve();
+        AddAssign::<&<Bls12 as Engine>::G1>::add_assign(&mut acc, &(params.vk.ic[1] * &lie));
+        AddAssign::<&<Bls12 as Engine>::G1Affine>::add_assign(&mut acc, &forged.commitment);
+        assert!(check_equation(&pvk, &forged.proof, &acc).is_ok());
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &forged, None, &[lie]).is_err());
+    }
+
+    #[test]
+    fn linked_commitment() {
+        let mut rng = rng();
+        let external = PedersenKey::<Bls12> {
+            bases: vec![
+                G1Projective::random(&mut rng).to_affine(),
+                G1Projective::random(&mut rng).to_affine(),
+            ],
+            blinding: G1Projective::random(&mut rng).to_affine(),
+        };
+        let (params, key) = generate_random_parameters(
+            Balances { witness: None },
+            &["balances"],
+            Some(&external),
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let (a, b, x) = (Scalar::from(3), Scalar::from(5), Scalar::from(7));
+        let blindings = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+        let proof = create_random_proof_with_committed_witness(
+            Balances {
+                witness: Some([a, b, x]),
+            },
+            &params,
+            &key,
+            &blindings,
+            &mut rng,
+        )
+        .unwrap();
+        let sum = Scalar::from(22);
+        let cm = external.commit(&[a, b], &blindings[1]);
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, Some(&cm), &[sum]).is_ok());
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, None, &[sum]).is_err());
+
+        // A commitment to other values, or under another blinding factor,
+        // is not linked to the proof.
+        let other = external.commit(&[a, a], &blindings[1]);
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, Some(&other), &[sum]).is_err());
+        let other = external.commit(&[a, b], &blindings[0]);
+        assert!(verify_proof_with_commitment(&pvk, &key.vk, &proof, Some(&other), &[sum]).is_err());
+
+        let mut bytes = vec![];
+        key.write(&mut bytes).unwrap();
+        assert!(CommitmentKey::<Bls12>::read(&bytes[..], true).unwrap() == key);
+
+        let short = PedersenKey::<Bls12> {
+            bases: vec![external.bases[0]],
+            blinding: external.blinding,
+        };
+        assert!(matches!(
+            generate_random_parameters::<Bls12, _, _>(
+                Balances { witness: None },
+                &["balances"],
+                Some(&short),
+                &mut rng,
+            ),
+            Err(SynthesisError::IoError(_))
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! Verification that does not reveal which check failed.
+//!
+//! [`Proof::read`] and [`verify_proof`] return as soon as a point fails to
+//! decode, or the number of inputs is wrong, so the time a verifier takes to
+//! answer tells which check a malformed proof failed. [`verify_proof_bytes`]
+//! instead decodes and checks the proof and the inputs without returning
+//! early: an invalid encoding is replaced with a valid placeholder, the
+//! pairing check is always computed, and the outcomes of all checks are
+//! combined before the result is returned, as a single
+//! [`VerificationError::InvalidProof`] whatever failed.
+//! [`verify_proof_time_boxed`] additionally holds the answer back until a
+//! fixed duration has elapsed, which also hides the variations that remain,
+//! such as those of the arithmetic of the curve.
+//!
+//! The time taken still depends on the number of public inputs the
+//! verifying key expects, which is public.
+//!
+//! [`Proof::read`]: super::Proof::read
+//! [`verify_proof`]: super::verify_proof
+
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+use pairing::{MillerLoopResult, MultiMillerLoop};
+use std::ops::AddAssign;
+use std::thread;
+use std::time::{Duration, Instant};
+use subtle::Choice;
+
+use super::PreparedVerifyingKey;
+use crate::metrics;
+use crate::VerificationError;
+
+/// Decodes a point from the front of `bytes`, substituting the generator if
+/// it is not a valid encoding of a point other than the identity.
+fn decode<G: PrimeCurveAffine + GroupEncoding>(bytes: &mut &[u8], valid: &mut Choice) -> G {
+    let mut repr = G::Repr::default();
+    let len = repr.as_ref().len().min(bytes.len());
+    repr.as_mut()[..len].copy_from_slice(&bytes[..len]);
+    *bytes = &bytes[len..];
+
+    let point = G::from_bytes(&repr);
+    *valid &= point.is_some();
+    let point = Option::from(point).unwrap_or_else(G::generator);
+    *valid &= !point.is_identity();
+    point
+}
+
+/// Verifies the proof encoded in `proof`, as by [`Proof::write`], against
+/// the canonical encodings of `public_inputs`, with the same work whether
+/// or not they are valid.
+///
+/// [`Proof::write`]: super::Proof::write
+pub fn verify_proof_bytes<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &[u8],
+    public_inputs: &[<E::Fr as PrimeField>::Repr],
+) -> Result<(), VerificationError> {
+    metrics::increment("bellman_verifications_total", &[]);
+
+    let expected_len = <E::G1Affine as GroupEncoding>::Repr::default()
+        .as_ref()
+        .len()
+        * 2
+        + <E::G2Affine as GroupEncoding>::Repr::default()
+            .as_ref()
+            .len();
+    let mut valid = Choice::from((proof.len() == expected_len) as u8);
+    valid &= Choice::from((public_inputs.len() + 1 == pvk.ic.len()) as u8);
+
+    let mut bytes = proof;
+    let a = decode::<E::G1Affine>(&mut bytes, &mut valid);
+    let b = decode::<E::G2Affine>(&mut bytes, &mut valid);
+    let c = decode::<E::G1Affine>(&mut bytes, &mut valid);
+
+    // Every element of the IC is multiplied by an input, using zero in
+    // place of the missing and non-canonical ones.
+    let mut acc = pvk.ic[0].to_curve();
+    for (i, base) in pvk.ic.iter().skip(1).enumerate() {
+        let input = match public_inputs.get(i) {
+            Some(repr) => {
+                let mut copy = <E::Fr as PrimeField>::Repr::default();
+                copy.as_mut().copy_from_slice(repr.as_ref());
+                E::Fr::from_repr(copy)
+            }
+            None => None,
+        };
+        valid &= Choice::from(input.is_some() as u8);
+        let input = input.unwrap_or_else(E::Fr::zero);
+        AddAssign::<&E::G1>::add_assign(&mut acc, &(*base * &input));
+    }
+
+    let pairing = E::multi_miller_loop(&[
+        (&a, &b.into()),
+        (&acc.to_affine(), &pvk.neg_gamma_g2),
+        (&c, &pvk.neg_delta_g2),
+    ])
+    .final_exponentiation();
+    valid &= Choice::from((pvk.alpha_g1_beta_g2 == pairing) as u8);
+
+    if bool::from(valid) {
+        Ok(())
+    } else {
+        metrics::increment("bellman_verification_failures_total", &[]);
+        Err(VerificationError::InvalidProof)
+    }
+}
+
+/// Verifies like [`verify_proof_bytes`], and returns no sooner than
+/// `budget` after being called. If verification takes longer, it returns
+/// as soon as it is done.
+pub fn verify_proof_time_boxed<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &[u8],
+    public_inputs: &[<E::Fr as PrimeField>::Repr],
+    budget: Duration,
+) -> Result<(), VerificationError> {
+    let start = Instant::now();
+    let result = verify_proof_bytes(pvk, proof, public_inputs);
+    if let Some(remaining) = budget.checked_sub(start.elapsed()) {
+        thread::sleep(remaining);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::{Bls12, Scalar};
+
+    #[test]
+    fn verifies_without_early_returns() {
+        let mut f = Fixture::<Bls12>::new();
+        let proof = f.proof();
+        let pvk = &f.pvk;
+
+        let mut bytes = vec![];
+        proof.write(&mut bytes).unwrap();
+        let inputs = f
+            .inputs()
+            .iter()
+            .map(|input| input.to_repr())
+            .collect::<Vec<_>>();
+        assert!(verify_proof_bytes(pvk, &bytes, &inputs).is_ok());
+
+        let rejected = |bytes: &[u8], inputs: &[_]| {
+            matches!(
+                verify_proof_bytes(pvk, bytes, inputs),
+                Err(VerificationError::InvalidProof)
+            )
+        };
+
+        // A point that does not decode.
+        let mut invalid = bytes.clone();
+        invalid[1] ^= 1;
+        assert!(rejected(&invalid, &inputs));
+
+        // The point at infinity.
+        let mut identity = bytes.clone();
+        identity[..48].copy_from_slice(bls12_381::G1Affine::identity().to_bytes().as_ref());
+        assert!(rejected(&identity, &inputs));
+
+        // Truncated or extended proofs.
+        assert!(rejected(&bytes[..bytes.len() - 1], &inputs));
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(rejected(&extended, &inputs));
+
+        // Missing, extra, non-canonical and wrong inputs.
+        if !inputs.is_empty() {
+            assert!(rejected(&bytes, &inputs[1..]));
+            let mut wrong = inputs.clone();
+            wrong[0] = (Scalar::from_repr(wrong[0]).unwrap() + Scalar::one()).to_repr();
+            assert!(rejected(&bytes, &wrong));
+            let mut non_canonical = inputs.clone();
+            non_canonical[0] = [0xff; 32];
+            assert!(rejected(&bytes, &non_canonical));
+        }
+        let mut extra = inputs.clone();
+        extra.push(Scalar::zero().to_repr());
+        assert!(rejected(&bytes, &extra));
+
+        // A valid proof of another statement.
+        let other = (bls12_381::G1Projective::generator() * Scalar::from(7u64)).to_affine();
+        let mut forged = bytes.clone();
+        forged[..48].copy_from_slice(other.to_bytes().as_ref());
+        assert!(rejected(&forged, &inputs));
+
+        let budget = Duration::from_millis(50);
+        let start = Instant::now();
+        assert!(verify_proof_time_boxed(pvk, &invalid, &inputs, budget).is_err());
+        assert!(start.elapsed() >= budget);
+    }
+}
@@ -0,0 +1,296 @@
+//! Estimates of the cost of Groth16 proving, for capacity planning.
+//!
+//! [`CircuitStats`] summarises the shape of a circuit: its size, and how
+//! many variables appear in each of the `A` and `B` matrices, which decides
+//! the size of the multiexponentiations. A [`HardwareProfile`] gives the
+//! cost of the primitive operations on a machine, and is best obtained by
+//! running [`HardwareProfile::calibrate`] on it. [`estimate`] combines the
+//! two into the proving time, peak memory, and size of the parameters.
+//!
+//! The model is linear in the number of operations: it does not capture the
+//! better per-base cost of large multiexponentiations, nor cache effects, so
+//! profiles should be calibrated at a size close to the circuits of
+//! interest.
+
+use ff::{Field, PrimeField};
+use group::prime::{PrimeCurve, PrimeCurveAffine};
+use group::UncompressedEncoding;
+use pairing::Engine;
+use std::mem;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use super::exporter::RawCircuit;
+use crate::domain::{EvaluationDomain, Scalar};
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+
+/// The statistics of a circuit that proving costs depend on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// The number of public inputs, including `ONE`.
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub num_constraints: usize,
+    /// The number of auxiliary variables appearing in `A`.
+    pub a_aux: usize,
+    /// The number of inputs appearing in `B`.
+    pub b_inputs: usize,
+    /// The number of auxiliary variables appearing in `B`.
+    pub b_aux: usize,
+    /// The number of non-zero entries in all three matrices.
+    pub terms: usize,
+}
+
+impl CircuitStats {
+    pub fn from_circuit<S: PrimeField>(circuit: &RawCircuit<S>) -> Self {
+        Self::from_columns(
+            circuit.num_inputs,
+            circuit.num_aux,
+            circuit.num_constraints,
+            [&circuit.at_inputs, &circuit.bt_inputs, &circuit.ct_inputs],
+            [&circuit.at_aux, &circuit.bt_aux, &circuit.ct_aux],
+        )
+    }
+
+    /// Returns the statistics of a circuit from the columns of its `A`, `B`
+    /// and `C` matrices, for the inputs and the auxiliary variables.
+    pub(crate) fn from_columns<S: PrimeField>(
+        num_inputs: usize,
+        num_aux: usize,
+        num_constraints: usize,
+        inputs: [&[Vec<(S, usize)>]; 3],
+        aux: [&[Vec<(S, usize)>]; 3],
+    ) -> Self {
+        let used = |columns: &[Vec<(S, usize)>]| columns.iter().filter(|c| !c.is_empty()).count();
+        let terms = inputs
+            .iter()
+            .chain(aux.iter())
+            .map(|columns| columns.iter().map(|c| c.len()).sum::<usize>())
+            .sum();
+
+        CircuitStats {
+            num_inputs,
+            num_aux,
+            num_constraints,
+            a_aux: used(aux[0]),
+            b_inputs: used(inputs[1]),
+            b_aux: used(aux[1]),
+            terms,
+        }
+    }
+
+    /// Returns the size of the evaluation domain. The prover adds a
+    /// constraint for each input, so the domain must hold those too.
+    pub fn domain_size(&self) -> usize {
+        (self.num_constraints + self.num_inputs).next_power_of_two()
+    }
+
+    /// Returns the number of G1 bases in the proving key.
+    fn g1_bases(&self) -> usize {
+        // H, L, A (every input appears in A through its input constraint),
+        // and B in G1.
+        (self.domain_size() - 1)
+            + self.num_aux
+            + (self.num_inputs + self.a_aux)
+            + (self.b_inputs + self.b_aux)
+    }
+
+    /// Returns the number of G2 bases in the proving key.
+    fn g2_bases(&self) -> usize {
+        self.b_inputs + self.b_aux
+    }
+}
+
+/// Keeps the optimizer from discarding the computation of `value`, as
+/// `std::hint::black_box` does on more recent Rust.
+fn black_box<T>(value: T) -> T {
+    // Safety: `value` is read once, and forgotten so that it is not dropped
+    // twice.
+    let read = unsafe { ptr::read_volatile(&value) };
+    mem::forget(value);
+    read
+}
+
+/// The cost of the primitive operations of proving on some machine, using
+/// all of its threads. Times are in nanoseconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HardwareProfile {
+    /// The time per base of a G1 multiexponentiation.
+    pub g1_multiexp_ns: f64,
+    /// The time per base of a G2 multiexponentiation.
+    pub g2_multiexp_ns: f64,
+    /// The time per element and radix-2 round of an FFT.
+    pub fft_ns: f64,
+    /// The time to evaluate one term of a linear combination.
+    pub term_ns: f64,
+}
+
+impl HardwareProfile {
+    /// Measures the cost of each operation on this machine, with
+    /// multiexponentiations and FFTs of `size` elements.
+    pub fn calibrate<E: Engine>(size: usize) -> Self {
+        let worker = Worker::new();
+        let size = std::cmp::max(size, 2).next_power_of_two();
+
+        // A cheap deterministic sequence of dense scalars.
+        let mut scalar = E::Fr::multiplicative_generator();
+        let scalars = (0..size)
+            .map(|_| {
+                scalar = scalar.square() + &E::Fr::one();
+                scalar
+            })
+            .collect::<Vec<_>>();
+
+        fn bases<G: PrimeCurve>(size: usize) -> Vec<G::Affine> {
+            let mut acc = G::generator();
+            let projective = (0..size)
+                .map(|_| {
+                    acc = acc.double() + G::generator();
+                    acc
+                })
+                .collect::<Vec<_>>();
+            let mut affine = vec![G::Affine::identity(); size];
+            G::batch_normalize(&projective, &mut affine);
+            affine
+        }
+
+        let time = |f: &mut dyn FnMut()| {
+            let start = Instant::now();
+            f();
+            start.elapsed().as_nanos() as f64
+        };
+
+        let g1 = bases::<E::G1>(size);
+        let g1_multiexp_ns = time(&mut || {
+            black_box(dense_multiexp::<E::G1>(&worker, &g1, &scalars).unwrap());
+        }) / size as f64;
+
+        let g2 = bases::<E::G2>(size);
+        let g2_multiexp_ns = time(&mut || {
+            black_box(dense_multiexp::<E::G2>(&worker, &g2, &scalars).unwrap());
+        }) / size as f64;
+
+        let mut domain =
+            EvaluationDomain::from_coeffs(scalars.iter().map(|s| Scalar(*s)).collect()).unwrap();
+        let rounds = size.trailing_zeros() as f64;
+        let fft_ns = time(&mut || domain.fft(&worker)) / (size as f64 * rounds);
+
+        let term_ns = time(&mut || {
+            let acc = scalars
+                .iter()
+                .zip(scalars.iter().skip(1))
+                .fold(E::Fr::zero(), |acc, (a, b)| acc + &(*a * b));
+            black_box(acc);
+        }) / (size - 1) as f64;
+
+        HardwareProfile {
+            g1_multiexp_ns,
+            g2_multiexp_ns,
+            fft_ns,
+            term_ns,
+        }
+    }
+}
+
+/// The predicted cost of proving a circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// The time to create one proof.
+    pub proving_time: Duration,
+    /// The memory used while proving, including the parameters.
+    pub peak_memory: usize,
+    /// The size of the parameters, as written by
+    /// [`Parameters::write`](super::Parameters::write).
+    pub parameter_size: usize,
+}
+
+/// Predicts the cost of proving a circuit with the given statistics.
+pub fn estimate<E: Engine>(stats: &CircuitStats, profile: &HardwareProfile) -> Estimate {
+    let m = stats.domain_size();
+
+    // Synthesis evaluates every term once, and the quotient polynomial
+    // takes seven FFTs over the domain.
+    let synthesis = stats.terms as f64 * profile.term_ns;
+    let ffts = 7.0 * m as f64 * f64::from(m.trailing_zeros()) * profile.fft_ns;
+    let multiexps = stats.g1_bases() as f64 * profile.g1_multiexp_ns
+        + stats.g2_bases() as f64 * profile.g2_multiexp_ns;
+    let proving_time = Duration::from_nanos((synthesis + ffts + multiexps) as u64);
+
+    // The parameters are held in affine form, next to the three evaluation
+    // domains and the assignment (as field elements and as bits).
+    let scalar = mem::size_of::<E::Fr>();
+    let in_memory = stats.g1_bases() * mem::size_of::<E::G1Affine>()
+        + stats.g2_bases() * mem::size_of::<E::G2Affine>();
+    let prover = 3 * m * scalar + 2 * (stats.num_inputs + stats.num_aux) * scalar;
+    let peak_memory = in_memory + prover;
+
+    fn encoded<G: UncompressedEncoding>() -> usize {
+        G::Uncompressed::default().as_ref().len()
+    }
+    let g1 = encoded::<E::G1Affine>();
+    let g2 = encoded::<E::G2Affine>();
+    let vk = 3 * g1 + 3 * g2 + 4 + stats.num_inputs * g1;
+    let parameter_size = vk + 5 * 4 + stats.g1_bases() * g1 + stats.g2_bases() * g2;
+
+    Estimate {
+        proving_time,
+        peak_memory,
+        parameter_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::fuzz::CircuitConfig;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn parameter_size_is_exact() {
+        let Fixture {
+            circuit, params, ..
+        } = Fixture::<Bls12>::with_config(&CircuitConfig {
+            num_inputs: 3,
+            num_aux: 7,
+            num_constraints: 12,
+            terms: 2,
+        });
+        let stats = CircuitStats::from_circuit(&circuit);
+        assert_eq!(stats.num_inputs, 4);
+        assert_eq!(stats.domain_size(), 16);
+
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+
+        let profile = HardwareProfile {
+            g1_multiexp_ns: 1.0,
+            g2_multiexp_ns: 3.0,
+            fft_ns: 0.5,
+            term_ns: 0.25,
+        };
+        let estimate = estimate::<Bls12>(&stats, &profile);
+        assert_eq!(estimate.parameter_size, bytes.len());
+        assert!(estimate.peak_memory > estimate.parameter_size / 2);
+
+        // 7 FFTs of 16 elements over 4 rounds, plus the multiexps and terms.
+        let expected = 7.0 * 16.0 * 4.0 * 0.5
+            + params.h.len() as f64
+            + params.l.len() as f64
+            + params.a.len() as f64
+            + params.b_g1.len() as f64
+            + 3.0 * params.b_g2.len() as f64
+            + stats.terms as f64 * 0.25;
+        assert_eq!(estimate.proving_time.as_nanos(), expected as u128);
+    }
+
+    #[test]
+    fn calibration() {
+        let profile = HardwareProfile::calibrate::<Bls12>(16);
+        assert!(profile.g1_multiexp_ns > 0.0);
+        assert!(profile.g2_multiexp_ns > 0.0);
+        assert!(profile.fft_ns > 0.0);
+        assert!(profile.term_ns > 0.0);
+    }
+}
@@ -0,0 +1,343 @@
+//! Proving the same circuit over two engines.
+//!
+//! Projects that move from one curve to another, such as from BN254 to
+//! BLS12-381, need the circuit to mean the same thing over both scalar
+//! fields. A [`FieldCircuit`] is written once against any [`PrimeField`],
+//! and [`prove_on_both`] synthesizes, proves and verifies it over two
+//! engines, then checks that the public inputs are the same integers in
+//! both fields. An input that is reduced differently by the two moduli, for
+//! instance a value that only fits in the larger field, is reported as a
+//! [`CrossCurveError::InputMismatch`].
+//!
+//! Gadgets whose behaviour depends on the size of the field, such as the
+//! number of bits [`multipack`] packs into each input, can be sized for both
+//! fields with [`common_capacity`], and constants can be moved between
+//! fields as integers with [`to_uint`] and [`from_uint`].
+//!
+//! [`multipack`]: crate::gadgets::multipack
+
+use ff::PrimeField;
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::cost_model::CircuitStats;
+use super::exporter::{Assignment, RawCircuit};
+use super::{create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof};
+use crate::{Circuit, ConstraintSystem, SynthesisError, VerificationError};
+
+/// A circuit that can be synthesized over any prime field.
+pub trait FieldCircuit {
+    fn synthesize<S: PrimeField, CS: ConstraintSystem<S>>(
+        &self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError>;
+}
+
+/// A [`FieldCircuit`] as a [`Circuit`] over `S`.
+pub struct OnField<'a, C, S> {
+    circuit: &'a C,
+    _field: PhantomData<S>,
+}
+
+impl<'a, C: FieldCircuit, S: PrimeField> OnField<'a, C, S> {
+    pub fn new(circuit: &'a C) -> Self {
+        OnField {
+            circuit,
+            _field: PhantomData,
+        }
+    }
+}
+
+impl<'a, C: FieldCircuit, S: PrimeField> Circuit<S> for OnField<'a, C, S> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.circuit.synthesize(cs)
+    }
+}
+
+/// Returns the largest number of bits that both fields can hold in an
+/// element without reduction.
+pub fn common_capacity<S1: PrimeField, S2: PrimeField>() -> u32 {
+    std::cmp::min(S1::CAPACITY, S2::CAPACITY)
+}
+
+/// Returns the canonical integer of `value`, in big-endian without leading
+/// zeros.
+pub fn to_uint<S: PrimeField>(value: &S) -> Vec<u8> {
+    let bits = value.to_le_bits();
+    let mut bytes = vec![0u8; (bits.len() + 7) / 8];
+    for (i, bit) in bits.iter().enumerate() {
+        if *bit {
+            let len = bytes.len();
+            bytes[len - 1 - i / 8] |= 1 << (i % 8);
+        }
+    }
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    bytes.split_off(zeros)
+}
+
+/// Returns the element of `S` that is the big-endian integer `bytes`, if it
+/// is less than the modulus.
+pub fn from_uint<S: PrimeField>(bytes: &[u8]) -> Option<S> {
+    let base = S::from(256);
+    let value = bytes
+        .iter()
+        .fold(S::zero(), |acc, byte| acc * base + S::from(*byte as u64));
+
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    if to_uint(&value)[..] == bytes[zeros..] {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// The outcome of proving a circuit over one engine.
+#[derive(Clone, Debug)]
+pub struct CurveRun {
+    /// The size of the circuit over this field.
+    pub stats: CircuitStats,
+    /// The public inputs, excluding `ONE`, as canonical integers.
+    pub inputs: Vec<Vec<u8>>,
+}
+
+/// The outcome of [`prove_on_both`].
+#[derive(Clone, Debug)]
+pub struct CrossCurveReport {
+    pub first: CurveRun,
+    pub second: CurveRun,
+}
+
+/// Why a circuit does not prove consistently over two engines.
+#[derive(Debug)]
+pub enum CrossCurveError {
+    /// Synthesis or proving failed over the first engine if `second` is
+    /// false, and over the second otherwise.
+    Synthesis { second: bool, error: SynthesisError },
+    /// The proof over one of the engines was rejected.
+    Rejected {
+        second: bool,
+        error: VerificationError,
+    },
+    /// The circuit has another number of public inputs over each engine.
+    InputCount { first: usize, second: usize },
+    /// Public input `index` is not the same integer over both engines.
+    InputMismatch { index: usize },
+}
+
+impl Error for CrossCurveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CrossCurveError::Synthesis { error, .. } => Some(error),
+            CrossCurveError::Rejected { error, .. } => Some(error),
+            CrossCurveError::InputCount { .. } | CrossCurveError::InputMismatch { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for CrossCurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let engine = |second: bool| if second { "second" } else { "first" };
+        match self {
+            CrossCurveError::Synthesis { second, error } => {
+                write!(
+                    f,
+                    "proving over the {} engine failed: {}",
+                    engine(*second),
+                    error
+                )
+            }
+            CrossCurveError::Rejected { second, error } => write!(
+                f,
+                "the proof over the {} engine was rejected: {}",
+                engine(*second),
+                error
+            ),
+            CrossCurveError::InputCount { first, second } => write!(
+                f,
+                "the circuit has {} public inputs over the first engine and {} over the second",
+                first, second
+            ),
+            CrossCurveError::InputMismatch { index } => {
+                write!(f, "public input {} differs between the two engines", index)
+            }
+        }
+    }
+}
+
+impl From<CrossCurveError> for crate::error::Error {
+    fn from(e: CrossCurveError) -> Self {
+        match e {
+            CrossCurveError::Synthesis { error, .. } => error.into(),
+            e @ CrossCurveError::Rejected { .. } => {
+                crate::error::Error::new(crate::error::ErrorKind::VerificationFailed, e)
+            }
+            e => crate::error::Error::new(crate::error::ErrorKind::Synthesis, e),
+        }
+    }
+}
+
+fn prove_on<E, C, R>(circuit: &C, rng: &mut R, second: bool) -> Result<CurveRun, CrossCurveError>
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: FieldCircuit,
+    R: RngCore,
+{
+    let synthesis = |error| CrossCurveError::Synthesis { second, error };
+
+    let raw = RawCircuit::synthesize(OnField::<C, E::Fr>::new(circuit)).map_err(synthesis)?;
+    let witness = Assignment::synthesize(OnField::<C, E::Fr>::new(circuit)).map_err(synthesis)?;
+    let params = generate_random_parameters::<E, _, _>(OnField::<C, E::Fr>::new(circuit), rng)
+        .map_err(synthesis)?;
+    let proof =
+        create_random_proof(OnField::<C, E::Fr>::new(circuit), &params, rng).map_err(synthesis)?;
+
+    let pvk = prepare_verifying_key(&params.vk);
+    verify_proof(&pvk, &proof, &witness.inputs[1..])
+        .map_err(|error| CrossCurveError::Rejected { second, error })?;
+
+    Ok(CurveRun {
+        stats: CircuitStats::from_circuit(&raw),
+        inputs: witness.inputs[1..].iter().map(to_uint).collect(),
+    })
+}
+
+/// Proves `circuit` over `E1` and over `E2`, verifies both proofs, and
+/// checks that the public inputs are the same integers over both.
+pub fn prove_on_both<E1, E2, C, R>(
+    circuit: &C,
+    rng: &mut R,
+) -> Result<CrossCurveReport, CrossCurveError>
+where
+    E1: MultiMillerLoop,
+    E1::G1: WnafGroup,
+    E1::G2: WnafGroup,
+    E2: MultiMillerLoop,
+    E2::G1: WnafGroup,
+    E2::G2: WnafGroup,
+    C: FieldCircuit,
+    R: RngCore,
+{
+    let first = prove_on::<E1, _, _>(circuit, rng, false)?;
+    let second = prove_on::<E2, _, _>(circuit, rng, true)?;
+
+    if first.inputs.len() != second.inputs.len() {
+        return Err(CrossCurveError::InputCount {
+            first: first.inputs.len(),
+            second: second.inputs.len(),
+        });
+    }
+    if let Some(index) = first
+        .inputs
+        .iter()
+        .zip(second.inputs.iter())
+        .position(|(a, b)| a != b)
+    {
+        return Err(CrossCurveError::InputMismatch { index });
+    }
+
+    Ok(CrossCurveReport { first, second })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::boolean::{AllocatedBit, Boolean};
+    use crate::gadgets::multipack;
+    use crate::groth16::tests::dummy_engine::{DummyEngine, Fr};
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::{Bls12, Scalar};
+
+    /// Exposes `x` and `x^2`, and the bits of `x` packed into as few inputs
+    /// as both fields allow.
+    struct Square {
+        x: u64,
+    }
+
+    impl FieldCircuit for Square {
+        fn synthesize<S: PrimeField, CS: ConstraintSystem<S>>(
+            &self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = S::from(self.x);
+            let input = cs.alloc_input(|| "x", || Ok(x))?;
+            let square = cs.alloc_input(|| "square", || Ok(x.square()))?;
+            cs.enforce(
+                || "square",
+                |lc| lc + input,
+                |lc| lc + input,
+                |lc| lc + square,
+            );
+
+            let bits = (0..20)
+                .map(|i| {
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("bit {}", i)),
+                        Some((self.x >> i) & 1 == 1),
+                    )
+                    .map(Boolean::from)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            // Packing by the capacity of one field would give a different
+            // number of inputs over the other.
+            let chunk = common_capacity::<Scalar, Fr>() as usize;
+            for (i, bits) in bits.chunks(chunk).enumerate() {
+                let packed = multipack::compute_multipacking::<S>(
+                    &bits
+                        .iter()
+                        .map(|b| b.get_value().unwrap())
+                        .collect::<Vec<_>>(),
+                )[0];
+                let input = cs.alloc_input(|| format!("packed {}", i), || Ok(packed))?;
+                let mut coeff = S::one();
+                let mut lc = crate::LinearCombination::zero();
+                for bit in bits {
+                    lc = lc + &bit.lc(CS::one(), coeff);
+                    coeff = coeff.double();
+                }
+                cs.enforce(
+                    || format!("packing {}", i),
+                    |_| lc,
+                    |lc| lc + CS::one(),
+                    |lc| lc + input,
+                );
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cross_curve() {
+        let mut rng = rng();
+
+        let report =
+            prove_on_both::<Bls12, DummyEngine, _, _>(&Square { x: 200 }, &mut rng).unwrap();
+        assert_eq!(report.first.inputs, report.second.inputs);
+        assert_eq!(report.first.inputs[0], vec![200]);
+        assert_eq!(report.first.inputs[1], vec![0x9c, 0x40]);
+        assert_eq!(report.first.inputs.len(), 4);
+        assert_eq!(
+            report.first.stats.num_constraints,
+            report.second.stats.num_constraints
+        );
+
+        // 300^2 does not fit in the small field.
+        assert!(matches!(
+            prove_on_both::<Bls12, DummyEngine, _, _>(&Square { x: 300 }, &mut rng),
+            Err(CrossCurveError::InputMismatch { index: 1 })
+        ));
+
+        assert_eq!(common_capacity::<Scalar, Fr>(), 15);
+        assert_eq!(from_uint::<Fr>(&[0xfc, 0x01]), None);
+        assert_eq!(from_uint::<Fr>(&[0, 0xfb, 0]), Some(Fr::from(0xfb00)));
+        let x = Scalar::from(0x1234_5678u64);
+        assert_eq!(from_uint::<Scalar>(&to_uint(&x)), Some(x));
+        assert_eq!(to_uint(&Scalar::zero()), Vec::<u8>::new());
+    }
+}
@@ -0,0 +1,148 @@
+//! Verification delegated to a service that does not see the inputs.
+//!
+//! The verifying key only uses the public inputs through their linear
+//! combination with its IC elements, a single point in G1. A client that
+//! holds the inputs can compute that point itself, and hand a verification
+//! service a [`BlindedStatement`]: the point, and the proof re-randomized so
+//! that the service cannot link it to the proof the client received. The
+//! service checks it with [`verify_blinded`], and learns whether it is
+//! valid, but not the inputs.
+//!
+//! The point is a deterministic function of the inputs, so a service that
+//! can guess the inputs can check its guesses. The inputs are only hidden
+//! if the statement includes enough entropy among them, for instance a
+//! random salt exposed as a public input alongside a commitment to the
+//! data it hides.
+
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+use pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::ops::AddAssign;
+
+use super::{rerandomize_proof, PreparedVerifyingKey, Proof, VerifyingKey};
+use crate::VerificationError;
+
+/// A proof, with the public inputs replaced by their combination with the
+/// IC elements of the verifying key.
+#[derive(Clone)]
+pub struct BlindedStatement<E: Engine> {
+    pub proof: Proof<E>,
+    pub inputs: E::G1Affine,
+}
+
+impl<E: Engine> PartialEq for BlindedStatement<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof && self.inputs == other.inputs
+    }
+}
+
+impl<E: Engine> BlindedStatement<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.proof.write(&mut writer)?;
+        writer.write_all(self.inputs.to_bytes().as_ref())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let proof = Proof::read(&mut reader)?;
+        let mut repr = <E::G1Affine as GroupEncoding>::Repr::default();
+        reader.read_exact(repr.as_mut())?;
+        match Option::from(E::G1Affine::from_bytes(&repr)) {
+            Some(inputs) => Ok(BlindedStatement { proof, inputs }),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid combination of inputs",
+            )),
+        }
+    }
+}
+
+/// Blinds `proof` of `public_inputs` for [`verify_blinded`], with fresh
+/// randomness from `rng`.
+pub fn blind_statement<E: Engine, R: RngCore>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+    rng: &mut R,
+) -> Result<BlindedStatement<E>, VerificationError> {
+    if public_inputs.len() + 1 != vk.ic.len() {
+        return Err(VerificationError::InvalidVerifyingKey);
+    }
+
+    let mut acc = vk.ic[0].to_curve();
+    for (i, b) in public_inputs.iter().zip(vk.ic.iter().skip(1)) {
+        AddAssign::<&E::G1>::add_assign(&mut acc, &(*b * i));
+    }
+
+    Ok(BlindedStatement {
+        proof: rerandomize_proof(vk, proof, rng),
+        inputs: acc.to_affine(),
+    })
+}
+
+/// Verifies a statement blinded by [`blind_statement`].
+pub fn verify_blinded<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    statement: &BlindedStatement<E>,
+) -> Result<(), VerificationError> {
+    let proof = &statement.proof;
+    if pvk.alpha_g1_beta_g2
+        == E::multi_miller_loop(&[
+            (&proof.a, &proof.b.into()),
+            (&statement.inputs, &pvk.neg_gamma_g2),
+            (&proof.c, &pvk.neg_delta_g2),
+        ])
+        .final_exponentiation()
+    {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidProof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::verify_proof;
+    use bls12_381::{Bls12, Scalar};
+
+    #[test]
+    fn delegated_verification() {
+        let mut f = Fixture::<Bls12>::new();
+        let proof = f.proof();
+        let Fixture {
+            mut rng,
+            witness,
+            params,
+            pvk,
+            ..
+        } = f;
+        let inputs = &witness.inputs[1..];
+
+        let statement = blind_statement(&params.vk, &proof, inputs, &mut rng).unwrap();
+        assert!(statement.proof != proof);
+        assert!(verify_proof(&pvk, &statement.proof, inputs).is_ok());
+        assert!(verify_blinded(&pvk, &statement).is_ok());
+
+        let mut bytes = vec![];
+        statement.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 240);
+        let read = BlindedStatement::<Bls12>::read(&bytes[..]).unwrap();
+        assert!(read == statement);
+
+        // Blinding twice gives unlinkable proofs of the same statement.
+        let again = blind_statement(&params.vk, &proof, inputs, &mut rng).unwrap();
+        assert!(again.proof != statement.proof);
+        assert!(again.inputs == statement.inputs);
+
+        let mut wrong = inputs.to_vec();
+        wrong[0] += Scalar::one();
+        let forged = blind_statement(&params.vk, &proof, &wrong, &mut rng).unwrap();
+        assert!(verify_blinded(&pvk, &forged).is_err());
+        assert!(matches!(
+            blind_statement(&params.vk, &proof, &wrong[1..], &mut rng),
+            Err(VerificationError::InvalidVerifyingKey)
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! Deltas between versions of parameters.
+//!
+//! A phase-2 contribution only changes `delta` and the `H` and `L` queries,
+//! which are a fraction of the parameters. [`ParameterDelta::diff`] records
+//! the sections of the parameters that differ between two versions, and
+//! [`ParameterDelta::apply`] rebuilds the new version from the old one and
+//! the delta, so that coordinators can distribute the delta alone. Both
+//! versions are identified by their [`hash`], which `apply` checks.
+//!
+//! # Format
+//!
+//! [`ParameterDelta::write`] emits the following, with integers in
+//! big-endian and points uncompressed:
+//!
+//! ```text
+//! magic      "bellman-params-delta"
+//! version    u32 (currently 1)
+//! base       32 bytes, the hash of the parameters the delta applies to
+//! result     32 bytes, the hash of the parameters it produces
+//! sections   u8 count, then for each section:
+//!   tag        u8: 0 vk, 1 h, 2 l, 3 a, 4 b_g1, 5 b_g2
+//!   contents   for vk, as written by VerifyingKey::write,
+//!              otherwise a u32 number of points, then the points
+//! ```
+
+use blake2s_simd::Params as Blake2sParams;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use group::UncompressedEncoding;
+use pairing::Engine;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use super::{Parameters, VerifyingKey};
+
+pub(super) const MAGIC: &[u8] = b"bellman-params-delta";
+const VERSION: u32 = 1;
+
+/// Returns a BLAKE2s hash of the serialized parameters, which identifies
+/// them.
+pub fn hash<E: Engine>(params: &Parameters<E>) -> [u8; 32] {
+    let mut state = Blake2sParams::new().personal(b"bellPrms").to_state();
+    params
+        .write(&mut state)
+        .expect("writing to a hash does not fail");
+
+    let mut hash = [0; 32];
+    hash.copy_from_slice(state.finalize().as_bytes());
+    hash
+}
+
+/// The sections that differ between two versions of parameters. Sections
+/// that are unchanged are `None`.
+#[derive(Clone)]
+pub struct ParameterDelta<E: Engine> {
+    pub base: [u8; 32],
+    pub result: [u8; 32],
+    pub vk: Option<VerifyingKey<E>>,
+    pub h: Option<Arc<Vec<E::G1Affine>>>,
+    pub l: Option<Arc<Vec<E::G1Affine>>>,
+    pub a: Option<Arc<Vec<E::G1Affine>>>,
+    pub b_g1: Option<Arc<Vec<E::G1Affine>>>,
+    pub b_g2: Option<Arc<Vec<E::G2Affine>>>,
+}
+
+fn changed<T: PartialEq>(old: &Arc<Vec<T>>, new: &Arc<Vec<T>>) -> Option<Arc<Vec<T>>> {
+    if old == new {
+        None
+    } else {
+        Some(new.clone())
+    }
+}
+
+fn write_points<G: UncompressedEncoding, W: Write>(writer: &mut W, points: &[G]) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(points.len() as u32)?;
+    for p in points {
+        writer.write_all(p.to_uncompressed().as_ref())?;
+    }
+    Ok(())
+}
+
+fn read_points<G: UncompressedEncoding, R: Read>(reader: &mut R) -> io::Result<Arc<Vec<G>>> {
+    let len = reader.read_u32::<BigEndian>()?;
+    let mut points = vec![];
+    for _ in 0..len {
+        let mut repr = G::Uncompressed::default();
+        reader.read_exact(repr.as_mut())?;
+        match Option::from(G::from_uncompressed(&repr)) {
+            Some(p) => points.push(p),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid point in parameter delta",
+                ))
+            }
+        }
+    }
+    Ok(Arc::new(points))
+}
+
+impl<E: Engine> ParameterDelta<E> {
+    /// Records how `new` differs from `old`.
+    pub fn diff(old: &Parameters<E>, new: &Parameters<E>) -> Self {
+        ParameterDelta {
+            base: hash(old),
+            result: hash(new),
+            vk: if old.vk == new.vk {
+                None
+            } else {
+                Some(new.vk.clone())
+            },
+            h: changed(&old.h, &new.h),
+            l: changed(&old.l, &new.l),
+            a: changed(&old.a, &new.a),
+            b_g1: changed(&old.b_g1, &new.b_g1),
+            b_g2: changed(&old.b_g2, &new.b_g2),
+        }
+    }
+
+    /// Applies the delta to `params`, which must be the version it was
+    /// computed from, and checks that the result is the version it was
+    /// computed to.
+    pub fn apply(&self, params: &Parameters<E>) -> io::Result<Parameters<E>> {
+        if hash(params) != self.base {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the delta does not apply to these parameters",
+            ));
+        }
+
+        let result = Parameters {
+            vk: self.vk.clone().unwrap_or_else(|| params.vk.clone()),
+            h: self.h.clone().unwrap_or_else(|| params.h.clone()),
+            l: self.l.clone().unwrap_or_else(|| params.l.clone()),
+            a: self.a.clone().unwrap_or_else(|| params.a.clone()),
+            b_g1: self.b_g1.clone().unwrap_or_else(|| params.b_g1.clone()),
+            b_g2: self.b_g2.clone().unwrap_or_else(|| params.b_g2.clone()),
+        };
+        if hash(&result) != self.result {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the delta does not produce the expected parameters",
+            ));
+        }
+        Ok(result)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_all(&self.base)?;
+        writer.write_all(&self.result)?;
+
+        let g1 = [&self.h, &self.l, &self.a, &self.b_g1];
+        let count = self.vk.is_some() as u8
+            + g1.iter().filter(|s| s.is_some()).count() as u8
+            + self.b_g2.is_some() as u8;
+        writer.write_u8(count)?;
+
+        if let Some(vk) = &self.vk {
+            writer.write_u8(0)?;
+            vk.write(&mut writer)?;
+        }
+        for (tag, section) in g1.iter().enumerate() {
+            if let Some(points) = section {
+                writer.write_u8(tag as u8 + 1)?;
+                write_points(&mut writer, points)?;
+            }
+        }
+        if let Some(points) = &self.b_g2 {
+            writer.write_u8(5)?;
+            write_points(&mut writer, points)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a parameter delta"));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(invalid("unsupported parameter delta version"));
+        }
+
+        let mut delta = ParameterDelta {
+            base: [0; 32],
+            result: [0; 32],
+            vk: None,
+            h: None,
+            l: None,
+            a: None,
+            b_g1: None,
+            b_g2: None,
+        };
+        reader.read_exact(&mut delta.base)?;
+        reader.read_exact(&mut delta.result)?;
+
+        let mut last = None;
+        for _ in 0..reader.read_u8()? {
+            let tag = reader.read_u8()?;
+            if last.map_or(false, |last| tag <= last) {
+                return Err(invalid("parameter delta sections are out of order"));
+            }
+            last = Some(tag);
+
+            match tag {
+                0 => delta.vk = Some(VerifyingKey::read(&mut reader)?),
+                1 => delta.h = Some(read_points(&mut reader)?),
+                2 => delta.l = Some(read_points(&mut reader)?),
+                3 => delta.a = Some(read_points(&mut reader)?),
+                4 => delta.b_g1 = Some(read_points(&mut reader)?),
+                5 => delta.b_g2 = Some(read_points(&mut reader)?),
+                _ => return Err(invalid("unknown parameter delta section")),
+            }
+        }
+        Ok(delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::ceremony::{contribute, initial_transcript};
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn contribution_deltas() {
+        let Fixture {
+            mut rng,
+            params: old,
+            ..
+        } = Fixture::<Bls12>::new();
+        let mut new = old.clone();
+        contribute(&mut new, &initial_transcript(&old), &mut rng);
+
+        let delta = ParameterDelta::diff(&old, &new);
+        assert!(delta.vk.is_some() && delta.h.is_some() && delta.l.is_some());
+        assert!(delta.a.is_none() && delta.b_g1.is_none() && delta.b_g2.is_none());
+
+        let mut bytes = vec![];
+        delta.write(&mut bytes).unwrap();
+        let mut full = vec![];
+        new.write(&mut full).unwrap();
+        assert!(bytes.len() < full.len());
+
+        let delta = ParameterDelta::read(&bytes[..]).unwrap();
+        assert!(delta.apply(&old).unwrap() == new);
+
+        // The delta only applies to the version it was computed from.
+        assert_eq!(
+            delta.apply(&new).err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+
+        let mut tampered = delta.clone();
+        tampered.h = Some(Arc::new(tampered.h.unwrap()[1..].to_vec()));
+        assert_eq!(
+            tampered.apply(&old).err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+
+        let same = ParameterDelta::diff(&new, &new);
+        assert!(same.vk.is_none() && same.h.is_none());
+        assert!(same.apply(&new).unwrap() == new);
+    }
+}
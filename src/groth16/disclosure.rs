@@ -0,0 +1,342 @@
+//! Witnesses with only some namespaces disclosed.
+//!
+//! An auditor checking a gadget needs the values of its variables, but
+//! handing over the whole [`Assignment`] reveals every secret of the
+//! statement. [`Disclosure::synthesize`] instead records the shape of the
+//! circuit, the path of every variable, and the values of only the
+//! variables allocated under the namespaces of a whitelist; the other
+//! auxiliary values are redacted. Public inputs are always disclosed.
+//!
+//! A namespace `a/b` covers the variables allocated directly in it and in
+//! every namespace below it, such as `a/b/x` and `a/b/c/y`, but not `a/bc/z`.
+//! [`Disclosure::check`] verifies the constraints whose variables are all
+//! disclosed, so that the auditor can check the disclosed part of the
+//! witness without the rest.
+//!
+//! # Format
+//!
+//! [`Disclosure::write`] emits the following, with integers in big-endian:
+//!
+//! ```text
+//! circuit    as written by RawCircuit::write
+//! inputs     num_inputs times: the path, as a u32 length and UTF-8 bytes,
+//!            then the canonical repr of the value
+//! aux        num_aux times: the path, then u8 1 and the repr of the value
+//!            if it is disclosed, or u8 0 if it is redacted
+//! ```
+//!
+//! [`Assignment`]: super::exporter::Assignment
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::PrimeField;
+use std::io::{self, Read, Write};
+
+use super::exporter::{read_scalar, RawCircuit};
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The shape of a circuit with part of a witness.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disclosure<S: PrimeField> {
+    pub circuit: RawCircuit<S>,
+    /// The path of each input, and its value. Input 0 is `ONE`.
+    pub inputs: Vec<(String, S)>,
+    /// The path of each auxiliary variable, and its value if it is disclosed.
+    pub aux: Vec<(String, Option<S>)>,
+}
+
+/// Returns whether `path` is in the namespace `namespace` or below it.
+pub(super) fn covers(namespace: &str, path: &str) -> bool {
+    namespace.is_empty()
+        || (path.starts_with(namespace) && path[namespace.len()..].starts_with('/'))
+}
+
+struct DisclosureCs<'a, S: PrimeField> {
+    whitelist: &'a [&'a str],
+    namespace: Vec<String>,
+    disclosure: Disclosure<S>,
+}
+
+impl<'a, S: PrimeField> DisclosureCs<'a, S> {
+    fn path(&self, annotation: String) -> String {
+        let mut path = self.namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&annotation);
+        path
+    }
+}
+
+impl<'a, S: PrimeField> ConstraintSystem<S> for DisclosureCs<'a, S> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let path = self.path(annotation().into());
+        let value = f()?;
+        let disclosed = self.whitelist.iter().any(|ns| covers(ns, &path));
+        self.disclosure
+            .aux
+            .push((path, if disclosed { Some(value) } else { None }));
+        self.disclosure.circuit.alloc(|| "", || Ok(value))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let path = self.path(annotation().into());
+        let value = f()?;
+        self.disclosure.inputs.push((path, value));
+        self.disclosure.circuit.alloc_input(|| "", || Ok(value))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        self.disclosure.circuit.enforce(annotation, a, b, c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        self.namespace.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+pub(super) fn write_path<W: Write>(writer: &mut W, path: &str) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(path.len() as u32)?;
+    writer.write_all(path.as_bytes())
+}
+
+pub(super) fn read_path<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = reader.read_u32::<BigEndian>()? as usize;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "path is not UTF-8"))
+}
+
+impl<S: PrimeField> Disclosure<S> {
+    /// Synthesizes `circuit`, disclosing the auxiliary variables allocated
+    /// under the namespaces of `whitelist`.
+    pub fn synthesize<C: Circuit<S>>(
+        circuit: C,
+        whitelist: &[&str],
+    ) -> Result<Self, SynthesisError> {
+        let mut cs = DisclosureCs {
+            whitelist,
+            namespace: vec![],
+            disclosure: Disclosure {
+                circuit: RawCircuit::synthesize(EmptyCircuit)?,
+                inputs: vec![("ONE".to_string(), S::one())],
+                aux: vec![],
+            },
+        };
+        circuit.synthesize(&mut cs)?;
+        Ok(cs.disclosure)
+    }
+
+    /// Returns the number of disclosed auxiliary variables.
+    pub fn num_disclosed(&self) -> usize {
+        self.aux.iter().filter(|(_, value)| value.is_some()).count()
+    }
+
+    /// Returns the value of the variable at `path`, if it is disclosed.
+    pub fn get(&self, path: &str) -> Option<S> {
+        self.inputs
+            .iter()
+            .map(|(p, v)| (p, Some(*v)))
+            .chain(self.aux.iter().map(|(p, v)| (p, *v)))
+            .find(|(p, _)| *p == path)
+            .and_then(|(_, v)| v)
+    }
+
+    /// Checks every constraint whose variables are all disclosed, and
+    /// returns how many there are, or the index of the first that does not
+    /// hold.
+    pub fn check(&self) -> Result<usize, usize> {
+        let value = |var: &Variable| match var.get_unchecked() {
+            Index::Input(i) => Some(self.inputs[i].1),
+            Index::Aux(i) => self.aux[i].1,
+        };
+        let eval = |lc: &LinearCombination<S>| {
+            lc.as_ref().iter().try_fold(S::zero(), |acc, (var, coeff)| {
+                value(var).map(|v| acc + v * coeff)
+            })
+        };
+
+        let mut checked = 0;
+        for (i, [a, b, c]) in self.circuit.constraints().iter().enumerate() {
+            if let (Some(a), Some(b), Some(c)) = (eval(a), eval(b), eval(c)) {
+                if a * b != c {
+                    return Err(i);
+                }
+                checked += 1;
+            }
+        }
+        Ok(checked)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.circuit.write(&mut writer)?;
+        for (path, value) in &self.inputs {
+            write_path(&mut writer, path)?;
+            writer.write_all(value.to_repr().as_ref())?;
+        }
+        for (path, value) in &self.aux {
+            write_path(&mut writer, path)?;
+            match value {
+                Some(value) => {
+                    writer.write_u8(1)?;
+                    writer.write_all(value.to_repr().as_ref())?;
+                }
+                None => writer.write_u8(0)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let circuit = RawCircuit::read(&mut reader)?;
+        let inputs = (0..circuit.num_inputs)
+            .map(|_| Ok((read_path(&mut reader)?, read_scalar(&mut reader)?)))
+            .collect::<io::Result<_>>()?;
+        let aux = (0..circuit.num_aux)
+            .map(|_| {
+                let path = read_path(&mut reader)?;
+                let value = match reader.read_u8()? {
+                    0 => None,
+                    1 => Some(read_scalar(&mut reader)?),
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "invalid disclosure flag",
+                        ))
+                    }
+                };
+                Ok((path, value))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Disclosure {
+            circuit,
+            inputs,
+            aux,
+        })
+    }
+}
+
+/// The circuit with only `ONE`, from which the shape is built.
+struct EmptyCircuit;
+
+impl<S: PrimeField> Circuit<S> for EmptyCircuit {
+    fn synthesize<CS: ConstraintSystem<S>>(self, _: &mut CS) -> Result<(), SynthesisError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use crate::groth16::exporter::Assignment;
+    use bls12_381::Scalar;
+
+    /// Proves knowledge of a secret whose square, plus a private salt, is
+    /// the public input.
+    struct Salted {
+        secret: Scalar,
+        salt: Scalar,
+    }
+
+    impl Circuit<Scalar> for Salted {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let mut square = cs.namespace(|| "square");
+            let secret = AllocatedNum::alloc(square.namespace(|| "secret"), || Ok(self.secret))?;
+            let squared = secret.square(square.namespace(|| "gadget"))?;
+            drop(square);
+
+            let salt = AllocatedNum::alloc(cs.namespace(|| "salt"), || Ok(self.salt))?;
+            let out = cs.alloc_input(|| "out", || Ok(self.secret.square() + self.salt))?;
+            cs.enforce(
+                || "salted",
+                |lc| lc + squared.get_variable() + salt.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + out,
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn selective_disclosure() {
+        let circuit = || Salted {
+            secret: Scalar::from(7),
+            salt: Scalar::from(1000),
+        };
+        let disclosure = Disclosure::synthesize(circuit(), &["square/gadget"]).unwrap();
+
+        // The shape is that of the whole circuit.
+        assert_eq!(
+            disclosure.circuit,
+            RawCircuit::synthesize(circuit()).unwrap()
+        );
+        assert_eq!(
+            disclosure
+                .aux
+                .iter()
+                .map(|(p, _)| p.as_str())
+                .collect::<Vec<_>>(),
+            vec!["square/secret/num", "square/gadget/squared num", "salt/num"]
+        );
+        assert_eq!(disclosure.num_disclosed(), 1);
+        assert_eq!(
+            disclosure.get("square/gadget/squared num"),
+            Some(Scalar::from(49))
+        );
+        assert_eq!(disclosure.get("square/secret/num"), None);
+        assert_eq!(disclosure.get("out"), Some(Scalar::from(1049)));
+        assert_eq!(disclosure.check(), Ok(0));
+
+        // Disclosing a parent namespace covers everything below it, and
+        // makes the constraints that only use it checkable.
+        let disclosure = Disclosure::synthesize(circuit(), &["square", "salt"]).unwrap();
+        let witness = Assignment::synthesize(circuit()).unwrap();
+        assert_eq!(disclosure.num_disclosed(), witness.aux.len());
+        assert_eq!(disclosure.check(), Ok(2));
+        assert!(!covers("squ", "square/secret/num"));
+
+        let mut bytes = vec![];
+        disclosure.write(&mut bytes).unwrap();
+        let mut read = Disclosure::read(&bytes[..]).unwrap();
+        assert_eq!(read, disclosure);
+
+        // A disclosed value that does not match the circuit is detected.
+        read.aux[1].1 = Some(Scalar::from(50));
+        assert_eq!(read.check(), Err(0));
+    }
+}
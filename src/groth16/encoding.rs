@@ -0,0 +1,187 @@
+//! The order of the halves of G2 coordinates in encoded points.
+//!
+//! A coordinate of a G2 point is an element `c0 + c1·u` of the quadratic
+//! extension of the base field, and encodings disagree on which half they
+//! serialize first. The curves of this crate write `c1` first. A [`Profile`]
+//! names the order, and the `_with` variants of the read and write methods
+//! of [`Proof`](super::Proof), [`VerifyingKey`](super::VerifyingKey) and
+//! [`Parameters`](super::Parameters) take one:
+//!
+//! ```ignore
+//! let profile = Profile::new(Fq2Order::RealFirst);
+//! proof.write_with(&mut bytes, &profile)?;
+//! let proof = Proof::<Bls12>::read_with(&bytes[..], &profile)?;
+//! ```
+//!
+//! The flags of the encoding, such as the compression and infinity bits,
+//! stay in the first byte of the point whatever the order. A profile only
+//! settles the order of the halves, which is not enough to exchange points
+//! with another library: most also write field elements in little-endian,
+//! put the flags elsewhere, or encode the point at infinity differently,
+//! and need those converted too.
+
+use group::{prime::PrimeCurveAffine, UncompressedEncoding};
+
+/// Which half of each coordinate `c0 + c1·u` of a G2 point comes first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fq2Order {
+    /// `c1` then `c0`, as the curves of this crate encode points.
+    ImaginaryFirst,
+    /// `c0` then `c1`.
+    RealFirst,
+}
+
+/// How the points of an encoding are laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Profile {
+    g2: Fq2Order,
+}
+
+impl Profile {
+    /// Returns the profile that encodes G2 coordinates in the order `g2`.
+    pub const fn new(g2: Fq2Order) -> Profile {
+        Profile { g2 }
+    }
+
+    /// Returns the order of the halves of G2 coordinates.
+    pub fn g2(&self) -> Fq2Order {
+        self.g2
+    }
+
+    /// Converts the consecutive G2 points of `size` bytes in `points`
+    /// between the encoding of the engine and this profile, in place. The
+    /// conversion is its own inverse, so it serves for reading and writing.
+    pub(super) fn reorder_g2<G>(&self, points: &mut [u8], size: usize)
+    where
+        G: PrimeCurveAffine + UncompressedEncoding,
+    {
+        if self.g2 == Fq2Order::ImaginaryFirst {
+            return;
+        }
+
+        // The compressed encoding is the x coordinate alone.
+        let half = G::Repr::default().as_ref().len() / 2;
+        let flags = flags::<G>();
+        for point in points.chunks_mut(size) {
+            let set = point[0] & flags;
+            point[0] &= !flags;
+            for coordinate in point.chunks_mut(2 * half) {
+                let (c1, c0) = coordinate.split_at_mut(half);
+                c1.swap_with_slice(c0);
+            }
+            point[0] |= set;
+        }
+    }
+}
+
+impl Default for Profile {
+    /// The encoding of the curves of this crate.
+    fn default() -> Self {
+        Profile::new(Fq2Order::ImaginaryFirst)
+    }
+}
+
+/// Returns the bits of the first byte of the encodings of `G` that hold
+/// flags rather than the coordinates.
+///
+/// A point and its negation have the same x coordinate, so their compressed
+/// and uncompressed encodings only differ in the flags of the first byte,
+/// and the encodings of the identity have no coordinates at all.
+fn flags<G: PrimeCurveAffine + UncompressedEncoding>() -> u8 {
+    let identity = G::identity();
+    let generator = G::generator();
+    [generator, -generator]
+        .iter()
+        .map(|point| point.to_bytes().as_ref()[0] ^ point.to_uncompressed().as_ref()[0])
+        .fold(
+            identity.to_bytes().as_ref()[0] | identity.to_uncompressed().as_ref()[0],
+            |flags, bits| flags | bits,
+        )
+}
+
+#[cfg(all(test, feature = "pairing"))]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{Parameters, Proof, VerifyingKey};
+    use bls12_381::{Bls12, G1Affine, G2Affine, Scalar};
+    use group::Curve;
+
+    #[test]
+    fn g2_orders() {
+        assert_eq!(flags::<G2Affine>(), 0b1110_0000);
+        assert_eq!(flags::<G1Affine>(), 0b1110_0000);
+
+        let b = (G2Affine::generator() * Scalar::from(7)).to_affine();
+        let proof = Proof::<Bls12> {
+            a: G1Affine::generator(),
+            b,
+            c: G1Affine::generator(),
+        };
+        let mut native = vec![];
+        proof.write(&mut native).unwrap();
+        let real_first = Profile::new(Fq2Order::RealFirst);
+        let mut swapped = vec![];
+        proof.write_with(&mut swapped, &real_first).unwrap();
+
+        // Only the halves of the x coordinate of B trade places, under the
+        // flags.
+        let (g1, g2) = (48, 96);
+        assert_eq!(swapped[..g1], native[..g1]);
+        assert_eq!(swapped[g1 + g2..], native[g1 + g2..]);
+        assert_eq!(swapped[g1] & 0xe0, native[g1] & 0xe0);
+        assert_eq!(swapped[g1] & 0x1f, native[g1 + 48]);
+        assert_eq!(swapped[g1 + 1..g1 + 48], native[g1 + 49..g1 + 96]);
+        assert_eq!(swapped[g1 + 48], native[g1] & 0x1f);
+        assert!(Proof::<Bls12>::read_with(&swapped[..], &real_first).unwrap() == proof);
+        assert!(Proof::<Bls12>::read(&swapped[..]).map_or(true, |read| read != proof));
+        assert!(Proof::<Bls12>::read_with(&native[..], &Profile::default()).unwrap() == proof);
+        assert_eq!(Profile::default().g2(), Fq2Order::ImaginaryFirst);
+
+        let mut uncompressed = vec![];
+        proof
+            .write_uncompressed_with(&mut uncompressed, &real_first)
+            .unwrap();
+        let read = Proof::<Bls12>::read_uncompressed_with(&uncompressed[..], &real_first);
+        assert!(read.unwrap() == proof);
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn bn254_flags() {
+        use crate::bn254::{Bn254, G1Affine, G2Affine};
+
+        assert_eq!(flags::<G2Affine>(), 0b1100_0000);
+        let proof = Proof::<Bn254> {
+            a: G1Affine::generator(),
+            b: -G2Affine::generator(),
+            c: G1Affine::generator(),
+        };
+        for &order in &[Fq2Order::ImaginaryFirst, Fq2Order::RealFirst] {
+            let profile = &Profile::new(order);
+            let mut bytes = vec![];
+            proof.write_with(&mut bytes, profile).unwrap();
+            assert!(Proof::<Bn254>::read_with(&bytes[..], profile).unwrap() == proof);
+        }
+    }
+
+    #[test]
+    fn keys_and_parameters() {
+        let params = Fixture::<Bls12>::new().params;
+        for &order in &[Fq2Order::ImaginaryFirst, Fq2Order::RealFirst] {
+            let profile = &Profile::new(order);
+            let mut vk = vec![];
+            params.vk.write_with(&mut vk, profile).unwrap();
+            assert!(VerifyingKey::<Bls12>::read_with(&vk[..], profile).unwrap() == params.vk);
+
+            let mut bytes = vec![];
+            params.write_with(&mut bytes, profile).unwrap();
+            let read = Parameters::<Bls12>::read_with(&bytes[..], true, profile).unwrap();
+            assert!(read == params);
+            assert_eq!(
+                Parameters::<Bls12>::read(&bytes[..], true).is_ok(),
+                *profile == Profile::default()
+            );
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Encrypted storage of parameters.
+//!
+//! [`EncryptedWriter`] and [`EncryptedReader`] wrap any writer or reader
+//! in an authenticated stream cipher, so that [`Parameters`] can be kept
+//! encrypted on disk and decrypted as they are read, without a plaintext
+//! copy ever being written out. The key is either 32 bytes provided by a
+//! key management service, or derived from a passphrase.
+//!
+//! The stream is a header followed by chunks of up to 64 KiB, each sealed
+//! with XChaCha20-Poly1305 following the STREAM construction: the nonce of
+//! a chunk is the random salt of the file, the index of the chunk and
+//! whether it is the last one, and the header is authenticated with every
+//! chunk, so that modified, reordered or truncated streams are rejected
+//! before any of their contents are returned.
+//!
+//! Passphrases are stretched with scrypt, with the cost stored in the
+//! header. The cost is checked against [`PASSPHRASE_LOG_N`] before any key
+//! is derived, as the header is only authenticated once a key is. A
+//! passphrase is only as strong as its entropy, and keys from a key
+//! management service should be preferred where one is available.
+//!
+//! [`Parameters`]: super::Parameters
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{AeadInPlace, NewAead};
+use chacha20poly1305::XChaCha20Poly1305;
+use pairing::Engine;
+use rand_core::RngCore;
+use scrypt::ScryptParams;
+use std::io::{self, Read, Write};
+
+use super::Parameters;
+use crate::zeroize::{zeroize, Secret};
+
+pub(super) const MAGIC: &[u8; 8] = b"bellEnc2";
+const CHUNK_SIZE: usize = 1 << 16;
+const HEADER_SIZE: usize = 8 + 1 + 1 + 16;
+const TAG_SIZE: usize = 16;
+
+/// The base 2 logarithm of the scrypt cost `N` of passphrases. Streams
+/// asking for more are rejected.
+pub const PASSPHRASE_LOG_N: u8 = 15;
+
+/// A key to encrypt or decrypt streams with.
+pub struct Key {
+    secret: KeySecret,
+}
+
+enum KeySecret {
+    Raw(Secret<[u8; 32]>),
+    Passphrase(Vec<u8>),
+}
+
+impl Key {
+    /// A key of 32 uniformly random bytes, such as a data key issued by a
+    /// key management service.
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Key {
+            secret: KeySecret::Raw(Secret::new(bytes, [0; 32])),
+        }
+    }
+
+    /// A key derived from `passphrase`, with a salt stored in each stream.
+    pub fn passphrase(passphrase: &[u8]) -> Self {
+        Key {
+            secret: KeySecret::Passphrase(passphrase.to_vec()),
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        match self.secret {
+            KeySecret::Raw(_) => 0,
+            KeySecret::Passphrase(_) => 1,
+        }
+    }
+
+    /// Returns the key of a stream with the given header fields.
+    fn derive(&self, kind: u8, log_n: u8, salt: &[u8; 16]) -> io::Result<Secret<[u8; 32]>> {
+        if kind != self.kind() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the stream was encrypted with another kind of key",
+            ));
+        }
+
+        match &self.secret {
+            KeySecret::Raw(bytes) => Ok(Secret::new(**bytes, [0; 32])),
+            KeySecret::Passphrase(passphrase) => {
+                if log_n > PASSPHRASE_LOG_N {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the passphrase cost of the stream is too high",
+                    ));
+                }
+                let params = ScryptParams::new(log_n, 8, 1).expect("the cost is capped");
+                let mut key = Secret::new([0; 32], [0; 32]);
+                scrypt::scrypt(passphrase, salt, &params, &mut *key)
+                    .expect("32 bytes is a valid output length");
+                Ok(key)
+            }
+        }
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        if let KeySecret::Passphrase(passphrase) = &mut self.secret {
+            zeroize(passphrase, 0);
+        }
+    }
+}
+
+/// The key and header of one stream.
+struct Cipher {
+    aead: XChaCha20Poly1305,
+    header: [u8; HEADER_SIZE],
+}
+
+impl Cipher {
+    fn new(key: &Key, header: [u8; HEADER_SIZE]) -> io::Result<Self> {
+        if &header[..8] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an encrypted stream",
+            ));
+        }
+        let mut salt = [0; 16];
+        salt.copy_from_slice(&header[10..]);
+
+        let key = key.derive(header[8], header[9], &salt)?;
+        Ok(Cipher {
+            aead: XChaCha20Poly1305::new(GenericArray::from_slice(&*key)),
+            header,
+        })
+    }
+
+    /// The nonce of chunk `index`: the salt, then the index on 7 bytes and
+    /// whether the chunk is the last one.
+    fn nonce(&self, index: u64, last: bool) -> [u8; 24] {
+        let mut nonce = [0; 24];
+        nonce[..16].copy_from_slice(&self.header[10..]);
+        nonce[16..23].copy_from_slice(&index.to_be_bytes()[1..]);
+        nonce[23] = last as u8;
+        nonce
+    }
+
+    /// Encrypts `data` in place, and returns its tag.
+    fn seal(&self, index: u64, last: bool, data: &mut [u8]) -> [u8; TAG_SIZE] {
+        let nonce = self.nonce(index, last);
+        let tag = self
+            .aead
+            .encrypt_in_place_detached(GenericArray::from_slice(&nonce), &self.header, data)
+            .expect("chunks are shorter than the limit of the cipher");
+        let mut bytes = [0; TAG_SIZE];
+        bytes.copy_from_slice(&tag);
+        bytes
+    }
+
+    /// Decrypts `data` in place if `tag` authenticates it, and leaves it
+    /// untouched otherwise.
+    fn open(&self, index: u64, last: bool, data: &mut [u8], tag: &[u8; TAG_SIZE]) -> bool {
+        let nonce = self.nonce(index, last);
+        self.aead
+            .decrypt_in_place_detached(
+                GenericArray::from_slice(&nonce),
+                &self.header,
+                data,
+                GenericArray::from_slice(tag),
+            )
+            .is_ok()
+    }
+}
+
+/// Encrypts everything written to it into an inner writer.
+///
+/// The stream must be ended with [`EncryptedWriter::finish`]: a stream that
+/// is dropped without it is rejected as truncated when read back.
+pub struct EncryptedWriter<W: Write> {
+    // Taken by `finish`.
+    writer: Option<W>,
+    cipher: Cipher,
+    buffer: Vec<u8>,
+    index: u64,
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    /// Writes the header of a new stream encrypted with `key` to `writer`.
+    pub fn new<R: RngCore>(mut writer: W, key: &Key, rng: &mut R) -> io::Result<Self> {
+        let mut header = [0; HEADER_SIZE];
+        header[..8].copy_from_slice(MAGIC);
+        header[8] = key.kind();
+        if key.kind() == 1 {
+            header[9] = PASSPHRASE_LOG_N;
+        }
+        rng.fill_bytes(&mut header[10..]);
+        writer.write_all(&header)?;
+
+        Ok(EncryptedWriter {
+            writer: Some(writer),
+            cipher: Cipher::new(key, header)?,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            index: 0,
+        })
+    }
+
+    fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+        let tag = self.cipher.seal(self.index, last, &mut self.buffer);
+
+        let writer = self.writer.as_mut().unwrap();
+        writer.write_u8(last as u8)?;
+        writer.write_u32::<BigEndian>(self.buffer.len() as u32)?;
+        writer.write_all(&self.buffer)?;
+        writer.write_all(&tag)?;
+
+        self.buffer.clear();
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Writes the last chunk, and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk(true)?;
+        let mut writer = self.writer.take().unwrap();
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_chunk(false)?;
+        }
+        let len = std::cmp::min(data.len(), CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Chunks are only written when full, so that a stream has the same
+        // layout however it was written.
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for EncryptedWriter<W> {
+    fn drop(&mut self) {
+        zeroize(&mut self.buffer, 0);
+    }
+}
+
+/// Decrypts a stream written by [`EncryptedWriter`] from an inner reader.
+///
+/// Every chunk is authenticated before any of it is returned; reads fail
+/// with [`io::ErrorKind::InvalidData`] if the key is wrong or the stream was
+/// modified, and with [`io::ErrorKind::UnexpectedEof`] if it was truncated.
+pub struct EncryptedReader<R: Read> {
+    reader: R,
+    cipher: Cipher,
+    buffer: Vec<u8>,
+    position: usize,
+    index: u64,
+    done: bool,
+}
+
+impl<R: Read> EncryptedReader<R> {
+    /// Reads the header of a stream encrypted with `key` from `reader`.
+    pub fn new(mut reader: R, key: &Key) -> io::Result<Self> {
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+
+        Ok(EncryptedReader {
+            reader,
+            cipher: Cipher::new(key, header)?,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            position: 0,
+            index: 0,
+            done: false,
+        })
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let last = match self.reader.read_u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid("invalid chunk header")),
+        };
+        let len = self.reader.read_u32::<BigEndian>()? as usize;
+        if len > CHUNK_SIZE || (!last && len != CHUNK_SIZE) {
+            return Err(invalid("invalid chunk length"));
+        }
+
+        zeroize(&mut self.buffer, 0);
+        self.buffer.resize(len, 0);
+        self.position = 0;
+        self.reader.read_exact(&mut self.buffer)?;
+        let mut tag = [0; TAG_SIZE];
+        self.reader.read_exact(&mut tag)?;
+
+        if !self.cipher.open(self.index, last, &mut self.buffer, &tag) {
+            self.buffer.clear();
+            return Err(invalid("wrong key, or the stream was modified"));
+        }
+
+        self.index += 1;
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for EncryptedReader<R> {
+    fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.read_chunk()?;
+        }
+
+        let len = std::cmp::min(data.len(), self.buffer.len() - self.position);
+        data[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl<R: Read> Drop for EncryptedReader<R> {
+    fn drop(&mut self) {
+        zeroize(&mut self.buffer, 0);
+    }
+}
+
+impl<E: Engine> Parameters<E> {
+    /// Writes the parameters to `writer`, encrypted with `key`.
+    pub fn write_encrypted<W: Write, R: RngCore>(
+        &self,
+        writer: W,
+        key: &Key,
+        rng: &mut R,
+    ) -> io::Result<W> {
+        let mut writer = EncryptedWriter::new(writer, key, rng)?;
+        self.write(&mut writer)?;
+        writer.finish()
+    }
+
+    /// Reads parameters written by [`Parameters::write_encrypted`],
+    /// decrypting them as they are read.
+    pub fn read_encrypted<R: Read>(reader: R, key: &Key, checked: bool) -> io::Result<Self> {
+        let mut reader = EncryptedReader::new(reader, key)?;
+        let params = Self::read(&mut reader, checked)?;
+
+        // Reading the parameters may stop short of the last chunk, which
+        // authenticates the end of the stream.
+        if reader.read(&mut [0])? != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing data after the parameters",
+            ));
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::{rng, Fixture};
+    use bls12_381::Bls12;
+
+    fn decrypt(bytes: &[u8], key: &Key) -> io::Result<Vec<u8>> {
+        let mut decrypted = vec![];
+        EncryptedReader::new(bytes, key)?.read_to_end(&mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    #[test]
+    fn round_trips_and_rejects_tampering() {
+        let mut rng = rng();
+        let key = Key::new([7; 32]);
+        let data = (0..3 * CHUNK_SIZE / 2).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut writer = EncryptedWriter::new(vec![], &key, &mut rng).unwrap();
+        writer.write_all(&data).unwrap();
+        let encrypted = writer.finish().unwrap();
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), data);
+
+        let wrong = decrypt(&encrypted, &Key::new([8; 32])).unwrap_err();
+        assert_eq!(wrong.kind(), io::ErrorKind::InvalidData);
+        let mut modified = encrypted.clone();
+        modified[HEADER_SIZE + 100] ^= 1;
+        assert!(decrypt(&modified, &key).is_err());
+        let first_chunk = HEADER_SIZE + 5 + CHUNK_SIZE + TAG_SIZE;
+        let truncated = decrypt(&encrypted[..first_chunk], &key).unwrap_err();
+        assert_eq!(truncated.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn encrypted_parameters() {
+        let Fixture {
+            mut rng, params, ..
+        } = Fixture::<Bls12>::new();
+
+        let key = Key::passphrase(b"correct horse battery staple");
+        let encrypted = params.write_encrypted(vec![], &key, &mut rng).unwrap();
+        let read = Parameters::<Bls12>::read_encrypted(&encrypted[..], &key, true).unwrap();
+        assert!(read == params);
+
+        for key in [Key::passphrase(b"wrong"), Key::new([0; 32])].iter() {
+            assert!(Parameters::<Bls12>::read_encrypted(&encrypted[..], key, true).is_err());
+        }
+    }
+
+    #[test]
+    fn passphrase_cost_is_capped() {
+        let key = Key::passphrase(b"correct horse battery staple");
+        let mut writer = EncryptedWriter::new(vec![], &key, &mut rng()).unwrap();
+        writer.write_all(b"secret").unwrap();
+        let encrypted = writer.finish().unwrap();
+        assert_eq!(encrypted[9], PASSPHRASE_LOG_N);
+
+        // A cost above the cap is rejected without deriving a key, which
+        // would take far longer than the test.
+        let mut tampered = encrypted.clone();
+        tampered[9] = 60;
+        let err = EncryptedReader::new(&tampered[..], &key).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // A cost below it derives another key, which fails to authenticate
+        // the header.
+        tampered[9] = PASSPHRASE_LOG_N - 1;
+        let err = decrypt(&tampered, &key).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), b"secret");
+    }
+}
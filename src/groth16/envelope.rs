@@ -0,0 +1,324 @@
+//! Proofs bundled with the metadata needed to route and audit them.
+//!
+//! An [`Envelope`] carries a proof together with the fingerprint of the
+//! circuit it was made for, the hash of the verifying key it should be
+//! checked against, the curve, the version of the prover, and when it was
+//! made. A [`SignedEnvelope`] adds a signature over all of it by a
+//! [`Signer`], so that consumers can tell which prover vouched for a proof.
+//! Signatures are pluggable: [`MacKey`] provides keyed BLAKE2s for
+//! deployments that share a secret, and public-key schemes can be added by
+//! implementing [`Signer`] and [`SignatureVerifier`].
+//!
+//! # Format
+//!
+//! [`Envelope::write`] emits the following, with integers in big-endian:
+//!
+//! ```text
+//! magic      "bellman-proof-envelope"
+//! version    u32 (currently 1)
+//! circuit    32 bytes, RawCircuit::fingerprint
+//! vk         32 bytes, VerifyingKey::hash
+//! curve      u32 length, then UTF-8 bytes
+//! prover     u32 length, then UTF-8 bytes
+//! timestamp  u64, seconds since the Unix epoch
+//! proof      as written by Proof::write
+//! ```
+//!
+//! and [`SignedEnvelope::write`] appends the key id, as a u32 length and
+//! UTF-8 bytes, and the signature, as a u32 length and bytes. Signatures
+//! are over [`SIGNATURE_DOMAIN`] followed by the envelope bytes.
+
+use blake2s_simd::Params as Blake2sParams;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use pairing::Engine;
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+
+use super::exporter::RawCircuit;
+use super::{Proof, VerifyingKey};
+use crate::zeroize::Secret;
+
+pub(super) const MAGIC: &[u8] = b"bellman-proof-envelope";
+const VERSION: u32 = 1;
+
+/// The prover recorded in new envelopes.
+pub const PROVER: &str = concat!("bellman ", env!("CARGO_PKG_VERSION"));
+
+/// The prefix of the messages signed by [`SignedEnvelope::sign`].
+pub const SIGNATURE_DOMAIN: &[u8] = b"bellman-proof-envelope-signature";
+
+/// A proof, and where it comes from.
+#[derive(Clone)]
+pub struct Envelope<E: Engine> {
+    pub proof: Proof<E>,
+    /// The fingerprint of the circuit the proof is for.
+    pub circuit: [u8; 32],
+    /// The hash of the verifying key the proof verifies against.
+    pub vk: [u8; 32],
+    /// The curve of the proof, such as `"bls12_381"`.
+    pub curve: String,
+    /// The software that made the proof.
+    pub prover: String,
+    /// When the proof was made, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl<E: Engine> PartialEq for Envelope<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof
+            && self.circuit == other.circuit
+            && self.vk == other.vk
+            && self.curve == other.curve
+            && self.prover == other.prover
+            && self.timestamp == other.timestamp
+    }
+}
+
+pub(super) fn write_string<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(s.len() as u32)?;
+    writer.write_all(s.as_bytes())
+}
+
+pub(super) fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = reader.read_u32::<BigEndian>()? as usize;
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+pub(super) fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader)?)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8"))
+}
+
+impl<E: Engine> Envelope<E> {
+    /// Wraps a proof for `circuit` that verifies against `vk`, made now by
+    /// this version of bellman.
+    pub fn new(
+        proof: Proof<E>,
+        circuit: &RawCircuit<E::Fr>,
+        vk: &VerifyingKey<E>,
+        curve: &str,
+    ) -> Self {
+        Envelope {
+            proof,
+            circuit: circuit.fingerprint(),
+            vk: vk.hash(),
+            curve: curve.to_string(),
+            prover: PROVER.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Returns `true` if the proof claims to verify against `vk`.
+    pub fn is_for(&self, vk: &VerifyingKey<E>) -> bool {
+        self.vk == vk.hash()
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_all(&self.circuit)?;
+        writer.write_all(&self.vk)?;
+        write_string(&mut writer, &self.curve)?;
+        write_string(&mut writer, &self.prover)?;
+        writer.write_u64::<BigEndian>(self.timestamp)?;
+        self.proof.write(&mut writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a proof envelope",
+            ));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported envelope version",
+            ));
+        }
+
+        let mut circuit = [0; 32];
+        reader.read_exact(&mut circuit)?;
+        let mut vk = [0; 32];
+        reader.read_exact(&mut vk)?;
+        let curve = read_string(&mut reader)?;
+        let prover = read_string(&mut reader)?;
+        let timestamp = reader.read_u64::<BigEndian>()?;
+        let proof = Proof::read(&mut reader)?;
+
+        Ok(Envelope {
+            proof,
+            circuit,
+            vk,
+            curve,
+            prover,
+            timestamp,
+        })
+    }
+
+    fn signed_message(&self) -> Vec<u8> {
+        let mut message = SIGNATURE_DOMAIN.to_vec();
+        self.write(&mut message)
+            .expect("writing to a Vec does not fail");
+        message
+    }
+}
+
+/// Signs envelopes.
+pub trait Signer {
+    /// Identifies the key, so that verifiers can pick the matching one.
+    fn key_id(&self) -> &str;
+
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// Checks signatures made by a [`Signer`].
+pub trait SignatureVerifier {
+    /// Returns `true` if `signature` is a valid signature of `message` by
+    /// the key `key_id`.
+    fn verify(&self, key_id: &str, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A secret key shared by signers and verifiers, which signs with keyed
+/// BLAKE2s.
+pub struct MacKey {
+    id: String,
+    key: Secret<[u8; 32]>,
+}
+
+impl MacKey {
+    pub fn new(id: &str, key: [u8; 32]) -> Self {
+        MacKey {
+            id: id.to_string(),
+            key: Secret::new(key, [0; 32]),
+        }
+    }
+
+    fn mac(&self, message: &[u8]) -> [u8; 32] {
+        let mut mac = [0; 32];
+        mac.copy_from_slice(
+            Blake2sParams::new()
+                .key(&*self.key)
+                .personal(b"bellEnvl")
+                .hash(message)
+                .as_bytes(),
+        );
+        mac
+    }
+}
+
+impl Signer for MacKey {
+    fn key_id(&self) -> &str {
+        &self.id
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.mac(message).to_vec()
+    }
+}
+
+impl SignatureVerifier for MacKey {
+    fn verify(&self, key_id: &str, message: &[u8], signature: &[u8]) -> bool {
+        key_id == self.id && bool::from(self.mac(message)[..].ct_eq(signature))
+    }
+}
+
+/// An envelope, signed by a prover.
+#[derive(Clone)]
+pub struct SignedEnvelope<E: Engine> {
+    pub envelope: Envelope<E>,
+    pub key_id: String,
+    pub signature: Vec<u8>,
+}
+
+impl<E: Engine> SignedEnvelope<E> {
+    pub fn sign<S: Signer>(envelope: Envelope<E>, signer: &S) -> Self {
+        let signature = signer.sign(&envelope.signed_message());
+        SignedEnvelope {
+            envelope,
+            key_id: signer.key_id().to_string(),
+            signature,
+        }
+    }
+
+    /// Returns the envelope if its signature is valid.
+    pub fn verify<V: SignatureVerifier>(&self, verifier: &V) -> Option<&Envelope<E>> {
+        if verifier.verify(
+            &self.key_id,
+            &self.envelope.signed_message(),
+            &self.signature,
+        ) {
+            Some(&self.envelope)
+        } else {
+            None
+        }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.envelope.write(&mut writer)?;
+        write_string(&mut writer, &self.key_id)?;
+        writer.write_u32::<BigEndian>(self.signature.len() as u32)?;
+        writer.write_all(&self.signature)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let envelope = Envelope::read(&mut reader)?;
+        let key_id = read_string(&mut reader)?;
+        let signature = read_bytes(&mut reader)?;
+
+        Ok(SignedEnvelope {
+            envelope,
+            key_id,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn signed_envelopes() {
+        let mut f = Fixture::<Bls12>::new();
+        let proof = f.proof();
+        let (circuit, params) = (&f.circuit, &f.params);
+
+        let envelope = Envelope::new(proof, circuit, &params.vk, "bls12_381");
+        assert_eq!(envelope.circuit, circuit.fingerprint());
+        assert!(envelope.is_for(&params.vk));
+        assert_eq!(envelope.prover, PROVER);
+
+        let key = MacKey::new("prover-1", [3; 32]);
+        let signed = SignedEnvelope::sign(envelope.clone(), &key);
+        let mut bytes = vec![];
+        signed.write(&mut bytes).unwrap();
+        let read = SignedEnvelope::<Bls12>::read(&bytes[..]).unwrap();
+        assert!(read.verify(&key).unwrap() == &envelope);
+
+        // Any change to the envelope, or another key, invalidates the
+        // signature.
+        let mut tampered = read.clone();
+        tampered.envelope.timestamp += 1;
+        assert!(tampered.verify(&key).is_none());
+        assert!(read.verify(&MacKey::new("prover-1", [4; 32])).is_none());
+        assert!(read.verify(&MacKey::new("prover-2", [3; 32])).is_none());
+
+        assert!(Envelope::<Bls12>::read(&bytes[1..]).is_err());
+        assert!(SignedEnvelope::<Bls12>::read(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,1546 @@
+use blake2s_simd::Params as Blake2sParams;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use ff::PrimeField;
+use std::io::{self, Read, Write};
+use std::panic::Location;
+
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// The R1CS matrices of a circuit, as seen by parameter generation.
+///
+/// Matrices are stored by column: `at_inputs[i]` lists the `(coefficient,
+/// constraint)` pairs in which input `i` appears in the `A` linear
+/// combination, and similarly for `B`, `C` and the auxiliary variables. Input
+/// 0 is the constant `ONE`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawCircuit<Scalar: PrimeField> {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub num_constraints: usize,
+    pub at_inputs: Vec<Vec<(Scalar, usize)>>,
+    pub bt_inputs: Vec<Vec<(Scalar, usize)>>,
+    pub ct_inputs: Vec<Vec<(Scalar, usize)>>,
+    pub at_aux: Vec<Vec<(Scalar, usize)>>,
+    pub bt_aux: Vec<Vec<(Scalar, usize)>>,
+    pub ct_aux: Vec<Vec<(Scalar, usize)>>,
+}
+
+impl<Scalar: PrimeField> RawCircuit<Scalar> {
+    /// Synthesizes the constraint matrices of `circuit`, without computing a
+    /// witness.
+    pub fn synthesize<C: Circuit<Scalar>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut raw = Self::with_one()?;
+        circuit.synthesize(&mut raw)?;
+
+        Ok(raw)
+    }
+
+    /// Returns a circuit with only the "one" input variable.
+    fn with_one() -> Result<Self, SynthesisError> {
+        let mut raw = RawCircuit {
+            num_inputs: 0,
+            num_aux: 0,
+            num_constraints: 0,
+            at_inputs: vec![],
+            bt_inputs: vec![],
+            ct_inputs: vec![],
+            at_aux: vec![],
+            bt_aux: vec![],
+            ct_aux: vec![],
+        };
+
+        // Allocate the "one" input variable
+        raw.alloc_input(|| "", || Ok(Scalar::one()))?;
+
+        Ok(raw)
+    }
+
+    /// Computes the products `(A z, B z, C z)` of the matrices with the full
+    /// assignment `z = (inputs, aux)`, one entry per constraint.
+    pub fn evaluate(
+        &self,
+        input_assignment: &[Scalar],
+        aux_assignment: &[Scalar],
+    ) -> (Vec<Scalar>, Vec<Scalar>, Vec<Scalar>) {
+        assert_eq!(input_assignment.len(), self.num_inputs);
+        assert_eq!(aux_assignment.len(), self.num_aux);
+
+        fn eval<Scalar: PrimeField>(
+            out: &mut [Scalar],
+            columns: &[Vec<(Scalar, usize)>],
+            assignment: &[Scalar],
+        ) {
+            for (column, value) in columns.iter().zip(assignment.iter()) {
+                for &(coeff, constraint) in column {
+                    out[constraint].add_assign(&(coeff * value));
+                }
+            }
+        }
+
+        let mut a = vec![Scalar::zero(); self.num_constraints];
+        let mut b = vec![Scalar::zero(); self.num_constraints];
+        let mut c = vec![Scalar::zero(); self.num_constraints];
+        eval(&mut a, &self.at_inputs, input_assignment);
+        eval(&mut a, &self.at_aux, aux_assignment);
+        eval(&mut b, &self.bt_inputs, input_assignment);
+        eval(&mut b, &self.bt_aux, aux_assignment);
+        eval(&mut c, &self.ct_inputs, input_assignment);
+        eval(&mut c, &self.ct_aux, aux_assignment);
+
+        (a, b, c)
+    }
+
+    fn matrices(&self) -> [&Vec<Vec<(Scalar, usize)>>; 6] {
+        [
+            &self.at_inputs,
+            &self.bt_inputs,
+            &self.ct_inputs,
+            &self.at_aux,
+            &self.bt_aux,
+            &self.ct_aux,
+        ]
+    }
+
+    /// Returns the constraints as rows `(A, B, C)` of linear combinations.
+    pub fn constraints(&self) -> Vec<[LinearCombination<Scalar>; 3]> {
+        let mut rows = (0..self.num_constraints)
+            .map(|_| {
+                [
+                    LinearCombination::zero(),
+                    LinearCombination::zero(),
+                    LinearCombination::zero(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        for (m, matrix) in self.matrices().iter().enumerate() {
+            for (i, column) in matrix.iter().enumerate() {
+                let var = if m < 3 {
+                    Variable(Index::Input(i))
+                } else {
+                    Variable(Index::Aux(i))
+                };
+                for &(coeff, constraint) in column {
+                    rows[constraint][m % 3].0.push((var, coeff));
+                }
+            }
+        }
+
+        rows
+    }
+
+    /// Returns a BLAKE2s hash of the serialized matrices, which identifies
+    /// the circuit.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut bytes = vec![];
+        self.write(&mut bytes)
+            .expect("writing to a Vec does not fail");
+
+        let mut fingerprint = [0; 32];
+        fingerprint.copy_from_slice(
+            Blake2sParams::new()
+                .personal(b"bellCrct")
+                .hash(&bytes)
+                .as_bytes(),
+        );
+        fingerprint
+    }
+
+    /// Serializes the matrices. Counts are written as big-endian `u32`s and
+    /// coefficients in their canonical representation.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_with(writer, &Convention::default())
+    }
+
+    /// Serializes the matrices like [`write`](Self::write), with coefficients
+    /// in the given `convention`.
+    pub fn write_with<W: Write>(&self, mut writer: W, convention: &Convention) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.num_inputs as u32)?;
+        writer.write_u32::<BigEndian>(self.num_aux as u32)?;
+        writer.write_u32::<BigEndian>(self.num_constraints as u32)?;
+
+        for matrix in self.matrices().iter() {
+            for column in matrix.iter() {
+                writer.write_u32::<BigEndian>(column.len() as u32)?;
+                for (coeff, constraint) in column {
+                    writer.write_all(convention.encode(*coeff)?.as_ref())?;
+                    writer.write_u32::<BigEndian>(*constraint as u32)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_with(reader, &Convention::default())
+    }
+
+    /// Deserializes matrices whose coefficients were written by another tool
+    /// in the given `convention`. Reading them with [`read`](Self::read)
+    /// instead would silently give other coefficients, and so keys for
+    /// another circuit.
+    pub fn read_with<R: Read>(mut reader: R, convention: &Convention) -> io::Result<Self> {
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+        let num_aux = reader.read_u32::<BigEndian>()? as usize;
+        let num_constraints = reader.read_u32::<BigEndian>()? as usize;
+
+        let mut read_matrix = |columns: usize| -> io::Result<Vec<Vec<(Scalar, usize)>>> {
+            (0..columns)
+                .map(|_| {
+                    let len = reader.read_u32::<BigEndian>()? as usize;
+                    (0..len)
+                        .map(|_| {
+                            let coeff = convention.read_scalar(&mut reader)?;
+                            let constraint = reader.read_u32::<BigEndian>()? as usize;
+                            if constraint >= num_constraints {
+                                return Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    "constraint index out of range",
+                                ));
+                            }
+                            Ok((coeff, constraint))
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        let at_inputs = read_matrix(num_inputs)?;
+        let bt_inputs = read_matrix(num_inputs)?;
+        let ct_inputs = read_matrix(num_inputs)?;
+        let at_aux = read_matrix(num_aux)?;
+        let bt_aux = read_matrix(num_aux)?;
+        let ct_aux = read_matrix(num_aux)?;
+
+        Ok(RawCircuit {
+            num_inputs,
+            num_aux,
+            num_constraints,
+            at_inputs,
+            bt_inputs,
+            ct_inputs,
+            at_aux,
+            bt_aux,
+            ct_aux,
+        })
+    }
+}
+
+impl<Scalar: PrimeField> ConstraintSystem<Scalar> for RawCircuit<Scalar> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        self.at_aux.push(vec![]);
+        self.bt_aux.push(vec![]);
+        self.ct_aux.push(vec![]);
+
+        Ok(Variable(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        self.at_inputs.push(vec![]);
+        self.bt_inputs.push(vec![]);
+        self.ct_inputs.push(vec![]);
+
+        Ok(Variable(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        fn eval<Scalar: PrimeField>(
+            l: LinearCombination<Scalar>,
+            inputs: &mut [Vec<(Scalar, usize)>],
+            aux: &mut [Vec<(Scalar, usize)>],
+            this_constraint: usize,
+        ) {
+            for (index, coeff) in l.0 {
+                match index {
+                    Variable(Index::Input(id)) => inputs[id].push((coeff, this_constraint)),
+                    Variable(Index::Aux(id)) => aux[id].push((coeff, this_constraint)),
+                }
+            }
+        }
+
+        eval(
+            a(LinearCombination::zero()),
+            &mut self.at_inputs,
+            &mut self.at_aux,
+            self.num_constraints,
+        );
+        eval(
+            b(LinearCombination::zero()),
+            &mut self.bt_inputs,
+            &mut self.bt_aux,
+            self.num_constraints,
+        );
+        eval(
+            c(LinearCombination::zero()),
+            &mut self.ct_inputs,
+            &mut self.ct_aux,
+            self.num_constraints,
+        );
+
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// A full assignment of a circuit's variables, collected by synthesizing it
+/// without enforcing any constraints. Input 0 is the constant `ONE`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment<Scalar: PrimeField> {
+    pub inputs: Vec<Scalar>,
+    pub aux: Vec<Scalar>,
+}
+
+impl<Scalar: PrimeField> Assignment<Scalar> {
+    /// Synthesizes `circuit`, recording the value of every variable.
+    pub fn synthesize<C: Circuit<Scalar>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut assignment = Assignment {
+            inputs: vec![],
+            aux: vec![],
+        };
+
+        // Allocate the "one" input variable
+        assignment.alloc_input(|| "", || Ok(Scalar::one()))?;
+
+        circuit.synthesize(&mut assignment)?;
+
+        Ok(assignment)
+    }
+
+    /// Returns whether this assignment satisfies every constraint of
+    /// `circuit`.
+    pub fn is_satisfied(&self, circuit: &RawCircuit<Scalar>) -> bool {
+        if self.inputs.len() != circuit.num_inputs || self.aux.len() != circuit.num_aux {
+            return false;
+        }
+
+        let (a, b, c) = circuit.evaluate(&self.inputs, &self.aux);
+        a.iter()
+            .zip(b.iter())
+            .zip(c.iter())
+            .all(|((a, b), c)| *a * b == *c)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.inputs.len() as u32)?;
+        writer.write_u32::<BigEndian>(self.aux.len() as u32)?;
+        for value in self.inputs.iter().chain(self.aux.iter()) {
+            writer.write_all(value.to_repr().as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_with(reader, &Convention::default())
+    }
+
+    /// Deserializes an assignment whose values were written by another tool
+    /// in the given `convention`.
+    pub fn read_with<R: Read>(mut reader: R, convention: &Convention) -> io::Result<Self> {
+        let num_inputs = reader.read_u32::<BigEndian>()? as usize;
+        let num_aux = reader.read_u32::<BigEndian>()? as usize;
+
+        let inputs = (0..num_inputs)
+            .map(|_| convention.read_scalar(&mut reader))
+            .collect::<io::Result<_>>()?;
+        let aux = (0..num_aux)
+            .map(|_| convention.read_scalar(&mut reader))
+            .collect::<io::Result<_>>()?;
+
+        Ok(Assignment { inputs, aux })
+    }
+}
+
+impl<Scalar: PrimeField> ConstraintSystem<Scalar> for Assignment<Scalar> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f()?);
+
+        Ok(Variable(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, _: LA, _: LB, _: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        // Constraints are not needed to compute the assignment.
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// A [`Circuit`] that replays exported constraint matrices, optionally with
+/// an assignment, so that circuits can be handed to parameter generation and
+/// the prover without their original synthesis code.
+pub struct ReplayCircuit<Scalar: PrimeField> {
+    pub circuit: RawCircuit<Scalar>,
+    pub assignment: Option<Assignment<Scalar>>,
+}
+
+impl<Scalar: PrimeField> Circuit<Scalar> for ReplayCircuit<Scalar> {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        if let Some(assignment) = &self.assignment {
+            if assignment.inputs.len() != self.circuit.num_inputs
+                || assignment.aux.len() != self.circuit.num_aux
+            {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+        let value = |values: fn(&Assignment<Scalar>) -> &Vec<Scalar>, i: usize| {
+            self.assignment
+                .as_ref()
+                .map(|a| values(a)[i])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+
+        // Input 0 is ONE, which the constraint system allocates itself.
+        let mut inputs = vec![CS::one()];
+        for i in 1..self.circuit.num_inputs {
+            inputs.push(cs.alloc_input(|| format!("input {}", i), || value(|a| &a.inputs, i))?);
+        }
+        let mut aux = vec![];
+        for i in 0..self.circuit.num_aux {
+            aux.push(cs.alloc(|| format!("aux {}", i), || value(|a| &a.aux, i))?);
+        }
+
+        let map = |lc: &LinearCombination<Scalar>| {
+            lc.as_ref()
+                .iter()
+                .fold(LinearCombination::zero(), |acc, (var, coeff)| {
+                    acc + (
+                        *coeff,
+                        match var.get_unchecked() {
+                            Index::Input(i) => inputs[i],
+                            Index::Aux(i) => aux[i],
+                        },
+                    )
+                })
+        };
+        for (i, [a, b, c]) in self.circuit.constraints().iter().enumerate() {
+            cs.enforce(
+                || format!("constraint {}", i),
+                |_| map(a),
+                |_| map(b),
+                |_| map(c),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The constraints of a circuit in a form that other tools can consume, as
+/// written by [`R1CSExport::write_json`].
+///
+/// Variables are numbered as wires: the inputs first, from `ONE` at 0, then
+/// the auxiliary variables. Each linear combination is a list of
+/// `(wire, coefficient)` terms.
+#[derive(Clone, Debug, PartialEq)]
+pub struct R1CSExport<Scalar: PrimeField> {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub constraints: Vec<[Vec<(usize, Scalar)>; 3]>,
+}
+
+impl<Scalar: PrimeField> R1CSExport<Scalar> {
+    pub fn new(circuit: &RawCircuit<Scalar>) -> Self {
+        let wire = |var: &Variable| match var.get_unchecked() {
+            Index::Input(i) => i,
+            Index::Aux(i) => circuit.num_inputs + i,
+        };
+        let terms = |lc: &LinearCombination<Scalar>| {
+            lc.as_ref()
+                .iter()
+                .map(|(var, coeff)| (wire(var), *coeff))
+                .collect::<Vec<_>>()
+        };
+        R1CSExport {
+            num_inputs: circuit.num_inputs,
+            num_aux: circuit.num_aux,
+            constraints: circuit
+                .constraints()
+                .iter()
+                .map(|[a, b, c]| [terms(a), terms(b), terms(c)])
+                .collect(),
+        }
+    }
+
+    /// Returns the modulus of the field, in big-endian.
+    pub fn field_modulus() -> Vec<u8> {
+        // The modulus is one more than the largest element.
+        let mut modulus = big_endian::<Scalar>((-Scalar::one()).to_repr());
+        for byte in modulus.iter_mut().rev() {
+            let (sum, carry) = byte.overflowing_add(1);
+            *byte = sum;
+            if !carry {
+                break;
+            }
+        }
+        modulus
+    }
+
+    /// Writes the export as a JSON object:
+    ///
+    /// ```text
+    /// {
+    ///   "field_modulus": "0x73ed...0001",
+    ///   "num_inputs": 2,
+    ///   "num_aux": 3,
+    ///   "constraints": [
+    ///     {"a": [[1, "0x...01"]], "b": [[0, "0x...01"]], "c": [[2, "0x...01"]]}
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Numbers that may not fit in a double, the modulus and the
+    /// coefficients, are strings of `0x` and their big-endian hexadecimal
+    /// digits, as wide as the representation of the field.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        fn hex(bytes: &[u8]) -> String {
+            let digits = bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            format!("\"0x{}\"", digits)
+        }
+        let lc = |terms: &[(usize, Scalar)]| {
+            let terms = terms
+                .iter()
+                .map(|(wire, coeff)| {
+                    format!(
+                        "[{}, {}]",
+                        wire,
+                        hex(&big_endian::<Scalar>(coeff.to_repr()))
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]", terms.join(", "))
+        };
+
+        writeln!(writer, "{{")?;
+        writeln!(
+            writer,
+            "  \"field_modulus\": {},",
+            hex(&Self::field_modulus())
+        )?;
+        writeln!(writer, "  \"num_inputs\": {},", self.num_inputs)?;
+        writeln!(writer, "  \"num_aux\": {},", self.num_aux)?;
+        write!(writer, "  \"constraints\": [")?;
+        for (i, [a, b, c]) in self.constraints.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(
+                writer,
+                "{}\n    {{\"a\": {}, \"b\": {}, \"c\": {}}}",
+                separator,
+                lc(a),
+                lc(b),
+                lc(c)
+            )?;
+        }
+        if !self.constraints.is_empty() {
+            write!(writer, "\n  ")?;
+        }
+        writeln!(writer, "]")?;
+        writeln!(writer, "}}")
+    }
+}
+
+/// Synthesizes `circuit` and writes its constraints to `writer` as JSON, in
+/// the format of [`R1CSExport::write_json`].
+pub fn export_to_writer<Scalar, C, W>(circuit: C, writer: W) -> Result<(), SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+    W: Write,
+{
+    let circuit = RawCircuit::synthesize(circuit)?;
+    R1CSExport::new(&circuit).write_json(writer)?;
+    Ok(())
+}
+
+/// Synthesizes `circuit` and writes it to `writer` in the binary `.r1cs`
+/// format of circom and snarkjs, with [`RawCircuit::write_r1cs`].
+pub fn export_to_r1cs<Scalar, C, W>(circuit: C, writer: W) -> Result<(), SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+    W: Write,
+{
+    RawCircuit::synthesize(circuit)?.write_r1cs(writer)?;
+    Ok(())
+}
+
+impl<Scalar: PrimeField> RawCircuit<Scalar> {
+    /// Writes the circuit in version 1 of the binary `.r1cs` format of circom
+    /// and snarkjs, with its header, constraint and wire-to-label sections.
+    ///
+    /// Wires are numbered as in [`R1CSExport`], and the inputs other than
+    /// `ONE` are the public inputs, so that the public signals of snarkjs are
+    /// the public inputs of the circuit in order. Every other variable is an
+    /// internal wire, and each wire is labelled with its own number.
+    pub fn write_r1cs<W: Write>(&self, writer: W) -> io::Result<()> {
+        let export = R1CSExport::new(self);
+        let num_wires = self.num_inputs + self.num_aux;
+
+        let mut header = field_header::<Scalar>()?;
+        header.write_u32::<LittleEndian>(num_wires as u32)?;
+        // The public outputs, the public inputs and the private inputs.
+        header.write_u32::<LittleEndian>(0)?;
+        header.write_u32::<LittleEndian>(self.num_inputs as u32 - 1)?;
+        header.write_u32::<LittleEndian>(0)?;
+        header.write_u64::<LittleEndian>(num_wires as u64)?;
+        header.write_u32::<LittleEndian>(self.num_constraints as u32)?;
+
+        let mut constraints = vec![];
+        for constraint in &export.constraints {
+            for terms in constraint {
+                constraints.write_u32::<LittleEndian>(terms.len() as u32)?;
+                for (wire, coeff) in terms {
+                    constraints.write_u32::<LittleEndian>(*wire as u32)?;
+                    constraints.write_all(&little_endian::<Scalar>(coeff.to_repr()))?;
+                }
+            }
+        }
+
+        let mut labels = vec![];
+        for wire in 0..num_wires {
+            labels.write_u64::<LittleEndian>(wire as u64)?;
+        }
+
+        write_sections(writer, b"r1cs", 1, &[header, constraints, labels])
+    }
+}
+
+impl<Scalar: PrimeField> Assignment<Scalar> {
+    /// Writes the assignment in version 2 of the binary `.wtns` format of
+    /// circom and snarkjs, with its header and witness sections.
+    ///
+    /// The values are in the order of the wires of [`RawCircuit::write_r1cs`]:
+    /// the inputs, from `ONE`, then the auxiliary variables.
+    pub fn write_wtns<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut header = field_header::<Scalar>()?;
+        header.write_u32::<LittleEndian>((self.inputs.len() + self.aux.len()) as u32)?;
+
+        let mut witness = vec![];
+        for value in self.inputs.iter().chain(self.aux.iter()) {
+            witness.write_all(&little_endian::<Scalar>(value.to_repr()))?;
+        }
+
+        write_sections(writer, b"wtns", 2, &[header, witness])
+    }
+}
+
+/// Synthesizes `circuit` and writes its assignment to `writer` in the binary
+/// `.wtns` format of circom and snarkjs, with [`Assignment::write_wtns`].
+pub fn export_witness<Scalar, C, W>(circuit: C, writer: W) -> Result<(), SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+    W: Write,
+{
+    Assignment::synthesize(circuit)?.write_wtns(writer)?;
+    Ok(())
+}
+
+/// The R1CS matrices of a circuit, with the path of every variable and
+/// constraint in the namespaces of the circuit, such as `hash/round 3/x`.
+/// Input 0 is `ONE`.
+///
+/// With the `locations` feature, `locations` has the call that enforced each
+/// constraint, such as `src/gadgets/boolean.rs:42:9`, for the exports to
+/// point at the gadget; without it, every location is `None`.
+#[derive(Clone, Debug)]
+pub struct NamedCircuit<Scalar: PrimeField> {
+    pub circuit: RawCircuit<Scalar>,
+    pub inputs: Vec<String>,
+    pub aux: Vec<String>,
+    pub constraints: Vec<String>,
+    pub locations: Vec<Option<&'static Location<'static>>>,
+}
+
+impl<Scalar: PrimeField> PartialEq for NamedCircuit<Scalar> {
+    fn eq(&self, other: &Self) -> bool {
+        // `Location` has no `PartialEq` in the oldest Rust we support.
+        let locations = |c: &Self| {
+            c.locations
+                .iter()
+                .map(|l| l.map(|l| (l.file(), l.line(), l.column())))
+                .collect::<Vec<_>>()
+        };
+        self.circuit == other.circuit
+            && self.inputs == other.inputs
+            && self.aux == other.aux
+            && self.constraints == other.constraints
+            && locations(self) == locations(other)
+    }
+}
+
+struct NamingCs<Scalar: PrimeField> {
+    namespace: Vec<String>,
+    named: NamedCircuit<Scalar>,
+}
+
+impl<Scalar: PrimeField> NamingCs<Scalar> {
+    fn path(&self, annotation: String) -> String {
+        let mut path = self.namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&annotation);
+        path
+    }
+}
+
+impl<Scalar: PrimeField> ConstraintSystem<Scalar> for NamingCs<Scalar> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let path = self.path(annotation().into());
+        self.named.aux.push(path);
+        self.named.circuit.alloc(|| "", f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let path = self.path(annotation().into());
+        self.named.inputs.push(path);
+        self.named.circuit.alloc_input(|| "", f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let path = self.path(annotation().into());
+        self.named.constraints.push(path);
+        self.named.locations.push(crate::caller_location());
+        self.named.circuit.enforce(|| "", a, b, c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        self.namespace.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+impl<Scalar: PrimeField> NamedCircuit<Scalar> {
+    /// Synthesizes the constraint matrices of `circuit` and the paths of its
+    /// variables and constraints, without computing a witness.
+    pub fn synthesize<C: Circuit<Scalar>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut cs = NamingCs {
+            namespace: vec![],
+            named: NamedCircuit {
+                circuit: RawCircuit::with_one()?,
+                inputs: vec!["ONE".to_string()],
+                aux: vec![],
+                constraints: vec![],
+                locations: vec![],
+            },
+        };
+        circuit.synthesize(&mut cs)?;
+        Ok(cs.named)
+    }
+
+    /// Writes the constraints as SMT-LIB assertions in the theory of finite
+    /// fields (the `QF_FF` logic of cvc5), with one constant of the field
+    /// per variable other than `ONE`.
+    ///
+    /// ```text
+    /// (set-logic QF_FF)
+    /// (define-sort F () (_ FiniteField 5243...0001))
+    /// ; inputs
+    /// (declare-const |c| F)
+    /// ; auxiliary variables
+    /// (declare-const |a| F)
+    /// (declare-const |b| F)
+    /// ; a * b = c
+    /// (assert (= (ff.mul |a| |b|) |c|))
+    /// ```
+    ///
+    /// Constants are named by the paths of their variables, as quoted
+    /// symbols. Paths that are used more than once get the number of their
+    /// wire, as numbered by [`R1CSExport`], as a suffix, such as `|x#7|`,
+    /// and the characters that quoted symbols cannot contain, `|` and `\`,
+    /// become `_`. There is no `check-sat`, so that an audit can append the
+    /// property to check, such as a second witness differing on an output,
+    /// to check that a gadget is deterministic.
+    pub fn write_smtlib<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let modulus = decimal(&R1CSExport::<Scalar>::field_modulus());
+        let constant = |value: &Scalar| {
+            format!(
+                "(as ff{} F)",
+                decimal(&big_endian::<Scalar>(value.to_repr()))
+            )
+        };
+
+        let mut used = std::collections::HashSet::new();
+        let names = self
+            .inputs
+            .iter()
+            .chain(self.aux.iter())
+            .enumerate()
+            .map(|(wire, path)| {
+                let path = path.replace(&['|', '\\'][..], "_");
+                if used.insert(path.clone()) {
+                    format!("|{}|", path)
+                } else {
+                    format!("|{}#{}|", path, wire)
+                }
+            })
+            .collect::<Vec<_>>();
+        let one = constant(&Scalar::one());
+        let term = |wire: usize, coeff: &Scalar| {
+            let var = if wire == 0 { &one } else { &names[wire] };
+            if *coeff == Scalar::one() {
+                var.clone()
+            } else {
+                format!("(ff.mul {} {})", constant(coeff), var)
+            }
+        };
+        let lc = |terms: &[(usize, Scalar)]| match terms {
+            [] => constant(&Scalar::zero()),
+            [(wire, coeff)] => term(*wire, coeff),
+            terms => {
+                let terms = terms
+                    .iter()
+                    .map(|(wire, coeff)| term(*wire, coeff))
+                    .collect::<Vec<_>>();
+                format!("(ff.add {})", terms.join(" "))
+            }
+        };
+
+        writeln!(writer, "(set-logic QF_FF)")?;
+        writeln!(writer, "(define-sort F () (_ FiniteField {}))", modulus)?;
+        writeln!(writer, "; inputs")?;
+        for name in &names[1..self.inputs.len()] {
+            writeln!(writer, "(declare-const {} F)", name)?;
+        }
+        writeln!(writer, "; auxiliary variables")?;
+        for name in &names[self.inputs.len()..] {
+            writeln!(writer, "(declare-const {} F)", name)?;
+        }
+        let export = R1CSExport::new(&self.circuit);
+        for ((path, location), [a, b, c]) in self
+            .constraints
+            .iter()
+            .zip(&self.locations)
+            .zip(&export.constraints)
+        {
+            write!(writer, "; {}", path.replace('\n', " "))?;
+            if let Some(location) = location {
+                write!(writer, " ({})", location)?;
+            }
+            writeln!(writer)?;
+            writeln!(
+                writer,
+                "(assert (= (ff.mul {} {}) {}))",
+                lc(a),
+                lc(b),
+                lc(c)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the symbol map of the circuit in the `.sym` format of circom,
+    /// so that debuggers can label the wires of [`RawCircuit::write_r1cs`]
+    /// and [`Assignment::write_wtns`] with the paths of their variables.
+    ///
+    /// ```text
+    /// 1,1,0,gadget/c
+    /// 2,2,0,gadget/a
+    /// ```
+    ///
+    /// Each variable other than `ONE` has a line with its label and its
+    /// wire, which are both its number in the `.r1cs` file, the number of
+    /// the namespace it was allocated in, in order of first use, and its
+    /// path. Commas and line breaks in paths become `_`.
+    pub fn write_sym<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut components = std::collections::HashMap::new();
+        for (wire, path) in self.inputs.iter().chain(&self.aux).enumerate().skip(1) {
+            let namespace = path.rfind('/').map_or("", |i| &path[..i]);
+            let next = components.len();
+            let component = *components.entry(namespace).or_insert(next);
+            let name = path.replace(&[',', '\n', '\r'][..], "_");
+            writeln!(writer, "{},{},{},{}", wire, wire, component, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Synthesizes `circuit` and writes the paths of its variables to `writer`
+/// in the `.sym` format of circom, with [`NamedCircuit::write_sym`].
+pub fn export_symbols<Scalar, C, W>(circuit: C, writer: W) -> Result<(), SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+    W: Write,
+{
+    NamedCircuit::synthesize(circuit)?.write_sym(writer)?;
+    Ok(())
+}
+
+/// Synthesizes `circuit` and writes its constraints to `writer` as SMT-LIB
+/// assertions, with [`NamedCircuit::write_smtlib`].
+pub fn export_to_smtlib<Scalar, C, W>(circuit: C, writer: W) -> Result<(), SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+    W: Write,
+{
+    NamedCircuit::synthesize(circuit)?.write_smtlib(writer)?;
+    Ok(())
+}
+
+/// Returns the decimal digits of a big-endian unsigned integer.
+fn decimal(bytes: &[u8]) -> String {
+    let mut digits = vec![];
+    let mut number = bytes.to_vec();
+    while number.iter().any(|b| *b != 0) {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 10) as u8;
+            remainder = acc % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Returns the start of the header of the binary formats of circom: the
+/// size of a field element, and the modulus, in little-endian.
+fn field_header<Scalar: PrimeField>() -> io::Result<Vec<u8>> {
+    let mut modulus = R1CSExport::<Scalar>::field_modulus();
+    modulus.reverse();
+
+    let mut header = vec![];
+    header.write_u32::<LittleEndian>(modulus.len() as u32)?;
+    header.write_all(&modulus)?;
+    Ok(header)
+}
+
+/// Writes a file of the binary formats of circom, whose sections are
+/// numbered from 1.
+fn write_sections<W: Write>(
+    mut writer: W,
+    magic: &[u8; 4],
+    version: u32,
+    sections: &[Vec<u8>],
+) -> io::Result<()> {
+    writer.write_all(magic)?;
+    writer.write_u32::<LittleEndian>(version)?;
+    writer.write_u32::<LittleEndian>(sections.len() as u32)?;
+    for (kind, section) in sections.iter().enumerate() {
+        writer.write_u32::<LittleEndian>(kind as u32 + 1)?;
+        writer.write_u64::<LittleEndian>(section.len() as u64)?;
+        writer.write_all(section)?;
+    }
+    Ok(())
+}
+
+/// How a coefficient is represented, beyond the byte order of the field's
+/// [`PrimeField::Repr`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Form {
+    /// The canonical representation of the value, as in bellman.
+    Standard,
+    /// The canonical representation of the value times `R = 2^(8 * n)`,
+    /// where `n` is the length of the representation, which is how
+    /// Montgomery arithmetic libraries store field elements.
+    Montgomery,
+}
+
+/// How a negative coefficient `-x` is represented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Negatives {
+    /// As the field element `p - x`, like any other, as in bellman.
+    Reduced,
+    /// As `x` with the most significant bit of the representation set. Only
+    /// fields whose elements leave that bit free can use it.
+    SignBit,
+}
+
+/// The convention in which a tool writes the coefficients of a circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Convention {
+    pub form: Form,
+    pub negatives: Negatives,
+}
+
+impl Default for Convention {
+    /// The convention of [`RawCircuit::write`].
+    fn default() -> Self {
+        Convention {
+            form: Form::Standard,
+            negatives: Negatives::Reduced,
+        }
+    }
+}
+
+/// Returns the index of the most significant byte of a representation.
+fn most_significant<Scalar: PrimeField>() -> usize {
+    let one = Scalar::one().to_repr();
+    if one.as_ref()[0] == 1 {
+        one.as_ref().len() - 1
+    } else {
+        0
+    }
+}
+
+/// Returns the bytes of a representation from the most significant down.
+fn big_endian<Scalar: PrimeField>(repr: Scalar::Repr) -> Vec<u8> {
+    let mut bytes = repr.as_ref().to_vec();
+    if most_significant::<Scalar>() != 0 {
+        bytes.reverse();
+    }
+    bytes
+}
+
+/// Returns the bytes of a representation from the least significant up.
+fn little_endian<Scalar: PrimeField>(repr: Scalar::Repr) -> Vec<u8> {
+    let mut bytes = big_endian::<Scalar>(repr);
+    bytes.reverse();
+    bytes
+}
+
+/// Returns the representation whose bytes from the least significant up are
+/// `bytes`, which must be as long as a representation.
+pub(crate) fn from_little_endian<Scalar: PrimeField>(bytes: &[u8]) -> Scalar::Repr {
+    let mut repr = Scalar::Repr::default();
+    repr.as_mut().copy_from_slice(bytes);
+    if most_significant::<Scalar>() == 0 {
+        repr.as_mut().reverse();
+    }
+    repr
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Convention {
+    /// Returns `R`, the Montgomery factor of the representation.
+    fn montgomery_factor<Scalar: PrimeField>() -> Scalar {
+        let bits = 8 * Scalar::Repr::default().as_ref().len() as u64;
+        Scalar::from(2).pow_vartime([bits])
+    }
+
+    fn sign_bit<Scalar: PrimeField>() -> io::Result<()> {
+        if Scalar::NUM_BITS as usize >= 8 * Scalar::Repr::default().as_ref().len() {
+            return Err(invalid_data("the field has no room for a sign bit"));
+        }
+        Ok(())
+    }
+
+    /// Returns the value that `repr` represents in this convention.
+    pub fn decode<Scalar: PrimeField>(&self, mut repr: Scalar::Repr) -> io::Result<Scalar> {
+        let negative = match self.negatives {
+            Negatives::Reduced => false,
+            Negatives::SignBit => {
+                Self::sign_bit::<Scalar>()?;
+                let byte = &mut repr.as_mut()[most_significant::<Scalar>()];
+                let negative = *byte & 0x80 != 0;
+                *byte &= 0x7f;
+                negative
+            }
+        };
+
+        let mut value = Scalar::from_repr(repr).ok_or_else(|| invalid_data("invalid scalar"))?;
+        if self.form == Form::Montgomery {
+            value *= Self::montgomery_factor::<Scalar>().invert().unwrap();
+        }
+        Ok(if negative { -value } else { value })
+    }
+
+    /// Returns the representation of `value` in this convention.
+    pub fn encode<Scalar: PrimeField>(&self, value: Scalar) -> io::Result<Scalar::Repr> {
+        let mut value = value;
+        let mut negative = false;
+        if self.negatives == Negatives::SignBit {
+            Self::sign_bit::<Scalar>()?;
+            // The smaller of `x` and `p - x` is written, as tools do.
+            let magnitude = -value;
+            if big_endian::<Scalar>(magnitude.to_repr()) < big_endian::<Scalar>(value.to_repr()) {
+                value = magnitude;
+                negative = true;
+            }
+        }
+        if self.form == Form::Montgomery {
+            value *= Self::montgomery_factor::<Scalar>();
+        }
+
+        let mut repr = value.to_repr();
+        if negative {
+            repr.as_mut()[most_significant::<Scalar>()] |= 0x80;
+        }
+        Ok(repr)
+    }
+
+    fn read_scalar<Scalar: PrimeField, R: Read>(&self, reader: &mut R) -> io::Result<Scalar> {
+        let mut repr = Scalar::Repr::default();
+        reader.read_exact(repr.as_mut())?;
+        self.decode(repr)
+    }
+}
+
+pub(crate) fn read_scalar<Scalar: PrimeField, R: Read>(reader: &mut R) -> io::Result<Scalar> {
+    let mut repr = Scalar::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+
+    Scalar::from_repr(repr)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid scalar"))
+}
+
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::Scalar;
+
+    struct MulCircuit {
+        a: Option<Scalar>,
+        b: Option<Scalar>,
+    }
+
+    impl Circuit<Scalar> for MulCircuit {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc(|| "b", || self.b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = cs.alloc_input(
+                || "c",
+                || {
+                    let a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+                    let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
+                    Ok(a * b)
+                },
+            )?;
+            cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serialization_and_replay() {
+        let circuit = RawCircuit::synthesize(MulCircuit { a: None, b: None }).unwrap();
+        let assignment = Assignment::synthesize(MulCircuit {
+            a: Some(Scalar::from(3)),
+            b: Some(Scalar::from(5)),
+        })
+        .unwrap();
+        assert!(assignment.is_satisfied(&circuit));
+
+        let mut bytes = vec![];
+        circuit.write(&mut bytes).unwrap();
+        assert_eq!(RawCircuit::read(&bytes[..]).unwrap(), circuit);
+        assert!(RawCircuit::<Scalar>::read(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut bytes = vec![];
+        assignment.write(&mut bytes).unwrap();
+        assert_eq!(Assignment::read(&bytes[..]).unwrap(), assignment);
+
+        let replay = ReplayCircuit {
+            circuit: circuit.clone(),
+            assignment: Some(assignment.clone()),
+        };
+        assert_eq!(
+            RawCircuit::synthesize(ReplayCircuit {
+                circuit: circuit.clone(),
+                assignment: None,
+            })
+            .unwrap(),
+            circuit
+        );
+        assert_eq!(Assignment::synthesize(replay).unwrap(), assignment);
+
+        let mut bad = assignment;
+        bad.inputs[1] = Scalar::from(16);
+        assert!(!bad.is_satisfied(&circuit));
+    }
+
+    #[test]
+    fn json_export() {
+        let mut json = vec![];
+        export_to_writer(MulCircuit { a: None, b: None }, &mut json).unwrap();
+
+        let one = format!("\"0x{:064x}\"", 1);
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            format!(
+                "{{\n  \"field_modulus\": \"0x{}\",\n  \"num_inputs\": 2,\n  \
+                 \"num_aux\": 2,\n  \"constraints\": [\n    \
+                 {{\"a\": [[2, {one}]], \"b\": [[3, {one}]], \"c\": [[1, {one}]]}}\n  ]\n}}\n",
+                "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001",
+                one = one
+            )
+        );
+
+        let empty = R1CSExport::<Scalar> {
+            num_inputs: 1,
+            num_aux: 0,
+            constraints: vec![],
+        };
+        let mut json = vec![];
+        empty.write_json(&mut json).unwrap();
+        assert!(String::from_utf8(json)
+            .unwrap()
+            .ends_with("\"constraints\": []\n}\n"));
+    }
+
+    #[test]
+    fn smtlib_export() {
+        struct Named;
+
+        impl Circuit<Scalar> for Named {
+            fn synthesize<CS: ConstraintSystem<Scalar>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let mut cs = cs.namespace(|| "gadget");
+                MulCircuit { a: None, b: None }.synthesize(&mut cs)?;
+                let x = cs.alloc(|| "a", || Err(SynthesisError::AssignmentMissing))?;
+                cs.enforce(
+                    || "x = 2 * x + 1",
+                    |lc| lc + x,
+                    |lc| lc + CS::one(),
+                    |lc| lc + (Scalar::from(2), x) + CS::one(),
+                );
+                Ok(())
+            }
+        }
+
+        let named = NamedCircuit::synthesize(Named).unwrap();
+        assert_eq!(named.inputs, ["ONE", "gadget/c"]);
+        assert_eq!(named.aux, ["gadget/a", "gadget/b", "gadget/a"]);
+        assert_eq!(named.circuit, RawCircuit::synthesize(Named).unwrap());
+
+        #[cfg(feature = "locations")]
+        {
+            let mut smt = vec![];
+            export_to_smtlib(Named, &mut smt).unwrap();
+            let location = named.locations[1].unwrap();
+            assert_eq!(location.file(), file!());
+            assert!(String::from_utf8(smt)
+                .unwrap()
+                .contains(&format!("; gadget/x = 2 * x + 1 ({})\n", location)));
+        }
+
+        // Without locations, the comments only have the paths.
+        let named = NamedCircuit {
+            locations: vec![None; 2],
+            ..named
+        };
+        let mut smt = vec![];
+        named.write_smtlib(&mut smt).unwrap();
+        assert_eq!(
+            String::from_utf8(smt).unwrap(),
+            "(set-logic QF_FF)\n\
+             (define-sort F () (_ FiniteField 52435875175126190479447740508185965837690552500527637822603658699938581184513))\n\
+             ; inputs\n\
+             (declare-const |gadget/c| F)\n\
+             ; auxiliary variables\n\
+             (declare-const |gadget/a| F)\n\
+             (declare-const |gadget/b| F)\n\
+             (declare-const |gadget/a#4| F)\n\
+             ; gadget/a * b = c\n\
+             (assert (= (ff.mul |gadget/a| |gadget/b|) |gadget/c|))\n\
+             ; gadget/x = 2 * x + 1\n\
+             (assert (= (ff.mul |gadget/a#4| (as ff1 F)) (ff.add (as ff1 F) (ff.mul (as ff2 F) |gadget/a#4|))))\n"
+        );
+        assert_eq!(decimal(&[0]), "0");
+        assert_eq!(decimal(&[1, 0]), "256");
+    }
+
+    #[test]
+    fn symbol_export() {
+        struct Named;
+
+        impl Circuit<Scalar> for Named {
+            fn synthesize<CS: ConstraintSystem<Scalar>>(
+                self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                MulCircuit { a: None, b: None }.synthesize(&mut cs.namespace(|| "gadget"))?;
+                cs.alloc(|| "x, y", || Err(SynthesisError::AssignmentMissing))?;
+                cs.namespace(|| "gadget")
+                    .alloc(|| "z", || Err(SynthesisError::AssignmentMissing))?;
+                Ok(())
+            }
+        }
+
+        let mut sym = vec![];
+        export_symbols(Named, &mut sym).unwrap();
+        assert_eq!(
+            String::from_utf8(sym).unwrap(),
+            "1,1,0,gadget/c\n\
+             2,2,0,gadget/a\n\
+             3,3,0,gadget/b\n\
+             4,4,1,x_ y\n\
+             5,5,0,gadget/z\n"
+        );
+    }
+
+    #[test]
+    fn r1cs_export() {
+        let mut circuit = RawCircuit::synthesize(MulCircuit { a: None, b: None }).unwrap();
+        circuit.ct_aux[1].push((-Scalar::from(2), 0));
+        let mut bytes = vec![];
+        circuit.write_r1cs(&mut bytes).unwrap();
+
+        let mut reader = &bytes[..];
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, b"r1cs");
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 1);
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 3);
+
+        let mut sections = vec![];
+        for kind in 1..=3 {
+            assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), kind);
+            let len = reader.read_u64::<LittleEndian>().unwrap() as usize;
+            sections.push(&reader[..len]);
+            reader = &reader[len..];
+        }
+        assert!(reader.is_empty());
+
+        let mut header = sections[0];
+        assert_eq!(header.read_u32::<LittleEndian>().unwrap(), 32);
+        let mut prime = [0; 32];
+        header.read_exact(&mut prime).unwrap();
+        prime.reverse();
+        assert_eq!(prime.to_vec(), R1CSExport::<Scalar>::field_modulus());
+        let counts = (0..4)
+            .map(|_| header.read_u32::<LittleEndian>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![4, 0, 1, 0]);
+        assert_eq!(header.read_u64::<LittleEndian>().unwrap(), 4);
+        assert_eq!(header.read_u32::<LittleEndian>().unwrap(), 1);
+
+        // The terms are those of the JSON export, with coefficients in
+        // little-endian.
+        let mut constraints = sections[1];
+        let expected = &R1CSExport::new(&circuit).constraints[0];
+        for terms in expected.iter() {
+            assert_eq!(
+                constraints.read_u32::<LittleEndian>().unwrap() as usize,
+                terms.len()
+            );
+            for (wire, coeff) in terms {
+                assert_eq!(
+                    constraints.read_u32::<LittleEndian>().unwrap() as usize,
+                    *wire
+                );
+                let mut repr = <Scalar as PrimeField>::Repr::default();
+                constraints.read_exact(repr.as_mut()).unwrap();
+                assert_eq!(Scalar::from_repr(repr).unwrap(), *coeff);
+            }
+        }
+        assert!(constraints.is_empty());
+        assert_eq!(sections[2].len(), 4 * 8);
+    }
+
+    #[test]
+    fn wtns_export() {
+        let circuit = || MulCircuit {
+            a: Some(Scalar::from(3)),
+            b: Some(Scalar::from(5)),
+        };
+        let assignment = Assignment::synthesize(circuit()).unwrap();
+        let mut bytes = vec![];
+        export_witness(circuit(), &mut bytes).unwrap();
+
+        let mut reader = &bytes[..];
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, b"wtns");
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 2);
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 2);
+
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 1);
+        assert_eq!(reader.read_u64::<LittleEndian>().unwrap(), 4 + 32 + 4);
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 32);
+        let mut prime = [0; 32];
+        reader.read_exact(&mut prime).unwrap();
+        prime.reverse();
+        assert_eq!(prime.to_vec(), R1CSExport::<Scalar>::field_modulus());
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 4);
+
+        // The values are in the order of the wires of the `.r1cs` export.
+        assert_eq!(reader.read_u32::<LittleEndian>().unwrap(), 2);
+        assert_eq!(reader.read_u64::<LittleEndian>().unwrap(), 4 * 32);
+        let values = (0..4)
+            .map(|_| read_scalar::<Scalar, _>(&mut reader).unwrap())
+            .collect::<Vec<_>>();
+        assert!(reader.is_empty());
+        assert_eq!(
+            values,
+            [&assignment.inputs[..], &assignment.aux[..]].concat()
+        );
+        assert_eq!(
+            values,
+            vec![
+                Scalar::one(),
+                Scalar::from(15),
+                Scalar::from(3),
+                Scalar::from(5)
+            ]
+        );
+    }
+
+    #[test]
+    fn coefficient_conventions() {
+        let conventions = [Form::Standard, Form::Montgomery]
+            .iter()
+            .flat_map(|&form| {
+                [Negatives::Reduced, Negatives::SignBit]
+                    .iter()
+                    .map(move |&negatives| Convention { form, negatives })
+            })
+            .collect::<Vec<_>>();
+
+        // -1 is the canonical representation of 1 with the sign bit set, or
+        // R in Montgomery form.
+        let minus_one = |convention: &Convention| convention.encode(-Scalar::one()).unwrap();
+        let mut signed_one = Scalar::one().to_repr();
+        signed_one[31] |= 0x80;
+        assert_eq!(minus_one(&conventions[1]), signed_one);
+        assert_eq!(
+            conventions[2].encode(Scalar::one()).unwrap(),
+            Convention::montgomery_factor::<Scalar>().to_repr()
+        );
+
+        let mut circuit = RawCircuit::synthesize(MulCircuit { a: None, b: None }).unwrap();
+        circuit.at_aux[0].push((-Scalar::from(7), 0));
+        for convention in &conventions {
+            assert_eq!(
+                convention.decode::<Scalar>(minus_one(convention)).unwrap(),
+                -Scalar::one()
+            );
+
+            let mut bytes = vec![];
+            circuit.write_with(&mut bytes, convention).unwrap();
+            assert_eq!(
+                RawCircuit::read_with(&bytes[..], convention).unwrap(),
+                circuit
+            );
+            // Reading in the wrong convention gives another circuit, or fails.
+            if *convention != Convention::default() {
+                assert!(RawCircuit::read(&bytes[..]).map_or(true, |read| read != circuit));
+            }
+        }
+    }
+}
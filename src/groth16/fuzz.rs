@@ -0,0 +1,379 @@
+//! Random satisfiable circuits, for fuzzing and differential testing.
+//!
+//! [`random_circuit`] generates an R1CS instance of a given shape together
+//! with a satisfying witness. [`fuzz`] then checks that every [`Backend`]
+//! accepts the witness, and rejects it once an auxiliary value has been
+//! changed. Backends are provided for a direct evaluation of the matrices,
+//! for [`TestConstraintSystem`], and for Groth16; implementing [`Backend`]
+//! for another proving system compares it against these.
+//!
+//! [`TestConstraintSystem`]: crate::gadgets::test::TestConstraintSystem
+
+use ff::PrimeField;
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+
+use super::exporter::{Assignment, RawCircuit, ReplayCircuit};
+use super::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    Parameters,
+};
+use crate::gadgets::test::TestConstraintSystem;
+use crate::{Circuit, SynthesisError};
+
+/// The shape of a random circuit.
+#[derive(Clone, Debug)]
+pub struct CircuitConfig {
+    /// The number of public inputs, excluding `ONE`.
+    pub num_inputs: usize,
+    /// The number of auxiliary variables.
+    pub num_aux: usize,
+    /// The number of constraints. Every auxiliary variable is determined by
+    /// a constraint of its own, so there are at least `num_aux` of them.
+    pub num_constraints: usize,
+    /// The number of terms in each linear combination.
+    pub terms: usize,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        CircuitConfig {
+            num_inputs: 2,
+            num_aux: 8,
+            num_constraints: 8,
+            terms: 3,
+        }
+    }
+}
+
+fn random_nonzero<S: PrimeField, R: RngCore>(rng: &mut R) -> S {
+    loop {
+        let value = S::random(&mut *rng);
+        if !value.is_zero() {
+            return value;
+        }
+    }
+}
+
+/// Generates a random circuit of the given shape, and a satisfying
+/// assignment for it.
+///
+/// The assignment is chosen first, with every variable non-zero. Each
+/// constraint then gets random linear combinations `A`, `B` and `C`, except
+/// for one term of `C` on its pivot variable, whose coefficient is solved
+/// for. Constraint `j` pivots on auxiliary variable `j mod num_aux`, so that
+/// none is left unconstrained.
+pub fn random_circuit<S: PrimeField, R: RngCore>(
+    config: &CircuitConfig,
+    rng: &mut R,
+) -> (RawCircuit<S>, Assignment<S>) {
+    let num_inputs = config.num_inputs + 1;
+    let num_constraints = std::cmp::max(config.num_constraints, config.num_aux);
+
+    let mut inputs = vec![S::one()];
+    inputs.extend((1..num_inputs).map(|_| random_nonzero::<S, _>(rng)));
+    let aux = (0..config.num_aux)
+        .map(|_| random_nonzero::<S, _>(rng))
+        .collect::<Vec<_>>();
+
+    let mut circuit = RawCircuit {
+        num_inputs,
+        num_aux: config.num_aux,
+        num_constraints,
+        at_inputs: vec![vec![]; num_inputs],
+        bt_inputs: vec![vec![]; num_inputs],
+        ct_inputs: vec![vec![]; num_inputs],
+        at_aux: vec![vec![]; config.num_aux],
+        bt_aux: vec![vec![]; config.num_aux],
+        ct_aux: vec![vec![]; config.num_aux],
+    };
+
+    // Variables are numbered with the inputs first.
+    let num_vars = num_inputs + config.num_aux;
+    let value = |var: usize| {
+        if var < num_inputs {
+            inputs[var]
+        } else {
+            aux[var - num_inputs]
+        }
+    };
+
+    for j in 0..num_constraints {
+        let terms = |count: usize, rng: &mut R| {
+            (0..count)
+                .map(|_| (rng.next_u32() as usize % num_vars, S::random(&mut *rng)))
+                .collect::<Vec<_>>()
+        };
+        let evaluate = |terms: &[(usize, S)]| {
+            terms
+                .iter()
+                .fold(S::zero(), |acc, (var, coeff)| acc + value(*var) * coeff)
+        };
+
+        let a = terms(config.terms, rng);
+        let b = terms(config.terms, rng);
+        let mut c = terms(config.terms.saturating_sub(1), rng);
+
+        // Without auxiliary variables, ONE is the only pivot.
+        let pivot = if config.num_aux == 0 {
+            0
+        } else {
+            num_inputs + j % config.num_aux
+        };
+        let remainder = evaluate(&a) * evaluate(&b) - evaluate(&c);
+        c.push((pivot, remainder * value(pivot).invert().unwrap()));
+
+        for (terms, (inputs, aux)) in [a, b, c].iter().zip(
+            [
+                (&mut circuit.at_inputs, &mut circuit.at_aux),
+                (&mut circuit.bt_inputs, &mut circuit.bt_aux),
+                (&mut circuit.ct_inputs, &mut circuit.ct_aux),
+            ]
+            .iter_mut(),
+        ) {
+            for (var, coeff) in terms.iter() {
+                if *var < num_inputs {
+                    inputs[*var].push((*coeff, j));
+                } else {
+                    aux[*var - num_inputs].push((*coeff, j));
+                }
+            }
+        }
+    }
+
+    (circuit, Assignment { inputs, aux })
+}
+
+/// An implementation that decides whether an assignment satisfies a
+/// circuit.
+pub trait Backend<S: PrimeField> {
+    /// A name for the backend, used in reported failures.
+    fn name(&self) -> &str;
+
+    /// Returns `true` if `assignment` satisfies `circuit`, according to this
+    /// backend.
+    fn accepts(
+        &mut self,
+        circuit: &RawCircuit<S>,
+        assignment: &Assignment<S>,
+    ) -> Result<bool, SynthesisError>;
+}
+
+/// Evaluates the constraint matrices directly.
+pub struct Reference;
+
+impl<S: PrimeField> Backend<S> for Reference {
+    fn name(&self) -> &str {
+        "reference"
+    }
+
+    fn accepts(
+        &mut self,
+        circuit: &RawCircuit<S>,
+        assignment: &Assignment<S>,
+    ) -> Result<bool, SynthesisError> {
+        Ok(assignment.is_satisfied(circuit))
+    }
+}
+
+/// Replays the circuit into a [`TestConstraintSystem`].
+pub struct TestSystem;
+
+impl<S: PrimeField> Backend<S> for TestSystem {
+    fn name(&self) -> &str {
+        "test-constraint-system"
+    }
+
+    fn accepts(
+        &mut self,
+        circuit: &RawCircuit<S>,
+        assignment: &Assignment<S>,
+    ) -> Result<bool, SynthesisError> {
+        let mut cs = TestConstraintSystem::new();
+        ReplayCircuit {
+            circuit: circuit.clone(),
+            assignment: Some(assignment.clone()),
+        }
+        .synthesize(&mut cs)?;
+
+        Ok(cs.is_satisfied())
+    }
+}
+
+/// Creates a Groth16 proof from the assignment, and verifies it against
+/// its public inputs. Parameters are reused while the circuit stays the
+/// same.
+pub struct Groth16<E: MultiMillerLoop, R: RngCore> {
+    rng: R,
+    params: Option<(RawCircuit<E::Fr>, Parameters<E>)>,
+}
+
+impl<E: MultiMillerLoop, R: RngCore> Groth16<E, R> {
+    pub fn new(rng: R) -> Self {
+        Groth16 { rng, params: None }
+    }
+}
+
+impl<E, R> Backend<E::Fr> for Groth16<E, R>
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    R: RngCore,
+{
+    fn name(&self) -> &str {
+        "groth16"
+    }
+
+    fn accepts(
+        &mut self,
+        circuit: &RawCircuit<E::Fr>,
+        assignment: &Assignment<E::Fr>,
+    ) -> Result<bool, SynthesisError> {
+        let stale = match &self.params {
+            Some((cached, _)) => cached != circuit,
+            None => true,
+        };
+        if stale {
+            let params = generate_random_parameters(
+                ReplayCircuit {
+                    circuit: circuit.clone(),
+                    assignment: None,
+                },
+                &mut self.rng,
+            )?;
+            self.params = Some((circuit.clone(), params));
+        }
+        let params = &self.params.as_ref().unwrap().1;
+
+        let proof = create_random_proof(
+            ReplayCircuit {
+                circuit: circuit.clone(),
+                assignment: Some(assignment.clone()),
+            },
+            params,
+            &mut self.rng,
+        )?;
+        let pvk = prepare_verifying_key(&params.vk);
+
+        Ok(verify_proof(&pvk, &proof, &assignment.inputs[1..]).is_ok())
+    }
+}
+
+/// A case on which a backend did not return the expected verdict.
+#[derive(Debug)]
+pub struct Failure<S: PrimeField> {
+    pub backend: String,
+    pub circuit: RawCircuit<S>,
+    pub assignment: Assignment<S>,
+    pub expected: bool,
+    pub result: Result<bool, SynthesisError>,
+}
+
+/// Checks that every backend returns `expected` for `assignment`.
+pub fn check<S: PrimeField>(
+    circuit: &RawCircuit<S>,
+    assignment: &Assignment<S>,
+    expected: bool,
+    backends: &mut [&mut dyn Backend<S>],
+) -> Result<(), Box<Failure<S>>> {
+    for backend in backends.iter_mut() {
+        let result = backend.accepts(circuit, assignment);
+        if result.as_ref().ok() != Some(&expected) {
+            return Err(Box::new(Failure {
+                backend: backend.name().to_string(),
+                circuit: circuit.clone(),
+                assignment: assignment.clone(),
+                expected,
+                result,
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `iterations` rounds of differential testing on random circuits of
+/// the given shape.
+///
+/// Each round checks that every backend accepts a satisfying assignment
+/// and, if the circuit has auxiliary variables, rejects the assignment with
+/// one of them incremented.
+pub fn fuzz<S: PrimeField, R: RngCore>(
+    config: &CircuitConfig,
+    iterations: usize,
+    rng: &mut R,
+    backends: &mut [&mut dyn Backend<S>],
+) -> Result<(), Box<Failure<S>>> {
+    for _ in 0..iterations {
+        let (circuit, mut assignment) = random_circuit(config, rng);
+        check(&circuit, &assignment, true, backends)?;
+
+        if config.num_aux > 0 {
+            let i = rng.next_u32() as usize % config.num_aux;
+            assignment.aux[i].add_assign(&S::one());
+            check(&circuit, &assignment, false, backends)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::{Bls12, Scalar};
+
+    #[test]
+    fn random_circuits_are_satisfiable() {
+        let mut rng = rng();
+        for config in [
+            CircuitConfig::default(),
+            CircuitConfig {
+                num_inputs: 0,
+                num_aux: 0,
+                num_constraints: 4,
+                terms: 1,
+            },
+            CircuitConfig {
+                num_inputs: 5,
+                num_aux: 20,
+                num_constraints: 3,
+                terms: 6,
+            },
+        ]
+        .iter()
+        {
+            let (circuit, assignment) = random_circuit::<Scalar, _>(config, &mut rng);
+            assert_eq!(circuit.num_inputs, config.num_inputs + 1);
+            assert_eq!(
+                circuit.num_constraints,
+                std::cmp::max(config.num_constraints, config.num_aux)
+            );
+            assert!(circuit.ct_aux.iter().all(|column| !column.is_empty()));
+            assert!(assignment.is_satisfied(&circuit));
+        }
+    }
+
+    #[test]
+    fn backends_agree() {
+        let mut rng = rng();
+        let mut groth16 = Groth16::<Bls12, _>::new(rng.clone());
+        let config = CircuitConfig {
+            num_inputs: 3,
+            num_aux: 6,
+            num_constraints: 10,
+            terms: 4,
+        };
+
+        fuzz::<Scalar, _>(
+            &config,
+            3,
+            &mut rng,
+            &mut [&mut Reference, &mut TestSystem, &mut groth16],
+        )
+        .unwrap();
+    }
+}
@@ -1,19 +1,28 @@
 use rand_core::RngCore;
 use std::ops::{AddAssign, MulAssign};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use blake2s_simd::Params as Blake2sParams;
 use ff::{Field, PrimeField};
-use group::{prime::PrimeCurveAffine, Curve, Group, Wnaf, WnafGroup};
+use group::{
+    prime::PrimeCurveAffine, Curve, Group, GroupEncoding, UncompressedEncoding, Wnaf, WnafGroup,
+};
 use pairing::Engine;
 
+use super::checkpoint::Checkpoint;
+use super::cost_model::CircuitStats;
 use super::{Parameters, VerifyingKey};
 
 use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 
-use crate::domain::{EvaluationDomain, Scalar};
+use crate::domain::{domain_size, Radix, Scalar};
 
 use crate::multicore::Worker;
 
+use crate::trace;
+use crate::zeroize::{Secret, SecretDomain};
+
 /// Generates a random common reference string for
 /// a circuit.
 pub fn generate_random_parameters<E, C, R>(
@@ -29,13 +38,78 @@ where
 {
     let g1 = E::G1::random(&mut rng);
     let g2 = E::G2::random(&mut rng);
-    let alpha = E::Fr::random(&mut rng);
-    let beta = E::Fr::random(&mut rng);
-    let gamma = E::Fr::random(&mut rng);
-    let delta = E::Fr::random(&mut rng);
-    let tau = E::Fr::random(&mut rng);
+    let alpha = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let beta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let gamma = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let delta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let tau = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    generate_parameters::<E, C>(circuit, g1, g2, *alpha, *beta, *gamma, *delta, *tau)
+}
+
+/// Generates a random common reference string for a circuit like
+/// [`generate_random_parameters`], and returns it with the statistics of the
+/// circuit, to be written with [`Parameters::write_with_stats`].
+pub fn generate_random_parameters_with_stats<E, C, R>(
+    circuit: C,
+    mut rng: &mut R,
+) -> Result<(Parameters<E>, CircuitStats), SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let g1 = E::G1::random(&mut rng);
+    let g2 = E::G2::random(&mut rng);
+    let alpha = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let beta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let gamma = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let delta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let tau = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    generate::<E, C>(
+        &Worker::new(),
+        circuit,
+        Radix::Two,
+        g1,
+        g2,
+        *alpha,
+        *beta,
+        *gamma,
+        *delta,
+        *tau,
+        None,
+        None,
+        None,
+    )
+}
+
+/// Generates a random common reference string for a circuit like
+/// [`generate_random_parameters`], on the threads of `worker` rather than a
+/// new pool.
+pub fn generate_random_parameters_on<E, C, R>(
+    worker: &Worker,
+    circuit: C,
+    mut rng: &mut R,
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let g1 = E::G1::random(&mut rng);
+    let g2 = E::G2::random(&mut rng);
+    let alpha = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let beta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let gamma = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let delta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let tau = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
 
-    generate_parameters::<E, C>(circuit, g1, g2, alpha, beta, gamma, delta, tau)
+    generate_parameters_on::<E, C>(worker, circuit, g1, g2, *alpha, *beta, *gamma, *delta, *tau)
 }
 
 /// This is our assembly structure that we'll use to synthesize the
@@ -171,6 +245,389 @@ where
     E::G2: WnafGroup,
     C: Circuit<E::Fr>,
 {
+    generate_parameters_on::<E, C>(
+        &Worker::new(),
+        circuit,
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau,
+    )
+}
+
+/// Create parameters for a circuit, given some toxic waste, on the threads
+/// of `worker` rather than a new pool.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_on<E, C>(
+    worker: &Worker,
+    circuit: C,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr,
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+{
+    generate_parameters_with_radix::<E, C>(
+        worker,
+        circuit,
+        Radix::Two,
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau,
+    )
+}
+
+/// Generates a random common reference string for a circuit like
+/// [`generate_random_parameters_on`], over an evaluation domain of `radix`.
+/// With [`Radix::Mixed`], the H query has as few points as the field
+/// allows, rather than a power of two, which proving with the parameters
+/// follows.
+pub fn generate_random_parameters_with_radix<E, C, R>(
+    worker: &Worker,
+    circuit: C,
+    radix: Radix,
+    mut rng: &mut R,
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let g1 = E::G1::random(&mut rng);
+    let g2 = E::G2::random(&mut rng);
+    let alpha = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let beta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let gamma = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let delta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let tau = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    generate_parameters_with_radix::<E, C>(
+        worker, circuit, radix, g1, g2, *alpha, *beta, *gamma, *delta, *tau,
+    )
+}
+
+/// Create parameters for a circuit, given some toxic waste, on the threads
+/// of `worker` and over an evaluation domain of `radix`.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_radix<E, C>(
+    worker: &Worker,
+    circuit: C,
+    radix: Radix,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr,
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+{
+    generate::<E, C>(
+        worker, circuit, radix, g1, g2, alpha, beta, gamma, delta, tau, None, None, None,
+    )
+    .map(|(params, _)| params)
+}
+
+/// A section of the parameters, as reported by [`KeygenProgress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeygenSection {
+    H,
+    A,
+    BG1,
+    BG2,
+    IC,
+    L,
+}
+
+/// The progress of parameter generation, reported after each window of a
+/// section is evaluated.
+#[derive(Clone, Debug)]
+pub struct KeygenProgress {
+    /// The section the window belongs to.
+    pub section: KeygenSection,
+    /// The elements of the section evaluated so far.
+    pub done: usize,
+    /// The elements of the section.
+    pub total: usize,
+    /// The elements of all sections evaluated so far.
+    pub overall_done: usize,
+    /// The elements of all sections.
+    pub overall_total: usize,
+    /// The time since parameter generation started.
+    pub elapsed: Duration,
+}
+
+impl KeygenProgress {
+    /// Estimates the time until parameter generation is done, assuming that
+    /// the remaining elements take as long as those evaluated so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.overall_done == 0 {
+            return None;
+        }
+        let remaining = (self.overall_total - self.overall_done) as f64;
+        Some(self.elapsed.mul_f64(remaining / self.overall_done as f64))
+    }
+}
+
+/// Tracks progress across sections for a [`KeygenProgress`] callback.
+struct Progress<'a> {
+    callback: &'a mut dyn FnMut(&KeygenProgress),
+    start: Instant,
+    done: [usize; 6],
+    totals: [usize; 6],
+}
+
+impl<'a> Progress<'a> {
+    fn advance(&mut self, section: KeygenSection, count: usize) {
+        self.done[section as usize] += count;
+        (self.callback)(&KeygenProgress {
+            section,
+            done: self.done[section as usize],
+            total: self.totals[section as usize],
+            overall_done: self.done.iter().sum(),
+            overall_total: self.totals.iter().sum(),
+            elapsed: self.start.elapsed(),
+        });
+    }
+}
+
+/// Generates a random common reference string for a circuit, evaluating
+/// each section in windows of `window` elements and passing `progress` the
+/// progress after each one.
+pub fn generate_random_parameters_with_progress<E, C, R>(
+    circuit: C,
+    mut rng: &mut R,
+    window: usize,
+    progress: &mut dyn FnMut(&KeygenProgress),
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let g1 = E::G1::random(&mut rng);
+    let g2 = E::G2::random(&mut rng);
+    let alpha = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let beta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let gamma = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let delta = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let tau = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    generate_parameters_with_progress::<E, C>(
+        circuit, g1, g2, *alpha, *beta, *gamma, *delta, *tau, window, progress,
+    )
+}
+
+/// Create parameters for a circuit, given some toxic waste, evaluating each
+/// section in windows of `window` elements and passing `progress` the
+/// progress after each one.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_progress<E, C>(
+    circuit: C,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr,
+    window: usize,
+    progress: &mut dyn FnMut(&KeygenProgress),
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+{
+    generate::<E, C>(
+        &Worker::new(),
+        circuit,
+        Radix::Two,
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau,
+        None,
+        Some(window),
+        Some(progress),
+    )
+    .map(|(params, _)| params)
+}
+
+/// Create parameters for a circuit, given some toxic waste, storing the
+/// queries in `checkpoint` as they are computed. If keygen is interrupted,
+/// calling this again with the same arguments resumes it from the windows
+/// completed so far.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_checkpointed<E, C>(
+    circuit: C,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr,
+    checkpoint: &Checkpoint,
+) -> Result<Parameters<E>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+{
+    generate::<E, C>(
+        &Worker::new(),
+        circuit,
+        Radix::Two,
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau,
+        Some(checkpoint),
+        None,
+        None,
+    )
+    .map(|(params, _)| params)
+}
+
+/// Binds the windows of a checkpoint to the circuit, the generators, the
+/// toxic waste and the window size they are computed with.
+fn binding<E: Engine>(
+    assembly: &KeypairAssembly<E::Fr>,
+    g1: E::G1,
+    g2: E::G2,
+    waste: [&E::Fr; 5],
+    window: usize,
+) -> [u8; 32] {
+    let mut state = Blake2sParams::new().personal(b"bellKgen").to_state();
+    state.update(g1.to_bytes().as_ref());
+    state.update(g2.to_bytes().as_ref());
+    for w in waste.iter() {
+        state.update(w.to_repr().as_ref());
+    }
+    state.update(&(window as u64).to_be_bytes());
+    state.update(&(assembly.num_constraints as u64).to_be_bytes());
+    for polynomials in [
+        &assembly.at_inputs,
+        &assembly.bt_inputs,
+        &assembly.ct_inputs,
+        &assembly.at_aux,
+        &assembly.bt_aux,
+        &assembly.ct_aux,
+    ]
+    .iter()
+    {
+        state.update(&(polynomials.len() as u64).to_be_bytes());
+        for p in polynomials.iter() {
+            state.update(&(p.len() as u64).to_be_bytes());
+            for (coeff, index) in p {
+                state.update(coeff.to_repr().as_ref());
+                state.update(&(*index as u64).to_be_bytes());
+            }
+        }
+    }
+
+    let mut binding = [0; 32];
+    binding.copy_from_slice(state.finalize().as_bytes());
+    binding
+}
+
+fn encode<G: UncompressedEncoding>(payload: &mut Vec<u8>, points: &[G]) {
+    for p in points {
+        payload.extend_from_slice(p.to_uncompressed().as_ref());
+    }
+}
+
+/// Decodes `points` from the front of `payload`. The payload was tagged
+/// when it was stored, so the points are not checked.
+fn decode<G: UncompressedEncoding>(payload: &mut &[u8], points: &mut [G]) -> bool {
+    for p in points {
+        let mut repr = G::Uncompressed::default();
+        let len = repr.as_ref().len();
+        if payload.len() < len {
+            return false;
+        }
+        repr.as_mut().copy_from_slice(&payload[..len]);
+        *payload = &payload[len..];
+        match Option::from(G::from_uncompressed_unchecked(&repr)) {
+            Some(point) => *p = point,
+            None => return false,
+        }
+    }
+    true
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate<E, C>(
+    worker: &Worker,
+    circuit: C,
+    radix: Radix,
+    g1: E::G1,
+    g2: E::G2,
+    alpha: E::Fr,
+    beta: E::Fr,
+    gamma: E::Fr,
+    delta: E::Fr,
+    tau: E::Fr,
+    checkpoint: Option<&Checkpoint>,
+    window: Option<usize>,
+    progress: Option<&mut dyn FnMut(&KeygenProgress)>,
+) -> Result<(Parameters<E>, CircuitStats), SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    C: Circuit<E::Fr>,
+{
+    let _span = trace::span("generate_parameters");
+    let start = Instant::now();
+
+    // The toxic waste, and the values derived from it, are erased as they
+    // go out of scope, on every path. The arguments themselves are not
+    // overwritten: once calls are inlined, rustc may pass a scalar argument
+    // as a pointer to the caller's copy even where the caller reads it
+    // again, so erasing it in place erases the caller's value. The
+    // checkpoint test `resumes_keygen` fails in release builds if `tau` is
+    // erased here, as its second run then generates with a zero `tau`.
+    let alpha = Secret::new(alpha, E::Fr::zero());
+    let beta = Secret::new(beta, E::Fr::zero());
+    let gamma = Secret::new(gamma, E::Fr::zero());
+    let delta = Secret::new(delta, E::Fr::zero());
+    let tau = Secret::new(tau, E::Fr::zero());
+
+    let mut span = trace::span("synthesize");
     let mut assembly = KeypairAssembly {
         num_inputs: 0,
         num_aux: 0,
@@ -189,15 +646,67 @@ where
     // Synthesize the circuit.
     circuit.synthesize(&mut assembly)?;
 
+    let stats = CircuitStats::from_columns(
+        assembly.num_inputs,
+        assembly.num_aux,
+        assembly.num_constraints,
+        [
+            &assembly.at_inputs,
+            &assembly.bt_inputs,
+            &assembly.ct_inputs,
+        ],
+        [&assembly.at_aux, &assembly.bt_aux, &assembly.ct_aux],
+    );
+
     // Input constraints to ensure full density of IC query
     // x * 0 = 0
     for i in 0..assembly.num_inputs {
         assembly.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
     }
 
+    span.record("inputs", assembly.num_inputs);
+    span.record("aux", assembly.num_aux);
+    span.record("constraints", assembly.num_constraints);
+    drop(span);
+
+    // Without a checkpoint, each query is computed in a single window.
+    let binding = match checkpoint {
+        Some(checkpoint) => binding::<E>(
+            &assembly,
+            g1,
+            g2,
+            [&alpha, &beta, &gamma, &delta, &tau],
+            checkpoint.window(),
+        ),
+        None => [0; 32],
+    };
+    let window = |len: usize| {
+        checkpoint
+            .map(|c| c.window())
+            .or(window)
+            .unwrap_or(len)
+            .max(1)
+    };
+
     // Create bases for blind evaluation of polynomials at tau
     let powers_of_tau = vec![Scalar::<E::Fr>(E::Fr::zero()); assembly.num_constraints];
-    let mut powers_of_tau = EvaluationDomain::from_coeffs(powers_of_tau)?;
+    let size = domain_size::<E::Fr>(powers_of_tau.len(), radix)?;
+    let mut powers_of_tau = SecretDomain::from_coeffs(&powers_of_tau, size)?;
+
+    let vars = assembly.num_inputs + assembly.num_aux;
+    let mut progress = progress.map(|callback| Progress {
+        callback,
+        start,
+        done: [0; 6],
+        totals: [
+            powers_of_tau.as_ref().len() - 1,
+            vars,
+            vars,
+            vars,
+            assembly.num_inputs,
+            assembly.num_aux,
+        ],
+    });
 
     // Compute G1 window table
     let mut g1_wnaf = Wnaf::new();
@@ -222,7 +731,7 @@ where
     let gamma_inverse = {
         let inverse = gamma.invert();
         if bool::from(inverse.is_some()) {
-            Ok(inverse.unwrap())
+            Ok(Secret::new(inverse.unwrap(), E::Fr::zero()))
         } else {
             Err(SynthesisError::UnexpectedIdentity)
         }
@@ -230,27 +739,29 @@ where
     let delta_inverse = {
         let inverse = delta.invert();
         if bool::from(inverse.is_some()) {
-            Ok(inverse.unwrap())
+            Ok(Secret::new(inverse.unwrap(), E::Fr::zero()))
         } else {
             Err(SynthesisError::UnexpectedIdentity)
         }
     }?;
 
-    let worker = Worker::new();
-
     let mut h = vec![E::G1Affine::identity(); powers_of_tau.as_ref().len() - 1];
     {
+        let mut span = trace::span("h_query");
+        span.record("size", h.len());
+
         // Compute powers of tau
         {
+            let tau: &E::Fr = &tau;
             let powers_of_tau = powers_of_tau.as_mut();
             worker.scope(powers_of_tau.len(), |scope, chunk| {
                 for (i, powers_of_tau) in powers_of_tau.chunks_mut(chunk).enumerate() {
                     scope.spawn(move |_scope| {
-                        let mut current_tau_power = tau.pow_vartime(&[(i * chunk) as u64]);
+                        let mut current_tau_power = tau.pow_vartime([(i * chunk) as u64]);
 
                         for p in powers_of_tau {
                             p.0 = current_tau_power;
-                            current_tau_power.mul_assign(&tau);
+                            current_tau_power.mul_assign(tau);
                         }
                     });
                 }
@@ -258,40 +769,66 @@ where
         }
 
         // coeff = t(x) / delta
-        let mut coeff = powers_of_tau.z(&tau);
-        coeff.mul_assign(&delta_inverse);
+        let mut coeff = Secret::new(powers_of_tau.z(&tau), E::Fr::zero());
+        coeff.mul_assign(&*delta_inverse);
+        let coeff: &E::Fr = &coeff;
+
+        let h_window = window(h.len());
+        for (i, (h, p)) in h
+            .chunks_mut(h_window)
+            .zip(powers_of_tau.as_ref().chunks(h_window))
+            .enumerate()
+        {
+            let name = format!("h-{}", i);
+            let stored = checkpoint.and_then(|c| c.load(&binding, &name));
+            if stored.map_or(false, |stored| {
+                let mut stored = &stored[..];
+                decode(&mut stored, h) && stored.is_empty()
+            }) {
+                if let Some(progress) = progress.as_mut() {
+                    progress.advance(KeygenSection::H, h.len());
+                }
+                continue;
+            }
 
-        // Compute the H query with multiple threads
-        worker.scope(h.len(), |scope, chunk| {
-            for (h, p) in h
-                .chunks_mut(chunk)
-                .zip(powers_of_tau.as_ref().chunks(chunk))
-            {
-                let mut g1_wnaf = g1_wnaf.shared();
+            // Compute the H query with multiple threads
+            worker.scope(h.len(), |scope, chunk| {
+                for (h, p) in h.chunks_mut(chunk).zip(p.chunks(chunk)) {
+                    let mut g1_wnaf = g1_wnaf.shared();
 
-                scope.spawn(move |_scope| {
-                    // Set values of the H query to g1^{(tau^i * t(tau)) / delta}
-                    let h_proj: Vec<_> = p[..h.len()]
-                        .iter()
-                        .map(|p| {
-                            // Compute final exponent
-                            let mut exp = p.0;
-                            exp.mul_assign(&coeff);
-
-                            // Exponentiate
-                            g1_wnaf.scalar(&exp)
-                        })
-                        .collect();
+                    scope.spawn(move |_scope| {
+                        // Set values of the H query to g1^{(tau^i * t(tau)) / delta}
+                        let h_proj: Vec<_> = p[..h.len()]
+                            .iter()
+                            .map(|p| {
+                                // Compute final exponent
+                                let mut exp = p.0;
+                                exp.mul_assign(coeff);
+
+                                // Exponentiate
+                                g1_wnaf.scalar(&exp)
+                            })
+                            .collect();
+
+                        // Batch normalize
+                        E::G1::batch_normalize(&h_proj, h);
+                    });
+                }
+            });
 
-                    // Batch normalize
-                    E::G1::batch_normalize(&h_proj, h);
-                });
+            if let Some(checkpoint) = checkpoint {
+                let mut payload = vec![];
+                encode(&mut payload, h);
+                checkpoint.store(&binding, &name, &payload)?;
             }
-        });
+            if let Some(progress) = progress.as_mut() {
+                progress.advance(KeygenSection::H, h.len());
+            }
+        }
     }
 
     // Use inverse FFT to convert powers of tau to Lagrange coefficients
-    powers_of_tau.ifft(&worker);
+    powers_of_tau.ifft(worker);
     let powers_of_tau = powers_of_tau.into_coeffs();
 
     let mut a = vec![E::G1Affine::identity(); assembly.num_inputs + assembly.num_aux];
@@ -419,41 +956,100 @@ where
         });
     }
 
-    // Evaluate for inputs.
-    eval::<E>(
-        &g1_wnaf,
-        &g2_wnaf,
-        &powers_of_tau,
-        &assembly.at_inputs,
-        &assembly.bt_inputs,
-        &assembly.ct_inputs,
-        &mut a[0..assembly.num_inputs],
-        &mut b_g1[0..assembly.num_inputs],
-        &mut b_g2[0..assembly.num_inputs],
-        &mut ic,
-        &gamma_inverse,
-        &alpha,
-        &beta,
-        &worker,
-    );
+    let mut span = trace::span("evaluate_queries");
+    span.record("inputs", assembly.num_inputs);
+    span.record("aux", assembly.num_aux);
+
+    // Evaluate for inputs, then for auxiliary variables.
+    let (a_inputs, a_aux) = a.split_at_mut(assembly.num_inputs);
+    let (b_g1_inputs, b_g1_aux) = b_g1.split_at_mut(assembly.num_inputs);
+    let (b_g2_inputs, b_g2_aux) = b_g2.split_at_mut(assembly.num_inputs);
+    let sections = vec![
+        (
+            "inputs",
+            &assembly.at_inputs,
+            &assembly.bt_inputs,
+            &assembly.ct_inputs,
+            a_inputs,
+            b_g1_inputs,
+            b_g2_inputs,
+            &mut ic[..],
+            KeygenSection::IC,
+            &gamma_inverse,
+        ),
+        (
+            "aux",
+            &assembly.at_aux,
+            &assembly.bt_aux,
+            &assembly.ct_aux,
+            a_aux,
+            b_g1_aux,
+            b_g2_aux,
+            &mut l[..],
+            KeygenSection::L,
+            &delta_inverse,
+        ),
+    ];
+    for (section, at, bt, ct, a, b_g1, b_g2, ext, ext_section, inv) in sections {
+        let section_window = window(a.len());
+        for (i, ((((((a, b_g1), b_g2), ext), at), bt), ct)) in a
+            .chunks_mut(section_window)
+            .zip(b_g1.chunks_mut(section_window))
+            .zip(b_g2.chunks_mut(section_window))
+            .zip(ext.chunks_mut(section_window))
+            .zip(at.chunks(section_window))
+            .zip(bt.chunks(section_window))
+            .zip(ct.chunks(section_window))
+            .enumerate()
+        {
+            let name = format!("{}-{}", section, i);
+            let stored = checkpoint.and_then(|c| c.load(&binding, &name));
+            let resumed = stored.map_or(false, |stored| {
+                let mut stored = &stored[..];
+                decode(&mut stored, a)
+                    && decode(&mut stored, b_g1)
+                    && decode(&mut stored, b_g2)
+                    && decode(&mut stored, ext)
+                    && stored.is_empty()
+            });
 
-    // Evaluate for auxiliary variables.
-    eval::<E>(
-        &g1_wnaf,
-        &g2_wnaf,
-        &powers_of_tau,
-        &assembly.at_aux,
-        &assembly.bt_aux,
-        &assembly.ct_aux,
-        &mut a[assembly.num_inputs..],
-        &mut b_g1[assembly.num_inputs..],
-        &mut b_g2[assembly.num_inputs..],
-        &mut l,
-        &delta_inverse,
-        &alpha,
-        &beta,
-        &worker,
-    );
+            if !resumed {
+                eval::<E>(
+                    &g1_wnaf,
+                    &g2_wnaf,
+                    &powers_of_tau,
+                    at,
+                    bt,
+                    ct,
+                    a,
+                    b_g1,
+                    b_g2,
+                    ext,
+                    inv,
+                    &alpha,
+                    &beta,
+                    worker,
+                );
+
+                if let Some(checkpoint) = checkpoint {
+                    let mut payload = vec![];
+                    encode(&mut payload, a);
+                    encode(&mut payload, b_g1);
+                    encode(&mut payload, b_g2);
+                    encode(&mut payload, ext);
+                    checkpoint.store(&binding, &name, &payload)?;
+                }
+            }
+
+            if let Some(progress) = progress.as_mut() {
+                progress.advance(KeygenSection::A, a.len());
+                progress.advance(KeygenSection::BG1, b_g1.len());
+                progress.advance(KeygenSection::BG2, b_g2.len());
+                progress.advance(ext_section, ext.len());
+            }
+        }
+    }
+    drop(span);
 
     // Don't allow any elements be unconstrained, so that
     // the L query is always fully dense.
@@ -467,16 +1063,16 @@ where
     let g2 = g2.to_affine();
 
     let vk = VerifyingKey::<E> {
-        alpha_g1: (g1 * &alpha).to_affine(),
-        beta_g1: (g1 * &beta).to_affine(),
-        beta_g2: (g2 * &beta).to_affine(),
-        gamma_g2: (g2 * &gamma).to_affine(),
-        delta_g1: (g1 * &delta).to_affine(),
-        delta_g2: (g2 * &delta).to_affine(),
+        alpha_g1: (g1 * &*alpha).to_affine(),
+        beta_g1: (g1 * &*beta).to_affine(),
+        beta_g2: (g2 * &*beta).to_affine(),
+        gamma_g2: (g2 * &*gamma).to_affine(),
+        delta_g1: (g1 * &*delta).to_affine(),
+        delta_g2: (g2 * &*delta).to_affine(),
         ic,
     };
 
-    Ok(Parameters {
+    let params = Parameters {
         vk,
         h: Arc::new(h),
         l: Arc::new(l),
@@ -497,5 +1093,6 @@ where
                 .filter(|e| bool::from(!e.is_identity()))
                 .collect(),
         ),
-    })
+    };
+    Ok((params, stats))
 }
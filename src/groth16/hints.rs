@@ -0,0 +1,143 @@
+//! BN254 proofs that carry the slopes of the Miller loop of their verifier.
+//!
+//! Verifying a proof prepares its point `B` for the Miller loop, which
+//! computes the slope of each of the [`G2Prepared::LINES`] lines of the loop
+//! with an inversion in `Fp2`. The other two G2 points of the equation are
+//! in the verifying key and prepared once, but `B` changes with every
+//! proof. A [`HintedProof`] carries the slopes of `B`, which
+//! [`verify_hinted_proof`] checks with a multiplication each instead of
+//! computing them (see [`G2Prepared::from_slopes`]). This trades 64 bytes
+//! per line, about 6.5 kB a proof, for the inversions, which are the
+//! costliest part of verification after the final exponentiation on
+//! devices without fast field arithmetic.
+//!
+//! # Format
+//!
+//! [`HintedProof::write`] emits the proof of [`Proof::write_uncompressed`],
+//! then the slope of each line in the order of the loop, as the big-endian
+//! encodings of its coefficients `c0` and `c1`.
+
+use std::io::{self, Read, Write};
+use std::ops::AddAssign;
+
+use super::verifier::{check_prepared_equation, input_sum};
+use super::{PreparedVerifyingKey, Proof};
+use crate::bn254::{Bn254, Fp2, Fr, G1Affine, G2Prepared};
+use crate::metrics;
+use crate::VerificationError;
+
+/// A proof with the lines of the Miller loop of its point `B`.
+#[derive(Clone)]
+pub struct HintedProof {
+    proof: Proof<Bn254>,
+    b: G2Prepared,
+}
+
+impl PartialEq for HintedProof {
+    fn eq(&self, other: &Self) -> bool {
+        // The lines are those of `B`.
+        self.proof == other.proof
+    }
+}
+
+impl HintedProof {
+    /// Computes the hints of `proof`.
+    pub fn new(proof: Proof<Bn254>) -> Self {
+        let b = proof.b.into();
+        HintedProof { proof, b }
+    }
+
+    /// Returns the proof without its hints.
+    pub fn proof(&self) -> &Proof<Bn254> {
+        &self.proof
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.proof.write_uncompressed(&mut writer)?;
+        for slope in self.b.slopes() {
+            writer.write_all(&slope.to_bytes_be())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a proof written by [`HintedProof::write`], checking its points
+    /// like [`Proof::read_uncompressed`] and its hints against `B`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let proof = Proof::<Bn254>::read_uncompressed(&mut reader)?;
+
+        let mut slopes = Vec::with_capacity(G2Prepared::LINES);
+        for _ in 0..G2Prepared::LINES {
+            let mut bytes = [0; 64];
+            reader.read_exact(&mut bytes)?;
+            let slope: Option<Fp2> = Fp2::from_bytes_be(&bytes).into();
+            slopes.push(
+                slope.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid slope"))?,
+            );
+        }
+        let b = G2Prepared::from_slopes(&proof.b, &slopes).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the hints are not the lines of B",
+            )
+        })?;
+
+        Ok(HintedProof { proof, b })
+    }
+}
+
+/// Verifies a hinted proof like [`verify_proof`](super::verify_proof),
+/// with the lines of its hints for `B`.
+pub fn verify_hinted_proof(
+    pvk: &PreparedVerifyingKey<Bn254>,
+    proof: &HintedProof,
+    public_inputs: &[Fr],
+) -> Result<(), VerificationError> {
+    metrics::increment("bellman_verifications_total", &[]);
+
+    if (public_inputs.len() + 1) != pvk.ic.len() {
+        metrics::increment("bellman_verification_failures_total", &[]);
+        return Err(VerificationError::InvalidVerifyingKey);
+    }
+
+    let mut acc = input_sum(pvk, public_inputs);
+    AddAssign::<&G1Affine>::add_assign(&mut acc, &pvk.ic[0]);
+
+    check_prepared_equation(pvk, &proof.proof, &proof.b, &acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+
+    #[test]
+    fn hinted_proofs() {
+        let mut f = Fixture::<Bn254>::new();
+        let proof = f.proof();
+
+        let mut v = vec![];
+        HintedProof::new(proof.clone()).write(&mut v).unwrap();
+        assert_eq!(v.len(), 256 + 64 * G2Prepared::LINES);
+        let hinted = HintedProof::read(&v[..]).unwrap();
+        assert!(hinted.proof() == &proof);
+
+        let inputs = f.inputs();
+        assert!(verify_hinted_proof(&f.pvk, &hinted, inputs).is_ok());
+        let mut wrong = inputs.to_vec();
+        wrong[0] += Fr::one();
+        assert!(verify_hinted_proof(&f.pvk, &hinted, &wrong).is_err());
+
+        // A slope that is still a field element, but not that of its line.
+        let mut tampered = v.clone();
+        tampered[256 + 63] ^= 1;
+        assert!(HintedProof::read(&tampered[..]).is_err());
+        // The hints of another proof.
+        let other = f.proof();
+        let mut mixed = vec![];
+        HintedProof::new(other).write(&mut mixed).unwrap();
+        mixed[..256].copy_from_slice(&v[..256]);
+        assert!(HintedProof::read(&mixed[..]).is_err());
+        assert!(HintedProof::read(&v[..v.len() - 1]).is_err());
+    }
+}
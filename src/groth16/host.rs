@@ -0,0 +1,292 @@
+//! Proof verification as a host function of a virtual machine.
+//!
+//! Blockchain VMs and rollup execution environments that expose Groth16
+//! verification to their programs need it to behave identically on every
+//! node, and to cost a price that is known before it runs. [`HostVerifier`]
+//! wraps the [`strict`](super::strict) decoding and [`verify_proof`] behind
+//! an interface made for that:
+//!
+//! - the arguments are the byte slices that the VM copies out of the memory
+//!   of the program, and the result is a numeric [`Status`] and the gas
+//!   used, which the VM hands back;
+//! - the price only depends on the lengths of the arguments, through an
+//!   integer [`GasSchedule`], and is charged before any decoding, so that a
+//!   call runs to completion or not at all;
+//! - [`HostLimits`] bound the lengths of the arguments, and so the memory
+//!   that decoding allocates, before anything is allocated;
+//! - verification uses no floating point and runs on the calling thread.
+//!
+//! [`verify_proof`]: super::verify_proof
+
+use core::marker::PhantomData;
+use ff::PrimeField;
+use pairing::MultiMillerLoop;
+
+use super::strict::{key_len, proof_len, Encoding, StrictError, StrictVerifier, FORMAT_VERSION};
+use crate::VerificationError;
+
+/// The result of a call, as returned to the program. The numeric codes are
+/// part of the interface, and do not change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Status {
+    /// The proof is valid.
+    Valid = 0,
+    /// The arguments decode, but the proof does not verify.
+    Invalid = 1,
+    /// The verifying key does not decode.
+    MalformedKey = 2,
+    /// The proof does not decode.
+    MalformedProof = 3,
+    /// The public inputs do not decode, or are not as many as the key has.
+    MalformedInputs = 4,
+    /// The call costs more than the gas it was given.
+    OutOfGas = 5,
+    /// An argument is longer than the limits of the host allow.
+    LimitExceeded = 6,
+}
+
+impl Status {
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        [
+            Status::Valid,
+            Status::Invalid,
+            Status::MalformedKey,
+            Status::MalformedProof,
+            Status::MalformedInputs,
+            Status::OutOfGas,
+            Status::LimitExceeded,
+        ]
+        .iter()
+        .copied()
+        .find(|status| status.code() == code)
+    }
+}
+
+/// The price of a call, in gas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasSchedule {
+    /// The price of the pairings of a call.
+    pub base: u64,
+    /// The price of each public input, for its scalar multiplication.
+    pub per_input: u64,
+    /// The price of each byte of the arguments, for decoding it.
+    pub per_byte: u64,
+}
+
+impl Default for GasSchedule {
+    /// A schedule where one gas is about the cost of decoding a byte: a call
+    /// prepares the key with one pairing and verifies with a multi-pairing of
+    /// three.
+    fn default() -> Self {
+        GasSchedule {
+            base: 400_000,
+            per_input: 25_000,
+            per_byte: 10,
+        }
+    }
+}
+
+impl GasSchedule {
+    /// Returns the price of a call with `inputs` public inputs and `bytes`
+    /// bytes of arguments, saturating at `u64::MAX`.
+    pub fn price(&self, inputs: u64, bytes: u64) -> u64 {
+        self.base
+            .saturating_add(self.per_input.saturating_mul(inputs))
+            .saturating_add(self.per_byte.saturating_mul(bytes))
+    }
+}
+
+/// Bounds on the arguments of a call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostLimits {
+    /// The largest number of public inputs of a verifying key.
+    pub max_inputs: usize,
+}
+
+impl Default for HostLimits {
+    fn default() -> Self {
+        HostLimits { max_inputs: 256 }
+    }
+}
+
+/// The outcome of a call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub status: Status,
+    pub gas_used: u64,
+}
+
+/// Groth16 verification over `E` as a host function.
+#[derive(Clone, Debug)]
+pub struct HostVerifier<E: MultiMillerLoop> {
+    pub schedule: GasSchedule,
+    pub limits: HostLimits,
+    _engine: PhantomData<E>,
+}
+
+impl<E: MultiMillerLoop> Default for HostVerifier<E> {
+    fn default() -> Self {
+        Self::new(GasSchedule::default(), HostLimits::default())
+    }
+}
+
+impl<E: MultiMillerLoop> HostVerifier<E> {
+    pub fn new(schedule: GasSchedule, limits: HostLimits) -> Self {
+        HostVerifier {
+            schedule,
+            limits,
+            _engine: PhantomData,
+        }
+    }
+
+    /// Returns the price of a call with arguments of the given lengths.
+    pub fn price(&self, key: usize, proof: usize, inputs: usize) -> u64 {
+        let repr = <E::Fr as PrimeField>::Repr::default().as_ref().len();
+        let bytes = (key as u64)
+            .saturating_add(proof as u64)
+            .saturating_add(inputs as u64);
+        self.schedule
+            .price(((inputs + repr - 1) / repr) as u64, bytes)
+    }
+
+    /// Verifies `proof` for the public `inputs` against the verifying
+    /// `key`, all in [format version 1](super::strict), with at most
+    /// `gas_limit` gas.
+    ///
+    /// A call that costs more than `gas_limit` uses all of it, and any other
+    /// call uses its [price](Self::price), whatever its status.
+    pub fn verify(&self, gas_limit: u64, key: &[u8], proof: &[u8], inputs: &[u8]) -> Outcome {
+        let price = self.price(key.len(), proof.len(), inputs.len());
+        if price > gas_limit {
+            return Outcome {
+                status: Status::OutOfGas,
+                gas_used: gas_limit,
+            };
+        }
+        let outcome = |status| Outcome {
+            status,
+            gas_used: price,
+        };
+
+        let repr = <E::Fr as PrimeField>::Repr::default().as_ref().len();
+        let max = self.limits.max_inputs;
+        if key.len() > key_len::<E>(max + 1)
+            || proof.len() > proof_len::<E>()
+            || inputs.len() > max * repr
+        {
+            return outcome(Status::LimitExceeded);
+        }
+
+        let verifier = match StrictVerifier::<E>::new(FORMAT_VERSION, key) {
+            Ok(verifier) => verifier,
+            Err(_) => return outcome(Status::MalformedKey),
+        };
+        outcome(match verifier.verify(proof, inputs) {
+            Ok(()) => Status::Valid,
+            Err(StrictError::Rejected(VerificationError::InvalidProof)) => Status::Invalid,
+            Err(StrictError::Length { encoding, .. })
+            | Err(StrictError::Invalid { encoding, .. })
+            | Err(StrictError::NonCanonical { encoding, .. })
+            | Err(StrictError::Identity { encoding, .. }) => match encoding {
+                Encoding::VerifyingKey => Status::MalformedKey,
+                Encoding::Proof => Status::MalformedProof,
+                Encoding::Inputs => Status::MalformedInputs,
+            },
+            // The inputs were decoded for the key, so any other error is
+            // that of the inputs.
+            Err(_) => Status::MalformedInputs,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "groth16"))]
+mod tests {
+    use super::*;
+    use crate::groth16::vectors::generate;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn host_verifier() {
+        let vectors = generate::<Bls12>(b"host").unwrap();
+        let vector = vectors
+            .iter()
+            .find(|v| v.expected && !v.inputs.is_empty())
+            .unwrap();
+        let mut key = vec![];
+        vector.params.vk.write(&mut key).unwrap();
+        let mut proof = vec![];
+        vector.proof.write(&mut proof).unwrap();
+        let inputs = vector
+            .inputs
+            .iter()
+            .flat_map(|input| input.to_repr().as_ref().to_vec())
+            .collect::<Vec<_>>();
+
+        let host = HostVerifier::<Bls12>::default();
+        let price = host.price(key.len(), proof.len(), inputs.len());
+        assert_eq!(
+            price,
+            400_000
+                + 25_000 * vector.inputs.len() as u64
+                + 10 * (key.len() + proof.len() + inputs.len()) as u64
+        );
+
+        let valid = host.verify(price, &key, &proof, &inputs);
+        assert_eq!(
+            valid,
+            Outcome {
+                status: Status::Valid,
+                gas_used: price
+            }
+        );
+        assert_eq!(Status::from_code(valid.status.code()), Some(Status::Valid));
+
+        assert_eq!(
+            host.verify(price - 1, &key, &proof, &inputs),
+            Outcome {
+                status: Status::OutOfGas,
+                gas_used: price - 1
+            }
+        );
+
+        // Failures cost the same as successes.
+        let mut wrong = inputs.clone();
+        wrong[0] ^= 1;
+        assert_eq!(
+            host.verify(price, &key, &proof, &wrong),
+            Outcome {
+                status: Status::Invalid,
+                gas_used: price
+            }
+        );
+        assert_eq!(
+            host.verify(u64::MAX, &key[1..], &proof, &inputs).status,
+            Status::MalformedKey
+        );
+        assert_eq!(
+            host.verify(u64::MAX, &key, &proof[1..], &inputs).status,
+            Status::MalformedProof
+        );
+        assert_eq!(
+            host.verify(u64::MAX, &key, &proof, &inputs[1..]).status,
+            Status::MalformedInputs
+        );
+
+        let strict = HostVerifier::<Bls12>::new(
+            GasSchedule::default(),
+            HostLimits {
+                max_inputs: vector.inputs.len() - 1,
+            },
+        );
+        assert_eq!(
+            strict.verify(u64::MAX, &key, &proof, &inputs).status,
+            Status::LimitExceeded
+        );
+        assert_eq!(Status::from_code(7), None);
+    }
+}
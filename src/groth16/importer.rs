@@ -0,0 +1,312 @@
+//! Circuits compiled by other tools, imported from the binary formats of
+//! circom and snarkjs.
+//!
+//! [`read_r1cs`] reads the constraints of a `.r1cs` file into a
+//! [`RawCircuit`], and [`read_wtns`] the matching `.wtns` witness into an
+//! [`Assignment`]. Together, as the [`ReplayCircuit`] returned by
+//! [`import`], they go through parameter generation, the prover and the
+//! verifier of this crate like any other circuit.
+//!
+//! Wires are numbered as in [`RawCircuit::write_r1cs`]: wire 0 is `ONE`,
+//! then come the public outputs and the public inputs, which are the public
+//! inputs of the circuit in that order, and every other wire is an
+//! auxiliary variable. The private inputs of circom are not distinguished
+//! from its internal wires.
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use ff::PrimeField;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+
+use super::exporter::{from_little_endian, Assignment, R1CSExport, RawCircuit, ReplayCircuit};
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Reads a file of the binary formats of circom, checking its magic and
+/// version, and returns its sections by type. A section that appears twice
+/// is rejected.
+fn read_sections<R: Read>(
+    mut reader: R,
+    magic: &[u8; 4],
+    version: u32,
+) -> io::Result<BTreeMap<u32, Vec<u8>>> {
+    let mut found = [0; 4];
+    reader.read_exact(&mut found)?;
+    if &found != magic {
+        return Err(invalid_data("unexpected file type"));
+    }
+    if reader.read_u32::<LittleEndian>()? != version {
+        return Err(invalid_data("unsupported version"));
+    }
+
+    let mut sections = BTreeMap::new();
+    for _ in 0..reader.read_u32::<LittleEndian>()? {
+        let kind = reader.read_u32::<LittleEndian>()?;
+        let len = reader.read_u64::<LittleEndian>()?;
+        // Not preallocated, so that a corrupted length fails at the end of
+        // the data rather than on allocation.
+        let mut section = vec![];
+        (&mut reader).take(len).read_to_end(&mut section)?;
+        if (section.len() as u64) < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if sections.insert(kind, section).is_some() {
+            return Err(invalid_data("duplicate section"));
+        }
+    }
+    Ok(sections)
+}
+
+/// Returns the section of type `kind`, or fails.
+fn section(sections: &BTreeMap<u32, Vec<u8>>, kind: u32) -> io::Result<&[u8]> {
+    sections
+        .get(&kind)
+        .map(|section| &section[..])
+        .ok_or_else(|| invalid_data("missing section"))
+}
+
+/// Reads the start of a header, the size and modulus of the field, and
+/// checks that they are those of `Scalar`.
+fn read_field<Scalar: PrimeField, R: Read>(reader: &mut R) -> io::Result<()> {
+    let mut modulus = R1CSExport::<Scalar>::field_modulus();
+    modulus.reverse();
+
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+    let mut prime = vec![0; std::cmp::min(len, modulus.len() + 1)];
+    reader.read_exact(&mut prime)?;
+    if len != modulus.len() || prime != modulus {
+        return Err(invalid_data("file is for another field"));
+    }
+    Ok(())
+}
+
+fn read_element<Scalar: PrimeField, R: Read>(reader: &mut R) -> io::Result<Scalar> {
+    let mut bytes = vec![0; Scalar::Repr::default().as_ref().len()];
+    reader.read_exact(&mut bytes)?;
+    Scalar::from_repr(from_little_endian::<Scalar>(&bytes))
+        .ok_or_else(|| invalid_data("invalid scalar"))
+}
+
+/// Reads the constraints of a circuit from version 1 of the binary `.r1cs`
+/// format of circom and snarkjs. Sections other than the header and the
+/// constraints, such as the labels of the wires, are ignored.
+pub fn read_r1cs<Scalar: PrimeField, R: Read>(reader: R) -> io::Result<RawCircuit<Scalar>> {
+    let sections = read_sections(reader, b"r1cs", 1)?;
+
+    let mut header = section(&sections, 1)?;
+    read_field::<Scalar, _>(&mut header)?;
+    let num_wires = header.read_u32::<LittleEndian>()? as usize;
+    let num_outputs = header.read_u32::<LittleEndian>()? as usize;
+    let num_public = header.read_u32::<LittleEndian>()? as usize;
+    let _num_private = header.read_u32::<LittleEndian>()?;
+    let _num_labels = header.read_u64::<LittleEndian>()?;
+    let num_constraints = header.read_u32::<LittleEndian>()? as usize;
+
+    let num_inputs = 1 + num_outputs + num_public;
+    if num_inputs > num_wires {
+        return Err(invalid_data("more public wires than wires"));
+    }
+    let num_aux = num_wires - num_inputs;
+
+    let mut circuit = RawCircuit {
+        num_inputs,
+        num_aux,
+        num_constraints,
+        at_inputs: vec![vec![]; num_inputs],
+        bt_inputs: vec![vec![]; num_inputs],
+        ct_inputs: vec![vec![]; num_inputs],
+        at_aux: vec![vec![]; num_aux],
+        bt_aux: vec![vec![]; num_aux],
+        ct_aux: vec![vec![]; num_aux],
+    };
+
+    let mut constraints = section(&sections, 2)?;
+    for constraint in 0..num_constraints {
+        for m in 0..3 {
+            for _ in 0..constraints.read_u32::<LittleEndian>()? {
+                let wire = constraints.read_u32::<LittleEndian>()? as usize;
+                let coeff = read_element::<Scalar, _>(&mut constraints)?;
+                let column = match (m, wire) {
+                    (_, w) if w >= num_wires => return Err(invalid_data("wire out of range")),
+                    (0, w) if w < num_inputs => &mut circuit.at_inputs[w],
+                    (1, w) if w < num_inputs => &mut circuit.bt_inputs[w],
+                    (_, w) if w < num_inputs => &mut circuit.ct_inputs[w],
+                    (0, w) => &mut circuit.at_aux[w - num_inputs],
+                    (1, w) => &mut circuit.bt_aux[w - num_inputs],
+                    (_, w) => &mut circuit.ct_aux[w - num_inputs],
+                };
+                column.push((coeff, constraint));
+            }
+        }
+    }
+    if !constraints.is_empty() {
+        return Err(invalid_data("trailing data after the constraints"));
+    }
+
+    Ok(circuit)
+}
+
+/// Reads the witness of `circuit` from version 2 of the binary `.wtns`
+/// format of circom and snarkjs, which has the value of every wire.
+pub fn read_wtns<Scalar: PrimeField, R: Read>(
+    reader: R,
+    circuit: &RawCircuit<Scalar>,
+) -> io::Result<Assignment<Scalar>> {
+    let sections = read_sections(reader, b"wtns", 2)?;
+
+    let mut header = section(&sections, 1)?;
+    read_field::<Scalar, _>(&mut header)?;
+    let num_values = header.read_u32::<LittleEndian>()? as usize;
+    if num_values != circuit.num_inputs + circuit.num_aux {
+        return Err(invalid_data("witness is for another circuit"));
+    }
+
+    let mut witness = section(&sections, 2)?;
+    let mut values = (0..num_values)
+        .map(|_| read_element::<Scalar, _>(&mut witness))
+        .collect::<io::Result<Vec<_>>>()?;
+    if !witness.is_empty() {
+        return Err(invalid_data("trailing data after the witness"));
+    }
+    if values[0] != Scalar::one() {
+        return Err(invalid_data("wire 0 is not one"));
+    }
+
+    let aux = values.split_off(circuit.num_inputs);
+    Ok(Assignment {
+        inputs: values,
+        aux,
+    })
+}
+
+/// Reads a `.r1cs` file and the matching `.wtns` file into a circuit that
+/// can be proven with the witness.
+pub fn import<Scalar, R, W>(r1cs: R, wtns: W) -> io::Result<ReplayCircuit<Scalar>>
+where
+    Scalar: PrimeField,
+    R: Read,
+    W: Read,
+{
+    let circuit = read_r1cs(r1cs)?;
+    let assignment = read_wtns(wtns, &circuit)?;
+    Ok(ReplayCircuit {
+        circuit,
+        assignment: Some(assignment),
+    })
+}
+
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::exporter::export_witness;
+    use crate::groth16::tests::fixture::rng;
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    use bls12_381::{Bls12, Scalar};
+
+    /// Proves knowledge of the factors `a` and `b` of `n`, with `a^2` as a
+    /// second public input.
+    struct Factors {
+        a: Option<u64>,
+        b: Option<u64>,
+    }
+
+    impl Circuit<Scalar> for Factors {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let value =
+                |v: Option<u64>| v.map(Scalar::from).ok_or(SynthesisError::AssignmentMissing);
+            let n = cs.alloc_input(|| "n", || Ok(value(self.a)? * value(self.b)?))?;
+            let square = cs.alloc_input(|| "square", || Ok(value(self.a)?.square()))?;
+            let a = cs.alloc(|| "a", || value(self.a))?;
+            let b = cs.alloc(|| "b", || value(self.b))?;
+            cs.enforce(|| "a * b = n", |lc| lc + a, |lc| lc + b, |lc| lc + n);
+            cs.enforce(
+                || "a * a = square",
+                |lc| lc + a,
+                |lc| lc + a,
+                |lc| lc + square,
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn import_and_prove() {
+        let mut rng = rng();
+        let circuit = RawCircuit::synthesize(Factors { a: None, b: None }).unwrap();
+        let mut r1cs = vec![];
+        circuit.write_r1cs(&mut r1cs).unwrap();
+        let mut wtns = vec![];
+        export_witness(
+            Factors {
+                a: Some(3),
+                b: Some(7),
+            },
+            &mut wtns,
+        )
+        .unwrap();
+
+        assert_eq!(read_r1cs::<Scalar, _>(&r1cs[..]).unwrap(), circuit);
+        let imported = import::<Scalar, _, _>(&r1cs[..], &wtns[..]).unwrap();
+        assert!(imported
+            .assignment
+            .as_ref()
+            .unwrap()
+            .is_satisfied(&imported.circuit));
+
+        let params = generate_random_parameters::<Bls12, _, _>(
+            ReplayCircuit {
+                circuit: imported.circuit.clone(),
+                assignment: None,
+            },
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let proof = create_random_proof(imported, &params, &mut rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(21), Scalar::from(9)]).is_ok());
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(9), Scalar::from(21)]).is_err());
+    }
+
+    #[test]
+    fn malformed_imports() {
+        let circuit = RawCircuit::synthesize(Factors { a: None, b: None }).unwrap();
+        let mut r1cs = vec![];
+        circuit.write_r1cs(&mut r1cs).unwrap();
+        let mut wtns = vec![];
+        export_witness(
+            Factors {
+                a: Some(3),
+                b: Some(7),
+            },
+            &mut wtns,
+        )
+        .unwrap();
+
+        // The files are not interchangeable.
+        assert!(read_r1cs::<Scalar, _>(&wtns[..]).is_err());
+        assert!(read_wtns(&r1cs[..], &circuit).is_err());
+        assert!(read_r1cs::<Scalar, _>(&r1cs[..r1cs.len() - 1]).is_err());
+
+        // The header of each starts with the size of an element and the
+        // modulus of the field, whose lowest byte is at offset 28.
+        let mut other = r1cs.clone();
+        other[28] ^= 1;
+        assert!(read_r1cs::<Scalar, _>(&other[..]).is_err());
+
+        let mut smaller = RawCircuit::synthesize(Factors { a: None, b: None }).unwrap();
+        smaller.num_aux -= 1;
+        smaller.at_aux.pop();
+        smaller.bt_aux.pop();
+        smaller.ct_aux.pop();
+        assert!(read_wtns(&wtns[..], &smaller).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Checks on public inputs, before they reach the verifier.
+//!
+//! [`verify_proof`] takes the public inputs as field elements, and reports a
+//! wrong number of them as a malformed verifying key. Verifiers that receive
+//! inputs from untrusted parties can instead decode and check them with
+//! [`canonicalize_inputs`], which rejects encodings that are not canonical,
+//! inputs that do not match the count of the verifying key, and, given an
+//! [`InputManifest`] for the circuit, inputs wider than the circuit
+//! declared, all before any pairing is computed.
+//!
+//! [`verify_proof`]: super::verify_proof
+
+use ff::PrimeField;
+use pairing::MultiMillerLoop;
+use std::error::Error;
+use std::fmt;
+
+use super::PreparedVerifyingKey;
+
+/// The bit-length bounds of the public inputs of a circuit, in order, not
+/// counting the constant input `ONE`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputManifest {
+    bounds: Vec<Option<u32>>,
+}
+
+impl InputManifest {
+    /// A manifest where input `i` must be less than `2^bits` if
+    /// `bounds[i]` is `Some(bits)`, and may be any field element otherwise.
+    pub fn new(bounds: Vec<Option<u32>>) -> Self {
+        InputManifest { bounds }
+    }
+
+    /// A manifest of `count` unbounded inputs.
+    pub fn unbounded(count: usize) -> Self {
+        InputManifest::new(vec![None; count])
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    pub fn bounds(&self) -> &[Option<u32>] {
+        &self.bounds
+    }
+}
+
+/// Why public inputs were rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputError {
+    /// The verifying key, or the manifest, expects another number of inputs.
+    Count { expected: usize, actual: usize },
+    /// The encoding of this input is not less than the modulus.
+    NonCanonical { index: usize },
+    /// This input is not less than `2^bits`.
+    OutOfBounds { index: usize, bits: u32 },
+}
+
+impl Error for InputError {}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Count { expected, actual } => {
+                write!(f, "expected {} public inputs, got {}", expected, actual)
+            }
+            InputError::NonCanonical { index } => {
+                write!(f, "public input {} is not canonically encoded", index)
+            }
+            InputError::OutOfBounds { index, bits } => {
+                write!(f, "public input {} does not fit in {} bits", index, bits)
+            }
+        }
+    }
+}
+
+impl From<InputError> for crate::error::Error {
+    fn from(e: InputError) -> Self {
+        crate::error::Error::new(crate::error::ErrorKind::MalformedInputs, e)
+    }
+}
+
+/// Checks the number of `inputs` against `pvk` and, if given, their count
+/// and bounds against `manifest`.
+pub fn check_inputs<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    inputs: &[E::Fr],
+    manifest: Option<&InputManifest>,
+) -> Result<(), InputError> {
+    let expected = pvk.ic.len() - 1;
+    if inputs.len() != expected {
+        return Err(InputError::Count {
+            expected,
+            actual: inputs.len(),
+        });
+    }
+
+    if let Some(manifest) = manifest {
+        if manifest.len() != inputs.len() {
+            return Err(InputError::Count {
+                expected: manifest.len(),
+                actual: inputs.len(),
+            });
+        }
+
+        for (index, (input, bound)) in inputs.iter().zip(manifest.bounds()).enumerate() {
+            if let Some(bits) = *bound {
+                let fits = input
+                    .to_le_bits()
+                    .iter()
+                    .skip(bits as usize)
+                    .all(|bit| !*bit);
+                if !fits {
+                    return Err(InputError::OutOfBounds { index, bits });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes `inputs` from their canonical representations,
+/// and checks them with [`check_inputs`].
+pub fn canonicalize_inputs<E: MultiMillerLoop>(
+    pvk: &PreparedVerifyingKey<E>,
+    inputs: &[<E::Fr as PrimeField>::Repr],
+    manifest: Option<&InputManifest>,
+) -> Result<Vec<E::Fr>, InputError> {
+    let expected = pvk.ic.len() - 1;
+    if inputs.len() != expected {
+        return Err(InputError::Count {
+            expected,
+            actual: inputs.len(),
+        });
+    }
+
+    let inputs = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, repr)| {
+            let mut copy = <E::Fr as PrimeField>::Repr::default();
+            copy.as_mut().copy_from_slice(repr.as_ref());
+            E::Fr::from_repr(copy).ok_or(InputError::NonCanonical { index })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    check_inputs(pvk, &inputs, manifest)?;
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::verify_proof;
+    use bls12_381::{Bls12, Scalar};
+
+    #[test]
+    fn rejects_malformed_inputs() {
+        let mut f = Fixture::<Bls12>::new();
+        let proof = f.proof();
+        let (pvk, inputs) = (&f.pvk, f.inputs());
+
+        let reprs = inputs.iter().map(|s| s.to_repr()).collect::<Vec<_>>();
+        let decoded = canonicalize_inputs(pvk, &reprs, None).unwrap();
+        assert!(verify_proof(pvk, &proof, &decoded).is_ok());
+
+        assert_eq!(
+            canonicalize_inputs(pvk, &reprs[1..], None),
+            Err(InputError::Count {
+                expected: inputs.len(),
+                actual: inputs.len() - 1
+            })
+        );
+
+        // The modulus itself is not canonical.
+        let mut modulus = reprs.clone();
+        let mut repr = (-Scalar::one()).to_repr();
+        repr[0] += 1;
+        modulus[0] = repr;
+        assert_eq!(
+            canonicalize_inputs(pvk, &modulus, None),
+            Err(InputError::NonCanonical { index: 0 })
+        );
+
+        let bounds = inputs.iter().map(|_| Some(1)).collect::<Vec<_>>();
+        let manifest = InputManifest::new(bounds);
+        let mut small = inputs.to_vec();
+        assert!(matches!(
+            check_inputs(pvk, &small, Some(&manifest)),
+            Err(InputError::OutOfBounds { bits: 1, .. })
+        ));
+        for (i, input) in small.iter_mut().enumerate() {
+            *input = Scalar::from((i % 2) as u64);
+        }
+        assert!(check_inputs(pvk, &small, Some(&manifest)).is_ok());
+        assert_eq!(
+            check_inputs(pvk, &small, Some(&InputManifest::unbounded(0))),
+            Err(InputError::Count {
+                expected: 0,
+                actual: small.len()
+            })
+        );
+    }
+}
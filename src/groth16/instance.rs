@@ -0,0 +1,149 @@
+//! A prover kept warm between proofs.
+//!
+//! A service that proves the same circuit over and over should not pay, for
+//! every proof, to read and check the parameters and to start a pool of
+//! threads. A [`ProverInstance`] does both once, when it is created, and
+//! then serves any number of [`prove`](ProverInstance::prove) calls, from
+//! any number of threads, on the same parameters and the same pool.
+//!
+//! The parameters are held behind an [`Arc`], so an instance can share them
+//! with a [`ParamsCache`](super::cache::ParamsCache) or with other
+//! instances. The FFTs of the prover derive their roots of unity from the
+//! size of each proof's domain, which costs a few squarings, so there is no
+//! table to precompute; the multiexponentiations read the queries of the
+//! parameters in place.
+
+use ff::Field;
+use pairing::Engine;
+use rand_core::RngCore;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::prover::create_proof_on;
+use super::{Parameters, Proof};
+use crate::multicore::Worker;
+use crate::zeroize::Secret;
+use crate::{Circuit, SynthesisError};
+
+/// Parameters loaded once, and a pool of threads, serving proofs.
+pub struct ProverInstance<E: Engine> {
+    params: Arc<Parameters<E>>,
+    worker: Worker,
+    proofs: AtomicU64,
+}
+
+impl<E: Engine> ProverInstance<E> {
+    pub fn new(params: Arc<Parameters<E>>) -> Self {
+        ProverInstance {
+            params,
+            worker: Worker::new(),
+            proofs: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads the parameters with [`Parameters::read`], checking their points
+    /// once for all the proofs of the instance.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        Ok(Self::new(Arc::new(Parameters::read(reader, true)?)))
+    }
+
+    pub fn params(&self) -> &Arc<Parameters<E>> {
+        &self.params
+    }
+
+    /// Returns the number of proofs the instance has created.
+    pub fn proofs(&self) -> u64 {
+        self.proofs.load(Ordering::Relaxed)
+    }
+
+    /// Creates a proof of `circuit` with random blinding factors, like
+    /// [`create_random_proof`](super::create_random_proof).
+    pub fn prove<C, R>(&self, circuit: C, rng: &mut R) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E::Fr>,
+        R: RngCore,
+    {
+        let r = Secret::new(E::Fr::random(&mut *rng), E::Fr::zero());
+        let s = Secret::new(E::Fr::random(&mut *rng), E::Fr::zero());
+        self.prove_with(circuit, *r, *s)
+    }
+
+    /// Creates a proof of `circuit` with the blinding factors `r` and `s`,
+    /// like [`create_proof`](super::create_proof).
+    pub fn prove_with<C>(&self, circuit: C, r: E::Fr, s: E::Fr) -> Result<Proof<E>, SynthesisError>
+    where
+        C: Circuit<E::Fr>,
+    {
+        let proof = create_proof_on(&self.worker, circuit, &*self.params, r, s)?;
+        self.proofs.fetch_add(1, Ordering::Relaxed);
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::rng;
+    use crate::groth16::{
+        create_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use crate::ConstraintSystem;
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    struct Square(Option<u64>);
+
+    impl Circuit<Scalar> for Square {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = self.0.map(Scalar::from);
+            let a = cs.alloc(|| "x", || x.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc_input(
+                || "x^2",
+                || {
+                    x.map(|x| x.square())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?;
+            cs.enforce(|| "x * x = x^2", |lc| lc + a, |lc| lc + a, |lc| lc + b);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn warm_prover() {
+        let mut rng = rng();
+        let params = generate_random_parameters::<Bls12, _, _>(Square(None), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+
+        let instance = Arc::new(ProverInstance::<Bls12>::read(&bytes[..]).unwrap());
+        let (r, s) = (Scalar::from(5), Scalar::from(7));
+        assert!(
+            instance.prove_with(Square(Some(3)), r, s).unwrap()
+                == create_proof(Square(Some(3)), &params, r, s).unwrap()
+        );
+
+        // Proofs are served concurrently with the same parameters.
+        let threads = (0..4u64)
+            .map(|i| {
+                let instance = instance.clone();
+                std::thread::spawn(move || {
+                    let mut rng = XorShiftRng::seed_from_u64(i);
+                    instance.prove(Square(Some(i)), &mut rng).unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        for (i, thread) in threads.into_iter().enumerate() {
+            let proof = thread.join().unwrap();
+            let square = Scalar::from(i as u64).square();
+            assert!(verify_proof(&pvk, &proof, &[square]).is_ok());
+        }
+        assert_eq!(instance.proofs(), 5);
+    }
+}
@@ -0,0 +1,382 @@
+//! Parameters read from their file as the prover uses them.
+//!
+//! [`Parameters::read`] holds every point of the proving key in memory,
+//! which for circuits of tens of millions of constraints is tens of
+//! gigabytes. [`LazyParameters::open`] reads the verifying key and where each
+//! query lies in the file, checking the points of the queries a chunk at a
+//! time. As a [`ParameterSource`], it then gives the prover bases that are
+//! read from the file and decoded as the multiexponentiations consume them,
+//! through a buffer per reader, so that the memory used by proving is that
+//! of the witness and evaluation domains rather than that of the parameters.
+//!
+//! Each window of a multiexponentiation reads its query again, so proving
+//! reads the file several times, mostly from the page cache. The points are
+//! only checked when the file is opened, so it must not change while the
+//! parameters are in use.
+//!
+//! [`Parameters::read`]: super::Parameters::read
+
+use byteorder::{BigEndian, ReadBytesExt};
+use group::{prime::PrimeCurveAffine, UncompressedEncoding};
+use pairing::Engine;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::cost_model::CircuitStats;
+use super::{decode_points, read_header, ParameterSource, VerifyingKey};
+use crate::error::{attribute, Context, ErrorKind, Tracked};
+use crate::multicore::Worker;
+use crate::multiexp::{Source, SourceBuilder};
+use crate::SynthesisError;
+
+/// The number of points checked at a time when opening parameters.
+const CHUNK: usize = 1 << 16;
+
+/// Where a query lies in the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Query {
+    offset: u64,
+    len: usize,
+}
+
+/// Groth16 parameters, as written by [`Parameters::write`], whose queries
+/// stay in their file.
+///
+/// [`Parameters::write`]: super::Parameters::write
+pub struct LazyParameters<E: Engine> {
+    path: Arc<PathBuf>,
+    vk: VerifyingKey<E>,
+    h: Query,
+    l: Query,
+    a: Query,
+    b_g1: Query,
+    b_g2: Query,
+    stats: Option<CircuitStats>,
+}
+
+impl<E: Engine> LazyParameters<E> {
+    /// Opens the parameters in the file at `path`. Every point is decoded
+    /// once, and rejected like [`Parameters::read`] does; if `checked`, the
+    /// points are also checked to be in the prime-order subgroup.
+    ///
+    /// [`Parameters::read`]: super::Parameters::read
+    pub fn open<P: AsRef<Path>>(path: P, checked: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let worker = Worker::new();
+        let (stats, mut reader) = read_header(BufReader::new(File::open(&path)?))?;
+        let kind = ErrorKind::MalformedParameters;
+
+        let vk = reader.within(kind, Context::Section("vk"), |r| VerifyingKey::<E>::read(r))?;
+
+        let mut query = |name, invalid, g2| {
+            reader.within(kind, Context::Section(name), |r| {
+                if g2 {
+                    index::<E::G2Affine, _>(r, &worker, kind, checked, invalid)
+                } else {
+                    index::<E::G1Affine, _>(r, &worker, kind, checked, invalid)
+                }
+            })
+        };
+        let h = query("h", "invalid G1", false)?;
+        let l = query("l", "invalid G1", false)?;
+        let a = query("a", "invalid G1", false)?;
+        let b_g1 = query("b_g1", "invalid G1", false)?;
+        let b_g2 = query("b_g2", "invalid G2", true)?;
+
+        Ok(LazyParameters {
+            path: Arc::new(path),
+            vk,
+            h,
+            l,
+            a,
+            b_g1,
+            b_g2,
+            stats,
+        })
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    /// Returns the statistics of the circuit, if the file has them.
+    pub fn stats(&self) -> Option<&CircuitStats> {
+        self.stats.as_ref()
+    }
+
+    /// Returns the bases of `query` from the element `skip` on.
+    fn bases<G>(&self, query: Query, skip: usize) -> LazyBases<G>
+    where
+        G: PrimeCurveAffine + UncompressedEncoding,
+    {
+        let skip = skip.min(query.len);
+        LazyBases {
+            path: self.path.clone(),
+            offset: query.offset + (skip * size::<G>()) as u64,
+            len: query.len - skip,
+            _point: PhantomData,
+        }
+    }
+}
+
+fn size<G: UncompressedEncoding>() -> usize {
+    G::Uncompressed::default().as_ref().len()
+}
+
+/// Reads the length of a query, and checks its points a chunk at a time.
+fn index<G, R>(
+    reader: &mut Tracked<R>,
+    worker: &Worker,
+    kind: ErrorKind,
+    checked: bool,
+    invalid: &'static str,
+) -> io::Result<Query>
+where
+    G: PrimeCurveAffine + UncompressedEncoding,
+    R: Read,
+{
+    let len = reader.read_u32::<BigEndian>()? as usize;
+    let size = size::<G>();
+    let offset = reader.offset();
+
+    let mut bytes = vec![];
+    for first in (0..len).step_by(CHUNK) {
+        let n = CHUNK.min(len - first);
+        let start = offset + (first * size) as u64;
+        bytes.clear();
+        reader.take((n * size) as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < n * size {
+            let i = bytes.len() / size;
+            let e = io::Error::new(io::ErrorKind::UnexpectedEof, "truncated query");
+            return Err(attribute(
+                e,
+                kind,
+                start + (i * size) as u64,
+                Context::Element(first + i),
+            ));
+        }
+        decode_points::<G>(&bytes, worker, kind, checked, invalid, start, first)?;
+    }
+
+    Ok(Query { offset, len })
+}
+
+/// The bases of a query, read from the file of [`LazyParameters`].
+pub struct LazyBases<G> {
+    path: Arc<PathBuf>,
+    offset: u64,
+    len: usize,
+    _point: PhantomData<fn() -> G>,
+}
+
+impl<G> Clone for LazyBases<G> {
+    fn clone(&self) -> Self {
+        LazyBases {
+            path: self.path.clone(),
+            offset: self.offset,
+            len: self.len,
+            _point: PhantomData,
+        }
+    }
+}
+
+impl<G: PrimeCurveAffine + UncompressedEncoding> SourceBuilder<G> for LazyBases<G> {
+    type Source = LazyReader<G>;
+
+    fn new(self) -> LazyReader<G> {
+        let reader = File::open(&*self.path).and_then(|mut file| {
+            file.seek(SeekFrom::Start(self.offset))?;
+            Ok(BufReader::new(file))
+        });
+        LazyReader {
+            reader: reader.map_err(Some),
+            remaining: self.len,
+            point: G::identity(),
+        }
+    }
+
+    fn num_bases(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// A reader of [`LazyBases`], which decodes one base at a time.
+pub struct LazyReader<G> {
+    /// The file, or the error opening it, until it is reported.
+    reader: Result<BufReader<File>, Option<io::Error>>,
+    remaining: usize,
+    point: G,
+}
+
+impl<G: PrimeCurveAffine + UncompressedEncoding> LazyReader<G> {
+    fn reader(&mut self) -> Result<&mut BufReader<File>, SynthesisError> {
+        if self.remaining == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "expected more bases from source",
+            )
+            .into());
+        }
+        match &mut self.reader {
+            Ok(reader) => Ok(reader),
+            Err(e) => Err(e
+                .take()
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "cannot open parameters"))
+                .into()),
+        }
+    }
+}
+
+impl<G: PrimeCurveAffine + UncompressedEncoding> Source<G> for LazyReader<G> {
+    fn next(&mut self) -> Result<&G, SynthesisError> {
+        let mut repr = G::Uncompressed::default();
+        self.reader()?.read_exact(repr.as_mut())?;
+        self.remaining -= 1;
+
+        // The points were checked when the parameters were opened.
+        let point: Option<G> = G::from_uncompressed_unchecked(&repr).into();
+        match point {
+            Some(point) if bool::from(!point.is_identity()) => {
+                self.point = point;
+                Ok(&self.point)
+            }
+            Some(_) => Err(SynthesisError::UnexpectedIdentity),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid point").into()),
+        }
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        let amt = amt.min(self.remaining);
+        let size = size::<G>();
+        self.reader()?
+            .seek(SeekFrom::Current((amt * size) as i64))?;
+        self.remaining -= amt;
+        Ok(())
+    }
+}
+
+impl<E: Engine> ParameterSource<E> for &LazyParameters<E> {
+    type G1Builder = LazyBases<E::G1Affine>;
+    type G2Builder = LazyBases<E::G2Affine>;
+
+    fn get_vk(&mut self, _: usize) -> Result<VerifyingKey<E>, SynthesisError> {
+        Ok(self.vk.clone())
+    }
+
+    fn get_h(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
+        Ok(self.bases(self.h, 0))
+    }
+
+    fn get_l(&mut self, _: usize) -> Result<Self::G1Builder, SynthesisError> {
+        Ok(self.bases(self.l, 0))
+    }
+
+    fn get_a(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        Ok((self.bases(self.a, 0), self.bases(self.a, num_inputs)))
+    }
+
+    fn get_b_g1(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        Ok((self.bases(self.b_g1, 0), self.bases(self.b_g1, num_inputs)))
+    }
+
+    fn get_b_g2(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
+        Ok((self.bases(self.b_g2, 0), self.bases(self.b_g2, num_inputs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::rng;
+    use crate::groth16::{
+        create_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use crate::{Circuit, ConstraintSystem};
+    use bls12_381::{Bls12, Scalar};
+    use ff::Field;
+    use std::io::Write;
+
+    /// Proves knowledge of the cube root of a public input.
+    struct Cube(Option<Scalar>);
+
+    impl Circuit<Scalar> for Cube {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let value = |f: fn(Scalar) -> Scalar| {
+                let x = self.0;
+                move || x.map(f).ok_or(SynthesisError::AssignmentMissing)
+            };
+            let x = cs.alloc(|| "x", value(|x| x))?;
+            let x2 = cs.alloc(|| "x^2", value(|x| x.square()))?;
+            let x3 = cs.alloc_input(|| "x^3", value(|x| x.square() * x))?;
+            cs.enforce(|| "square", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            cs.enforce(|| "cube", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lazy_parameters() {
+        let mut rng = rng();
+        let params = generate_random_parameters::<Bls12, _, _>(Cube(None), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let path = std::env::temp_dir().join(format!("bellman-lazy-params-{}", std::process::id()));
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let lazy = LazyParameters::<Bls12>::open(&path, true).unwrap();
+        assert!(lazy.vk() == &params.vk);
+
+        // The same blinding factors give the same proof as from memory.
+        let x = Scalar::from(3);
+        let (r, s) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let proof = create_proof(Cube(Some(x)), &lazy, r, s).unwrap();
+        assert!(proof == create_proof(Cube(Some(x)), &params, r, s).unwrap());
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(27)]).is_ok());
+
+        // Corrupted and truncated files are rejected when opened, with the
+        // element at fault.
+        let vk_len = {
+            let mut vk = vec![];
+            params.vk.write(&mut vk).unwrap();
+            vk.len()
+        };
+        let described = |bytes: &[u8]| {
+            std::fs::File::create(&path)
+                .unwrap()
+                .write_all(bytes)
+                .unwrap();
+            let e = LazyParameters::<Bls12>::open(&path, true).err().unwrap();
+            crate::error::Error::from(e).to_string()
+        };
+        let mut corrupted = bytes.clone();
+        corrupted[vk_len + 4 + 96 + 10] ^= 1;
+        assert!(described(&corrupted).contains("element 1"));
+        assert!(described(&bytes[..bytes.len() - 1]).contains("truncated query"));
+
+        // A file that goes away after opening fails the proof.
+        std::fs::write(&path, &bytes).unwrap();
+        let lazy = LazyParameters::<Bls12>::open(&path, false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(create_proof(Cube(Some(x)), &lazy, r, s).is_err());
+    }
+}
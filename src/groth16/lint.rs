@@ -0,0 +1,903 @@
+//! Cross-checks of the files a proof is made from.
+//!
+//! Most proofs that do not verify were made from files that do not belong
+//! together: parameters for another version of the circuit or for another
+//! curve, a witness exported without the constant `ONE` input or in
+//! Montgomery form, a verifying key where the parameters were expected, or
+//! a download cut short. [`lint`] reads the circuit, witness and parameters
+//! files of a proof, as far as each is given, and checks them against each
+//! other and against the fingerprints the operator expects:
+//!
+//! - each file is complete, and is not a bundle, an envelope or another of
+//!   the containers of the crate;
+//! - the witness values are canonical field elements, the first being
+//!   `ONE`, and the witness has as many inputs and auxiliary variables as
+//!   the circuit, and satisfies it;
+//! - the parameters are laid out for the points of the engine, and each of
+//!   their queries has as many points as keygen makes for the circuit;
+//! - the fingerprint of the circuit and the hash of the verifying key are
+//!   the expected ones.
+//!
+//! Only counts, lengths and headers are read, and the constraints
+//! evaluated, so that the files are checked before any point is decoded.
+//! Each problem is reported as a [`Diagnostic`], with a hint at its likeliest
+//! cause.
+
+use byteorder::{BigEndian, ByteOrder};
+use ff::PrimeField;
+use group::{GroupEncoding, UncompressedEncoding};
+use pairing::Engine;
+use std::fmt;
+
+use super::exporter::{Assignment, RawCircuit};
+use super::structure::query_lengths;
+use super::{audit, bundle, delta, encrypted, envelope, provenance, sealed, vectors};
+use crate::domain::{domain_size, Radix};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The files cannot make a proof that verifies.
+    Error,
+    /// The files are suspicious, but may work.
+    Warning,
+}
+
+/// A problem found by [`lint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The name of the check, such as `input-count`, for tools to match on.
+    pub code: &'static str,
+    /// What is wrong.
+    pub message: String,
+    /// What probably caused it, and how to fix it.
+    pub hint: String,
+}
+
+/// The outcome of [`lint`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// Returns whether no check failed with an error.
+    pub fn is_ok(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Returns the diagnostics that are errors.
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Returns whether a check with the given code failed.
+    pub fn has(&self, code: &str) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.code == code)
+    }
+
+    fn push(&mut self, severity: Severity, code: &'static str, message: String, hint: &str) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            message,
+            hint: hint.to_string(),
+        });
+    }
+
+    fn error(&mut self, code: &'static str, message: String, hint: &str) {
+        self.push(Severity::Error, code, message, hint);
+    }
+
+    fn warning(&mut self, code: &'static str, message: String, hint: &str) {
+        self.push(Severity::Warning, code, message, hint);
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.diagnostics.is_empty() {
+            return writeln!(f, "no problems found");
+        }
+        for diagnostic in &self.diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            writeln!(
+                f,
+                "{}[{}]: {}",
+                severity, diagnostic.code, diagnostic.message
+            )?;
+            writeln!(f, "  hint: {}", diagnostic.hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// The files to check, and what they are expected to be. The checks that
+/// need a file which is not given are skipped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LintInputs<'a> {
+    /// A circuit written by [`RawCircuit::write`].
+    pub circuit: Option<&'a [u8]>,
+    /// A witness written by [`Assignment::write`].
+    pub witness: Option<&'a [u8]>,
+    /// Parameters written by [`Parameters::write`](super::Parameters::write).
+    pub parameters: Option<&'a [u8]>,
+    /// The [fingerprint](RawCircuit::fingerprint) the circuit should have.
+    pub circuit_fingerprint: Option<[u8; 32]>,
+    /// The [hash](super::VerifyingKey::hash) the verifying key of the
+    /// parameters should have.
+    pub vk_hash: Option<[u8; 32]>,
+}
+
+/// Checks the files of `inputs` for a proof over `E`, alone and against
+/// each other.
+pub fn lint<E: Engine>(inputs: &LintInputs<'_>) -> LintReport {
+    let mut report = LintReport::default();
+
+    let circuit = inputs
+        .circuit
+        .and_then(|bytes| read_circuit::<E::Fr>(&mut report, bytes, inputs.circuit_fingerprint));
+    let witness = inputs
+        .witness
+        .and_then(|bytes| read_witness::<E::Fr>(&mut report, bytes));
+    let layout = inputs
+        .parameters
+        .and_then(|bytes| read_parameters::<E>(&mut report, bytes, inputs.vk_hash));
+
+    if let (Some(circuit), Some(witness)) = (&circuit, &witness) {
+        check_witness_fit(&mut report, circuit, witness);
+    }
+    match (&circuit, &witness, &layout) {
+        (Some(circuit), _, Some(layout)) => check_parameters_fit(&mut report, circuit, layout),
+        (None, Some(witness), Some(layout)) if layout.ic != witness.inputs.len() => report.error(
+            "input-count",
+            format!(
+                "the witness has {} inputs, counting ONE, but the parameters have an ic \
+                 point for each of {}",
+                witness.inputs.len(),
+                layout.ic
+            ),
+            missing_one_hint(witness, layout.ic),
+        ),
+        _ => {}
+    }
+
+    report
+}
+
+/// The files of the crate that are mistaken for circuits, witnesses or
+/// parameters, with what to do with them instead.
+const CONTAINERS: &[(&[u8], &str, &str)] = &[
+    (
+        bundle::MAGIC,
+        "a bundle",
+        "load the parameters of one of its entries with Bundle::read",
+    ),
+    (
+        delta::MAGIC,
+        "a parameters delta",
+        "apply it to the parameters it was made from with ParameterDelta::apply",
+    ),
+    (
+        encrypted::MAGIC,
+        "encrypted parameters",
+        "decrypt them with Parameters::read_encrypted and the key they were written with",
+    ),
+    (
+        envelope::MAGIC,
+        "a proof envelope",
+        "envelopes hold proofs; pass the parameters the proof is made with",
+    ),
+    (
+        provenance::MAGIC,
+        "a signed verifying key",
+        "it only holds the verifying key; pass the parameters the key was taken from",
+    ),
+    (
+        sealed::MAGIC,
+        "a sealed witness",
+        "prove it with SealedWitness::prove, which needs no witness file",
+    ),
+    (
+        audit::MAGIC,
+        "a keygen log",
+        "pass the parameters whose generation it records",
+    ),
+    (
+        vectors::MAGIC,
+        "a set of test vectors",
+        "test vectors are read by vectors::read_vectors",
+    ),
+];
+
+/// Reports `bytes` if they are one of the [`CONTAINERS`], and returns
+/// whether they are.
+fn check_container(report: &mut LintReport, file: &str, code: &'static str, bytes: &[u8]) -> bool {
+    let (magic, what, hint) = match CONTAINERS
+        .iter()
+        .find(|(magic, _, _)| bytes.starts_with(magic))
+    {
+        Some(container) => container,
+        None => return false,
+    };
+
+    let mut message = format!("the {} file is {}", file, what);
+    if *magic == bundle::MAGIC {
+        let version = bytes
+            .get(magic.len()..magic.len() + 4)
+            .map(BigEndian::read_u32);
+        if let Some(version) = version.filter(|version| *version != bundle::VERSION) {
+            message += &format!(
+                " of version {}, but this version of bellman reads version {}",
+                version,
+                bundle::VERSION
+            );
+        }
+    }
+    report.error(code, message, hint);
+    true
+}
+
+fn read_circuit<S: PrimeField>(
+    report: &mut LintReport,
+    bytes: &[u8],
+    fingerprint: Option<[u8; 32]>,
+) -> Option<RawCircuit<S>> {
+    if check_container(report, "circuit", "circuit-format", bytes) {
+        return None;
+    }
+
+    let mut rest = bytes;
+    let circuit = match RawCircuit::<S>::read(&mut rest) {
+        Ok(circuit) => circuit,
+        Err(e) => {
+            report.error(
+                "circuit-unreadable",
+                format!("the circuit does not read: {}", e),
+                "if another tool exported it, its coefficients may be in Montgomery form \
+                 or have negatives as a sign bit; read it with RawCircuit::read_with and \
+                 the matching Convention",
+            );
+            return None;
+        }
+    };
+    if !rest.is_empty() {
+        report.warning(
+            "circuit-trailing-bytes",
+            format!(
+                "the circuit file has {} bytes after the circuit",
+                rest.len()
+            ),
+            "the file may hold several circuits, or another file appended to it",
+        );
+    }
+
+    if let Some(expected) = fingerprint {
+        let actual = circuit.fingerprint();
+        if actual != expected {
+            report.error(
+                "circuit-fingerprint",
+                format!(
+                    "the circuit has fingerprint {}, not the expected {}",
+                    hex(&actual),
+                    hex(&expected)
+                ),
+                "the circuit was changed since the fingerprint was taken, or is another \
+                 circuit; parameters and proofs for the expected one do not fit it",
+            );
+        }
+    }
+    Some(circuit)
+}
+
+fn read_witness<S: PrimeField>(report: &mut LintReport, bytes: &[u8]) -> Option<Assignment<S>> {
+    if check_container(report, "witness", "witness-format", bytes) {
+        return None;
+    }
+    let mut rest = bytes;
+    if RawCircuit::<S>::read(&mut rest).is_ok() && rest.is_empty() {
+        report.error(
+            "witness-format",
+            "the witness file holds a circuit".to_string(),
+            "the circuit and witness files may have been swapped",
+        );
+        return None;
+    }
+    if bytes.len() < 8 {
+        report.error(
+            "witness-truncated",
+            format!(
+                "the witness file has {} bytes, too few for its header",
+                bytes.len()
+            ),
+            "the file is empty or was cut short",
+        );
+        return None;
+    }
+
+    let num_inputs = BigEndian::read_u32(&bytes[0..4]) as usize;
+    let num_aux = BigEndian::read_u32(&bytes[4..8]) as usize;
+    let size = S::Repr::default().as_ref().len();
+    let needed = 8 + (num_inputs + num_aux) * size;
+    if bytes.len() < needed {
+        report.error(
+            "witness-truncated",
+            format!(
+                "the witness file has {} bytes, but its {} inputs and {} auxiliary values \
+                 need {}",
+                bytes.len(),
+                num_inputs,
+                num_aux,
+                needed
+            ),
+            "the file was cut short, or its values are shorter than those of this curve",
+        );
+        return None;
+    }
+    if bytes.len() > needed {
+        report.warning(
+            "witness-trailing-bytes",
+            format!(
+                "the witness file has {} bytes after its values",
+                bytes.len() - needed
+            ),
+            "the values may be longer than those of this curve, or several witnesses \
+             were written to the file",
+        );
+    }
+
+    let values = bytes[8..needed]
+        .chunks(size)
+        .map(|chunk| {
+            let mut repr = S::Repr::default();
+            repr.as_mut().copy_from_slice(chunk);
+            S::from_repr(repr)
+        })
+        .collect::<Vec<_>>();
+    let invalid = values.iter().filter(|value| value.is_none()).count();
+    if let Some(first) = values.iter().position(|value| value.is_none()) {
+        let which = if first < num_inputs {
+            format!("input {}", first)
+        } else {
+            format!("auxiliary value {}", first - num_inputs)
+        };
+        report.error(
+            "witness-value",
+            format!(
+                "{} of the {} witness values are not canonical field elements, the first \
+                 being {}",
+                invalid,
+                values.len(),
+                which
+            ),
+            "if another tool exported the witness, its values may be in Montgomery form \
+             or of another byte order; read it with Assignment::read_with and the \
+             matching Convention",
+        );
+        return None;
+    }
+
+    let mut values = values.into_iter().map(Option::unwrap);
+    let witness = Assignment {
+        inputs: values.by_ref().take(num_inputs).collect(),
+        aux: values.collect(),
+    };
+    match witness.inputs.first() {
+        Some(one) if *one == S::one() => {}
+        first => report.error(
+            "witness-one",
+            match first {
+                Some(value) => format!("the first input of the witness is {:?}, not ONE", value),
+                None => "the witness has no inputs, not even ONE".to_string(),
+            },
+            "the first input of an assignment is the constant ONE, which some exporters \
+             leave out; prepend it",
+        ),
+    }
+    Some(witness)
+}
+
+/// The hint for inputs of a witness that do not match the `expected` count.
+fn missing_one_hint<S: PrimeField>(witness: &Assignment<S>, expected: usize) -> &'static str {
+    if witness.inputs.len() + 1 == expected && witness.inputs.first() != Some(&S::one()) {
+        "the witness lacks the constant ONE input; prepend it"
+    } else {
+        "the witness was made for another circuit, or another version of it"
+    }
+}
+
+fn check_witness_fit<S: PrimeField>(
+    report: &mut LintReport,
+    circuit: &RawCircuit<S>,
+    witness: &Assignment<S>,
+) {
+    let swapped = witness.inputs.len() + witness.aux.len() == circuit.num_inputs + circuit.num_aux;
+    let hint = |expected| {
+        if swapped {
+            "the witness has as many variables as the circuit, split differently between \
+             inputs and auxiliary values; a variable may have been made public or private \
+             in another version of the circuit"
+        } else {
+            missing_one_hint(witness, expected)
+        }
+    };
+    if witness.inputs.len() != circuit.num_inputs {
+        report.error(
+            "input-count",
+            format!(
+                "the witness has {} inputs, counting ONE, but the circuit has {}",
+                witness.inputs.len(),
+                circuit.num_inputs
+            ),
+            hint(circuit.num_inputs),
+        );
+    }
+    if witness.aux.len() != circuit.num_aux {
+        report.error(
+            "aux-count",
+            format!(
+                "the witness has {} auxiliary values, but the circuit has {}",
+                witness.aux.len(),
+                circuit.num_aux
+            ),
+            hint(circuit.num_inputs),
+        );
+    }
+    if witness.inputs.len() != circuit.num_inputs || witness.aux.len() != circuit.num_aux {
+        return;
+    }
+
+    let (a, b, c) = circuit.evaluate(&witness.inputs, &witness.aux);
+    let unsatisfied = a
+        .iter()
+        .zip(b.iter())
+        .zip(c.iter())
+        .enumerate()
+        .filter(|(_, ((a, b), c))| **a * *b != **c)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if let Some(first) = unsatisfied.first() {
+        report.error(
+            "unsatisfied",
+            format!(
+                "{} of the {} constraints are not satisfied, the first being constraint {}",
+                unsatisfied.len(),
+                circuit.num_constraints,
+                first
+            ),
+            "the witness was computed from values that do not satisfy the statement, or \
+             for another version of the circuit; witness::check_witness on the circuit \
+             names the constraint",
+        );
+    }
+}
+
+/// The curves whose parameters are recognized by the lengths of their
+/// uncompressed G1 and G2 points.
+const CURVES: &[(&str, usize, usize)] = &[("bls12_381", 96, 192), ("bn254", 64, 128)];
+
+/// The counts of a parameters file, read without decoding its points.
+struct Layout {
+    /// The length of the verifying key at the start of the file.
+    vk: usize,
+    ic: usize,
+    /// The lengths of the `h`, `l`, `a`, `b_g1` and `b_g2` queries, or
+    /// `None` if the file is only a verifying key.
+    queries: Option<[usize; 5]>,
+    /// The length of the parameters.
+    end: usize,
+}
+
+/// Reads the counts of parameters with points of `g1` and `g2` bytes, or
+/// returns how many bytes they need if `bytes` are too short.
+fn layout(bytes: &[u8], g1: usize, g2: usize) -> Result<Layout, usize> {
+    let query = |pos: &mut usize, size: usize| {
+        let start = *pos + 4;
+        if bytes.len() < start {
+            return Err(start);
+        }
+        let count = BigEndian::read_u32(&bytes[*pos..start]) as usize;
+        *pos = start + count * size;
+        if bytes.len() < *pos {
+            Err(*pos)
+        } else {
+            Ok(count)
+        }
+    };
+
+    let mut pos = 3 * g1 + 3 * g2;
+    let ic = query(&mut pos, g1)?;
+    let vk = pos;
+    if pos == bytes.len() {
+        return Ok(Layout {
+            vk,
+            ic,
+            queries: None,
+            end: pos,
+        });
+    }
+    let queries = [
+        query(&mut pos, g1)?,
+        query(&mut pos, g1)?,
+        query(&mut pos, g1)?,
+        query(&mut pos, g1)?,
+        query(&mut pos, g2)?,
+    ];
+    Ok(Layout {
+        vk,
+        ic,
+        queries: Some(queries),
+        end: pos,
+    })
+}
+
+fn read_parameters<E: Engine>(
+    report: &mut LintReport,
+    bytes: &[u8],
+    vk_hash: Option<[u8; 32]>,
+) -> Option<Layout> {
+    if check_container(report, "parameters", "params-format", bytes) {
+        return None;
+    }
+
+    let len = |bytes: &[u8]| bytes.len();
+    let g1 = len(<E::G1Affine as UncompressedEncoding>::Uncompressed::default().as_ref());
+    let g2 = len(<E::G2Affine as UncompressedEncoding>::Uncompressed::default().as_ref());
+    // Parameters for another curve may also read as a shorter file with
+    // the points of this one, followed by trailing bytes.
+    let layout = layout(bytes, g1, g2);
+    if layout
+        .as_ref()
+        .map_or(true, |layout| layout.end < bytes.len())
+    {
+        let fits =
+            |g1, g2| matches!(self::layout(bytes, g1, g2), Ok(layout) if layout.end == bytes.len());
+        let g1_compressed = len(<E::G1Affine as GroupEncoding>::Repr::default().as_ref());
+        let g2_compressed = len(<E::G2Affine as GroupEncoding>::Repr::default().as_ref());
+        if let Some((curve, _, _)) = CURVES
+            .iter()
+            .filter(|(_, other_g1, other_g2)| (*other_g1, *other_g2) != (g1, g2))
+            .find(|(_, g1, g2)| fits(*g1, *g2))
+        {
+            report.error(
+                "params-curve",
+                format!(
+                    "the parameters are laid out for the points of {}, not those of this \
+                     engine",
+                    curve
+                ),
+                "the parameters were generated on another curve; use those for the curve \
+                 the prover runs on",
+            );
+            return None;
+        }
+        if fits(g1_compressed, g2_compressed) {
+            report.error(
+                "params-compressed",
+                "the parameters are laid out for compressed points".to_string(),
+                "Parameters::read takes uncompressed points; re-encode them, or write them \
+                 with Parameters::write",
+            );
+            return None;
+        }
+    }
+    let layout = match layout {
+        Ok(layout) => layout,
+        Err(needed) => {
+            report.error(
+                "params-truncated",
+                format!(
+                    "the parameters file has {} bytes, but its counts need at least {}",
+                    bytes.len(),
+                    needed
+                ),
+                "the file was cut short by an interrupted download or copy, or is not a \
+                 parameters file",
+            );
+            return None;
+        }
+    };
+
+    if layout.queries.is_none() {
+        report.error(
+            "params-verifying-key",
+            "the parameters file only holds a verifying key".to_string(),
+            "proving needs the parameters written by Parameters::write, which start with \
+             the verifying key",
+        );
+    } else if layout.end < bytes.len() {
+        report.warning(
+            "params-trailing-bytes",
+            format!(
+                "the parameters file has {} bytes after the parameters",
+                bytes.len() - layout.end
+            ),
+            "the file may hold several parameters, or another file appended to it",
+        );
+    }
+
+    if let Some(expected) = vk_hash {
+        let actual = super::hash_verifying_key(&bytes[..layout.vk]);
+        if actual != expected {
+            report.error(
+                "vk-hash",
+                format!(
+                    "the verifying key of the parameters has hash {}, not the expected {}",
+                    hex(&actual),
+                    hex(&expected)
+                ),
+                "the parameters come from another keygen or ceremony than the verifying \
+                 key that checks the proofs, and their proofs will not verify against it",
+            );
+        }
+    }
+    Some(layout)
+}
+
+fn check_parameters_fit<S: PrimeField>(
+    report: &mut LintReport,
+    circuit: &RawCircuit<S>,
+    layout: &Layout,
+) {
+    let mut actual = vec![("ic", layout.ic)];
+    if let Some(queries) = layout.queries {
+        actual.extend(
+            ["h", "l", "a", "b_g1", "b_g2"]
+                .iter()
+                .copied()
+                .zip(queries.iter().copied()),
+        );
+    }
+    let mixed = domain_size::<S>(circuit.num_constraints + circuit.num_inputs, Radix::Mixed).ok();
+
+    let expected = query_lengths(circuit);
+    let mismatches = actual
+        .iter()
+        .filter(|(query, len)| !(*query == "h" && mixed == Some(len + 1)))
+        .filter_map(|(query, len)| {
+            let (_, expected) = expected.iter().find(|(name, _)| name == query)?;
+            if len == expected {
+                None
+            } else {
+                Some((*query, *len, *expected))
+            }
+        })
+        .collect::<Vec<_>>();
+    if mismatches.is_empty() {
+        return;
+    }
+
+    let hint = if mismatches.iter().all(|(query, _, _)| *query == "ic") {
+        "the parameters were generated for a version of the circuit with another number \
+         of public inputs"
+    } else {
+        "the parameters were generated for another circuit, or another version of it; \
+         regenerate them, or use the circuit they were made for"
+    };
+    report.error(
+        "query-length",
+        format!(
+            "the parameters do not fit the circuit: {}",
+            mismatches
+                .iter()
+                .map(|(query, actual, expected)| format!(
+                    "{} has {} points where the circuit needs {}",
+                    query, actual, expected
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        hint,
+    );
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(all(test, feature = "pairing"))]
+mod tests {
+    use super::*;
+    use crate::groth16::fuzz::CircuitConfig;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::{Bls12, Scalar};
+
+    struct Files {
+        circuit: Vec<u8>,
+        witness: Vec<u8>,
+        parameters: Vec<u8>,
+        raw: RawCircuit<Scalar>,
+        assignment: Assignment<Scalar>,
+    }
+
+    impl Files {
+        fn inputs(&self) -> LintInputs<'_> {
+            LintInputs {
+                circuit: Some(&self.circuit),
+                witness: Some(&self.witness),
+                parameters: Some(&self.parameters),
+                circuit_fingerprint: Some(self.raw.fingerprint()),
+                vk_hash: None,
+            }
+        }
+    }
+
+    fn files(config: &CircuitConfig) -> Files {
+        let f = Fixture::<Bls12>::with_config(config);
+
+        let (mut circuit, mut witness, mut parameters) = (vec![], vec![], vec![]);
+        f.circuit.write(&mut circuit).unwrap();
+        f.witness.write(&mut witness).unwrap();
+        f.params.write(&mut parameters).unwrap();
+        Files {
+            circuit,
+            witness,
+            parameters,
+            raw: f.circuit,
+            assignment: f.witness,
+        }
+    }
+
+    fn codes(report: &LintReport) -> Vec<&'static str> {
+        report.diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn matching_files() {
+        let files = files(&CircuitConfig::default());
+        let params =
+            crate::groth16::Parameters::<Bls12>::read(&files.parameters[..], false).unwrap();
+
+        let report = lint::<Bls12>(&LintInputs {
+            vk_hash: Some(params.vk.hash()),
+            ..files.inputs()
+        });
+        assert!(report.diagnostics.is_empty(), "{}", report);
+        assert_eq!(report.to_string(), "no problems found\n");
+
+        // Each file is checked on its own too.
+        for inputs in [
+            LintInputs {
+                circuit: Some(&files.circuit),
+                ..LintInputs::default()
+            },
+            LintInputs {
+                witness: Some(&files.witness),
+                parameters: Some(&files.parameters),
+                ..LintInputs::default()
+            },
+        ]
+        .iter()
+        {
+            assert!(lint::<Bls12>(inputs).diagnostics.is_empty());
+        }
+    }
+
+    #[test]
+    fn witness_mistakes() {
+        let files = files(&CircuitConfig::default());
+        let check = |witness: &[u8]| {
+            lint::<Bls12>(&LintInputs {
+                witness: Some(witness),
+                ..files.inputs()
+            })
+        };
+
+        // An exporter that leaves out ONE.
+        let mut without_one = files.assignment.clone();
+        without_one.inputs.remove(0);
+        let mut bytes = vec![];
+        without_one.write(&mut bytes).unwrap();
+        let report = check(&bytes);
+        assert_eq!(codes(&report), ["witness-one", "input-count"]);
+        assert!(report.diagnostics[1]
+            .hint
+            .contains("lacks the constant ONE"));
+        assert!(report.to_string().starts_with("error[witness-one]: "));
+
+        // Values in Montgomery form are rarely canonical.
+        let mut montgomery = files.witness.clone();
+        for byte in &mut montgomery[8 + 32..8 + 64] {
+            *byte = 0xff;
+        }
+        let report = check(&montgomery);
+        assert_eq!(codes(&report), ["witness-value"]);
+        assert!(report.diagnostics[0]
+            .message
+            .contains("the first being input 1"));
+
+        // Truncated files, and the circuit passed as the witness.
+        let report = check(&files.witness[..files.witness.len() - 1]);
+        assert_eq!(codes(&report), ["witness-truncated"]);
+        let report = check(&files.circuit);
+        assert_eq!(codes(&report), ["witness-format"]);
+        assert!(report.diagnostics[0].hint.contains("swapped"));
+
+        // Trailing bytes are only a warning.
+        let mut longer = files.witness.clone();
+        longer.push(0);
+        let report = check(&longer);
+        assert_eq!(codes(&report), ["witness-trailing-bytes"]);
+        assert!(report.is_ok());
+
+        // A witness that does not satisfy the circuit.
+        let mut wrong = files.assignment.clone();
+        wrong.aux[0] += Scalar::one();
+        let mut bytes = vec![];
+        wrong.write(&mut bytes).unwrap();
+        let report = check(&bytes);
+        assert_eq!(codes(&report), ["unsatisfied"]);
+        assert!(!report.is_ok());
+
+        // A witness for another version of the circuit, with an input made
+        // private.
+        let mut moved = files.assignment.clone();
+        moved.aux.push(moved.inputs.pop().unwrap());
+        let mut bytes = vec![];
+        moved.write(&mut bytes).unwrap();
+        let report = check(&bytes);
+        assert_eq!(codes(&report), ["input-count", "aux-count"]);
+        assert!(report.diagnostics[0].hint.contains("split differently"));
+    }
+
+    #[test]
+    fn parameter_mistakes() {
+        let files = files(&CircuitConfig::default());
+        let check = |parameters: &[u8]| {
+            lint::<Bls12>(&LintInputs {
+                parameters: Some(parameters),
+                ..files.inputs()
+            })
+        };
+
+        // Parameters of another circuit.
+        let other = self::files(&CircuitConfig {
+            num_inputs: 3,
+            ..CircuitConfig::default()
+        });
+        let report = check(&other.parameters);
+        assert_eq!(codes(&report), ["query-length"]);
+        assert!(report.diagnostics[0].message.contains("ic has 4 points"));
+
+        // The verifying key, a truncated download, and a bundle.
+        let vk = crate::groth16::Parameters::<Bls12>::read(&files.parameters[..], false)
+            .unwrap()
+            .vk;
+        let mut bytes = vec![];
+        vk.write(&mut bytes).unwrap();
+        assert_eq!(codes(&check(&bytes)), ["params-verifying-key"]);
+        let report = check(&files.parameters[..files.parameters.len() - 100]);
+        assert_eq!(codes(&report), ["params-truncated"]);
+        let mut bundle = bundle::MAGIC.to_vec();
+        bundle.extend_from_slice(&2u32.to_be_bytes());
+        let report = check(&bundle);
+        assert_eq!(codes(&report), ["params-format"]);
+        assert!(report.diagnostics[0].message.contains("of version 2"));
+
+        // Parameters laid out for BN254, whose points are shorter.
+        let mut bn254 = vec![0; 3 * 64 + 3 * 128];
+        for (count, size) in [(2, 64), (3, 64), (1, 64), (2, 64), (1, 64), (1, 128)].iter() {
+            bn254.extend_from_slice(&(*count as u32).to_be_bytes());
+            bn254.extend(std::iter::repeat(0).take(count * size));
+        }
+        let report = check(&bn254);
+        assert_eq!(codes(&report), ["params-curve"]);
+        assert!(report.diagnostics[0].message.contains("bn254"));
+
+        // Parameters and a circuit that are not the expected ones.
+        let report = lint::<Bls12>(&LintInputs {
+            circuit_fingerprint: Some([0; 32]),
+            vk_hash: Some([0; 32]),
+            ..files.inputs()
+        });
+        assert_eq!(codes(&report), ["circuit-fingerprint", "vk-hash"]);
+    }
+}
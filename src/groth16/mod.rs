@@ -1,25 +1,143 @@
 //! The [Groth16] proving system.
 //!
+//! The `verifier` feature, without `groth16` or `std`, builds only the
+//! verifier, for targets such as `wasm32-unknown-unknown` and embedded
+//! devices that have an allocator but no standard library or threads:
+//! [`Proof`], [`VerifyingKey`], [`prepare_verifying_key`], [`verify_proof`]
+//! and [`verify_proofs_batch`], with proofs and keys decoded from byte slices
+//! by [`strict`], and the [`host`] verifier. The prover, the parameter
+//! generator, the readers and writers of `std::io` and the multicore worker
+//! need `groth16`.
+//!
 //! [Groth16]: https://eprint.iacr.org/2016/260
 
-use group::{prime::PrimeCurveAffine, GroupEncoding, UncompressedEncoding};
+use alloc::vec::Vec;
+use group::prime::PrimeCurveAffine;
 use pairing::{Engine, MultiMillerLoop};
 
+#[cfg(feature = "groth16")]
+use blake2s_simd::Params as Blake2sParams;
+#[cfg(feature = "groth16")]
+use group::{GroupEncoding, UncompressedEncoding};
+
+#[cfg(feature = "groth16")]
+use self::cost_model::CircuitStats;
+#[cfg(feature = "groth16")]
+use self::encoding::Profile;
+#[cfg(feature = "groth16")]
+use crate::error::{attribute, Context, ErrorKind, Tracked};
+#[cfg(feature = "groth16")]
 use crate::SynthesisError;
 
+#[cfg(feature = "groth16")]
+use crate::multicore::Worker;
+#[cfg(feature = "groth16")]
 use crate::multiexp::SourceBuilder;
+#[cfg(feature = "groth16")]
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "groth16")]
 use std::io::{self, Read, Write};
+#[cfg(feature = "groth16")]
 use std::sync::Arc;
 
-#[cfg(test)]
-mod tests;
-
+#[cfg(all(test, feature = "groth16"))]
+pub(crate) mod tests;
+
+#[cfg(feature = "groth16")]
+pub mod aggregate;
+#[cfg(feature = "groth16")]
+pub mod arithmetic;
+#[cfg(feature = "groth16")]
+pub mod audit;
+#[cfg(feature = "groth16")]
+pub mod bench;
+#[cfg(feature = "groth16")]
+pub mod bundle;
+#[cfg(feature = "groth16")]
+pub mod cache;
+#[cfg(feature = "groth16")]
+pub mod ceremony;
+#[cfg(feature = "groth16")]
+pub mod chain_cost;
+#[cfg(feature = "groth16")]
+pub mod checkpoint;
+#[cfg(feature = "groth16")]
+pub mod collaborative;
+#[cfg(feature = "groth16")]
+pub mod commitment;
+#[cfg(feature = "groth16")]
+pub mod constant_time;
+#[cfg(feature = "groth16")]
+pub mod cost_model;
+#[cfg(feature = "groth16")]
+pub mod cross_curve;
+#[cfg(feature = "groth16")]
+pub mod delegated;
+#[cfg(feature = "groth16")]
+pub mod delta;
+#[cfg(feature = "groth16")]
+pub mod disclosure;
+#[cfg(feature = "groth16")]
+pub mod encoding;
+#[cfg(feature = "groth16")]
+pub mod encrypted;
+#[cfg(feature = "groth16")]
+pub mod envelope;
+#[cfg(feature = "groth16")]
+pub mod exporter;
+#[cfg(feature = "groth16")]
+pub mod fuzz;
+#[cfg(feature = "groth16")]
 mod generator;
+#[cfg(all(feature = "groth16", feature = "bn254"))]
+pub mod hints;
+pub mod host;
+#[cfg(feature = "groth16")]
+pub mod importer;
+#[cfg(feature = "groth16")]
+pub mod inputs;
+#[cfg(feature = "groth16")]
+pub mod instance;
+#[cfg(feature = "groth16")]
+pub mod lazy;
+#[cfg(feature = "groth16")]
+pub mod lint;
+#[cfg(feature = "groth16")]
+pub mod mpc;
+#[cfg(feature = "groth16")]
+pub mod mutation;
+#[cfg(feature = "groth16")]
+pub mod optimizer;
+#[cfg(feature = "groth16")]
+pub mod planner;
+#[cfg(feature = "groth16")]
+pub mod provenance;
+#[cfg(feature = "groth16")]
 mod prover;
+#[cfg(all(test, feature = "groth16"))]
+mod reproducible;
+#[cfg(feature = "groth16")]
+pub mod rng;
+#[cfg(feature = "groth16")]
+pub mod sealed;
+#[cfg(feature = "groth16")]
+pub mod solidity;
+#[cfg(feature = "groth16")]
+pub mod stream;
+pub mod strict;
+#[cfg(feature = "groth16")]
+pub mod structure;
+#[cfg(feature = "groth16")]
+pub mod vectors;
 mod verifier;
+#[cfg(feature = "groth16")]
+pub mod vk_set;
+#[cfg(feature = "groth16")]
+pub mod witness;
 
+#[cfg(feature = "groth16")]
 pub use self::generator::*;
+#[cfg(feature = "groth16")]
 pub use self::prover::*;
 pub use self::verifier::*;
 
@@ -36,17 +154,33 @@ impl<E: Engine> PartialEq for Proof<E> {
     }
 }
 
+#[cfg(feature = "groth16")]
 impl<E: Engine> Proof<E> {
-    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_with(writer, &Profile::default())
+    }
+
+    /// Writes the proof like [`Proof::write`], with `B` encoded in the given
+    /// `profile`.
+    pub fn write_with<W: Write>(&self, mut writer: W, profile: &Profile) -> io::Result<()> {
+        let mut b = self.b.to_bytes();
+        let size = b.as_ref().len();
+        profile.reorder_g2::<E::G2Affine>(b.as_mut(), size);
+
         writer.write_all(self.a.to_bytes().as_ref())?;
-        writer.write_all(self.b.to_bytes().as_ref())?;
+        writer.write_all(b.as_ref())?;
         writer.write_all(self.c.to_bytes().as_ref())?;
 
         Ok(())
     }
 
-    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
-        let read_g1 = |reader: &mut R| -> io::Result<E::G1Affine> {
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_with(reader, &Profile::default())
+    }
+
+    /// Reads a proof written by [`Proof::write_with`] in the given `profile`.
+    pub fn read_with<R: Read>(reader: R, profile: &Profile) -> io::Result<Self> {
+        let read_g1 = |reader: &mut Tracked<R>| -> io::Result<E::G1Affine> {
             let mut g1_repr = <E::G1Affine as GroupEncoding>::Repr::default();
             reader.read_exact(g1_repr.as_mut())?;
 
@@ -69,9 +203,11 @@ impl<E: Engine> Proof<E> {
             })
         };
 
-        let read_g2 = |reader: &mut R| -> io::Result<E::G2Affine> {
+        let read_g2 = |reader: &mut Tracked<R>| -> io::Result<E::G2Affine> {
             let mut g2_repr = <E::G2Affine as GroupEncoding>::Repr::default();
             reader.read_exact(g2_repr.as_mut())?;
+            let size = g2_repr.as_ref().len();
+            profile.reorder_g2::<E::G2Affine>(g2_repr.as_mut(), size);
 
             let affine = E::G2Affine::from_bytes(&g2_repr);
             let affine = if affine.is_some().into() {
@@ -92,9 +228,86 @@ impl<E: Engine> Proof<E> {
             })
         };
 
-        let a = read_g1(&mut reader)?;
-        let b = read_g2(&mut reader)?;
-        let c = read_g1(&mut reader)?;
+        let mut reader = Tracked::new(reader);
+        let kind = ErrorKind::MalformedProof;
+        let a = reader.within(kind, Context::Section("a"), |r| read_g1(r))?;
+        let b = reader.within(kind, Context::Section("b"), |r| read_g2(r))?;
+        let c = reader.within(kind, Context::Section("c"), |r| read_g1(r))?;
+
+        Ok(Proof { a, b, c })
+    }
+
+    /// Writes the proof with uncompressed points, twice the size of
+    /// [`Proof::write`].
+    ///
+    /// Decompressing a point takes a square root in its base field, which
+    /// dominates decoding, and on small devices is a noticeable part of
+    /// verification. Proofs meant for such verifiers can carry the full
+    /// coordinates instead, which [`Proof::read_uncompressed`] only checks.
+    /// With the `bn254` feature, the proofs of the `hints` module also carry
+    /// the slopes of the Miller loop of `B`, which spare the verifier an
+    /// inversion for each line.
+    pub fn write_uncompressed<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_uncompressed_with(writer, &Profile::default())
+    }
+
+    /// Writes the proof like [`Proof::write_uncompressed`], with `B` encoded
+    /// in the given `profile`.
+    pub fn write_uncompressed_with<W: Write>(
+        &self,
+        mut writer: W,
+        profile: &Profile,
+    ) -> io::Result<()> {
+        let mut b = self.b.to_uncompressed();
+        let size = b.as_ref().len();
+        profile.reorder_g2::<E::G2Affine>(b.as_mut(), size);
+
+        writer.write_all(self.a.to_uncompressed().as_ref())?;
+        writer.write_all(b.as_ref())?;
+        writer.write_all(self.c.to_uncompressed().as_ref())?;
+
+        Ok(())
+    }
+
+    /// Reads a proof written by [`Proof::write_uncompressed`], checking that
+    /// its points are on the curve, in the prime-order subgroup, and not the
+    /// point at infinity, as [`Proof::read`] does.
+    pub fn read_uncompressed<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_uncompressed_with(reader, &Profile::default())
+    }
+
+    /// Reads a proof written by [`Proof::write_uncompressed_with`] in the
+    /// given `profile`.
+    pub fn read_uncompressed_with<R: Read>(reader: R, profile: &Profile) -> io::Result<Self> {
+        fn read_point<G: PrimeCurveAffine + UncompressedEncoding, R: Read>(
+            reader: &mut Tracked<R>,
+            profile: Option<&Profile>,
+        ) -> io::Result<G> {
+            let mut repr = G::Uncompressed::default();
+            reader.read_exact(repr.as_mut())?;
+            if let Some(profile) = profile {
+                let size = repr.as_ref().len();
+                profile.reorder_g2::<G>(repr.as_mut(), size);
+            }
+
+            let point: Option<G> = G::from_uncompressed(&repr).into();
+            match point {
+                Some(point) if bool::from(!point.is_identity()) => Ok(point),
+                Some(_) => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "point at infinity",
+                )),
+                None => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid point")),
+            }
+        }
+
+        let mut reader = Tracked::new(reader);
+        let kind = ErrorKind::MalformedProof;
+        let a = reader.within(kind, Context::Section("a"), |r| read_point(r, None))?;
+        let b = reader.within(kind, Context::Section("b"), |r| {
+            read_point(r, Some(profile))
+        })?;
+        let c = reader.within(kind, Context::Section("c"), |r| read_point(r, None))?;
 
         Ok(Proof { a, b, c })
     }
@@ -141,13 +354,102 @@ impl<E: Engine> PartialEq for VerifyingKey<E> {
 }
 
 impl<E: Engine> VerifyingKey<E> {
-    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    /// Returns the number of public inputs, not counting the constant input
+    /// `ONE`.
+    pub fn num_inputs(&self) -> usize {
+        self.ic.len().saturating_sub(1)
+    }
+
+    /// Returns the IC element of the constant input `ONE`, which is the
+    /// starting point of the linear combination of the public inputs.
+    pub fn ic_constant(&self) -> Option<&E::G1Affine> {
+        self.ic.first()
+    }
+
+    /// Returns the IC element multiplied by public input `index`.
+    pub fn ic_input(&self, index: usize) -> Option<&E::G1Affine> {
+        self.ic.get(index + 1)
+    }
+
+    /// Returns the IC elements of the public inputs, in order, not counting
+    /// the constant input `ONE`.
+    pub fn ic_inputs(&self) -> &[E::G1Affine] {
+        self.ic.get(1..).unwrap_or(&[])
+    }
+
+    /// Returns `true` if the key has an IC element for `ONE`, and none of
+    /// its elements are the point at infinity, as for every key produced by
+    /// keygen.
+    pub fn is_well_formed(&self) -> bool {
+        let g1 = [self.alpha_g1, self.beta_g1, self.delta_g1];
+        let g2 = [self.beta_g2, self.gamma_g2, self.delta_g2];
+        !self.ic.is_empty()
+            && g1
+                .iter()
+                .chain(&self.ic)
+                .all(|p| bool::from(!p.is_identity()))
+            && g2.iter().all(|p| bool::from(!p.is_identity()))
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<E: Engine> VerifyingKey<E> {
+    /// Returns a BLAKE2s hash of the serialized key, which identifies it.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut bytes = vec![];
+        self.write(&mut bytes)
+            .expect("writing to a Vec does not fail");
+        hash_verifying_key(&bytes)
+    }
+
+    /// Checks that the key has as many public inputs as `manifest`.
+    pub fn check_manifest(
+        &self,
+        manifest: &inputs::InputManifest,
+    ) -> Result<(), inputs::InputError> {
+        if manifest.len() != self.num_inputs() {
+            return Err(inputs::InputError::Count {
+                expected: self.num_inputs(),
+                actual: manifest.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that the key has as many public inputs as `circuit`.
+    pub fn check_circuit(
+        &self,
+        circuit: &exporter::RawCircuit<E::Fr>,
+    ) -> Result<(), inputs::InputError> {
+        if circuit.num_inputs != self.ic.len() {
+            return Err(inputs::InputError::Count {
+                expected: self.num_inputs(),
+                actual: circuit.num_inputs.saturating_sub(1),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_with(writer, &Profile::default())
+    }
+
+    /// Writes the key like [`VerifyingKey::write`], with its G2 points
+    /// encoded in the given `profile`.
+    pub fn write_with<W: Write>(&self, mut writer: W, profile: &Profile) -> io::Result<()> {
+        let g2 = |point: &E::G2Affine| {
+            let mut bytes = point.to_uncompressed();
+            let size = bytes.as_ref().len();
+            profile.reorder_g2::<E::G2Affine>(bytes.as_mut(), size);
+            bytes
+        };
+
         writer.write_all(self.alpha_g1.to_uncompressed().as_ref())?;
         writer.write_all(self.beta_g1.to_uncompressed().as_ref())?;
-        writer.write_all(self.beta_g2.to_uncompressed().as_ref())?;
-        writer.write_all(self.gamma_g2.to_uncompressed().as_ref())?;
+        writer.write_all(g2(&self.beta_g2).as_ref())?;
+        writer.write_all(g2(&self.gamma_g2).as_ref())?;
         writer.write_all(self.delta_g1.to_uncompressed().as_ref())?;
-        writer.write_all(self.delta_g2.to_uncompressed().as_ref())?;
+        writer.write_all(g2(&self.delta_g2).as_ref())?;
         writer.write_u32::<BigEndian>(self.ic.len() as u32)?;
         for ic in &self.ic {
             writer.write_all(ic.to_uncompressed().as_ref())?;
@@ -156,8 +458,14 @@ impl<E: Engine> VerifyingKey<E> {
         Ok(())
     }
 
-    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
-        let read_g1 = |reader: &mut R| -> io::Result<E::G1Affine> {
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_with(reader, &Profile::default())
+    }
+
+    /// Reads a key written by [`VerifyingKey::write_with`] in the given
+    /// `profile`.
+    pub fn read_with<R: Read>(reader: R, profile: &Profile) -> io::Result<Self> {
+        let read_g1 = |reader: &mut Tracked<R>| -> io::Result<E::G1Affine> {
             let mut g1_repr = <E::G1Affine as UncompressedEncoding>::Uncompressed::default();
             reader.read_exact(g1_repr.as_mut())?;
 
@@ -169,9 +477,11 @@ impl<E: Engine> VerifyingKey<E> {
             }
         };
 
-        let read_g2 = |reader: &mut R| -> io::Result<E::G2Affine> {
+        let read_g2 = |reader: &mut Tracked<R>| -> io::Result<E::G2Affine> {
             let mut g2_repr = <E::G2Affine as UncompressedEncoding>::Uncompressed::default();
             reader.read_exact(g2_repr.as_mut())?;
+            let size = g2_repr.as_ref().len();
+            profile.reorder_g2::<E::G2Affine>(g2_repr.as_mut(), size);
 
             let affine = E::G2Affine::from_uncompressed(&g2_repr);
             if affine.is_some().into() {
@@ -181,31 +491,39 @@ impl<E: Engine> VerifyingKey<E> {
             }
         };
 
-        let alpha_g1 = read_g1(&mut reader)?;
-        let beta_g1 = read_g1(&mut reader)?;
-        let beta_g2 = read_g2(&mut reader)?;
-        let gamma_g2 = read_g2(&mut reader)?;
-        let delta_g1 = read_g1(&mut reader)?;
-        let delta_g2 = read_g2(&mut reader)?;
-
-        let ic_len = reader.read_u32::<BigEndian>()? as usize;
-
-        let mut ic = vec![];
-
-        for _ in 0..ic_len {
-            let g1 = read_g1(&mut reader).and_then(|e| {
-                if e.is_identity().into() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })?;
+        let mut reader = Tracked::new(reader);
+        let kind = ErrorKind::MalformedParameters;
+        let alpha_g1 = reader.within(kind, Context::Section("alpha_g1"), |r| read_g1(r))?;
+        let beta_g1 = reader.within(kind, Context::Section("beta_g1"), |r| read_g1(r))?;
+        let beta_g2 = reader.within(kind, Context::Section("beta_g2"), |r| read_g2(r))?;
+        let gamma_g2 = reader.within(kind, Context::Section("gamma_g2"), |r| read_g2(r))?;
+        let delta_g1 = reader.within(kind, Context::Section("delta_g1"), |r| read_g1(r))?;
+        let delta_g2 = reader.within(kind, Context::Section("delta_g2"), |r| read_g2(r))?;
+
+        let ic = reader.within(kind, Context::Section("ic"), |reader| {
+            let ic_len = reader.read_u32::<BigEndian>()? as usize;
+
+            let mut ic = vec![];
+
+            for i in 0..ic_len {
+                let g1 = reader.within(kind, Context::Element(i), |reader| {
+                    read_g1(reader).and_then(|e| {
+                        if e.is_identity().into() {
+                            Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "point at infinity",
+                            ))
+                        } else {
+                            Ok(e)
+                        }
+                    })
+                })?;
+
+                ic.push(g1);
+            }
 
-            ic.push(g1);
-        }
+            Ok(ic)
+        })?;
 
         Ok(VerifyingKey {
             alpha_g1,
@@ -219,6 +537,21 @@ impl<E: Engine> VerifyingKey<E> {
     }
 }
 
+/// Hashes a verifying key serialized by [`VerifyingKey::write`], as
+/// [`VerifyingKey::hash`] does.
+#[cfg(feature = "groth16")]
+fn hash_verifying_key(bytes: &[u8]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(
+        Blake2sParams::new()
+            .personal(b"bellVKey")
+            .hash(bytes)
+            .as_bytes(),
+    );
+    hash
+}
+
+#[cfg(feature = "groth16")]
 #[derive(Clone)]
 pub struct Parameters<E: Engine> {
     pub vk: VerifyingKey<E>,
@@ -244,6 +577,7 @@ pub struct Parameters<E: Engine> {
     pub b_g2: Arc<Vec<E::G2Affine>>,
 }
 
+#[cfg(feature = "groth16")]
 impl<E: Engine> PartialEq for Parameters<E> {
     fn eq(&self, other: &Self) -> bool {
         self.vk == other.vk
@@ -255,9 +589,72 @@ impl<E: Engine> PartialEq for Parameters<E> {
     }
 }
 
+#[cfg(feature = "groth16")]
+const STATS_MAGIC: &[u8] = b"bellman-circuit-stats";
+#[cfg(feature = "groth16")]
+const STATS_VERSION: u32 = 1;
+
+/// The number of points of a query that are read and decoded at a time.
+#[cfg(feature = "groth16")]
+const READ_CHUNK: usize = 1 << 16;
+
+/// Reads the header of parameters written by [`Parameters::write_with_stats`], and
+/// returns the statistics of the circuit, if there are any, and a reader of
+/// the rest of the parameters that counts offsets from the start.
+///
+/// Parameters without statistics start with the verifying key, whose first
+/// bytes are read back from the returned reader.
+#[cfg(feature = "groth16")]
+#[allow(clippy::type_complexity)]
+pub(crate) fn read_header<R: Read>(
+    mut reader: R,
+) -> io::Result<(
+    Option<CircuitStats>,
+    Tracked<io::Chain<io::Cursor<Vec<u8>>, R>>,
+)> {
+    let mut prefix = vec![];
+    (&mut reader)
+        .take(STATS_MAGIC.len() as u64)
+        .read_to_end(&mut prefix)?;
+    if prefix != STATS_MAGIC {
+        return Ok((None, Tracked::new(io::Cursor::new(prefix).chain(reader))));
+    }
+
+    let mut reader = Tracked::at(
+        io::Cursor::new(vec![]).chain(reader),
+        STATS_MAGIC.len() as u64,
+    );
+    let kind = ErrorKind::MalformedParameters;
+    let stats = reader.within(kind, Context::Section("stats"), |r| {
+        if r.read_u32::<BigEndian>()? != STATS_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported circuit statistics version",
+            ));
+        }
+        Ok(CircuitStats {
+            num_inputs: r.read_u64::<BigEndian>()? as usize,
+            num_aux: r.read_u64::<BigEndian>()? as usize,
+            num_constraints: r.read_u64::<BigEndian>()? as usize,
+            a_aux: r.read_u64::<BigEndian>()? as usize,
+            b_inputs: r.read_u64::<BigEndian>()? as usize,
+            b_aux: r.read_u64::<BigEndian>()? as usize,
+            terms: r.read_u64::<BigEndian>()? as usize,
+        })
+    })?;
+    Ok((Some(stats), reader))
+}
+
+#[cfg(feature = "groth16")]
 impl<E: Engine> Parameters<E> {
-    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
-        self.vk.write(&mut writer)?;
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_with(writer, &Profile::default())
+    }
+
+    /// Writes the parameters like [`Parameters::write`], with their G2
+    /// points encoded in the given `profile`.
+    pub fn write_with<W: Write>(&self, mut writer: W, profile: &Profile) -> io::Result<()> {
+        self.vk.write_with(&mut writer, profile)?;
 
         writer.write_u32::<BigEndian>(self.h.len() as u32)?;
         for g in &self.h[..] {
@@ -281,120 +678,258 @@ impl<E: Engine> Parameters<E> {
 
         writer.write_u32::<BigEndian>(self.b_g2.len() as u32)?;
         for g in &self.b_g2[..] {
-            writer.write_all(g.to_uncompressed().as_ref())?;
+            let mut bytes = g.to_uncompressed();
+            let size = bytes.as_ref().len();
+            profile.reorder_g2::<E::G2Affine>(bytes.as_mut(), size);
+            writer.write_all(bytes.as_ref())?;
         }
 
         Ok(())
     }
 
-    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
-        let read_g1 = |reader: &mut R| -> io::Result<E::G1Affine> {
-            let mut repr = <E::G1Affine as UncompressedEncoding>::Uncompressed::default();
-            reader.read_exact(repr.as_mut())?;
+    /// Reads parameters written by [`Parameters::write`]. If `checked`, the
+    /// points are checked to be in the prime-order subgroup. Parameters from
+    /// an untrusted source should also be checked against their circuit with
+    /// [`Parameters::verify_structure`].
+    ///
+    /// Each query is read whole, then its points are decoded and checked in
+    /// parallel on a [`Worker`], which is most of the cost of loading large
+    /// parameters.
+    pub fn read<R: Read>(reader: R, checked: bool) -> io::Result<Self> {
+        Self::read_with(reader, checked, &Profile::default())
+    }
 
-            let affine = if checked {
-                E::G1Affine::from_uncompressed(&repr)
-            } else {
-                E::G1Affine::from_uncompressed_unchecked(&repr)
-            };
+    /// Reads parameters written by [`Parameters::write_with`] in the given
+    /// `profile`, like [`Parameters::read`].
+    pub fn read_with<R: Read>(reader: R, checked: bool, profile: &Profile) -> io::Result<Self> {
+        Self::read_inner(reader, checked, profile).map(|(params, _)| params)
+    }
 
-            let affine = if affine.is_some().into() {
-                Ok(affine.unwrap())
-            } else {
-                Err(io::Error::new(io::ErrorKind::InvalidData, "invalid G1"))
-            };
+    /// Reads parameters like [`Parameters::read`], and returns them with the
+    /// statistics of the circuit if they were written by
+    /// [`Parameters::write_with_stats`].
+    pub fn read_with_stats<R: Read>(
+        reader: R,
+        checked: bool,
+    ) -> io::Result<(Self, Option<CircuitStats>)> {
+        Self::read_inner(reader, checked, &Profile::default())
+    }
 
-            affine.and_then(|e| {
-                if e.is_identity().into() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
+    fn read_inner<R: Read>(
+        reader: R,
+        checked: bool,
+        profile: &Profile,
+    ) -> io::Result<(Self, Option<CircuitStats>)> {
+        let worker = Worker::new();
+        let (stats, mut reader) = read_header(reader)?;
+        let kind = ErrorKind::MalformedParameters;
+
+        // The verifying key comes first, so its offsets are also offsets in
+        // the parameters, unless they have statistics.
+        let vk = reader.within(kind, Context::Section("vk"), |r| {
+            VerifyingKey::<E>::read_with(r, profile)
+        })?;
+
+        let h = reader.within(kind, Context::Section("h"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let l = reader.within(kind, Context::Section("l"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let a = reader.within(kind, Context::Section("a"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let b_g1 = reader.within(kind, Context::Section("b_g1"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let b_g2 = reader.within(kind, Context::Section("b_g2"), |r| {
+            read_points_with(r, &worker, kind, checked, "invalid G2", |bytes| {
+                let size = <E::G2Affine as UncompressedEncoding>::Uncompressed::default()
+                    .as_ref()
+                    .len();
+                profile.reorder_g2::<E::G2Affine>(bytes, size)
             })
-        };
-
-        let read_g2 = |reader: &mut R| -> io::Result<E::G2Affine> {
-            let mut repr = <E::G2Affine as UncompressedEncoding>::Uncompressed::default();
-            reader.read_exact(repr.as_mut())?;
-
-            let affine = if checked {
-                E::G2Affine::from_uncompressed(&repr)
-            } else {
-                E::G2Affine::from_uncompressed_unchecked(&repr)
-            };
-
-            let affine = if affine.is_some().into() {
-                Ok(affine.unwrap())
-            } else {
-                Err(io::Error::new(io::ErrorKind::InvalidData, "invalid G2"))
-            };
+        })?;
 
-            affine.and_then(|e| {
-                if e.is_identity().into() {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "point at infinity",
-                    ))
-                } else {
-                    Ok(e)
-                }
-            })
+        let params = Parameters {
+            vk,
+            h: Arc::new(h),
+            l: Arc::new(l),
+            a: Arc::new(a),
+            b_g1: Arc::new(b_g1),
+            b_g2: Arc::new(b_g2),
         };
+        Ok((params, stats))
+    }
 
-        let vk = VerifyingKey::<E>::read(&mut reader)?;
+    /// Writes the parameters like [`Parameters::write`], preceded by the
+    /// statistics of their circuit: `"bellman-circuit-stats"`, a big-endian
+    /// `u32` version (currently 1), and each statistic as a big-endian
+    /// `u64`. [`Parameters::read`] reads either, but tools that only know
+    /// the format of [`Parameters::write`] do not read these.
+    pub fn write_with_stats<W: Write>(
+        &self,
+        stats: &CircuitStats,
+        mut writer: W,
+    ) -> io::Result<()> {
+        writer.write_all(STATS_MAGIC)?;
+        writer.write_u32::<BigEndian>(STATS_VERSION)?;
+        for &n in &[
+            stats.num_inputs,
+            stats.num_aux,
+            stats.num_constraints,
+            stats.a_aux,
+            stats.b_inputs,
+            stats.b_aux,
+            stats.terms,
+        ] {
+            writer.write_u64::<BigEndian>(n as u64)?;
+        }
+        self.write(writer)
+    }
 
-        let mut h = vec![];
-        let mut l = vec![];
-        let mut a = vec![];
-        let mut b_g1 = vec![];
-        let mut b_g2 = vec![];
+    /// Reads the statistics of the circuit from the header of parameters
+    /// written by [`Parameters::write_with_stats`], without reading the
+    /// rest. Returns `None` for parameters without statistics.
+    pub fn read_stats<R: Read>(reader: R) -> io::Result<Option<CircuitStats>> {
+        read_header(reader).map(|(stats, _)| stats)
+    }
+}
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                h.push(read_g1(&mut reader)?);
-            }
-        }
+/// Reads a query of uncompressed points prefixed by its length, decoding the
+/// points in parallel, and reports the first that is invalid or the point at
+/// infinity as [`Tracked::within`] would.
+#[cfg(feature = "groth16")]
+fn read_points<G, R>(
+    reader: &mut Tracked<R>,
+    worker: &Worker,
+    kind: ErrorKind,
+    checked: bool,
+    invalid: &'static str,
+) -> io::Result<Vec<G>>
+where
+    G: PrimeCurveAffine + UncompressedEncoding,
+    R: Read,
+{
+    read_points_with(reader, worker, kind, checked, invalid, |_| ())
+}
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                l.push(read_g1(&mut reader)?);
-            }
+/// Reads a query like [`read_points`], passing its bytes through `prepare`
+/// before they are decoded. The query is read and decoded a chunk of
+/// [`READ_CHUNK`] points at a time, so that its encoding is never held in
+/// memory as a whole.
+#[cfg(feature = "groth16")]
+fn read_points_with<G, R, F>(
+    reader: &mut Tracked<R>,
+    worker: &Worker,
+    kind: ErrorKind,
+    checked: bool,
+    invalid: &'static str,
+    mut prepare: F,
+) -> io::Result<Vec<G>>
+where
+    G: PrimeCurveAffine + UncompressedEncoding,
+    R: Read,
+    F: FnMut(&mut [u8]),
+{
+    let len = reader.read_u32::<BigEndian>()? as usize;
+    let size = G::Uncompressed::default().as_ref().len();
+    let offset = reader.offset();
+
+    // Not preallocated, so that a corrupted length fails at the end of the
+    // data rather than on allocation.
+    let mut points = vec![];
+    let mut bytes = vec![];
+    for first in (0..len).step_by(READ_CHUNK) {
+        let n = READ_CHUNK.min(len - first);
+        let start = offset + (first * size) as u64;
+        bytes.clear();
+        reader.take((n * size) as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < n * size {
+            let i = bytes.len() / size;
+            let e = io::Error::new(io::ErrorKind::UnexpectedEof, "truncated query");
+            return Err(attribute(
+                e,
+                kind,
+                start + (i * size) as u64,
+                Context::Element(first + i),
+            ));
         }
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                a.push(read_g1(&mut reader)?);
-            }
-        }
+        prepare(&mut bytes);
+        points.extend(decode_points::<G>(
+            &bytes, worker, kind, checked, invalid, start, first,
+        )?);
+    }
 
-        {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                b_g1.push(read_g1(&mut reader)?);
-            }
-        }
+    Ok(points)
+}
 
+/// Decodes uncompressed points in parallel, and reports the first that is
+/// invalid or the point at infinity as [`Tracked::within`] would, the points
+/// being elements `first..` of a section from offset `start`.
+#[cfg(feature = "groth16")]
+pub(crate) fn decode_points<G>(
+    bytes: &[u8],
+    worker: &Worker,
+    kind: ErrorKind,
+    checked: bool,
+    invalid: &'static str,
+    start: u64,
+    first: usize,
+) -> io::Result<Vec<G>>
+where
+    G: PrimeCurveAffine + UncompressedEncoding,
+{
+    let size = G::Uncompressed::default().as_ref().len();
+    let len = bytes.len() / size;
+
+    let mut points = vec![G::identity(); len];
+    let mut failures = vec![];
+    worker.scope(len, |scope, chunk| {
+        failures = vec![None; (len + chunk - 1) / chunk];
+        for (((points, bytes), failure), offset) in points
+            .chunks_mut(chunk)
+            .zip(bytes.chunks(chunk * size))
+            .zip(failures.iter_mut())
+            .zip((0..).step_by(chunk))
         {
-            let len = reader.read_u32::<BigEndian>()? as usize;
-            for _ in 0..len {
-                b_g2.push(read_g2(&mut reader)?);
-            }
+            scope.spawn(move |_| {
+                for (i, (point, bytes)) in points.iter_mut().zip(bytes.chunks(size)).enumerate() {
+                    let mut repr = G::Uncompressed::default();
+                    repr.as_mut().copy_from_slice(bytes);
+                    let decoded: Option<G> = if checked {
+                        G::from_uncompressed(&repr).into()
+                    } else {
+                        G::from_uncompressed_unchecked(&repr).into()
+                    };
+                    match decoded {
+                        Some(p) if bool::from(!p.is_identity()) => *point = p,
+                        Some(_) => {
+                            *failure = Some((offset + i, "point at infinity"));
+                            return;
+                        }
+                        None => {
+                            *failure = Some((offset + i, invalid));
+                            return;
+                        }
+                    }
+                }
+            });
         }
-
-        Ok(Parameters {
-            vk,
-            h: Arc::new(h),
-            l: Arc::new(l),
-            a: Arc::new(a),
-            b_g1: Arc::new(b_g1),
-            b_g2: Arc::new(b_g2),
-        })
+    });
+
+    match failures.into_iter().flatten().next() {
+        Some((i, msg)) => {
+            let e = io::Error::new(io::ErrorKind::InvalidData, msg);
+            Err(attribute(
+                e,
+                kind,
+                start + (i * size) as u64,
+                Context::Element(first + i),
+            ))
+        }
+        None => Ok(points),
     }
 }
 
@@ -407,8 +942,11 @@ pub struct PreparedVerifyingKey<E: MultiMillerLoop> {
     neg_delta_g2: E::G2Prepared,
     /// Copy of IC from `VerifiyingKey`.
     ic: Vec<E::G1Affine>,
+    /// Multiples of IC, if they were precomputed.
+    input_tables: Option<InputTables<E::G1Affine>>,
 }
 
+#[cfg(feature = "groth16")]
 pub trait ParameterSource<E: Engine> {
     type G1Builder: SourceBuilder<E::G1Affine>;
     type G2Builder: SourceBuilder<E::G2Affine>;
@@ -433,7 +971,8 @@ pub trait ParameterSource<E: Engine> {
     ) -> Result<(Self::G2Builder, Self::G2Builder), SynthesisError>;
 }
 
-impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
+#[cfg(feature = "groth16")]
+impl<E: Engine> ParameterSource<E> for &Parameters<E> {
     type G1Builder = (Arc<Vec<E::G1Affine>>, usize);
     type G2Builder = (Arc<Vec<E::G2Affine>>, usize);
 
@@ -474,7 +1013,7 @@ impl<'a, E: Engine> ParameterSource<E> for &'a Parameters<E> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "groth16"))]
 mod test_with_bls12_381 {
     use super::*;
     use crate::{Circuit, ConstraintSystem, SynthesisError};
@@ -517,7 +1056,7 @@ mod test_with_bls12_381 {
 
         let mut rng = thread_rng();
 
-        let params = generate_random_parameters::<Bls12, _, _>(
+        let (params, stats) = generate_random_parameters_with_stats::<Bls12, _, _>(
             MySillyCircuit { a: None, b: None },
             &mut rng,
         )
@@ -534,6 +1073,35 @@ mod test_with_bls12_381 {
 
             let de_params = Parameters::read(&v[..], false).unwrap();
             assert!(params == de_params);
+            let (_, de_stats) = Parameters::<Bls12>::read_with_stats(&v[..], false).unwrap();
+            assert_eq!(de_stats, None);
+            assert_eq!(Parameters::<Bls12>::read_stats(&v[..]).unwrap(), None);
+        }
+
+        {
+            assert_eq!(
+                (stats.num_inputs, stats.num_aux, stats.num_constraints),
+                (2, 2, 1)
+            );
+
+            let mut v = vec![];
+            params.write_with_stats(&stats, &mut v).unwrap();
+            assert_eq!(v.len(), 2136 + 21 + 4 + 7 * 8);
+            assert_eq!(
+                Parameters::<Bls12>::read_stats(&v[..]).unwrap(),
+                Some(stats.clone())
+            );
+
+            let de_params = Parameters::read(&v[..], true).unwrap();
+            assert!(params == de_params);
+            let (de_params, de_stats) = Parameters::read_with_stats(&v[..], true).unwrap();
+            assert!(params == de_params);
+            assert_eq!(de_stats, Some(stats));
+
+            // The offsets of errors count the header.
+            v[21] ^= 1;
+            let err = Parameters::<Bls12>::read(&v[..], false).err().unwrap();
+            assert!(err.to_string().contains("offset 21"), "{}", err);
         }
 
         let pvk = prepare_verifying_key::<Bls12>(&params.vk);
@@ -562,8 +1130,59 @@ mod test_with_bls12_381 {
             let de_proof = Proof::read(&v[..]).unwrap();
             assert!(proof == de_proof);
 
+            let mut v = vec![];
+            proof.write_uncompressed(&mut v).unwrap();
+            assert_eq!(v.len(), 384);
+            assert!(proof == Proof::read_uncompressed(&v[..]).unwrap());
+            v[1] ^= 1;
+            assert!(Proof::<Bls12>::read_uncompressed(&v[..]).is_err());
+
             assert!(verify_proof(&pvk, &proof, &[c]).is_ok());
             assert!(verify_proof(&pvk, &proof, &[a]).is_err());
         }
     }
+
+    #[test]
+    fn inspect_verifying_key() {
+        use crate::groth16::inputs::{InputError, InputManifest};
+        use crate::groth16::tests::fixture::Fixture;
+
+        let Fixture {
+            circuit, params, ..
+        } = Fixture::<Bls12>::new();
+        let vk = &params.vk;
+
+        assert_eq!(vk.num_inputs(), circuit.num_inputs - 1);
+        assert_eq!(vk.ic_constant(), Some(&vk.ic[0]));
+        assert_eq!(vk.ic_input(0), Some(&vk.ic[1]));
+        assert_eq!(vk.ic_input(vk.num_inputs()), None);
+        assert_eq!(vk.ic_inputs(), &vk.ic[1..]);
+        assert!(vk.is_well_formed());
+
+        assert!(vk.check_circuit(&circuit).is_ok());
+        assert!(vk
+            .check_manifest(&InputManifest::unbounded(vk.num_inputs()))
+            .is_ok());
+        assert_eq!(
+            vk.check_manifest(&InputManifest::unbounded(vk.num_inputs() + 1)),
+            Err(InputError::Count {
+                expected: vk.num_inputs(),
+                actual: vk.num_inputs() + 1,
+            })
+        );
+
+        let mut truncated = vk.clone();
+        truncated.ic.pop();
+        assert!(truncated.check_circuit(&circuit).is_err());
+
+        let mut empty = vk.clone();
+        empty.ic.clear();
+        assert_eq!(empty.num_inputs(), 0);
+        assert!(empty.ic_inputs().is_empty());
+        assert!(!empty.is_well_formed());
+
+        let mut degenerate = vk.clone();
+        degenerate.delta_g2 = <Bls12 as Engine>::G2Affine::identity();
+        assert!(!degenerate.is_well_formed());
+    }
 }
@@ -0,0 +1,550 @@
+//! Phase-2 parameters for a circuit, derived from the powers of tau.
+//!
+//! A Groth16 trusted setup has two phases. The first, shared by every
+//! circuit up to some size, is a powers-of-tau ceremony whose output, a
+//! [`PowersOfTau`], holds `tau^i`, `alpha * tau^i` and `beta * tau^i` in the
+//! exponent for secrets `tau`, `alpha` and `beta` that nobody knows.
+//! [`MPCParameters::new`] derives from it the parameters of a circuit with
+//! `gamma` and `delta` equal to one, which involves no secret: anyone can
+//! derive them again, and they are of no use to forge proofs until `delta`
+//! is randomized.
+//!
+//! The second phase is a ceremony for the circuit, in which participants in
+//! turn [`contribute`] a secret to `delta` as in [`ceremony`], and pass the
+//! parameters on. [`verify_contribution`] checks one step, between the
+//! parameters a participant received and those they published, and
+//! [`MPCParameters::verify`] checks the whole chain against the circuit and
+//! the powers of tau. As long as one participant destroyed their secret, the
+//! parameters returned by [`MPCParameters::params`] are safe to use.
+//!
+//! The powers of tau are trusted: their own ceremony checks that they are
+//! consistent.
+//!
+//! [`contribute`]: MPCParameters::contribute
+//! [`ceremony`]: super::ceremony
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::Field;
+use group::{prime::PrimeCurve, prime::PrimeCurveAffine, Group as _, UncompressedEncoding};
+use pairing::Engine;
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use super::ceremony::{self, initial_transcript, verify_chain, Contribution, Report};
+use super::exporter::RawCircuit;
+use super::{read_points, Parameters, VerifyingKey};
+use crate::domain::{EvaluationDomain, Group};
+use crate::error::{Context, ErrorKind, Tracked};
+use crate::multicore::Worker;
+use crate::{Circuit, SynthesisError};
+
+/// The output of the first phase: powers of the secret `tau` in the
+/// exponent, and their products with the secrets `alpha` and `beta`.
+#[derive(Clone)]
+pub struct PowersOfTau<E: Engine> {
+    /// `tau^i` in G1, for `i < 2n - 1`.
+    pub tau_g1: Vec<E::G1Affine>,
+    /// `tau^i` in G2, for `i < n`.
+    pub tau_g2: Vec<E::G2Affine>,
+    /// `alpha * tau^i` in G1, for `i < n`.
+    pub alpha_tau_g1: Vec<E::G1Affine>,
+    /// `beta * tau^i` in G1, for `i < n`.
+    pub beta_tau_g1: Vec<E::G1Affine>,
+    /// `beta` in G2.
+    pub beta_g2: E::G2Affine,
+}
+
+impl<E: Engine> PowersOfTau<E> {
+    /// Returns the largest `n` these powers are given for, which bounds the
+    /// evaluation domain of the circuits they can be used for.
+    pub fn size(&self) -> usize {
+        ((self.tau_g1.len() + 1) / 2)
+            .min(self.tau_g2.len())
+            .min(self.alpha_tau_g1.len())
+            .min(self.beta_tau_g1.len())
+    }
+
+    /// Writes `beta` in G2, then each sequence of powers as a big-endian
+    /// `u32` length followed by its points, uncompressed.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        fn points<G: UncompressedEncoding, W: Write>(
+            writer: &mut W,
+            points: &[G],
+        ) -> io::Result<()> {
+            writer.write_u32::<BigEndian>(points.len() as u32)?;
+            for p in points {
+                writer.write_all(p.to_uncompressed().as_ref())?;
+            }
+            Ok(())
+        }
+
+        writer.write_all(self.beta_g2.to_uncompressed().as_ref())?;
+        points(&mut writer, &self.tau_g1)?;
+        points(&mut writer, &self.tau_g2)?;
+        points(&mut writer, &self.alpha_tau_g1)?;
+        points(&mut writer, &self.beta_tau_g1)
+    }
+
+    /// Reads powers written by [`PowersOfTau::write`]. If `checked`, the
+    /// points are checked to be in the prime-order subgroup.
+    pub fn read<R: Read>(reader: R, checked: bool) -> io::Result<Self> {
+        let worker = Worker::new();
+        let mut reader = Tracked::new(reader);
+        let kind = ErrorKind::MalformedParameters;
+
+        let beta_g2 = reader.within(kind, Context::Section("beta_g2"), |r| {
+            let mut repr = <E::G2Affine as UncompressedEncoding>::Uncompressed::default();
+            r.read_exact(repr.as_mut())?;
+            Option::from(E::G2Affine::from_uncompressed(&repr))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid G2"))
+        })?;
+        let tau_g1 = reader.within(kind, Context::Section("tau_g1"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let tau_g2 = reader.within(kind, Context::Section("tau_g2"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G2")
+        })?;
+        let alpha_tau_g1 = reader.within(kind, Context::Section("alpha_tau_g1"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+        let beta_tau_g1 = reader.within(kind, Context::Section("beta_tau_g1"), |r| {
+            read_points(r, &worker, kind, checked, "invalid G1")
+        })?;
+
+        Ok(PowersOfTau {
+            tau_g1,
+            tau_g2,
+            alpha_tau_g1,
+            beta_tau_g1,
+            beta_g2,
+        })
+    }
+}
+
+/// A point in projective form, for FFTs over the group.
+#[derive(Clone, Copy)]
+struct Projective<G: PrimeCurve>(G);
+
+impl<G: PrimeCurve> Group<G::Scalar> for Projective<G> {
+    fn group_zero() -> Self {
+        Projective(G::identity())
+    }
+    fn group_mul_assign(&mut self, by: &G::Scalar) {
+        self.0 *= by;
+    }
+    fn group_add_assign(&mut self, other: &Self) {
+        self.0 += other.0;
+    }
+    fn group_sub_assign(&mut self, other: &Self) {
+        self.0 -= other.0;
+    }
+}
+
+/// Converts the `m` first powers of tau to the Lagrange basis of the domain
+/// of size `m`, by an inverse FFT in the exponent.
+fn lagrange<G: PrimeCurve>(
+    worker: &Worker,
+    powers: &[G::Affine],
+    m: usize,
+) -> Result<Vec<G::Affine>, SynthesisError> {
+    let coeffs = powers[..m]
+        .iter()
+        .map(|p| Projective(p.to_curve()))
+        .collect();
+    let mut domain = EvaluationDomain::<G::Scalar, _>::from_coeffs(coeffs)?;
+    domain.ifft(worker);
+
+    let projective = domain
+        .into_coeffs()
+        .into_iter()
+        .map(|p| p.0)
+        .collect::<Vec<_>>();
+    let mut affine = vec![G::Affine::identity(); m];
+    G::batch_normalize(&projective, &mut affine);
+    Ok(affine)
+}
+
+/// Evaluates the polynomial of a column of the matrices at tau, given the
+/// Lagrange basis in the exponent.
+fn evaluate<G: PrimeCurve>(basis: &[G::Affine], column: &[(G::Scalar, usize)]) -> G {
+    column
+        .iter()
+        .fold(G::identity(), |acc, (coeff, j)| acc + basis[*j] * *coeff)
+}
+
+/// Derives the parameters of `circuit` from `powers`, with `gamma` and
+/// `delta` equal to one, as [`generate_parameters`] would from the secrets.
+///
+/// [`generate_parameters`]: super::generate_parameters
+fn derive<E: Engine>(
+    circuit: &RawCircuit<E::Fr>,
+    powers: &PowersOfTau<E>,
+) -> Result<Parameters<E>, SynthesisError> {
+    let worker = Worker::new();
+
+    // The prover adds a constraint `input * 0 = 0` for each input.
+    let mut at_inputs = circuit.at_inputs.clone();
+    for (i, column) in at_inputs.iter_mut().enumerate() {
+        column.push((E::Fr::one(), circuit.num_constraints + i));
+    }
+    let m = (circuit.num_constraints + circuit.num_inputs).next_power_of_two();
+    if m > powers.size() {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    let coeffs_g1 = lagrange::<E::G1>(&worker, &powers.tau_g1, m)?;
+    let coeffs_g2 = lagrange::<E::G2>(&worker, &powers.tau_g2, m)?;
+    let alpha_coeffs_g1 = lagrange::<E::G1>(&worker, &powers.alpha_tau_g1, m)?;
+    let beta_coeffs_g1 = lagrange::<E::G1>(&worker, &powers.beta_tau_g1, m)?;
+
+    // tau^i * t(tau) = tau^(i + m) - tau^i
+    let h = (0..m - 1)
+        .map(|i| powers.tau_g1[i + m].to_curve() - &powers.tau_g1[i])
+        .collect::<Vec<_>>();
+
+    let vars = circuit.num_inputs + circuit.num_aux;
+    let columns = |inputs: &[Vec<(E::Fr, usize)>], aux: &[Vec<(E::Fr, usize)>]| {
+        inputs.iter().chain(aux.iter()).cloned().collect::<Vec<_>>()
+    };
+    let at = columns(&at_inputs, &circuit.at_aux);
+    let bt = columns(&circuit.bt_inputs, &circuit.bt_aux);
+    let ct = columns(&circuit.ct_inputs, &circuit.ct_aux);
+
+    let mut a = vec![E::G1::identity(); vars];
+    let mut b_g1 = vec![E::G1::identity(); vars];
+    let mut b_g2 = vec![E::G2::identity(); vars];
+    let mut ext = vec![E::G1::identity(); vars];
+    worker.scope(vars, |scope, chunk| {
+        for ((((a, b_g1), b_g2), ext), (at, (bt, ct))) in a
+            .chunks_mut(chunk)
+            .zip(b_g1.chunks_mut(chunk))
+            .zip(b_g2.chunks_mut(chunk))
+            .zip(ext.chunks_mut(chunk))
+            .zip(at.chunks(chunk).zip(bt.chunks(chunk).zip(ct.chunks(chunk))))
+        {
+            let (coeffs_g1, coeffs_g2) = (&coeffs_g1, &coeffs_g2);
+            let (alpha_coeffs_g1, beta_coeffs_g1) = (&alpha_coeffs_g1, &beta_coeffs_g1);
+            scope.spawn(move |_| {
+                for (j, (((a, b_g1), b_g2), ext)) in a
+                    .iter_mut()
+                    .zip(b_g1.iter_mut())
+                    .zip(b_g2.iter_mut())
+                    .zip(ext.iter_mut())
+                    .enumerate()
+                {
+                    *a = evaluate::<E::G1>(coeffs_g1, &at[j]);
+                    *b_g1 = evaluate::<E::G1>(coeffs_g1, &bt[j]);
+                    *b_g2 = evaluate::<E::G2>(coeffs_g2, &bt[j]);
+                    *ext = [
+                        evaluate::<E::G1>(beta_coeffs_g1, &at[j]),
+                        evaluate::<E::G1>(alpha_coeffs_g1, &bt[j]),
+                        evaluate::<E::G1>(coeffs_g1, &ct[j]),
+                    ]
+                    .iter()
+                    .sum();
+                }
+            });
+        }
+    });
+
+    fn normalize<G: PrimeCurve>(points: &[G]) -> Vec<G::Affine> {
+        let mut affine = vec![G::Affine::identity(); points.len()];
+        G::batch_normalize(points, &mut affine);
+        affine
+    }
+    let nonzero = |points: Vec<_>| -> Vec<_> {
+        points
+            .into_iter()
+            .filter(|p: &E::G1Affine| bool::from(!p.is_identity()))
+            .collect()
+    };
+
+    let mut ext = normalize(&ext);
+    let l = ext.split_off(circuit.num_inputs);
+    if l.iter().any(|p| bool::from(p.is_identity())) {
+        return Err(SynthesisError::UnconstrainedVariable);
+    }
+
+    let g1 = powers.tau_g1[0];
+    let g2 = powers.tau_g2[0];
+    let vk = VerifyingKey {
+        alpha_g1: powers.alpha_tau_g1[0],
+        beta_g1: powers.beta_tau_g1[0],
+        beta_g2: powers.beta_g2,
+        gamma_g2: g2,
+        delta_g1: g1,
+        delta_g2: g2,
+        ic: ext,
+    };
+
+    Ok(Parameters {
+        vk,
+        h: Arc::new(normalize(&h)),
+        l: Arc::new(l),
+        a: Arc::new(nonzero(normalize(&a))),
+        b_g1: Arc::new(nonzero(normalize(&b_g1))),
+        b_g2: Arc::new(
+            normalize(&b_g2)
+                .into_iter()
+                .filter(|p| bool::from(!p.is_identity()))
+                .collect(),
+        ),
+    })
+}
+
+/// The parameters of a circuit during the second phase, with the
+/// contributions made to them so far.
+#[derive(Clone)]
+pub struct MPCParameters<E: Engine> {
+    params: Parameters<E>,
+    /// The fingerprint of the circuit.
+    circuit: [u8; 32],
+    /// The transcript of the parameters derived from the powers of tau.
+    initial: [u8; 32],
+    contributions: Vec<Contribution<E>>,
+}
+
+impl<E: Engine> PartialEq for MPCParameters<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.params == other.params
+            && self.circuit == other.circuit
+            && self.initial == other.initial
+            && self.contributions == other.contributions
+    }
+}
+
+impl<E: Engine> MPCParameters<E> {
+    /// Derives the parameters of `circuit` from `powers`, before any
+    /// contribution.
+    pub fn new<C: Circuit<E::Fr>>(
+        circuit: C,
+        powers: &PowersOfTau<E>,
+    ) -> Result<Self, SynthesisError> {
+        let circuit = RawCircuit::synthesize(circuit)?;
+        let params = derive(&circuit, powers)?;
+
+        Ok(MPCParameters {
+            initial: initial_transcript(&params),
+            circuit: circuit.fingerprint(),
+            params,
+            contributions: vec![],
+        })
+    }
+
+    /// Returns the parameters, which can be used to prove once the
+    /// ceremony is over.
+    pub fn params(&self) -> &Parameters<E> {
+        &self.params
+    }
+
+    pub fn into_params(self) -> Parameters<E> {
+        self.params
+    }
+
+    pub fn contributions(&self) -> &[Contribution<E>] {
+        &self.contributions
+    }
+
+    /// Returns the transcript that the next contribution extends.
+    pub fn transcript(&self) -> [u8; 32] {
+        self.contributions
+            .last()
+            .map_or(self.initial, Contribution::hash)
+    }
+
+    /// Contributes a random secret to the parameters, and returns the hash
+    /// of the contribution, which the participant publishes so that others
+    /// can check it is part of the final transcript. The secret is erased
+    /// before this returns.
+    pub fn contribute<R: RngCore>(&mut self, rng: &mut R) -> [u8; 32] {
+        let transcript = self.transcript();
+        let contribution = ceremony::contribute(&mut self.params, &transcript, rng);
+        let hash = contribution.hash();
+        self.contributions.push(contribution);
+        hash
+    }
+
+    /// Verifies that these parameters result from the contributions they
+    /// list, in order, applied to the parameters of `circuit` derived from
+    /// `powers`. `rng` is used to batch the checks of the `H` and `L`
+    /// queries.
+    pub fn verify<C: Circuit<E::Fr>, R: RngCore>(
+        &self,
+        circuit: C,
+        powers: &PowersOfTau<E>,
+        rng: &mut R,
+    ) -> Result<Report, SynthesisError> {
+        let circuit = RawCircuit::synthesize(circuit)?;
+        let initial = derive(&circuit, powers)?;
+
+        let mut report =
+            ceremony::verify_transcript(&circuit, &initial, &self.contributions, &self.params, rng);
+        report.check(
+            self.circuit == circuit.fingerprint() && self.initial == report.initial,
+            "parameters are for the circuit and the powers of tau".to_string(),
+        );
+        Ok(report)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.params.write(&mut writer)?;
+        writer.write_all(&self.circuit)?;
+        writer.write_all(&self.initial)?;
+        writer.write_u32::<BigEndian>(self.contributions.len() as u32)?;
+        for contribution in &self.contributions {
+            contribution.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// Reads parameters written by [`MPCParameters::write`]. If `checked`,
+    /// the points of the parameters are checked to be in the prime-order
+    /// subgroup; those of the contributions always are.
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
+        let params = Parameters::read(&mut reader, checked)?;
+        let mut circuit = [0; 32];
+        reader.read_exact(&mut circuit)?;
+        let mut initial = [0; 32];
+        reader.read_exact(&mut initial)?;
+        let len = reader.read_u32::<BigEndian>()? as usize;
+        let contributions = (0..len)
+            .map(|_| Contribution::read(&mut reader))
+            .collect::<io::Result<_>>()?;
+
+        Ok(MPCParameters {
+            params,
+            circuit,
+            initial,
+            contributions,
+        })
+    }
+}
+
+/// Verifies that `after` results from one contribution to `before`, as a
+/// participant publishes it, and returns the checks made. `rng` is used to
+/// batch the checks of the `H` and `L` queries.
+pub fn verify_contribution<E: Engine, R: RngCore>(
+    before: &MPCParameters<E>,
+    after: &MPCParameters<E>,
+    rng: &mut R,
+) -> Report {
+    let n = before.contributions.len();
+    let added = after.contributions.get(n..).unwrap_or(&[]);
+    let mut report = Report {
+        initial: before.transcript(),
+        contributions: added.iter().map(Contribution::hash).collect(),
+        checks: vec![],
+    };
+
+    report.check(
+        before.circuit == after.circuit && before.initial == after.initial,
+        "parameters are for the same circuit".to_string(),
+    );
+    report.check(
+        added.len() == 1 && after.contributions[..n] == before.contributions[..],
+        "one contribution is added to the previous ones".to_string(),
+    );
+    verify_chain(&mut report, &before.params, added, &after.params, rng);
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{
+        create_random_proof, generate_parameters, prepare_verifying_key, verify_proof,
+    };
+    use bls12_381::{Bls12, G1Projective, G2Projective, Scalar};
+    use group::Curve;
+
+    /// Powers of tau for known secrets, as only a test can have them.
+    fn powers(n: usize, tau: Scalar, alpha: Scalar, beta: Scalar) -> PowersOfTau<Bls12> {
+        let power = |i: usize| Field::pow_vartime(&tau, [i as u64]);
+        let g1 = |x: Scalar| (G1Projective::generator() * x).to_affine();
+        let g2 = |x: Scalar| (G2Projective::generator() * x).to_affine();
+        PowersOfTau {
+            tau_g1: (0..2 * n - 1).map(|i| g1(power(i))).collect(),
+            tau_g2: (0..n).map(|i| g2(power(i))).collect(),
+            alpha_tau_g1: (0..n).map(|i| g1(alpha * power(i))).collect(),
+            beta_tau_g1: (0..n).map(|i| g1(beta * power(i))).collect(),
+            beta_g2: g2(beta),
+        }
+    }
+
+    #[test]
+    fn phase2_ceremony() {
+        let mut f = Fixture::<Bls12>::new();
+        let (tau, alpha, beta) = (
+            Scalar::random(&mut f.rng),
+            Scalar::random(&mut f.rng),
+            Scalar::random(&mut f.rng),
+        );
+        let powers = powers(32, tau, alpha, beta);
+
+        let mut bytes = vec![];
+        powers.write(&mut bytes).unwrap();
+        let read = PowersOfTau::<Bls12>::read(&bytes[..], true).unwrap();
+        assert!(read.tau_g1 == powers.tau_g1 && read.beta_g2 == powers.beta_g2);
+        assert_eq!(read.size(), 32);
+
+        // The derived parameters are those generated from the secrets.
+        let mut mpc = MPCParameters::new(f.shape(), &powers).unwrap();
+        let generated = generate_parameters::<Bls12, _>(
+            f.shape(),
+            G1Projective::generator(),
+            G2Projective::generator(),
+            alpha,
+            beta,
+            Scalar::one(),
+            Scalar::one(),
+            tau,
+        )
+        .unwrap();
+        assert!(mpc.params() == &generated);
+
+        let mut hashes = vec![];
+        for _ in 0..3 {
+            let before = mpc.clone();
+            hashes.push(mpc.contribute(&mut f.rng));
+            let report = verify_contribution(&before, &mpc, &mut f.rng);
+            assert!(report.is_valid(), "{}", report);
+            assert_eq!(report.contributions, [*hashes.last().unwrap()]);
+        }
+
+        let mut bytes = vec![];
+        mpc.write(&mut bytes).unwrap();
+        assert!(MPCParameters::<Bls12>::read(&bytes[..], true).unwrap() == mpc);
+
+        let report = mpc.verify(f.shape(), &powers, &mut f.rng).unwrap();
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(report.contributions, hashes);
+
+        let params = mpc.clone().into_params();
+        let proof = create_random_proof(f.circuit(), &params, &mut f.rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, f.inputs()).is_ok());
+
+        // Two contributions at once, or none, are not one step.
+        let mut skipped = mpc.clone();
+        skipped.contribute(&mut f.rng);
+        skipped.contribute(&mut f.rng);
+        assert!(!verify_contribution(&mpc, &skipped, &mut f.rng).is_valid());
+        assert!(!verify_contribution(&mpc, &mpc, &mut f.rng).is_valid());
+
+        // Parameters for other powers of tau.
+        let other = super::tests::powers(32, tau + Scalar::one(), alpha, beta);
+        assert!(!mpc
+            .verify(f.shape(), &other, &mut f.rng)
+            .unwrap()
+            .is_valid());
+
+        // Powers of tau too small for the circuit.
+        assert!(matches!(
+            MPCParameters::new(f.shape(), &super::tests::powers(4, tau, alpha, beta)),
+            Err(SynthesisError::PolynomialDegreeTooLarge)
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+//! Mutation testing of verifiers.
+//!
+//! A verifier that accepts every valid proof can still accept invalid ones,
+//! if it skips a check: the length of the public inputs, a term of the
+//! verification equation, or an element of the verifying key. Given a valid
+//! proof, [`check_rejections`] perturbs each point of the proof, each
+//! public input and each element of the verifying key that verification
+//! uses, one at a time, and records whether the verifier under test
+//! rejected the result. A [`Report`] lists every mutation with its outcome,
+//! so that an audit can see what was tried as well as what got through.
+//!
+//! The mutations that change a point by a random scalar draw it from the
+//! RNG that is passed in, so that a seeded RNG makes a run reproducible.
+//! The IC element of a public input that is zero does not contribute to
+//! verification, and is not mutated.
+
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Curve, GroupOps, GroupOpsOwned};
+use pairing::MultiMillerLoop;
+use rand_core::RngCore;
+use std::fmt;
+
+use super::{prepare_verifying_key, verify_proof, Proof, VerifyingKey};
+
+/// A point of a proof or of a verifying key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Point {
+    ProofA,
+    ProofB,
+    ProofC,
+    AlphaG1,
+    BetaG2,
+    GammaG2,
+    DeltaG2,
+    Ic(usize),
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Point::ProofA => f.write_str("proof.a"),
+            Point::ProofB => f.write_str("proof.b"),
+            Point::ProofC => f.write_str("proof.c"),
+            Point::AlphaG1 => f.write_str("vk.alpha_g1"),
+            Point::BetaG2 => f.write_str("vk.beta_g2"),
+            Point::GammaG2 => f.write_str("vk.gamma_g2"),
+            Point::DeltaG2 => f.write_str("vk.delta_g2"),
+            Point::Ic(i) => write!(f, "vk.ic[{}]", i),
+        }
+    }
+}
+
+/// How a [`Point`] is changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Replaced by the point at infinity.
+    Identity,
+    /// Negated.
+    Negated,
+    /// Added to the generator.
+    PlusGenerator,
+    /// Multiplied by a random scalar other than zero and one.
+    Scaled,
+}
+
+/// A change to a valid proof, its public inputs or its verifying key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    Point(Point, Change),
+    /// The `A` and `C` points of the proof are swapped.
+    SwappedAC,
+    /// The public input at the index is incremented.
+    IncrementedInput(usize),
+    /// The public input at the index is replaced by a random value.
+    RandomizedInput(usize),
+    /// The last public input is removed.
+    DroppedInput,
+    /// A zero is appended to the public inputs.
+    AppendedInput,
+    /// The last IC element is removed.
+    DroppedIc,
+    /// A copy of the first IC element is appended.
+    AppendedIc,
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mutation::Point(point, Change::Identity) => write!(f, "{} set to infinity", point),
+            Mutation::Point(point, Change::Negated) => write!(f, "{} negated", point),
+            Mutation::Point(point, Change::PlusGenerator) => {
+                write!(f, "{} plus the generator", point)
+            }
+            Mutation::Point(point, Change::Scaled) => write!(f, "{} scaled", point),
+            Mutation::SwappedAC => f.write_str("proof.a and proof.c swapped"),
+            Mutation::IncrementedInput(i) => write!(f, "input {} incremented", i),
+            Mutation::RandomizedInput(i) => write!(f, "input {} randomized", i),
+            Mutation::DroppedInput => f.write_str("last input dropped"),
+            Mutation::AppendedInput => f.write_str("zero input appended"),
+            Mutation::DroppedIc => f.write_str("last IC element dropped"),
+            Mutation::AppendedIc => f.write_str("IC element appended"),
+        }
+    }
+}
+
+/// Whether the verifier rejected a mutation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub mutation: Mutation,
+    pub rejected: bool,
+}
+
+/// The result of [`check_rejections`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// Whether the verifier accepted the proof before any mutation.
+    pub accepted_original: bool,
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Report {
+    /// Returns `true` if the verifier accepted the original proof and
+    /// rejected every mutation.
+    pub fn is_valid(&self) -> bool {
+        self.accepted_original && self.outcomes.iter().all(|outcome| outcome.rejected)
+    }
+
+    /// Returns the mutations that the verifier accepted.
+    pub fn accepted(&self) -> impl Iterator<Item = Mutation> + '_ {
+        self.outcomes
+            .iter()
+            .filter(|outcome| !outcome.rejected)
+            .map(|outcome| outcome.mutation)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = if self.accepted_original {
+            "ok"
+        } else {
+            "FAILED"
+        };
+        writeln!(f, "{:<8} original proof accepted", status)?;
+        for outcome in &self.outcomes {
+            let status = if outcome.rejected { "ok" } else { "ACCEPTED" };
+            writeln!(f, "{:<8} {}", status, outcome.mutation)?;
+        }
+        let result = if self.is_valid() { "sound" } else { "UNSOUND" };
+        writeln!(
+            f,
+            "verifier is {} on {} mutations",
+            result,
+            self.outcomes.len()
+        )
+    }
+}
+
+/// Returns a scalar other than zero and one.
+fn random_scale<F: Field, R: RngCore>(rng: &mut R) -> F {
+    loop {
+        let scalar = F::random(&mut *rng);
+        if !scalar.is_zero() && scalar != F::one() {
+            return scalar;
+        }
+    }
+}
+
+// The bound is implied by `PrimeCurveAffine`, but older compilers do not see it.
+fn change<G: PrimeCurveAffine>(point: &G, change: Change, scalar: G::Scalar) -> G
+where
+    G::Curve: GroupOps<G> + GroupOpsOwned<G>,
+{
+    match change {
+        Change::Identity => G::identity(),
+        Change::Negated => -*point,
+        Change::PlusGenerator => (point.to_curve() + &G::generator().to_curve()).to_affine(),
+        Change::Scaled => (*point * scalar).to_affine(),
+    }
+}
+
+/// Verifies `proof` of `inputs` with `verifier`, then each mutation of
+/// them, and reports which were rejected. `verifier` returns whether it
+/// accepts a proof; [`groth16_verifier`] is the verifier of this crate.
+pub fn check_rejections<E, R, V>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    inputs: &[E::Fr],
+    rng: &mut R,
+    mut verifier: V,
+) -> Report
+where
+    E: MultiMillerLoop,
+    R: RngCore,
+    V: FnMut(&VerifyingKey<E>, &Proof<E>, &[E::Fr]) -> bool,
+{
+    let mut report = Report {
+        accepted_original: verifier(vk, proof, inputs),
+        outcomes: vec![],
+    };
+    let mut record = |mutation, vk: &VerifyingKey<E>, proof: &Proof<E>, inputs: &[E::Fr]| {
+        report.outcomes.push(Outcome {
+            mutation,
+            rejected: !verifier(vk, proof, inputs),
+        });
+    };
+
+    let mut points = vec![
+        Point::ProofA,
+        Point::ProofB,
+        Point::ProofC,
+        Point::AlphaG1,
+        Point::BetaG2,
+        Point::GammaG2,
+        Point::DeltaG2,
+        Point::Ic(0),
+    ];
+    points.extend(
+        (0..inputs.len())
+            .filter(|&i| !inputs[i].is_zero())
+            .map(|i| Point::Ic(i + 1)),
+    );
+    let changes = [
+        Change::Identity,
+        Change::Negated,
+        Change::PlusGenerator,
+        Change::Scaled,
+    ];
+    for &point in &points {
+        for &c in &changes {
+            let scalar = random_scale(&mut *rng);
+            let mut vk = vk.clone();
+            let mut proof = proof.clone();
+            match point {
+                Point::ProofA => proof.a = change(&proof.a, c, scalar),
+                Point::ProofB => proof.b = change(&proof.b, c, scalar),
+                Point::ProofC => proof.c = change(&proof.c, c, scalar),
+                Point::AlphaG1 => vk.alpha_g1 = change(&vk.alpha_g1, c, scalar),
+                Point::BetaG2 => vk.beta_g2 = change(&vk.beta_g2, c, scalar),
+                Point::GammaG2 => vk.gamma_g2 = change(&vk.gamma_g2, c, scalar),
+                Point::DeltaG2 => vk.delta_g2 = change(&vk.delta_g2, c, scalar),
+                Point::Ic(i) => vk.ic[i] = change(&vk.ic[i], c, scalar),
+            }
+            record(Mutation::Point(point, c), &vk, &proof, inputs);
+        }
+    }
+
+    let mut swapped = proof.clone();
+    std::mem::swap(&mut swapped.a, &mut swapped.c);
+    record(Mutation::SwappedAC, vk, &swapped, inputs);
+
+    for i in 0..inputs.len() {
+        let mut changed = inputs.to_vec();
+        changed[i] += &E::Fr::one();
+        record(Mutation::IncrementedInput(i), vk, proof, &changed);
+
+        let mut changed = inputs.to_vec();
+        changed[i] = E::Fr::random(&mut *rng);
+        record(Mutation::RandomizedInput(i), vk, proof, &changed);
+    }
+    if let Some((_, rest)) = inputs.split_last() {
+        record(Mutation::DroppedInput, vk, proof, rest);
+    }
+    let mut appended = inputs.to_vec();
+    appended.push(E::Fr::zero());
+    record(Mutation::AppendedInput, vk, proof, &appended);
+
+    if !vk.ic.is_empty() {
+        let mut dropped = vk.clone();
+        dropped.ic.pop();
+        record(Mutation::DroppedIc, &dropped, proof, inputs);
+
+        let mut appended = vk.clone();
+        appended.ic.push(vk.ic[0]);
+        record(Mutation::AppendedIc, &appended, proof, inputs);
+    }
+
+    report
+}
+
+/// Returns whether [`verify_proof`] accepts `proof`, for
+/// [`check_rejections`].
+pub fn groth16_verifier<E: MultiMillerLoop>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    inputs: &[E::Fr],
+) -> bool {
+    verify_proof(&prepare_verifying_key(vk), proof, inputs).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{verify_proof_prepared, verify_proofs_batch, PreparedInputs};
+    use bls12_381::{Bls12, Scalar};
+
+    #[test]
+    fn verifiers_reject_mutations() {
+        let mut f = Fixture::<Bls12>::new();
+        let proof = f.proof();
+        let Fixture {
+            mut rng,
+            params,
+            witness,
+            ..
+        } = f;
+        let inputs = &witness.inputs[1..];
+
+        let report = check_rejections(&params.vk, &proof, inputs, &mut rng, groth16_verifier);
+        assert!(report.is_valid(), "{}", report);
+        // Four changes to each of the 3 points of the proof, the 4 of the
+        // key and the 3 IC elements, a swap, two per input and four lengths.
+        assert_eq!(report.outcomes.len(), 4 * (3 + 4 + 3) + 1 + 2 * 2 + 4);
+        assert!(report
+            .to_string()
+            .ends_with("verifier is sound on 49 mutations\n"));
+
+        let mut batch_rng = rng.clone();
+        let batched = |vk: &VerifyingKey<Bls12>, proof: &Proof<Bls12>, inputs: &[Scalar]| {
+            verify_proofs_batch(
+                &prepare_verifying_key(vk),
+                &[(proof, inputs)],
+                &mut batch_rng,
+            )
+            .is_ok()
+        };
+        assert!(check_rejections(&params.vk, &proof, inputs, &mut rng, batched).is_valid());
+
+        let prepared = |vk: &VerifyingKey<Bls12>, proof: &Proof<Bls12>, inputs: &[Scalar]| {
+            let pvk = prepare_verifying_key(vk).with_input_tables(4);
+            match PreparedInputs::new(&pvk, inputs) {
+                Ok(inputs) => verify_proof_prepared(&pvk, proof, &inputs).is_ok(),
+                Err(_) => false,
+            }
+        };
+        assert!(check_rejections(&params.vk, &proof, inputs, &mut rng, prepared).is_valid());
+
+        // A verifier that ignores extra inputs, rather than rejecting them,
+        // is caught.
+        let lax = |vk: &VerifyingKey<Bls12>, proof: &Proof<Bls12>, inputs: &[Scalar]| {
+            let len = std::cmp::min(inputs.len(), vk.num_inputs());
+            groth16_verifier(vk, proof, &inputs[..len])
+        };
+        let report = check_rejections(&params.vk, &proof, inputs, &mut rng, lax);
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.accepted().collect::<Vec<_>>(),
+            [Mutation::AppendedInput]
+        );
+        assert!(report
+            .to_string()
+            .contains("ACCEPTED zero input appended\n"));
+    }
+}
@@ -0,0 +1,917 @@
+//! An optimizer for R1CS matrices, run before parameter generation.
+//!
+//! Circuits built by composing gadgets naively often carry redundancy:
+//! variables that are constrained to constants, identical constraints, and
+//! linear combinations with repeated or cancelling terms. An [`Optimizer`]
+//! runs a pipeline of [`Pass`]es over a [`RawCircuit`] to remove them, and
+//! records the [`CircuitStats`] after each pass.
+//!
+//! Passes never remove inputs. The [`Optimized`] result maps the auxiliary
+//! variables of the original circuit to those of the optimized one, and
+//! records how to compute the variables that passes such as [`PackWires`]
+//! add, so that a witness for the original circuit can be carried over with
+//! [`Optimized::assignment`].
+//!
+//! The optimized circuit goes through parameter generation and the prover in
+//! place of the original with [`Optimized::circuit`], which wraps the
+//! original circuit and synthesizes the optimized matrices with its witness
+//! carried over:
+//!
+//! ```ignore
+//! let optimized = Optimizer::default().optimize_circuit(MyCircuit { x: None })?;
+//! let params = generate_random_parameters(optimized.circuit(MyCircuit { x: None }), rng)?;
+//! let proof = create_random_proof(optimized.circuit(MyCircuit { x: Some(x) }), &params, rng)?;
+//! ```
+
+use ff::PrimeField;
+use std::collections::{HashMap, HashSet};
+
+use super::cost_model::CircuitStats;
+use super::exporter::{Assignment, RawCircuit, ReplayCircuit};
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// A circuit as rows of linear combinations, which passes operate on.
+pub struct R1cs<S: PrimeField> {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub constraints: Vec<[LinearCombination<S>; 3]>,
+    /// For each auxiliary variable of the original circuit, its index in
+    /// this one, if it has not been removed.
+    pub aux_map: Vec<Option<usize>>,
+    /// The auxiliary variables of this circuit that are not in the original
+    /// one: their index, and their value as a combination of the auxiliary
+    /// variables of the original circuit.
+    pub derived: Vec<(usize, Vec<(usize, S)>)>,
+}
+
+impl<S: PrimeField> R1cs<S> {
+    fn from_circuit(circuit: &RawCircuit<S>) -> Self {
+        R1cs {
+            num_inputs: circuit.num_inputs,
+            num_aux: circuit.num_aux,
+            constraints: circuit.constraints(),
+            aux_map: (0..circuit.num_aux).map(Some).collect(),
+            derived: vec![],
+        }
+    }
+
+    fn to_circuit(&self) -> RawCircuit<S> {
+        let mut circuit = RawCircuit {
+            num_inputs: self.num_inputs,
+            num_aux: self.num_aux,
+            num_constraints: 0,
+            at_inputs: vec![vec![]; self.num_inputs],
+            bt_inputs: vec![vec![]; self.num_inputs],
+            ct_inputs: vec![vec![]; self.num_inputs],
+            at_aux: vec![vec![]; self.num_aux],
+            bt_aux: vec![vec![]; self.num_aux],
+            ct_aux: vec![vec![]; self.num_aux],
+        };
+        for [a, b, c] in self.constraints.iter() {
+            circuit.enforce(|| "", |_| a.clone(), |_| b.clone(), |_| c.clone());
+        }
+
+        circuit
+    }
+}
+
+/// A transformation of a circuit that preserves its satisfiability: every
+/// witness of the original circuit, mapped through `aux_map`, satisfies the
+/// transformed one.
+pub trait Pass<S: PrimeField> {
+    fn name(&self) -> &'static str;
+
+    fn run(&self, r1cs: &mut R1cs<S>);
+}
+
+fn order(var: &Variable) -> (u8, usize) {
+    match var.get_unchecked() {
+        Index::Input(i) => (0, i),
+        Index::Aux(i) => (1, i),
+    }
+}
+
+/// Sorts the terms of a linear combination by variable, merges repeated
+/// variables, and drops zero coefficients.
+fn canonicalize<S: PrimeField>(lc: &mut LinearCombination<S>) {
+    lc.0.sort_by_key(|(var, _)| order(var));
+
+    let mut terms: Vec<(Variable, S)> = Vec::with_capacity(lc.0.len());
+    for (var, coeff) in lc.0.drain(..) {
+        match terms.last_mut() {
+            Some((last, acc)) if order(last) == order(&var) => acc.add_assign(&coeff),
+            _ => terms.push((var, coeff)),
+        }
+    }
+    terms.retain(|(_, coeff)| !coeff.is_zero());
+
+    lc.0 = terms;
+}
+
+/// Puts every linear combination in canonical form.
+pub struct Canonicalize;
+
+impl<S: PrimeField> Pass<S> for Canonicalize {
+    fn name(&self) -> &'static str {
+        "canonicalize"
+    }
+
+    fn run(&self, r1cs: &mut R1cs<S>) {
+        for lc in r1cs.constraints.iter_mut().flat_map(|row| row.iter_mut()) {
+            canonicalize(lc);
+        }
+    }
+}
+
+/// Returns `Some(k)` if the canonical `lc` is the constant `k`.
+fn constant<S: PrimeField>(lc: &LinearCombination<S>) -> Option<S> {
+    match lc.0.as_slice() {
+        [] => Some(S::zero()),
+        [(var, coeff)] if order(var) == (0, 0) => Some(*coeff),
+        _ => None,
+    }
+}
+
+/// Returns the linear combination `L` such that the constraint is `L = 0`,
+/// if it is linear.
+fn linear<S: PrimeField>(row: &[LinearCombination<S>; 3]) -> Option<LinearCombination<S>> {
+    let [a, b, c] = row;
+    let (k, other) = match (constant(a), constant(b)) {
+        (Some(k), _) => (k, b),
+        (None, Some(k)) => (k, a),
+        (None, None) => return None,
+    };
+
+    let mut l = LinearCombination::zero();
+    for (var, coeff) in other.0.iter() {
+        l.0.push((*var, *coeff * k));
+    }
+    for (var, coeff) in c.0.iter() {
+        l.0.push((*var, coeff.neg()));
+    }
+    canonicalize(&mut l);
+
+    Some(l)
+}
+
+/// Finds auxiliary variables that constraints fix to a constant, substitutes
+/// the constant for them everywhere, and drops constraints that become
+/// trivially satisfied.
+///
+/// A constraint `A * B = C` in which `A` is a constant `k` is the linear
+/// constraint `k B - C = 0`, and similarly when `B` is constant. If that
+/// linear constraint is on a single auxiliary variable, it fixes its value.
+/// Constraints that reduce to `0 = 0` are removed; those that reduce to a
+/// false statement are kept, so that the circuit stays unsatisfiable.
+pub struct ConstantPropagation;
+
+impl<S: PrimeField> Pass<S> for ConstantPropagation {
+    fn name(&self) -> &'static str {
+        "constant-propagation"
+    }
+
+    fn run(&self, r1cs: &mut R1cs<S>) {
+        for lc in r1cs.constraints.iter_mut().flat_map(|row| row.iter_mut()) {
+            canonicalize(lc);
+        }
+
+        let mut i = 0;
+        while i < r1cs.constraints.len() {
+            let l = match linear(&r1cs.constraints[i]) {
+                Some(l) => l,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            // Split L into its constant term and the rest.
+            let mut offset = S::zero();
+            let mut terms = vec![];
+            for (var, coeff) in l.0.iter() {
+                if order(var) == (0, 0) {
+                    offset = *coeff;
+                } else {
+                    terms.push((*var, *coeff));
+                }
+            }
+
+            match terms.as_slice() {
+                [] if offset.is_zero() => {
+                    r1cs.constraints.remove(i);
+                }
+                [(var, coeff)] if order(var).0 == 1 => {
+                    // coeff * x + offset = 0
+                    let value = offset.neg() * coeff.invert().unwrap();
+                    let x = *var;
+                    r1cs.constraints.remove(i);
+                    for lc in r1cs.constraints.iter_mut().flat_map(|row| row.iter_mut()) {
+                        if lc.0.iter().any(|(v, _)| order(v) == order(&x)) {
+                            for term in lc.0.iter_mut() {
+                                if order(&term.0) == order(&x) {
+                                    *term =
+                                        (Variable::new_unchecked(Index::Input(0)), term.1 * value);
+                                }
+                            }
+                            canonicalize(lc);
+                        }
+                    }
+
+                    // Earlier constraints may have become linear.
+                    i = 0;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+/// Removes constraints that are identical to an earlier one, up to swapping
+/// `A` and `B`. Linear combinations must be canonical for duplicates to be
+/// found.
+pub struct RemoveDuplicates;
+
+impl<S: PrimeField> Pass<S> for RemoveDuplicates {
+    fn name(&self) -> &'static str {
+        "remove-duplicates"
+    }
+
+    fn run(&self, r1cs: &mut R1cs<S>) {
+        fn encode<S: PrimeField>(lc: &LinearCombination<S>) -> Vec<u8> {
+            let mut bytes = vec![];
+            for (var, coeff) in lc.0.iter() {
+                let (kind, index) = order(var);
+                bytes.push(kind);
+                bytes.extend_from_slice(&(index as u64).to_le_bytes());
+                bytes.extend_from_slice(coeff.to_repr().as_ref());
+            }
+            bytes
+        }
+
+        let mut seen = HashSet::new();
+        r1cs.constraints.retain(|[a, b, c]| {
+            let (mut a, mut b) = (encode(a), encode(b));
+            if a > b {
+                std::mem::swap(&mut a, &mut b);
+            }
+            // Lengths separate the three encodings.
+            let mut key = vec![];
+            for part in [a, b, encode(c)].iter() {
+                key.extend_from_slice(&(part.len() as u64).to_le_bytes());
+                key.extend_from_slice(part);
+            }
+            seen.insert(key)
+        });
+    }
+}
+
+/// Removes auxiliary variables that appear in no constraint, and renumbers
+/// the others.
+pub struct RemoveDeadVariables;
+
+impl<S: PrimeField> Pass<S> for RemoveDeadVariables {
+    fn name(&self) -> &'static str {
+        "remove-dead-variables"
+    }
+
+    fn run(&self, r1cs: &mut R1cs<S>) {
+        let mut live = vec![false; r1cs.num_aux];
+        for lc in r1cs.constraints.iter().flat_map(|row| row.iter()) {
+            for (var, _) in lc.0.iter() {
+                if let Index::Aux(i) = var.get_unchecked() {
+                    live[i] = true;
+                }
+            }
+        }
+
+        let mut renumbered = vec![None; r1cs.num_aux];
+        let mut next = 0;
+        for (i, live) in live.iter().enumerate() {
+            if *live {
+                renumbered[i] = Some(next);
+                next += 1;
+            }
+        }
+
+        for lc in r1cs.constraints.iter_mut().flat_map(|row| row.iter_mut()) {
+            for (var, _) in lc.0.iter_mut() {
+                if let Index::Aux(i) = var.get_unchecked() {
+                    *var = Variable::new_unchecked(Index::Aux(renumbered[i].unwrap()));
+                }
+            }
+        }
+        for entry in r1cs.aux_map.iter_mut() {
+            *entry = entry.and_then(|i| renumbered[i]);
+        }
+        r1cs.derived = r1cs
+            .derived
+            .drain(..)
+            .filter_map(|(i, combination)| renumbered[i].map(|i| (i, combination)))
+            .collect();
+        r1cs.num_aux = next;
+    }
+}
+
+/// Returns `Some(x)` if the canonical `row` only enforces that the auxiliary
+/// variable `x` is boolean, as `x * x = x` or `(1 - x) * x = 0`.
+fn booleanity<S: PrimeField>(row: &[LinearCombination<S>; 3]) -> Option<usize> {
+    let single = |lc: &LinearCombination<S>| match lc.0.as_slice() {
+        [(var, coeff)] if *coeff == S::one() => match var.get_unchecked() {
+            Index::Aux(i) => Some(i),
+            Index::Input(_) => None,
+        },
+        _ => None,
+    };
+    let complement = |lc: &LinearCombination<S>, x| match lc.0.as_slice() {
+        [(one, a), (var, b)] => {
+            order(one) == (0, 0) && order(var) == (1, x) && *a == S::one() && *b == S::one().neg()
+        }
+        _ => false,
+    };
+
+    let [a, b, c] = row;
+    match (single(a), single(b), single(c)) {
+        (Some(x), Some(y), Some(z)) if x == y && y == z => Some(x),
+        (Some(x), None, None) if c.0.is_empty() && complement(b, x) => Some(x),
+        (None, Some(x), None) if c.0.is_empty() && complement(a, x) => Some(x),
+        _ => None,
+    }
+}
+
+/// A group of auxiliary variables that constraints only use through one
+/// linear combination of them, found by [`PackWires::analyze`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WireGroup<S: PrimeField> {
+    /// The variables and their coefficients in the combination.
+    pub members: Vec<(usize, S)>,
+    /// The linear combinations that use the group: the index of the
+    /// constraint, 0, 1 or 2 for `A`, `B` or `C`, and the coefficient of the
+    /// combination in it.
+    pub uses: Vec<(usize, usize, S)>,
+    /// Whether some members are also constrained individually to be
+    /// boolean.
+    pub range_checked: bool,
+}
+
+/// Replaces groups of auxiliary variables that are only used packed
+/// together with a single variable for the packed value.
+///
+/// Boolean decompositions are often only consumed through the number they
+/// pack: every constraint that uses the bits, apart from those enforcing
+/// that each is boolean, uses them through the same weighted sum. This pass
+/// allocates a variable for that sum and substitutes it in every such
+/// constraint. The bits are then only used by their boolean constraints and
+/// by a single new constraint that unpacks the packed variable into them,
+/// which shortens the linear combinations of the other constraints. Groups
+/// that are not range checked are not needed at all once packed, and are
+/// left for [`RemoveDeadVariables`] to remove, which shortens the witness
+/// and the `L` query; this pass should run before it.
+///
+/// Linear combinations must be canonical for groups to be found.
+pub struct PackWires;
+
+impl PackWires {
+    /// Returns the groups that this pass would pack: those whose packing
+    /// removes variables, or shortens the linear combinations.
+    pub fn analyze<S: PrimeField>(r1cs: &R1cs<S>) -> Vec<WireGroup<S>> {
+        // For each variable, its coefficient in the first linear combination
+        // that uses it, and its uses relative to that coefficient.
+        let mut weights = vec![None; r1cs.num_aux];
+        let mut uses = vec![vec![]; r1cs.num_aux];
+        let mut range_checked = vec![false; r1cs.num_aux];
+        for (i, row) in r1cs.constraints.iter().enumerate() {
+            if let Some(x) = booleanity(row) {
+                range_checked[x] = true;
+                continue;
+            }
+            for (slot, lc) in row.iter().enumerate() {
+                for (var, coeff) in lc.0.iter() {
+                    if let Index::Aux(x) = var.get_unchecked() {
+                        let weight: &mut S = weights[x].get_or_insert(*coeff);
+                        uses[x].push((i, slot, *coeff * weight.invert().unwrap()));
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<Vec<u8>, WireGroup<S>> = HashMap::new();
+        let mut keys = vec![];
+        for (x, (weight, uses)) in weights.into_iter().zip(uses).enumerate() {
+            let weight = match weight {
+                Some(weight) => weight,
+                None => continue,
+            };
+            let mut key = vec![];
+            for (i, slot, coeff) in uses.iter() {
+                key.extend_from_slice(&(*i as u64).to_le_bytes());
+                key.push(*slot as u8);
+                key.extend_from_slice(coeff.to_repr().as_ref());
+            }
+            let group = groups.entry(key.clone()).or_insert_with(|| {
+                keys.push(key);
+                WireGroup {
+                    members: vec![],
+                    uses,
+                    range_checked: false,
+                }
+            });
+            group.members.push((x, weight));
+            group.range_checked |= range_checked[x];
+        }
+
+        keys.into_iter()
+            .filter_map(|key| groups.remove(&key))
+            .filter(|group| {
+                let (k, n) = (group.members.len(), group.uses.len());
+                // An unpacking constraint costs k + 1 terms, and packing
+                // saves k - 1 terms in each use.
+                k > 1 && (!group.range_checked || (k - 1) * n > k + 1)
+            })
+            .collect()
+    }
+}
+
+impl<S: PrimeField> Pass<S> for PackWires {
+    fn name(&self) -> &'static str {
+        "pack-wires"
+    }
+
+    fn run(&self, r1cs: &mut R1cs<S>) {
+        let mut original = HashMap::new();
+        for (j, entry) in r1cs.aux_map.iter().enumerate() {
+            if let Some(x) = entry {
+                original.insert(*x, vec![(j, S::one())]);
+            }
+        }
+        for (x, combination) in r1cs.derived.iter() {
+            original.insert(*x, combination.clone());
+        }
+
+        for group in PackWires::analyze(r1cs) {
+            let packed = Variable::new_unchecked(Index::Aux(r1cs.num_aux));
+            r1cs.num_aux += 1;
+
+            let is_member =
+                |var: &Variable| group.members.iter().any(|(x, _)| order(var) == (1, *x));
+            for (i, slot, coeff) in group.uses.iter() {
+                let lc = &mut r1cs.constraints[*i][*slot];
+                lc.0.retain(|(var, _)| !is_member(var));
+                lc.0.push((packed, *coeff));
+                canonicalize(lc);
+            }
+
+            if group.range_checked {
+                let mut unpacked = LinearCombination::zero();
+                for (x, weight) in group.members.iter() {
+                    unpacked
+                        .0
+                        .push((Variable::new_unchecked(Index::Aux(*x)), *weight));
+                }
+                canonicalize(&mut unpacked);
+                r1cs.constraints.push([
+                    unpacked,
+                    LinearCombination::zero() + Variable::new_unchecked(Index::Input(0)),
+                    LinearCombination::zero() + packed,
+                ]);
+            }
+
+            let mut combination = vec![];
+            for (x, weight) in group.members.iter() {
+                for (j, coeff) in original[x].iter() {
+                    combination.push((*j, *coeff * weight));
+                }
+            }
+            r1cs.derived.push((r1cs.num_aux - 1, combination));
+        }
+    }
+}
+
+/// The result of optimizing a circuit.
+pub struct Optimized<S: PrimeField> {
+    pub circuit: RawCircuit<S>,
+    /// For each auxiliary variable of the original circuit, its index in the
+    /// optimized one, if it has not been removed.
+    pub aux_map: Vec<Option<usize>>,
+    /// The auxiliary variables of the optimized circuit that are not in the
+    /// original one, with their values as combinations of its variables.
+    pub derived: Vec<(usize, Vec<(usize, S)>)>,
+    /// The statistics of the original circuit.
+    pub before: CircuitStats,
+    /// The name of each pass that ran, with the statistics after it.
+    pub passes: Vec<(&'static str, CircuitStats)>,
+}
+
+impl<S: PrimeField> Optimized<S> {
+    /// Returns the statistics of the optimized circuit.
+    pub fn after(&self) -> &CircuitStats {
+        self.passes.last().map(|(_, s)| s).unwrap_or(&self.before)
+    }
+
+    /// Maps an assignment of the original circuit to the optimized one.
+    pub fn assignment(&self, original: &Assignment<S>) -> Assignment<S> {
+        let mut aux = vec![S::zero(); self.circuit.num_aux];
+        for (value, index) in original.aux.iter().zip(self.aux_map.iter()) {
+            if let Some(i) = index {
+                aux[*i] = *value;
+            }
+        }
+        for (i, combination) in self.derived.iter() {
+            aux[*i] = combination
+                .iter()
+                .fold(S::zero(), |acc, (j, coeff)| acc + original.aux[*j] * coeff);
+        }
+
+        Assignment {
+            inputs: original.inputs.clone(),
+            aux,
+        }
+    }
+
+    /// Returns the optimized circuit, for parameter generation, or with an
+    /// assignment of the original circuit, for proving.
+    pub fn replay(&self, original: Option<&Assignment<S>>) -> ReplayCircuit<S> {
+        ReplayCircuit {
+            circuit: self.circuit.clone(),
+            assignment: original.map(|original| self.assignment(original)),
+        }
+    }
+
+    /// Wraps `circuit`, an instance of the original circuit, so that it
+    /// synthesizes as the optimized one. Its witness, if it has one, is
+    /// carried over to the optimized circuit.
+    pub fn circuit<C: Circuit<S>>(&self, circuit: C) -> OptimizedCircuit<'_, S, C> {
+        OptimizedCircuit {
+            optimized: self,
+            circuit,
+        }
+    }
+}
+
+/// An instance of a circuit that synthesizes as its [`Optimized`] form,
+/// returned by [`Optimized::circuit`].
+pub struct OptimizedCircuit<'a, S: PrimeField, C> {
+    optimized: &'a Optimized<S>,
+    circuit: C,
+}
+
+impl<'a, S: PrimeField, C: Circuit<S>> Circuit<S> for OptimizedCircuit<'a, S, C> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        // Parameter generation synthesizes circuits without their witness.
+        let original = match Assignment::synthesize(self.circuit) {
+            Ok(original) => Some(original),
+            Err(SynthesisError::AssignmentMissing) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(original) = &original {
+            if original.inputs.len() != self.optimized.circuit.num_inputs
+                || original.aux.len() != self.optimized.aux_map.len()
+            {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        self.optimized.replay(original.as_ref()).synthesize(cs)
+    }
+}
+
+/// A pipeline of passes.
+pub struct Optimizer<S: PrimeField> {
+    passes: Vec<Box<dyn Pass<S>>>,
+}
+
+impl<S: PrimeField> Default for Optimizer<S> {
+    /// Returns the standard pipeline: canonicalization, constant
+    /// propagation, duplicate removal, and dead variable elimination.
+    fn default() -> Self {
+        Optimizer::empty()
+            .with_pass(Canonicalize)
+            .with_pass(ConstantPropagation)
+            .with_pass(RemoveDuplicates)
+            .with_pass(RemoveDeadVariables)
+    }
+}
+
+impl<S: PrimeField> Optimizer<S> {
+    /// Returns a pipeline without any passes.
+    pub fn empty() -> Self {
+        Optimizer { passes: vec![] }
+    }
+
+    /// Appends a pass to the pipeline.
+    pub fn with_pass<P: Pass<S> + 'static>(mut self, pass: P) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Synthesizes `circuit`, without its witness, and optimizes its
+    /// matrices.
+    pub fn optimize_circuit<C: Circuit<S>>(
+        &self,
+        circuit: C,
+    ) -> Result<Optimized<S>, SynthesisError> {
+        Ok(self.optimize(&RawCircuit::synthesize(circuit)?))
+    }
+
+    /// Runs each pass in order over `circuit`.
+    pub fn optimize(&self, circuit: &RawCircuit<S>) -> Optimized<S> {
+        let before = CircuitStats::from_circuit(circuit);
+        let mut r1cs = R1cs::from_circuit(circuit);
+
+        let mut passes = vec![];
+        for pass in self.passes.iter() {
+            pass.run(&mut r1cs);
+            passes.push((pass.name(), CircuitStats::from_circuit(&r1cs.to_circuit())));
+        }
+
+        Optimized {
+            circuit: r1cs.to_circuit(),
+            aux_map: r1cs.aux_map,
+            derived: r1cs.derived,
+            before,
+            passes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::exporter::ReplayCircuit;
+    use crate::groth16::tests::fixture::rng;
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use bls12_381::{Bls12, Scalar};
+
+    /// Proves `y = 3 * x^2`, with the kind of redundancy that gadget
+    /// composition produces.
+    struct Redundant {
+        x: Option<Scalar>,
+    }
+
+    impl Circuit<Scalar> for Redundant {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let value = |f: &dyn Fn(Scalar) -> Scalar| {
+                x_value.map(f).ok_or(SynthesisError::AssignmentMissing)
+            };
+
+            let y = cs.alloc_input(|| "y", || value(&|x| x.square() * Scalar::from(3)))?;
+            let x = cs.alloc(|| "x", || value(&|x| x))?;
+            // An unused variable.
+            cs.alloc(|| "unused", || value(&|x| x))?;
+            // A variable constrained to the constant 3.
+            let three = cs.alloc(|| "three", || Ok(Scalar::from(3)))?;
+            cs.enforce(
+                || "three",
+                |lc| lc + (Scalar::from(3), CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + three,
+            );
+            let x2 = cs.alloc(|| "x2", || value(&|x| x.square()))?;
+            // The same constraint twice, with the second written as x * (2x - x).
+            cs.enforce(|| "x2", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+            cs.enforce(
+                || "x2 again",
+                |lc| lc + x + x - x,
+                |lc| lc + x,
+                |lc| lc + x2,
+            );
+            cs.enforce(|| "y", |lc| lc + three, |lc| lc + x2, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn standard_pipeline() {
+        let circuit = RawCircuit::synthesize(Redundant { x: None }).unwrap();
+        let witness = Assignment::synthesize(Redundant {
+            x: Some(Scalar::from(5)),
+        })
+        .unwrap();
+        assert!(witness.is_satisfied(&circuit));
+
+        let optimized = Optimizer::default().optimize(&circuit);
+        assert_eq!(optimized.before.num_aux, 4);
+        assert_eq!(optimized.before.num_constraints, 4);
+        assert_eq!(optimized.after().num_aux, 2);
+        assert_eq!(optimized.after().num_constraints, 2);
+        assert_eq!(
+            optimized.passes.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            vec![
+                "canonicalize",
+                "constant-propagation",
+                "remove-duplicates",
+                "remove-dead-variables"
+            ]
+        );
+        assert_eq!(optimized.aux_map, vec![Some(0), None, None, Some(1)]);
+
+        let mapped = optimized.assignment(&witness);
+        assert!(mapped.is_satisfied(&optimized.circuit));
+        let mut bad = mapped.clone();
+        bad.inputs[1] = Scalar::from(74);
+        assert!(!bad.is_satisfied(&optimized.circuit));
+
+        // The original circuit has an unconstrained variable, so only the
+        // optimized one can be used for Groth16.
+        let mut rng = rng();
+        assert!(matches!(
+            generate_random_parameters::<Bls12, _, _>(Redundant { x: None }, &mut rng),
+            Err(SynthesisError::UnconstrainedVariable)
+        ));
+        let params = generate_random_parameters::<Bls12, _, _>(
+            ReplayCircuit {
+                circuit: optimized.circuit.clone(),
+                assignment: None,
+            },
+            &mut rng,
+        )
+        .unwrap();
+        let proof = create_random_proof(
+            ReplayCircuit {
+                circuit: optimized.circuit.clone(),
+                assignment: Some(mapped),
+            },
+            &params,
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(75)]).is_ok());
+    }
+
+    #[test]
+    fn optimized_keygen_and_proving() {
+        let optimized = Optimizer::default()
+            .optimize_circuit(Redundant { x: None })
+            .unwrap();
+
+        let mut rng = rng();
+        let params = generate_random_parameters::<Bls12, _, _>(
+            optimized.circuit(Redundant { x: None }),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(params.l.len(), optimized.after().num_aux);
+
+        let proof = create_random_proof(
+            optimized.circuit(Redundant {
+                x: Some(Scalar::from(5)),
+            }),
+            &params,
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(75)]).is_ok());
+
+        // Instances of another circuit are rejected.
+        let other = Optimizer::default()
+            .optimize_circuit(Packed { x: None })
+            .unwrap();
+        assert!(matches!(
+            create_random_proof(
+                other.circuit(Redundant {
+                    x: Some(Scalar::from(5)),
+                }),
+                &params,
+                &mut rng,
+            ),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    /// Proves that `y` is a byte and `z = y^2`, with the byte packed from
+    /// its bits in every constraint, and that `w = u + 2v + 4t` for some
+    /// `u`, `v` and `t`.
+    struct Packed {
+        x: Option<u8>,
+    }
+
+    impl Circuit<Scalar> for Packed {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = self.x;
+            let value = |f: &dyn Fn(u64) -> u64| {
+                x.map(|x| Scalar::from(f(x as u64)))
+                    .ok_or(SynthesisError::AssignmentMissing)
+            };
+
+            let y = cs.alloc_input(|| "y", || value(&|x| x))?;
+            let z = cs.alloc_input(|| "z", || value(&|x| x * x))?;
+            let w = cs.alloc_input(|| "w", || value(&|x| x + 1))?;
+
+            let mut packed = LinearCombination::zero();
+            for i in 0..8 {
+                let bit = cs.alloc(|| format!("bit {}", i), || value(&|x| (x >> i) & 1))?;
+                cs.enforce(
+                    || format!("bit {} is boolean", i),
+                    |lc| lc + CS::one() - bit,
+                    |lc| lc + bit,
+                    |lc| lc,
+                );
+                packed = packed + (Scalar::from(1 << i), bit);
+            }
+            let square = cs.alloc(|| "square", || value(&|x| x * x))?;
+            cs.enforce(|| "y", |_| packed.clone(), |lc| lc + CS::one(), |lc| lc + y);
+            cs.enforce(
+                || "square",
+                |_| packed.clone(),
+                |_| packed.clone(),
+                |lc| lc + square,
+            );
+            cs.enforce(|| "z", |lc| lc + square, |lc| lc + CS::one(), |lc| lc + z);
+
+            let u = cs.alloc(|| "u", || value(&|x| x + 1))?;
+            let v = cs.alloc(|| "v", || Ok(Scalar::zero()))?;
+            let t = cs.alloc(|| "t", || Ok(Scalar::zero()))?;
+            cs.enforce(
+                || "w",
+                |lc| lc + u + (Scalar::from(2), v) + (Scalar::from(4), t),
+                |lc| lc + CS::one(),
+                |lc| lc + w,
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pack_wires() {
+        let circuit = RawCircuit::synthesize(Packed { x: None }).unwrap();
+        let witness = Assignment::synthesize(Packed { x: Some(13) }).unwrap();
+        assert!(witness.is_satisfied(&circuit));
+
+        let mut r1cs = R1cs::from_circuit(&circuit);
+        Canonicalize.run(&mut r1cs);
+        let groups = PackWires::analyze(&r1cs);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].members.len(), 8);
+        assert!(groups[0].range_checked);
+        assert_eq!(groups[0].uses.len(), 3);
+        assert_eq!(
+            groups[1].members,
+            vec![
+                (9, Scalar::one()),
+                (10, Scalar::from(2)),
+                (11, Scalar::from(4))
+            ]
+        );
+        assert!(!groups[1].range_checked);
+
+        let optimized = Optimizer::empty()
+            .with_pass(Canonicalize)
+            .with_pass(PackWires)
+            .with_pass(RemoveDeadVariables)
+            .optimize(&circuit);
+        assert_eq!(optimized.before.num_aux, 12);
+        assert_eq!(optimized.after().num_aux, 11);
+        assert_eq!(optimized.after().num_constraints, 13);
+        assert!(optimized.after().terms < optimized.before.terms);
+
+        let mapped = optimized.assignment(&witness);
+        assert!(mapped.is_satisfied(&optimized.circuit));
+
+        // The byte is still range checked.
+        let mut bad = mapped.clone();
+        let packed = optimized.derived[0].0;
+        bad.aux[packed] += Scalar::from(256);
+        assert!(!bad.is_satisfied(&optimized.circuit));
+
+        let mut rng = rng();
+        let replay = |assignment| ReplayCircuit {
+            circuit: optimized.circuit.clone(),
+            assignment,
+        };
+        let params = generate_random_parameters::<Bls12, _, _>(replay(None), &mut rng).unwrap();
+        let proof = create_random_proof(replay(Some(mapped)), &params, &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let inputs = [Scalar::from(13), Scalar::from(169), Scalar::from(14)];
+        assert!(verify_proof(&pvk, &proof, &inputs).is_ok());
+    }
+
+    #[test]
+    fn false_constants_are_kept() {
+        let mut r1cs = R1cs::<Scalar> {
+            num_inputs: 1,
+            num_aux: 0,
+            constraints: vec![[
+                LinearCombination::zero() + Variable::new_unchecked(Index::Input(0)),
+                LinearCombination::zero() + Variable::new_unchecked(Index::Input(0)),
+                LinearCombination::zero()
+                    + (Scalar::from(2), Variable::new_unchecked(Index::Input(0))),
+            ]],
+            aux_map: vec![],
+            derived: vec![],
+        };
+        ConstantPropagation.run(&mut r1cs);
+        assert_eq!(r1cs.constraints.len(), 1);
+    }
+}
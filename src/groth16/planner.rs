@@ -0,0 +1,327 @@
+//! Choosing how to prove a circuit, from its statistics and the machine.
+//!
+//! Before computing a proof, the prover measures the synthesized circuit as
+//! [`ProvingStats`], detects what the machine offers as [`Capabilities`],
+//! and asks [`plan`] for a [`Plan`]: how the quotient polynomial is
+//! computed, how the windows of the multiexponentiations are sized, and
+//! whether the multiexponentiations run at once or one after the other.
+//! Every plan yields the same proof; they differ in time and peak memory.
+//! [`create_proof_planned`] lets callers replace [`plan`] with their own
+//! choice, and returns the plan that was used.
+//!
+//! [`create_proof_planned`]: super::create_proof_planned
+
+use std::fmt;
+
+use crate::multicore::Worker;
+
+/// The sizes of a synthesized circuit, as the prover sees them. Unlike
+/// [`cost_model::CircuitStats`], these count the constraints the prover adds
+/// on the inputs.
+///
+/// [`cost_model::CircuitStats`]: super::cost_model::CircuitStats
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProvingStats {
+    /// The number of public inputs, including `ONE`.
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    /// The number of constraints, including those the prover adds on the
+    /// inputs.
+    pub num_constraints: usize,
+    /// The size of the evaluation domain of the quotient.
+    pub domain_size: usize,
+    /// The number of auxiliary variables that appear in `A`.
+    pub a_aux_density: usize,
+    /// The number of inputs that appear in `B`.
+    pub b_input_density: usize,
+    /// The number of auxiliary variables that appear in `B`.
+    pub b_aux_density: usize,
+    /// The size in bytes of a scalar.
+    pub scalar_bytes: usize,
+}
+
+/// What the machine and the parameters offer to the prover.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// The number of threads of the worker.
+    pub threads: usize,
+    /// The memory available to the process, in bytes, if it is known, or
+    /// the memory left in the budget of the worker if that is less.
+    pub available_memory: Option<u64>,
+    /// Whether multiexponentiations and FFTs can be offloaded to devices.
+    pub devices: bool,
+    /// Whether the bases of the parameters are in memory, rather than read
+    /// from a file as they are used.
+    pub params_in_memory: bool,
+}
+
+impl Capabilities {
+    /// Detects the capabilities of `worker` and of this machine, for
+    /// parameters in memory.
+    pub fn detect(worker: &Worker) -> Self {
+        #[cfg(feature = "gpu")]
+        let devices = worker.devices().is_some();
+        #[cfg(not(feature = "gpu"))]
+        let devices = false;
+
+        let budget = worker.budget.remaining_memory().map(|bytes| bytes as u64);
+        Capabilities {
+            threads: 1 << worker.log_num_cpus(),
+            available_memory: match (available_memory(), budget) {
+                (Some(available), Some(budget)) => Some(available.min(budget)),
+                (available, budget) => available.or(budget),
+            },
+            devices,
+            params_in_memory: true,
+        }
+    }
+}
+
+/// Reads the memory available to new allocations, on Linux.
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    let kib = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kib * 1024)
+}
+
+/// How the quotient polynomial `h` is computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quotient {
+    /// The evaluations of `A`, `B` and `C` are transformed at the same time,
+    /// each on its own thread. This is faster when the domain is too small
+    /// for each FFT to use every thread, and holds the three polynomials
+    /// and the witness evaluations at once.
+    Together,
+    /// The evaluations are transformed one after the other, each freed as
+    /// soon as it is interpolated, so that at most two polynomials are held
+    /// at once.
+    Staged,
+}
+
+/// How the windows of the multiexponentiations are sized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Windows {
+    /// By the length of each query.
+    Length,
+    /// By the number of bases each query sums, which is smaller for the
+    /// sparse `A` and `B` queries, and makes them cheaper.
+    Density,
+}
+
+/// When the multiexponentiations run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// All at once, so that the threads stay busy as the smaller ones
+    /// finish.
+    Concurrent,
+    /// One after the other, so that only one query is read at a time, which
+    /// keeps parameters streamed from a file from being read in parallel.
+    Sequential,
+}
+
+/// The strategies used to compute a proof.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub quotient: Quotient,
+    pub windows: Windows,
+    pub schedule: Schedule,
+    /// The estimated peak memory of the witness and the quotient, in bytes,
+    /// excluding the parameters.
+    pub estimated_memory: u64,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "quotient {:?}, windows by {:?}, {:?} multiexps, about {} bytes",
+            self.quotient, self.windows, self.schedule, self.estimated_memory
+        )
+    }
+}
+
+/// Returns the peak memory, in bytes, of the witness and the quotient when
+/// computed with `quotient`.
+pub fn estimate_memory(stats: &ProvingStats, quotient: Quotient) -> u64 {
+    let (m, n) = (stats.num_constraints as u64, stats.domain_size as u64);
+    let scalars = match quotient {
+        // The three witness evaluations and the three polynomials.
+        Quotient::Together => 3 * m + 3 * n,
+        // The evaluations are freed as the polynomials are interpolated.
+        Quotient::Staged => (3 * m).max(n + 2 * m).max(2 * n + m),
+    };
+    // The assignment, and then its bits for the multiexponentiations.
+    let assignment = 2 * (stats.num_inputs + stats.num_aux) as u64;
+    (scalars + assignment) * stats.scalar_bytes as u64
+}
+
+/// Chooses a plan for a circuit with `stats`, on a machine with
+/// `capabilities`.
+///
+/// The quotient is computed together when the domain is too small for each
+/// FFT to be parallel and the memory allows it; the windows are sized by
+/// density when the `A` or `B` query is less than half full; and the
+/// multiexponentiations run one after the other when the parameters are
+/// read from a file or there is only one thread.
+pub fn plan(stats: &ProvingStats, capabilities: &Capabilities) -> Plan {
+    let small_domain = stats.domain_size <= capabilities.threads;
+    let fits = |quotient| {
+        capabilities.available_memory.map_or(true, |available| {
+            estimate_memory(stats, quotient) <= available / 2
+        })
+    };
+    let quotient = if small_domain && capabilities.threads > 1 && fits(Quotient::Together) {
+        Quotient::Together
+    } else {
+        Quotient::Staged
+    };
+
+    let sparse = |density: usize, len: usize| 2 * density < len;
+    let windows = if sparse(stats.a_aux_density, stats.num_aux)
+        || sparse(stats.b_aux_density, stats.num_aux)
+        || sparse(stats.b_input_density, stats.num_inputs)
+    {
+        Windows::Density
+    } else {
+        Windows::Length
+    };
+
+    let schedule = if !capabilities.params_in_memory || capabilities.threads == 1 {
+        Schedule::Sequential
+    } else {
+        Schedule::Concurrent
+    };
+
+    Plan {
+        quotient,
+        windows,
+        schedule,
+        estimated_memory: estimate_memory(stats, quotient),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{create_proof_on, create_proof_planned};
+    use bls12_381::{Bls12, Scalar};
+    use ff::Field;
+
+    #[test]
+    fn planned_proofs() {
+        let mut f = Fixture::<Bls12>::new();
+        let (r, s) = (Scalar::random(&mut f.rng), Scalar::random(&mut f.rng));
+        let (params, replay) = (&f.params, || f.circuit());
+
+        let worker = Worker::new();
+        let expected = create_proof_on(&worker, replay(), params, r, s).unwrap();
+
+        // Every plan gives the same proof.
+        let mut seen = None;
+        for &quotient in &[Quotient::Together, Quotient::Staged] {
+            for &windows in &[Windows::Length, Windows::Density] {
+                for &schedule in &[Schedule::Concurrent, Schedule::Sequential] {
+                    let (proof, used) =
+                        create_proof_planned(&worker, replay(), params, r, s, |stats, _| {
+                            seen = Some(stats.clone());
+                            Plan {
+                                quotient,
+                                windows,
+                                schedule,
+                                estimated_memory: estimate_memory(stats, quotient),
+                            }
+                        })
+                        .unwrap();
+                    assert!(proof == expected);
+                    assert_eq!(
+                        (used.quotient, used.windows, used.schedule),
+                        (quotient, windows, schedule)
+                    );
+                }
+            }
+        }
+
+        let stats = seen.unwrap();
+        assert_eq!(stats.num_inputs, f.circuit.num_inputs);
+        assert_eq!(stats.num_aux, f.circuit.num_aux);
+        assert_eq!(
+            stats.num_constraints,
+            f.circuit.num_constraints + f.circuit.num_inputs
+        );
+        assert_eq!(stats.domain_size, stats.num_constraints.next_power_of_two());
+        assert_eq!(stats.scalar_bytes, 32);
+    }
+
+    #[test]
+    fn plans() {
+        let stats = ProvingStats {
+            num_inputs: 2,
+            num_aux: 1000,
+            num_constraints: 1000,
+            domain_size: 1024,
+            a_aux_density: 900,
+            b_input_density: 2,
+            b_aux_density: 800,
+            scalar_bytes: 32,
+        };
+        let capabilities = Capabilities {
+            threads: 8,
+            available_memory: None,
+            devices: false,
+            params_in_memory: true,
+        };
+        assert_eq!(
+            plan(&stats, &capabilities),
+            Plan {
+                quotient: Quotient::Staged,
+                windows: Windows::Length,
+                schedule: Schedule::Concurrent,
+                estimated_memory: estimate_memory(&stats, Quotient::Staged),
+            }
+        );
+        assert!(
+            estimate_memory(&stats, Quotient::Staged) < estimate_memory(&stats, Quotient::Together)
+        );
+
+        // Small domains are transformed together, if they fit in memory.
+        let small = ProvingStats {
+            domain_size: 8,
+            num_constraints: 6,
+            ..stats.clone()
+        };
+        let many = Capabilities {
+            threads: 16,
+            ..capabilities.clone()
+        };
+        assert_eq!(plan(&small, &many).quotient, Quotient::Together);
+        let scarce = Capabilities {
+            available_memory: Some(1024),
+            ..many.clone()
+        };
+        assert_eq!(plan(&small, &scarce).quotient, Quotient::Staged);
+
+        // Sparse queries get windows by density.
+        let sparse = ProvingStats {
+            b_aux_density: 100,
+            ..stats.clone()
+        };
+        assert_eq!(plan(&sparse, &capabilities).windows, Windows::Density);
+
+        // Streamed parameters, or a single thread, are read one query at a
+        // time.
+        let streamed = Capabilities {
+            params_in_memory: false,
+            ..capabilities.clone()
+        };
+        assert_eq!(plan(&stats, &streamed).schedule, Schedule::Sequential);
+        let single = Capabilities {
+            threads: 1,
+            ..capabilities
+        };
+        assert_eq!(plan(&stats, &single).schedule, Schedule::Sequential);
+    }
+}
@@ -0,0 +1,335 @@
+//! Verifying keys signed by the parties that vouch for them.
+//!
+//! Verifying keys often reach verifiers through channels they do not trust,
+//! such as a package registry or a file next to the binary. A
+//! [`SignedVerifyingKey`] binds a key to the fingerprint of its circuit and
+//! to the hash of the ceremony transcript its parameters came out of, and
+//! carries a chain of signatures over them: each link signs the statement
+//! and every signature before it, so that, for instance, the coordinator of
+//! a ceremony can sign the key it produced and a release key, kept offline,
+//! can then countersign it for deployment.
+//!
+//! At load time, [`SignedVerifyingKey::verify`] checks the chain against
+//! the key ids a deployment expects, in order, using any
+//! [`SignatureVerifier`]. Signatures are as in [`envelope`].
+//!
+//! # Format
+//!
+//! [`SignedVerifyingKey::write`] emits the following, with integers in
+//! big-endian:
+//!
+//! ```text
+//! magic       "bellman-vk-provenance"
+//! version     u32 (currently 1)
+//! circuit     32 bytes, RawCircuit::fingerprint
+//! transcript  32 bytes, the hash of the last ceremony contribution
+//! vk          as written by VerifyingKey::write
+//! signatures  u32 count, then for each the key id, as a u32 length and
+//!             UTF-8 bytes, and the signature, as a u32 length and bytes
+//! ```
+//!
+//! Link `i` signs [`SIGNATURE_DOMAIN`], the circuit, the transcript and the
+//! [hash] of the verifying key, followed by links `0` to `i - 1` as written
+//! above.
+//!
+//! [`envelope`]: super::envelope
+//! [hash]: super::VerifyingKey::hash
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use pairing::Engine;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::envelope::{read_bytes, read_string, write_string, SignatureVerifier, Signer};
+use super::VerifyingKey;
+
+pub(super) const MAGIC: &[u8] = b"bellman-vk-provenance";
+const VERSION: u32 = 1;
+
+/// The prefix of the messages signed by [`SignedVerifyingKey::sign`].
+pub const SIGNATURE_DOMAIN: &[u8] = b"bellman-vk-provenance-signature";
+
+/// Why a [`SignedVerifyingKey`] was not accepted.
+#[derive(Debug, PartialEq)]
+pub enum ProvenanceError {
+    /// The key was not signed by the expected keys, in the expected order.
+    UnexpectedChain {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    /// The signature of a link of the chain is invalid.
+    InvalidSignature { link: usize, key_id: String },
+}
+
+impl Error for ProvenanceError {}
+
+impl fmt::Display for ProvenanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProvenanceError::UnexpectedChain { expected, found } => write!(
+                f,
+                "the verifying key is signed by [{}], expected [{}]",
+                found.join(", "),
+                expected.join(", ")
+            ),
+            ProvenanceError::InvalidSignature { link, key_id } => write!(
+                f,
+                "invalid signature by {} at link {} of the chain",
+                key_id, link
+            ),
+        }
+    }
+}
+
+impl From<ProvenanceError> for crate::error::Error {
+    fn from(e: ProvenanceError) -> Self {
+        crate::error::Error::new(crate::error::ErrorKind::MalformedParameters, e)
+    }
+}
+
+/// A verifying key, what it was generated for, and who vouches for it.
+#[derive(Clone)]
+pub struct SignedVerifyingKey<E: Engine> {
+    pub vk: VerifyingKey<E>,
+    /// The fingerprint of the circuit the key is for.
+    pub circuit: [u8; 32],
+    /// The hash of the ceremony transcript the parameters came out of, such
+    /// as [`MPCParameters::transcript`].
+    ///
+    /// [`MPCParameters::transcript`]: super::mpc::MPCParameters::transcript
+    pub transcript: [u8; 32],
+    /// The key ids and signatures of the chain, in the order they were made.
+    pub signatures: Vec<(String, Vec<u8>)>,
+}
+
+impl<E: Engine> SignedVerifyingKey<E> {
+    /// Signs `vk` for the circuit with fingerprint `circuit`, generated by
+    /// the ceremony with transcript `transcript`, as the first link of the
+    /// chain.
+    pub fn sign<S: Signer>(
+        vk: VerifyingKey<E>,
+        circuit: [u8; 32],
+        transcript: [u8; 32],
+        signer: &S,
+    ) -> Self {
+        let mut signed = SignedVerifyingKey {
+            vk,
+            circuit,
+            transcript,
+            signatures: vec![],
+        };
+        signed.countersign(signer);
+        signed
+    }
+
+    /// Appends a signature by `signer` over the statement and the chain so
+    /// far.
+    pub fn countersign<S: Signer>(&mut self, signer: &S) {
+        let signature = signer.sign(&self.signed_message(self.signatures.len()));
+        self.signatures
+            .push((signer.key_id().to_string(), signature));
+    }
+
+    /// Returns the key ids of the chain, in order.
+    pub fn signers(&self) -> Vec<&str> {
+        self.signatures.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    /// Returns the verifying key if it is signed by exactly the keys
+    /// `chain`, in order, with valid signatures.
+    pub fn verify<V: SignatureVerifier>(
+        &self,
+        verifier: &V,
+        chain: &[&str],
+    ) -> Result<&VerifyingKey<E>, ProvenanceError> {
+        if self.signers() != chain {
+            return Err(ProvenanceError::UnexpectedChain {
+                expected: chain.iter().map(|id| id.to_string()).collect(),
+                found: self.signatures.iter().map(|(id, _)| id.clone()).collect(),
+            });
+        }
+        for (link, (key_id, signature)) in self.signatures.iter().enumerate() {
+            if !verifier.verify(key_id, &self.signed_message(link), signature) {
+                return Err(ProvenanceError::InvalidSignature {
+                    link,
+                    key_id: key_id.clone(),
+                });
+            }
+        }
+        Ok(&self.vk)
+    }
+
+    /// Reads a signed verifying key and returns the key if its chain
+    /// verifies as by [`SignedVerifyingKey::verify`].
+    pub fn load<R: Read, V: SignatureVerifier>(
+        reader: R,
+        verifier: &V,
+        chain: &[&str],
+    ) -> io::Result<VerifyingKey<E>> {
+        let signed = Self::read(reader)?;
+        signed
+            .verify(verifier, chain)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(signed.vk)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.write_statement(&mut writer)?;
+        writer.write_u32::<BigEndian>(self.signatures.len() as u32)?;
+        for (key_id, signature) in &self.signatures {
+            write_link(&mut writer, key_id, signature)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a signed verifying key",
+            ));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported signed verifying key version",
+            ));
+        }
+
+        let mut circuit = [0; 32];
+        reader.read_exact(&mut circuit)?;
+        let mut transcript = [0; 32];
+        reader.read_exact(&mut transcript)?;
+        let vk = VerifyingKey::read(&mut reader)?;
+        let len = reader.read_u32::<BigEndian>()? as usize;
+        let signatures = (0..len)
+            .map(|_| Ok((read_string(&mut reader)?, read_bytes(&mut reader)?)))
+            .collect::<io::Result<_>>()?;
+
+        Ok(SignedVerifyingKey {
+            vk,
+            circuit,
+            transcript,
+            signatures,
+        })
+    }
+
+    fn write_statement<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(VERSION)?;
+        writer.write_all(&self.circuit)?;
+        writer.write_all(&self.transcript)?;
+        self.vk.write(writer)
+    }
+
+    /// Returns the message signed by link `link` of the chain.
+    fn signed_message(&self, link: usize) -> Vec<u8> {
+        let mut message = SIGNATURE_DOMAIN.to_vec();
+        message.extend_from_slice(&self.circuit);
+        message.extend_from_slice(&self.transcript);
+        message.extend_from_slice(&self.vk.hash());
+        for (key_id, signature) in &self.signatures[..link] {
+            write_link(&mut message, key_id, signature).expect("writing to a Vec does not fail");
+        }
+        message
+    }
+}
+
+fn write_link<W: Write>(writer: &mut W, key_id: &str, signature: &[u8]) -> io::Result<()> {
+    write_string(writer, key_id)?;
+    writer.write_u32::<BigEndian>(signature.len() as u32)?;
+    writer.write_all(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::envelope::MacKey;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::Bls12;
+
+    /// Verifies with whichever of several shared keys has the id.
+    struct Keys(Vec<MacKey>);
+
+    impl SignatureVerifier for Keys {
+        fn verify(&self, key_id: &str, message: &[u8], signature: &[u8]) -> bool {
+            self.0
+                .iter()
+                .any(|key| key.verify(key_id, message, signature))
+        }
+    }
+
+    #[test]
+    fn signature_chain() {
+        let Fixture {
+            circuit, params, ..
+        } = Fixture::<Bls12>::new();
+
+        let coordinator = MacKey::new("coordinator", [1; 32]);
+        let release = MacKey::new("release", [2; 32]);
+        let keys = Keys(vec![
+            MacKey::new("coordinator", [1; 32]),
+            MacKey::new("release", [2; 32]),
+        ]);
+        let chain = ["coordinator", "release"];
+
+        let mut signed = SignedVerifyingKey::sign(
+            params.vk.clone(),
+            circuit.fingerprint(),
+            [7; 32],
+            &coordinator,
+        );
+        assert_eq!(
+            signed.verify(&keys, &chain).err(),
+            Some(ProvenanceError::UnexpectedChain {
+                expected: vec!["coordinator".to_string(), "release".to_string()],
+                found: vec!["coordinator".to_string()],
+            })
+        );
+        signed.countersign(&release);
+        assert!(signed.verify(&keys, &chain).unwrap() == &params.vk);
+
+        let mut bytes = vec![];
+        signed.write(&mut bytes).unwrap();
+        let vk = SignedVerifyingKey::<Bls12>::load(&bytes[..], &keys, &chain).unwrap();
+        assert!(vk == params.vk);
+        let read = SignedVerifyingKey::<Bls12>::read(&bytes[..]).unwrap();
+        assert_eq!(read.circuit, circuit.fingerprint());
+        assert_eq!(read.transcript, [7; 32]);
+
+        // The countersignature covers the statement and the first signature.
+        let mut tampered = read.clone();
+        tampered.transcript = [8; 32];
+        assert_eq!(
+            tampered.verify(&keys, &chain).err(),
+            Some(ProvenanceError::InvalidSignature {
+                link: 0,
+                key_id: "coordinator".to_string(),
+            })
+        );
+        let mut tampered = read.clone();
+        tampered.signatures[0] = ("coordinator".to_string(), coordinator.sign(b"other"));
+        assert!(tampered.verify(&keys, &chain).is_err());
+        let mut resigned = SignedVerifyingKey::sign(
+            params.vk.clone(),
+            circuit.fingerprint(),
+            [7; 32],
+            &MacKey::new("coordinator", [3; 32]),
+        );
+        resigned.countersign(&release);
+        assert_eq!(
+            resigned.verify(&keys, &chain).err(),
+            Some(ProvenanceError::InvalidSignature {
+                link: 0,
+                key_id: "coordinator".to_string(),
+            })
+        );
+
+        assert!(SignedVerifyingKey::<Bls12>::load(&bytes[..], &keys, &["release"]).is_err());
+        assert!(SignedVerifyingKey::<Bls12>::read(&bytes[1..]).is_err());
+        assert!(SignedVerifyingKey::<Bls12>::read(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
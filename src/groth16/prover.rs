@@ -1,23 +1,43 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use rand_core::RngCore;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::ops::{AddAssign, MulAssign};
 use std::sync::Arc;
 
-use futures::Future;
+use futures::{future, Future};
 
 use ff::{Field, PrimeField};
-use group::{prime::PrimeCurveAffine, Curve};
-use pairing::Engine;
+use group::{
+    prime::{PrimeCurve, PrimeCurveAffine},
+    Curve,
+};
+use pairing::{Engine, MultiMillerLoop};
 
-use super::{ParameterSource, Proof};
+use super::checkpoint::ProofCheckpoint;
+use super::planner::{plan, Capabilities, Plan, ProvingStats, Quotient, Schedule, Windows};
+use super::{prepare_verifying_key, verify_proof, ParameterSource, Proof, VerifyingKey};
 
-use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use crate::{
+    Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable,
+    VerificationError,
+};
 
-use crate::domain::{EvaluationDomain, Scalar};
+use crate::budget::{BudgetError, ProvingBudget};
+use crate::domain::{domain_size, Radix, Scalar};
 
-use crate::multiexp::{multiexp, DensityTracker, FullDensity};
+use crate::multiexp::{
+    self, multiexp_with_window, DensityTracker, FullDensity, QueryDensity, SourceBuilder,
+};
 
 use crate::multicore::Worker;
 
+use crate::metrics;
+use crate::trace;
+use crate::zeroize::{Secret, SecretBits, SecretDomain, SecretVec};
+
 fn eval<S: PrimeField>(
     lc: &LinearCombination<S>,
     mut input_density: Option<&mut DensityTracker>,
@@ -63,13 +83,13 @@ struct ProvingAssignment<S: PrimeField> {
     b_aux_density: DensityTracker,
 
     // Evaluations of A, B, C polynomials
-    a: Vec<Scalar<S>>,
-    b: Vec<Scalar<S>>,
-    c: Vec<Scalar<S>>,
+    a: SecretVec<Scalar<S>>,
+    b: SecretVec<Scalar<S>>,
+    c: SecretVec<Scalar<S>>,
 
     // Assignments of variables
     input_assignment: Vec<S>,
-    aux_assignment: Vec<S>,
+    aux_assignment: SecretVec<S>,
 }
 
 impl<S: PrimeField> ConstraintSystem<S> for ProvingAssignment<S> {
@@ -169,70 +189,929 @@ where
     C: Circuit<E::Fr>,
     R: RngCore,
 {
-    let r = E::Fr::random(&mut rng);
+    let r = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let s = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    create_proof::<E, C, P>(circuit, params, *r, *s)
+}
+
+/// Returns a proof of the same statement as `proof`, with fresh randomness
+/// from `rng`, so that the two cannot be linked.
+///
+/// For random `r != 0` and `s`, `(A / r, r * B + r * s * delta, C + s * A)`
+/// satisfies the verification equation exactly when `(A, B, C)` does. Like
+/// the proofs of [`create_proof`] with random factors, its `A` and `B` are
+/// uniform and independent, and determine `C`, so it is distributed as a
+/// fresh proof of the statement.
+pub fn rerandomize_proof<E, R>(vk: &VerifyingKey<E>, proof: &Proof<E>, mut rng: &mut R) -> Proof<E>
+where
+    E: Engine,
+    R: RngCore,
+{
+    let r = loop {
+        let r = E::Fr::random(&mut rng);
+        if !r.is_zero() {
+            break r;
+        }
+    };
     let s = E::Fr::random(&mut rng);
 
-    create_proof::<E, C, P>(circuit, params, r, s)
+    rerandomize_with(vk, proof, r, s)
+}
+
+/// Re-randomizes `proof` with the factors `r`, which must not be zero, and
+/// `s`.
+pub(super) fn rerandomize_with<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    r: E::Fr,
+    s: E::Fr,
+) -> Proof<E> {
+    let a = proof.a * &r.invert().unwrap();
+    let mut b = proof.b * &r;
+    AddAssign::<&E::G2>::add_assign(&mut b, &(vk.delta_g2 * &(r * &s)));
+    let mut c = proof.c.to_curve();
+    AddAssign::<&E::G1>::add_assign(&mut c, &(proof.a * &s));
+    Proof {
+        a: a.to_affine(),
+        b: b.to_affine(),
+        c: c.to_affine(),
+    }
 }
 
 pub fn create_proof<E, C, P: ParameterSource<E>>(
     circuit: C,
-    mut params: P,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+{
+    create_proof_on(&Worker::new(), circuit, params, r, s)
+}
+
+/// Creates a proof like [`create_proof`], on the threads of `worker` rather
+/// than a new pool, and on the kernels it offloads to with the `gpu`
+/// feature. A worker made by [`Worker::with_budget`] holds the proof to its
+/// budget.
+pub fn create_proof_on<E, C, P>(
+    worker: &Worker,
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    P: ParameterSource<E>,
+{
+    let _span = trace::span("create_proof");
+
+    // The witness and the blinding factors are erased as they go out of
+    // scope, on every path.
+    let r = Secret::new(r, E::Fr::zero());
+    let s = Secret::new(s, E::Fr::zero());
+
+    let prover = synthesize(circuit)?;
+    prove_assignment(worker, prover, params, r, s, &mut Phases::none(), &mut plan)
+        .map(|(proof, ..)| proof)
+}
+
+/// Creates a proof like [`create_proof`], on a worker held to `budget`, and
+/// fails with [`BudgetError::Exceeded`] if the proof runs out of it.
+pub fn create_proof_with_budget<E, C, P>(
+    budget: &ProvingBudget,
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+) -> Result<Proof<E>, BudgetError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    P: ParameterSource<E>,
+{
+    create_proof_on(&Worker::with_budget(budget), circuit, params, r, s).map_err(BudgetError::from)
+}
+
+/// Creates a proof like [`create_proof_on`], with the plan returned by
+/// `choose` for the statistics of the circuit and the capabilities of
+/// `worker` and `params`, rather than [`plan`]. Returns the plan along with
+/// the proof.
+///
+/// [`plan`]: super::planner::plan
+pub fn create_proof_planned<E, C, P, F>(
+    worker: &Worker,
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    mut choose: F,
+) -> Result<(Proof<E>, Plan), SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    P: ParameterSource<E>,
+    F: FnMut(&ProvingStats, &Capabilities) -> Plan,
+{
+    let _span = trace::span("create_proof");
+
+    let r = Secret::new(r, E::Fr::zero());
+    let s = Secret::new(s, E::Fr::zero());
+
+    let prover = synthesize(circuit)?;
+    prove_assignment(
+        worker,
+        prover,
+        params,
+        r,
+        s,
+        &mut Phases::none(),
+        &mut choose,
+    )
+    .map(|(proof, _, plan)| (proof, plan))
+}
+
+/// Creates a proof like [`create_random_proof`], storing each phase in
+/// `checkpoint` as it completes: the evaluated witness, the coefficients of
+/// `h`, and the result of each multiexponentiation. If proving is
+/// interrupted, calling this again with the same checkpoint resumes from the
+/// phases completed so far; once the witness is stored, the circuit is not
+/// synthesized again.
+///
+/// The witness is only bound to the checkpoint, so a checkpoint must not be
+/// reused for another proof before it is cleared. The multiexponentiations
+/// are bound to the verifying key as well, and are recomputed if the
+/// parameters change.
+pub fn create_random_proof_checkpointed<E, C, R, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    mut rng: &mut R,
+    checkpoint: &ProofCheckpoint,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let _span = trace::span("create_proof_checkpointed");
+
+    let r = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let s = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    let loaded = checkpoint.load("witness", |reader| {
+        let mut run = [0; 16];
+        reader.read_exact(&mut run)?;
+        Ok((run, read_witness(reader)?))
+    });
+    let (run, prover) = match loaded {
+        Some(loaded) => loaded,
+        None => {
+            let prover = synthesize(circuit)?;
+            let mut run = [0; 16];
+            rng.fill_bytes(&mut run);
+            checkpoint.store("witness", rng, |writer| {
+                writer.write_all(&run)?;
+                write_witness(writer, &prover)
+            })?;
+            (run, prover)
+        }
+    };
+
+    let mut phases = Phases {
+        checkpoint: Some((checkpoint, rng as &mut dyn RngCore)),
+        run,
+        vk: [0; 32],
+    };
+    prove_assignment(&Worker::new(), prover, params, r, s, &mut phases, &mut plan)
+        .map(|(proof, ..)| proof)
+}
+
+/// The constraints of a circuit and the densities of its queries, captured
+/// once so that [`create_proof_with_cache`] only computes the witness of
+/// each proof.
+///
+/// Proving synthesizes the circuit, building the linear combinations of
+/// every constraint and tracking which variables each query uses, before
+/// it evaluates them. For a circuit that is proven many times with
+/// different witnesses, [`SynthesisCache::new`] does that once; each proof
+/// then synthesizes the circuit only to assign its variables, without
+/// calling the closures that build the linear combinations, and evaluates
+/// the cached constraints on the assignment in parallel.
+///
+/// The cache describes one circuit. A proof of a circuit with another
+/// number of inputs or auxiliary variables fails, but one with the same
+/// variables and other constraints is not detected, and does not verify.
+pub struct SynthesisCache<S: PrimeField> {
+    num_inputs: usize,
+    num_aux: usize,
+    constraints: Vec<(
+        LinearCombination<S>,
+        LinearCombination<S>,
+        LinearCombination<S>,
+    )>,
+    a_aux_density: DensityTracker,
+    b_input_density: DensityTracker,
+    b_aux_density: DensityTracker,
+}
+
+impl<S: PrimeField> SynthesisCache<S> {
+    /// Captures the constraints of `circuit`, without computing a witness.
+    pub fn new<C: Circuit<S>>(circuit: C) -> Result<Self, SynthesisError> {
+        let _span = trace::span("synthesis_cache");
+        let mut cache = SynthesisCache {
+            num_inputs: 0,
+            num_aux: 0,
+            constraints: vec![],
+            a_aux_density: DensityTracker::new(),
+            b_input_density: DensityTracker::new(),
+            b_aux_density: DensityTracker::new(),
+        };
+
+        cache.alloc_input(|| "", || Ok(S::one()))?;
+        circuit.synthesize(&mut cache)?;
+        for i in 0..cache.num_inputs {
+            cache.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
+        }
+
+        Ok(cache)
+    }
+
+    /// Returns the number of constraints, including those that the prover
+    /// adds for the public inputs.
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Evaluates the cached constraints on the assignment of `circuit`.
+    fn prover(
+        &self,
+        worker: &Worker,
+        circuit: impl Circuit<S>,
+    ) -> Result<ProvingAssignment<S>, SynthesisError> {
+        let mut span = trace::span("synthesize");
+        let mut witness = WitnessAssignment {
+            inputs: vec![],
+            aux: SecretVec::new(S::zero()),
+        };
+        witness.alloc_input(|| "", || Ok(S::one()))?;
+        circuit.synthesize(&mut witness)?;
+        witness.aux.check()?;
+        if witness.inputs.len() != self.num_inputs || witness.aux.len() != self.num_aux {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let zero = Scalar(S::zero());
+        let (mut a, mut b, mut c) = (
+            SecretVec::new(zero),
+            SecretVec::new(zero),
+            SecretVec::new(zero),
+        );
+        for _ in 0..self.constraints.len() {
+            a.push(zero);
+            b.push(zero);
+            c.push(zero);
+        }
+        a.check()?;
+        b.check()?;
+        c.check()?;
+
+        let (inputs, aux) = (&witness.inputs, &witness.aux);
+        worker.scope(self.constraints.len(), |scope, chunk| {
+            for (((constraints, a), b), c) in self
+                .constraints
+                .chunks(chunk)
+                .zip(a.chunks_mut(chunk))
+                .zip(b.chunks_mut(chunk))
+                .zip(c.chunks_mut(chunk))
+            {
+                scope.spawn(move |_| {
+                    for (((lcs, a), b), c) in constraints
+                        .iter()
+                        .zip(a.iter_mut())
+                        .zip(b.iter_mut())
+                        .zip(c.iter_mut())
+                    {
+                        let (lc_a, lc_b, lc_c) = lcs;
+                        *a = Scalar(eval(lc_a, None, None, inputs, aux));
+                        *b = Scalar(eval(lc_b, None, None, inputs, aux));
+                        *c = Scalar(eval(lc_c, None, None, inputs, aux));
+                    }
+                });
+            }
+        });
+
+        span.record("inputs", witness.inputs.len());
+        span.record("aux", witness.aux.len());
+        span.record("constraints", a.len());
+
+        Ok(ProvingAssignment {
+            a_aux_density: self.a_aux_density.clone(),
+            b_input_density: self.b_input_density.clone(),
+            b_aux_density: self.b_aux_density.clone(),
+            a,
+            b,
+            c,
+            input_assignment: witness.inputs,
+            aux_assignment: witness.aux,
+        })
+    }
+}
+
+impl<S: PrimeField> ConstraintSystem<S> for SynthesisCache<S> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.num_aux += 1;
+        self.a_aux_density.add_element();
+        self.b_aux_density.add_element();
+
+        Ok(Variable(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.num_inputs += 1;
+        self.b_input_density.add_element();
+
+        Ok(Variable(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        // The densities are tracked as ProvingAssignment::enforce does.
+        for &(var, _) in a.0.iter() {
+            if let Variable(Index::Aux(i)) = var {
+                self.a_aux_density.inc(i);
+            }
+        }
+        for &(var, _) in b.0.iter() {
+            match var {
+                Variable(Index::Input(i)) => self.b_input_density.inc(i),
+                Variable(Index::Aux(i)) => self.b_aux_density.inc(i),
+            }
+        }
+
+        self.constraints.push((a, b, c));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// The assignment of the variables of a circuit, whose constraints are
+/// known from a [`SynthesisCache`].
+struct WitnessAssignment<S: PrimeField> {
+    inputs: Vec<S>,
+    aux: SecretVec<S>,
+}
+
+impl<S: PrimeField> ConstraintSystem<S> for WitnessAssignment<S> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f()?);
+
+        Ok(Variable(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, _: LA, _: LB, _: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        // The constraints are cached.
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Creates a proof like [`create_proof`], for a circuit whose constraints
+/// are captured in `cache`: the circuit is synthesized only to compute its
+/// witness. Fails with [`SynthesisError::Unsatisfiable`] if the circuit
+/// does not have the variables of the cached one.
+pub fn create_proof_with_cache<E, C, P>(
+    circuit: C,
+    cache: &SynthesisCache<E::Fr>,
+    params: P,
     r: E::Fr,
     s: E::Fr,
 ) -> Result<Proof<E>, SynthesisError>
 where
     E: Engine,
     C: Circuit<E::Fr>,
+    P: ParameterSource<E>,
+{
+    let _span = trace::span("create_proof");
+
+    let r = Secret::new(r, E::Fr::zero());
+    let s = Secret::new(s, E::Fr::zero());
+
+    let worker = Worker::new();
+    let prover = cache.prover(&worker, circuit)?;
+    prove_assignment(
+        &worker,
+        prover,
+        params,
+        r,
+        s,
+        &mut Phases::none(),
+        &mut plan,
+    )
+    .map(|(proof, ..)| proof)
+}
+
+fn write_scalars<S, I>(writer: &mut dyn Write, values: I) -> io::Result<()>
+where
+    S: PrimeField,
+    I: ExactSizeIterator<Item = S>,
+{
+    writer.write_u64::<BigEndian>(values.len() as u64)?;
+    for value in values {
+        writer.write_all(value.to_repr().as_ref())?;
+    }
+    Ok(())
+}
+
+fn read_scalars<S: PrimeField, F: FnMut(S)>(reader: &mut dyn Read, mut push: F) -> io::Result<()> {
+    for _ in 0..reader.read_u64::<BigEndian>()? {
+        let mut repr = S::Repr::default();
+        reader.read_exact(repr.as_mut())?;
+        match S::from_repr(repr) {
+            Some(value) => push(value),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid scalar")),
+        }
+    }
+    Ok(())
+}
+
+fn write_density(writer: &mut dyn Write, density: &DensityTracker) -> io::Result<()> {
+    let bits = density.iter().collect::<Vec<_>>();
+    writer.write_u64::<BigEndian>(bits.len() as u64)?;
+    for byte in bits.chunks(8) {
+        writer.write_u8(
+            byte.iter()
+                .enumerate()
+                .fold(0, |acc, (i, bit)| acc | ((*bit as u8) << i)),
+        )?;
+    }
+    Ok(())
+}
+
+fn read_density(reader: &mut dyn Read) -> io::Result<DensityTracker> {
+    let mut density = DensityTracker::new();
+    let len = reader.read_u64::<BigEndian>()? as usize;
+    let mut byte = 0;
+    for i in 0..len {
+        if i % 8 == 0 {
+            byte = reader.read_u8()?;
+        }
+        density.add_element();
+        if byte & (1 << (i % 8)) != 0 {
+            density.inc(i);
+        }
+    }
+    Ok(density)
+}
+
+fn write_witness<S: PrimeField>(
+    writer: &mut dyn Write,
+    prover: &ProvingAssignment<S>,
+) -> io::Result<()> {
+    write_density(writer, &prover.a_aux_density)?;
+    write_density(writer, &prover.b_input_density)?;
+    write_density(writer, &prover.b_aux_density)?;
+    write_scalars(writer, prover.a.iter().map(|s| s.0))?;
+    write_scalars(writer, prover.b.iter().map(|s| s.0))?;
+    write_scalars(writer, prover.c.iter().map(|s| s.0))?;
+    write_scalars(writer, prover.input_assignment.iter().copied())?;
+    write_scalars(writer, prover.aux_assignment.iter().copied())
+}
+
+fn read_witness<S: PrimeField>(reader: &mut dyn Read) -> io::Result<ProvingAssignment<S>> {
+    let mut prover = ProvingAssignment {
+        a_aux_density: read_density(reader)?,
+        b_input_density: read_density(reader)?,
+        b_aux_density: read_density(reader)?,
+        a: SecretVec::new(Scalar(S::zero())),
+        b: SecretVec::new(Scalar(S::zero())),
+        c: SecretVec::new(Scalar(S::zero())),
+        input_assignment: vec![],
+        aux_assignment: SecretVec::new(S::zero()),
+    };
+    read_scalars(reader, |s| prover.a.push(Scalar(s)))?;
+    read_scalars(reader, |s| prover.b.push(Scalar(s)))?;
+    read_scalars(reader, |s| prover.c.push(Scalar(s)))?;
+    read_scalars(reader, |s| prover.input_assignment.push(s))?;
+    read_scalars(reader, |s| prover.aux_assignment.push(s))?;
+    Ok(prover)
+}
+
+/// Synthesizes `circuit` and writes the evaluated witness, for
+/// [`prove_evaluated`].
+pub(super) fn write_evaluated<S, C>(
+    circuit: C,
+    writer: &mut dyn Write,
+) -> Result<(), SynthesisError>
+where
+    S: PrimeField,
+    C: Circuit<S>,
+{
+    let prover = synthesize(circuit)?;
+    Ok(write_witness(writer, &prover)?)
+}
+
+/// Proves a witness written by [`write_evaluated`].
+pub(super) fn prove_evaluated<E, P>(
+    reader: &mut dyn Read,
+    params: P,
+    r: Secret<E::Fr>,
+    s: Secret<E::Fr>,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    P: ParameterSource<E>,
+{
+    let mut prover = read_witness(reader)?;
+    if reader.read(&mut [0])? != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "trailing data after the witness",
+        )
+        .into());
+    }
+    prover.a.check()?;
+    prover.b.check()?;
+    prover.c.check()?;
+    prover.aux_assignment.check()?;
+    prove_assignment(
+        &Worker::new(),
+        prover,
+        params,
+        r,
+        s,
+        &mut Phases::none(),
+        &mut plan,
+    )
+    .map(|(proof, ..)| proof)
+}
+
+/// The phases of a proof, stored in a checkpoint as they complete, if there
+/// is one.
+struct Phases<'a> {
+    checkpoint: Option<(&'a ProofCheckpoint, &'a mut dyn RngCore)>,
+    // Identifies the proof, so that the phases of another one are not used.
+    run: [u8; 16],
+    // The hash of the verifying key, for the phases that depend on the
+    // parameters.
+    vk: [u8; 32],
+}
+
+/// A multiexponentiation, which is stored as a phase once it completes.
+struct Pending<G> {
+    name: &'static str,
+    stored: bool,
+    result: Box<dyn Future<Item = G, Error = SynthesisError>>,
+}
+
+impl<'a> Phases<'a> {
+    fn none() -> Self {
+        Phases {
+            checkpoint: None,
+            run: [0; 16],
+            vk: [0; 32],
+        }
+    }
+
+    fn header(&self, params: bool) -> Vec<u8> {
+        let mut header = self.run.to_vec();
+        if params {
+            header.extend_from_slice(&self.vk);
+        }
+        header
+    }
+
+    fn load<T, F>(&self, name: &str, params: bool, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut dyn Read) -> io::Result<T>,
+    {
+        let (checkpoint, _) = self.checkpoint.as_ref()?;
+        let expected = self.header(params);
+        checkpoint.load(name, |reader| {
+            let mut header = vec![0; expected.len()];
+            reader.read_exact(&mut header)?;
+            if header != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the phase belongs to another proof",
+                ));
+            }
+            f(reader)
+        })
+    }
+
+    fn store<F>(&mut self, name: &str, params: bool, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()>,
+    {
+        let header = self.header(params);
+        match &mut self.checkpoint {
+            Some((checkpoint, rng)) => checkpoint.store(name, &mut **rng, |writer| {
+                writer.write_all(&header)?;
+                f(writer)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Resumes the multiexponentiation `name`, or starts it.
+    fn multiexp<G, F>(&self, name: &'static str, start: F) -> Pending<G>
+    where
+        G: PrimeCurve,
+        F: FnOnce() -> Box<dyn Future<Item = G, Error = SynthesisError>>,
+    {
+        let loaded = self.load(name, true, |reader| {
+            let mut repr = G::Repr::default();
+            reader.read_exact(repr.as_mut())?;
+            Option::from(G::from_bytes(&repr))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid point"))
+        });
+        match loaded {
+            Some(result) => Pending {
+                name,
+                stored: true,
+                result: Box::new(future::ok(result)),
+            },
+            None => Pending {
+                name,
+                stored: false,
+                result: start(),
+            },
+        }
+    }
+
+    fn wait<G: PrimeCurve>(&mut self, pending: Pending<G>) -> Result<G, SynthesisError> {
+        let result = pending.result.wait()?;
+        if !pending.stored {
+            self.store(pending.name, true, |writer| {
+                writer.write_all(result.to_bytes().as_ref())
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+/// Waits for `pending` right away if the multiexponentiations run one after
+/// the other.
+fn scheduled<G: 'static>(schedule: Schedule, pending: Pending<G>) -> Pending<G> {
+    match schedule {
+        Schedule::Concurrent => pending,
+        Schedule::Sequential => Pending {
+            name: pending.name,
+            stored: pending.stored,
+            result: Box::new(future::result(pending.result.wait())),
+        },
+    }
+}
+
+fn synthesize<S, C>(circuit: C) -> Result<ProvingAssignment<S>, SynthesisError>
+where
+    S: PrimeField,
+    C: Circuit<S>,
 {
+    let mut span = trace::span("synthesize");
     let mut prover = ProvingAssignment {
         a_aux_density: DensityTracker::new(),
         b_input_density: DensityTracker::new(),
         b_aux_density: DensityTracker::new(),
-        a: vec![],
-        b: vec![],
-        c: vec![],
+        a: SecretVec::new(Scalar(S::zero())),
+        b: SecretVec::new(Scalar(S::zero())),
+        c: SecretVec::new(Scalar(S::zero())),
         input_assignment: vec![],
-        aux_assignment: vec![],
+        aux_assignment: SecretVec::new(S::zero()),
     };
 
-    prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
+    prover.alloc_input(|| "", || Ok(S::one()))?;
 
     circuit.synthesize(&mut prover)?;
 
     for i in 0..prover.input_assignment.len() {
         prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
     }
+    prover.a.check()?;
+    prover.b.check()?;
+    prover.c.check()?;
+    prover.aux_assignment.check()?;
 
-    let worker = Worker::new();
+    span.record("inputs", prover.input_assignment.len());
+    span.record("aux", prover.aux_assignment.len());
+    span.record("constraints", prover.a.len());
 
+    Ok(prover)
+}
+
+fn prove_assignment<E, P>(
+    worker: &Worker,
+    mut prover: ProvingAssignment<E::Fr>,
+    mut params: P,
+    r: Secret<E::Fr>,
+    s: Secret<E::Fr>,
+    phases: &mut Phases<'_>,
+    choose: &mut dyn FnMut(&ProvingStats, &Capabilities) -> Plan,
+) -> Result<(Proof<E>, VerifyingKey<E>, Plan), SynthesisError>
+where
+    E: Engine,
+    P: ParameterSource<E>,
+{
+    worker.check()?;
     let vk = params.get_vk(prover.input_assignment.len())?;
+    if phases.checkpoint.is_some() {
+        phases.vk = vk.hash();
+    }
 
-    let h = {
-        let mut a = EvaluationDomain::from_coeffs(prover.a)?;
-        let mut b = EvaluationDomain::from_coeffs(prover.b)?;
-        let mut c = EvaluationDomain::from_coeffs(prover.c)?;
-        a.ifft(&worker);
-        a.coset_fft(&worker);
-        b.ifft(&worker);
-        b.coset_fft(&worker);
-        c.ifft(&worker);
-        c.coset_fft(&worker);
-
-        a.mul_assign(&worker, &b);
-        drop(b);
-        a.sub_assign(&worker, &c);
-        drop(c);
-        a.divide_by_z_on_coset(&worker);
-        a.icoset_fft(&worker);
+    // Parameters generated for a mixed-radix domain have an H query of a
+    // point less than it, which is smaller than the power of two.
+    let default_size = domain_size::<E::Fr>(prover.a.len(), Radix::Two)?;
+    let h_source = params.get_h(default_size - 1)?;
+    let domain_size = match h_source.num_bases() {
+        Some(num_h)
+            if num_h + 1 < default_size
+                && domain_size::<E::Fr>(prover.a.len(), Radix::Mixed).ok() == Some(num_h + 1) =>
+        {
+            num_h + 1
+        }
+        _ => default_size,
+    };
+    let stats = ProvingStats {
+        num_inputs: prover.input_assignment.len(),
+        num_aux: prover.aux_assignment.len(),
+        num_constraints: prover.a.len(),
+        domain_size,
+        a_aux_density: prover.a_aux_density.get_total_density(),
+        b_input_density: prover.b_input_density.get_total_density(),
+        b_aux_density: prover.b_aux_density.get_total_density(),
+        scalar_bytes: std::mem::size_of::<E::Fr>(),
+    };
+    let mut capabilities = Capabilities::detect(worker);
+    capabilities.params_in_memory = h_source.as_slice().is_some();
+    let plan = choose(&stats, &capabilities);
+    // The witness and the quotient are held until the proof is assembled.
+    let _witness = worker
+        .budget
+        .reserve(usize::try_from(plan.estimated_memory).unwrap_or(usize::MAX))?;
+
+    let loaded = phases.load("h", false, |reader| {
+        let mut h = SecretVec::new(E::Fr::zero());
+        read_scalars(reader, |s| h.push(s))?;
+        Ok(h)
+    });
+    let h_bits = if let Some(mut h) = loaded {
+        h.check()?;
+        SecretBits::new(h.iter().copied())?
+    } else {
+        let mut span = trace::span("quotient");
+        span.record("size", prover.a.len());
+
+        let mut a = match plan.quotient {
+            Quotient::Together => {
+                let mut a = SecretDomain::from_coeffs(&prover.a, domain_size)?;
+                prover.a.truncate(0);
+                let mut b = SecretDomain::from_coeffs(&prover.b, domain_size)?;
+                prover.b.truncate(0);
+                let mut c = SecretDomain::from_coeffs(&prover.c, domain_size)?;
+                prover.c.truncate(0);
+                worker.scope(3, |scope, _| {
+                    for domain in vec![&mut a, &mut b, &mut c] {
+                        scope.spawn(move |_| {
+                            domain.ifft(worker);
+                            domain.coset_fft(worker);
+                        });
+                    }
+                });
+
+                a.mul_assign(worker, &b);
+                drop(b);
+                a.sub_assign(worker, &c);
+                drop(c);
+                a
+            }
+            Quotient::Staged => {
+                // Each evaluation is replaced, and so erased, as soon as it
+                // is copied into its domain.
+                let transform = |values: &mut SecretVec<Scalar<E::Fr>>| {
+                    let mut domain = SecretDomain::from_coeffs(values, domain_size)?;
+                    *values = SecretVec::new(Scalar(E::Fr::zero()));
+                    domain.ifft(worker);
+                    domain.coset_fft(worker);
+                    Ok::<_, SynthesisError>(domain)
+                };
+                let mut a = transform(&mut prover.a)?;
+                let b = transform(&mut prover.b)?;
+                a.mul_assign(worker, &b);
+                drop(b);
+                let c = transform(&mut prover.c)?;
+                a.sub_assign(worker, &c);
+                drop(c);
+                a
+            }
+        };
+        a.divide_by_z_on_coset(worker);
+        a.icoset_fft(worker);
+        // The transforms stop early once the budget is exceeded.
+        worker.check()?;
         let mut a = a.into_coeffs();
         let a_len = a.len() - 1;
         a.truncate(a_len);
+        phases.store("h", false, |writer| {
+            write_scalars(writer, a.iter().map(|s| s.0))
+        })?;
         // TODO: parallelize if it's even helpful
-        let a = Arc::new(a.into_iter().map(|s| s.0.to_le_bits()).collect::<Vec<_>>());
+        SecretBits::new(a.iter().map(|s| s.0))?
+    };
 
-        multiexp(&worker, params.get_h(a.len())?, FullDensity, a)
+    // The multiexponentiations run in the background until they are waited
+    // on below, so this span lasts until the proof is assembled.
+    let _span = trace::span("multiexp");
+    let window = |len: usize, density: usize| match plan.windows {
+        Windows::Length => multiexp::window(len),
+        Windows::Density => multiexp::window(density),
     };
+    let schedule = plan.schedule;
+    let h_window = window(h_bits.len(), h_bits.len());
+    let h = scheduled(
+        schedule,
+        phases.multiexp("h_query", || {
+            multiexp_with_window(worker, h_source, FullDensity, h_bits.shared(), h_window)
+        }),
+    );
 
     // TODO: parallelize if it's even helpful
     let input_assignment = Arc::new(
@@ -242,19 +1121,22 @@ where
             .map(|s| s.to_le_bits())
             .collect::<Vec<_>>(),
     );
-    let aux_assignment = Arc::new(
-        prover
-            .aux_assignment
-            .into_iter()
-            .map(|s| s.to_le_bits())
-            .collect::<Vec<_>>(),
-    );
+    let aux_bits = SecretBits::new(prover.aux_assignment.iter().copied())?;
+    let aux_assignment = aux_bits.shared();
 
-    let l = multiexp(
-        &worker,
-        params.get_l(aux_assignment.len())?,
-        FullDensity,
-        aux_assignment.clone(),
+    let l_source = params.get_l(aux_assignment.len())?;
+    let l_window = window(aux_assignment.len(), aux_assignment.len());
+    let l = scheduled(
+        schedule,
+        phases.multiexp("l_query", || {
+            multiexp_with_window(
+                worker,
+                l_source,
+                FullDensity,
+                aux_assignment.clone(),
+                l_window,
+            )
+        }),
     );
 
     let a_aux_density_total = prover.a_aux_density.get_total_density();
@@ -262,50 +1144,96 @@ where
     let (a_inputs_source, a_aux_source) =
         params.get_a(input_assignment.len(), a_aux_density_total)?;
 
-    let a_inputs = multiexp(
-        &worker,
-        a_inputs_source,
-        FullDensity,
-        input_assignment.clone(),
+    let inputs_window = window(input_assignment.len(), input_assignment.len());
+    let a_inputs = scheduled(
+        schedule,
+        phases.multiexp("a_inputs", || {
+            multiexp_with_window(
+                worker,
+                a_inputs_source,
+                FullDensity,
+                input_assignment.clone(),
+                inputs_window,
+            )
+        }),
     );
-    let a_aux = multiexp(
-        &worker,
-        a_aux_source,
-        Arc::new(prover.a_aux_density),
-        aux_assignment.clone(),
+    let a_aux_density = prover.a_aux_density;
+    let a_aux_window = window(aux_assignment.len(), a_aux_density_total);
+    let a_aux = scheduled(
+        schedule,
+        phases.multiexp("a_aux", || {
+            multiexp_with_window(
+                worker,
+                a_aux_source,
+                Arc::new(a_aux_density),
+                aux_assignment.clone(),
+                a_aux_window,
+            )
+        }),
     );
 
     let b_input_density = Arc::new(prover.b_input_density);
     let b_input_density_total = b_input_density.get_total_density();
     let b_aux_density = Arc::new(prover.b_aux_density);
     let b_aux_density_total = b_aux_density.get_total_density();
+    let b_inputs_window = window(input_assignment.len(), b_input_density_total);
+    let b_aux_window = window(aux_assignment.len(), b_aux_density_total);
 
     let (b_g1_inputs_source, b_g1_aux_source) =
         params.get_b_g1(b_input_density_total, b_aux_density_total)?;
 
-    let b_g1_inputs = multiexp(
-        &worker,
-        b_g1_inputs_source,
-        b_input_density.clone(),
-        input_assignment.clone(),
+    let b_g1_inputs = scheduled(
+        schedule,
+        phases.multiexp("b_g1_inputs", || {
+            multiexp_with_window(
+                worker,
+                b_g1_inputs_source,
+                b_input_density.clone(),
+                input_assignment.clone(),
+                b_inputs_window,
+            )
+        }),
     );
-    let b_g1_aux = multiexp(
-        &worker,
-        b_g1_aux_source,
-        b_aux_density.clone(),
-        aux_assignment.clone(),
+    let b_g1_aux = scheduled(
+        schedule,
+        phases.multiexp("b_g1_aux", || {
+            multiexp_with_window(
+                worker,
+                b_g1_aux_source,
+                b_aux_density.clone(),
+                aux_assignment.clone(),
+                b_aux_window,
+            )
+        }),
     );
 
     let (b_g2_inputs_source, b_g2_aux_source) =
         params.get_b_g2(b_input_density_total, b_aux_density_total)?;
 
-    let b_g2_inputs = multiexp(
-        &worker,
-        b_g2_inputs_source,
-        b_input_density,
-        input_assignment,
+    let b_g2_inputs = scheduled(
+        schedule,
+        phases.multiexp("b_g2_inputs", || {
+            multiexp_with_window(
+                worker,
+                b_g2_inputs_source,
+                b_input_density,
+                input_assignment,
+                b_inputs_window,
+            )
+        }),
+    );
+    let b_g2_aux = scheduled(
+        schedule,
+        phases.multiexp("b_g2_aux", || {
+            multiexp_with_window(
+                worker,
+                b_g2_aux_source,
+                b_aux_density,
+                aux_assignment,
+                b_aux_window,
+            )
+        }),
     );
-    let b_g2_aux = multiexp(&worker, b_g2_aux_source, b_aux_density, aux_assignment);
 
     if bool::from(vk.delta_g1.is_identity() | vk.delta_g2.is_identity()) {
         // If this element is zero, someone is trying to perform a
@@ -313,39 +1241,187 @@ where
         return Err(SynthesisError::UnexpectedIdentity);
     }
 
-    let mut g_a = vk.delta_g1 * &r;
+    let mut g_a = vk.delta_g1 * &*r;
     AddAssign::<&E::G1Affine>::add_assign(&mut g_a, &vk.alpha_g1);
-    let mut g_b = vk.delta_g2 * &s;
+    let mut g_b = vk.delta_g2 * &*s;
     AddAssign::<&E::G2Affine>::add_assign(&mut g_b, &vk.beta_g2);
     let mut g_c;
     {
-        let mut rs = r;
-        rs.mul_assign(&s);
+        let mut rs = Secret::new(*r, E::Fr::zero());
+        rs.mul_assign(&*s);
 
-        g_c = vk.delta_g1 * &rs;
-        AddAssign::<&E::G1>::add_assign(&mut g_c, &(vk.alpha_g1 * &s));
-        AddAssign::<&E::G1>::add_assign(&mut g_c, &(vk.beta_g1 * &r));
+        g_c = vk.delta_g1 * &*rs;
+        AddAssign::<&E::G1>::add_assign(&mut g_c, &(vk.alpha_g1 * &*s));
+        AddAssign::<&E::G1>::add_assign(&mut g_c, &(vk.beta_g1 * &*r));
     }
-    let mut a_answer = a_inputs.wait()?;
-    AddAssign::<&E::G1>::add_assign(&mut a_answer, &a_aux.wait()?);
+    let mut a_answer = phases.wait(a_inputs)?;
+    AddAssign::<&E::G1>::add_assign(&mut a_answer, &phases.wait(a_aux)?);
     AddAssign::<&E::G1>::add_assign(&mut g_a, &a_answer);
-    MulAssign::<E::Fr>::mul_assign(&mut a_answer, s);
+    MulAssign::<E::Fr>::mul_assign(&mut a_answer, *s);
     AddAssign::<&E::G1>::add_assign(&mut g_c, &a_answer);
 
-    let mut b1_answer: E::G1 = b_g1_inputs.wait()?;
-    AddAssign::<&E::G1>::add_assign(&mut b1_answer, &b_g1_aux.wait()?);
-    let mut b2_answer = b_g2_inputs.wait()?;
-    AddAssign::<&E::G2>::add_assign(&mut b2_answer, &b_g2_aux.wait()?);
+    let mut b1_answer: E::G1 = phases.wait(b_g1_inputs)?;
+    AddAssign::<&E::G1>::add_assign(&mut b1_answer, &phases.wait(b_g1_aux)?);
+    let mut b2_answer = phases.wait(b_g2_inputs)?;
+    AddAssign::<&E::G2>::add_assign(&mut b2_answer, &phases.wait(b_g2_aux)?);
 
     AddAssign::<&E::G2>::add_assign(&mut g_b, &b2_answer);
-    MulAssign::<E::Fr>::mul_assign(&mut b1_answer, r);
+    MulAssign::<E::Fr>::mul_assign(&mut b1_answer, *r);
     AddAssign::<&E::G1>::add_assign(&mut g_c, &b1_answer);
-    AddAssign::<&E::G1>::add_assign(&mut g_c, &h.wait()?);
-    AddAssign::<&E::G1>::add_assign(&mut g_c, &l.wait()?);
+    AddAssign::<&E::G1>::add_assign(&mut g_c, &phases.wait(h)?);
+    AddAssign::<&E::G1>::add_assign(&mut g_c, &phases.wait(l)?);
 
-    Ok(Proof {
+    metrics::increment("bellman_proofs_created_total", &[]);
+
+    let proof = Proof {
         a: g_a.to_affine(),
         b: g_b.to_affine(),
         c: g_c.to_affine(),
-    })
+    };
+    Ok((proof, vk, plan))
+}
+
+/// Options for [`create_proof_paranoid`].
+#[derive(Clone, Debug)]
+pub struct ParanoidOptions {
+    /// Whether to check that the witness satisfies every constraint before
+    /// proving. On by default.
+    pub check_constraints: bool,
+}
+
+impl Default for ParanoidOptions {
+    fn default() -> Self {
+        ParanoidOptions {
+            check_constraints: true,
+        }
+    }
+}
+
+/// Why [`create_proof_paranoid`] did not return a proof.
+#[derive(Debug)]
+pub enum ParanoidError {
+    /// Synthesis or proving failed.
+    Synthesis(SynthesisError),
+    /// The witness does not satisfy the constraint with this index, in the
+    /// order in which the circuit enforced them.
+    Unsatisfied { constraint: usize },
+    /// The proof does not verify against the verifying key of the
+    /// parameters with the public inputs of the witness.
+    Rejected(VerificationError),
+}
+
+impl From<SynthesisError> for ParanoidError {
+    fn from(e: SynthesisError) -> Self {
+        ParanoidError::Synthesis(e)
+    }
+}
+
+impl Error for ParanoidError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParanoidError::Synthesis(e) => Some(e),
+            ParanoidError::Unsatisfied { .. } => None,
+            ParanoidError::Rejected(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for ParanoidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParanoidError::Synthesis(e) => write!(f, "proving failed: {}", e),
+            ParanoidError::Unsatisfied { constraint } => {
+                write!(f, "the witness does not satisfy constraint {}", constraint)
+            }
+            ParanoidError::Rejected(e) => write!(f, "the proof was rejected: {}", e),
+        }
+    }
+}
+
+impl From<ParanoidError> for crate::error::Error {
+    fn from(e: ParanoidError) -> Self {
+        match e {
+            ParanoidError::Synthesis(e) => e.into(),
+            e @ ParanoidError::Unsatisfied { .. } => {
+                crate::error::Error::new(crate::error::ErrorKind::Synthesis, e)
+            }
+            e @ ParanoidError::Rejected(_) => {
+                crate::error::Error::new(crate::error::ErrorKind::VerificationFailed, e)
+            }
+        }
+    }
+}
+
+/// Creates a proof like [`create_proof`], and verifies it against the
+/// verifying key of `params` before returning it, so that a bad proof is
+/// never emitted silently. With [`ParanoidOptions::check_constraints`], the
+/// witness is first checked against every constraint, which pinpoints the
+/// first unsatisfied one instead of only reporting a rejected proof.
+///
+/// This costs a verification, and a pass over the constraints, on top of
+/// proving.
+pub fn create_proof_paranoid<E, C, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    r: E::Fr,
+    s: E::Fr,
+    options: &ParanoidOptions,
+) -> Result<Proof<E>, ParanoidError>
+where
+    E: MultiMillerLoop,
+    C: Circuit<E::Fr>,
+{
+    let _span = trace::span("create_proof_paranoid");
+
+    let r = Secret::new(r, E::Fr::zero());
+    let s = Secret::new(s, E::Fr::zero());
+
+    let prover = synthesize(circuit)?;
+    if options.check_constraints {
+        let unsatisfied = prover
+            .a
+            .iter()
+            .zip(prover.b.iter())
+            .zip(prover.c.iter())
+            .position(|((a, b), c)| a.0 * &b.0 != c.0);
+        if let Some(constraint) = unsatisfied {
+            return Err(ParanoidError::Unsatisfied { constraint });
+        }
+    }
+
+    // Input 0 is ONE, which is not a public input of the proof.
+    let inputs = prover.input_assignment[1..].to_vec();
+    let (proof, vk, _) = prove_assignment(
+        &Worker::new(),
+        prover,
+        params,
+        r,
+        s,
+        &mut Phases::none(),
+        &mut plan,
+    )?;
+
+    let pvk = prepare_verifying_key(&vk);
+    verify_proof(&pvk, &proof, &inputs).map_err(ParanoidError::Rejected)?;
+
+    Ok(proof)
+}
+
+/// Creates a proof with [`create_proof_paranoid`], with random blinding
+/// factors.
+pub fn create_random_proof_paranoid<E, C, R, P: ParameterSource<E>>(
+    circuit: C,
+    params: P,
+    mut rng: &mut R,
+    options: &ParanoidOptions,
+) -> Result<Proof<E>, ParanoidError>
+where
+    E: MultiMillerLoop,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let r = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+    let s = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+
+    create_proof_paranoid::<E, C, P>(circuit, params, *r, *s, options)
 }
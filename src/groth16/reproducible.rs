@@ -0,0 +1,137 @@
+//! A deterministic proving pipeline, for catching platform-dependent bugs.
+//!
+//! A proof is a function of the parameters, the witness and the blinding
+//! factors alone, so every platform must produce the same bytes for them.
+//! [`transcript`] derives all of those from a seed, proves on a fixed number
+//! of threads with a fixed plan, and writes the parameters and the proofs.
+//! The tests pin the digest of the transcript, so that a build whose field
+//! arithmetic or serialization differs on some target (aarch64 rather than
+//! x86_64, say) fails them before it is released.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::{Field, PrimeField};
+use group::{Group, WnafGroup};
+use pairing::MultiMillerLoop;
+
+use super::exporter::ReplayCircuit;
+use super::fuzz::{random_circuit, CircuitConfig};
+use super::planner::{estimate_memory, Capabilities, Plan, ProvingStats, Quotient};
+use super::planner::{Schedule, Windows};
+use super::vectors::{configs, SeededRng};
+use super::{create_proof_planned, generate_parameters, prepare_verifying_key, verify_proof};
+use crate::multicore::Worker;
+
+/// The number of threads of the pipeline, whatever the machine has.
+const THREADS: usize = 4;
+
+/// The plan of the pipeline: the quotient, the windows and the
+/// multiexponentiations are computed in the same order on every machine.
+fn fixed_plan(stats: &ProvingStats, _: &Capabilities) -> Plan {
+    Plan {
+        quotient: Quotient::Staged,
+        windows: Windows::Length,
+        schedule: Schedule::Sequential,
+        estimated_memory: estimate_memory(stats, Quotient::Staged),
+    }
+}
+
+/// Generates parameters and a proof for each circuit of the test vectors,
+/// and one whose domain is larger than the threads, from `seed`. Proves on
+/// `threads` threads with the plan chosen by `choose`, checks each proof,
+/// and returns the parameters, the public inputs and the proofs as written
+/// by this crate.
+fn transcript<E, F>(seed: &[u8], threads: usize, mut choose: F) -> Vec<u8>
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+    F: FnMut(&ProvingStats, &Capabilities) -> Plan,
+{
+    let worker = Worker::new_with_cpus(threads);
+    let mut circuits = configs();
+    circuits.push((
+        "parallel",
+        CircuitConfig {
+            num_inputs: 3,
+            num_aux: 40,
+            num_constraints: 100,
+            terms: 3,
+        },
+    ));
+
+    let mut bytes = vec![];
+    for (name, config) in circuits {
+        let mut rng = SeededRng::new(seed, name);
+        let (circuit, witness) = random_circuit::<E::Fr, _>(&config, &mut rng);
+        let params = generate_parameters::<E, _>(
+            ReplayCircuit {
+                circuit: circuit.clone(),
+                assignment: None,
+            },
+            E::G1::generator(),
+            E::G2::generator(),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+        )
+        .unwrap();
+        let (proof, _) = create_proof_planned(
+            &worker,
+            ReplayCircuit {
+                circuit,
+                assignment: Some(witness.clone()),
+            },
+            &params,
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            &mut choose,
+        )
+        .unwrap();
+        let inputs = &witness.inputs[1..];
+        assert!(verify_proof(&prepare_verifying_key(&params.vk), &proof, inputs).is_ok());
+
+        bytes.extend_from_slice(name.as_bytes());
+        params.write(&mut bytes).unwrap();
+        for input in inputs {
+            bytes.extend_from_slice(input.to_repr().as_ref());
+        }
+        proof.write(&mut bytes).unwrap();
+    }
+    bytes
+}
+
+/// Checks that the transcript for `seed` does not depend on the threads or
+/// the plan, and returns its digest.
+fn digest<E>(seed: &[u8]) -> String
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+{
+    let bytes = transcript::<E, _>(seed, THREADS, fixed_plan);
+    assert!(transcript::<E, _>(seed, 1, fixed_plan) == bytes);
+    assert!(transcript::<E, _>(seed, THREADS, super::planner::plan) == bytes);
+    Blake2sParams::new().hash(&bytes).to_hex().to_string()
+}
+
+// A change to these digests must be deliberate: a change to the encodings
+// or to the way the transcript is derived, not to the platform.
+
+#[test]
+fn reproducible_bls12_381() {
+    assert_eq!(
+        digest::<bls12_381::Bls12>(b"bellman reproducible proofs"),
+        "c92052d30a56cd9b9e34c887b9e0780c589e2c1c36b47763ecda821cd1409e0d"
+    );
+}
+
+#[cfg(feature = "bn254")]
+#[test]
+fn reproducible_bn254() {
+    assert_eq!(
+        digest::<crate::bn254::Bn254>(b"bellman reproducible proofs"),
+        "51808af00d5fb9cf558f3fc7c76fa2859b6b23b64db4ab5db9d95ab34062afd2"
+    );
+}
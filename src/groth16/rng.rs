@@ -0,0 +1,286 @@
+//! Randomness for the blinding factors of proofs.
+//!
+//! The blinding factors `r` and `s` of a proof are what makes it zero
+//! knowledge: a proof made with predictable factors, or two proofs made with
+//! the same ones, reveal information about the witness. [`create_random_proof`]
+//! accepts any [`RngCore`]; [`create_proof_with_rng`] instead takes a
+//! [`ProverRng`], which only wraps OS entropy or a [`CryptoRng`] that passes a
+//! health check, and refuses to hand out a factor that is zero or that it
+//! handed out among its last [`REMEMBERED_FACTORS`]. Deterministic RNGs, for
+//! tests, have to be opted into with [`ProverRng::insecure`].
+//!
+//! [`create_random_proof`]: super::create_random_proof
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::PrimeField;
+use pairing::Engine;
+use rand_core::{CryptoRng, RngCore};
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use super::{create_proof, ParameterSource, Proof};
+use crate::zeroize::Secret;
+use crate::{Circuit, SynthesisError};
+
+/// The number of blocks of 32 bytes drawn by the health check.
+const HEALTH_BLOCKS: usize = 4;
+
+/// The number of the latest blinding factors that a [`ProverRng`] checks new
+/// ones against, so that a long-lived one does not grow without bound.
+pub const REMEMBERED_FACTORS: usize = 1 << 10;
+
+/// Why a proof was not created with a [`ProverRng`].
+#[derive(Debug)]
+pub enum ProverRngError {
+    /// Synthesis or proving failed.
+    Synthesis(SynthesisError),
+    /// The RNG failed the health check: it repeated itself, or produced a
+    /// block of identical bytes.
+    Unhealthy,
+    /// The RNG produced a blinding factor that it already produced.
+    Reused,
+    /// The RNG produced a blinding factor that is zero.
+    ZeroFactor,
+}
+
+impl From<SynthesisError> for ProverRngError {
+    fn from(e: SynthesisError) -> Self {
+        ProverRngError::Synthesis(e)
+    }
+}
+
+impl Error for ProverRngError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProverRngError::Synthesis(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProverRngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProverRngError::Synthesis(e) => write!(f, "proving failed: {}", e),
+            ProverRngError::Unhealthy => write!(f, "the RNG failed its health check"),
+            ProverRngError::Reused => write!(f, "the RNG repeated a blinding factor"),
+            ProverRngError::ZeroFactor => write!(f, "the RNG produced a zero blinding factor"),
+        }
+    }
+}
+
+impl From<ProverRngError> for crate::error::Error {
+    fn from(e: ProverRngError) -> Self {
+        match e {
+            ProverRngError::Synthesis(e) => e.into(),
+            e => crate::error::Error::new(crate::error::ErrorKind::Synthesis, e),
+        }
+    }
+}
+
+/// An RNG that is trusted to draw blinding factors.
+pub struct ProverRng<R: RngCore> {
+    rng: R,
+    // Hashes of the latest factors drawn, to detect reuse without keeping
+    // the factors themselves, and the order in which they were drawn.
+    drawn: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+}
+
+#[cfg(feature = "os-rng")]
+impl ProverRng<rand_core::OsRng> {
+    /// Draws blinding factors from the entropy of the operating system.
+    pub fn os() -> Result<Self, ProverRngError> {
+        ProverRng::new(rand_core::OsRng)
+    }
+}
+
+impl<R: RngCore + CryptoRng> ProverRng<R> {
+    /// Draws blinding factors from `rng`, if it passes a health check.
+    pub fn new(mut rng: R) -> Result<Self, ProverRngError> {
+        let mut blocks = [[0u8; 32]; HEALTH_BLOCKS];
+        for block in blocks.iter_mut() {
+            rng.try_fill_bytes(block)
+                .map_err(|_| ProverRngError::Unhealthy)?;
+        }
+
+        let repeated = blocks
+            .iter()
+            .enumerate()
+            .any(|(i, block)| block.iter().all(|b| *b == block[0]) || blocks[..i].contains(block));
+        if repeated {
+            return Err(ProverRngError::Unhealthy);
+        }
+
+        Ok(ProverRng::insecure(rng))
+    }
+}
+
+impl<R: RngCore> ProverRng<R> {
+    /// Draws blinding factors from `rng`, without checking that it is a
+    /// cryptographic RNG. Proofs made this way are only zero knowledge if
+    /// `rng` is unpredictable, so this is meant for tests and for
+    /// reproducing proofs.
+    pub fn insecure(rng: R) -> Self {
+        ProverRng {
+            rng,
+            drawn: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn draw<S: PrimeField>(&mut self) -> Result<Secret<S>, ProverRngError> {
+        let factor = Secret::new(S::random(&mut self.rng), S::zero());
+        if factor.is_zero() {
+            return Err(ProverRngError::ZeroFactor);
+        }
+
+        let mut hash = [0; 32];
+        hash.copy_from_slice(
+            Blake2sParams::new()
+                .personal(b"bellBlnd")
+                .hash(factor.to_repr().as_ref())
+                .as_bytes(),
+        );
+        if !self.drawn.insert(hash) {
+            return Err(ProverRngError::Reused);
+        }
+        self.order.push_back(hash);
+        if self.order.len() > REMEMBERED_FACTORS {
+            let oldest = self.order.pop_front().unwrap();
+            self.drawn.remove(&oldest);
+        }
+        Ok(factor)
+    }
+
+    /// Draws the blinding factors `r` and `s` of a proof.
+    pub(crate) fn blinding<S: PrimeField>(
+        &mut self,
+    ) -> Result<(Secret<S>, Secret<S>), ProverRngError> {
+        let r = self.draw()?;
+        let s = self.draw()?;
+        Ok((r, s))
+    }
+}
+
+/// Creates a proof like [`create_random_proof`], with blinding factors drawn
+/// from `rng`.
+///
+/// [`create_random_proof`]: super::create_random_proof
+pub fn create_proof_with_rng<E, C, R, P>(
+    circuit: C,
+    params: P,
+    rng: &mut ProverRng<R>,
+) -> Result<Proof<E>, ProverRngError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+    P: ParameterSource<E>,
+{
+    let (r, s) = rng.blinding::<E::Fr>()?;
+    Ok(create_proof::<E, C, P>(circuit, params, *r, *s)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::{rng, Fixture};
+    use crate::groth16::verify_proof;
+    use bls12_381::{Bls12, Scalar};
+
+    /// Claims to be a cryptographic RNG, and repeats `bytes`.
+    struct Periodic {
+        bytes: Vec<u8>,
+        pos: usize,
+    }
+
+    impl CryptoRng for Periodic {}
+
+    impl RngCore for Periodic {
+        fn next_u32(&mut self) -> u32 {
+            rand_core::impls::next_u32_via_fill(self)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            rand_core::impls::next_u64_via_fill(self)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for b in dest {
+                *b = self.bytes[self.pos % self.bytes.len()];
+                self.pos += 1;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn enforces_blinding_entropy() {
+        let mut f = Fixture::<Bls12>::new();
+
+        #[cfg(feature = "os-rng")]
+        {
+            let mut os = ProverRng::os().unwrap();
+            for _ in 0..2 {
+                let proof = create_proof_with_rng(f.circuit(), &f.params, &mut os).unwrap();
+                assert!(verify_proof(&f.pvk, &proof, f.inputs()).is_ok());
+            }
+        }
+
+        let mut insecure = ProverRng::insecure(f.rng.clone());
+        let proof = create_proof_with_rng(f.circuit(), &f.params, &mut insecure).unwrap();
+        assert!(verify_proof(&f.pvk, &proof, f.inputs()).is_ok());
+
+        assert!(matches!(
+            ProverRng::new(Periodic {
+                bytes: vec![0],
+                pos: 0
+            }),
+            Err(ProverRngError::Unhealthy)
+        ));
+        assert!(matches!(
+            ProverRng::new(Periodic {
+                bytes: (0..64).collect(),
+                pos: 0
+            }),
+            Err(ProverRngError::Unhealthy)
+        ));
+
+        // A period of 192 bytes passes the health check, which draws 128,
+        // but repeats the first factor on the second proof, which draws 128
+        // per proof.
+        let mut bytes = vec![0; 192];
+        f.rng.fill_bytes(&mut bytes);
+        let mut periodic = ProverRng::new(Periodic { bytes, pos: 0 }).unwrap();
+        assert!(create_proof_with_rng(f.circuit(), &f.params, &mut periodic).is_ok());
+        assert!(matches!(
+            create_proof_with_rng(f.circuit(), &f.params, &mut periodic),
+            Err(ProverRngError::Reused)
+        ));
+
+        // The same period, with zeros where the first factor is drawn.
+        let mut bytes = vec![0; 192];
+        f.rng.fill_bytes(&mut bytes[..128]);
+        let mut zero = ProverRng::new(Periodic { bytes, pos: 0 }).unwrap();
+        assert!(matches!(
+            create_proof_with_rng(f.circuit(), &f.params, &mut zero),
+            Err(ProverRngError::ZeroFactor)
+        ));
+    }
+
+    #[test]
+    fn remembers_the_latest_factors() {
+        let mut rng = ProverRng::insecure(rng());
+        for _ in 0..REMEMBERED_FACTORS + 10 {
+            rng.draw::<Scalar>().unwrap();
+        }
+        assert_eq!(rng.drawn.len(), REMEMBERED_FACTORS);
+        assert_eq!(rng.order.len(), REMEMBERED_FACTORS);
+    }
+}
@@ -0,0 +1,168 @@
+//! Evaluated witnesses, encrypted to be proven later.
+//!
+//! Queue-based provers separate witness generation from proving, in time or
+//! across machines. [`seal_witness`] synthesizes a circuit and writes its
+//! evaluated witness encrypted with a [`Key`], and [`SealedWitness::prove`]
+//! proves it with the parameters of the circuit, without the circuit itself.
+//!
+//! The fingerprint of the circuit, such as [`RawCircuit::fingerprint`], is
+//! written in the clear, so that queues can route a sealed witness to the
+//! prover holding the right parameters. It is repeated in the encrypted
+//! stream, where it is authenticated.
+//!
+//! # Format
+//!
+//! ```text
+//! magic      "bellman-sealed-witness"
+//! version    u32, big-endian (currently 1)
+//! circuit    32 bytes
+//! witness    a stream encrypted as by EncryptedWriter, of the circuit
+//!            fingerprint again, followed by the evaluated witness
+//! ```
+//!
+//! [`RawCircuit::fingerprint`]: super::exporter::RawCircuit::fingerprint
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField};
+use pairing::Engine;
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+
+use super::encrypted::{EncryptedReader, EncryptedWriter, Key};
+use super::prover::{prove_evaluated, write_evaluated};
+use super::{ParameterSource, Proof};
+use crate::zeroize::Secret;
+use crate::{Circuit, SynthesisError};
+
+pub(super) const MAGIC: &[u8] = b"bellman-sealed-witness";
+const VERSION: u32 = 1;
+
+/// Synthesizes `circuit`, the circuit with fingerprint `fingerprint`, and
+/// writes its evaluated witness to `writer`, encrypted with `key`.
+pub fn seal_witness<S, C, W, R>(
+    circuit: C,
+    fingerprint: &[u8; 32],
+    mut writer: W,
+    key: &Key,
+    rng: &mut R,
+) -> Result<W, SynthesisError>
+where
+    S: PrimeField,
+    C: Circuit<S>,
+    W: Write,
+    R: RngCore,
+{
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<BigEndian>(VERSION)?;
+    writer.write_all(fingerprint)?;
+
+    let mut writer = EncryptedWriter::new(writer, key, rng)?;
+    writer.write_all(fingerprint)?;
+    write_evaluated(circuit, &mut writer)?;
+    Ok(writer.finish()?)
+}
+
+/// A witness written by [`seal_witness`], whose header has been read.
+pub struct SealedWitness<R: Read> {
+    circuit: [u8; 32],
+    reader: R,
+}
+
+impl<R: Read> SealedWitness<R> {
+    /// Reads the header of a sealed witness, leaving the witness itself to
+    /// be decrypted by [`SealedWitness::prove`].
+    pub fn read(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a sealed witness",
+            ));
+        }
+        if reader.read_u32::<BigEndian>()? != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported sealed witness version",
+            ));
+        }
+
+        let mut circuit = [0; 32];
+        reader.read_exact(&mut circuit)?;
+        Ok(SealedWitness { circuit, reader })
+    }
+
+    /// Returns the fingerprint of the circuit the witness is for, as claimed
+    /// by its header.
+    pub fn circuit(&self) -> &[u8; 32] {
+        &self.circuit
+    }
+
+    /// Decrypts the witness with `key`, and proves it with `params`.
+    pub fn prove<E, P, G>(
+        self,
+        key: &Key,
+        params: P,
+        mut rng: &mut G,
+    ) -> Result<Proof<E>, SynthesisError>
+    where
+        E: Engine,
+        P: ParameterSource<E>,
+        G: RngCore,
+    {
+        let mut reader = EncryptedReader::new(self.reader, key)?;
+        let mut circuit = [0; 32];
+        reader.read_exact(&mut circuit)?;
+        if circuit != self.circuit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the header does not match the sealed circuit",
+            )
+            .into());
+        }
+
+        let r = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+        let s = Secret::new(E::Fr::random(&mut rng), E::Fr::zero());
+        prove_evaluated(&mut reader, params, r, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::verify_proof;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn deferred_proving() {
+        let mut f = Fixture::<Bls12>::new();
+        let fingerprint = f.circuit.fingerprint();
+        let key = Key::new([5; 32]);
+
+        let sealed = seal_witness(f.circuit(), &fingerprint, vec![], &key, &mut f.rng).unwrap();
+
+        let opened = SealedWitness::read(&sealed[..]).unwrap();
+        assert_eq!(opened.circuit(), &fingerprint);
+        let proof = opened.prove(&key, &f.params, &mut f.rng).unwrap();
+        assert!(verify_proof(&f.pvk, &proof, f.inputs()).is_ok());
+
+        let wrong = SealedWitness::read(&sealed[..]).unwrap();
+        assert!(wrong
+            .prove::<Bls12, _, _>(&Key::new([6; 32]), &f.params, &mut f.rng)
+            .is_err());
+
+        // The fingerprint in the clear is checked against the sealed one.
+        let mut relabeled = sealed.clone();
+        relabeled[MAGIC.len() + 4] ^= 1;
+        let relabeled = SealedWitness::read(&relabeled[..]).unwrap();
+        assert!(relabeled
+            .prove::<Bls12, _, _>(&key, &f.params, &mut f.rng)
+            .is_err());
+
+        assert!(SealedWitness::read(&sealed[..sealed.len() - 1])
+            .unwrap()
+            .prove::<Bls12, _, _>(&key, &f.params, &mut f.rng)
+            .is_err());
+    }
+}
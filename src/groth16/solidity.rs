@@ -0,0 +1,649 @@
+//! Solidity verifiers for verifying keys, and the encodings of the EVM
+//! precompiles.
+//!
+//! [`VerifyingKey::export_solidity`] writes a contract that verifies proofs
+//! for one verifying key with the precompiles of its curve, whose points
+//! are baked into the code. The contract takes proofs in the encoding of the
+//! precompiles, as written by [`Proof::write_ethereum_abi`], and the public inputs as
+//! `uint256` values below the modulus of the scalar field.
+//!
+//! Curves with precompiles implement [`EvmEngine`]: this module implements
+//! it for BLS12-381, with the precompiles of EIP-2537, and, with the
+//! `bn254` feature, for BN254, with those of EIP-196 and EIP-197 that
+//! Ethereum has today.
+//!
+//! A proof is valid when `e(A, B) = e(α, β) · e(vk_x, γ) · e(C, δ)`, where
+//! `vk_x` combines the IC points with the inputs. The contract checks the
+//! equivalent `e(A, B) · e(α, -β) · e(vk_x, -γ) · e(C, -δ) = 1` with one
+//! call to the pairing precompile, negating the fixed G2 points rather than
+//! the points of the proof, so that it does no arithmetic in the base field.
+
+use group::prime::PrimeCurveAffine;
+use pairing::MultiMillerLoop;
+use std::io::{self, Read, Write};
+use std::ops::Neg;
+
+use super::exporter::R1CSExport;
+use super::{Proof, VerifyingKey};
+
+/// The addresses of the precompiles of a curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Precompiles {
+    /// Adds two G1 points.
+    pub g1_add: u8,
+    /// Multiplies a G1 point by a scalar, given as the point followed by the
+    /// scalar as 32 big-endian bytes.
+    pub g1_mul: u8,
+    /// Whether `g1_mul` takes any number of pairs of a point and a scalar,
+    /// and returns the sum of their products.
+    pub g1_msm: bool,
+    /// Returns 1, as 32 big-endian bytes, if the product of the pairings of
+    /// the given pairs of a G1 point and a G2 point is the identity.
+    pub pairing: u8,
+}
+
+/// An engine whose curve operations have EVM precompiles.
+pub trait EvmEngine: MultiMillerLoop {
+    /// The name of the curve, for the comments of the contract.
+    const CURVE: &'static str;
+
+    const PRECOMPILES: Precompiles;
+
+    /// Encodes a G1 point as the precompiles expect it.
+    fn encode_g1(point: &Self::G1Affine) -> Vec<u8>;
+
+    /// Decodes a G1 point encoded by [`EvmEngine::encode_g1`], checking that
+    /// it is in the prime-order subgroup.
+    fn decode_g1(bytes: &[u8]) -> Option<Self::G1Affine>;
+
+    /// Encodes a G2 point as the precompiles expect it.
+    fn encode_g2(point: &Self::G2Affine) -> Vec<u8>;
+
+    /// Decodes a G2 point encoded by [`EvmEngine::encode_g2`], checking that
+    /// it is in the prime-order subgroup.
+    fn decode_g2(bytes: &[u8]) -> Option<Self::G2Affine>;
+
+    /// Encodes a scalar as a big-endian `uint256`.
+    fn encode_scalar(scalar: &Self::Fr) -> [u8; 32];
+}
+
+fn g1_len<E: EvmEngine>() -> usize {
+    E::encode_g1(&E::G1Affine::generator()).len()
+}
+
+fn g2_len<E: EvmEngine>() -> usize {
+    E::encode_g2(&E::G2Affine::generator()).len()
+}
+
+impl<E: EvmEngine> Proof<E> {
+    /// Writes `A`, `B` and `C` in the encoding of the precompiles, as taken
+    /// by the contracts of [`VerifyingKey::export_solidity`].
+    ///
+    /// On BN254 these are the eight words of the proof of the usual
+    /// verifiers, `abi.encode(uint256[2] a, uint256[2][2] b, uint256[2] c)`,
+    /// with the imaginary parts of the coordinates of `B` first.
+    pub fn write_ethereum_abi<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&E::encode_g1(&self.a))?;
+        writer.write_all(&E::encode_g2(&self.b))?;
+        writer.write_all(&E::encode_g1(&self.c))?;
+        Ok(())
+    }
+
+    /// Reads a proof written by [`Proof::write_ethereum_abi`], checking that its
+    /// points are in the prime-order subgroups.
+    pub fn read_ethereum_abi<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |point| io::Error::new(io::ErrorKind::InvalidData, point);
+        let mut g1 = vec![0; g1_len::<E>()];
+        let mut g2 = vec![0; g2_len::<E>()];
+
+        reader.read_exact(&mut g1)?;
+        let a = E::decode_g1(&g1).ok_or_else(|| invalid("invalid A"))?;
+        reader.read_exact(&mut g2)?;
+        let b = E::decode_g2(&g2).ok_or_else(|| invalid("invalid B"))?;
+        reader.read_exact(&mut g1)?;
+        let c = E::decode_g1(&g1).ok_or_else(|| invalid("invalid C"))?;
+
+        Ok(Proof { a, b, c })
+    }
+}
+
+/// Encodes `value` as a big-endian `uint256`.
+fn word(value: usize) -> [u8; 32] {
+    let mut word = [0; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+impl<E: EvmEngine> VerifyingKey<E> {
+    /// Writes the points that verifiers need in the ABI encoding of the
+    /// tuple `(alpha_g1, beta_g2, gamma_g2, delta_g2, ic)`, where points are
+    /// static arrays of the words of their encodings and `ic` is a dynamic
+    /// array of them. On BN254 this is decoded by
+    /// `abi.decode(data, (uint256[2], uint256[2][2], uint256[2][2], uint256[2][2], uint256[2][]))`.
+    ///
+    /// `beta_g1` and `delta_g1`, which only provers use, are left out, so
+    /// that the key cannot be read back.
+    pub fn write_ethereum_abi<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let head = g1_len::<E>() + 3 * g2_len::<E>() + 32;
+        writer.write_all(&E::encode_g1(&self.alpha_g1))?;
+        writer.write_all(&E::encode_g2(&self.beta_g2))?;
+        writer.write_all(&E::encode_g2(&self.gamma_g2))?;
+        writer.write_all(&E::encode_g2(&self.delta_g2))?;
+        // The offset of the dynamic array, after the head.
+        writer.write_all(&word(head))?;
+        writer.write_all(&word(self.ic.len()))?;
+        for ic in &self.ic {
+            writer.write_all(&E::encode_g1(ic))?;
+        }
+        Ok(())
+    }
+
+    /// Writes a Solidity contract, `Groth16Verifier`, whose `verifyProof`
+    /// function checks proofs for this key:
+    ///
+    /// ```text
+    /// function verifyProof(bytes calldata proof, uint256[] calldata inputs)
+    ///     external view returns (bool)
+    /// ```
+    ///
+    /// `proof` is encoded by [`Proof::write_ethereum_abi`], and `inputs` are the
+    /// public inputs without `ONE`, encoded by [`EvmEngine::encode_scalar`].
+    /// Proofs of the wrong length, inputs of the wrong number or not below
+    /// the modulus, and points the precompiles reject are invalid.
+    pub fn export_solidity<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let precompiles = E::PRECOMPILES;
+        let (g1_len, g2_len) = (g1_len::<E>(), g2_len::<E>());
+        let num_inputs = self.ic.len() - 1;
+        let g1 = |point: &E::G1Affine| format!("hex\"{}\"", hex(&E::encode_g1(point)));
+        let g2 = |point: &E::G2Affine| format!("hex\"{}\"", hex(&E::encode_g2(point)));
+
+        writeln!(writer, "// SPDX-License-Identifier: MIT OR Apache-2.0")?;
+        writeln!(
+            writer,
+            "// Verifies Groth16 proofs on {} for one verifying key.",
+            E::CURVE
+        )?;
+        writeln!(writer, "pragma solidity ^0.8.4;")?;
+        writeln!(writer)?;
+        writeln!(writer, "contract Groth16Verifier {{")?;
+        writeln!(
+            writer,
+            "    uint256 private constant SCALAR_MODULUS = 0x{};",
+            hex(&R1CSExport::<E::Fr>::field_modulus())
+        )?;
+        writeln!(
+            writer,
+            "    uint256 private constant NUM_INPUTS = {};",
+            num_inputs
+        )?;
+        writeln!(writer, "    uint256 private constant G1_LEN = {};", g1_len)?;
+        writeln!(writer, "    uint256 private constant G2_LEN = {};", g2_len)?;
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "    bytes private constant ALPHA_G1 = {};",
+            g1(&self.alpha_g1)
+        )?;
+        writeln!(
+            writer,
+            "    bytes private constant NEG_BETA_G2 = {};",
+            g2(&self.beta_g2.neg())
+        )?;
+        writeln!(
+            writer,
+            "    bytes private constant NEG_GAMMA_G2 = {};",
+            g2(&self.gamma_g2.neg())
+        )?;
+        writeln!(
+            writer,
+            "    bytes private constant NEG_DELTA_G2 = {};",
+            g2(&self.delta_g2.neg())
+        )?;
+        writeln!(writer)?;
+        write!(
+            writer,
+            "    function verifyProof(bytes calldata proof, uint256[] calldata inputs)
+        external
+        view
+        returns (bool)
+    {{
+        if (proof.length != 2 * G1_LEN + G2_LEN || inputs.length != NUM_INPUTS) {{
+            return false;
+        }}
+        for (uint256 i = 0; i < NUM_INPUTS; i++) {{
+            if (inputs[i] >= SCALAR_MODULUS) {{
+                return false;
+            }}
+        }}
+
+        (bool ok, bytes memory vkX) = combineInputs(inputs);
+        if (!ok) {{
+            return false;
+        }}
+        bytes memory pairs = bytes.concat(
+            proof[0:G1_LEN + G2_LEN],
+            ALPHA_G1,
+            NEG_BETA_G2,
+            vkX,
+            NEG_GAMMA_G2,
+            proof[G1_LEN + G2_LEN:],
+            NEG_DELTA_G2
+        );
+        bytes memory result;
+        (ok, result) = precompile(0x{:02x}, pairs, 32);
+        return ok && abi.decode(result, (uint256)) == 1;
+    }}
+
+    /// Computes IC[0] + inputs[0] * IC[1] + ... + inputs[n - 1] * IC[n].
+    function combineInputs(uint256[] calldata inputs) private view returns (bool, bytes memory) {{
+",
+            precompiles.pairing
+        )?;
+
+        if precompiles.g1_msm {
+            writeln!(writer, "        bytes memory pairs = bytes.concat(")?;
+            writeln!(writer, "            {},", g1(&self.ic[0]))?;
+            write!(writer, "            bytes32(uint256(1))")?;
+            for (i, ic) in self.ic[1..].iter().enumerate() {
+                write!(writer, ",\n            {},\n", g1(ic))?;
+                write!(writer, "            bytes32(inputs[{}])", i)?;
+            }
+            writeln!(writer, "\n        );")?;
+            writeln!(
+                writer,
+                "        return precompile(0x{:02x}, pairs, G1_LEN);",
+                precompiles.g1_mul
+            )?;
+        } else {
+            writeln!(writer, "        bool ok = true;")?;
+            writeln!(writer, "        bytes memory sum = {};", g1(&self.ic[0]))?;
+            writeln!(writer, "        bytes memory term;")?;
+            for (i, ic) in self.ic[1..].iter().enumerate() {
+                writeln!(
+                    writer,
+                    "        (ok, term) = precompile(0x{:02x}, bytes.concat({}, bytes32(inputs[{}])), G1_LEN);",
+                    precompiles.g1_mul,
+                    g1(ic),
+                    i
+                )?;
+                writeln!(
+                    writer,
+                    "        if (!ok) {{\n            return (false, sum);\n        }}"
+                )?;
+                writeln!(
+                    writer,
+                    "        (ok, sum) = precompile(0x{:02x}, bytes.concat(sum, term), G1_LEN);",
+                    precompiles.g1_add
+                )?;
+                writeln!(
+                    writer,
+                    "        if (!ok) {{\n            return (false, sum);\n        }}"
+                )?;
+            }
+            writeln!(writer, "        return (ok, sum);")?;
+        }
+
+        write!(
+            writer,
+            "    }}
+
+    function precompile(uint256 addr, bytes memory input, uint256 outputLength)
+        private
+        view
+        returns (bool, bytes memory)
+    {{
+        (bool ok, bytes memory output) = address(uint160(addr)).staticcall(input);
+        return (ok && output.length == outputLength, output);
+    }}
+}}
+"
+        )
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Pads each big-endian coordinate of 48 bytes to the 64 bytes of EIP-2537.
+#[cfg(any(test, feature = "bls12_381"))]
+fn pad_coordinates(coordinates: &[u8]) -> Vec<u8> {
+    coordinates
+        .chunks(48)
+        .flat_map(|coordinate| [0; 16].iter().chain(coordinate).copied())
+        .collect()
+}
+
+/// Strips the padding of [`pad_coordinates`], which must be zero.
+#[cfg(any(test, feature = "bls12_381"))]
+fn strip_coordinates(bytes: &[u8], len: usize) -> Option<Vec<u8>> {
+    if bytes.len() != len {
+        return None;
+    }
+    let mut coordinates = vec![];
+    for padded in bytes.chunks(64) {
+        if padded[..16].iter().any(|&b| b != 0) {
+            return None;
+        }
+        coordinates.extend_from_slice(&padded[16..]);
+    }
+    Some(coordinates)
+}
+
+/// BLS12-381, with the precompiles of EIP-2537, where coordinates are 64
+/// big-endian bytes, those of G2 with the real part first, and the identity
+/// is encoded as zeros.
+#[cfg(any(test, feature = "bls12_381"))]
+impl EvmEngine for bls12_381::Bls12 {
+    const CURVE: &'static str = "BLS12-381";
+
+    const PRECOMPILES: Precompiles = Precompiles {
+        g1_add: 0x0b,
+        g1_mul: 0x0c,
+        g1_msm: true,
+        pairing: 0x0f,
+    };
+
+    fn encode_g1(point: &bls12_381::G1Affine) -> Vec<u8> {
+        if bool::from(point.is_identity()) {
+            return vec![0; 128];
+        }
+        pad_coordinates(&point.to_uncompressed())
+    }
+
+    fn decode_g1(bytes: &[u8]) -> Option<bls12_381::G1Affine> {
+        let coordinates = strip_coordinates(bytes, 128)?;
+        if coordinates.iter().all(|&b| b == 0) {
+            return Some(bls12_381::G1Affine::identity());
+        }
+        let mut uncompressed = [0; 96];
+        uncompressed.copy_from_slice(&coordinates);
+        // The flags of the encoding of the crate are in the top bits, which
+        // are zero in field elements.
+        if uncompressed[0] & 0xe0 != 0 {
+            return None;
+        }
+        bls12_381::G1Affine::from_uncompressed(&uncompressed).into()
+    }
+
+    fn encode_g2(point: &bls12_381::G2Affine) -> Vec<u8> {
+        if bool::from(point.is_identity()) {
+            return vec![0; 256];
+        }
+        // The crate writes the imaginary part first.
+        let uncompressed = point.to_uncompressed();
+        let mut reordered = vec![];
+        for coordinate in uncompressed.chunks(96) {
+            reordered.extend_from_slice(&coordinate[48..]);
+            reordered.extend_from_slice(&coordinate[..48]);
+        }
+        pad_coordinates(&reordered)
+    }
+
+    fn decode_g2(bytes: &[u8]) -> Option<bls12_381::G2Affine> {
+        let coordinates = strip_coordinates(bytes, 256)?;
+        if coordinates.iter().all(|&b| b == 0) {
+            return Some(bls12_381::G2Affine::identity());
+        }
+        let mut uncompressed = [0; 192];
+        for (i, coordinate) in coordinates.chunks(96).enumerate() {
+            uncompressed[96 * i..96 * i + 48].copy_from_slice(&coordinate[48..]);
+            uncompressed[96 * i + 48..96 * (i + 1)].copy_from_slice(&coordinate[..48]);
+        }
+        if uncompressed[0] & 0xe0 != 0 {
+            return None;
+        }
+        bls12_381::G2Affine::from_uncompressed(&uncompressed).into()
+    }
+
+    fn encode_scalar(scalar: &bls12_381::Scalar) -> [u8; 32] {
+        let mut bytes = scalar.to_bytes();
+        bytes.reverse();
+        bytes
+    }
+}
+
+/// BN254, with the precompiles of EIP-196 and EIP-197, where coordinates
+/// are 32 big-endian bytes, those of G2 with the imaginary part first, and
+/// the identity is encoded as zeros. Apart from the identity, these are the
+/// uncompressed encodings of [`bn254`](crate::bn254).
+#[cfg(feature = "bn254")]
+impl EvmEngine for crate::bn254::Bn254 {
+    const CURVE: &'static str = "BN254";
+
+    const PRECOMPILES: Precompiles = Precompiles {
+        g1_add: 0x06,
+        g1_mul: 0x07,
+        g1_msm: false,
+        pairing: 0x08,
+    };
+
+    fn encode_g1(point: &crate::bn254::G1Affine) -> Vec<u8> {
+        if bool::from(point.is_identity()) {
+            return vec![0; 64];
+        }
+        point.to_uncompressed().to_vec()
+    }
+
+    fn decode_g1(bytes: &[u8]) -> Option<crate::bn254::G1Affine> {
+        let mut uncompressed = [0; 64];
+        if bytes.len() != 64 {
+            return None;
+        }
+        uncompressed.copy_from_slice(bytes);
+        if uncompressed.iter().all(|&b| b == 0) {
+            return Some(crate::bn254::G1Affine::identity());
+        }
+        // The flags of the encoding of the crate are in the top bits, which
+        // are zero in field elements.
+        if uncompressed[0] & 0xc0 != 0 {
+            return None;
+        }
+        crate::bn254::G1Affine::from_uncompressed(&uncompressed).into()
+    }
+
+    fn encode_g2(point: &crate::bn254::G2Affine) -> Vec<u8> {
+        if bool::from(point.is_identity()) {
+            return vec![0; 128];
+        }
+        point.to_uncompressed().to_vec()
+    }
+
+    fn decode_g2(bytes: &[u8]) -> Option<crate::bn254::G2Affine> {
+        let mut uncompressed = [0; 128];
+        if bytes.len() != 128 {
+            return None;
+        }
+        uncompressed.copy_from_slice(bytes);
+        if uncompressed.iter().all(|&b| b == 0) {
+            return Some(crate::bn254::G2Affine::identity());
+        }
+        if uncompressed[0] & 0xc0 != 0 {
+            return None;
+        }
+        crate::bn254::G2Affine::from_uncompressed(&uncompressed).into()
+    }
+
+    fn encode_scalar(scalar: &crate::bn254::Fr) -> [u8; 32] {
+        scalar.to_bytes_be()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::{Bls12, G1Affine, G1Projective, G2Affine, G2Prepared, Scalar};
+    use group::Curve;
+
+    /// Returns the bytes of the hexadecimal literals of `source`, in order.
+    fn literals(source: &str) -> Vec<Vec<u8>> {
+        source
+            .split("hex\"")
+            .skip(1)
+            .map(|rest| {
+                let digits = &rest[..rest.find('"').unwrap()];
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encodings() {
+        let g1 = (G1Projective::generator() * Scalar::from(7)).to_affine();
+        let encoded = Bls12::encode_g1(&g1);
+        assert_eq!(encoded.len(), 128);
+        assert_eq!(Bls12::decode_g1(&encoded), Some(g1));
+        assert_eq!(Bls12::encode_g1(&G1Affine::identity()), vec![0; 128]);
+        assert_eq!(Bls12::decode_g1(&[0; 128]), Some(G1Affine::identity()));
+        let mut padded = encoded.clone();
+        padded[0] = 1;
+        assert_eq!(Bls12::decode_g1(&padded), None);
+
+        // The real part of the x-coordinate of the generator comes first.
+        let g2 = G2Affine::generator();
+        let encoded = Bls12::encode_g2(&g2);
+        assert_eq!(encoded.len(), 256);
+        assert_eq!(&encoded[16..64], &g2.to_uncompressed()[48..96]);
+        assert_eq!(Bls12::decode_g2(&encoded), Some(g2));
+        assert_eq!(Bls12::decode_g2(&encoded[..255]), None);
+
+        let mut one = [0; 32];
+        one[31] = 1;
+        assert_eq!(Bls12::encode_scalar(&Scalar::one()), one);
+    }
+
+    #[test]
+    fn solidity_verifier() {
+        let mut f = Fixture::<Bls12>::new();
+        let proof = f.proof();
+        let (params, inputs) = (&f.params, f.inputs());
+
+        let mut encoded = vec![];
+        proof.write_ethereum_abi(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), 2 * 128 + 256);
+        assert!(Proof::<Bls12>::read_ethereum_abi(&encoded[..]).unwrap() == proof);
+        encoded[200] ^= 1;
+        assert!(Proof::<Bls12>::read_ethereum_abi(&encoded[..]).is_err());
+
+        let mut contract = vec![];
+        params.vk.export_solidity(&mut contract).unwrap();
+        let contract = String::from_utf8(contract).unwrap();
+        assert!(contract.contains("contract Groth16Verifier {"));
+        assert!(contract.contains(&format!(
+            "uint256 private constant NUM_INPUTS = {};",
+            inputs.len()
+        )));
+        assert!(contract.contains(&format!("bytes32(inputs[{}])", inputs.len() - 1)));
+        assert!(contract.contains("precompile(0x0f, pairs, 32)"));
+
+        // The check of the contract, with the points it bakes in, accepts
+        // the proof.
+        let literals = literals(&contract);
+        assert_eq!(literals.len(), 4 + params.vk.ic.len());
+        let alpha = Bls12::decode_g1(&literals[0]).unwrap();
+        let neg_g2 = |i: usize| G2Prepared::from(Bls12::decode_g2(&literals[i]).unwrap());
+        let mut vk_x = G1Projective::from(Bls12::decode_g1(&literals[4]).unwrap());
+        for (ic, input) in literals[5..].iter().zip(inputs) {
+            vk_x += Bls12::decode_g1(ic).unwrap() * input;
+        }
+        let check = |a: &G1Affine| {
+            Bls12::multi_miller_loop(&[
+                (a, &G2Prepared::from(proof.b)),
+                (&alpha, &neg_g2(1)),
+                (&vk_x.to_affine(), &neg_g2(2)),
+                (&proof.c, &neg_g2(3)),
+            ])
+            .final_exponentiation()
+                == bls12_381::Gt::identity()
+        };
+        assert!(check(&proof.a));
+        assert!(!check(&proof.c));
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn bn254() {
+        use crate::bn254::{self, Bn254, Fr};
+        use crate::groth16::{prepare_verifying_key, verify_proof};
+        use group::Group;
+        use pairing::MillerLoopResult;
+
+        let word = |encoded: &[u8], i: usize| hex(&encoded[32 * i..32 * (i + 1)]);
+
+        // The generators of EIP-197, with the imaginary parts first in G2.
+        let encoded = Bn254::encode_g1(&bn254::G1Affine::generator());
+        assert_eq!(word(&encoded, 0), format!("{:064x}", 1));
+        assert_eq!(word(&encoded, 1), format!("{:064x}", 2));
+        let encoded = Bn254::encode_g2(&bn254::G2Affine::generator());
+        assert_eq!(
+            word(&encoded, 0),
+            "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2"
+        );
+        assert_eq!(
+            word(&encoded, 1),
+            "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed"
+        );
+        assert_eq!(
+            Bn254::decode_g2(&encoded),
+            Some(bn254::G2Affine::generator())
+        );
+        assert_eq!(
+            Bn254::decode_g1(&[0; 64]),
+            Some(bn254::G1Affine::identity())
+        );
+        assert_eq!(Bn254::encode_scalar(&Fr::from(258))[30..], [1, 2]);
+
+        let mut f = Fixture::<Bn254>::new();
+        let proof = f.proof();
+        let (params, inputs) = (&f.params, f.inputs());
+
+        let mut encoded = vec![];
+        proof.write_ethereum_abi(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), 8 * 32);
+        let read = Proof::<Bn254>::read_ethereum_abi(&encoded[..]).unwrap();
+        assert!(verify_proof(&prepare_verifying_key(&params.vk), &read, inputs).is_ok());
+
+        let mut encoded = vec![];
+        params.vk.write_ethereum_abi(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), 15 * 32 + 32 + 64 * params.vk.ic.len());
+        assert_eq!(word(&encoded, 14), format!("{:064x}", 15 * 32));
+        assert_eq!(word(&encoded, 15), format!("{:064x}", params.vk.ic.len()));
+        assert_eq!(
+            Bn254::decode_g1(&encoded[16 * 32..18 * 32]),
+            Some(params.vk.ic[0])
+        );
+
+        // Without a multiexponentiation precompile, the contract adds the
+        // products of the IC points one by one.
+        let mut contract = vec![];
+        params.vk.export_solidity(&mut contract).unwrap();
+        let contract = String::from_utf8(contract).unwrap();
+        assert!(contract.contains("precompile(0x08, pairs, 32)"));
+        assert!(contract.contains("(ok, sum) = precompile(0x06, bytes.concat(sum, term), G1_LEN);"));
+
+        let literals = literals(&contract);
+        assert_eq!(literals.len(), 4 + params.vk.ic.len());
+        let alpha = Bn254::decode_g1(&literals[0]).unwrap();
+        let neg_g2 = |i: usize| bn254::G2Prepared::from(Bn254::decode_g2(&literals[i]).unwrap());
+        let mut vk_x = bn254::G1Projective::from(Bn254::decode_g1(&literals[4]).unwrap());
+        for (ic, input) in literals[5..].iter().zip(inputs) {
+            vk_x += Bn254::decode_g1(ic).unwrap() * input;
+        }
+        let check = |a: &bn254::G1Affine| {
+            Bn254::multi_miller_loop(&[
+                (a, &bn254::G2Prepared::from(proof.b)),
+                (&alpha, &neg_g2(1)),
+                (&vk_x.to_affine(), &neg_g2(2)),
+                (&proof.c, &neg_g2(3)),
+            ])
+            .final_exponentiation()
+                == bn254::Gt::identity()
+        };
+        assert!(check(&proof.a));
+        assert!(!check(&proof.c));
+    }
+}
@@ -0,0 +1,307 @@
+//! Proving a stream of witnesses for one circuit.
+//!
+//! [`prove_stream`] takes the witnesses of many statements of a circuit as
+//! an iterator, such as the lines of a file or a channel receiver, and
+//! returns an iterator of their proofs, in the same order. Witnesses are
+//! proven on a pool of worker threads, and only as many are taken from the
+//! input as fit in the memory budget of a [`StreamConfig`]: once the proofs
+//! that have not been consumed yet use the whole budget, no more witnesses
+//! are read until the consumer catches up, so that a slow consumer or a
+//! fast producer cannot make the pipeline grow without bound.
+//!
+//! The blinding factors of every proof are drawn from the RNG given to
+//! [`prove_stream`], in the order of the witnesses, so that a seeded RNG
+//! gives the same proofs on every run whatever the number of workers.
+
+use ff::{Field, PrimeField};
+use pairing::Engine;
+use rand_core::RngCore;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use super::exporter::{Assignment, RawCircuit, ReplayCircuit};
+use super::{create_proof, Parameters, Proof};
+use crate::zeroize::Secret;
+use crate::SynthesisError;
+
+/// The configuration of [`prove_stream`].
+#[derive(Clone, Debug)]
+pub struct StreamConfig {
+    /// The number of proofs created concurrently.
+    pub workers: usize,
+    /// The memory that the witnesses in flight may use, in bytes. At least
+    /// one witness is always in flight, whatever its size.
+    pub memory_budget: usize,
+}
+
+/// The number of CPUs, if the `multicore` feature knows it, or 1.
+fn available_parallelism() -> usize {
+    #[cfg(feature = "multicore")]
+    {
+        num_cpus::get()
+    }
+    #[cfg(not(feature = "multicore"))]
+    {
+        1
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        StreamConfig {
+            workers: available_parallelism(),
+            memory_budget: 1 << 30,
+        }
+    }
+}
+
+impl StreamConfig {
+    /// Returns the number of witnesses of `circuit` that may be in flight.
+    pub fn window<S: PrimeField>(&self, circuit: &RawCircuit<S>) -> usize {
+        std::cmp::max(1, self.memory_budget / job_memory(circuit))
+    }
+}
+
+/// Returns the memory used while proving a witness of `circuit`, not
+/// counting the parameters, which are shared: the witness itself and the
+/// evaluations of the quotient polynomial.
+pub fn job_memory<S: PrimeField>(circuit: &RawCircuit<S>) -> usize {
+    let scalar = std::mem::size_of::<S>();
+    let domain = (circuit.num_constraints + circuit.num_inputs).next_power_of_two();
+    let witness = circuit.num_inputs + circuit.num_aux;
+
+    (3 * domain + 2 * witness) * scalar
+}
+
+/// The number of witnesses taken from the input whose proofs have not been
+/// consumed yet.
+struct Window {
+    state: Mutex<(usize, bool)>,
+    changed: Condvar,
+    limit: usize,
+}
+
+impl Window {
+    /// Waits for room for another witness, and returns false if the stream
+    /// was dropped instead.
+    fn acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while state.0 >= self.limit && !state.1 {
+            state = self.changed.wait(state).unwrap();
+        }
+        state.0 += 1;
+        !state.1
+    }
+
+    fn release(&self) {
+        self.state.lock().unwrap().0 -= 1;
+        self.changed.notify_all();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.changed.notify_all();
+    }
+}
+
+struct Job<S: PrimeField> {
+    index: usize,
+    witness: Assignment<S>,
+    r: Secret<S>,
+    s: Secret<S>,
+}
+
+type Outcome<E> = (usize, Result<Proof<E>, SynthesisError>);
+
+/// The proofs of a stream of witnesses, in the order of the witnesses.
+pub struct ProofStream<E: Engine> {
+    results: Receiver<Outcome<E>>,
+    pending: BTreeMap<usize, Result<Proof<E>, SynthesisError>>,
+    next: usize,
+    window: Arc<Window>,
+}
+
+impl<E: Engine> ProofStream<E> {
+    /// Returns the number of witnesses that may be in flight at once.
+    pub fn window(&self) -> usize {
+        self.window.limit
+    }
+}
+
+impl<E: Engine> Iterator for ProofStream<E> {
+    type Item = Result<Proof<E>, SynthesisError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.pending.remove(&self.next) {
+                self.next += 1;
+                self.window.release();
+                return Some(result);
+            }
+            match self.results.recv() {
+                Ok((index, result)) => {
+                    self.pending.insert(index, result);
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+impl<E: Engine> Drop for ProofStream<E> {
+    fn drop(&mut self) {
+        // Stops taking witnesses; the workers finish the jobs they have.
+        self.window.close();
+    }
+}
+
+fn work<E: Engine>(
+    circuit: &RawCircuit<E::Fr>,
+    params: &Parameters<E>,
+    jobs: &Mutex<Receiver<Job<E::Fr>>>,
+    results: &Sender<Outcome<E>>,
+) {
+    loop {
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let replay = ReplayCircuit {
+            circuit: circuit.clone(),
+            assignment: Some(job.witness),
+        };
+        let result = create_proof(replay, params, *job.r, *job.s);
+        if results.send((job.index, result)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Proves each witness of `witnesses` for `circuit`, and returns their
+/// proofs as they are consumed, in order. A witness that does not satisfy
+/// the circuit gives an error in its place, and the stream goes on.
+pub fn prove_stream<E, I, R>(
+    params: Arc<Parameters<E>>,
+    circuit: Arc<RawCircuit<E::Fr>>,
+    witnesses: I,
+    mut rng: R,
+    config: &StreamConfig,
+) -> ProofStream<E>
+where
+    E: Engine,
+    Parameters<E>: Send + Sync,
+    Proof<E>: Send,
+    I: IntoIterator<Item = Assignment<E::Fr>>,
+    I::IntoIter: Send + 'static,
+    R: RngCore + Send + 'static,
+{
+    let window = Arc::new(Window {
+        state: Mutex::new((0, false)),
+        changed: Condvar::new(),
+        limit: config.window(&circuit),
+    });
+    let (job_sender, job_receiver) = sync_channel::<Job<E::Fr>>(0);
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    let (result_sender, results) = channel();
+
+    for _ in 0..std::cmp::max(1, config.workers) {
+        let (params, circuit) = (params.clone(), circuit.clone());
+        let (jobs, results) = (job_receiver.clone(), result_sender.clone());
+        thread::spawn(move || work(&circuit, &params, &jobs, &results));
+    }
+
+    let feeder = window.clone();
+    let witnesses = witnesses.into_iter();
+    thread::spawn(move || {
+        for (index, witness) in witnesses.enumerate() {
+            if !feeder.acquire() {
+                return;
+            }
+            let job = Job {
+                index,
+                witness,
+                r: Secret::new(E::Fr::random(&mut rng), E::Fr::zero()),
+                s: Secret::new(E::Fr::random(&mut rng), E::Fr::zero()),
+            };
+            if job_sender.send(job).is_err() {
+                return;
+            }
+        }
+    });
+
+    ProofStream {
+        results,
+        pending: BTreeMap::new(),
+        next: 0,
+        window,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{create_random_proof, verify_proof};
+    use bls12_381::Bls12;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn proof_stream() {
+        let Fixture {
+            rng,
+            circuit,
+            witness,
+            params,
+            pvk,
+        } = Fixture::<Bls12>::new();
+
+        let mut witnesses = vec![witness.clone(); 12];
+        witnesses[5].aux.pop();
+
+        let config = StreamConfig {
+            workers: 3,
+            memory_budget: 2 * job_memory(&circuit),
+        };
+        let taken = Arc::new(AtomicUsize::new(0));
+        let counter = taken.clone();
+        let input = witnesses.into_iter().inspect(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let seed = rng.clone();
+        let mut stream = prove_stream(
+            Arc::new(params.clone()),
+            Arc::new(circuit.clone()),
+            input,
+            seed.clone(),
+            &config,
+        );
+        assert_eq!(stream.window(), 2);
+
+        // Nothing past the window is read before the consumer asks.
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(taken.load(Ordering::SeqCst) <= 3);
+
+        let first = stream.next().unwrap().unwrap();
+        let rest = stream.collect::<Vec<_>>();
+        assert_eq!(rest.len(), 11);
+        assert!(rest[4].is_err());
+        for proof in std::iter::once(&first).chain(rest.iter().filter_map(|r| r.as_ref().ok())) {
+            assert!(verify_proof(&pvk, proof, &witness.inputs[1..]).is_ok());
+        }
+
+        // The proofs only depend on the RNG, not on the workers.
+        let mut seed = seed;
+        let expected = create_random_proof(
+            ReplayCircuit {
+                circuit,
+                assignment: Some(witness),
+            },
+            &params,
+            &mut seed,
+        )
+        .unwrap();
+        assert!(first == expected);
+    }
+}
@@ -0,0 +1,507 @@
+//! A verifier whose accepted encodings are fixed byte for byte.
+//!
+//! Nodes of a blockchain must all accept exactly the same proofs, or they
+//! fork. [`Proof::read`] and [`VerifyingKey::read`] accept whatever the
+//! curve implementation decodes, ignore trailing bytes, and leave the
+//! checks on public inputs to the caller. A [`StrictVerifier`] instead
+//! decodes from byte slices under a pinned [format version](FORMAT_VERSION),
+//! and rejects:
+//!
+//! - encodings that are shorter or longer than the format says;
+//! - points that do not re-encode to the same bytes, so that every point has
+//!   a single accepted encoding whatever the curve implementation tolerates;
+//! - the point at infinity anywhere in a proof or verifying key;
+//! - public inputs that are not the canonical representation of a field
+//!   element, or not as many as the verifying key has.
+//!
+//! # Format version 1
+//!
+//! ```text
+//! verifying key  as written by VerifyingKey::write: alpha_g1, beta_g1,
+//!                beta_g2, gamma_g2, delta_g1 and delta_g2 uncompressed, then
+//!                a u32 big-endian count of at least 1 and that many
+//!                uncompressed IC points
+//! proof          as written by Proof::write: a, b and c compressed
+//! inputs         the PrimeField::to_repr of each public input, excluding
+//!                ONE, concatenated, with no count
+//! ```
+//!
+//! [`Proof::read`]: super::Proof::read
+//! [`VerifyingKey::read`]: super::VerifyingKey::read
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use byteorder::{BigEndian, ByteOrder};
+use core::fmt;
+use ff::PrimeField;
+use group::prime::PrimeCurveAffine;
+use group::{GroupEncoding, UncompressedEncoding};
+use pairing::MultiMillerLoop;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use super::{prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey};
+use crate::VerificationError;
+
+/// The only format version that [`StrictVerifier`] accepts.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The encodings that a [`StrictVerifier`] decodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    VerifyingKey,
+    Proof,
+    Inputs,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::VerifyingKey => "verifying key",
+            Encoding::Proof => "proof",
+            Encoding::Inputs => "public inputs",
+        })
+    }
+}
+
+/// Why a [`StrictVerifier`] rejected an encoding or a proof.
+#[derive(Debug, PartialEq)]
+pub enum StrictError {
+    /// The verifier was asked for a format version other than
+    /// [`FORMAT_VERSION`].
+    UnsupportedVersion(u32),
+    /// The encoding is not of the length the format requires.
+    Length {
+        encoding: Encoding,
+        expected: usize,
+        actual: usize,
+    },
+    /// An element does not decode to a point of the prime-order subgroup,
+    /// or to a field element.
+    Invalid { encoding: Encoding, element: String },
+    /// An element decodes, but is not the canonical encoding of its value.
+    NonCanonical { encoding: Encoding, element: String },
+    /// An element is the point at infinity.
+    Identity { encoding: Encoding, element: String },
+    /// The verifying key has no IC point for `ONE`.
+    MissingIc,
+    /// The proof does not verify.
+    Rejected(VerificationError),
+}
+
+#[cfg(feature = "std")]
+impl Error for StrictError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StrictError::Rejected(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StrictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrictError::UnsupportedVersion(version) => {
+                write!(f, "unsupported strict format version {}", version)
+            }
+            StrictError::Length {
+                encoding,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} is {} bytes long, expected {}",
+                encoding, actual, expected
+            ),
+            StrictError::Invalid { encoding, element } => {
+                write!(f, "invalid {} in {}", element, encoding)
+            }
+            StrictError::NonCanonical { encoding, element } => {
+                write!(f, "non-canonical encoding of {} in {}", element, encoding)
+            }
+            StrictError::Identity { encoding, element } => {
+                write!(f, "{} in {} is the point at infinity", element, encoding)
+            }
+            StrictError::MissingIc => f.write_str("verifying key has no IC points"),
+            StrictError::Rejected(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<StrictError> for crate::error::Error {
+    fn from(e: StrictError) -> Self {
+        use crate::error::ErrorKind;
+
+        let kind = match &e {
+            StrictError::Length { encoding, .. }
+            | StrictError::Invalid { encoding, .. }
+            | StrictError::NonCanonical { encoding, .. }
+            | StrictError::Identity { encoding, .. } => match encoding {
+                Encoding::VerifyingKey => ErrorKind::MalformedParameters,
+                Encoding::Proof => ErrorKind::MalformedProof,
+                Encoding::Inputs => ErrorKind::MalformedInputs,
+            },
+            StrictError::UnsupportedVersion(_) | StrictError::MissingIc => {
+                ErrorKind::MalformedParameters
+            }
+            StrictError::Rejected(_) => ErrorKind::VerificationFailed,
+        };
+        crate::error::Error::new(kind, e)
+    }
+}
+
+/// Checks a decoded point: it must re-encode to `bytes`, and not be the
+/// point at infinity.
+fn check_point<G: PrimeCurveAffine>(
+    point: Option<G>,
+    encode: impl Fn(&G) -> Vec<u8>,
+    bytes: &[u8],
+    encoding: Encoding,
+    element: String,
+) -> Result<G, StrictError> {
+    let point = point.ok_or_else(|| StrictError::Invalid {
+        encoding,
+        element: element.clone(),
+    })?;
+    if encode(&point) != bytes {
+        return Err(StrictError::NonCanonical { encoding, element });
+    }
+    if bool::from(point.is_identity()) {
+        return Err(StrictError::Identity { encoding, element });
+    }
+    Ok(point)
+}
+
+fn uncompressed<G: PrimeCurveAffine + UncompressedEncoding>(
+    bytes: &[u8],
+    element: String,
+) -> Result<G, StrictError> {
+    let mut repr = G::Uncompressed::default();
+    repr.as_mut().copy_from_slice(bytes);
+    check_point(
+        G::from_uncompressed(&repr).into(),
+        |p| p.to_uncompressed().as_ref().to_vec(),
+        bytes,
+        Encoding::VerifyingKey,
+        element,
+    )
+}
+
+fn compressed<G: PrimeCurveAffine>(bytes: &[u8], element: String) -> Result<G, StrictError> {
+    let mut repr = G::Repr::default();
+    repr.as_mut().copy_from_slice(bytes);
+    check_point(
+        G::from_bytes(&repr).into(),
+        |p| p.to_bytes().as_ref().to_vec(),
+        bytes,
+        Encoding::Proof,
+        element,
+    )
+}
+
+fn uncompressed_len<G: UncompressedEncoding>() -> usize {
+    G::Uncompressed::default().as_ref().len()
+}
+
+fn compressed_len<G: GroupEncoding>() -> usize {
+    G::Repr::default().as_ref().len()
+}
+
+/// Returns the length of a verifying key with `ic` IC points in format
+/// version 1.
+pub fn key_len<E: MultiMillerLoop>(ic: usize) -> usize {
+    let g1 = uncompressed_len::<E::G1Affine>();
+    let g2 = uncompressed_len::<E::G2Affine>();
+    3 * g1 + 3 * g2 + 4 + ic * g1
+}
+
+/// Returns the length of a proof in format version 1.
+pub fn proof_len<E: MultiMillerLoop>() -> usize {
+    2 * compressed_len::<E::G1Affine>() + compressed_len::<E::G2Affine>()
+}
+
+/// Decodes a verifying key in format version 1.
+pub fn decode_key<E: MultiMillerLoop>(bytes: &[u8]) -> Result<VerifyingKey<E>, StrictError> {
+    let g1 = uncompressed_len::<E::G1Affine>();
+    let g2 = uncompressed_len::<E::G2Affine>();
+    let fixed = key_len::<E>(0);
+    if bytes.len() < fixed {
+        return Err(StrictError::Length {
+            encoding: Encoding::VerifyingKey,
+            expected: fixed,
+            actual: bytes.len(),
+        });
+    }
+
+    let count = BigEndian::read_u32(&bytes[fixed - 4..fixed]) as usize;
+    if count == 0 {
+        return Err(StrictError::MissingIc);
+    }
+    let expected = count
+        .checked_mul(g1)
+        .and_then(|ic| ic.checked_add(fixed))
+        .unwrap_or(usize::MAX);
+    if bytes.len() != expected {
+        return Err(StrictError::Length {
+            encoding: Encoding::VerifyingKey,
+            expected,
+            actual: bytes.len(),
+        });
+    }
+
+    let mut offset = 0;
+    let mut take = |len: usize| {
+        offset += len;
+        &bytes[offset - len..offset]
+    };
+    let alpha_g1 = uncompressed(take(g1), "alpha_g1".to_string())?;
+    let beta_g1 = uncompressed(take(g1), "beta_g1".to_string())?;
+    let beta_g2 = uncompressed(take(g2), "beta_g2".to_string())?;
+    let gamma_g2 = uncompressed(take(g2), "gamma_g2".to_string())?;
+    let delta_g1 = uncompressed(take(g1), "delta_g1".to_string())?;
+    let delta_g2 = uncompressed(take(g2), "delta_g2".to_string())?;
+    take(4);
+    let ic = (0..count)
+        .map(|i| uncompressed(take(g1), format!("ic[{}]", i)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(VerifyingKey {
+        alpha_g1,
+        beta_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g1,
+        delta_g2,
+        ic,
+    })
+}
+
+/// Decodes a proof in format version 1.
+pub fn decode_proof<E: MultiMillerLoop>(bytes: &[u8]) -> Result<Proof<E>, StrictError> {
+    let g1 = compressed_len::<E::G1Affine>();
+    let g2 = compressed_len::<E::G2Affine>();
+    if bytes.len() != proof_len::<E>() {
+        return Err(StrictError::Length {
+            encoding: Encoding::Proof,
+            expected: proof_len::<E>(),
+            actual: bytes.len(),
+        });
+    }
+
+    Ok(Proof {
+        a: compressed(&bytes[..g1], "a".to_string())?,
+        b: compressed(&bytes[g1..g1 + g2], "b".to_string())?,
+        c: compressed(&bytes[g1 + g2..], "c".to_string())?,
+    })
+}
+
+/// Decodes `count` public inputs in format version 1.
+pub fn decode_inputs<S: PrimeField>(bytes: &[u8], count: usize) -> Result<Vec<S>, StrictError> {
+    let len = S::Repr::default().as_ref().len();
+    if bytes.len() != count * len {
+        return Err(StrictError::Length {
+            encoding: Encoding::Inputs,
+            expected: count * len,
+            actual: bytes.len(),
+        });
+    }
+
+    bytes
+        .chunks(len)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut repr = S::Repr::default();
+            repr.as_mut().copy_from_slice(chunk);
+            let input = S::from_repr(repr).ok_or_else(|| StrictError::NonCanonical {
+                encoding: Encoding::Inputs,
+                element: format!("input {}", i),
+            })?;
+            if input.to_repr().as_ref() != chunk {
+                return Err(StrictError::NonCanonical {
+                    encoding: Encoding::Inputs,
+                    element: format!("input {}", i),
+                });
+            }
+            Ok(input)
+        })
+        .collect()
+}
+
+/// A verifier for one verifying key that only accepts the encodings of a
+/// pinned format version.
+pub struct StrictVerifier<E: MultiMillerLoop> {
+    vk: VerifyingKey<E>,
+    pvk: PreparedVerifyingKey<E>,
+}
+
+impl<E: MultiMillerLoop> StrictVerifier<E> {
+    /// Decodes the verifying key `key` in format `version`, which must be
+    /// [`FORMAT_VERSION`].
+    pub fn new(version: u32, key: &[u8]) -> Result<Self, StrictError> {
+        if version != FORMAT_VERSION {
+            return Err(StrictError::UnsupportedVersion(version));
+        }
+        let vk = decode_key(key)?;
+        let pvk = prepare_verifying_key(&vk);
+        Ok(StrictVerifier { vk, pvk })
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    /// Returns the exact length that encoded public inputs must have.
+    pub fn inputs_len(&self) -> usize {
+        self.vk.num_inputs() * <E::Fr as PrimeField>::Repr::default().as_ref().len()
+    }
+
+    /// Decodes `proof` and `inputs`, and verifies the proof.
+    pub fn verify(&self, proof: &[u8], inputs: &[u8]) -> Result<(), StrictError> {
+        let proof = decode_proof::<E>(proof)?;
+        let inputs = decode_inputs::<E::Fr>(inputs, self.vk.num_inputs())?;
+        verify_proof(&self.pvk, &proof, &inputs).map_err(StrictError::Rejected)
+    }
+}
+
+#[cfg(all(test, feature = "groth16"))]
+mod tests {
+    use super::*;
+    use crate::groth16::vectors::generate;
+    use bls12_381::{Bls12, G1Affine, Scalar};
+
+    /// The BLAKE2s hash of the key, proof and inputs of the vector below.
+    /// Changing it breaks every verifier that relies on format version 1.
+    const PINNED: &str = "70bb5e800e455bff25818807d9575cbe40da52e53eb011d4ece68ac1f1ff100a";
+
+    fn encode(vector: &crate::groth16::vectors::TestVector<Bls12>) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut key = vec![];
+        vector.params.vk.write(&mut key).unwrap();
+        let mut proof = vec![];
+        vector.proof.write(&mut proof).unwrap();
+        let inputs = vector
+            .inputs
+            .iter()
+            .flat_map(|input| input.to_repr().as_ref().to_vec())
+            .collect();
+        (key, proof, inputs)
+    }
+
+    #[test]
+    fn strict_verifier() {
+        let vectors = generate::<Bls12>(b"strict").unwrap();
+        let vector = vectors
+            .iter()
+            .find(|v| v.expected && !v.inputs.is_empty())
+            .unwrap();
+        let (key, proof, inputs) = encode(vector);
+
+        // The encodings of format version 1 are pinned.
+        let pinned = blake2s_simd::Params::new()
+            .personal(b"bellStrV")
+            .to_state()
+            .update(&key)
+            .update(&proof)
+            .update(&inputs)
+            .finalize();
+        assert_eq!(proof.len(), proof_len::<Bls12>());
+        assert_eq!(key.len(), key_len::<Bls12>(vector.params.vk.ic.len()));
+        assert_eq!(pinned.to_hex().as_str(), PINNED);
+
+        assert_eq!(
+            StrictVerifier::<Bls12>::new(2, &key).err(),
+            Some(StrictError::UnsupportedVersion(2))
+        );
+        let verifier = StrictVerifier::<Bls12>::new(FORMAT_VERSION, &key).unwrap();
+        assert_eq!(verifier.inputs_len(), inputs.len());
+        assert_eq!(verifier.verify(&proof, &inputs), Ok(()));
+
+        // Trailing or missing bytes.
+        let mut long = key.clone();
+        long.push(0);
+        assert!(matches!(
+            StrictVerifier::<Bls12>::new(FORMAT_VERSION, &long),
+            Err(StrictError::Length {
+                encoding: Encoding::VerifyingKey,
+                ..
+            })
+        ));
+        let mut long = proof.clone();
+        long.push(0);
+        assert!(matches!(
+            verifier.verify(&long, &inputs),
+            Err(StrictError::Length {
+                encoding: Encoding::Proof,
+                ..
+            })
+        ));
+        assert!(matches!(
+            verifier.verify(&proof, &inputs[1..]),
+            Err(StrictError::Length {
+                encoding: Encoding::Inputs,
+                ..
+            })
+        ));
+
+        // The point at infinity.
+        let mut infinite = proof.clone();
+        infinite[96 + 48..].copy_from_slice(G1Affine::identity().to_bytes().as_ref());
+        assert_eq!(
+            verifier.verify(&infinite, &inputs),
+            Err(StrictError::Identity {
+                encoding: Encoding::Proof,
+                element: "c".to_string(),
+            })
+        );
+        let mut infinite = key.clone();
+        infinite[..96].copy_from_slice(G1Affine::identity().to_uncompressed().as_ref());
+        assert!(matches!(
+            StrictVerifier::<Bls12>::new(FORMAT_VERSION, &infinite),
+            Err(StrictError::Identity { .. })
+        ));
+
+        // The infinity flag with other bits set, and a coordinate that is
+        // not reduced.
+        let mut flagged = proof.clone();
+        flagged[96 + 48..].copy_from_slice(G1Affine::identity().to_bytes().as_ref());
+        flagged[proof.len() - 1] = 1;
+        assert!(verifier.verify(&flagged, &inputs).is_err());
+        let mut unreduced = proof.clone();
+        for byte in &mut unreduced[1..48] {
+            *byte = 0xff;
+        }
+        unreduced[0] |= 0x1f;
+        assert!(matches!(
+            verifier.verify(&unreduced, &inputs),
+            Err(StrictError::Invalid { .. }) | Err(StrictError::NonCanonical { .. })
+        ));
+
+        // Inputs must be reduced.
+        let mut unreduced = inputs.clone();
+        for byte in &mut unreduced[..32] {
+            *byte = 0xff;
+        }
+        assert_eq!(
+            verifier.verify(&proof, &unreduced),
+            Err(StrictError::NonCanonical {
+                encoding: Encoding::Inputs,
+                element: "input 0".to_string(),
+            })
+        );
+
+        // Valid encodings of the wrong statement are rejected by the
+        // pairing check.
+        let mut other = vector.inputs.clone();
+        other[0] += Scalar::one();
+        let other = other
+            .iter()
+            .flat_map(|input| input.to_repr().as_ref().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            verifier.verify(&proof, &other),
+            Err(StrictError::Rejected(VerificationError::InvalidProof))
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Structural validation of parameters from an untrusted source.
+//!
+//! [`Parameters::read`] with `checked` decodes each point into the
+//! prime-order subgroup, but says nothing of whether the parameters fit the
+//! circuit, or whether the points that keygen derives from the same secret
+//! agree. [`Parameters::verify_structure`] and
+//! [`VerifyingKey::verify_structure`] check parameters downloaded from a
+//! ceremony before they are trusted:
+//!
+//! - every point is in the prime-order subgroup, and none is the point at
+//!   infinity;
+//! - `ic` has an element per public input of the circuit, `l` one per
+//!   auxiliary variable, `h` one per power of the domain but the last, and
+//!   the `A` and `B` queries one per variable that appears in them;
+//! - `beta` and each element of the `B` query are to `delta` in G1 as they
+//!   are in G2, the generators being unknown, and the elements of the `B`
+//!   query are checked at once with a random linear combination.
+//!
+//! This does not show that the parameters are sound: that `h` is made of
+//! powers of the same `tau`, or that `l` and `ic` combine the `A`, `B` and
+//! `C` polynomials of the circuit, depends on secrets that only a
+//! [ceremony transcript](super::ceremony) can vouch for.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding, UncompressedEncoding};
+use pairing::Engine;
+use std::error::Error;
+use std::fmt;
+
+use super::ceremony::same_ratio;
+use super::exporter::RawCircuit;
+use super::vectors::SeededRng;
+use super::{Parameters, VerifyingKey};
+use crate::domain::{domain_size, Radix};
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+use crate::{Circuit, SynthesisError};
+
+/// Why parameters or a verifying key failed a structural check.
+#[derive(Debug)]
+pub enum StructureError {
+    /// The circuit could not be synthesized.
+    Synthesis(SynthesisError),
+    /// A query does not have as many points as the circuit needs.
+    Length {
+        query: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// A point is the point at infinity, which keygen never produces.
+    Identity { element: String },
+    /// A point is not in the prime-order subgroup.
+    NotInSubgroup { element: String },
+    /// Points that keygen derives from the same secret do not agree.
+    Inconsistent(&'static str),
+}
+
+impl Error for StructureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StructureError::Synthesis(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureError::Synthesis(e) => write!(f, "cannot synthesize the circuit: {}", e),
+            StructureError::Length {
+                query,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} has {} points, but the circuit needs {}",
+                query, actual, expected
+            ),
+            StructureError::Identity { element } => {
+                write!(f, "{} is the point at infinity", element)
+            }
+            StructureError::NotInSubgroup { element } => {
+                write!(f, "{} is not in the prime-order subgroup", element)
+            }
+            StructureError::Inconsistent(what) => write!(f, "{} are inconsistent", what),
+        }
+    }
+}
+
+impl From<SynthesisError> for StructureError {
+    fn from(e: SynthesisError) -> Self {
+        StructureError::Synthesis(e)
+    }
+}
+
+/// The number of points of the `ic`, `l`, `h`, `a`, `b_g1` and `b_g2`
+/// queries of the parameters that keygen generates for `circuit`.
+///
+/// The generator drops the `A` and `B` query elements of variables that do
+/// not appear in the matrix, and adds a constraint for each input.
+pub fn query_lengths<Scalar: PrimeField>(
+    circuit: &RawCircuit<Scalar>,
+) -> [(&'static str, usize); 6] {
+    let used = |columns: &[Vec<(Scalar, usize)>]| columns.iter().filter(|c| !c.is_empty()).count();
+    let h = domain_size::<Scalar>(circuit.num_constraints + circuit.num_inputs, Radix::Two)
+        .map_or(0, |domain| domain - 1);
+    let b = used(&circuit.bt_inputs) + used(&circuit.bt_aux);
+    [
+        ("ic", circuit.num_inputs),
+        ("l", circuit.num_aux),
+        ("h", h),
+        ("a", circuit.num_inputs + used(&circuit.at_aux)),
+        ("b_g1", b),
+        ("b_g2", b),
+    ]
+}
+
+/// Checks that `actual` has as many points as `query` needs in `expected`.
+pub fn check_length(
+    expected: &[(&'static str, usize)],
+    query: &'static str,
+    actual: usize,
+) -> Result<(), StructureError> {
+    let expected = expected
+        .iter()
+        .find(|(name, _)| *name == query)
+        .map(|(_, len)| *len)
+        .expect("the query has a length");
+    if actual != expected {
+        return Err(StructureError::Length {
+            query,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that the point named by `element` is in the prime-order
+/// subgroup, by decoding its encoding with the checks of
+/// [`Parameters::read`], and not the point at infinity.
+fn check_point<G, F>(point: &G, element: F) -> Result<(), StructureError>
+where
+    G: PrimeCurveAffine + UncompressedEncoding,
+    F: FnOnce() -> String,
+{
+    if bool::from(point.is_identity()) {
+        return Err(StructureError::Identity { element: element() });
+    }
+    if bool::from(G::from_uncompressed(&point.to_uncompressed()).is_none()) {
+        return Err(StructureError::NotInSubgroup { element: element() });
+    }
+    Ok(())
+}
+
+/// Checks the points of `query` in parallel, and reports the first that
+/// fails [`check_point`].
+fn check_query<G>(worker: &Worker, query: &str, points: &[G]) -> Result<(), StructureError>
+where
+    G: PrimeCurveAffine + UncompressedEncoding,
+{
+    let mut failures = vec![];
+    worker.scope(points.len(), |scope, chunk| {
+        failures = (0..(points.len() + chunk - 1) / chunk)
+            .map(|_| None)
+            .collect();
+        for ((points, failure), offset) in points
+            .chunks(chunk)
+            .zip(failures.iter_mut())
+            .zip((0..).step_by(chunk))
+        {
+            scope.spawn(move |_| {
+                *failure = points.iter().enumerate().find_map(|(i, point)| {
+                    check_point(point, || format!("{}[{}]", query, offset + i)).err()
+                });
+            });
+        }
+    });
+
+    match failures.into_iter().flatten().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    /// Checks the points of the key, and that it has an IC element for each
+    /// public input of `circuit`, as described in the
+    /// [module documentation](super::structure).
+    pub fn verify_structure<C: Circuit<E::Fr>>(&self, circuit: C) -> Result<(), StructureError> {
+        let circuit = RawCircuit::synthesize(circuit)?;
+        self.check_structure(&query_lengths(&circuit))
+    }
+
+    fn check_structure(&self, lengths: &[(&'static str, usize)]) -> Result<(), StructureError> {
+        check_point(&self.alpha_g1, || "alpha_g1".to_string())?;
+        check_point(&self.beta_g1, || "beta_g1".to_string())?;
+        check_point(&self.beta_g2, || "beta_g2".to_string())?;
+        check_point(&self.gamma_g2, || "gamma_g2".to_string())?;
+        check_point(&self.delta_g1, || "delta_g1".to_string())?;
+        check_point(&self.delta_g2, || "delta_g2".to_string())?;
+        check_length(lengths, "ic", self.ic.len())?;
+        check_query(&Worker::new(), "ic", &self.ic)?;
+
+        if !same_ratio::<E>((self.delta_g1, self.beta_g1), (self.delta_g2, self.beta_g2)) {
+            return Err(StructureError::Inconsistent("beta and delta"));
+        }
+        Ok(())
+    }
+}
+
+impl<E: Engine> Parameters<E> {
+    /// Checks the points of the parameters, that their queries have the
+    /// lengths that `circuit` needs, and that the points derived from the
+    /// same secrets agree, as described in the
+    /// [module documentation](super::structure).
+    pub fn verify_structure<C: Circuit<E::Fr>>(&self, circuit: C) -> Result<(), StructureError> {
+        let circuit = RawCircuit::synthesize(circuit)?;
+        let lengths = query_lengths(&circuit);
+        self.vk.check_structure(&lengths)?;
+
+        check_length(&lengths, "l", self.l.len())?;
+        // The H query of a mixed-radix domain is shorter than a power of two.
+        let mixed =
+            domain_size::<E::Fr>(circuit.num_constraints + circuit.num_inputs, Radix::Mixed).ok();
+        if mixed != Some(self.h.len() + 1) {
+            check_length(&lengths, "h", self.h.len())?;
+        }
+        check_length(&lengths, "a", self.a.len())?;
+        check_length(&lengths, "b_g1", self.b_g1.len())?;
+        check_length(&lengths, "b_g2", self.b_g2.len())?;
+
+        let worker = Worker::new();
+        check_query(&worker, "h", &self.h)?;
+        check_query(&worker, "l", &self.l)?;
+        check_query(&worker, "a", &self.a)?;
+        check_query(&worker, "b_g1", &self.b_g1)?;
+        check_query(&worker, "b_g2", &self.b_g2)?;
+
+        // The coefficients are derived from the points they combine, so that
+        // the parameters cannot be chosen to cancel out.
+        let mut seed = Blake2sParams::new().personal(b"bellStrc").to_state();
+        seed.update(&self.vk.hash());
+        for (g1, g2) in self.b_g1.iter().zip(self.b_g2.iter()) {
+            seed.update(g1.to_bytes().as_ref());
+            seed.update(g2.to_bytes().as_ref());
+        }
+        let mut rng = SeededRng::new(seed.finalize().as_bytes(), "b query");
+        let coeffs = (0..self.b_g1.len())
+            .map(|_| E::Fr::random(&mut rng))
+            .collect::<Vec<_>>();
+        let b_g1 = dense_multiexp::<E::G1>(&worker, &self.b_g1, &coeffs)?;
+        let b_g2 = dense_multiexp::<E::G2>(&worker, &self.b_g2, &coeffs)?;
+        if !same_ratio::<E>(
+            (self.vk.delta_g1, b_g1.to_affine()),
+            (self.vk.delta_g2, b_g2.to_affine()),
+        ) {
+            return Err(StructureError::Inconsistent("the B query and delta"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::exporter::ReplayCircuit;
+    use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+    use crate::groth16::tests::fixture::{rng, Fixture};
+    use bls12_381::{Bls12, G1Affine, G1Projective, Scalar};
+    use std::sync::Arc;
+
+    /// Returns a point of the curve outside of the prime-order subgroup.
+    fn outside_subgroup() -> G1Affine {
+        (1u8..)
+            .find_map(|x| {
+                let mut bytes = [0; 48];
+                bytes[0] = 0x80;
+                bytes[47] = x;
+                Option::from(G1Affine::from_compressed_unchecked(&bytes))
+                    .filter(|_: &G1Affine| bool::from(G1Affine::from_compressed(&bytes).is_none()))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn parameter_structure() {
+        let f = Fixture::<Bls12>::new();
+        let (params, replay) = (&f.params, || f.shape());
+        params.verify_structure(replay()).unwrap();
+        assert!(params.vk.verify_structure(replay()).is_ok());
+
+        // Parameters for another circuit have other lengths.
+        let (other, _) = random_circuit::<Scalar, _>(
+            &CircuitConfig {
+                num_aux: CircuitConfig::default().num_aux + 1,
+                ..CircuitConfig::default()
+            },
+            &mut rng(),
+        );
+        assert!(matches!(
+            params.verify_structure(ReplayCircuit {
+                circuit: other,
+                assignment: None,
+            }),
+            Err(StructureError::Length { query: "l", .. })
+        ));
+
+        let mut tampered = params.clone();
+        let mut l = tampered.l.to_vec();
+        l[1] = G1Affine::identity();
+        tampered.l = Arc::new(l);
+        let error = tampered.verify_structure(replay()).unwrap_err();
+        assert_eq!(error.to_string(), "l[1] is the point at infinity");
+
+        let mut tampered = params.clone();
+        let mut h = tampered.h.to_vec();
+        h[2] = outside_subgroup();
+        tampered.h = Arc::new(h);
+        let error = tampered.verify_structure(replay()).unwrap_err();
+        assert_eq!(error.to_string(), "h[2] is not in the prime-order subgroup");
+
+        let mut tampered = params.clone();
+        tampered.vk.delta_g1 = (G1Projective::from(params.vk.delta_g1).double()).to_affine();
+        assert!(matches!(
+            tampered.vk.verify_structure(replay()),
+            Err(StructureError::Inconsistent("beta and delta"))
+        ));
+
+        let mut tampered = params.clone();
+        let mut b_g1 = tampered.b_g1.to_vec();
+        b_g1.swap(0, 1);
+        tampered.b_g1 = Arc::new(b_g1);
+        assert!(matches!(
+            tampered.verify_structure(replay()),
+            Err(StructureError::Inconsistent("the B query and delta"))
+        ));
+    }
+}
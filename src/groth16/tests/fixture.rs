@@ -0,0 +1,92 @@
+//! The circuit, witness and parameters that the tests of most modules start
+//! from.
+
+use group::WnafGroup;
+use pairing::MultiMillerLoop;
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use crate::groth16::exporter::{Assignment, RawCircuit, ReplayCircuit};
+use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+use crate::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, Parameters,
+    PreparedVerifyingKey, Proof,
+};
+
+/// The generator the tests draw from, with a fixed seed.
+pub(crate) fn rng() -> XorShiftRng {
+    XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ])
+}
+
+/// A random circuit with a witness and parameters, and the generator they
+/// were drawn from.
+pub(crate) struct Fixture<E: MultiMillerLoop> {
+    pub rng: XorShiftRng,
+    pub circuit: RawCircuit<E::Fr>,
+    pub witness: Assignment<E::Fr>,
+    pub params: Parameters<E>,
+    pub pvk: PreparedVerifyingKey<E>,
+}
+
+impl<E> Fixture<E>
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+{
+    /// A circuit of the default shape.
+    pub fn new() -> Self {
+        Self::with_config(&CircuitConfig::default())
+    }
+
+    pub fn with_config(config: &CircuitConfig) -> Self {
+        let mut rng = rng();
+        let (circuit, witness) = random_circuit::<E::Fr, _>(config, &mut rng);
+        let params = generate_random_parameters::<E, _, _>(
+            ReplayCircuit {
+                circuit: circuit.clone(),
+                assignment: None,
+            },
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        Fixture {
+            rng,
+            circuit,
+            witness,
+            params,
+            pvk,
+        }
+    }
+
+    /// The circuit with its witness.
+    pub fn circuit(&self) -> ReplayCircuit<E::Fr> {
+        ReplayCircuit {
+            circuit: self.circuit.clone(),
+            assignment: Some(self.witness.clone()),
+        }
+    }
+
+    /// The circuit without a witness, as for generating parameters.
+    pub fn shape(&self) -> ReplayCircuit<E::Fr> {
+        ReplayCircuit {
+            circuit: self.circuit.clone(),
+            assignment: None,
+        }
+    }
+
+    /// The public inputs of the witness, without `ONE`.
+    pub fn inputs(&self) -> &[E::Fr] {
+        &self.witness.inputs[1..]
+    }
+
+    /// A proof of the witness.
+    pub fn proof(&mut self) -> Proof<E> {
+        create_random_proof(self.circuit(), &self.params, &mut self.rng).unwrap()
+    }
+}
@@ -1,7 +1,9 @@
 use ff::{Field, PrimeField};
 
-mod dummy_engine;
+pub(crate) mod dummy_engine;
+pub(crate) mod fixture;
 use self::dummy_engine::*;
+use self::fixture::{rng, Fixture};
 
 use std::marker::PhantomData;
 use std::ops::{AddAssign, MulAssign, SubAssign};
@@ -379,3 +381,583 @@ fn test_xordemo() {
 
     assert!(verify_proof(&pvk, &proof, &[Fr::one()]).is_ok());
 }
+
+#[test]
+fn malformed_encodings_report_their_location() {
+    use super::{create_random_proof, generate_random_parameters, Parameters, Proof};
+    use crate::error::{Context, Error, ErrorKind};
+    use bls12_381::{Bls12, Scalar};
+
+    let mut rng = rng();
+    let circuit = || XORDemo::<Scalar> {
+        a: Some(true),
+        b: Some(false),
+        _marker: PhantomData,
+    };
+    let params = generate_random_parameters::<Bls12, _, _>(circuit(), &mut rng).unwrap();
+
+    let mut bytes = vec![];
+    params.write(&mut bytes).unwrap();
+    let e = Error::from(
+        Parameters::<Bls12>::read(&bytes[..bytes.len() - 10], false)
+            .err()
+            .unwrap(),
+    );
+    assert_eq!(e.kind(), ErrorKind::MalformedParameters);
+    assert_eq!(e.section(), Some("b_g2"));
+    assert_eq!(e.offset(), Some(bytes.len() as u64 - 192));
+
+    // Points are decoded in parallel, but the first invalid one is reported.
+    let mut vk = vec![];
+    params.vk.write(&mut vk).unwrap();
+    let a = vk.len() + 4 + 96 * params.h.len() + 4 + 96 * params.l.len() + 4;
+    let mut corrupted = bytes.clone();
+    corrupted[a + 96 + 50] ^= 0xff;
+    corrupted[a + 2 * 96 + 50] ^= 0xff;
+    let e = Error::from(
+        Parameters::<Bls12>::read(&corrupted[..], true)
+            .err()
+            .unwrap(),
+    );
+    assert_eq!(
+        e.context().collect::<Vec<_>>(),
+        vec![
+            &Context::Section("a"),
+            &Context::Element(1),
+            &Context::Offset((a + 96) as u64)
+        ]
+    );
+
+    // An invalid point in the verifying key.
+    bytes[96] ^= 0xff;
+    let e = Error::from(Parameters::<Bls12>::read(&bytes[..], true).err().unwrap());
+    assert_eq!(
+        e.context().collect::<Vec<_>>(),
+        vec![
+            &Context::Section("vk"),
+            &Context::Section("beta_g1"),
+            &Context::Offset(96)
+        ]
+    );
+
+    let proof = create_random_proof(circuit(), &params, &mut rng).unwrap();
+    let mut bytes = vec![];
+    proof.write(&mut bytes).unwrap();
+    let e = Error::from(Proof::<Bls12>::read(&bytes[..150]).err().unwrap());
+    assert_eq!(e.kind(), ErrorKind::MalformedProof);
+    assert_eq!(e.section(), Some("c"));
+    assert_eq!(e.offset(), Some(144));
+}
+
+#[test]
+fn paranoid_proving() {
+    use super::exporter::ReplayCircuit;
+    use super::{create_random_proof_paranoid, ParanoidError, ParanoidOptions};
+    use bls12_381::{Bls12, Scalar};
+
+    let Fixture {
+        mut rng,
+        circuit,
+        witness,
+        params,
+        ..
+    } = Fixture::<Bls12>::new();
+    let replay = |assignment| ReplayCircuit {
+        circuit: circuit.clone(),
+        assignment: Some(assignment),
+    };
+    let options = ParanoidOptions::default();
+
+    assert!(
+        create_random_proof_paranoid(replay(witness.clone()), &params, &mut rng, &options).is_ok()
+    );
+
+    // A witness that violates a constraint is caught before proving, or by
+    // verification when the check is disabled.
+    let (_, constraint) = circuit.at_aux[0][0];
+    let mut bad = witness.clone();
+    bad.aux[0] += Scalar::one();
+    let e = create_random_proof_paranoid(replay(bad.clone()), &params, &mut rng, &options);
+    assert!(matches!(
+        e,
+        Err(ParanoidError::Unsatisfied { constraint: c }) if c <= constraint
+    ));
+    let unchecked = ParanoidOptions {
+        check_constraints: false,
+    };
+    let e = create_random_proof_paranoid(replay(bad), &params, &mut rng, &unchecked);
+    assert!(matches!(e, Err(ParanoidError::Rejected(_))));
+}
+
+#[test]
+fn keygen_progress() {
+    use super::{generate_parameters_with_progress, KeygenProgress, KeygenSection};
+    use bls12_381::{Bls12, G1Projective, G2Projective, Scalar};
+    use group::Group;
+
+    let mut f = Fixture::<Bls12>::new();
+    let g1 = G1Projective::random(&mut f.rng);
+    let g2 = G2Projective::random(&mut f.rng);
+    let waste = (0..5)
+        .map(|_| Scalar::random(&mut f.rng))
+        .collect::<Vec<_>>();
+    let (alpha, beta, gamma, delta, tau) = (waste[0], waste[1], waste[2], waste[3], waste[4]);
+
+    let expected =
+        generate_parameters::<Bls12, _>(f.shape(), g1, g2, alpha, beta, gamma, delta, tau).unwrap();
+
+    let mut events: Vec<KeygenProgress> = vec![];
+    let params = generate_parameters_with_progress::<Bls12, _>(
+        f.shape(),
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau,
+        2,
+        &mut |p| events.push(p.clone()),
+    )
+    .unwrap();
+    assert!(params == expected);
+
+    // Each section is reported window by window, up to its full size.
+    let last = |section| {
+        events
+            .iter()
+            .rev()
+            .find(|e| e.section == section)
+            .unwrap()
+            .clone()
+    };
+    let vars = f.circuit.num_inputs + f.circuit.num_aux;
+    assert_eq!(last(KeygenSection::H).done, params.h.len());
+    assert_eq!(last(KeygenSection::A).done, vars);
+    assert_eq!(last(KeygenSection::BG1).done, vars);
+    assert_eq!(last(KeygenSection::BG2).done, vars);
+    assert_eq!(last(KeygenSection::IC).done, params.vk.ic.len());
+    assert_eq!(last(KeygenSection::L).done, params.l.len());
+    assert!(
+        events
+            .iter()
+            .filter(|e| e.section == KeygenSection::H)
+            .count()
+            > 1
+    );
+    for e in events.iter() {
+        assert!(e.done <= e.total && e.overall_done <= e.overall_total);
+    }
+    assert!(events
+        .windows(2)
+        .all(|w| w[0].overall_done < w[1].overall_done));
+    let done = events.last().unwrap();
+    assert_eq!(done.overall_done, done.overall_total);
+    assert_eq!(done.eta(), Some(std::time::Duration::from_secs(0)));
+}
+
+#[test]
+fn batch_verification() {
+    use super::{create_random_proof, generate_random_parameters, verify_proofs_batch};
+    use crate::VerificationError;
+    use bls12_381::{Bls12, Scalar};
+
+    let mut rng = rng();
+    let circuit = |a, b| XORDemo::<Scalar> {
+        a,
+        b,
+        _marker: PhantomData,
+    };
+    let params = generate_random_parameters::<Bls12, _, _>(circuit(None, None), &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let mut proofs = vec![];
+    for i in 0..8 {
+        let (a, b) = (i & 1 == 1, i & 2 == 2);
+        let proof = create_random_proof(circuit(Some(a), Some(b)), &params, &mut rng).unwrap();
+        let input = if a ^ b { Scalar::one() } else { Scalar::zero() };
+        proofs.push((proof, vec![input]));
+    }
+    fn batch(
+        proofs: &[(super::Proof<Bls12>, Vec<Scalar>)],
+    ) -> Vec<(&super::Proof<Bls12>, &[Scalar])> {
+        proofs
+            .iter()
+            .map(|(proof, inputs)| (proof, &inputs[..]))
+            .collect()
+    }
+
+    assert!(verify_proofs_batch(&pvk, &batch(&proofs), &mut rng).is_ok());
+    assert!(verify_proofs_batch(&pvk, &batch(&proofs[..1]), &mut rng).is_ok());
+    assert!(verify_proofs_batch(&pvk, &[], &mut rng).is_ok());
+
+    // A single wrong input, or a proof out of place, fails the batch.
+    let mut wrong = proofs.clone();
+    wrong[5].1[0] += Scalar::one();
+    assert_eq!(
+        verify_proofs_batch(&pvk, &batch(&wrong), &mut rng),
+        Err(VerificationError::InvalidProof)
+    );
+    let mut swapped = proofs.clone();
+    let proof = swapped[1].0.clone();
+    swapped[0].0 = proof;
+    assert_eq!(
+        verify_proofs_batch(&pvk, &batch(&swapped), &mut rng),
+        Err(VerificationError::InvalidProof)
+    );
+
+    let mut short = proofs;
+    short[3].1.clear();
+    assert_eq!(
+        verify_proofs_batch(&pvk, &batch(&short), &mut rng),
+        Err(VerificationError::InvalidVerifyingKey)
+    );
+}
+
+#[test]
+fn input_tables() {
+    use super::fuzz::CircuitConfig;
+    use super::verify_proofs_batch;
+    use crate::VerificationError;
+    use bls12_381::{Bls12, Scalar};
+
+    let mut f = Fixture::<Bls12>::with_config(&CircuitConfig {
+        num_inputs: 20,
+        ..CircuitConfig::default()
+    });
+    let proof = f.proof();
+    let Fixture {
+        mut rng,
+        params,
+        witness,
+        ..
+    } = f;
+    let inputs = &witness.inputs[1..];
+    let mut wrong = inputs.to_vec();
+    wrong[7] = -Scalar::one();
+
+    for &window in &[1, 4, 7, 8] {
+        let pvk = prepare_verifying_key(&params.vk).with_input_tables(window);
+        assert!(verify_proof(&pvk, &proof, inputs).is_ok());
+        assert_eq!(
+            verify_proof(&pvk, &proof, &wrong),
+            Err(VerificationError::InvalidProof)
+        );
+        assert_eq!(
+            verify_proof(&pvk, &proof, &inputs[1..]),
+            Err(VerificationError::InvalidVerifyingKey)
+        );
+        assert!(verify_proofs_batch(&pvk, &[(&proof, inputs), (&proof, inputs)], &mut rng).is_ok());
+    }
+}
+
+/// Allocates its inputs, and their sum as an auxiliary variable.
+struct InputSum(Vec<Option<bls12_381::Scalar>>);
+
+impl Circuit<bls12_381::Scalar> for InputSum {
+    fn synthesize<CS: ConstraintSystem<bls12_381::Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError> {
+        let mut sum = Some(bls12_381::Scalar::zero());
+        let mut lc = crate::LinearCombination::zero();
+        for (i, x) in self.0.into_iter().enumerate() {
+            let var = cs.alloc_input(
+                || format!("x {}", i),
+                || x.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+            lc = lc + var;
+            sum = sum.and_then(|sum| x.map(|x| sum + x));
+        }
+        let y = cs.alloc(|| "sum", || sum.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce(|| "sum", |_| lc, |lc| lc + CS::one(), |lc| lc + y);
+        Ok(())
+    }
+}
+
+#[test]
+fn incremental_inputs() {
+    use super::{
+        create_random_proof, generate_random_parameters, verify_proof_prepared, PreparedInputs,
+    };
+    use crate::VerificationError;
+    use bls12_381::{Bls12, Scalar};
+
+    let mut rng = rng();
+    let params =
+        generate_random_parameters::<Bls12, _, _>(InputSum(vec![None; 6]), &mut rng).unwrap();
+    let initial = (0..6).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+
+    for pvk in vec![
+        prepare_verifying_key(&params.vk),
+        prepare_verifying_key(&params.vk).with_input_tables(4),
+    ] {
+        let mut inputs = initial.clone();
+        let mut prepared = PreparedInputs::new(&pvk, &inputs).unwrap();
+        assert_eq!(prepared.inputs(), &inputs[..]);
+
+        // Each proof of the sequence changes one input of the previous one.
+        for step in 0..4 {
+            let circuit = InputSum(inputs.iter().copied().map(Some).collect());
+            let proof = create_random_proof(circuit, &params, &mut rng).unwrap();
+            assert!(verify_proof_prepared(&pvk, &proof, &prepared).is_ok());
+
+            let index = step % 6;
+            inputs[index] = Scalar::random(&mut rng);
+            prepared.update(&pvk, index, inputs[index]);
+            assert_eq!(prepared.inputs(), &inputs[..]);
+            assert_eq!(
+                verify_proof_prepared(&pvk, &proof, &prepared),
+                Err(VerificationError::InvalidProof)
+            );
+        }
+    }
+
+    let pvk = prepare_verifying_key(&params.vk);
+    assert!(matches!(
+        PreparedInputs::new(&pvk, &initial[1..]),
+        Err(VerificationError::InvalidVerifyingKey)
+    ));
+}
+
+#[test]
+fn proof_rerandomization() {
+    use super::rerandomize_with;
+    use super::{create_random_proof, generate_random_parameters, rerandomize_proof};
+    use crate::VerificationError;
+    use bls12_381::{Bls12, Scalar};
+    use group::Curve;
+
+    let mut rng = rng();
+    let params =
+        generate_random_parameters::<Bls12, _, _>(InputSum(vec![None; 3]), &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let inputs = (0..3).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+    let circuit = || InputSum(inputs.iter().copied().map(Some).collect());
+
+    let proof = create_random_proof(circuit(), &params, &mut rng).unwrap();
+    let first = rerandomize_proof(&params.vk, &proof, &mut rng);
+    let second = rerandomize_proof(&params.vk, &first, &mut rng);
+    for rerandomized in &[&first, &second] {
+        assert!(verify_proof(&pvk, rerandomized, &inputs).is_ok());
+        assert!(rerandomized.a != proof.a && rerandomized.b != proof.b);
+        assert!(rerandomized.c != proof.c);
+    }
+    assert!(first != second);
+
+    // Re-randomizing a proof of another statement does not make it verify.
+    let mut other = inputs.clone();
+    other[0] += Scalar::one();
+    assert_eq!(
+        verify_proof(&pvk, &first, &other),
+        Err(VerificationError::InvalidProof)
+    );
+
+    // Shifting `s` gives exactly the fresh proof with that shift, and
+    // scaling by `r` then maps it to another pair of uniform `A` and `B`
+    // with the `C` they determine, as a fresh proof with other factors.
+    let (r, s, t) = (
+        Scalar::random(&mut rng),
+        Scalar::random(&mut rng),
+        Scalar::random(&mut rng),
+    );
+    let fresh = create_proof(circuit(), &params, r, s).unwrap();
+    assert!(
+        rerandomize_with(&params.vk, &fresh, Scalar::one(), t)
+            == create_proof(circuit(), &params, r, s + t).unwrap()
+    );
+    let scaled = rerandomize_with(&params.vk, &fresh, t, Scalar::zero());
+    assert!(verify_proof(&pvk, &scaled, &inputs).is_ok());
+    assert!((scaled.a * t).to_affine() == fresh.a);
+    assert!((fresh.b * t).to_affine() == scaled.b);
+}
+
+#[test]
+fn cached_synthesis() {
+    use super::{create_proof_with_cache, generate_random_parameters, SynthesisCache};
+    use bls12_381::{Bls12, Scalar};
+
+    let mut rng = rng();
+    let params =
+        generate_random_parameters::<Bls12, _, _>(InputSum(vec![None; 4]), &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let cache = SynthesisCache::new(InputSum(vec![None; 4])).unwrap();
+    // The sum, and a constraint for each of the inputs and ONE.
+    assert_eq!(cache.num_constraints(), 1 + 5);
+
+    for _ in 0..3 {
+        let inputs = (0..4).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+        let circuit = || InputSum(inputs.iter().copied().map(Some).collect());
+        let (r, s) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let cached = create_proof_with_cache(circuit(), &cache, &params, r, s).unwrap();
+        assert!(cached == create_proof(circuit(), &params, r, s).unwrap());
+        assert!(verify_proof(&pvk, &cached, &inputs).is_ok());
+    }
+
+    // A circuit with other variables than the cached one is rejected.
+    assert!(matches!(
+        create_proof_with_cache(
+            InputSum(vec![Some(Scalar::one()); 3]),
+            &cache,
+            &params,
+            Scalar::one(),
+            Scalar::one()
+        ),
+        Err(SynthesisError::Unsatisfiable)
+    ));
+
+    // The densities of the queries are cached along with the constraints.
+    let f = Fixture::<Bls12>::new();
+    let cache = SynthesisCache::new(f.shape()).unwrap();
+    let (r, s) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+    assert!(
+        create_proof_with_cache(f.circuit(), &cache, &f.params, r, s).unwrap()
+            == create_proof(f.circuit(), &f.params, r, s).unwrap()
+    );
+}
+
+#[test]
+fn proving_on_executors() {
+    use super::{create_proof_on, generate_random_parameters, generate_random_parameters_on};
+    use crate::multicore::{Executor, Worker};
+    use bls12_381::{Bls12, Scalar};
+    use std::sync::Arc;
+
+    // An executor that runs each job as it is spawned.
+    struct Inline;
+
+    impl Executor for Inline {
+        fn threads(&self) -> usize {
+            1
+        }
+
+        fn spawn(&self, job: Box<dyn FnOnce() + Send>) {
+            job()
+        }
+    }
+
+    let circuit = || InputSum(vec![Some(Scalar::one()); 4]);
+    let params = generate_random_parameters::<Bls12, _, _>(circuit(), &mut rng()).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let (r, s) = (Scalar::from(3), Scalar::from(5));
+    let expected = create_proof(circuit(), &params, r, s).unwrap();
+
+    for worker in &[
+        Worker::with_threads(2),
+        Worker::with_executor(Arc::new(Inline)),
+    ] {
+        let on =
+            generate_random_parameters_on::<Bls12, _, _>(worker, circuit(), &mut rng()).unwrap();
+        assert!(on == params);
+        let proof = create_proof_on(worker, circuit(), &params, r, s).unwrap();
+        assert!(proof == expected);
+        assert!(verify_proof(&pvk, &proof, &[Scalar::one(); 4]).is_ok());
+    }
+}
+
+/// Squares a private value `n` times, exposing the last square.
+struct Squarings<Scalar: PrimeField>(usize, Option<Scalar>);
+
+impl<Scalar: PrimeField> Circuit<Scalar> for Squarings<Scalar> {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut value = self.1;
+        let mut var = cs.alloc(|| "x 0", || value.ok_or(SynthesisError::AssignmentMissing))?;
+        for i in 1..=self.0 {
+            value = value.map(|x| x.square());
+            let square = || value.ok_or(SynthesisError::AssignmentMissing);
+            let next = if i == self.0 {
+                cs.alloc_input(|| format!("x {}", i), square)?
+            } else {
+                cs.alloc(|| format!("x {}", i), square)?
+            };
+            cs.enforce(
+                || format!("square {}", i),
+                |lc| lc + var,
+                |lc| lc + var,
+                |lc| lc + next,
+            );
+            var = next;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn mixed_radix_domains() {
+    use super::{generate_random_parameters_with_radix, prepare_verifying_key};
+    use crate::domain::{EvaluationDomain, Radix};
+    use crate::multicore::Worker;
+    use bls12_381::{Bls12, Scalar};
+
+    let mut rng = rng();
+
+    // The sum, and a constraint for each of the inputs and ONE, fit in 6
+    // points rather than 8.
+    let worker = Worker::new();
+    let circuit = || InputSum(vec![Some(Scalar::one()); 4]);
+    for &(radix, num_h) in &[(Radix::Two, 7), (Radix::Mixed, 5)] {
+        let params = generate_random_parameters_with_radix::<Bls12, _, _>(
+            &worker,
+            circuit(),
+            radix,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(params.h.len(), num_h);
+        assert!(params.verify_structure(circuit()).is_ok());
+        let pvk = prepare_verifying_key(&params.vk);
+        let (r, s) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let proof = create_proof(circuit(), &params, r, s).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Scalar::one(); 4]).is_ok());
+    }
+
+    // The dummy field has roots of unity of order 2^10 and 9, so a circuit
+    // of more than 512 constraints needs a mixed-radix domain, of 768.
+    let circuit = |x| Squarings(600, x);
+    let (g1, g2) = (Fr::one(), Fr::one());
+    let alpha = Fr::from_str("48577").unwrap();
+    let beta = Fr::from_str("22580").unwrap();
+    let gamma = Fr::from_str("53332").unwrap();
+    let delta = Fr::from_str("5481").unwrap();
+    let tau = Fr::from_str("3673").unwrap();
+    let params = generate_parameters::<DummyEngine, _>(
+        circuit(None),
+        g1,
+        g2,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        tau,
+    )
+    .unwrap();
+    assert_eq!(params.h.len(), 767);
+
+    let x = Fr::from_str("3").unwrap();
+    let y = (0..600).fold(x, |x, _| x.square());
+    let pvk = prepare_verifying_key(&params.vk);
+    let r = Fr::from_str("27134").unwrap();
+    let s = Fr::from_str("17146").unwrap();
+    let proof = create_proof(circuit(Some(x)), &params, r, s).unwrap();
+    assert!(verify_proof(&pvk, &proof, &[y]).is_ok());
+    assert!(verify_proof(&pvk, &proof, &[y.double()]).is_err());
+
+    // Domains of 9 times a power of two transform radix-3 twice.
+    let coeffs = (0..36u64)
+        .map(|i| crate::domain::Scalar(Fr::from_str(&format!("{}", i * i + 1)).unwrap()))
+        .collect::<Vec<_>>();
+    let mut domain = EvaluationDomain::with_size(coeffs.clone(), 36).unwrap();
+    domain.fft(&worker);
+    let omega = Fr::root_of_unity().pow_vartime(&[1 << 8])
+        * Fr::multiplicative_generator().pow_vartime(&[64512 / 9]);
+    for (i, value) in domain.as_ref().iter().enumerate() {
+        let point = omega.pow_vartime(&[i as u64]);
+        let expected = coeffs
+            .iter()
+            .rev()
+            .fold(Fr::zero(), |acc, c| acc * point + c.0);
+        assert_eq!(value.0, expected);
+    }
+    domain.ifft(&worker);
+    assert!(domain.into_coeffs() == coeffs);
+}
@@ -0,0 +1,374 @@
+//! Deterministic Groth16 test vectors, for checking that other
+//! implementations are compatible with bellman byte for byte.
+//!
+//! [`generate`] derives every value of a fixed set of vectors from a seed,
+//! so that the same seed always produces the same bytes. Each vector holds
+//! a circuit, parameters, a witness, public inputs, a proof, and whether
+//! the proof is expected to verify against those public inputs.
+//!
+//! # Format
+//!
+//! [`write_vectors`] emits the following, with integers in big-endian:
+//!
+//! ```text
+//! magic      "bellman-groth16-vectors"
+//! version    u32 (currently 1)
+//! count      u32
+//! count times:
+//!   name       u32 length, then UTF-8 bytes
+//!   circuit    as written by RawCircuit::write
+//!   parameters as written by Parameters::write
+//!   witness    as written by Assignment::write, with ONE as input 0
+//!   inputs     u32 count, then each public input as its canonical repr
+//!              (excluding ONE)
+//!   proof      as written by Proof::write
+//!   expected   u8: 1 if the proof verifies against the inputs, 0 otherwise
+//! ```
+//!
+//! Field elements are written in the canonical representation of
+//! [`PrimeField::to_repr`], and group elements as in the rest of this crate:
+//! compressed in proofs, uncompressed in parameters.
+
+use blake2s_simd::Params as Blake2sParams;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField};
+use group::{Group, WnafGroup};
+use pairing::{Engine, MultiMillerLoop};
+use rand_core::{impls, Error, RngCore};
+use std::io::{self, Read, Write};
+
+use super::exporter::{Assignment, RawCircuit, ReplayCircuit};
+use super::fuzz::{random_circuit, CircuitConfig};
+use super::{create_proof, generate_parameters, prepare_verifying_key, verify_proof};
+use super::{Parameters, Proof};
+use crate::SynthesisError;
+
+pub(super) const MAGIC: &[u8] = b"bellman-groth16-vectors";
+const VERSION: u32 = 1;
+
+/// A single test vector.
+pub struct TestVector<E: Engine> {
+    pub name: String,
+    pub circuit: RawCircuit<E::Fr>,
+    pub params: Parameters<E>,
+    pub witness: Assignment<E::Fr>,
+    pub inputs: Vec<E::Fr>,
+    pub proof: Proof<E>,
+    pub expected: bool,
+}
+
+impl<E: Engine> PartialEq for TestVector<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.circuit == other.circuit
+            && self.params == other.params
+            && self.witness == other.witness
+            && self.inputs == other.inputs
+            && self.proof == other.proof
+            && self.expected == other.expected
+    }
+}
+
+impl<E: MultiMillerLoop> TestVector<E> {
+    /// Returns `true` if verifying the proof gives the expected result.
+    pub fn check(&self) -> bool {
+        let pvk = prepare_verifying_key(&self.params.vk);
+        verify_proof(&pvk, &self.proof, &self.inputs).is_ok() == self.expected
+    }
+}
+
+impl<E: Engine> TestVector<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<BigEndian>(self.name.len() as u32)?;
+        writer.write_all(self.name.as_bytes())?;
+        self.circuit.write(&mut writer)?;
+        self.params.write(&mut writer)?;
+        self.witness.write(&mut writer)?;
+        writer.write_u32::<BigEndian>(self.inputs.len() as u32)?;
+        for input in self.inputs.iter() {
+            writer.write_all(input.to_repr().as_ref())?;
+        }
+        self.proof.write(&mut writer)?;
+        writer.write_u8(self.expected as u8)
+    }
+
+    /// Reads a vector; `checked` is passed on to [`Parameters::read`].
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
+        let len = reader.read_u32::<BigEndian>()? as usize;
+        let mut name = vec![0; len];
+        reader.read_exact(&mut name)?;
+        let name =
+            String::from_utf8(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let circuit = RawCircuit::read(&mut reader)?;
+        let params = Parameters::read(&mut reader, checked)?;
+        let witness = Assignment::read(&mut reader)?;
+
+        let count = reader.read_u32::<BigEndian>()? as usize;
+        let mut inputs = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            reader.read_exact(repr.as_mut())?;
+            let input = E::Fr::from_repr(repr).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "input is not a canonical field element",
+                )
+            })?;
+            inputs.push(input);
+        }
+
+        let proof = Proof::read(&mut reader)?;
+        let expected = match reader.read_u8()? {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "expected result is not a boolean",
+                ))
+            }
+        };
+
+        Ok(TestVector {
+            name,
+            circuit,
+            params,
+            witness,
+            inputs,
+            proof,
+            expected,
+        })
+    }
+}
+
+/// Writes vectors in the format described in the [module
+/// documentation](self).
+pub fn write_vectors<E: Engine, W: Write>(
+    vectors: &[TestVector<E>],
+    mut writer: W,
+) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<BigEndian>(VERSION)?;
+    writer.write_u32::<BigEndian>(vectors.len() as u32)?;
+    for vector in vectors {
+        vector.write(&mut writer)?;
+    }
+
+    Ok(())
+}
+
+/// Reads vectors written by [`write_vectors`]; `checked` is passed on to
+/// [`Parameters::read`].
+pub fn read_vectors<E: Engine, R: Read>(
+    mut reader: R,
+    checked: bool,
+) -> io::Result<Vec<TestVector<E>>> {
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a test vector file",
+        ));
+    }
+    if reader.read_u32::<BigEndian>()? != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported test vector version",
+        ));
+    }
+
+    let count = reader.read_u32::<BigEndian>()?;
+    (0..count)
+        .map(|_| TestVector::read(&mut reader, checked))
+        .collect()
+}
+
+/// A deterministic RNG, producing BLAKE2s(seed || label || counter) blocks.
+pub(super) struct SeededRng {
+    seed: Vec<u8>,
+    counter: u64,
+}
+
+impl SeededRng {
+    pub(super) fn new(seed: &[u8], label: &str) -> Self {
+        let mut state = Blake2sParams::new().personal(b"bellVecs").to_state();
+        state.update(seed);
+        state.update(label.as_bytes());
+
+        SeededRng {
+            seed: state.finalize().as_bytes().to_vec(),
+            counter: 0,
+        }
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(32) {
+            let block = Blake2sParams::new()
+                .key(&self.seed)
+                .to_state()
+                .update(&self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+            chunk.copy_from_slice(&block.as_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The circuits that vectors are generated for.
+pub(super) fn configs() -> Vec<(&'static str, CircuitConfig)> {
+    vec![
+        (
+            "single-constraint",
+            CircuitConfig {
+                num_inputs: 1,
+                num_aux: 1,
+                num_constraints: 1,
+                terms: 1,
+            },
+        ),
+        (
+            "no-public-inputs",
+            CircuitConfig {
+                num_inputs: 0,
+                num_aux: 3,
+                num_constraints: 4,
+                terms: 2,
+            },
+        ),
+        (
+            "dense",
+            CircuitConfig {
+                num_inputs: 4,
+                num_aux: 8,
+                num_constraints: 13,
+                terms: 5,
+            },
+        ),
+    ]
+}
+
+/// Generates the test vectors for `seed`.
+///
+/// For each circuit, there is a vector with a valid proof, followed by one
+/// whose first public input (or, without public inputs, the proof) has been
+/// altered so that it does not verify.
+pub fn generate<E>(seed: &[u8]) -> Result<Vec<TestVector<E>>, SynthesisError>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+{
+    let mut vectors = vec![];
+
+    for (name, config) in configs() {
+        let mut rng = SeededRng::new(seed, name);
+        let (circuit, witness) = random_circuit::<E::Fr, _>(&config, &mut rng);
+
+        let params = generate_parameters::<E, _>(
+            ReplayCircuit {
+                circuit: circuit.clone(),
+                assignment: None,
+            },
+            E::G1::generator(),
+            E::G2::generator(),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+        )?;
+        let proof = create_proof(
+            ReplayCircuit {
+                circuit: circuit.clone(),
+                assignment: Some(witness.clone()),
+            },
+            &params,
+            E::Fr::random(&mut rng),
+            E::Fr::random(&mut rng),
+        )?;
+        let inputs = witness.inputs[1..].to_vec();
+
+        let mut bad_inputs = inputs.clone();
+        let mut bad_proof = proof.clone();
+        match bad_inputs.first_mut() {
+            Some(input) => *input += &E::Fr::one(),
+            None => bad_proof.a = bad_proof.c,
+        }
+
+        vectors.push(TestVector {
+            name: format!("{}-valid", name),
+            circuit: circuit.clone(),
+            params: params.clone(),
+            witness: witness.clone(),
+            inputs,
+            proof,
+            expected: true,
+        });
+        vectors.push(TestVector {
+            name: format!("{}-invalid", name),
+            circuit,
+            params,
+            witness,
+            inputs: bad_inputs,
+            proof: bad_proof,
+            expected: false,
+        });
+    }
+
+    Ok(vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::Bls12;
+
+    #[test]
+    fn vectors_are_deterministic() {
+        let vectors = generate::<Bls12>(b"bellman test vectors").unwrap();
+        assert_eq!(vectors.len(), 6);
+        assert!(vectors.iter().all(|v| v.check()));
+
+        let mut bytes = vec![];
+        write_vectors(&vectors, &mut bytes).unwrap();
+
+        let again = generate::<Bls12>(b"bellman test vectors").unwrap();
+        let mut again_bytes = vec![];
+        write_vectors(&again, &mut again_bytes).unwrap();
+        assert_eq!(bytes, again_bytes);
+
+        let read = read_vectors::<Bls12, _>(&bytes[..], false).unwrap();
+        assert!(read == vectors);
+
+        // Changing the encoding, or the way vectors are derived, must be a
+        // deliberate change to this digest (and the format version).
+        let digest = Blake2sParams::new().hash(&bytes);
+        assert_eq!(
+            digest.to_hex().as_str(),
+            "7d7297c9c6a1242b87f90248e78af8b1768b3dba2fb1e82f939d8261342c0d59"
+        );
+
+        let other = generate::<Bls12>(b"other seed").unwrap();
+        assert!(other[0].proof != vectors[0].proof);
+
+        bytes[MAGIC.len() + 3] = 2;
+        assert!(read_vectors::<Bls12, _>(&bytes[..], false).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! Verification against any verifying key of an authorized set.
+//!
+//! Protocols that rotate the version of a circuit need to accept proofs for
+//! each of the versions they still authorize, without changing how proofs
+//! are checked. A [`VerifyingKeySet`] commits to its verifying keys with a
+//! Merkle root over their [hashes], and verifies a proof against the key at
+//! the index that accompanies it. Verifiers that only hold the root, such as
+//! contracts, can instead be given the verifying key and a [`MembershipPath`]
+//! for it, and check both with [`verify_member`].
+//!
+//! The tree is a binary BLAKE2s tree over the hashes of the keys, padded
+//! with zero leaves to a power of two, with leaves and inner nodes hashed
+//! under distinct prefixes.
+//!
+//! [hashes]: super::VerifyingKey::hash
+
+use blake2s_simd::Params as Blake2sParams;
+use pairing::{Engine, MultiMillerLoop};
+use std::error::Error;
+use std::fmt;
+
+use super::{prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey};
+use crate::VerificationError;
+
+/// Why a proof was not accepted by a [`VerifyingKeySet`].
+#[derive(Debug)]
+pub enum VkSetError {
+    /// There is no verifying key at this index in the set.
+    UnknownIndex(usize),
+    /// The verifying key is not in the set with this root.
+    NotAuthorized,
+    /// The proof does not verify against the verifying key.
+    Verification(VerificationError),
+}
+
+impl From<VerificationError> for VkSetError {
+    fn from(e: VerificationError) -> Self {
+        VkSetError::Verification(e)
+    }
+}
+
+impl Error for VkSetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VkSetError::Verification(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VkSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VkSetError::UnknownIndex(index) => {
+                write!(f, "no verifying key at index {} of the set", index)
+            }
+            VkSetError::NotAuthorized => write!(f, "the verifying key is not in the set"),
+            VkSetError::Verification(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<VkSetError> for crate::error::Error {
+    fn from(e: VkSetError) -> Self {
+        match e {
+            VkSetError::Verification(e) => e.into(),
+            e => crate::error::Error::new(crate::error::ErrorKind::VerificationFailed, e),
+        }
+    }
+}
+
+fn leaf(hash: &[u8; 32]) -> [u8; 32] {
+    node(0, hash, &[])
+}
+
+fn node(prefix: u8, left: &[u8], right: &[u8]) -> [u8; 32] {
+    let mut node = [0; 32];
+    node.copy_from_slice(
+        Blake2sParams::new()
+            .personal(b"bellVkSt")
+            .to_state()
+            .update(&[prefix])
+            .update(left)
+            .update(right)
+            .finalize()
+            .as_bytes(),
+    );
+    node
+}
+
+/// The siblings of a leaf, from the bottom of the tree up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MembershipPath {
+    pub index: usize,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MembershipPath {
+    /// Returns the root of the tree in which `vk` is at this path.
+    pub fn root<E: Engine>(&self, vk: &VerifyingKey<E>) -> [u8; 32] {
+        let mut acc = leaf(&vk.hash());
+        for (level, sibling) in self.siblings.iter().enumerate() {
+            acc = if (self.index >> level) & 1 == 0 {
+                node(1, &acc, sibling)
+            } else {
+                node(1, sibling, &acc)
+            };
+        }
+        acc
+    }
+}
+
+/// A set of verifying keys, committed to by a Merkle root.
+pub struct VerifyingKeySet<E: MultiMillerLoop> {
+    keys: Vec<(VerifyingKey<E>, PreparedVerifyingKey<E>)>,
+    // The levels of the tree, from the leaves up to the root.
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl<E: MultiMillerLoop> VerifyingKeySet<E> {
+    /// Authorizes `keys`, in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` is empty.
+    pub fn new(keys: Vec<VerifyingKey<E>>) -> Self {
+        assert!(!keys.is_empty(), "a set needs at least one verifying key");
+
+        let mut leaves = keys.iter().map(|vk| leaf(&vk.hash())).collect::<Vec<_>>();
+        leaves.resize(keys.len().next_power_of_two(), [0; 32]);
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| node(1, &pair[0], &pair[1]))
+                .collect();
+            levels.push(next);
+        }
+
+        VerifyingKeySet {
+            keys: keys
+                .into_iter()
+                .map(|vk| {
+                    let pvk = prepare_verifying_key(&vk);
+                    (vk, pvk)
+                })
+                .collect(),
+            levels,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the commitment to the set.
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub fn get(&self, index: usize) -> Option<&VerifyingKey<E>> {
+        self.keys.get(index).map(|(vk, _)| vk)
+    }
+
+    /// Returns the index of `vk` in the set, if it is authorized.
+    pub fn position(&self, vk: &VerifyingKey<E>) -> Option<usize> {
+        self.keys.iter().position(|(other, _)| other == vk)
+    }
+
+    /// Returns the path of the key at `index`, for [`verify_member`].
+    pub fn path(&self, index: usize) -> Option<MembershipPath> {
+        if index >= self.keys.len() {
+            return None;
+        }
+
+        let siblings = self.levels[..self.levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(level, nodes)| nodes[(index >> level) ^ 1])
+            .collect();
+        Some(MembershipPath { index, siblings })
+    }
+
+    /// Verifies `proof` against the verifying key at `index`.
+    pub fn verify(
+        &self,
+        index: usize,
+        proof: &Proof<E>,
+        public_inputs: &[E::Fr],
+    ) -> Result<(), VkSetError> {
+        let (_, pvk) = self
+            .keys
+            .get(index)
+            .ok_or(VkSetError::UnknownIndex(index))?;
+        Ok(verify_proof(pvk, proof, public_inputs)?)
+    }
+}
+
+/// Verifies `proof` against `vk`, if `path` shows that `vk` is in the set
+/// committed to by `root`.
+pub fn verify_member<E: MultiMillerLoop>(
+    root: &[u8; 32],
+    vk: &VerifyingKey<E>,
+    path: &MembershipPath,
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+) -> Result<(), VkSetError> {
+    if &path.root(vk) != root {
+        return Err(VkSetError::NotAuthorized);
+    }
+    Ok(verify_proof(
+        &prepare_verifying_key(vk),
+        proof,
+        public_inputs,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use crate::groth16::{create_random_proof, generate_random_parameters};
+    use bls12_381::Bls12;
+
+    #[test]
+    fn versioned_verification() {
+        let mut f = Fixture::<Bls12>::new();
+
+        // Three versions of the parameters, of which the first two are
+        // authorized.
+        let mut params = vec![f.params.clone()];
+        for _ in 0..2 {
+            params.push(generate_random_parameters(f.shape(), &mut f.rng).unwrap());
+        }
+        let set = VerifyingKeySet::new(vec![params[0].vk.clone(), params[1].vk.clone()]);
+        let inputs = &f.witness.inputs[1..];
+
+        let proof = create_random_proof(f.circuit(), &params[1], &mut f.rng).unwrap();
+        assert_eq!(set.position(&params[1].vk), Some(1));
+        assert!(set.verify(1, &proof, inputs).is_ok());
+        assert!(matches!(
+            set.verify(0, &proof, inputs),
+            Err(VkSetError::Verification(VerificationError::InvalidProof))
+        ));
+        assert!(matches!(
+            set.verify(2, &proof, inputs),
+            Err(VkSetError::UnknownIndex(2))
+        ));
+
+        let path = set.path(1).unwrap();
+        assert!(verify_member(&set.root(), &params[1].vk, &path, &proof, inputs).is_ok());
+        assert!(set.path(2).is_none());
+
+        let proof = create_random_proof(f.circuit(), &params[2], &mut f.rng).unwrap();
+        assert!(matches!(
+            verify_member(&set.root(), &params[2].vk, &path, &proof, inputs),
+            Err(VkSetError::NotAuthorized)
+        ));
+        assert!(set.position(&params[2].vk).is_none());
+
+        // A single key is its own tree.
+        let single = VerifyingKeySet::new(vec![params[2].vk.clone()]);
+        let path = single.path(0).unwrap();
+        assert!(path.siblings.is_empty());
+        assert!(verify_member(&single.root(), &params[2].vk, &path, &proof, inputs).is_ok());
+    }
+}
@@ -0,0 +1,259 @@
+//! Checking a witness without proving.
+//!
+//! A proving service should reject requests whose witness does not satisfy
+//! the circuit before it queues them, rather than after it spends the FFTs
+//! and multiexponentiations of a proof on them. [`check_witness`]
+//! synthesizes the circuit once, evaluating each constraint as it is
+//! enforced, and returns a [`SatisfactionReport`]. It needs no parameters,
+//! and holds only the values of the variables, not the constraints.
+
+use ff::PrimeField;
+use std::fmt;
+
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// A constraint that the witness does not satisfy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsatisfiedConstraint<Scalar: PrimeField> {
+    /// The position of the constraint in the order of synthesis.
+    pub index: usize,
+    /// The path of the constraint in the namespaces of the circuit, such as
+    /// `hash/round 3/x`.
+    pub path: String,
+    /// The values of its three linear combinations, with `a * b != c`.
+    pub a: Scalar,
+    pub b: Scalar,
+    pub c: Scalar,
+}
+
+/// The outcome of [`check_witness`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SatisfactionReport<Scalar: PrimeField> {
+    /// The public inputs, excluding `ONE`, as passed to
+    /// [`verify_proof`](super::verify_proof).
+    pub inputs: Vec<Scalar>,
+    pub num_aux: usize,
+    pub num_constraints: usize,
+    /// The constraints the witness does not satisfy, in order.
+    pub unsatisfied: Vec<UnsatisfiedConstraint<Scalar>>,
+}
+
+impl<Scalar: PrimeField> SatisfactionReport<Scalar> {
+    /// Returns whether the witness satisfies every constraint, so that a
+    /// proof of it would verify.
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied.is_empty()
+    }
+}
+
+impl<Scalar: PrimeField> fmt::Display for SatisfactionReport<Scalar> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} constraints unsatisfied ({} inputs, {} auxiliary variables)",
+            self.unsatisfied.len(),
+            self.num_constraints,
+            self.inputs.len(),
+            self.num_aux
+        )?;
+        for constraint in &self.unsatisfied {
+            write!(f, "\n  #{} {}", constraint.index, constraint.path)?;
+        }
+        Ok(())
+    }
+}
+
+struct CheckingCs<Scalar: PrimeField> {
+    inputs: Vec<Scalar>,
+    aux: Vec<Scalar>,
+    namespace: Vec<String>,
+    num_constraints: usize,
+    unsatisfied: Vec<UnsatisfiedConstraint<Scalar>>,
+}
+
+impl<Scalar: PrimeField> CheckingCs<Scalar> {
+    fn eval(&self, lc: &LinearCombination<Scalar>) -> Scalar {
+        lc.as_ref()
+            .iter()
+            .fold(Scalar::zero(), |acc, (var, coeff)| {
+                match var.get_unchecked() {
+                    Index::Input(i) => acc + self.inputs[i] * coeff,
+                    Index::Aux(i) => acc + self.aux[i] * coeff,
+                }
+            })
+    }
+}
+
+impl<Scalar: PrimeField> ConstraintSystem<Scalar> for CheckingCs<Scalar> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f()?);
+
+        Ok(Variable(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f()?);
+
+        Ok(Variable(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let index = self.num_constraints;
+        self.num_constraints += 1;
+
+        let a = self.eval(&a(LinearCombination::zero()));
+        let b = self.eval(&b(LinearCombination::zero()));
+        let c = self.eval(&c(LinearCombination::zero()));
+        if a * b != c {
+            // Only the constraints that fail are named.
+            let mut path = self.namespace.join("/");
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&annotation().into());
+            self.unsatisfied.push(UnsatisfiedConstraint {
+                index,
+                path,
+                a,
+                b,
+                c,
+            });
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespace.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        self.namespace.pop();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Synthesizes `circuit` with its witness and checks every constraint,
+/// without parameters and without computing a proof.
+///
+/// Errors of synthesis, such as [`SynthesisError::AssignmentMissing`] for a
+/// circuit without a witness, are returned as they are; a witness that
+/// synthesizes but violates constraints gives a report that is not
+/// [satisfied](SatisfactionReport::is_satisfied). The prover adds no
+/// constraints that a witness could violate, so a satisfied report means
+/// that proving will succeed and the proof verify for the reported inputs.
+pub fn check_witness<Scalar, C>(circuit: C) -> Result<SatisfactionReport<Scalar>, SynthesisError>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+{
+    let mut cs = CheckingCs {
+        inputs: vec![Scalar::one()],
+        aux: vec![],
+        namespace: vec![],
+        num_constraints: 0,
+        unsatisfied: vec![],
+    };
+    circuit.synthesize(&mut cs)?;
+
+    cs.inputs.remove(0);
+    Ok(SatisfactionReport {
+        inputs: cs.inputs,
+        num_aux: cs.aux.len(),
+        num_constraints: cs.num_constraints,
+        unsatisfied: cs.unsatisfied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::fixture::Fixture;
+    use bls12_381::{Bls12, Scalar};
+
+    struct Square {
+        x: Option<Scalar>,
+        y: Option<Scalar>,
+    }
+
+    impl Circuit<Scalar> for Square {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let y = cs.alloc_input(|| "y", || self.y.ok_or(SynthesisError::AssignmentMissing))?;
+            let mut cs = cs.namespace(|| "square");
+            let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn witness_checks() {
+        let f = Fixture::<Bls12>::new();
+        let report = check_witness(f.circuit()).unwrap();
+        assert!(report.is_satisfied());
+        assert_eq!(report.inputs[..], *f.inputs());
+        assert_eq!(report.num_aux, f.circuit.num_aux);
+        assert_eq!(report.num_constraints, f.circuit.num_constraints);
+
+        let report = check_witness(Square {
+            x: Some(Scalar::from(3)),
+            y: Some(Scalar::from(9)),
+        })
+        .unwrap();
+        assert!(report.is_satisfied());
+        assert_eq!(report.inputs, [Scalar::from(9)]);
+
+        let report = check_witness(Square {
+            x: Some(Scalar::from(3)),
+            y: Some(Scalar::from(10)),
+        })
+        .unwrap();
+        assert!(!report.is_satisfied());
+        assert_eq!(
+            report.unsatisfied,
+            [UnsatisfiedConstraint {
+                index: 0,
+                path: "square/x * x = y".to_string(),
+                a: Scalar::from(3),
+                b: Scalar::from(3),
+                c: Scalar::from(10),
+            }]
+        );
+        assert_eq!(
+            report.to_string(),
+            "1 of 1 constraints unsatisfied (1 inputs, 1 auxiliary variables)\n  #0 square/x * x = y"
+        );
+
+        assert!(matches!(
+            check_witness(Square { x: None, y: None }),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+}
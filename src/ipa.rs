@@ -0,0 +1,792 @@
+//! A transparent polynomial commitment scheme based on the inner product
+//! argument (IPA) from [Bulletproofs], in the form used for polynomial openings
+//! by [Halo].
+//!
+//! Commitments are Pedersen vector commitments to the coefficients of a
+//! polynomial over any prime-order group, so neither a pairing nor a trusted
+//! setup is required. An opening proof for a polynomial of degree less than
+//! 2<sup>k</sup> consists of `k` pairs of group elements and two scalars.
+//!
+//! Several polynomials can be opened at the same point with a single proof, and
+//! the linear-time part of verification can be deferred with a
+//! [`BatchVerifier`] so that any number of proofs is checked with one
+//! multi-exponentiation.
+//!
+//! Challenges are derived from a caller-provided [`Transcript`], so openings
+//! can be bound to the protocol they are part of.
+//!
+//! [Bulletproofs]: https://eprint.iacr.org/2017/1066
+//! [Halo]: https://eprint.iacr.org/2019/1021
+//! [`BatchVerifier`]: crate::ipa::BatchVerifier
+//! [`Transcript`]: crate::transcript::Transcript
+
+use blake2s_simd::{Params as Blake2sParams, State as Blake2sState};
+use ff::{Field, PrimeField};
+use group::{
+    prime::{PrimeCurve, PrimeCurveAffine},
+    GroupEncoding,
+};
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::ops::{AddAssign, MulAssign, Neg, SubAssign};
+
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+use crate::transcript::Transcript;
+use crate::{SynthesisError, VerificationError};
+
+/// Public parameters for committing to polynomials of degree less than
+/// 2<sup>k</sup>.
+#[derive(Clone, Debug)]
+pub struct Params<G: PrimeCurve> {
+    k: u32,
+    g: Vec<G::Affine>,
+    h: G::Affine,
+    u: G::Affine,
+}
+
+impl<G: PrimeCurve> PartialEq for Params<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.k == other.k && self.g == other.g && self.h == other.h && self.u == other.u
+    }
+}
+
+impl<G: PrimeCurve> Params<G> {
+    /// Derives parameters for polynomials of degree less than 2<sup>k</sup>
+    /// from a public `seed`.
+    ///
+    /// The generators are sampled with [`Group::random`](group::Group::random) from an RNG seeded
+    /// with `seed`, so the setup is transparent as long as the group samples
+    /// random elements without learning their discrete logarithms (as
+    /// `bls12_381` does by sampling a random x-coordinate).
+    pub fn new(k: u32, seed: &[u8]) -> Self {
+        let mut rng = HashRng::new(seed);
+        let n = 1 << k;
+
+        let g = (0..n).map(|_| G::random(&mut rng)).collect::<Vec<_>>();
+        let h = G::random(&mut rng);
+        let u = G::random(&mut rng);
+
+        Self::from_generators(k, &g, h, u)
+    }
+
+    fn from_generators(k: u32, g: &[G], h: G, u: G) -> Self {
+        assert_eq!(g.len(), 1 << k);
+
+        let mut g_affine = vec![G::Affine::identity(); g.len()];
+        G::batch_normalize(g, &mut g_affine);
+
+        Params {
+            k,
+            g: g_affine,
+            h: h.to_affine(),
+            u: u.to_affine(),
+        }
+    }
+
+    /// Returns `k`, the base-2 logarithm of the number of supported
+    /// coefficients.
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    /// Returns the maximum number of coefficients a committed polynomial can
+    /// have.
+    pub fn n(&self) -> usize {
+        self.g.len()
+    }
+
+    /// Returns the generators of the coefficients.
+    pub fn g(&self) -> &[G::Affine] {
+        &self.g
+    }
+
+    /// Returns the generator of the blinding factor.
+    pub fn h(&self) -> &G::Affine {
+        &self.h
+    }
+
+    /// Commits to the polynomial with the given coefficients (in ascending
+    /// order of degree) using the blinding factor `blind`.
+    pub fn commit(&self, coeffs: &[G::Scalar], blind: G::Scalar) -> Result<G, SynthesisError> {
+        if coeffs.len() > self.n() {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        let worker = Worker::new();
+        let mut acc = dense_multiexp::<G>(&worker, &self.g[..coeffs.len()], coeffs)?;
+        acc.add_assign(&(self.h * blind));
+
+        Ok(acc)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.k.to_be_bytes())?;
+        for g in &self.g {
+            writer.write_all(g.to_bytes().as_ref())?;
+        }
+        writer.write_all(self.h.to_bytes().as_ref())?;
+        writer.write_all(self.u.to_bytes().as_ref())?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut k = [0u8; 4];
+        reader.read_exact(&mut k)?;
+        let k = u32::from_be_bytes(k);
+        if k >= 32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported parameter size",
+            ));
+        }
+
+        // Not preallocated, so that a corrupted `k` fails at the end of the
+        // data rather than on allocation.
+        let mut g = vec![];
+        for _ in 0..(1usize << k) {
+            g.push(read_point::<G, _>(&mut reader)?);
+        }
+        let h = read_point::<G, _>(&mut reader)?;
+        let u = read_point::<G, _>(&mut reader)?;
+
+        Ok(Params { k, g, h, u })
+    }
+}
+
+/// An opening proof for one or more polynomials at a single point.
+#[derive(Clone, Debug)]
+pub struct Proof<G: PrimeCurve> {
+    rounds: Vec<(G::Affine, G::Affine)>,
+    a: G::Scalar,
+    blind: G::Scalar,
+}
+
+impl<G: PrimeCurve> PartialEq for Proof<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rounds == other.rounds && self.a == other.a && self.blind == other.blind
+    }
+}
+
+impl<G: PrimeCurve> Proof<G> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (l, r) in &self.rounds {
+            writer.write_all(l.to_bytes().as_ref())?;
+            writer.write_all(r.to_bytes().as_ref())?;
+        }
+        writer.write_all(self.a.to_repr().as_ref())?;
+        writer.write_all(self.blind.to_repr().as_ref())?;
+
+        Ok(())
+    }
+
+    /// Reads a proof for parameters of size 2<sup>k</sup>.
+    pub fn read<R: Read>(mut reader: R, k: u32) -> io::Result<Self> {
+        let mut rounds = vec![];
+        for _ in 0..k {
+            let l = read_point::<G, _>(&mut reader)?;
+            let r = read_point::<G, _>(&mut reader)?;
+            rounds.push((l, r));
+        }
+        let a = read_scalar::<G::Scalar, _>(&mut reader)?;
+        let blind = read_scalar::<G::Scalar, _>(&mut reader)?;
+
+        Ok(Proof { rounds, a, blind })
+    }
+}
+
+/// Evaluates the polynomial with the given coefficients at `x`.
+pub fn evaluate<S: PrimeField>(coeffs: &[S], x: S) -> S {
+    coeffs.iter().rev().fold(S::zero(), |mut acc, coeff| {
+        acc.mul_assign(&x);
+        acc.add_assign(coeff);
+        acc
+    })
+}
+
+/// Creates a proof that each of the given polynomials, committed to with
+/// [`Params::commit`] under the paired blinding factor, evaluates at `x` to
+/// the value returned by [`evaluate`].
+///
+/// The commitments, values and `x` are absorbed into `transcript`, which may
+/// already be bound to any surrounding context; the verifier must start from
+/// a transcript in the same state.
+pub fn create_proof<G, T, R>(
+    params: &Params<G>,
+    transcript: &mut T,
+    polys: &[(&[G::Scalar], G::Scalar)],
+    x: G::Scalar,
+    mut rng: R,
+) -> Result<Proof<G>, SynthesisError>
+where
+    G: PrimeCurve,
+    T: Transcript<G::Scalar>,
+    R: RngCore,
+{
+    let worker = Worker::new();
+    let n = params.n();
+
+    transcript.domain_separate(b"ipa");
+    transcript.absorb_u64(b"n", n as u64);
+    transcript.absorb_u64(b"polys", polys.len() as u64);
+    for &(coeffs, blind) in polys {
+        let commitment = params.commit(coeffs, blind)?;
+        transcript.absorb_point(b"commitment", &commitment.to_affine());
+        transcript.absorb_scalar(b"value", &evaluate(coeffs, x));
+    }
+    transcript.absorb_scalar(b"x", &x);
+
+    // Combine all of the polynomials into one with a random linear combination.
+    let rho = transcript.squeeze_challenge(b"rho");
+    let mut a = vec![G::Scalar::zero(); n];
+    let mut blind = G::Scalar::zero();
+    let mut rho_i = G::Scalar::one();
+    for &(coeffs, poly_blind) in polys {
+        for (a, coeff) in a.iter_mut().zip(coeffs.iter()) {
+            a.add_assign(&(*coeff * &rho_i));
+        }
+        blind.add_assign(&(poly_blind * &rho_i));
+        rho_i.mul_assign(&rho);
+    }
+
+    // Bind the claimed evaluation to a fresh generator.
+    let xi = transcript.squeeze_challenge(b"xi");
+    let u = params.u * xi;
+
+    let mut b = Vec::with_capacity(n);
+    let mut x_i = G::Scalar::one();
+    for _ in 0..n {
+        b.push(x_i);
+        x_i.mul_assign(&x);
+    }
+
+    let mut g = params.g.clone();
+    let mut rounds = Vec::with_capacity(params.k as usize);
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_lo, a_hi) = a.split_at(half);
+        let (b_lo, b_hi) = b.split_at(half);
+        let (g_lo, g_hi) = g.split_at(half);
+
+        let l_blind = G::Scalar::random(&mut rng);
+        let r_blind = G::Scalar::random(&mut rng);
+
+        let mut l = dense_multiexp::<G>(&worker, g_hi, a_lo)?;
+        l.add_assign(&(u * inner_product(a_lo, b_hi)));
+        l.add_assign(&(params.h * l_blind));
+
+        let mut r = dense_multiexp::<G>(&worker, g_lo, a_hi)?;
+        r.add_assign(&(u * inner_product(a_hi, b_lo)));
+        r.add_assign(&(params.h * r_blind));
+
+        let l = l.to_affine();
+        let r = r.to_affine();
+        transcript.absorb_point(b"L", &l);
+        transcript.absorb_point(b"R", &r);
+        rounds.push((l, r));
+
+        let challenge = transcript.squeeze_challenge(b"u");
+        let challenge_inv = {
+            let inverse = challenge.invert();
+            if bool::from(inverse.is_some()) {
+                Ok(inverse.unwrap())
+            } else {
+                Err(SynthesisError::DivisionByZero)
+            }
+        }?;
+
+        // a' = u a_lo + u^-1 a_hi
+        // b' = u^-1 b_lo + u b_hi
+        // G' = u^-1 G_lo + u G_hi
+        let a_next = a_lo
+            .iter()
+            .zip(a_hi.iter())
+            .map(|(lo, hi)| *lo * &challenge + &(*hi * &challenge_inv))
+            .collect::<Vec<_>>();
+        let b_next = b_lo
+            .iter()
+            .zip(b_hi.iter())
+            .map(|(lo, hi)| *lo * &challenge_inv + &(*hi * &challenge))
+            .collect::<Vec<_>>();
+        let g_proj = g_lo
+            .iter()
+            .zip(g_hi.iter())
+            .map(|(lo, hi)| (*lo * challenge_inv) + (*hi * challenge))
+            .collect::<Vec<_>>();
+        let mut g_next = vec![G::Affine::identity(); half];
+        G::batch_normalize(&g_proj, &mut g_next);
+
+        // The blinding factor of the folded commitment picks up the blinding
+        // factors of L and R with the same weights.
+        let challenge_sq = challenge.square();
+        let challenge_inv_sq = challenge_inv.square();
+        blind.add_assign(&(l_blind * &challenge_sq));
+        blind.add_assign(&(r_blind * &challenge_inv_sq));
+
+        a = a_next;
+        b = b_next;
+        g = g_next;
+    }
+
+    Ok(Proof {
+        rounds,
+        a: a[0],
+        blind,
+    })
+}
+
+/// Checks a proof that the polynomials committed to in `commitments` evaluate
+/// at `x` to the paired values.
+pub fn verify_proof<G: PrimeCurve, T: Transcript<G::Scalar>>(
+    params: &Params<G>,
+    transcript: &mut T,
+    commitments: &[(G, G::Scalar)],
+    x: G::Scalar,
+    proof: &Proof<G>,
+) -> Result<(), VerificationError> {
+    let mut batch = BatchVerifier::new(params);
+    batch.accumulate(transcript, commitments, x, proof, G::Scalar::one())?;
+    batch.finalize()
+}
+
+/// Accumulates the expensive part of verifying many opening proofs so that
+/// they can be checked together with a single multi-exponentiation.
+pub struct BatchVerifier<'a, G: PrimeCurve> {
+    params: &'a Params<G>,
+    g_coeffs: Vec<G::Scalar>,
+    h_coeff: G::Scalar,
+    u_coeff: G::Scalar,
+    others: Vec<(G::Affine, G::Scalar)>,
+}
+
+impl<'a, G: PrimeCurve> BatchVerifier<'a, G> {
+    pub fn new(params: &'a Params<G>) -> Self {
+        BatchVerifier {
+            params,
+            g_coeffs: vec![G::Scalar::zero(); params.n()],
+            h_coeff: G::Scalar::zero(),
+            u_coeff: G::Scalar::zero(),
+            others: vec![],
+        }
+    }
+
+    /// Adds a proof to the batch, weighting it with a random scalar drawn
+    /// from `rng` so that invalid proofs cannot cancel each other out.
+    ///
+    /// Structural problems with the proof are reported immediately; whether
+    /// the proof is valid is only known once [`BatchVerifier::finalize`] is
+    /// called.
+    pub fn add<T: Transcript<G::Scalar>, R: RngCore>(
+        &mut self,
+        transcript: &mut T,
+        commitments: &[(G, G::Scalar)],
+        x: G::Scalar,
+        proof: &Proof<G>,
+        mut rng: R,
+    ) -> Result<(), VerificationError> {
+        let weight = G::Scalar::random(&mut rng);
+        self.accumulate(transcript, commitments, x, proof, weight)
+    }
+
+    fn accumulate<T: Transcript<G::Scalar>>(
+        &mut self,
+        transcript: &mut T,
+        commitments: &[(G, G::Scalar)],
+        x: G::Scalar,
+        proof: &Proof<G>,
+        weight: G::Scalar,
+    ) -> Result<(), VerificationError> {
+        let n = self.params.n();
+        if proof.rounds.len() != self.params.k as usize {
+            return Err(VerificationError::InvalidProof);
+        }
+
+        transcript.domain_separate(b"ipa");
+        transcript.absorb_u64(b"n", n as u64);
+        transcript.absorb_u64(b"polys", commitments.len() as u64);
+        for (commitment, value) in commitments {
+            transcript.absorb_point(b"commitment", &commitment.to_affine());
+            transcript.absorb_scalar(b"value", value);
+        }
+        transcript.absorb_scalar(b"x", &x);
+
+        let rho = transcript.squeeze_challenge(b"rho");
+        let mut value = G::Scalar::zero();
+        let mut rho_i = weight;
+        for (commitment, v) in commitments {
+            self.others.push((commitment.to_affine(), rho_i));
+            value.add_assign(&(*v * &rho_i));
+            rho_i.mul_assign(&rho);
+        }
+
+        let xi = transcript.squeeze_challenge(b"xi");
+
+        let mut challenges = Vec::with_capacity(proof.rounds.len());
+        for (l, r) in &proof.rounds {
+            transcript.absorb_point(b"L", l);
+            transcript.absorb_point(b"R", r);
+
+            let challenge = transcript.squeeze_challenge(b"u");
+            let challenge_inv = challenge.invert();
+            if bool::from(challenge_inv.is_none()) {
+                return Err(VerificationError::InvalidProof);
+            }
+            let challenge_inv = challenge_inv.unwrap();
+
+            self.others.push((*l, challenge.square() * &weight));
+            self.others.push((*r, challenge_inv.square() * &weight));
+            challenges.push((challenge, challenge_inv));
+        }
+
+        // The folded generator is <s, G> where s_i is the product of u_j or
+        // u_j^-1, depending on whether bit (k - 1 - j) of i is set.
+        let mut s = vec![G::Scalar::one(); n];
+        for (j, &(challenge, challenge_inv)) in challenges.iter().enumerate() {
+            let bit = n >> (j + 1);
+            for (i, s) in s.iter_mut().enumerate() {
+                if i & bit != 0 {
+                    s.mul_assign(&challenge);
+                } else {
+                    s.mul_assign(&challenge_inv);
+                }
+            }
+        }
+
+        // The folded evaluation vector has the closed form
+        // prod_j (u_j^-1 + u_j x^(n / 2^(j + 1))).
+        let mut b = G::Scalar::one();
+        for (j, &(challenge, challenge_inv)) in challenges.iter().enumerate() {
+            let x_pow = x.pow_vartime([(n >> (j + 1)) as u64]);
+            b.mul_assign(&(challenge_inv + &(challenge * &x_pow)));
+        }
+
+        // P + sum(u_j^2 L_j + u_j^-2 R_j) = a <s, G> + (a b) xi U + blind H
+        let neg_a = (proof.a * &weight).neg();
+        for (coeff, s) in self.g_coeffs.iter_mut().zip(s.iter()) {
+            coeff.add_assign(&(neg_a * s));
+        }
+        self.h_coeff.sub_assign(&(proof.blind * &weight));
+        self.u_coeff
+            .add_assign(&(xi * &(value - &(proof.a * &b * &weight))));
+
+        Ok(())
+    }
+
+    /// Checks every proof added to the batch at once.
+    pub fn finalize(self) -> Result<(), VerificationError> {
+        let mut bases = self.params.g.clone();
+        let mut scalars = self.g_coeffs;
+        bases.push(self.params.h);
+        scalars.push(self.h_coeff);
+        bases.push(self.params.u);
+        scalars.push(self.u_coeff);
+        for (base, scalar) in self.others {
+            bases.push(base);
+            scalars.push(scalar);
+        }
+
+        let worker = Worker::new();
+        let acc = dense_multiexp::<G>(&worker, &bases, &scalars)
+            .map_err(|_| VerificationError::InvalidProof)?;
+
+        if bool::from(acc.is_identity()) {
+            Ok(())
+        } else {
+            Err(VerificationError::InvalidProof)
+        }
+    }
+}
+
+fn inner_product<S: PrimeField>(a: &[S], b: &[S]) -> S {
+    a.iter()
+        .zip(b.iter())
+        .fold(S::zero(), |acc, (a, b)| acc + *a * b)
+}
+
+fn read_point<G: PrimeCurve, R: Read>(reader: &mut R) -> io::Result<G::Affine> {
+    let mut repr = <G::Affine as GroupEncoding>::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+
+    let affine = G::Affine::from_bytes(&repr);
+    if affine.is_some().into() {
+        Ok(affine.unwrap())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "invalid point"))
+    }
+}
+
+fn read_scalar<S: PrimeField, R: Read>(reader: &mut R) -> io::Result<S> {
+    let mut repr = S::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+
+    S::from_repr(repr).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid scalar"))
+}
+
+/// A deterministic RNG producing a BLAKE2s keystream from a seed, used to
+/// derive "nothing up my sleeve" generators.
+struct HashRng {
+    seed: Blake2sState,
+    counter: u64,
+    buf: [u8; 32],
+    pos: usize,
+}
+
+impl HashRng {
+    fn new(seed: &[u8]) -> Self {
+        let mut state = Blake2sParams::new().personal(b"bellmGen").to_state();
+        state.update(seed);
+
+        HashRng {
+            seed: state,
+            counter: 0,
+            buf: [0; 32],
+            pos: 32,
+        }
+    }
+}
+
+impl RngCore for HashRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            if self.pos == self.buf.len() {
+                let mut state = self.seed.clone();
+                state.update(&self.counter.to_le_bytes());
+                self.buf.copy_from_slice(state.finalize().as_bytes());
+                self.counter += 1;
+                self.pos = 0;
+            }
+            *byte = self.buf[self.pos];
+            self.pos += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Blake2sTranscript;
+
+    use bls12_381::{G1Projective, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ])
+    }
+
+    #[test]
+    fn params_are_deterministic() {
+        let a = Params::<G1Projective>::new(2, b"seed");
+        let b = Params::<G1Projective>::new(2, b"seed");
+        let c = Params::<G1Projective>::new(2, b"other seed");
+
+        assert!(a == b);
+        assert!(a != c);
+
+        let mut v = vec![];
+        a.write(&mut v).unwrap();
+        assert!(Params::<G1Projective>::read(&v[..]).unwrap() == a);
+
+        // A truncated encoding claiming the largest size.
+        v[..4].copy_from_slice(&31u32.to_be_bytes());
+        assert_eq!(
+            Params::<G1Projective>::read(&v[..]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn open_single_polynomial() {
+        let mut rng = rng();
+        let params = Params::<G1Projective>::new(3, b"test");
+
+        for len in 0..=8 {
+            let coeffs = (0..len)
+                .map(|_| Scalar::random(&mut rng))
+                .collect::<Vec<_>>();
+            let blind = Scalar::random(&mut rng);
+            let x = Scalar::random(&mut rng);
+
+            let commitment = params.commit(&coeffs, blind).unwrap();
+            let value = evaluate(&coeffs, x);
+
+            let proof = create_proof(
+                &params,
+                &mut Blake2sTranscript::new(b"test"),
+                &[(&coeffs, blind)],
+                x,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(verify_proof(
+                &params,
+                &mut Blake2sTranscript::new(b"test"),
+                &[(commitment, value)],
+                x,
+                &proof
+            )
+            .is_ok());
+
+            // Wrong evaluation
+            let wrong = value + Scalar::one();
+            assert!(verify_proof(
+                &params,
+                &mut Blake2sTranscript::new(b"test"),
+                &[(commitment, wrong)],
+                x,
+                &proof
+            )
+            .is_err());
+
+            // Wrong point
+            let other_x = x + Scalar::one();
+            assert!(verify_proof(
+                &params,
+                &mut Blake2sTranscript::new(b"test"),
+                &[(commitment, value)],
+                other_x,
+                &proof
+            )
+            .is_err());
+
+            let mut v = vec![];
+            proof.write(&mut v).unwrap();
+            let de_proof = Proof::<G1Projective>::read(&v[..], params.k()).unwrap();
+            assert!(proof == de_proof);
+        }
+    }
+
+    #[test]
+    fn too_many_coefficients() {
+        let params = Params::<G1Projective>::new(1, b"test");
+        let coeffs = vec![Scalar::one(); 3];
+
+        assert!(params.commit(&coeffs, Scalar::zero()).is_err());
+    }
+
+    #[test]
+    fn batched_opening() {
+        let mut rng = rng();
+        let params = Params::<G1Projective>::new(3, b"test");
+        let x = Scalar::random(&mut rng);
+
+        let polys = (0..3)
+            .map(|i| {
+                let coeffs = (0..(8 - i))
+                    .map(|_| Scalar::random(&mut rng))
+                    .collect::<Vec<_>>();
+                (coeffs, Scalar::random(&mut rng))
+            })
+            .collect::<Vec<_>>();
+        let openings = polys
+            .iter()
+            .map(|(coeffs, blind)| (&coeffs[..], *blind))
+            .collect::<Vec<_>>();
+        let mut commitments = polys
+            .iter()
+            .map(|(coeffs, blind)| (params.commit(coeffs, *blind).unwrap(), evaluate(coeffs, x)))
+            .collect::<Vec<_>>();
+
+        let proof = create_proof(
+            &params,
+            &mut Blake2sTranscript::new(b"test"),
+            &openings,
+            x,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verify_proof(
+            &params,
+            &mut Blake2sTranscript::new(b"test"),
+            &commitments,
+            x,
+            &proof
+        )
+        .is_ok());
+
+        commitments.swap(0, 1);
+        assert!(verify_proof(
+            &params,
+            &mut Blake2sTranscript::new(b"test"),
+            &commitments,
+            x,
+            &proof
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn amortized_verification() {
+        let mut rng = rng();
+        let params = Params::<G1Projective>::new(2, b"test");
+
+        let mut claims = vec![];
+        for _ in 0..4 {
+            let coeffs = (0..4).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+            let blind = Scalar::random(&mut rng);
+            let x = Scalar::random(&mut rng);
+            let proof = create_proof(
+                &params,
+                &mut Blake2sTranscript::new(b"test"),
+                &[(&coeffs, blind)],
+                x,
+                &mut rng,
+            )
+            .unwrap();
+            let commitment = params.commit(&coeffs, blind).unwrap();
+            claims.push((commitment, evaluate(&coeffs, x), x, proof));
+        }
+
+        let mut batch = BatchVerifier::new(&params);
+        for (commitment, value, x, proof) in &claims {
+            batch
+                .add(
+                    &mut Blake2sTranscript::new(b"test"),
+                    &[(*commitment, *value)],
+                    *x,
+                    proof,
+                    &mut rng,
+                )
+                .unwrap();
+        }
+        assert!(batch.finalize().is_ok());
+
+        // A single bad claim poisons the whole batch.
+        let mut batch = BatchVerifier::new(&params);
+        for (i, (commitment, value, x, proof)) in claims.iter().enumerate() {
+            let value = if i == 2 {
+                *value + Scalar::one()
+            } else {
+                *value
+            };
+            batch
+                .add(
+                    &mut Blake2sTranscript::new(b"test"),
+                    &[(*commitment, value)],
+                    *x,
+                    proof,
+                    &mut rng,
+                )
+                .unwrap();
+        }
+        assert!(batch.finalize().is_err());
+    }
+}
@@ -134,23 +134,76 @@
 //! using the [`ff`] and [`group`] crates, while specific proving systems will
 //! be separate crates that pull in the dependencies they require.
 
-// Catch documentation errors caused by code changes.
-#![deny(intra_doc_link_resolution_failure)]
-
+// Catch documentation errors caused by code changes. The documentation of
+// the verifier alone links to the parts of the crate that it leaves out.
+#![cfg_attr(feature = "groth16", deny(intra_doc_link_resolution_failure))]
+#![cfg_attr(not(feature = "std"), no_std)]
+// Generic code borrows the right operand of arithmetic on field and group
+// elements, for older compilers to find the impl.
+#![allow(clippy::op_ref)]
+
+extern crate alloc;
+
+#[cfg(feature = "bn254")]
+pub mod bn254;
+#[cfg(feature = "std")]
+pub mod budget;
+#[cfg(feature = "std")]
 pub mod domain;
-pub mod gadgets;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "examples-circuits")]
+pub mod examples;
 #[cfg(feature = "groth16")]
+pub mod folding;
+#[cfg(feature = "gadgets")]
+pub mod gadgets;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "verifier")]
 pub mod groth16;
+#[cfg(feature = "std")]
+pub mod ipa;
+#[cfg(feature = "std")]
+pub mod memo;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(not(feature = "metrics"))]
+mod metrics;
+#[cfg(feature = "std")]
+pub mod mmr;
+#[cfg(feature = "std")]
 pub mod multicore;
+#[cfg(feature = "std")]
 pub mod multiexp;
+#[cfg(feature = "std")]
+pub mod poseidon;
+#[cfg(feature = "std")]
+pub mod proof_system;
+#[cfg(feature = "gadgets")]
+pub mod protocols;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "sonic")]
+pub mod sonic;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod transcript;
+#[cfg(feature = "std")]
+pub mod zeroize;
 
 use ff::PrimeField;
 
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Add, Sub};
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
-use std::marker::PhantomData;
-use std::ops::{Add, Sub};
 
 /// Computations are expressed in terms of arithmetic circuits, in particular
 /// rank-1 quadratic constraint systems. The `Circuit` trait represents a
@@ -200,7 +253,7 @@ impl<Scalar: PrimeField> AsRef<[(Variable, Scalar)]> for LinearCombination<Scala
 
 impl<Scalar: PrimeField> LinearCombination<Scalar> {
     pub fn zero() -> LinearCombination<Scalar> {
-        LinearCombination(vec![])
+        LinearCombination(Vec::new())
     }
 }
 
@@ -316,19 +369,21 @@ pub enum SynthesisError {
     /// During proof generation, we encountered an identity in the CRS
     UnexpectedIdentity,
     /// During proof generation, we encountered an I/O error with the CRS
+    #[cfg(feature = "std")]
     IoError(io::Error),
     /// During CRS generation, we observed an unconstrained auxiliary variable
     UnconstrainedVariable,
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for SynthesisError {
     fn from(e: io::Error) -> SynthesisError {
         SynthesisError::IoError(e)
     }
 }
 
-impl Error for SynthesisError {
-    fn description(&self) -> &str {
+impl SynthesisError {
+    fn message(&self) -> &'static str {
         match *self {
             SynthesisError::AssignmentMissing => {
                 "an assignment for a variable could not be computed"
@@ -337,25 +392,42 @@ impl Error for SynthesisError {
             SynthesisError::Unsatisfiable => "unsatisfiable constraint system",
             SynthesisError::PolynomialDegreeTooLarge => "polynomial degree is too large",
             SynthesisError::UnexpectedIdentity => "encountered an identity element in the CRS",
+            #[cfg(feature = "std")]
             SynthesisError::IoError(_) => "encountered an I/O error",
             SynthesisError::UnconstrainedVariable => "auxiliary variable was unconstrained",
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl Error for SynthesisError {
+    fn description(&self) -> &str {
+        self.message()
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SynthesisError::IoError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for SynthesisError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        if let SynthesisError::IoError(ref e) = *self {
-            write!(f, "I/O error: ")?;
-            e.fmt(f)
-        } else {
-            write!(f, "{}", self)
+        match *self {
+            #[cfg(feature = "std")]
+            SynthesisError::IoError(ref e) => {
+                write!(f, "I/O error: ")?;
+                e.fmt(f)
+            }
+            _ => f.write_str(self.message()),
         }
     }
 }
 
 /// An error during verification.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VerificationError {
     /// Verification was attempted with a malformed verifying key.
     InvalidVerifyingKey,
@@ -363,8 +435,8 @@ pub enum VerificationError {
     InvalidProof,
 }
 
-impl Error for VerificationError {
-    fn description(&self) -> &str {
+impl VerificationError {
+    fn message(&self) -> &'static str {
         match *self {
             VerificationError::InvalidVerifyingKey => "malformed verifying key",
             VerificationError::InvalidProof => "proof verification failed",
@@ -372,9 +444,33 @@ impl Error for VerificationError {
     }
 }
 
+#[cfg(feature = "std")]
+impl Error for VerificationError {
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
 impl fmt::Display for VerificationError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        write!(f, "{}", self)
+        f.write_str(self.message())
+    }
+}
+
+/// Returns the location in the source of the call to [`ConstraintSystem::alloc`],
+/// [`ConstraintSystem::alloc_input`] or [`ConstraintSystem::enforce`] that is being
+/// handled, when called from an implementation of them with the `locations` feature.
+/// Without the feature, there is no location to capture, and this returns `None`.
+#[cfg_attr(feature = "locations", track_caller)]
+#[inline]
+pub fn caller_location() -> Option<&'static core::panic::Location<'static>> {
+    #[cfg(feature = "locations")]
+    {
+        Some(core::panic::Location::caller())
+    }
+    #[cfg(not(feature = "locations"))]
+    {
+        None
     }
 }
 
@@ -394,6 +490,7 @@ pub trait ConstraintSystem<Scalar: PrimeField>: Sized {
     /// determine the assignment of the variable. The given `annotation` function is invoked
     /// in testing contexts in order to derive a unique name for this variable in the current
     /// namespace.
+    #[cfg_attr(feature = "locations", track_caller)]
     fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<Scalar, SynthesisError>,
@@ -402,6 +499,7 @@ pub trait ConstraintSystem<Scalar: PrimeField>: Sized {
 
     /// Allocate a public variable in the constraint system. The provided function is used to
     /// determine the assignment of the variable.
+    #[cfg_attr(feature = "locations", track_caller)]
     fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
     where
         F: FnOnce() -> Result<Scalar, SynthesisError>,
@@ -410,6 +508,11 @@ pub trait ConstraintSystem<Scalar: PrimeField>: Sized {
 
     /// Enforce that `A` * `B` = `C`. The `annotation` function is invoked in testing contexts
     /// in order to derive a unique name for the constraint in the current namespace.
+    ///
+    /// With the `locations` feature, these three methods are `#[track_caller]`, so that
+    /// [`caller_location`] in an implementation returns the line of the gadget that called
+    /// them, through any [`Namespace`].
+    #[cfg_attr(feature = "locations", track_caller)]
     fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
     where
         A: FnOnce() -> AR,
@@ -0,0 +1,482 @@
+//! Memoization of repeated sub-circuits.
+//!
+//! Circuits often synthesize the same gadget over the same inputs several
+//! times, for instance hashing a constant prefix in every round. [`Memoize`]
+//! wraps a constraint system and buffers each namespace until it is popped.
+//! If an earlier namespace allocated the same variables and enforced the
+//! same constraints over the same outer variables, the variables of the
+//! repeated one are mapped to the earlier ones, and its constraints are
+//! dropped. [`Memoized`] applies this to a whole circuit.
+//!
+//! Whether a namespace is shared only depends on its structure, so keygen
+//! and proving agree on the shape of the constraint system. Sharing adds
+//! the requirement that the repeated variables are equal to the earlier
+//! ones, which holds if the sub-circuit computes its variables from the
+//! outer ones. When values are assigned, they are checked, and synthesis
+//! fails with [`SynthesisError::Unsatisfiable`] if they differ. Namespaces
+//! allocating public inputs are never shared.
+
+use blake2s_simd::Params as Blake2sParams;
+use ff::PrimeField;
+use std::collections::HashMap;
+
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// How much a [`Memoize`] saved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    /// The namespaces that were mapped to an earlier one.
+    pub namespaces: usize,
+    pub variables: usize,
+    pub constraints: usize,
+}
+
+enum Binding {
+    Pending,
+    Real(Variable),
+    Alias(usize),
+}
+
+enum Op<S: PrimeField> {
+    Alloc(usize, String),
+    Enforce(String, [Vec<(Variable, S)>; 3]),
+    Push(String),
+    Pop,
+}
+
+#[derive(Default)]
+struct Frame<S: PrimeField> {
+    ops: Vec<Op<S>>,
+    // The variables allocated in the namespace and its children, and not
+    // shared with earlier ones, in order.
+    locals: Vec<usize>,
+    // Whether the namespace allocated a public input.
+    opaque: bool,
+}
+
+/// A constraint system that shares the variables of repeated namespaces.
+///
+/// Variables are allocated lazily: until the outermost buffered namespace is
+/// popped, the wrapped constraint system has not seen them.
+pub struct Memoize<S: PrimeField, CS: ConstraintSystem<S>> {
+    cs: CS,
+    bindings: Vec<Binding>,
+    values: Vec<Option<S>>,
+    frames: Vec<Frame<S>>,
+    cache: HashMap<[u8; 32], Vec<usize>>,
+    stats: MemoStats,
+    error: Option<SynthesisError>,
+}
+
+impl<S: PrimeField, CS: ConstraintSystem<S>> Memoize<S, CS> {
+    pub fn new(cs: CS) -> Self {
+        Memoize {
+            cs,
+            bindings: vec![],
+            values: vec![],
+            frames: vec![],
+            cache: HashMap::new(),
+            stats: MemoStats::default(),
+            error: None,
+        }
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        self.stats
+    }
+
+    /// Returns the wrapped constraint system, or the error of a shared
+    /// namespace whose values differed from the earlier one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a namespace is still open.
+    pub fn finish(self) -> Result<CS, SynthesisError> {
+        assert!(self.frames.is_empty(), "a namespace is still open");
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.cs),
+        }
+    }
+
+    fn resolve(&self, mut id: usize) -> usize {
+        while let Binding::Alias(next) = self.bindings[id] {
+            id = next;
+        }
+        id
+    }
+
+    fn canonical(&self, var: Variable) -> Variable {
+        match var.get_unchecked() {
+            Index::Aux(id) => Variable(Index::Aux(self.resolve(id))),
+            Index::Input(_) => var,
+        }
+    }
+
+    fn real(&self, var: Variable) -> Variable {
+        match var.get_unchecked() {
+            Index::Aux(id) => match self.bindings[self.resolve(id)] {
+                Binding::Real(var) => var,
+                _ => unreachable!("variables are allocated before they are used"),
+            },
+            Index::Input(_) => var,
+        }
+    }
+
+    fn run(&mut self, op: Op<S>) -> Result<(), SynthesisError> {
+        match op {
+            Op::Alloc(id, annotation) => {
+                let value = self.values[id];
+                let var = self.cs.alloc(
+                    || annotation,
+                    || value.ok_or(SynthesisError::AssignmentMissing),
+                )?;
+                self.bindings[id] = Binding::Real(var);
+            }
+            Op::Enforce(annotation, [a, b, c]) => {
+                let lc = |terms: Vec<(Variable, S)>| {
+                    terms
+                        .into_iter()
+                        .fold(LinearCombination::zero(), |lc, (var, coeff)| {
+                            lc + (coeff, self.real(var))
+                        })
+                };
+                let (a, b, c) = (lc(a), lc(b), lc(c));
+                self.cs.enforce(|| annotation, |_| a, |_| b, |_| c);
+            }
+            Op::Push(name) => self.cs.push_namespace(|| name),
+            Op::Pop => self.cs.pop_namespace(),
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, op: Op<S>) -> Result<(), SynthesisError> {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                frame.ops.push(op);
+                Ok(())
+            }
+            None => self.run(op),
+        }
+    }
+
+    /// Hashes the structure of a namespace, with its own variables numbered
+    /// in order of allocation.
+    fn key(&self, frame: &Frame<S>) -> [u8; 32] {
+        let local = frame
+            .locals
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut state = Blake2sParams::new().personal(b"bellMemo").to_state();
+        for op in &frame.ops {
+            match op {
+                Op::Alloc(..) => {
+                    state.update(&[0]);
+                }
+                Op::Push(_) => {
+                    state.update(&[1]);
+                }
+                Op::Pop => {
+                    state.update(&[2]);
+                }
+                Op::Enforce(_, lcs) => {
+                    state.update(&[3]);
+                    for terms in lcs {
+                        state.update(&(terms.len() as u64).to_be_bytes());
+                        for (var, coeff) in terms {
+                            let (tag, index) = match var.get_unchecked() {
+                                Index::Input(i) => (0, i),
+                                Index::Aux(id) => match local.get(&id) {
+                                    Some(i) => (1, *i),
+                                    None => (2, id),
+                                },
+                            };
+                            state.update(&[tag]);
+                            state.update(&(index as u64).to_be_bytes());
+                            state.update(coeff.to_repr().as_ref());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut key = [0; 32];
+        key.copy_from_slice(state.finalize().as_bytes());
+        key
+    }
+
+    fn close(&mut self, mut frame: Frame<S>) -> Result<(), SynthesisError> {
+        frame.ops.push(Op::Pop);
+
+        if !frame.opaque {
+            let key = self.key(&frame);
+            if let Some(earlier) = self.cache.get(&key) {
+                let earlier = earlier.clone();
+                let differs = frame.locals.iter().zip(&earlier).any(|(&id, &other)| {
+                    match (self.values[id], self.values[self.resolve(other)]) {
+                        (Some(value), Some(other)) => value != other,
+                        _ => false,
+                    }
+                });
+                if differs {
+                    self.error.get_or_insert(SynthesisError::Unsatisfiable);
+                }
+
+                for (&id, &other) in frame.locals.iter().zip(&earlier) {
+                    self.bindings[id] = Binding::Alias(other);
+                }
+                self.stats.namespaces += 1;
+                self.stats.variables += frame.locals.len();
+                self.stats.constraints += frame
+                    .ops
+                    .iter()
+                    .filter(|op| matches!(op, Op::Enforce(..)))
+                    .count();
+                return Ok(());
+            }
+            self.cache.insert(key, frame.locals.clone());
+        }
+
+        match self.frames.last_mut() {
+            Some(parent) => {
+                parent.ops.extend(frame.ops);
+                parent.locals.extend(frame.locals);
+                parent.opaque |= frame.opaque;
+            }
+            None => {
+                for op in frame.ops {
+                    self.run(op)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: PrimeField, CS: ConstraintSystem<S>> ConstraintSystem<S> for Memoize<S, CS> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let value = match f() {
+            Ok(value) => Some(value),
+            Err(SynthesisError::AssignmentMissing) => None,
+            Err(e) => return Err(e),
+        };
+
+        let id = self.bindings.len();
+        self.bindings.push(Binding::Pending);
+        self.values.push(value);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.locals.push(id);
+        }
+        self.emit(Op::Alloc(id, annotation().into()))?;
+        Ok(Variable(Index::Aux(id)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.opaque = true;
+        }
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        let terms = |lc: LinearCombination<S>| {
+            lc.as_ref()
+                .iter()
+                .map(|&(var, coeff)| (self.canonical(var), coeff))
+                .collect::<Vec<_>>()
+        };
+        let lcs = [
+            terms(a(LinearCombination::zero())),
+            terms(b(LinearCombination::zero())),
+            terms(c(LinearCombination::zero())),
+        ];
+
+        // Enforcing only fails if allocating did, which is reported by
+        // `finish`.
+        if let Err(e) = self.emit(Op::Enforce(annotation().into(), lcs)) {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.frames.push(Frame {
+            ops: vec![Op::Push(name_fn().into())],
+            ..Frame::default()
+        });
+    }
+
+    fn pop_namespace(&mut self) {
+        let frame = self.frames.pop().expect("no namespace to pop");
+        if let Err(e) = self.close(frame) {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// A circuit synthesized through [`Memoize`].
+#[derive(Clone)]
+pub struct Memoized<C>(pub C);
+
+impl<S: PrimeField, C: Circuit<S>> Circuit<S> for Memoized<C> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut memo = Memoize::new(cs);
+        self.0.synthesize(&mut memo)?;
+        memo.finish().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    /// Squares `x` `rounds` times, `repeats` times over.
+    struct Squares {
+        x: Option<Scalar>,
+        rounds: usize,
+        repeats: usize,
+        // Perturbs the values of the last repeat.
+        perturb: bool,
+    }
+
+    impl Circuit<Scalar> for Squares {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc_input(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            let mut outputs = vec![];
+            for r in 0..self.repeats {
+                let mut cs = cs.namespace(|| format!("repeat {}", r));
+                let mut value = self.x;
+                let mut current = x;
+                for i in 0..self.rounds {
+                    value = value.map(|v| v.square());
+                    if self.perturb && r == self.repeats - 1 {
+                        value = value.map(|v| v + Scalar::one());
+                    }
+                    let next = cs.alloc(
+                        || format!("square {}", i),
+                        || value.ok_or(SynthesisError::AssignmentMissing),
+                    )?;
+                    cs.enforce(
+                        || format!("square {} constraint", i),
+                        |lc| lc + current,
+                        |lc| lc + current,
+                        |lc| lc + next,
+                    );
+                    current = next;
+                }
+                outputs.push(current);
+            }
+
+            // The outputs are used after their namespaces are closed.
+            for (i, pair) in outputs.windows(2).enumerate() {
+                cs.enforce(
+                    || format!("equal {}", i),
+                    |lc| lc + pair[0],
+                    |lc| lc + CS::one(),
+                    |lc| lc + pair[1],
+                );
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shares_repeated_namespaces() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let squares = |perturb| Squares {
+            x: Some(Scalar::from(3u64)),
+            rounds: 4,
+            repeats: 3,
+            perturb,
+        };
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        squares(false).synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 14);
+
+        let mut memo = Memoize::new(TestConstraintSystem::<Scalar>::new());
+        squares(false).synthesize(&mut memo).unwrap();
+        assert_eq!(
+            memo.stats(),
+            MemoStats {
+                namespaces: 2,
+                variables: 8,
+                constraints: 8,
+            }
+        );
+        let mut cs = memo.finish().unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 6);
+        assert!(cs.get("repeat 0/square 3") == Scalar::from(43_046_721u64));
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        Memoized(squares(false)).synthesize(&mut cs).unwrap();
+        assert_eq!(cs.num_constraints(), 6);
+
+        // Keygen shares the same namespaces without values.
+        let params = generate_random_parameters::<Bls12, _, _>(
+            Memoized(Squares {
+                x: None,
+                ..squares(false)
+            }),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(params.h.len(), 7);
+        let proof = create_random_proof(Memoized(squares(false)), &params, &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(3u64)]).is_ok());
+
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        assert!(matches!(
+            Memoized(squares(true)).synthesize(&mut cs),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+//! Counters and histograms for provers and verifiers.
+//!
+//! With the `metrics` feature enabled, this crate reports the following to
+//! the `Recorder` installed with `set_recorder`:
+//!
+//! - `bellman_proofs_created_total`, a counter of Groth16 proofs created;
+//! - `bellman_verifications_total` and `bellman_verification_failures_total`,
+//!   counters of Groth16 proofs verified, and of those that were rejected;
+//! - `bellman_param_cache_requests_total`, a counter of parameter cache
+//!   lookups labelled with `result="hit"` or `result="miss"`, for caches that
+//!   report them with `record_cache_access`;
+//! - `bellman_phase_seconds`, a histogram of the duration of every
+//!   [`trace`](crate::trace) span, labelled with its `phase`.
+//!
+//! `PrometheusRecorder` keeps them in memory and renders them in the
+//! Prometheus text exposition format, for serving from a `/metrics`
+//! endpoint. Other backends only need to implement `Recorder`.
+
+#[cfg(feature = "metrics")]
+use std::collections::BTreeMap;
+#[cfg(feature = "metrics")]
+use std::fmt::Write;
+#[cfg(feature = "metrics")]
+use std::sync::{Arc, Mutex, RwLock};
+#[cfg(feature = "tracing")]
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use crate::multicore::Global;
+
+/// Metric labels, as `(name, value)` pairs.
+#[cfg(feature = "metrics")]
+pub type Labels = [(&'static str, &'static str)];
+
+/// Receives the metrics recorded by this crate.
+#[cfg(feature = "metrics")]
+pub trait Recorder: Send + Sync {
+    /// Increments the counter `name` by one.
+    fn increment_counter(&self, name: &'static str, labels: &Labels);
+
+    /// Records an observation of the histogram `name`.
+    fn record_histogram(&self, name: &'static str, labels: &Labels, value: f64);
+}
+
+#[cfg(feature = "metrics")]
+impl<R: Recorder> Recorder for Arc<R> {
+    fn increment_counter(&self, name: &'static str, labels: &Labels) {
+        (**self).increment_counter(name, labels)
+    }
+
+    fn record_histogram(&self, name: &'static str, labels: &Labels, value: f64) {
+        (**self).record_histogram(name, labels, value)
+    }
+}
+
+#[cfg(feature = "metrics")]
+static RECORDER: Global<RwLock<Option<Box<dyn Recorder>>>> = Global::new();
+
+/// Installs the recorder for all threads, replacing any previous one.
+#[cfg(feature = "metrics")]
+pub fn set_recorder<R: Recorder + 'static>(recorder: R) {
+    *RECORDER.get().write().unwrap() = Some(Box::new(recorder));
+}
+
+/// Removes the installed recorder, if any.
+#[cfg(feature = "metrics")]
+pub fn clear_recorder() {
+    *RECORDER.get().write().unwrap() = None;
+}
+
+/// Records a lookup in a parameter cache.
+#[cfg(feature = "metrics")]
+pub fn record_cache_access(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    increment("bellman_param_cache_requests_total", &[("result", result)]);
+}
+
+/// Increments a counter, if a recorder is installed.
+#[cfg(feature = "metrics")]
+pub(crate) fn increment(name: &'static str, labels: &Labels) {
+    if let Some(recorder) = RECORDER.get().read().unwrap().as_ref() {
+        recorder.increment_counter(name, labels);
+    }
+}
+
+/// Returns `true` if spans should be timed for the phase histogram.
+#[cfg(all(feature = "metrics", feature = "tracing"))]
+pub(crate) fn enabled() -> bool {
+    RECORDER.get().read().unwrap().is_some()
+}
+
+/// Records the duration of a span.
+#[cfg(all(feature = "metrics", feature = "tracing"))]
+pub(crate) fn record_phase(phase: &'static str, elapsed: Duration) {
+    if let Some(recorder) = RECORDER.get().read().unwrap().as_ref() {
+        recorder.record_histogram(
+            "bellman_phase_seconds",
+            &[("phase", phase)],
+            elapsed.as_secs_f64(),
+        );
+    }
+}
+
+// Without the feature, the call sites record into these stubs, which
+// compile to nothing.
+
+#[cfg(all(feature = "verifier", not(feature = "metrics")))]
+#[inline(always)]
+pub(crate) fn increment(_: &'static str, _: &[(&'static str, &'static str)]) {}
+
+#[cfg(all(feature = "tracing", not(feature = "metrics")))]
+#[inline(always)]
+pub(crate) fn enabled() -> bool {
+    false
+}
+
+#[cfg(all(feature = "tracing", not(feature = "metrics")))]
+#[inline(always)]
+pub(crate) fn record_phase(_: &'static str, _: Duration) {}
+
+/// The upper bounds of the histogram buckets, in seconds.
+#[cfg(feature = "metrics")]
+const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 60.0];
+
+#[cfg(feature = "metrics")]
+type Key = (&'static str, Vec<(&'static str, &'static str)>);
+
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+struct Histogram {
+    // Non-cumulative counts for each bucket, and for values above them all.
+    buckets: [u64; BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+struct Metrics {
+    counters: BTreeMap<Key, u64>,
+    histograms: BTreeMap<Key, Histogram>,
+}
+
+/// A [`Recorder`] that keeps metrics in memory and renders them in the
+/// Prometheus text exposition format.
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+pub struct PrometheusRecorder {
+    metrics: Mutex<Metrics>,
+}
+
+#[cfg(feature = "metrics")]
+impl PrometheusRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current value of a counter.
+    pub fn counter(&self, name: &'static str, labels: &Labels) -> u64 {
+        let metrics = self.metrics.lock().unwrap();
+        metrics
+            .counters
+            .get(&(name, labels.to_vec()))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        fn labels(labels: &[(&str, &str)], extra: Option<(&str, &str)>) -> String {
+            let labels = labels
+                .iter()
+                .cloned()
+                .chain(extra)
+                .map(|(k, v)| format!("{}=\"{}\"", k, v))
+                .collect::<Vec<_>>();
+            if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels.join(","))
+            }
+        }
+
+        let metrics = self.metrics.lock().unwrap();
+        let mut out = String::new();
+        let mut last = None;
+
+        for ((name, l), value) in metrics.counters.iter() {
+            if last != Some(*name) {
+                writeln!(out, "# TYPE {} counter", name).unwrap();
+                last = Some(*name);
+            }
+            writeln!(out, "{}{} {}", name, labels(l, None), value).unwrap();
+        }
+
+        for ((name, l), histogram) in metrics.histograms.iter() {
+            if last != Some(*name) {
+                writeln!(out, "# TYPE {} histogram", name).unwrap();
+                last = Some(*name);
+            }
+            let mut cumulative = 0;
+            for (i, count) in histogram.buckets.iter().enumerate() {
+                cumulative += count;
+                let le = BUCKETS
+                    .get(i)
+                    .map(|b| b.to_string())
+                    .unwrap_or_else(|| "+Inf".to_string());
+                writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    name,
+                    labels(l, Some(("le", &le))),
+                    cumulative
+                )
+                .unwrap();
+            }
+            writeln!(out, "{}_sum{} {}", name, labels(l, None), histogram.sum).unwrap();
+            writeln!(out, "{}_count{} {}", name, labels(l, None), histogram.count).unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Recorder for PrometheusRecorder {
+    fn increment_counter(&self, name: &'static str, labels: &Labels) {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics.counters.entry((name, labels.to_vec())).or_insert(0) += 1;
+    }
+
+    fn record_histogram(&self, name: &'static str, labels: &Labels, value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        let histogram = metrics
+            .histograms
+            .entry((name, labels.to_vec()))
+            .or_default();
+        let bucket = BUCKETS
+            .iter()
+            .position(|b| value <= *b)
+            .unwrap_or(BUCKETS.len());
+        histogram.buckets[bucket] += 1;
+        histogram.sum += value;
+        histogram.count += 1;
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "pairing")]
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    #[cfg(feature = "pairing")]
+    use bls12_381::Scalar;
+
+    #[cfg(feature = "pairing")]
+    struct Square(Option<Scalar>);
+
+    #[cfg(feature = "pairing")]
+    impl Circuit<Scalar> for Square {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || self.0.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    self.0
+                        .map(|x| x.square())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "pairing")]
+    #[test]
+    fn records_proving_and_verification() {
+        use crate::groth16::{
+            create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+        };
+        use bls12_381::Bls12;
+        use rand_core::SeedableRng;
+        use rand_xorshift::XorShiftRng;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = generate_random_parameters::<Bls12, _, _>(Square(None), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        // Other tests may run concurrently, so counts are lower bounds.
+        let recorder = Arc::new(PrometheusRecorder::new());
+        set_recorder(recorder.clone());
+        let proof = create_random_proof(Square(Some(Scalar::from(3))), &params, &mut rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(9)]).is_ok());
+        assert!(verify_proof(&pvk, &proof, &[Scalar::from(8)]).is_err());
+        clear_recorder();
+
+        assert!(recorder.counter("bellman_proofs_created_total", &[]) >= 1);
+        assert!(recorder.counter("bellman_verifications_total", &[]) >= 2);
+        assert!(recorder.counter("bellman_verification_failures_total", &[]) >= 1);
+        assert!(recorder
+            .render()
+            .contains("bellman_phase_seconds_count{phase=\"create_proof\"}"));
+    }
+
+    #[test]
+    fn prometheus_rendering() {
+        let recorder = PrometheusRecorder::new();
+        recorder.increment_counter("bellman_proofs_created_total", &[]);
+        recorder.increment_counter("bellman_proofs_created_total", &[]);
+        recorder.increment_counter("bellman_param_cache_requests_total", &[("result", "hit")]);
+        recorder.record_histogram("bellman_phase_seconds", &[("phase", "fft")], 0.002);
+        recorder.record_histogram("bellman_phase_seconds", &[("phase", "fft")], 100.0);
+
+        assert_eq!(recorder.counter("bellman_proofs_created_total", &[]), 2);
+        assert_eq!(
+            recorder.counter("bellman_param_cache_requests_total", &[("result", "miss")]),
+            0
+        );
+
+        let rendered = recorder.render();
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(
+            &lines[..5],
+            &[
+                "# TYPE bellman_param_cache_requests_total counter",
+                "bellman_param_cache_requests_total{result=\"hit\"} 1",
+                "# TYPE bellman_proofs_created_total counter",
+                "bellman_proofs_created_total 2",
+                "# TYPE bellman_phase_seconds histogram",
+            ]
+        );
+        assert!(lines.contains(&"bellman_phase_seconds_bucket{phase=\"fft\",le=\"0.001\"} 0"));
+        assert!(lines.contains(&"bellman_phase_seconds_bucket{phase=\"fft\",le=\"0.005\"} 1"));
+        assert!(lines.contains(&"bellman_phase_seconds_bucket{phase=\"fft\",le=\"+Inf\"} 2"));
+        assert!(lines.contains(&"bellman_phase_seconds_count{phase=\"fft\"} 2"));
+    }
+}
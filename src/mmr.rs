@@ -0,0 +1,244 @@
+//! An append-only Merkle mountain range over the [Poseidon] hash of
+//! [`crate::poseidon`], which circuits can maintain with the gadgets of
+//! [`crate::gadgets::mmr`].
+//!
+//! The range is a list of perfect binary trees, the peaks, of distinct
+//! heights: after `n` leaves there is a peak of height `h` exactly when bit
+//! `h` of `n` is set. Appending a leaf adds a peak of height zero and merges
+//! peaks of equal height, as adding one carries through the bits of `n`. A
+//! range of depth `d` holds up to `2^d - 1` leaves, so that its state is
+//! always `d` slots of peaks, with zero in the empty ones.
+//!
+//! The [`commitment`](Mmr::commitment) of the range hashes the number of
+//! leaves and the peaks, and changes with every append, so that a sequence
+//! of commitments records the range over successive epochs. A leaf is shown
+//! to be in the range by an [`MmrProof`], its path to one of the peaks.
+//!
+//! Leaves are hashed as messages of one element and nodes as messages of
+//! two, so that the length separates them.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458
+
+use ff::PrimeField;
+
+use crate::poseidon::PoseidonParams;
+
+/// Returns the hash of `leaf`.
+pub fn hash_leaf<S: PrimeField>(params: &PoseidonParams<S>, leaf: S) -> S {
+    params.hash(&[leaf])
+}
+
+/// Returns the hash of the node with children `left` and `right`.
+pub fn hash_node<S: PrimeField>(params: &PoseidonParams<S>, left: S, right: S) -> S {
+    params.hash(&[left, right])
+}
+
+/// Returns the commitment to a range of `len` leaves with the given peaks.
+pub fn commitment<S: PrimeField>(params: &PoseidonParams<S>, len: u64, peaks: &[S]) -> S {
+    let mut message = vec![S::from(len)];
+    message.extend_from_slice(peaks);
+    params.hash(&message)
+}
+
+/// A Merkle mountain range, with every node it has built.
+#[derive(Clone, Debug)]
+pub struct Mmr<S: PrimeField> {
+    params: PoseidonParams<S>,
+    depth: usize,
+    // The nodes of each height, from left to right.
+    levels: Vec<Vec<S>>,
+}
+
+impl<S: PrimeField> Mmr<S> {
+    /// Creates an empty range of the given depth.
+    pub fn new(params: PoseidonParams<S>, depth: usize) -> Self {
+        assert!(depth > 0 && depth < 64, "unsupported depth {}", depth);
+        Mmr {
+            params,
+            depth,
+            levels: vec![vec![]; depth],
+        }
+    }
+
+    pub fn params(&self) -> &PoseidonParams<S> {
+        &self.params
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the maximum number of leaves, `2^depth - 1`.
+    pub fn capacity(&self) -> u64 {
+        (1 << self.depth) - 1
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Appends `leaf`, and returns its index, or `None` if the range is full.
+    pub fn append(&mut self, leaf: S) -> Option<u64> {
+        if self.len() == self.capacity() {
+            return None;
+        }
+
+        let index = self.len();
+        let mut node = hash_leaf(&self.params, leaf);
+        for height in 0..self.depth {
+            self.levels[height].push(node);
+            let level = &self.levels[height];
+            if level.len() % 2 == 1 {
+                break;
+            }
+            node = hash_node(&self.params, level[level.len() - 2], level[level.len() - 1]);
+        }
+        Some(index)
+    }
+
+    /// Returns the peak of each height, or zero where there is none.
+    pub fn peaks(&self) -> Vec<S> {
+        let len = self.len();
+        self.levels
+            .iter()
+            .enumerate()
+            .map(|(height, level)| {
+                if (len >> height) & 1 == 1 {
+                    *level.last().unwrap()
+                } else {
+                    S::zero()
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the commitment to the current state of the range.
+    pub fn commitment(&self) -> S {
+        commitment(&self.params, self.len(), &self.peaks())
+    }
+
+    /// Returns the path from the leaf at `index` to its peak.
+    pub fn proof(&self, index: u64) -> Option<MmrProof<S>> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut siblings = vec![];
+        for (height, level) in self.levels.iter().enumerate() {
+            match level.get(((index >> height) ^ 1) as usize) {
+                Some(sibling) => siblings.push(*sibling),
+                None => break,
+            }
+        }
+        Some(MmrProof { index, siblings })
+    }
+}
+
+/// The path from a leaf to the peak that contains it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MmrProof<S: PrimeField> {
+    /// The index of the leaf.
+    pub index: u64,
+    /// The siblings of the nodes on the path, from the leaf up. Their number
+    /// is the height of the peak.
+    pub siblings: Vec<S>,
+}
+
+impl<S: PrimeField> MmrProof<S> {
+    /// Returns the height of the peak that contains the leaf.
+    pub fn height(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Returns whether bit `height` of the index is set, that is, whether
+    /// the path goes up from a right child at that height.
+    pub fn is_right(&self, height: usize) -> bool {
+        (self.index >> height) & 1 == 1
+    }
+
+    /// Returns the peak that the path leads to from `leaf`.
+    pub fn peak(&self, params: &PoseidonParams<S>, leaf: S) -> S {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(hash_leaf(params, leaf), |node, (height, sibling)| {
+                if self.is_right(height) {
+                    hash_node(params, *sibling, node)
+                } else {
+                    hash_node(params, node, *sibling)
+                }
+            })
+    }
+
+    /// Returns whether the path leads from `leaf` to the peak of its height
+    /// among `peaks`.
+    pub fn verify(&self, params: &PoseidonParams<S>, peaks: &[S], leaf: S) -> bool {
+        peaks
+            .get(self.height())
+            .map_or(false, |peak| *peak == self.peak(params, leaf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::Scalar;
+
+    #[test]
+    fn append_and_prove() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        let mut mmr = Mmr::new(params.clone(), 3);
+        assert_eq!(mmr.capacity(), 7);
+        assert_eq!(mmr.peaks(), vec![Scalar::zero(); 3]);
+
+        let leaves = (1..=7u64).map(Scalar::from).collect::<Vec<_>>();
+        let mut commitments = vec![mmr.commitment()];
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(mmr.append(*leaf), Some(i as u64));
+            commitments.push(mmr.commitment());
+
+            let peaks = mmr.peaks();
+            for (j, leaf) in leaves[..=i].iter().enumerate() {
+                let proof = mmr.proof(j as u64).unwrap();
+                assert_ne!(peaks[proof.height()], Scalar::zero());
+                assert!(proof.verify(&params, &peaks, *leaf));
+                assert!(!proof.verify(&params, &peaks, *leaf + Scalar::one()));
+            }
+            assert!(mmr.proof(i as u64 + 1).is_none());
+        }
+        assert_eq!(mmr.append(Scalar::one()), None);
+
+        // Four leaves make a single peak of height two.
+        let mut four = Mmr::new(params.clone(), 3);
+        for leaf in &leaves[..4] {
+            four.append(*leaf);
+        }
+        let left = hash_node(
+            &params,
+            hash_leaf(&params, leaves[0]),
+            hash_leaf(&params, leaves[1]),
+        );
+        let right = hash_node(
+            &params,
+            hash_leaf(&params, leaves[2]),
+            hash_leaf(&params, leaves[3]),
+        );
+        let peaks = vec![
+            Scalar::zero(),
+            Scalar::zero(),
+            hash_node(&params, left, right),
+        ];
+        assert_eq!(four.peaks(), peaks);
+        assert_eq!(four.commitment(), commitment(&params, 4, &peaks));
+
+        // Every append changes the commitment.
+        for (i, c) in commitments.iter().enumerate() {
+            assert!(!commitments[i + 1..].contains(c));
+        }
+    }
+}
@@ -1,38 +1,171 @@
 //! An interface for dealing with the kinds of parallel computations involved in
-//! `bellman`. It's currently just a thin wrapper around [`CpuPool`] and
-//! [`crossbeam`] but may be extended in the future to allow for various
-//! parallelism strategies.
+//! `bellman`.
 //!
-//! [`CpuPool`]: futures_cpupool::CpuPool
+//! A [`Worker`] runs the jobs of the FFTs, the multiexponentiations and the
+//! other parallel parts of proving and parameter generation on an
+//! [`Executor`]. By default, each worker has a [`CpuPool`] of its own, of a
+//! thread per CPU; [`Worker::with_threads`] bounds its threads, and
+//! [`Worker::with_executor`] runs the jobs on threads the application
+//! already has instead, such as those of an async runtime's blocking pool
+//! or of a rayon pool, with an executor that forwards to it. The worker is
+//! then passed to the functions that take one, such as
+//! [`create_proof_on`] and [`generate_parameters_on`].
+//!
+//! Without the `multicore` feature, every job runs on the calling thread.
+//!
+//! [`CpuPool`]: https://docs.rs/futures-cpupool
+//! [`create_proof_on`]: crate::groth16::create_proof_on
+//! [`generate_parameters_on`]: crate::groth16::generate_parameters_on
+
+#[cfg(any(feature = "groth16", feature = "tracing"))]
+use std::marker::PhantomData;
+#[cfg(any(feature = "groth16", feature = "tracing"))]
+use std::ptr;
+#[cfg(any(feature = "groth16", feature = "tracing"))]
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// Runs the jobs of a [`Worker`] made by [`Worker::with_executor`].
+///
+/// The worker spawns as many jobs at once as the executor has threads. A
+/// job blocks while it waits for the jobs it spawns in turn, but runs them
+/// itself if no thread of the executor is free, so a busy executor only
+/// slows proving down. The futures returned by [`Worker::compute`] resolve
+/// once the executor runs their job, so a thread that waits for one must
+/// not be the only thread of the executor.
+pub trait Executor: Send + Sync {
+    /// The number of threads the jobs run on.
+    fn threads(&self) -> usize;
+
+    /// Runs `job` on one of the threads.
+    fn spawn(&self, job: Box<dyn FnOnce() + Send>);
+}
+
+/// A value shared by all threads, created on first use, for the statics
+/// whose types have no `const` constructor in the oldest Rust we support,
+/// such as locks. The value is never dropped.
+#[cfg(any(feature = "groth16", feature = "tracing"))]
+pub(crate) struct Global<T>(AtomicPtr<T>, PhantomData<T>);
+
+#[cfg(any(feature = "groth16", feature = "tracing"))]
+impl<T> Global<T> {
+    pub(crate) const fn new() -> Self {
+        Global(AtomicPtr::new(ptr::null_mut()), PhantomData)
+    }
+}
+
+#[cfg(any(feature = "groth16", feature = "tracing"))]
+impl<T: Default> Global<T> {
+    pub(crate) fn get(&self) -> &T {
+        let mut value = self.0.load(Ordering::Acquire);
+        if value.is_null() {
+            let new = Box::into_raw(Box::<T>::default());
+            value = match self.0.compare_exchange(
+                ptr::null_mut(),
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new,
+                Err(first) => {
+                    // Another thread created the value first, and ours was
+                    // never shared.
+                    drop(unsafe { Box::from_raw(new) });
+                    first
+                }
+            };
+        }
+        // Once shared, the value lives as long as the static.
+        unsafe { &*value }
+    }
+}
 
 #[cfg(feature = "multicore")]
 mod implementation {
-    use crossbeam::{self, thread::Scope};
-    use futures::{Future, IntoFuture, Poll};
-    use futures_cpupool::{CpuFuture, CpuPool};
+    use std::any::Any;
+    use std::collections::VecDeque;
+    use std::marker::PhantomData;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Condvar, Mutex};
+
+    use futures::sync::oneshot;
+    use futures::{Async, Future, IntoFuture, Poll};
+    use futures_cpupool::CpuPool;
     use num_cpus;
 
+    use super::Executor;
+    use crate::budget::{Budget, ProvingBudget};
+
+    /// The executor of a worker made without one, a pool of its own.
+    struct Pool {
+        pool: CpuPool,
+        threads: usize,
+    }
+
+    impl Executor for Pool {
+        fn threads(&self) -> usize {
+            self.threads
+        }
+
+        fn spawn(&self, job: Box<dyn FnOnce() + Send>) {
+            self.pool
+                .spawn_fn(move || {
+                    job();
+                    Ok::<_, ()>(())
+                })
+                .forget();
+        }
+    }
+
     #[derive(Clone)]
     pub struct Worker {
         cpus: usize,
-        pool: CpuPool,
+        executor: Arc<dyn Executor>,
+        pub(crate) budget: Budget,
+        #[cfg(feature = "gpu")]
+        pub(crate) devices: Option<std::sync::Arc<crate::gpu::Devices>>,
     }
 
     impl Worker {
-        // We don't expose this outside the library so that
-        // all `Worker` instances have the same number of
-        // CPUs configured.
         pub(crate) fn new_with_cpus(cpus: usize) -> Worker {
-            Worker {
-                cpus,
+            Self::with_executor(Arc::new(Pool {
                 pool: CpuPool::new(cpus),
-            }
+                threads: cpus,
+            }))
         }
 
         pub fn new() -> Worker {
             Self::new_with_cpus(num_cpus::get())
         }
 
+        /// Returns a worker on a pool of its own of `threads` threads, or of
+        /// one if `threads` is zero.
+        pub fn with_threads(threads: usize) -> Worker {
+            Self::new_with_cpus(threads.max(1))
+        }
+
+        /// Returns a worker that runs its jobs on `executor`, such as the
+        /// thread pool of the application, rather than on a pool of its own.
+        pub fn with_executor(executor: Arc<dyn Executor>) -> Worker {
+            Worker {
+                cpus: executor.threads().max(1),
+                executor,
+                budget: Budget::default(),
+                #[cfg(feature = "gpu")]
+                devices: None,
+            }
+        }
+
+        /// Returns a worker for one job within `budget`, on its own pool of
+        /// at most `max_threads` threads. The wall time of the budget counts
+        /// from here. See [`budget`](crate::budget).
+        pub fn with_budget(budget: &ProvingBudget) -> Worker {
+            let cpus = num_cpus::get();
+            let cpus = budget.max_threads.map_or(cpus, |max| max.max(1).min(cpus));
+            let mut worker = Self::new_with_cpus(cpus);
+            worker.budget = Budget::new(budget);
+            worker
+        }
+
         pub fn log_num_cpus(&self) -> u32 {
             log2_floor(self.cpus)
         }
@@ -45,11 +178,18 @@ mod implementation {
             R::Item: Send + 'static,
             R::Error: Send + 'static,
         {
-            WorkerFuture {
-                future: self.pool.spawn_fn(f),
-            }
+            let (sender, receiver) = oneshot::channel();
+            self.executor.spawn(Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| f().into_future().wait()));
+                let _ = sender.send(result);
+            }));
+            WorkerFuture { receiver }
         }
 
+        /// Runs `f` with a scope on which to spawn jobs that borrow from the
+        /// caller, and returns once they have all run. The calling thread
+        /// runs the jobs that no thread of the executor has picked up, so
+        /// that scopes nested in the jobs of a busy executor still finish.
         pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
         where
             F: FnOnce(&Scope<'a>, usize) -> R,
@@ -60,14 +200,139 @@ mod implementation {
                 elements / self.cpus
             };
 
-            // TODO: Handle case where threads fail
-            crossbeam::scope(|scope| f(scope, chunk_size))
-                .expect("Threads aren't allowed to fail yet")
+            let scope = Scope {
+                shared: Arc::new(Shared {
+                    state: Mutex::new(State {
+                        queue: VecDeque::new(),
+                        pending: 0,
+                        helpers: 0,
+                        panic: None,
+                    }),
+                    changed: Condvar::new(),
+                    executor: self.executor.clone(),
+                    threads: self.cpus,
+                }),
+                marker: PhantomData,
+            };
+
+            // The jobs may borrow what `f` unwinds, so they are waited for
+            // even if it panics.
+            struct Join<'s>(&'s Arc<Shared>);
+
+            impl<'s> Drop for Join<'s> {
+                fn drop(&mut self) {
+                    Shared::run(self.0, true);
+                }
+            }
+
+            let result = {
+                let _join = Join(&scope.shared);
+                f(&scope, chunk_size)
+            };
+            let panic = scope.shared.state.lock().unwrap().panic.take();
+            if let Some(payload) = panic {
+                panic::resume_unwind(payload);
+            }
+            result
+        }
+    }
+
+    type Job = Box<dyn FnOnce(&Scope<'static>) + Send>;
+
+    struct State {
+        queue: VecDeque<Job>,
+        /// The jobs queued or running.
+        pending: usize,
+        /// The jobs on the executor running queued jobs.
+        helpers: usize,
+        /// The first panic of a job, resumed once they have all run.
+        panic: Option<Box<dyn Any + Send>>,
+    }
+
+    struct Shared {
+        state: Mutex<State>,
+        /// Signalled when a job is queued or the last one finishes.
+        changed: Condvar,
+        executor: Arc<dyn Executor>,
+        threads: usize,
+    }
+
+    impl Shared {
+        /// Runs queued jobs until there are none, or if `wait`, until every
+        /// job has run.
+        fn run(shared: &Arc<Shared>, wait: bool) {
+            let scope = Scope {
+                shared: shared.clone(),
+                marker: PhantomData,
+            };
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if let Some(job) = state.queue.pop_front() {
+                    drop(state);
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| job(&scope)));
+                    state = shared.state.lock().unwrap();
+                    if let Err(payload) = result {
+                        if state.panic.is_none() {
+                            state.panic = Some(payload);
+                        }
+                    }
+                    state.pending -= 1;
+                    if state.pending == 0 {
+                        shared.changed.notify_all();
+                    }
+                } else if wait && state.pending > 0 {
+                    state = shared.changed.wait(state).unwrap();
+                } else {
+                    break;
+                }
+            }
+            if !wait {
+                state.helpers -= 1;
+            }
+        }
+    }
+
+    /// The scope of [`Worker::scope`], on which jobs are spawned.
+    pub struct Scope<'a> {
+        shared: Arc<Shared>,
+        marker: PhantomData<&'a mut &'a ()>,
+    }
+
+    impl<'a> Scope<'a> {
+        /// Queues `f` to run on the executor, or on the thread of the scope.
+        pub fn spawn<F>(&self, f: F)
+        where
+            F: FnOnce(&Scope<'a>) + Send + 'a,
+        {
+            let job: Box<dyn FnOnce(&Scope<'a>) + Send + 'a> = Box::new(f);
+            // SAFETY: `Worker::scope` does not return or unwind until every
+            // job has run, so nothing a job borrows is used after it ends.
+            let job: Job = unsafe { std::mem::transmute(job) };
+
+            let help = {
+                let mut state = self.shared.state.lock().unwrap();
+                state.queue.push_back(job);
+                state.pending += 1;
+                // The thread of the scope is one of the threads.
+                let help = state.helpers + 1 < self.shared.threads;
+                if help {
+                    state.helpers += 1;
+                }
+                help
+            };
+            self.shared.changed.notify_all();
+
+            if help {
+                let shared = self.shared.clone();
+                self.shared
+                    .executor
+                    .spawn(Box::new(move || Shared::run(&shared, false)));
+            }
         }
     }
 
     pub struct WorkerFuture<T, E> {
-        future: CpuFuture<T, E>,
+        receiver: oneshot::Receiver<std::thread::Result<Result<T, E>>>,
     }
 
     impl<T: Send + 'static, E: Send + 'static> Future for WorkerFuture<T, E> {
@@ -75,7 +340,12 @@ mod implementation {
         type Error = E;
 
         fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-            self.future.poll()
+            match self.receiver.poll() {
+                Ok(Async::Ready(Ok(result))) => result.map(Async::Ready),
+                Ok(Async::Ready(Err(payload))) => panic::resume_unwind(payload),
+                Ok(Async::NotReady) => Ok(Async::NotReady),
+                Err(oneshot::Canceled) => panic!("the executor dropped a job of the worker"),
+            }
         }
     }
 
@@ -108,12 +378,51 @@ mod implementation {
 mod implementation {
     use futures::{future, Future, IntoFuture, Poll};
 
+    use crate::budget::{Budget, ProvingBudget};
+
     #[derive(Clone)]
-    pub struct Worker;
+    pub struct Worker {
+        pub(crate) budget: Budget,
+        #[cfg(feature = "gpu")]
+        pub(crate) devices: Option<std::sync::Arc<crate::gpu::Devices>>,
+    }
 
     impl Worker {
+        // Without the feature, there is only ever the calling thread.
+        #[allow(dead_code)]
+        pub(crate) fn new_with_cpus(_cpus: usize) -> Worker {
+            Self::new()
+        }
+
         pub fn new() -> Worker {
-            Worker
+            Worker {
+                budget: Budget::default(),
+                #[cfg(feature = "gpu")]
+                devices: None,
+            }
+        }
+
+        /// Returns a worker, which runs on the calling thread whatever the
+        /// number of `threads`.
+        pub fn with_threads(_threads: usize) -> Worker {
+            Self::new()
+        }
+
+        /// Returns a worker, which runs on the calling thread rather than on
+        /// `executor`.
+        pub fn with_executor(_executor: std::sync::Arc<dyn super::Executor>) -> Worker {
+            Self::new()
+        }
+
+        /// Returns a worker for one job within `budget`, which runs on the
+        /// calling thread whatever its `max_threads`. The wall time of the
+        /// budget counts from here. See [`budget`](crate::budget).
+        pub fn with_budget(budget: &ProvingBudget) -> Worker {
+            Worker {
+                budget: Budget::new(budget),
+                #[cfg(feature = "gpu")]
+                devices: None,
+            }
         }
 
         pub fn log_num_cpus(&self) -> u32 {
@@ -162,3 +471,127 @@ mod implementation {
 }
 
 pub use self::implementation::*;
+
+impl Worker {
+    /// Fails with a [`BudgetExceeded`] error if the job has run out of the
+    /// budget of this worker. The computations that cannot fail, such as
+    /// [`EvaluationDomain::fft`], stop early once it has, and leave their
+    /// output unspecified; callers check here after them.
+    ///
+    /// [`BudgetExceeded`]: crate::budget::BudgetExceeded
+    /// [`EvaluationDomain::fft`]: crate::domain::EvaluationDomain::fft
+    pub fn check(&self) -> Result<(), crate::SynthesisError> {
+        self.budget.check()
+    }
+}
+
+#[cfg(all(test, feature = "multicore"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{channel, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use futures::Future;
+
+    use super::{Executor, Worker};
+
+    /// An application's pool of threads, counting the jobs run on it.
+    struct Threads {
+        sender: Mutex<Sender<Box<dyn FnOnce() + Send>>>,
+        threads: usize,
+        jobs: Arc<AtomicUsize>,
+    }
+
+    impl Threads {
+        fn new(threads: usize) -> Arc<Self> {
+            let (sender, receiver) = channel::<Box<dyn FnOnce() + Send>>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            let jobs = Arc::new(AtomicUsize::new(0));
+            for _ in 0..threads {
+                let receiver = receiver.clone();
+                let jobs = jobs.clone();
+                thread::spawn(move || loop {
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => {
+                            job();
+                            jobs.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(_) => break,
+                    }
+                });
+            }
+            Arc::new(Threads {
+                sender: Mutex::new(sender),
+                threads,
+                jobs,
+            })
+        }
+    }
+
+    impl Executor for Threads {
+        fn threads(&self) -> usize {
+            self.threads
+        }
+
+        fn spawn(&self, job: Box<dyn FnOnce() + Send>) {
+            self.sender.lock().unwrap().send(job).unwrap();
+        }
+    }
+
+    fn sum(worker: &Worker, values: &[u64]) -> u64 {
+        let mut sums = vec![0; values.len()];
+        worker.scope(values.len(), |scope, chunk| {
+            for (sum, values) in sums.chunks_mut(1).zip(values.chunks(chunk)) {
+                scope.spawn(move |_| sum[0] = values.iter().sum());
+            }
+        });
+        sums.iter().sum()
+    }
+
+    #[test]
+    fn external_executor() {
+        let values = (0..1000).collect::<Vec<u64>>();
+        let expected = values.iter().sum::<u64>();
+
+        let threads = Threads::new(4);
+        let worker = Worker::with_executor(threads.clone());
+        assert_eq!(worker.log_num_cpus(), 2);
+        assert_eq!(sum(&worker, &values), expected);
+        assert_eq!(worker.compute(|| Ok::<_, ()>(7)).wait(), Ok(7));
+        assert!(threads.jobs.load(Ordering::SeqCst) > 0);
+        assert_eq!(sum(&Worker::with_threads(3), &values), expected);
+
+        // Scopes nested in every thread of the executor run their jobs on
+        // the threads that wait for them.
+        let threads = Threads::new(2);
+        let worker = Worker::with_executor(threads);
+        let mut sums = [0; 8];
+        worker.scope(sums.len(), |scope, _| {
+            for sum in sums.iter_mut() {
+                let worker = &worker;
+                let values = &values;
+                scope.spawn(move |_| *sum = self::sum(worker, values));
+            }
+        });
+        assert_eq!(sums, [expected; 8]);
+
+        // A panicking job panics the scope once the others have run.
+        let mut ran = [false; 4];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            worker.scope(ran.len(), |scope, _| {
+                for (i, ran) in ran.iter_mut().enumerate() {
+                    scope.spawn(move |_| {
+                        if i == 0 {
+                            panic!("job failed");
+                        }
+                        *ran = true;
+                    });
+                }
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(ran, [false, true, true, true]);
+    }
+}
@@ -15,6 +15,17 @@ pub trait SourceBuilder<G: PrimeCurveAffine>: Send + Sync + 'static + Clone {
     type Source: Source<G>;
 
     fn new(self) -> Self::Source;
+
+    /// Returns the bases as a slice, if they are held in memory, so that
+    /// they can be handed to an accelerator.
+    fn as_slice(&self) -> Option<&[G]> {
+        None
+    }
+
+    /// Returns the number of bases, if it is known before they are read.
+    fn num_bases(&self) -> Option<usize> {
+        self.as_slice().map(<[G]>::len)
+    }
 }
 
 /// A source of bases, like an iterator.
@@ -43,6 +54,10 @@ impl<G: PrimeCurveAffine> SourceBuilder<G> for (Arc<Vec<G>>, usize) {
     fn new(self) -> (Arc<Vec<G>>, usize) {
         (self.0.clone(), self.1)
     }
+
+    fn as_slice(&self) -> Option<&[G]> {
+        self.0.get(self.1..)
+    }
 }
 
 impl<G: PrimeCurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
@@ -97,7 +112,7 @@ impl AsRef<FullDensity> for FullDensity {
     }
 }
 
-impl<'a> QueryDensity for &'a FullDensity {
+impl QueryDensity for &FullDensity {
     type Iter = iter::Repeat<bool>;
 
     fn iter(self) -> Self::Iter {
@@ -109,6 +124,7 @@ impl<'a> QueryDensity for &'a FullDensity {
     }
 }
 
+#[derive(Clone)]
 pub struct DensityTracker {
     bv: BitVec,
 }
@@ -165,8 +181,13 @@ where
         let bases = bases.clone();
         let exponents = exponents.clone();
         let density_map = density_map.clone();
+        let budget = pool.budget.clone();
 
         pool.compute(move || {
+            let mut span = crate::trace::span("multiexp_window");
+            span.record("size", exponents.len());
+            span.record("window", c as usize);
+
             // Accumulate the result
             let mut acc = G::identity();
 
@@ -174,10 +195,18 @@ where
             let mut bases = bases.new();
 
             // Create space for the buckets
+            let _buckets = budget.reserve(((1 << c) - 1) * std::mem::size_of::<G>())?;
             let mut buckets = vec![G::identity(); (1 << c) - 1];
 
             // Sort the bases into buckets
-            for (exp, density) in exponents.iter().zip(density_map.as_ref().iter()) {
+            for (i, (exp, density)) in exponents
+                .iter()
+                .zip(density_map.as_ref().iter())
+                .enumerate()
+            {
+                if i % 1024 == 0 {
+                    budget.check()?;
+                }
                 if density {
                     let (exp_is_zero, exp_is_one) = {
                         let (first, rest) = exp.split_first().unwrap();
@@ -271,12 +300,34 @@ where
     G: PrimeCurve,
     S: SourceBuilder<<G as PrimeCurve>::Affine>,
 {
-    let c = if exponents.len() < 32 {
+    let c = window(exponents.len());
+    multiexp_with_window(pool, bases, density_map, exponents, c)
+}
+
+/// Returns the window size for a multiexponentiation summing `bases` bases.
+pub(crate) fn window(bases: usize) -> u32 {
+    if bases < 32 {
         3u32
     } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
-    };
+        (f64::from(bases as u32)).ln().ceil() as u32
+    }
+}
 
+/// Performs a multi-exponentiation like [`multiexp`], with windows of `c`
+/// bits.
+pub(crate) fn multiexp_with_window<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<BitArray<Lsb0, <G::Scalar as PrimeField>::ReprBits>>>,
+    c: u32,
+) -> Box<dyn Future<Item = G, Error = SynthesisError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: PrimeCurve,
+    S: SourceBuilder<<G as PrimeCurve>::Affine>,
+{
     if let Some(query_size) = density_map.as_ref().get_query_size() {
         // If the density map has a known query size, it should not be
         // inconsistent with the number of exponents.
@@ -284,9 +335,76 @@ where
         assert!(query_size == exponents.len());
     }
 
+    if let Err(e) = pool.check() {
+        return Box::new(futures::future::err(e));
+    }
+
+    #[cfg(feature = "gpu")]
+    {
+        if let Some(result) = offload(pool, &bases, density_map.as_ref(), &exponents) {
+            return Box::new(futures::future::ok(result));
+        }
+    }
+
     multiexp_inner(pool, bases, density_map, exponents, 0, c, true)
 }
 
+/// Runs a multiexponentiation on the devices of `pool`, if it has a kernel
+/// for `G` and the bases are in memory. Inputs that the CPU would reject,
+/// with too few bases or an identity among them, are left to it to report.
+#[cfg(feature = "gpu")]
+fn offload<Q, G, S>(
+    pool: &Worker,
+    bases: &S,
+    density_map: &Q,
+    exponents: &[BitArray<Lsb0, <G::Scalar as PrimeField>::ReprBits>],
+) -> Option<G>
+where
+    for<'a> &'a Q: QueryDensity,
+    G: PrimeCurve,
+    S: SourceBuilder<<G as PrimeCurve>::Affine>,
+{
+    let devices = pool.devices()?;
+    let bases = bases.as_slice()?;
+
+    let exponents = exponents
+        .iter()
+        .zip(density_map.iter())
+        .filter(|(_, density)| *density)
+        .map(|(exp, _)| exp)
+        .collect::<Vec<_>>();
+    let bases = bases.get(..exponents.len())?;
+    if bases.iter().any(|base| bool::from(base.is_identity())) {
+        return None;
+    }
+
+    devices.multiexp(bases, &exponents)
+}
+
+/// Computes `sum(bases[i] * scalars[i])` over slices of bases and scalars,
+/// skipping any bases that are the identity (which [`multiexp`] rejects).
+pub(crate) fn dense_multiexp<G: PrimeCurve>(
+    pool: &Worker,
+    bases: &[G::Affine],
+    scalars: &[G::Scalar],
+) -> Result<G, SynthesisError> {
+    let mut span = crate::trace::span("dense_multiexp");
+    span.record("size", bases.len());
+
+    let (bases, exponents): (Vec<_>, Vec<_>) = bases
+        .iter()
+        .zip(scalars.iter())
+        .filter(|(base, _)| !bool::from(base.is_identity()))
+        .map(|(base, scalar)| (*base, scalar.to_le_bits()))
+        .unzip();
+
+    if bases.is_empty() {
+        return Ok(G::identity());
+    }
+
+    multiexp(pool, (Arc::new(bases), 0), FullDensity, Arc::new(exponents)).wait()
+}
+
 #[cfg(feature = "pairing")]
 #[test]
 fn test_with_bls12() {
@@ -0,0 +1,375 @@
+//! A native implementation of the [Poseidon] permutation and sponge over any
+//! [`PrimeField`].
+//!
+//! Round constants are generated with the Grain LFSR described in the paper,
+//! and the MDS matrix is the Cauchy matrix `M[i][j] = 1 / (i + (t + j))`. The
+//! S-box exponent is the smallest `alpha` in `{3, 5, 7, 11, 13, 17}` for which
+//! `x^alpha` is a permutation of the field.
+//!
+//! [Poseidon]: https://eprint.iacr.org/2019/458
+
+use ff::PrimeField;
+
+/// Parameters of a Poseidon permutation of width `t`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoseidonParams<S: PrimeField> {
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    alpha: u64,
+    round_constants: Vec<Vec<S>>,
+    mds: Vec<Vec<S>>,
+}
+
+impl<S: PrimeField> PoseidonParams<S> {
+    /// Generates parameters for a permutation of the given width with the
+    /// given number of full and partial rounds.
+    ///
+    /// Panics if `width < 2`, if `full_rounds` is odd, or if no supported
+    /// S-box exponent is coprime to `p - 1`.
+    pub fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(width >= 2, "Poseidon width must be at least 2");
+        assert!(full_rounds & 1 == 0, "full rounds must be split evenly");
+
+        let alpha = [3, 5, 7, 11, 13, 17]
+            .iter()
+            .cloned()
+            .find(|alpha| modulus_minus_one_mod::<S>(*alpha) != 0)
+            .expect("no supported S-box exponent for this field");
+
+        let mut grain = Grain::new::<S>(width, full_rounds, partial_rounds);
+        let round_constants = (0..(full_rounds + partial_rounds))
+            .map(|_| (0..width).map(|_| grain.next_field_element()).collect())
+            .collect();
+
+        let mds = (0..width)
+            .map(|i| {
+                (0..width)
+                    // The entries of the Cauchy matrix are nonzero.
+                    .map(|j| S::from((i + width + j) as u64).invert().unwrap())
+                    .collect()
+            })
+            .collect();
+
+        PoseidonParams {
+            width,
+            full_rounds,
+            partial_rounds,
+            alpha,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Returns parameters for the given width with the round numbers that the
+    /// Poseidon paper recommends for 128-bit security over ~255-bit fields
+    /// with `x^5`, such as the BLS12-381 scalar field. Supports widths 2 to 8.
+    pub fn for_width(width: usize) -> Self {
+        let partial_rounds = match width {
+            2 => 56,
+            3 => 57,
+            4 => 56,
+            5 => 60,
+            6 => 60,
+            7 => 63,
+            8 => 64,
+            _ => panic!("no recommended round numbers for width {}", width),
+        };
+
+        Self::new(width, 8, partial_rounds)
+    }
+
+    /// Returns the width `t` of the permutation.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    /// Returns the S-box exponent.
+    pub fn alpha(&self) -> u64 {
+        self.alpha
+    }
+
+    /// Returns the round constants, one row of `width` elements per round.
+    pub fn round_constants(&self) -> &[Vec<S>] {
+        &self.round_constants
+    }
+
+    /// Returns the `width * width` MDS matrix.
+    pub fn mds(&self) -> &[Vec<S>] {
+        &self.mds
+    }
+
+    /// Returns whether round `round` applies the S-box to the full state.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        let half = self.full_rounds / 2;
+        round < half || round >= half + self.partial_rounds
+    }
+
+    /// Applies the permutation to `state` in place.
+    pub fn permute(&self, state: &mut [S]) {
+        assert_eq!(state.len(), self.width);
+
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            for (s, c) in state.iter_mut().zip(constants.iter()) {
+                s.add_assign(c);
+            }
+
+            if self.is_full_round(round) {
+                for s in state.iter_mut() {
+                    *s = s.pow_vartime([self.alpha]);
+                }
+            } else {
+                state[0] = state[0].pow_vartime([self.alpha]);
+            }
+
+            let mixed = self
+                .mds
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .zip(state.iter())
+                        .fold(S::zero(), |acc, (m, s)| acc + *m * s)
+                })
+                .collect::<Vec<_>>();
+            state.copy_from_slice(&mixed);
+        }
+    }
+
+    /// Hashes a fixed-length message with a sponge of rate `width - 1`,
+    /// using the message length as the domain separator.
+    pub fn hash(&self, message: &[S]) -> S {
+        let mut sponge = Sponge::new(self.clone(), S::from(message.len() as u64));
+        for m in message {
+            sponge.absorb(*m);
+        }
+        sponge.squeeze()
+    }
+}
+
+/// A duplex sponge over the Poseidon permutation with rate `width - 1` and a
+/// single capacity element.
+#[derive(Clone, Debug)]
+pub struct Sponge<S: PrimeField> {
+    params: PoseidonParams<S>,
+    state: Vec<S>,
+    absorbed: usize,
+    squeezed: Option<usize>,
+}
+
+impl<S: PrimeField> Sponge<S> {
+    /// Creates a sponge whose capacity element is initialized with
+    /// `domain`, which separates unrelated uses of the same parameters.
+    pub fn new(params: PoseidonParams<S>, domain: S) -> Self {
+        let mut state = vec![S::zero(); params.width];
+        state[0] = domain;
+
+        Sponge {
+            params,
+            state,
+            absorbed: 0,
+            squeezed: None,
+        }
+    }
+
+    fn rate(&self) -> usize {
+        self.params.width - 1
+    }
+
+    pub fn absorb(&mut self, value: S) {
+        if self.absorbed == self.rate() || self.squeezed.is_some() {
+            self.params.permute(&mut self.state);
+            self.absorbed = 0;
+            self.squeezed = None;
+        }
+
+        self.state[1 + self.absorbed].add_assign(&value);
+        self.absorbed += 1;
+    }
+
+    pub fn squeeze(&mut self) -> S {
+        let position = match self.squeezed {
+            Some(position) if position < self.rate() => position,
+            _ => {
+                self.params.permute(&mut self.state);
+                self.absorbed = 0;
+                0
+            }
+        };
+
+        self.squeezed = Some(position + 1);
+        self.state[1 + position]
+    }
+}
+
+/// Returns `(p - 1) mod m`, computed from the bits of the modulus.
+fn modulus_minus_one_mod<S: PrimeField>(m: u64) -> u64 {
+    let p_mod = modulus_bits_be::<S>()
+        .into_iter()
+        .fold(0u64, |acc, bit| (acc * 2 + (bit as u64)) % m);
+
+    (p_mod + m - 1) % m
+}
+
+/// Returns the `NUM_BITS` bits of the modulus, most significant first.
+fn modulus_bits_be<S: PrimeField>() -> Vec<bool> {
+    let mut bits = S::char_le_bits()
+        .iter()
+        .take(S::NUM_BITS as usize)
+        .copied()
+        .collect::<Vec<_>>();
+    bits.reverse();
+    bits
+}
+
+/// The Grain LFSR used by the Poseidon reference implementation to derive round
+/// constants.
+struct Grain {
+    state: [bool; 80],
+}
+
+impl Grain {
+    fn new<S: PrimeField>(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut bits = Vec::with_capacity(80);
+        let mut push = |value: u64, n: usize| {
+            for i in (0..n).rev() {
+                bits.push((value >> i) & 1 == 1);
+            }
+        };
+
+        // Prime field, x^alpha S-box.
+        push(1, 2);
+        push(0, 4);
+        push(u64::from(S::NUM_BITS), 12);
+        push(width as u64, 12);
+        push(full_rounds as u64, 10);
+        push(partial_rounds as u64, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut state = [false; 80];
+        state.copy_from_slice(&bits);
+
+        let mut grain = Grain { state };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let s = &self.state;
+        let new = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.copy_within(1.., 0);
+        self.state[79] = new;
+        new
+    }
+
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let first = self.step();
+            let second = self.step();
+            if first {
+                return second;
+            }
+        }
+    }
+
+    /// Samples `NUM_BITS` bits (most significant first) and rejects values
+    /// that are not smaller than the modulus.
+    fn next_field_element<S: PrimeField>(&mut self) -> S {
+        let modulus = modulus_bits_be::<S>();
+        let num_bits = S::NUM_BITS as usize;
+
+        loop {
+            let bits = (0..num_bits).map(|_| self.next_bit()).collect::<Vec<_>>();
+
+            // Compare with the modulus, most significant bit first.
+            let less = bits
+                .iter()
+                .zip(modulus.iter())
+                .find(|(a, b)| a != b)
+                .map(|(a, _)| !*a)
+                .unwrap_or(false);
+
+            if less {
+                let two = S::from(2);
+                return bits.iter().fold(S::zero(), |mut acc, bit| {
+                    acc.mul_assign(&two);
+                    if *bit {
+                        acc.add_assign(&S::one());
+                    }
+                    acc
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::Scalar;
+    use ff::Field;
+
+    #[test]
+    fn parameters() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        assert_eq!(params.alpha(), 5);
+        assert_eq!(params.round_constants().len(), 65);
+        assert_eq!(params.mds().len(), 3);
+        assert!(params == PoseidonParams::<Scalar>::for_width(3));
+        assert!(params != PoseidonParams::<Scalar>::new(3, 8, 56));
+
+        assert!(params.is_full_round(3));
+        assert!(!params.is_full_round(4));
+        assert!(!params.is_full_round(60));
+        assert!(params.is_full_round(61));
+    }
+
+    #[test]
+    fn permutation_is_not_trivial() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+
+        let mut a = vec![Scalar::zero(); 3];
+        params.permute(&mut a);
+        assert!(a.iter().all(|s| !s.is_zero()));
+
+        let mut b = vec![Scalar::zero(), Scalar::one(), Scalar::zero()];
+        params.permute(&mut b);
+        assert!(a != b);
+    }
+
+    #[test]
+    fn hash_depends_on_message_and_length() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        let one = Scalar::one();
+
+        let h1 = params.hash(&[one, one]);
+        assert_eq!(h1, params.hash(&[one, one]));
+        assert!(h1 != params.hash(&[one, one.double()]));
+        assert!(h1 != params.hash(&[one, one, Scalar::zero()]));
+        assert!(params.hash(&[]) != params.hash(&[Scalar::zero()]));
+    }
+
+    #[test]
+    fn sponge_squeezes_distinct_values() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        let mut sponge = Sponge::new(params, Scalar::zero());
+        sponge.absorb(Scalar::one());
+
+        let outputs = (0..5).map(|_| sponge.squeeze()).collect::<Vec<_>>();
+        for i in 0..outputs.len() {
+            for j in (i + 1)..outputs.len() {
+                assert!(outputs[i] != outputs[j]);
+            }
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! A common interface to the proof systems of this crate.
+//!
+//! [`ProofSystem`] describes a proof system by its setup, proving and
+//! verification, and the encodings of its keys and proofs, so that code
+//! written against it can switch between [`Groth16`] and [`Sonic`] with a
+//! type parameter. [`Backend`] names the proof systems at runtime, for
+//! tools that choose one from a configuration file or a command-line flag,
+//! and exchanges keys and proofs as bytes.
+//!
+//! For Sonic, the setup generates a reference string just large enough for
+//! the circuit. A reference string shared between circuits can be used by
+//! building the keys directly instead.
+
+use ff::PrimeField;
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use crate::{Circuit, SynthesisError, VerificationError};
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+use crate::error::Error;
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+use group::WnafGroup;
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+use pairing::MultiMillerLoop;
+
+/// A proof system for circuits over `Self::Scalar`.
+pub trait ProofSystem {
+    type Scalar: PrimeField;
+    type ProvingKey;
+    type VerifyingKey;
+    type Proof;
+
+    /// The name of the proof system, as accepted by [`Backend::from_name`].
+    const NAME: &'static str;
+
+    /// Generates the keys for `circuit`, which is synthesized without a
+    /// witness.
+    fn setup<C: Circuit<Self::Scalar>, R: RngCore>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), SynthesisError>;
+
+    /// Proves that `circuit` is satisfied by its witness.
+    fn prove<C: Circuit<Self::Scalar>, R: RngCore>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Self::Proof, SynthesisError>;
+
+    /// Verifies `proof` against the public inputs of the circuit, excluding
+    /// `ONE`.
+    fn verify(
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        public_inputs: &[Self::Scalar],
+    ) -> Result<(), VerificationError>;
+
+    fn write_proving_key<W: Write>(pk: &Self::ProvingKey, writer: W) -> io::Result<()>;
+
+    fn read_proving_key<R: Read>(reader: R) -> io::Result<Self::ProvingKey>;
+
+    fn write_verifying_key<W: Write>(vk: &Self::VerifyingKey, writer: W) -> io::Result<()>;
+
+    fn read_verifying_key<R: Read>(reader: R) -> io::Result<Self::VerifyingKey>;
+
+    fn write_proof<W: Write>(proof: &Self::Proof, writer: W) -> io::Result<()>;
+
+    fn read_proof<R: Read>(reader: R) -> io::Result<Self::Proof>;
+}
+
+/// The [Groth16] proof system over `E`, with a circuit-specific setup.
+///
+/// [Groth16]: crate::groth16
+pub struct Groth16<E>(PhantomData<E>);
+
+#[cfg(feature = "groth16")]
+impl<E> ProofSystem for Groth16<E>
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+{
+    type Scalar = E::Fr;
+    type ProvingKey = crate::groth16::Parameters<E>;
+    type VerifyingKey = crate::groth16::VerifyingKey<E>;
+    type Proof = crate::groth16::Proof<E>;
+
+    const NAME: &'static str = "groth16";
+
+    fn setup<C: Circuit<E::Fr>, R: RngCore>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), SynthesisError> {
+        let params = crate::groth16::generate_random_parameters::<E, _, _>(circuit, rng)?;
+        let vk = params.vk.clone();
+        Ok((params, vk))
+    }
+
+    fn prove<C: Circuit<E::Fr>, R: RngCore>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Self::Proof, SynthesisError> {
+        crate::groth16::create_random_proof(circuit, pk, rng)
+    }
+
+    fn verify(
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        public_inputs: &[E::Fr],
+    ) -> Result<(), VerificationError> {
+        let pvk = crate::groth16::prepare_verifying_key(vk);
+        crate::groth16::verify_proof(&pvk, proof, public_inputs)
+    }
+
+    fn write_proving_key<W: Write>(pk: &Self::ProvingKey, writer: W) -> io::Result<()> {
+        pk.write(writer)
+    }
+
+    fn read_proving_key<R: Read>(reader: R) -> io::Result<Self::ProvingKey> {
+        crate::groth16::Parameters::read(reader, true)
+    }
+
+    fn write_verifying_key<W: Write>(vk: &Self::VerifyingKey, writer: W) -> io::Result<()> {
+        vk.write(writer)
+    }
+
+    fn read_verifying_key<R: Read>(reader: R) -> io::Result<Self::VerifyingKey> {
+        crate::groth16::VerifyingKey::read(reader)
+    }
+
+    fn write_proof<W: Write>(proof: &Self::Proof, writer: W) -> io::Result<()> {
+        proof.write(writer)
+    }
+
+    fn read_proof<R: Read>(reader: R) -> io::Result<Self::Proof> {
+        crate::groth16::Proof::read(reader)
+    }
+}
+
+/// The [Sonic] proof system over `E`, with a universal reference string.
+///
+/// [Sonic]: crate::sonic
+pub struct Sonic<E>(PhantomData<E>);
+
+/// The verifying key of [`Sonic`]: the reference string, and the structure
+/// of the circuit that the verifier evaluates.
+#[cfg(feature = "sonic")]
+#[derive(Clone)]
+pub struct SonicVerifyingKey<E: pairing::Engine> {
+    pub srs: crate::sonic::SRS<E>,
+    pub circuit: crate::sonic::AdaptedCircuit<E::Fr>,
+}
+
+#[cfg(feature = "sonic")]
+impl<E> ProofSystem for Sonic<E>
+where
+    E: MultiMillerLoop,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+{
+    type Scalar = E::Fr;
+    type ProvingKey = crate::sonic::SRS<E>;
+    type VerifyingKey = SonicVerifyingKey<E>;
+    type Proof = crate::sonic::Proof<E>;
+
+    const NAME: &'static str = "sonic";
+
+    fn setup<C: Circuit<E::Fr>, R: RngCore>(
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<(Self::ProvingKey, Self::VerifyingKey), SynthesisError> {
+        let circuit = crate::sonic::AdaptedCircuit::new(circuit)?;
+        let srs = crate::sonic::SRS::random(circuit.required_srs_degree(), rng)?;
+        Ok((srs.clone(), SonicVerifyingKey { srs, circuit }))
+    }
+
+    fn prove<C: Circuit<E::Fr>, R: RngCore>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut R,
+    ) -> Result<Self::Proof, SynthesisError> {
+        crate::sonic::create_proof(circuit, pk, rng)
+    }
+
+    fn verify(
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+        public_inputs: &[E::Fr],
+    ) -> Result<(), VerificationError> {
+        crate::sonic::verify_proof(&vk.srs, &vk.circuit, proof, public_inputs)
+    }
+
+    fn write_proving_key<W: Write>(pk: &Self::ProvingKey, writer: W) -> io::Result<()> {
+        pk.write(writer)
+    }
+
+    fn read_proving_key<R: Read>(reader: R) -> io::Result<Self::ProvingKey> {
+        crate::sonic::SRS::read(reader)
+    }
+
+    fn write_verifying_key<W: Write>(vk: &Self::VerifyingKey, mut writer: W) -> io::Result<()> {
+        vk.srs.write(&mut writer)?;
+        vk.circuit.write(&mut writer)
+    }
+
+    fn read_verifying_key<R: Read>(mut reader: R) -> io::Result<Self::VerifyingKey> {
+        Ok(SonicVerifyingKey {
+            srs: crate::sonic::SRS::read(&mut reader)?,
+            circuit: crate::sonic::AdaptedCircuit::read(&mut reader)?,
+        })
+    }
+
+    fn write_proof<W: Write>(proof: &Self::Proof, writer: W) -> io::Result<()> {
+        proof.write(writer)
+    }
+
+    fn read_proof<R: Read>(reader: R) -> io::Result<Self::Proof> {
+        crate::sonic::Proof::read(reader)
+    }
+}
+
+/// The proof systems of this crate, chosen at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    #[cfg(feature = "groth16")]
+    Groth16,
+    #[cfg(feature = "sonic")]
+    Sonic,
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+fn setup_bytes<P: ProofSystem, C: Circuit<P::Scalar>, R: RngCore>(
+    circuit: C,
+    rng: &mut R,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let (pk, vk) = P::setup(circuit, rng)?;
+    let (mut pk_bytes, mut vk_bytes) = (vec![], vec![]);
+    P::write_proving_key(&pk, &mut pk_bytes)?;
+    P::write_verifying_key(&vk, &mut vk_bytes)?;
+    Ok((pk_bytes, vk_bytes))
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+fn prove_bytes<P: ProofSystem, C: Circuit<P::Scalar>, R: RngCore>(
+    pk: &[u8],
+    circuit: C,
+    rng: &mut R,
+) -> Result<Vec<u8>, Error> {
+    let pk = P::read_proving_key(pk)?;
+    let proof = P::prove(&pk, circuit, rng)?;
+    let mut bytes = vec![];
+    P::write_proof(&proof, &mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+fn verify_bytes<P: ProofSystem>(
+    vk: &[u8],
+    proof: &[u8],
+    public_inputs: &[P::Scalar],
+) -> Result<(), Error> {
+    let vk = P::read_verifying_key(vk)?;
+    let proof = P::read_proof(proof)?;
+    Ok(P::verify(&vk, &proof, public_inputs)?)
+}
+
+impl Backend {
+    /// Returns every proof system that this build supports.
+    pub fn all() -> &'static [Backend] {
+        &[
+            #[cfg(feature = "groth16")]
+            Backend::Groth16,
+            #[cfg(feature = "sonic")]
+            Backend::Sonic,
+        ]
+    }
+
+    /// Returns the proof system with the given [`ProofSystem::NAME`], if
+    /// this build supports it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Backend::all().iter().copied().find(|b| b.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "groth16")]
+            Backend::Groth16 => "groth16",
+            #[cfg(feature = "sonic")]
+            Backend::Sonic => "sonic",
+        }
+    }
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+impl Backend {
+    /// Generates keys for `circuit` with this proof system over `E`, and
+    /// returns the encodings of the proving and verifying keys.
+    pub fn setup<E, C, R>(&self, circuit: C, rng: &mut R) -> Result<(Vec<u8>, Vec<u8>), Error>
+    where
+        E: MultiMillerLoop,
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+        C: Circuit<E::Fr>,
+        R: RngCore,
+    {
+        match *self {
+            #[cfg(feature = "groth16")]
+            Backend::Groth16 => setup_bytes::<Groth16<E>, _, _>(circuit, rng),
+            #[cfg(feature = "sonic")]
+            Backend::Sonic => setup_bytes::<Sonic<E>, _, _>(circuit, rng),
+        }
+    }
+
+    /// Proves `circuit` with the encoded proving key, and returns the
+    /// encoding of the proof.
+    pub fn prove<E, C, R>(&self, pk: &[u8], circuit: C, rng: &mut R) -> Result<Vec<u8>, Error>
+    where
+        E: MultiMillerLoop,
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+        C: Circuit<E::Fr>,
+        R: RngCore,
+    {
+        match *self {
+            #[cfg(feature = "groth16")]
+            Backend::Groth16 => prove_bytes::<Groth16<E>, _, _>(pk, circuit, rng),
+            #[cfg(feature = "sonic")]
+            Backend::Sonic => prove_bytes::<Sonic<E>, _, _>(pk, circuit, rng),
+        }
+    }
+
+    /// Verifies the encoded proof with the encoded verifying key.
+    pub fn verify<E>(&self, vk: &[u8], proof: &[u8], public_inputs: &[E::Fr]) -> Result<(), Error>
+    where
+        E: MultiMillerLoop,
+        E::G1: WnafGroup,
+        E::G2: WnafGroup,
+    {
+        match *self {
+            #[cfg(feature = "groth16")]
+            Backend::Groth16 => verify_bytes::<Groth16<E>>(vk, proof, public_inputs),
+            #[cfg(feature = "sonic")]
+            Backend::Sonic => verify_bytes::<Sonic<E>>(vk, proof, public_inputs),
+        }
+    }
+}
+
+#[cfg(all(feature = "groth16", feature = "sonic"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::groth16::exporter::ReplayCircuit;
+    use crate::groth16::fuzz::{random_circuit, CircuitConfig};
+    use crate::groth16::tests::fixture::rng;
+    use bls12_381::{Bls12, Scalar};
+
+    fn round_trip<P: ProofSystem<Scalar = Scalar>>() {
+        let mut rng = rng();
+        let (circuit, witness) = random_circuit::<Scalar, _>(&CircuitConfig::default(), &mut rng);
+        let replay = |assignment| ReplayCircuit {
+            circuit: circuit.clone(),
+            assignment,
+        };
+        let inputs = &witness.inputs[1..];
+
+        let (pk, vk) = P::setup(replay(None), &mut rng).unwrap();
+        let proof = P::prove(&pk, replay(Some(witness.clone())), &mut rng).unwrap();
+        assert!(P::verify(&vk, &proof, inputs).is_ok());
+
+        let mut bytes = vec![];
+        P::write_verifying_key(&vk, &mut bytes).unwrap();
+        let vk = P::read_verifying_key(&bytes[..]).unwrap();
+        let mut bytes = vec![];
+        P::write_proof(&proof, &mut bytes).unwrap();
+        let proof = P::read_proof(&bytes[..]).unwrap();
+        assert!(P::verify(&vk, &proof, inputs).is_ok());
+
+        let mut wrong = inputs.to_vec();
+        wrong[0] += Scalar::one();
+        assert!(P::verify(&vk, &proof, &wrong).is_err());
+
+        // The same flow, with the proof system chosen at runtime.
+        let backend = Backend::from_name(P::NAME).unwrap();
+        let (pk, vk) = backend
+            .setup::<Bls12, _, _>(replay(None), &mut rng)
+            .unwrap();
+        let proof = backend
+            .prove::<Bls12, _, _>(&pk, replay(Some(witness.clone())), &mut rng)
+            .unwrap();
+        assert!(backend.verify::<Bls12>(&vk, &proof, inputs).is_ok());
+        assert_eq!(
+            backend
+                .verify::<Bls12>(&vk, &proof, &wrong)
+                .err()
+                .map(|e| e.kind()),
+            Some(ErrorKind::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn proof_systems() {
+        round_trip::<Groth16<Bls12>>();
+        round_trip::<Sonic<Bls12>>();
+
+        assert_eq!(Backend::all(), &[Backend::Groth16, Backend::Sonic]);
+        assert_eq!(Backend::from_name("sonic"), Some(Backend::Sonic));
+        assert_eq!(Backend::from_name("plonk"), None);
+    }
+}
@@ -0,0 +1,17 @@
+//! Ready-made circuits for protocols built from the gadgets of this crate,
+//! with the native code that produces their inputs.
+
+use rand_core::RngCore;
+
+pub mod credentials;
+pub mod shuffle;
+
+/// Returns `n` random bits, as the little-endian bits of secret scalars.
+fn random_bits<R: RngCore>(rng: &mut R, n: usize) -> Vec<bool> {
+    let mut bits = Vec::with_capacity(n);
+    while bits.len() < n {
+        let word = rng.next_u64();
+        bits.extend((0..64).map(|i| (word >> i) & 1 == 1).take(n - bits.len()));
+    }
+    bits
+}
@@ -0,0 +1,500 @@
+//! Credentials with selective disclosure of their attributes.
+//!
+//! An [`Issuer`] signs a list of attributes, such as a date of birth and a
+//! country, into a [`Credential`] for its holder. The holder then proves,
+//! with a [`DisclosureCircuit`], a [`Statement`] about the credential:
+//! that it was signed by one of the issuers of a registry, that some of its
+//! attributes have the values it discloses, and that others are at least
+//! some public thresholds, without revealing the other attributes, the
+//! signature, or which issuer signed it. Proving that an age is over `N`
+//! is proving that it is at least `N + 1`.
+//!
+//! The circuit composes the gadgets of this crate:
+//!
+//! - the attributes are hashed into the signed message with [`poseidon`];
+//! - the signature is checked with the [`ecc`] gadgets over an
+//!   [`EmbeddedCurve`];
+//! - the key of the issuer is a leaf of a registry, a Merkle mountain range
+//!   of [`crate::mmr`] whose commitment is a public input, checked with
+//!   [`MmrState::enforce_membership`];
+//! - thresholds are compared with [`RangeChecks`].
+//!
+//! Signatures are Schnorr signatures whose challenge is a Poseidon hash and
+//! whose response is not reduced modulo the order of the curve, as in the
+//! scheme of Girault, Poupard and Stern: the nonce is wider than the product
+//! of the challenge and the secret key by [`HIDING_BITS`], which hides the
+//! key, so that neither side needs that order.
+//!
+//! [`poseidon`]: crate::gadgets::poseidon
+//! [`ecc`]: crate::gadgets::ecc
+
+use ff::PrimeField;
+use rand_core::RngCore;
+use std::marker::PhantomData;
+
+use super::random_bits;
+use crate::gadgets::boolean::{AllocatedBit, Boolean};
+use crate::gadgets::ecc::{EdwardsPoint, EmbeddedCurve};
+use crate::gadgets::mmr::MmrState;
+use crate::gadgets::num::AllocatedNum;
+use crate::gadgets::poseidon;
+use crate::gadgets::range::RangeChecks;
+use crate::gadgets::Assignment;
+use crate::mmr::Mmr;
+use crate::poseidon::PoseidonParams;
+use crate::{Circuit, ConstraintSystem, SynthesisError};
+
+/// The number of bits of a secret key.
+pub const SECRET_BITS: usize = 256;
+
+/// The number of bits by which the nonce of a signature is wider than the
+/// product of its challenge and the secret key.
+pub const HIDING_BITS: usize = 128;
+
+/// Returns the number of bits of the response of a signature.
+pub fn response_bits<S: PrimeField>() -> usize {
+    SECRET_BITS + S::NUM_BITS as usize + HIDING_BITS + 1
+}
+
+fn to_limbs(bits: &[bool]) -> Vec<u64> {
+    bits.chunks(64)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |limb, (i, bit)| limb | (*bit as u64) << i)
+        })
+        .collect()
+}
+
+/// Returns the little-endian bits of `k + a * b`.
+fn mul_add(k: &[bool], a: &[bool], b: &[bool]) -> Vec<bool> {
+    let (a, b) = (to_limbs(a), to_limbs(b));
+    let mut result = to_limbs(k);
+    result.resize(std::cmp::max(result.len(), a.len() + b.len()) + 1, 0);
+    for (i, a) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, b) in b.iter().enumerate() {
+            let t = result[i + j] as u128 + *a as u128 * *b as u128 + carry;
+            result[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        let mut index = i + b.len();
+        while carry != 0 {
+            let t = result[index] as u128 + carry;
+            result[index] = t as u64;
+            carry = t >> 64;
+            index += 1;
+        }
+    }
+    result
+        .iter()
+        .flat_map(|limb| (0..64).map(move |i| (limb >> i) & 1 == 1))
+        .collect()
+}
+
+fn field_bits<S: PrimeField>(value: S) -> Vec<bool> {
+    value
+        .to_le_bits()
+        .iter()
+        .take(S::NUM_BITS as usize)
+        .copied()
+        .collect()
+}
+
+/// Returns the message that the signature of a credential signs: the
+/// Poseidon hash of its attributes.
+pub fn message<S: PrimeField>(params: &PoseidonParams<S>, attributes: &[S]) -> S {
+    params.hash(attributes)
+}
+
+/// Returns the challenge of a signature by `key` of `message` with the
+/// commitment `r`.
+fn challenge<S: PrimeField>(params: &PoseidonParams<S>, r: (S, S), key: (S, S), message: S) -> S {
+    params.hash(&[r.0, r.1, key.0, key.1, message])
+}
+
+/// Returns the leaf of the key of an issuer in a registry.
+pub fn issuer_leaf<S: PrimeField>(params: &PoseidonParams<S>, key: (S, S)) -> S {
+    params.hash(&[key.0, key.1])
+}
+
+/// A signature of a credential.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature<S: PrimeField> {
+    /// The commitment to the nonce.
+    pub r: (S, S),
+    /// The response, as [`response_bits`] little-endian bits.
+    pub s: Vec<bool>,
+}
+
+/// Attributes, signed by an issuer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Credential<S: PrimeField> {
+    pub attributes: Vec<S>,
+    /// The public key of the issuer.
+    pub issuer: (S, S),
+    pub signature: Signature<S>,
+}
+
+impl<S: PrimeField> Credential<S> {
+    /// Returns whether the signature of the credential is valid.
+    pub fn verify<C: EmbeddedCurve<S>>(&self, params: &PoseidonParams<S>) -> bool {
+        let Signature { r, s } = &self.signature;
+        let c = challenge(params, *r, self.issuer, message(params, &self.attributes));
+        s.len() == response_bits::<S>()
+            && C::mul(C::generator(), s) == C::add(*r, C::mul(self.issuer, &field_bits(c)))
+    }
+}
+
+/// A key that issues credentials.
+pub struct Issuer<S: PrimeField, C: EmbeddedCurve<S>> {
+    secret: Vec<bool>,
+    public: (S, S),
+    _curve: PhantomData<C>,
+}
+
+impl<S: PrimeField, C: EmbeddedCurve<S>> Issuer<S, C> {
+    pub fn random<R: RngCore>(rng: &mut R) -> Self {
+        let secret = random_bits(rng, SECRET_BITS);
+        let public = C::mul(C::generator(), &secret);
+        Issuer {
+            secret,
+            public,
+            _curve: PhantomData,
+        }
+    }
+
+    pub fn public_key(&self) -> (S, S) {
+        self.public
+    }
+
+    /// Signs `attributes` into a credential.
+    pub fn issue<R: RngCore>(
+        &self,
+        params: &PoseidonParams<S>,
+        attributes: Vec<S>,
+        rng: &mut R,
+    ) -> Credential<S> {
+        let k = random_bits(rng, response_bits::<S>() - 1);
+        let r = C::mul(C::generator(), &k);
+        let c = challenge(params, r, self.public, message(params, &attributes));
+        let mut s = mul_add(&k, &field_bits(c), &self.secret);
+        s.truncate(response_bits::<S>());
+
+        Credential {
+            attributes,
+            issuer: self.public,
+            signature: Signature { r, s },
+        }
+    }
+}
+
+/// What a presentation of a credential proves, which fixes the shape of the
+/// [`DisclosureCircuit`] and so its parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statement {
+    pub num_attributes: usize,
+    /// The indices of the attributes whose values are disclosed.
+    pub disclosed: Vec<usize>,
+    /// The indices of the attributes that are at least a threshold, with
+    /// the number of bits of their difference to it, which bounds how far
+    /// above the threshold they may be.
+    pub at_least: Vec<(usize, usize)>,
+    /// The depth of the registry of issuers.
+    pub registry_depth: usize,
+}
+
+impl Statement {
+    /// Returns whether `attributes` satisfy the statement with `thresholds`.
+    pub fn holds<S: PrimeField>(&self, attributes: &[S], thresholds: &[S]) -> bool {
+        attributes.len() == self.num_attributes
+            && thresholds.len() == self.at_least.len()
+            && self
+                .at_least
+                .iter()
+                .zip(thresholds)
+                .all(|(&(index, bits), threshold)| {
+                    let difference = field_bits(attributes[index] - threshold);
+                    difference.iter().skip(bits).all(|bit| !bit)
+                })
+    }
+
+    /// Returns the public inputs of a presentation, in the order in which
+    /// the circuit allocates them: the commitment of the registry, the
+    /// disclosed values, then the thresholds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not as many disclosed values and thresholds as
+    /// the statement has.
+    pub fn public_inputs<S: PrimeField>(
+        &self,
+        registry: S,
+        disclosed: &[S],
+        thresholds: &[S],
+    ) -> Vec<S> {
+        assert_eq!(disclosed.len(), self.disclosed.len());
+        assert_eq!(thresholds.len(), self.at_least.len());
+        let mut inputs = vec![registry];
+        inputs.extend_from_slice(disclosed);
+        inputs.extend_from_slice(thresholds);
+        inputs
+    }
+}
+
+/// What the holder of a credential knows to present it.
+#[derive(Clone, Debug)]
+pub struct Presentation<S: PrimeField> {
+    pub credential: Credential<S>,
+    /// The registry of issuers, built with the same parameters as the
+    /// circuit, in which the issuer of the credential is at `issuer_index`.
+    pub registry: Mmr<S>,
+    pub issuer_index: u64,
+    pub thresholds: Vec<S>,
+}
+
+/// Proves a [`Statement`] about a credential. Without a presentation, it
+/// synthesizes the circuit for parameter generation.
+pub struct DisclosureCircuit<'a, S: PrimeField, C: EmbeddedCurve<S>> {
+    pub params: &'a PoseidonParams<S>,
+    pub statement: &'a Statement,
+    pub presentation: Option<Presentation<S>>,
+    pub _curve: PhantomData<C>,
+}
+
+impl<'a, S: PrimeField, C: EmbeddedCurve<S>> Circuit<S> for DisclosureCircuit<'a, S, C> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let DisclosureCircuit {
+            params,
+            statement,
+            presentation,
+            ..
+        } = self;
+        let credential = presentation.as_ref().map(|p| &p.credential);
+        if let Some(credential) = credential {
+            if credential.attributes.len() != statement.num_attributes
+                || credential.signature.s.len() != response_bits::<S>()
+            {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        // The issuer is in the registry.
+        let registry = MmrState::alloc(
+            cs.namespace(|| "registry"),
+            statement.registry_depth,
+            presentation.as_ref().map(|p| &p.registry),
+        )?;
+        registry
+            .commitment(cs.namespace(|| "registry commitment"), params)?
+            .inputize(cs.namespace(|| "registry input"))?;
+        let issuer =
+            EdwardsPoint::<S, C>::witness(cs.namespace(|| "issuer"), credential.map(|c| c.issuer))?;
+        let leaf = poseidon::hash(
+            cs.namespace(|| "issuer leaf"),
+            params,
+            &[issuer.x().clone(), issuer.y().clone()],
+        )?;
+        let proof = presentation
+            .as_ref()
+            .map(|p| {
+                p.registry
+                    .proof(p.issuer_index)
+                    .ok_or(SynthesisError::Unsatisfiable)
+            })
+            .transpose()?;
+        registry.enforce_membership(
+            cs.namespace(|| "issuer membership"),
+            params,
+            &leaf,
+            proof.as_ref(),
+        )?;
+
+        // The issuer signed the attributes: s * G = R + c * key.
+        let attributes = (0..statement.num_attributes)
+            .map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("attribute {}", i)), || {
+                    Ok(credential.get()?.attributes[i])
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let message = poseidon::hash(cs.namespace(|| "message"), params, &attributes)?;
+        let r = EdwardsPoint::<S, C>::witness(
+            cs.namespace(|| "commitment"),
+            credential.map(|c| c.signature.r),
+        )?;
+        let c = poseidon::hash(
+            cs.namespace(|| "challenge"),
+            params,
+            &[
+                r.x().clone(),
+                r.y().clone(),
+                issuer.x().clone(),
+                issuer.y().clone(),
+                message,
+            ],
+        )?;
+        let c_bits = c.to_bits_le_strict(cs.namespace(|| "challenge bits"))?;
+        let s_bits = (0..response_bits::<S>())
+            .map(|i| {
+                AllocatedBit::alloc(
+                    cs.namespace(|| format!("response bit {}", i)),
+                    credential.map(|c| c.signature.s[i]),
+                )
+                .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let table = C::window_table(C::generator(), (s_bits.len() + 2) / 3);
+        let lhs = EdwardsPoint::<S, C>::fixed_base_mul(cs.namespace(|| "s * G"), &table, &s_bits)?;
+        let rhs = issuer.mul(cs.namespace(|| "c * key"), &c_bits)?;
+        let rhs = r.add(cs.namespace(|| "R + c * key"), &rhs)?;
+        cs.enforce(
+            || "signature x",
+            |lc| lc + lhs.x().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + rhs.x().get_variable(),
+        );
+        cs.enforce(
+            || "signature y",
+            |lc| lc + lhs.y().get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + rhs.y().get_variable(),
+        );
+
+        // The disclosed attributes, then the thresholds.
+        for (i, &index) in statement.disclosed.iter().enumerate() {
+            attributes[index].inputize(cs.namespace(|| format!("disclosed {}", i)))?;
+        }
+        let mut checks = RangeChecks::new();
+        for (i, &(index, bits)) in statement.at_least.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("at least {}", i));
+            let threshold = AllocatedNum::alloc(cs.namespace(|| "threshold"), || {
+                Ok(presentation.as_ref().get()?.thresholds[i])
+            })?;
+            threshold.inputize(cs.namespace(|| "threshold input"))?;
+            let attribute = &attributes[index];
+            let difference = AllocatedNum::alloc(cs.namespace(|| "difference"), || {
+                Ok(*attribute.get_value().get()? - threshold.get_value().get()?)
+            })?;
+            cs.enforce(
+                || "difference constraint",
+                |lc| lc + attribute.get_variable() - threshold.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + difference.get_variable(),
+            );
+            checks.check_num(&difference, bits);
+        }
+        checks.enforce(cs.namespace(|| "thresholds"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::ecc::Jubjub;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::groth16::exporter::RawCircuit;
+    use bls12_381::Scalar;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn big_integers() {
+        let bits = |n: u128| (0..128).map(|i| (n >> i) & 1 == 1).collect::<Vec<_>>();
+        // 7 + (2^128 - 1)^2 = (2^128 - 2) * 2^128 + 8
+        let product = mul_add(&bits(7), &bits(u128::MAX), &bits(u128::MAX));
+        assert_eq!(&product[..128], &bits(8)[..]);
+        assert_eq!(&product[128..256], &bits(u128::MAX - 1)[..]);
+        assert!(product[256..].iter().all(|bit| !bit));
+    }
+
+    #[test]
+    fn selective_disclosure() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = PoseidonParams::<Scalar>::for_width(3);
+
+        let issuers = (0..3)
+            .map(|_| Issuer::<Scalar, Jubjub>::random(&mut rng))
+            .collect::<Vec<_>>();
+        let mut registry = Mmr::new(params.clone(), 2);
+        for issuer in &issuers {
+            registry.append(issuer_leaf(&params, issuer.public_key()));
+        }
+
+        // An age, a country, and an identifier.
+        let attributes = vec![Scalar::from(30), Scalar::from(33), Scalar::from(12345)];
+        let credential = issuers[1].issue(&params, attributes, &mut rng);
+        assert!(credential.verify::<Jubjub>(&params));
+        let mut forged = credential.clone();
+        forged.attributes[0] = Scalar::from(31);
+        assert!(!forged.verify::<Jubjub>(&params));
+
+        // The country is disclosed, and the age is over 18.
+        let statement = Statement {
+            num_attributes: 3,
+            disclosed: vec![1],
+            at_least: vec![(0, 8)],
+            registry_depth: 2,
+        };
+        let present = |credential: &Credential<Scalar>, threshold: u64| {
+            let mut cs = TestConstraintSystem::new();
+            DisclosureCircuit::<Scalar, Jubjub> {
+                params: &params,
+                statement: &statement,
+                presentation: Some(Presentation {
+                    credential: credential.clone(),
+                    registry: registry.clone(),
+                    issuer_index: 1,
+                    thresholds: vec![Scalar::from(threshold)],
+                }),
+                _curve: PhantomData,
+            }
+            .synthesize(&mut cs)
+            .unwrap();
+            cs
+        };
+
+        let cs = present(&credential, 19);
+        assert!(cs.is_satisfied());
+        assert!(statement.holds(&credential.attributes, &[Scalar::from(19)]));
+        assert!(cs.verify(&statement.public_inputs(
+            registry.commitment(),
+            &[Scalar::from(33)],
+            &[Scalar::from(19)],
+        )));
+
+        // An age below the threshold, a forged attribute, or an issuer that
+        // is not registered do not satisfy the circuit.
+        assert!(!present(&credential, 31).is_satisfied());
+        assert!(!statement.holds(&credential.attributes, &[Scalar::from(31)]));
+        assert!(!present(&forged, 19).is_satisfied());
+        let rogue = Issuer::<Scalar, Jubjub>::random(&mut rng);
+        let credential = rogue.issue(&params, credential.attributes, &mut rng);
+        assert!(credential.verify::<Jubjub>(&params));
+        assert!(!present(&credential, 19).is_satisfied());
+
+        // The shape of the circuit does not depend on the presentation.
+        let shape = |presentation| {
+            RawCircuit::synthesize(DisclosureCircuit::<Scalar, Jubjub> {
+                params: &params,
+                statement: &statement,
+                presentation,
+                _curve: PhantomData,
+            })
+            .unwrap()
+            .num_constraints
+        };
+        let presentation = Presentation {
+            credential: forged,
+            registry: registry.clone(),
+            issuer_index: 1,
+            thresholds: vec![Scalar::from(19)],
+        };
+        assert_eq!(shape(None), shape(Some(presentation)));
+    }
+}
@@ -0,0 +1,393 @@
+//! Verifiable shuffles of ElGamal ciphertexts, for mixnets.
+//!
+//! A mix server receives ciphertexts, such as encrypted ballots, and outputs
+//! the same plaintexts in a secret order, each encrypted again with fresh
+//! randomness so that no output can be linked to its input. A
+//! [`ShuffleCircuit`] proves that it did so: that there is a permutation `π`
+//! and randomness `k_i` such that output `i` is input `π(i)` re-encrypted
+//! with `k_i`, without revealing either.
+//!
+//! Ciphertexts are ElGamal encryptions of points of an [`EmbeddedCurve`],
+//! `(k·G, M + k·PK)`, so that re-encryption is two scalar multiplications
+//! that the [`ecc`](crate::gadgets::ecc) gadgets check. Messages are encoded
+//! as points by the caller, such as `v·G` for a vote `v` from a small set.
+//!
+//! The permutation is proven with a permutation matrix of bits: one bit per
+//! pair of an output and an input, with a single bit set in each row and in
+//! each column. This takes `4n²` constraints for `n` ciphertexts, which is
+//! little next to the re-encryptions for the tens to hundreds of ciphertexts
+//! of a batch; larger mixes shuffle in batches or chain several servers.
+
+use ff::PrimeField;
+use rand_core::RngCore;
+use std::marker::PhantomData;
+
+use super::random_bits;
+use crate::gadgets::boolean::{AllocatedBit, Boolean};
+use crate::gadgets::ecc::{EdwardsPoint, EmbeddedCurve};
+use crate::{Circuit, ConstraintSystem, LinearCombination, SynthesisError};
+
+/// Returns the number of bits of the randomness of an encryption, which
+/// exceeds the order of the curve by 128 bits so that the randomness is
+/// statistically close to uniform modulo that order.
+pub fn randomness_bits<S: PrimeField>() -> usize {
+    S::NUM_BITS as usize + 128
+}
+
+fn neg<S: PrimeField>(p: (S, S)) -> (S, S) {
+    (-p.0, p.1)
+}
+
+/// An ElGamal encryption of a point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ciphertext<S: PrimeField> {
+    pub c1: (S, S),
+    pub c2: (S, S),
+}
+
+impl<S: PrimeField> Ciphertext<S> {
+    /// Encrypts `message` to `public_key`.
+    pub fn encrypt<C: EmbeddedCurve<S>, R: RngCore>(
+        public_key: (S, S),
+        message: (S, S),
+        rng: &mut R,
+    ) -> Self {
+        let identity = Ciphertext {
+            c1: (S::zero(), S::one()),
+            c2: message,
+        };
+        identity.rerandomize::<C>(public_key, &random_bits(rng, randomness_bits::<S>()))
+    }
+
+    /// Re-encrypts the ciphertext with the little-endian bits `randomness`,
+    /// which changes the ciphertext but not its plaintext.
+    pub fn rerandomize<C: EmbeddedCurve<S>>(
+        &self,
+        public_key: (S, S),
+        randomness: &[bool],
+    ) -> Self {
+        Ciphertext {
+            c1: C::add(self.c1, C::mul(C::generator(), randomness)),
+            c2: C::add(self.c2, C::mul(public_key, randomness)),
+        }
+    }
+}
+
+/// A key pair of the ElGamal encryption.
+pub struct KeyPair<S: PrimeField, C: EmbeddedCurve<S>> {
+    secret: Vec<bool>,
+    public: (S, S),
+    _curve: PhantomData<C>,
+}
+
+impl<S: PrimeField, C: EmbeddedCurve<S>> KeyPair<S, C> {
+    pub fn random<R: RngCore>(rng: &mut R) -> Self {
+        let secret = random_bits(rng, randomness_bits::<S>());
+        let public = C::mul(C::generator(), &secret);
+        KeyPair {
+            secret,
+            public,
+            _curve: PhantomData,
+        }
+    }
+
+    pub fn public_key(&self) -> (S, S) {
+        self.public
+    }
+
+    /// Returns the point that `ciphertext` encrypts.
+    pub fn decrypt(&self, ciphertext: &Ciphertext<S>) -> (S, S) {
+        C::add(ciphertext.c2, neg(C::mul(ciphertext.c1, &self.secret)))
+    }
+}
+
+/// A shuffle of ciphertexts, with the permutation and the randomness that
+/// prove it.
+#[derive(Clone, Debug)]
+pub struct Shuffle<S: PrimeField> {
+    pub public_key: (S, S),
+    pub inputs: Vec<Ciphertext<S>>,
+    pub outputs: Vec<Ciphertext<S>>,
+    /// Output `i` re-encrypts input `permutation[i]`.
+    pub permutation: Vec<usize>,
+    /// The randomness of the re-encryption of each output, as
+    /// [`randomness_bits`] little-endian bits.
+    pub randomness: Vec<Vec<bool>>,
+}
+
+impl<S: PrimeField> Shuffle<S> {
+    /// Shuffles `inputs` with a random permutation, and re-encrypts them to
+    /// `public_key`.
+    pub fn random<C: EmbeddedCurve<S>, R: RngCore>(
+        public_key: (S, S),
+        inputs: Vec<Ciphertext<S>>,
+        rng: &mut R,
+    ) -> Self {
+        let mut permutation = (0..inputs.len()).collect::<Vec<_>>();
+        for i in (1..permutation.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            permutation.swap(i, j);
+        }
+        let randomness = (0..inputs.len())
+            .map(|_| random_bits(rng, randomness_bits::<S>()))
+            .collect::<Vec<_>>();
+        let outputs = permutation
+            .iter()
+            .zip(&randomness)
+            .map(|(i, k)| inputs[*i].rerandomize::<C>(public_key, k))
+            .collect();
+
+        Shuffle {
+            public_key,
+            inputs,
+            outputs,
+            permutation,
+            randomness,
+        }
+    }
+
+    /// Returns the public inputs of the shuffle, in the order in which the
+    /// circuit allocates them.
+    pub fn public_inputs(&self) -> Vec<S> {
+        public_inputs(self.public_key, &self.inputs, &self.outputs)
+    }
+}
+
+/// Returns the public inputs of a shuffle of `inputs` into `outputs`, in the
+/// order in which the circuit allocates them: the coordinates of the public
+/// key, then those of `c1` and `c2` for each input, then for each output.
+pub fn public_inputs<S: PrimeField>(
+    public_key: (S, S),
+    inputs: &[Ciphertext<S>],
+    outputs: &[Ciphertext<S>],
+) -> Vec<S> {
+    let mut values = vec![public_key.0, public_key.1];
+    for ciphertext in inputs.iter().chain(outputs) {
+        values.extend_from_slice(&[ciphertext.c1.0, ciphertext.c1.1]);
+        values.extend_from_slice(&[ciphertext.c2.0, ciphertext.c2.1]);
+    }
+    values
+}
+
+/// Proves a shuffle of `size` ciphertexts. Without a shuffle, it synthesizes
+/// the circuit for parameter generation.
+pub struct ShuffleCircuit<S: PrimeField, C: EmbeddedCurve<S>> {
+    pub size: usize,
+    pub shuffle: Option<Shuffle<S>>,
+    pub _curve: PhantomData<C>,
+}
+
+type Pair<S, C> = (EdwardsPoint<S, C>, EdwardsPoint<S, C>);
+
+fn witness_ciphertext<S, C, CS>(
+    mut cs: CS,
+    ciphertext: Option<&Ciphertext<S>>,
+) -> Result<Pair<S, C>, SynthesisError>
+where
+    S: PrimeField,
+    C: EmbeddedCurve<S>,
+    CS: ConstraintSystem<S>,
+{
+    Ok((
+        EdwardsPoint::witness(cs.namespace(|| "c1"), ciphertext.map(|c| c.c1))?,
+        EdwardsPoint::witness(cs.namespace(|| "c2"), ciphertext.map(|c| c.c2))?,
+    ))
+}
+
+impl<S: PrimeField, C: EmbeddedCurve<S>> Circuit<S> for ShuffleCircuit<S, C> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let ShuffleCircuit { size, shuffle, .. } = self;
+        if let Some(shuffle) = &shuffle {
+            if shuffle.inputs.len() != size
+                || shuffle.outputs.len() != size
+                || shuffle.permutation.len() != size
+                || shuffle.randomness.len() != size
+                || shuffle
+                    .randomness
+                    .iter()
+                    .any(|k| k.len() != randomness_bits::<S>())
+            {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        let public_key = EdwardsPoint::<S, C>::witness(
+            cs.namespace(|| "public key"),
+            shuffle.as_ref().map(|s| s.public_key),
+        )?;
+        public_key.inputize(cs.namespace(|| "public key input"))?;
+        let inputs = (0..size)
+            .map(|i| {
+                let mut cs = cs.namespace(|| format!("input {}", i));
+                let input = witness_ciphertext(
+                    cs.namespace(|| "ciphertext"),
+                    shuffle.as_ref().map(|s| &s.inputs[i]),
+                )?;
+                input.0.inputize(cs.namespace(|| "c1 input"))?;
+                input.1.inputize(cs.namespace(|| "c2 input"))?;
+                Ok(input)
+            })
+            .collect::<Result<Vec<Pair<S, C>>, SynthesisError>>()?;
+
+        // The permutation matrix: bit (i, j) is set if output i re-encrypts
+        // input j.
+        let matrix = (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| {
+                        AllocatedBit::alloc(
+                            cs.namespace(|| format!("permutation {} {}", i, j)),
+                            shuffle.as_ref().map(|s| s.permutation[i] == j),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for i in 0..size {
+            let row = matrix[i]
+                .iter()
+                .fold(LinearCombination::zero(), |lc, bit| lc + bit.get_variable());
+            let column = matrix.iter().fold(LinearCombination::zero(), |lc, row| {
+                lc + row[i].get_variable()
+            });
+            cs.enforce(
+                || format!("row {}", i),
+                |_| row,
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+            cs.enforce(
+                || format!("column {}", i),
+                |_| column,
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+        }
+
+        let table = C::window_table(C::generator(), (randomness_bits::<S>() + 2) / 3);
+        let shuffle = shuffle.as_ref();
+        for (i, row) in matrix.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("output {}", i));
+
+            // The input that the output re-encrypts, which equals input j
+            // wherever bit (i, j) is set.
+            let source = witness_ciphertext(
+                cs.namespace(|| "source"),
+                shuffle.map(|s| &s.inputs[s.permutation[i] % size]),
+            )?;
+            for (j, (bit, input)) in row.iter().zip(&inputs).enumerate() {
+                let pairs = [
+                    (source.0.x(), input.0.x()),
+                    (source.0.y(), input.0.y()),
+                    (source.1.x(), input.1.x()),
+                    (source.1.y(), input.1.y()),
+                ];
+                for (k, (source, input)) in pairs.iter().enumerate() {
+                    cs.enforce(
+                        || format!("selects input {} coordinate {}", j, k),
+                        |lc| lc + bit.get_variable(),
+                        |lc| lc + source.get_variable() - input.get_variable(),
+                        |lc| lc,
+                    );
+                }
+            }
+
+            let randomness = (0..randomness_bits::<S>())
+                .map(|b| {
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("randomness bit {}", b)),
+                        shuffle.map(|s| s.randomness[i][b]),
+                    )
+                    .map(Boolean::from)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let k_g = EdwardsPoint::<S, C>::fixed_base_mul(
+                cs.namespace(|| "k * G"),
+                &table,
+                &randomness,
+            )?;
+            let k_pk = public_key.mul(cs.namespace(|| "k * PK"), &randomness)?;
+            let c1 = source.0.add(cs.namespace(|| "c1"), &k_g)?;
+            let c2 = source.1.add(cs.namespace(|| "c2"), &k_pk)?;
+            c1.inputize(cs.namespace(|| "c1 input"))?;
+            c2.inputize(cs.namespace(|| "c2 input"))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::ecc::Jubjub;
+    use crate::gadgets::test::TestConstraintSystem;
+    use crate::groth16::exporter::RawCircuit;
+    use bls12_381::Scalar;
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn reencryption_shuffle() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let keys = KeyPair::<Scalar, Jubjub>::random(&mut rng);
+        let votes = (1..=3u64)
+            .map(|v| {
+                let bits = (0..64).map(|i| (v >> i) & 1 == 1).collect::<Vec<_>>();
+                Jubjub::mul(Jubjub::generator(), &bits)
+            })
+            .collect::<Vec<_>>();
+        let ballots = votes
+            .iter()
+            .map(|v| Ciphertext::encrypt::<Jubjub, _>(keys.public_key(), *v, &mut rng))
+            .collect::<Vec<_>>();
+        assert_eq!(keys.decrypt(&ballots[1]), votes[1]);
+
+        let shuffle = Shuffle::random::<Jubjub, _>(keys.public_key(), ballots, &mut rng);
+        for (output, i) in shuffle.outputs.iter().zip(&shuffle.permutation) {
+            assert_ne!(*output, shuffle.inputs[*i]);
+            assert_eq!(keys.decrypt(output), votes[*i]);
+        }
+
+        let prove = |shuffle: &Shuffle<Scalar>| {
+            let mut cs = TestConstraintSystem::new();
+            ShuffleCircuit::<Scalar, Jubjub> {
+                size: 3,
+                shuffle: Some(shuffle.clone()),
+                _curve: PhantomData,
+            }
+            .synthesize(&mut cs)
+            .unwrap();
+            cs
+        };
+        let cs = prove(&shuffle);
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&shuffle.public_inputs()));
+
+        // Outputs that are not re-encryptions of the inputs, or that
+        // duplicate one of them, are rejected.
+        let mut tampered = shuffle.clone();
+        tampered.permutation.swap(0, 1);
+        assert!(!prove(&tampered).verify(&shuffle.public_inputs()));
+        let mut duplicated = shuffle.clone();
+        duplicated.permutation[1] = duplicated.permutation[0];
+        duplicated.outputs[1] = duplicated.inputs[duplicated.permutation[1]]
+            .rerandomize::<Jubjub>(keys.public_key(), &duplicated.randomness[1]);
+        assert!(!prove(&duplicated).is_satisfied());
+
+        // The shape of the circuit does not depend on the shuffle.
+        let shape = |shuffle| {
+            RawCircuit::synthesize(ShuffleCircuit::<Scalar, Jubjub> {
+                size: 3,
+                shuffle,
+                _curve: PhantomData,
+            })
+            .unwrap()
+            .num_constraints
+        };
+        assert_eq!(shape(None), shape(Some(shuffle)));
+    }
+}
@@ -0,0 +1,615 @@
+//! A proving service that runs Groth16 jobs on a pool of worker threads.
+//!
+//! Circuits are registered once under an identifier, together with their
+//! [`RawCircuit`] and parameters. Clients then submit a witness for a
+//! circuit and receive a [`JobId`] that can be polled for the status and,
+//! eventually, the proof.
+//!
+//! The service does not bind to any transport itself. [`ProvingServer::handle`]
+//! maps a [`Request`] to a [`Response`], so that an HTTP or gRPC frontend only
+//! needs to translate its own messages into these.
+//!
+//! Jobs wait in a bounded queue, and submissions are rejected once it is
+//! full, or if their estimated memory usage is over the configured limit.
+//! Lifecycle events are reported to a [`Metrics`] implementation.
+
+use pairing::Engine;
+use rand_core::OsRng;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::groth16::exporter::{Assignment, RawCircuit, ReplayCircuit};
+use crate::groth16::{create_random_proof, Parameters, Proof};
+
+/// The identifier of a submitted job.
+pub type JobId = u64;
+
+/// The configuration of a [`ProvingServer`].
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    /// The number of jobs that are proven concurrently.
+    pub workers: usize,
+    /// The number of jobs that may wait for a worker before submissions are
+    /// rejected.
+    pub queue_capacity: usize,
+    /// The largest estimated memory usage of a single job, in bytes. See
+    /// [`estimate_memory`].
+    pub max_job_memory: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            workers: 1,
+            queue_capacity: 64,
+            max_job_memory: None,
+        }
+    }
+}
+
+/// An error when registering a circuit or submitting a job.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerError {
+    /// No circuit is registered under this identifier.
+    UnknownCircuit(String),
+    /// A circuit is already registered under this identifier.
+    DuplicateCircuit(String),
+    /// The parameters do not match the circuit they were registered with.
+    InvalidParameters,
+    /// The witness does not have as many variables as the circuit.
+    InvalidWitness,
+    /// The job queue is full.
+    QueueFull,
+    /// The job would use more memory than the configured limit.
+    MemoryLimit { required: usize, limit: usize },
+}
+
+impl Error for ServerError {}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ServerError::UnknownCircuit(id) => write!(f, "unknown circuit `{}`", id),
+            ServerError::DuplicateCircuit(id) => write!(f, "circuit `{}` already exists", id),
+            ServerError::InvalidParameters => write!(f, "parameters do not match the circuit"),
+            ServerError::InvalidWitness => write!(f, "witness does not match the circuit"),
+            ServerError::QueueFull => write!(f, "job queue is full"),
+            ServerError::MemoryLimit { required, limit } => write!(
+                f,
+                "job requires {} bytes, over the limit of {} bytes",
+                required, limit
+            ),
+        }
+    }
+}
+
+/// The status of a job.
+pub enum JobStatus<E: Engine> {
+    /// The job is waiting for a worker.
+    Queued,
+    /// The job is being proven.
+    Running,
+    /// The job finished with a proof.
+    Done(Proof<E>),
+    /// The job failed; the witness did not satisfy the circuit, or proving
+    /// returned an error.
+    Failed(String),
+}
+
+impl<E: Engine> JobStatus<E> {
+    /// Returns `true` if the job will not change status again.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Done(_) | JobStatus::Failed(_))
+    }
+}
+
+impl<E: Engine> Clone for JobStatus<E> {
+    fn clone(&self) -> Self {
+        match self {
+            JobStatus::Queued => JobStatus::Queued,
+            JobStatus::Running => JobStatus::Running,
+            JobStatus::Done(proof) => JobStatus::Done(proof.clone()),
+            JobStatus::Failed(e) => JobStatus::Failed(e.clone()),
+        }
+    }
+}
+
+impl<E: Engine> fmt::Debug for JobStatus<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobStatus::Queued => write!(f, "Queued"),
+            JobStatus::Running => write!(f, "Running"),
+            JobStatus::Done(_) => write!(f, "Done"),
+            JobStatus::Failed(e) => write!(f, "Failed({:?})", e),
+        }
+    }
+}
+
+/// Hooks for observing the server. Every method does nothing by default.
+pub trait Metrics: Send + Sync {
+    /// A job was added to the queue, which now holds `queue_len` jobs.
+    fn job_queued(&self, _id: JobId, _queue_len: usize) {}
+
+    /// A submission was rejected.
+    fn job_rejected(&self, _error: &ServerError) {}
+
+    /// A worker picked up a job after it waited for `wait`.
+    fn job_started(&self, _id: JobId, _wait: Duration) {}
+
+    /// A job finished after running for `elapsed`.
+    fn job_finished(&self, _id: JobId, _elapsed: Duration, _success: bool) {}
+}
+
+/// A [`Metrics`] implementation that ignores all events.
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+/// A request to a [`ProvingServer`].
+pub enum Request<E: Engine> {
+    /// Submits a witness for the circuit with the given identifier.
+    Submit {
+        circuit_id: String,
+        witness: Assignment<E::Fr>,
+    },
+    /// Polls the status of a job.
+    Status(JobId),
+    /// Removes a finished job, returning its final status.
+    Take(JobId),
+}
+
+/// The response to a [`Request`].
+pub enum Response<E: Engine> {
+    Submitted(JobId),
+    Status(JobStatus<E>),
+    UnknownJob(JobId),
+    Rejected(ServerError),
+}
+
+/// Estimates the number of bytes used while proving `circuit`, which is
+/// dominated by the three evaluation domains of the prover.
+pub fn estimate_memory<S: ff::PrimeField>(circuit: &RawCircuit<S>) -> usize {
+    let scalar = std::mem::size_of::<S>();
+    let domain = (circuit.num_constraints + circuit.num_inputs).next_power_of_two();
+    let witness = circuit.num_inputs + circuit.num_aux;
+
+    (3 * domain + 2 * witness) * scalar
+}
+
+struct RegisteredCircuit<E: Engine> {
+    circuit: RawCircuit<E::Fr>,
+    params: Parameters<E>,
+    memory: usize,
+}
+
+struct QueuedJob<E: Engine> {
+    id: JobId,
+    circuit: Arc<RegisteredCircuit<E>>,
+    witness: Assignment<E::Fr>,
+    queued_at: Instant,
+}
+
+struct State<E: Engine> {
+    queue: VecDeque<QueuedJob<E>>,
+    jobs: HashMap<JobId, JobStatus<E>>,
+    next_id: JobId,
+    shutdown: bool,
+}
+
+struct Shared<E: Engine> {
+    config: ServerConfig,
+    circuits: RwLock<HashMap<String, Arc<RegisteredCircuit<E>>>>,
+    state: Mutex<State<E>>,
+    available: Condvar,
+    metrics: Box<dyn Metrics>,
+}
+
+/// A Groth16 proving service. Dropping it waits for running jobs and
+/// discards queued ones.
+pub struct ProvingServer<E: Engine> {
+    shared: Arc<Shared<E>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<E: Engine> ProvingServer<E> {
+    pub fn new(config: ServerConfig) -> Self {
+        Self::with_metrics(config, NoMetrics)
+    }
+
+    pub fn with_metrics<M: Metrics + 'static>(config: ServerConfig, metrics: M) -> Self {
+        let shared = Arc::new(Shared {
+            config,
+            circuits: RwLock::new(HashMap::new()),
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                jobs: HashMap::new(),
+                next_id: 0,
+                shutdown: false,
+            }),
+            available: Condvar::new(),
+            metrics: Box::new(metrics),
+        });
+
+        let workers = (0..shared.config.workers)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.work())
+            })
+            .collect();
+
+        ProvingServer { shared, workers }
+    }
+
+    /// Registers a circuit and the parameters to prove it with.
+    pub fn register_circuit(
+        &self,
+        id: &str,
+        circuit: RawCircuit<E::Fr>,
+        params: Parameters<E>,
+    ) -> Result<(), ServerError> {
+        if params.vk.ic.len() != circuit.num_inputs
+            || params.l.len() != circuit.num_aux
+            || params.h.len() + 1 < circuit.num_constraints + circuit.num_inputs
+        {
+            return Err(ServerError::InvalidParameters);
+        }
+
+        let mut circuits = self.shared.circuits.write().unwrap();
+        if circuits.contains_key(id) {
+            return Err(ServerError::DuplicateCircuit(id.to_string()));
+        }
+        let memory = estimate_memory(&circuit);
+        circuits.insert(
+            id.to_string(),
+            Arc::new(RegisteredCircuit {
+                circuit,
+                params,
+                memory,
+            }),
+        );
+
+        Ok(())
+    }
+
+    /// Queues a proving job for the circuit registered under `circuit_id`.
+    pub fn submit(
+        &self,
+        circuit_id: &str,
+        witness: Assignment<E::Fr>,
+    ) -> Result<JobId, ServerError> {
+        let result = self.shared.submit(circuit_id, witness);
+        if let Err(e) = &result {
+            self.shared.metrics.job_rejected(e);
+        }
+
+        result
+    }
+
+    /// Returns the status of a job, or `None` if there is no such job.
+    pub fn status(&self, id: JobId) -> Option<JobStatus<E>> {
+        self.shared.state.lock().unwrap().jobs.get(&id).cloned()
+    }
+
+    /// Removes a finished job and returns its final status. Returns `None`
+    /// if the job does not exist or has not finished yet.
+    pub fn take(&self, id: JobId) -> Option<JobStatus<E>> {
+        let mut state = self.shared.state.lock().unwrap();
+        match state.jobs.get(&id) {
+            Some(status) if status.is_finished() => state.jobs.remove(&id),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of jobs waiting for a worker.
+    pub fn queue_len(&self) -> usize {
+        self.shared.state.lock().unwrap().queue.len()
+    }
+
+    /// Handles a transport-independent request.
+    pub fn handle(&self, request: Request<E>) -> Response<E> {
+        match request {
+            Request::Submit {
+                circuit_id,
+                witness,
+            } => match self.submit(&circuit_id, witness) {
+                Ok(id) => Response::Submitted(id),
+                Err(e) => Response::Rejected(e),
+            },
+            Request::Status(id) => match self.status(id) {
+                Some(status) => Response::Status(status),
+                None => Response::UnknownJob(id),
+            },
+            Request::Take(id) => match self.take(id) {
+                Some(status) => Response::Status(status),
+                None => Response::UnknownJob(id),
+            },
+        }
+    }
+}
+
+impl<E: Engine> Drop for ProvingServer<E> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<E: Engine> Shared<E> {
+    fn submit(&self, circuit_id: &str, witness: Assignment<E::Fr>) -> Result<JobId, ServerError> {
+        let circuit = self
+            .circuits
+            .read()
+            .unwrap()
+            .get(circuit_id)
+            .cloned()
+            .ok_or_else(|| ServerError::UnknownCircuit(circuit_id.to_string()))?;
+
+        if witness.inputs.len() != circuit.circuit.num_inputs
+            || witness.aux.len() != circuit.circuit.num_aux
+        {
+            return Err(ServerError::InvalidWitness);
+        }
+        if let Some(limit) = self.config.max_job_memory {
+            if circuit.memory > limit {
+                return Err(ServerError::MemoryLimit {
+                    required: circuit.memory,
+                    limit,
+                });
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.queue.len() >= self.config.queue_capacity {
+            return Err(ServerError::QueueFull);
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.insert(id, JobStatus::Queued);
+        state.queue.push_back(QueuedJob {
+            id,
+            circuit,
+            witness,
+            queued_at: Instant::now(),
+        });
+        let queue_len = state.queue.len();
+        drop(state);
+
+        self.metrics.job_queued(id, queue_len);
+        self.available.notify_one();
+
+        Ok(id)
+    }
+
+    fn work(&self) {
+        loop {
+            let job = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if state.shutdown {
+                        return;
+                    }
+                    if let Some(job) = state.queue.pop_front() {
+                        state.jobs.insert(job.id, JobStatus::Running);
+                        break job;
+                    }
+                    state = self.available.wait(state).unwrap();
+                }
+            };
+
+            self.metrics.job_started(job.id, job.queued_at.elapsed());
+            let start = Instant::now();
+            let status = prove(&job);
+            self.metrics.job_finished(
+                job.id,
+                start.elapsed(),
+                matches!(status, JobStatus::Done(_)),
+            );
+
+            self.state.lock().unwrap().jobs.insert(job.id, status);
+        }
+    }
+}
+
+fn prove<E: Engine>(job: &QueuedJob<E>) -> JobStatus<E> {
+    if !job.witness.is_satisfied(&job.circuit.circuit) {
+        return JobStatus::Failed("witness does not satisfy the circuit".to_string());
+    }
+
+    let circuit = ReplayCircuit {
+        circuit: job.circuit.circuit.clone(),
+        assignment: Some(job.witness.clone()),
+    };
+    match create_random_proof(circuit, &job.circuit.params, &mut OsRng) {
+        Ok(proof) => JobStatus::Done(proof),
+        Err(e) => JobStatus::Failed(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MulCircuit {
+        a: Option<Scalar>,
+        b: Option<Scalar>,
+    }
+
+    impl Circuit<Scalar> for MulCircuit {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc(|| "b", || self.b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = cs.alloc_input(
+                || "c",
+                || {
+                    let a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+                    let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
+                    Ok(a * b)
+                },
+            )?;
+            cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+
+            Ok(())
+        }
+    }
+
+    fn setup() -> (RawCircuit<Scalar>, Parameters<Bls12>) {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let circuit = RawCircuit::synthesize(MulCircuit { a: None, b: None }).unwrap();
+        let params =
+            generate_random_parameters::<Bls12, _, _>(MulCircuit { a: None, b: None }, &mut rng)
+                .unwrap();
+
+        (circuit, params)
+    }
+
+    fn witness(a: u64, b: u64) -> Assignment<Scalar> {
+        Assignment::synthesize(MulCircuit {
+            a: Some(Scalar::from(a)),
+            b: Some(Scalar::from(b)),
+        })
+        .unwrap()
+    }
+
+    fn wait(server: &ProvingServer<Bls12>, id: JobId) -> JobStatus<Bls12> {
+        loop {
+            if let Some(status) = server.take(id) {
+                return status;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[derive(Default)]
+    struct Counts {
+        queued: AtomicUsize,
+        rejected: AtomicUsize,
+        finished: AtomicUsize,
+    }
+
+    impl Metrics for Arc<Counts> {
+        fn job_queued(&self, _: JobId, _: usize) {
+            self.queued.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn job_rejected(&self, _: &ServerError) {
+            self.rejected.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn job_finished(&self, _: JobId, _: Duration, _: bool) {
+            self.finished.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn proves_submitted_jobs() {
+        let (circuit, params) = setup();
+        let pvk = prepare_verifying_key(&params.vk);
+        let counts = Arc::new(Counts::default());
+        let server = ProvingServer::with_metrics(
+            ServerConfig {
+                workers: 2,
+                ..ServerConfig::default()
+            },
+            counts.clone(),
+        );
+        server.register_circuit("mul", circuit, params).unwrap();
+
+        let good = server.submit("mul", witness(3, 5)).unwrap();
+        let mut bad = witness(3, 5);
+        bad.inputs[1] = Scalar::from(16);
+        let bad = server.submit("mul", bad).unwrap();
+
+        match wait(&server, good) {
+            JobStatus::Done(proof) => {
+                assert!(verify_proof(&pvk, &proof, &[Scalar::from(15)]).is_ok())
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+        assert!(matches!(wait(&server, bad), JobStatus::Failed(_)));
+        assert!(server.status(good).is_none());
+
+        assert!(matches!(
+            server.handle(Request::Submit {
+                circuit_id: "div".to_string(),
+                witness: witness(1, 1),
+            }),
+            Response::Rejected(ServerError::UnknownCircuit(_))
+        ));
+
+        assert_eq!(counts.queued.load(Ordering::SeqCst), 2);
+        assert_eq!(counts.finished.load(Ordering::SeqCst), 2);
+        assert_eq!(counts.rejected.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn rejects_over_limits() {
+        let (circuit, params) = setup();
+        let memory = estimate_memory(&circuit);
+
+        // Without workers, jobs stay in the queue.
+        let server = ProvingServer::new(ServerConfig {
+            workers: 0,
+            queue_capacity: 1,
+            max_job_memory: None,
+        });
+        server
+            .register_circuit("mul", circuit.clone(), params.clone())
+            .unwrap();
+        assert_eq!(
+            server.register_circuit("mul", circuit.clone(), params.clone()),
+            Err(ServerError::DuplicateCircuit("mul".to_string()))
+        );
+
+        let id = server.submit("mul", witness(2, 2)).unwrap();
+        assert!(matches!(server.status(id), Some(JobStatus::Queued)));
+        assert!(server.take(id).is_none());
+        assert_eq!(
+            server.submit("mul", witness(2, 3)),
+            Err(ServerError::QueueFull)
+        );
+        assert_eq!(
+            server.submit(
+                "mul",
+                Assignment {
+                    inputs: vec![],
+                    aux: vec![]
+                }
+            ),
+            Err(ServerError::InvalidWitness)
+        );
+        assert_eq!(server.queue_len(), 1);
+
+        let server = ProvingServer::new(ServerConfig {
+            max_job_memory: Some(memory - 1),
+            ..ServerConfig::default()
+        });
+        server.register_circuit("mul", circuit, params).unwrap();
+        assert_eq!(
+            server.submit("mul", witness(2, 2)),
+            Err(ServerError::MemoryLimit {
+                required: memory,
+                limit: memory - 1
+            })
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use ff::PrimeField;
+use std::io::{self, Read, Write};
+
+use super::poly::{pow, Laurent};
+use crate::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// A wire of a Sonic multiplication gate `a_i * b_i = c_i`. Gates are
+/// numbered from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Wire {
+    A(usize),
+    B(usize),
+    C(usize),
+}
+
+/// A circuit in Sonic's constraint form: `n` multiplication gates together
+/// with `Q` linear constraints
+///
+/// ```text
+/// sum_i (u_{i,q} a_i + v_{i,q} b_i + w_{i,q} c_i) = k_q
+/// ```
+///
+/// where each `k_q` is either zero or one of the public inputs.
+///
+/// A bellman [`Circuit`] is adapted by packing its variables two to a gate,
+/// and turning every R1CS constraint `A * B = C` into a fresh gate whose
+/// wires are tied to `A`, `B` and `C` by three linear constraints. Each
+/// public input (including the constant `ONE`) is tied to its value through
+/// `k`.
+#[derive(Clone, Debug)]
+pub struct AdaptedCircuit<S: PrimeField> {
+    n: usize,
+    constraints: Vec<Vec<(Wire, S)>>,
+    // For each linear constraint, the index of the public input it is equal
+    // to, if any. Input 0 is the constant ONE.
+    k: Vec<Option<usize>>,
+    num_inputs: usize,
+}
+
+/// The values of the wires of every multiplication gate.
+pub(crate) struct Assignment<S: PrimeField> {
+    pub a: Vec<S>,
+    pub b: Vec<S>,
+    pub c: Vec<S>,
+}
+
+impl<S: PrimeField> AdaptedCircuit<S> {
+    /// Synthesizes the structure of `circuit`, without computing a witness.
+    pub fn new<C: Circuit<S>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut adaptor = Adaptor::new(false)?;
+        circuit.synthesize(&mut adaptor)?;
+
+        Ok(adaptor.circuit)
+    }
+
+    /// Synthesizes `circuit` together with its witness, returning the wire
+    /// assignment and the public inputs (excluding `ONE`).
+    pub(crate) fn with_witness<C: Circuit<S>>(
+        circuit: C,
+    ) -> Result<(Self, Assignment<S>, Vec<S>), SynthesisError> {
+        let mut adaptor = Adaptor::new(true)?;
+        circuit.synthesize(&mut adaptor)?;
+
+        let inputs = adaptor.inputs[1..].to_vec();
+        Ok((adaptor.circuit, adaptor.assignment, inputs))
+    }
+
+    /// Writes the structure of the circuit: the number of gates and of
+    /// public inputs, then each linear constraint as its terms and its
+    /// public input, with integers in big-endian.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.n as u32).to_be_bytes())?;
+        writer.write_all(&(self.num_inputs as u32).to_be_bytes())?;
+        writer.write_all(&(self.constraints.len() as u32).to_be_bytes())?;
+        for (terms, k) in self.constraints.iter().zip(self.k.iter()) {
+            writer.write_all(&(terms.len() as u32).to_be_bytes())?;
+            for (wire, coeff) in terms {
+                let (tag, gate) = match *wire {
+                    Wire::A(i) => (0, i),
+                    Wire::B(i) => (1, i),
+                    Wire::C(i) => (2, i),
+                };
+                writer.write_all(&[tag])?;
+                writer.write_all(&(gate as u32).to_be_bytes())?;
+                writer.write_all(coeff.to_repr().as_ref())?;
+            }
+            // Inputs are numbered from one, so that zero means none.
+            let k = k.map_or(0, |i| i as u32 + 1);
+            writer.write_all(&k.to_be_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a circuit written by [`AdaptedCircuit::write`].
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let read_u32 = |reader: &mut R| -> io::Result<usize> {
+            let mut bytes = [0u8; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_be_bytes(bytes) as usize)
+        };
+
+        let n = read_u32(&mut reader)?;
+        let num_inputs = read_u32(&mut reader)?;
+        let q = read_u32(&mut reader)?;
+        let mut constraints = vec![];
+        let mut k = vec![];
+        for _ in 0..q {
+            let mut terms = vec![];
+            for _ in 0..read_u32(&mut reader)? {
+                let mut tag = [0u8; 1];
+                reader.read_exact(&mut tag)?;
+                let gate = read_u32(&mut reader)?;
+                if gate == 0 || gate > n {
+                    return Err(invalid("gate out of range"));
+                }
+                let wire = match tag[0] {
+                    0 => Wire::A(gate),
+                    1 => Wire::B(gate),
+                    2 => Wire::C(gate),
+                    _ => return Err(invalid("unknown wire")),
+                };
+                let mut repr = S::Repr::default();
+                reader.read_exact(repr.as_mut())?;
+                let coeff = S::from_repr(repr).ok_or_else(|| invalid("invalid scalar"))?;
+                terms.push((wire, coeff));
+            }
+            constraints.push(terms);
+
+            k.push(match read_u32(&mut reader)? {
+                0 => None,
+                i if i <= num_inputs => Some(i - 1),
+                _ => return Err(invalid("public input out of range")),
+            });
+        }
+
+        Ok(AdaptedCircuit {
+            n,
+            constraints,
+            k,
+            num_inputs,
+        })
+    }
+
+    /// Returns the number of multiplication gates.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the number of linear constraints.
+    pub fn q(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Returns the number of public inputs, excluding `ONE`.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs - 1
+    }
+
+    /// Returns the smallest reference string size that can prove this
+    /// circuit.
+    pub fn required_srs_degree(&self) -> usize {
+        std::cmp::max(4 * self.n + 8, 2 * self.n + self.q() + 1)
+    }
+
+    /// Evaluates `k(y) = sum_q y^(q + n) k_q` for the given public inputs.
+    pub(crate) fn k(&self, inputs: &[S], y: S) -> Result<S, SynthesisError> {
+        if inputs.len() + 1 != self.num_inputs {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut acc = S::zero();
+        let mut y_q = pow(y, self.n as i64)?;
+        for k in self.k.iter() {
+            y_q.mul_assign(&y);
+            match *k {
+                Some(0) => acc.add_assign(&y_q),
+                Some(i) => acc.add_assign(&(y_q * inputs[i - 1])),
+                None => {}
+            }
+        }
+
+        Ok(acc)
+    }
+
+    /// Returns `x^e` for the power of `X` that a wire carries in `s(X, Y)`.
+    fn wire_degree(&self, wire: Wire) -> i64 {
+        match wire {
+            Wire::A(i) => -(i as i64),
+            Wire::B(i) => i as i64,
+            Wire::C(i) => (i + self.n) as i64,
+        }
+    }
+
+    /// Evaluates `s(x, y)` directly.
+    pub(crate) fn s_eval(&self, x: S, y: S) -> Result<S, SynthesisError> {
+        let s_y = self.s_poly_y(x)?;
+        s_y.evaluate(y)
+    }
+
+    /// Returns `s(X, y)` as a Laurent polynomial in `X`, with degrees in
+    /// `-n..=2n`.
+    pub(crate) fn s_poly_x(&self, y: S) -> Result<Laurent<S>, SynthesisError> {
+        let n = self.n as i64;
+        let mut s = Laurent::zero(-n, 2 * n);
+
+        let mut y_q = pow(y, n)?;
+        for constraint in self.constraints.iter() {
+            y_q.mul_assign(&y);
+            for (wire, coeff) in constraint.iter() {
+                s.add_term(self.wire_degree(*wire), *coeff * y_q);
+            }
+        }
+
+        let y_inv = pow(y, -1)?;
+        let (mut y_i, mut y_inv_i) = (S::one(), S::one());
+        for i in 1..=n {
+            y_i.mul_assign(&y);
+            y_inv_i.mul_assign(&y_inv);
+            s.add_term(i + n, (y_i + y_inv_i).neg());
+        }
+
+        Ok(s)
+    }
+
+    /// Returns `s(x, Y)` as a Laurent polynomial in `Y`, with degrees in
+    /// `-n..=n+Q`.
+    pub(crate) fn s_poly_y(&self, x: S) -> Result<Laurent<S>, SynthesisError> {
+        let n = self.n as i64;
+        let mut s = Laurent::zero(-n, n + self.q() as i64);
+
+        // Powers x^e for e in -n..=2n.
+        let x_inv = pow(x, -1)?;
+        let mut x_powers = vec![S::one(); 3 * self.n + 1];
+        for i in 1..=self.n {
+            x_powers[self.n + i] = x_powers[self.n + i - 1] * x;
+            x_powers[self.n - i] = x_powers[self.n - i + 1] * x_inv;
+        }
+        for i in (2 * self.n + 1)..=(3 * self.n) {
+            x_powers[i] = x_powers[i - 1] * x;
+        }
+        let x_pow = |e: i64| x_powers[(e + n) as usize];
+
+        for (q, constraint) in self.constraints.iter().enumerate() {
+            let mut acc = S::zero();
+            for (wire, coeff) in constraint.iter() {
+                acc.add_assign(&(*coeff * x_pow(self.wire_degree(*wire))));
+            }
+            s.add_term(q as i64 + 1 + n, acc);
+        }
+
+        for i in 1..=n {
+            let term = x_pow(i + n).neg();
+            s.add_term(i, term);
+            s.add_term(-i, term);
+        }
+
+        Ok(s)
+    }
+}
+
+/// A [`ConstraintSystem`] that builds an [`AdaptedCircuit`].
+struct Adaptor<S: PrimeField> {
+    circuit: AdaptedCircuit<S>,
+    assignment: Assignment<S>,
+    with_witness: bool,
+
+    // The wire and value of every bellman variable.
+    input_wires: Vec<Wire>,
+    aux_wires: Vec<Wire>,
+    inputs: Vec<S>,
+    aux: Vec<S>,
+
+    // A gate whose `b` wire is still free for the next allocation.
+    half_gate: Option<usize>,
+}
+
+impl<S: PrimeField> Adaptor<S> {
+    fn new(with_witness: bool) -> Result<Self, SynthesisError> {
+        let mut adaptor = Adaptor {
+            circuit: AdaptedCircuit {
+                n: 0,
+                constraints: vec![],
+                k: vec![],
+                num_inputs: 0,
+            },
+            assignment: Assignment {
+                a: vec![],
+                b: vec![],
+                c: vec![],
+            },
+            with_witness,
+            input_wires: vec![],
+            aux_wires: vec![],
+            inputs: vec![],
+            aux: vec![],
+            half_gate: None,
+        };
+        adaptor.alloc_input(|| "", || Ok(S::one()))?;
+
+        Ok(adaptor)
+    }
+
+    fn new_gate(&mut self, a: S, b: S, c: S) -> usize {
+        self.circuit.n += 1;
+        self.assignment.a.push(a);
+        self.assignment.b.push(b);
+        self.assignment.c.push(c);
+        self.circuit.n
+    }
+
+    /// Places a value on a free wire, two values to a gate so that the
+    /// multiplication `a * b = c` holds trivially.
+    fn place(&mut self, value: S) -> Wire {
+        match self.half_gate.take() {
+            Some(gate) => {
+                let a = self.assignment.a[gate - 1];
+                self.assignment.b[gate - 1] = value;
+                self.assignment.c[gate - 1] = a * value;
+                Wire::B(gate)
+            }
+            None => {
+                let gate = self.new_gate(value, S::zero(), S::zero());
+                self.half_gate = Some(gate);
+                Wire::A(gate)
+            }
+        }
+    }
+
+    fn value<F>(&self, f: F) -> Result<S, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+    {
+        if self.with_witness {
+            f()
+        } else {
+            Ok(S::zero())
+        }
+    }
+
+    fn wire_of(&self, var: Variable) -> (Wire, S) {
+        match var.get_unchecked() {
+            Index::Input(i) => (self.input_wires[i], self.inputs[i]),
+            Index::Aux(i) => (self.aux_wires[i], self.aux[i]),
+        }
+    }
+
+    /// Adds the constraint `wire - lc = 0` and returns the value of `lc`.
+    fn tie(&mut self, wire: Wire, lc: &LinearCombination<S>) -> S {
+        let mut value = S::zero();
+        let mut constraint = vec![(wire, S::one())];
+        for (var, coeff) in lc.as_ref() {
+            let (w, v) = self.wire_of(*var);
+            constraint.push((w, coeff.neg()));
+            value.add_assign(&(v * coeff));
+        }
+        self.circuit.constraints.push(constraint);
+        self.circuit.k.push(None);
+
+        value
+    }
+}
+
+impl<S: PrimeField> ConstraintSystem<S> for Adaptor<S> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = self.value(f)?;
+        let wire = self.place(value);
+        self.aux_wires.push(wire);
+        self.aux.push(value);
+
+        Ok(Variable(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = self.value(f)?;
+        let wire = self.place(value);
+        let index = self.inputs.len();
+        self.input_wires.push(wire);
+        self.inputs.push(value);
+
+        self.circuit.constraints.push(vec![(wire, S::one())]);
+        self.circuit.k.push(Some(index));
+        self.circuit.num_inputs += 1;
+
+        Ok(Variable(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LB: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+        LC: FnOnce(LinearCombination<S>) -> LinearCombination<S>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        let gate = self.new_gate(S::zero(), S::zero(), S::zero());
+        self.assignment.a[gate - 1] = self.tie(Wire::A(gate), &a);
+        self.assignment.b[gate - 1] = self.tie(Wire::B(gate), &b);
+        self.assignment.c[gate - 1] = self.tie(Wire::C(gate), &c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,149 @@
+use pairing::{Engine, MultiMillerLoop};
+
+use super::verifier::PairingBatch;
+use super::{AdaptedCircuit, Proof, SRS};
+use crate::multicore::Worker;
+use crate::transcript::{Blake2sTranscript, Transcript};
+use crate::{SynthesisError, VerificationError};
+
+/// A helper's advice for a single proof: a commitment to `s(X, y)` and its
+/// evaluation at `z`.
+#[derive(Clone)]
+pub struct SxyAdvice<E: Engine> {
+    pub s: E::G1Affine,
+    pub opening: E::G1Affine,
+    pub szy: E::Fr,
+}
+
+/// A helper's proof that the advice for a batch of proofs commits to the
+/// correct polynomials `s(X, y_j)`.
+///
+/// It consists of a commitment `C` to `s(u, Y)` for a random `u`, openings
+/// of `C` at every `y_j` and of every advice commitment at `u` (which must
+/// agree), and an opening of `C` at a random `w`, which the verifier checks
+/// against its own evaluation of `s(u, w)`.
+#[derive(Clone)]
+pub struct Aggregate<E: Engine> {
+    pub c: E::G1Affine,
+    pub s_openings: Vec<E::G1Affine>,
+    pub c_openings: Vec<(E::G1Affine, E::Fr)>,
+    pub opening: E::G1Affine,
+}
+
+/// A proof in a helped batch, with its public inputs and advice.
+pub type HelpedProof<'a, E> = (&'a Proof<E>, &'a [<E as Engine>::Fr], &'a SxyAdvice<E>);
+
+/// Computes the advice for a single proof.
+pub fn create_advice<E: Engine>(
+    srs: &SRS<E>,
+    circuit: &AdaptedCircuit<E::Fr>,
+    proof: &Proof<E>,
+    inputs: &[E::Fr],
+) -> Result<SxyAdvice<E>, SynthesisError> {
+    let worker = Worker::new();
+    let (y, z) = proof.challenges(inputs);
+
+    let s = circuit.s_poly_x(y)?;
+    let commitment = srs.commit(&worker, 2 * circuit.n() as i64, &s)?;
+    let (szy, opening) = srs.open(&worker, &s, z)?;
+
+    Ok(SxyAdvice {
+        s: commitment,
+        opening,
+        szy,
+    })
+}
+
+/// Derives the challenge `u`, given the proofs and advice in a batch.
+fn challenge_u<E: Engine>(
+    batch: &[HelpedProof<'_, E>],
+) -> (Blake2sTranscript<E::Fr>, Vec<E::Fr>, E::Fr) {
+    let mut transcript = Blake2sTranscript::new(b"bellman-sonic-aggregate");
+    transcript.absorb_u64(b"proofs", batch.len() as u64);
+
+    let mut ys = Vec::with_capacity(batch.len());
+    for (proof, inputs, advice) in batch.iter() {
+        let (y, z) = proof.challenges(inputs);
+        transcript.absorb_scalar(b"y", &y);
+        transcript.absorb_scalar(b"z", &z);
+        transcript.absorb_point(b"s", &advice.s);
+        transcript.absorb_scalar(b"szy", &advice.szy);
+        ys.push(y);
+    }
+    let u = transcript.squeeze_challenge(b"u");
+
+    (transcript, ys, u)
+}
+
+/// Computes the aggregate for a batch of proofs of the same circuit, each
+/// given with its public inputs and advice.
+pub fn create_aggregate<E: Engine>(
+    srs: &SRS<E>,
+    circuit: &AdaptedCircuit<E::Fr>,
+    batch: &[HelpedProof<'_, E>],
+) -> Result<Aggregate<E>, SynthesisError> {
+    let worker = Worker::new();
+    let (mut transcript, ys, u) = challenge_u(batch);
+
+    let s_u = circuit.s_poly_y(u)?;
+    let c = srs.commit(&worker, (circuit.n() + circuit.q()) as i64, &s_u)?;
+    transcript.absorb_point(b"c", &c);
+    let w = transcript.squeeze_challenge(b"w");
+
+    let mut s_openings = Vec::with_capacity(batch.len());
+    let mut c_openings = Vec::with_capacity(batch.len());
+    for y in ys {
+        let (_, s_opening) = srs.open(&worker, &circuit.s_poly_x(y)?, u)?;
+        s_openings.push(s_opening);
+        let (value, c_opening) = srs.open(&worker, &s_u, y)?;
+        c_openings.push((c_opening, value));
+    }
+    let (_, opening) = srs.open(&worker, &s_u, w)?;
+
+    Ok(Aggregate {
+        c,
+        s_openings,
+        c_openings,
+        opening,
+    })
+}
+
+/// Verifies a batch of proofs of the same circuit with the help of an
+/// aggregate, evaluating `s(X, Y)` only once.
+pub fn verify_aggregate<E: MultiMillerLoop>(
+    srs: &SRS<E>,
+    circuit: &AdaptedCircuit<E::Fr>,
+    batch: &[HelpedProof<'_, E>],
+    aggregate: &Aggregate<E>,
+) -> Result<(), VerificationError> {
+    if aggregate.s_openings.len() != batch.len() || aggregate.c_openings.len() != batch.len() {
+        return Err(VerificationError::InvalidProof);
+    }
+
+    let (mut transcript, _, u) = challenge_u(batch);
+    transcript.absorb_point(b"c", &aggregate.c);
+    let w = transcript.squeeze_challenge(b"w");
+
+    let s_max = 2 * circuit.n() as i64;
+    let c_max = (circuit.n() + circuit.q()) as i64;
+
+    let mut pairings = PairingBatch::new(srs);
+    for (((proof, inputs, advice), s_opening), (c_opening, s_uy)) in batch
+        .iter()
+        .zip(aggregate.s_openings.iter())
+        .zip(aggregate.c_openings.iter())
+    {
+        let (y, z) = proof.challenges(inputs);
+        pairings.add_proof(circuit, proof, inputs, y, z, advice.szy)?;
+        pairings.add_opening(advice.s, s_max, z, advice.szy, advice.opening);
+        pairings.add_opening(advice.s, s_max, u, *s_uy, *s_opening);
+        pairings.add_opening(aggregate.c, c_max, y, *s_uy, *c_opening);
+    }
+
+    let s_uw = circuit
+        .s_eval(u, w)
+        .map_err(|_| VerificationError::InvalidProof)?;
+    pairings.add_opening(aggregate.c, c_max, w, s_uw, aggregate.opening);
+
+    pairings.finalize()
+}
@@ -0,0 +1,142 @@
+//! The [Sonic] proving system, which uses a universal and updatable
+//! structured reference string: a single [`SRS`] of size `d` can prove any
+//! circuit that fits in it.
+//!
+//! Circuits are written against the usual [`ConstraintSystem`] and adapted
+//! into Sonic's constraint form by [`AdaptedCircuit`]. Proofs can be checked
+//! on their own with [`verify_proof`], which evaluates the circuit's
+//! polynomial `s(X, Y)` directly, or in batches with the help of an
+//! untrusted helper (see [`create_advice`] and [`create_aggregate`]) so that
+//! the verifier evaluates `s(X, Y)` only once per batch.
+//!
+//! [Sonic]: https://eprint.iacr.org/2019/099
+//! [`ConstraintSystem`]: crate::ConstraintSystem
+//! [`SRS`]: crate::sonic::SRS
+//! [`AdaptedCircuit`]: crate::sonic::AdaptedCircuit
+//! [`verify_proof`]: crate::sonic::verify_proof
+//! [`create_advice`]: crate::sonic::create_advice
+//! [`create_aggregate`]: crate::sonic::create_aggregate
+
+use ff::PrimeField;
+use group::GroupEncoding;
+use pairing::Engine;
+use std::io::{self, Read, Write};
+
+use crate::transcript::{Blake2sTranscript, Transcript};
+
+#[cfg(test)]
+mod tests;
+
+mod adaptor;
+mod helper;
+mod poly;
+mod prover;
+mod srs;
+mod verifier;
+
+pub use self::adaptor::AdaptedCircuit;
+pub use self::helper::*;
+pub use self::prover::*;
+pub use self::srs::{UpdateProof, SRS};
+pub use self::verifier::verify_proof;
+
+/// A Sonic proof: commitments to `r(X, 1)` and `t(X, y)`, together with
+/// openings of `r` at `z` and `yz` and of `t` at `z`.
+#[derive(Clone)]
+pub struct Proof<E: Engine> {
+    pub r: E::G1Affine,
+    pub t: E::G1Affine,
+    pub rz: E::Fr,
+    pub rzy: E::Fr,
+    pub z_opening: E::G1Affine,
+    pub zy_opening: E::G1Affine,
+    pub t_opening: E::G1Affine,
+}
+
+impl<E: Engine> PartialEq for Proof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.r == other.r
+            && self.t == other.t
+            && self.rz == other.rz
+            && self.rzy == other.rzy
+            && self.z_opening == other.z_opening
+            && self.zy_opening == other.zy_opening
+            && self.t_opening == other.t_opening
+    }
+}
+
+impl<E: Engine> Proof<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.r.to_bytes().as_ref())?;
+        writer.write_all(self.t.to_bytes().as_ref())?;
+        writer.write_all(self.rz.to_repr().as_ref())?;
+        writer.write_all(self.rzy.to_repr().as_ref())?;
+        writer.write_all(self.z_opening.to_bytes().as_ref())?;
+        writer.write_all(self.zy_opening.to_bytes().as_ref())?;
+        writer.write_all(self.t_opening.to_bytes().as_ref())?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let read_g1 = |reader: &mut R| -> io::Result<E::G1Affine> {
+            let mut g1_repr = <E::G1Affine as GroupEncoding>::Repr::default();
+            reader.read_exact(g1_repr.as_mut())?;
+
+            let affine = E::G1Affine::from_bytes(&g1_repr);
+            if affine.is_some().into() {
+                Ok(affine.unwrap())
+            } else {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "invalid G1"))
+            }
+        };
+
+        let read_fr = |reader: &mut R| -> io::Result<E::Fr> {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            reader.read_exact(repr.as_mut())?;
+
+            E::Fr::from_repr(repr)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid scalar"))
+        };
+
+        let r = read_g1(&mut reader)?;
+        let t = read_g1(&mut reader)?;
+        let rz = read_fr(&mut reader)?;
+        let rzy = read_fr(&mut reader)?;
+        let z_opening = read_g1(&mut reader)?;
+        let zy_opening = read_g1(&mut reader)?;
+        let t_opening = read_g1(&mut reader)?;
+
+        Ok(Proof {
+            r,
+            t,
+            rz,
+            rzy,
+            z_opening,
+            zy_opening,
+            t_opening,
+        })
+    }
+
+    /// Recomputes the verifier's challenges `y` and `z`.
+    pub(crate) fn challenges(&self, inputs: &[E::Fr]) -> (E::Fr, E::Fr) {
+        let mut transcript = transcript(inputs);
+        transcript.absorb_point(b"r", &self.r);
+        let y = transcript.squeeze_challenge(b"y");
+        transcript.absorb_point(b"t", &self.t);
+        let z = transcript.squeeze_challenge(b"z");
+
+        (y, z)
+    }
+}
+
+/// Starts the transcript of a proof for the given public inputs.
+fn transcript<S: PrimeField>(inputs: &[S]) -> Blake2sTranscript<S> {
+    let mut transcript = Blake2sTranscript::new(b"bellman-sonic");
+    transcript.absorb_u64(b"inputs", inputs.len() as u64);
+    for input in inputs {
+        transcript.absorb_scalar(b"input", input);
+    }
+
+    transcript
+}
@@ -0,0 +1,152 @@
+//! Laurent polynomials, which Sonic uses for all of its polynomials.
+
+use ff::PrimeField;
+
+use crate::domain::{EvaluationDomain, Scalar};
+use crate::multicore::Worker;
+use crate::SynthesisError;
+
+/// A Laurent polynomial `sum(coeffs[j] * X^(min + j))`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Laurent<S: PrimeField> {
+    pub min: i64,
+    pub coeffs: Vec<S>,
+}
+
+impl<S: PrimeField> Laurent<S> {
+    /// Returns the zero polynomial, with room for the degrees `min..=max`.
+    pub fn zero(min: i64, max: i64) -> Self {
+        Laurent {
+            min,
+            coeffs: vec![S::zero(); (max - min + 1) as usize],
+        }
+    }
+
+    pub fn max(&self) -> i64 {
+        self.min + self.coeffs.len() as i64 - 1
+    }
+
+    /// Adds `coeff * X^degree`, which must be within the allocated range.
+    pub fn add_term(&mut self, degree: i64, coeff: S) {
+        self.coeffs[(degree - self.min) as usize].add_assign(&coeff);
+    }
+
+    pub fn evaluate(&self, x: S) -> Result<S, SynthesisError> {
+        let mut acc = self
+            .coeffs
+            .iter()
+            .rev()
+            .fold(S::zero(), |acc, c| acc * x + c);
+        acc.mul_assign(&pow(x, self.min)?);
+
+        Ok(acc)
+    }
+
+    /// Returns `f(yX)`.
+    pub fn scale(&self, y: S) -> Result<Self, SynthesisError> {
+        let mut power = pow(y, self.min)?;
+        let coeffs = self
+            .coeffs
+            .iter()
+            .map(|c| {
+                let term = *c * power;
+                power.mul_assign(&y);
+                term
+            })
+            .collect();
+
+        Ok(Laurent {
+            min: self.min,
+            coeffs,
+        })
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let mut sum = Laurent::zero(
+            std::cmp::min(self.min, other.min),
+            std::cmp::max(self.max(), other.max()),
+        );
+        for f in [self, other].iter() {
+            for (j, c) in f.coeffs.iter().enumerate() {
+                sum.add_term(f.min + j as i64, *c);
+            }
+        }
+
+        sum
+    }
+
+    /// Multiplies two polynomials using the FFT.
+    pub fn mul(&self, other: &Self, worker: &Worker) -> Result<Self, SynthesisError> {
+        let len = self.coeffs.len() + other.coeffs.len() - 1;
+        let pad = |coeffs: &[S]| {
+            let mut padded = coeffs.iter().map(|c| Scalar(*c)).collect::<Vec<_>>();
+            padded.resize(len, Scalar(S::zero()));
+            EvaluationDomain::from_coeffs(padded)
+        };
+
+        let mut a = pad(&self.coeffs)?;
+        let mut b = pad(&other.coeffs)?;
+        a.fft(worker);
+        b.fft(worker);
+        a.mul_assign(worker, &b);
+        a.ifft(worker);
+
+        let mut coeffs = a.into_coeffs().into_iter().map(|c| c.0).collect::<Vec<_>>();
+        coeffs.truncate(len);
+
+        Ok(Laurent {
+            min: self.min + other.min,
+            coeffs,
+        })
+    }
+
+    /// Returns `(f(X) - f(z)) / (X - z)`, which is again a Laurent polynomial.
+    ///
+    /// Writing `f(X) = X^m p(X)` with `m < 0`, the polynomial
+    /// `q(X) = p(X) - f(z) X^(-m)` vanishes at `z`, and the quotient is
+    /// `X^m q(X) / (X - z)`.
+    pub fn quotient(&self, z: S) -> Result<Self, SynthesisError> {
+        let value = self.evaluate(z)?;
+
+        let (min, mut q) = if self.min >= 0 {
+            let mut q = vec![S::zero(); self.min as usize];
+            q.extend_from_slice(&self.coeffs);
+            (0, q)
+        } else {
+            let shift = (-self.min) as usize;
+            let mut q = self.coeffs.clone();
+            if q.len() <= shift {
+                q.resize(shift + 1, S::zero());
+            }
+            (self.min, q)
+        };
+        q[(-min) as usize].sub_assign(&value);
+
+        // Synthetic division by (X - z); the remainder is zero.
+        let mut quotient = vec![S::zero(); q.len() - 1];
+        let mut carry = S::zero();
+        for (i, c) in q.iter().enumerate().skip(1).rev() {
+            carry.mul_assign(&z);
+            carry.add_assign(c);
+            quotient[i - 1] = carry;
+        }
+
+        Ok(Laurent {
+            min,
+            coeffs: quotient,
+        })
+    }
+}
+
+/// Computes `x^exp` for a possibly negative exponent.
+pub(crate) fn pow<S: PrimeField>(x: S, exp: i64) -> Result<S, SynthesisError> {
+    if exp >= 0 {
+        Ok(x.pow_vartime([exp as u64]))
+    } else {
+        let inv = x.invert();
+        if inv.is_none().into() {
+            return Err(SynthesisError::DivisionByZero);
+        }
+        Ok(inv.unwrap().pow_vartime([(-exp) as u64]))
+    }
+}
@@ -0,0 +1,76 @@
+use ff::Field;
+use pairing::Engine;
+use rand_core::RngCore;
+use std::ops::Neg;
+
+use super::poly::Laurent;
+use super::{AdaptedCircuit, Proof, SRS};
+use crate::multicore::Worker;
+use crate::transcript::Transcript;
+use crate::{Circuit, SynthesisError};
+
+/// Creates a Sonic proof for `circuit`, which must fit in the reference
+/// string (see [`AdaptedCircuit::required_srs_degree`]).
+pub fn create_proof<E, C, R>(
+    circuit: C,
+    srs: &SRS<E>,
+    rng: &mut R,
+) -> Result<Proof<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E::Fr>,
+    R: RngCore,
+{
+    let (structure, assignment, inputs) = AdaptedCircuit::with_witness(circuit)?;
+    if srs.d() < structure.required_srs_degree() {
+        return Err(SynthesisError::PolynomialDegreeTooLarge);
+    }
+
+    let worker = Worker::new();
+    let n = structure.n() as i64;
+    let d = srs.d() as i64;
+
+    // r(X, 1), blinded with four random coefficients below the degrees the
+    // verifier ever sees in s(X, Y).
+    let mut r = Laurent::zero(-2 * n - 4, n);
+    for i in 1..=n {
+        let gate = (i - 1) as usize;
+        r.add_term(i, assignment.a[gate]);
+        r.add_term(-i, assignment.b[gate]);
+        r.add_term(-i - n, assignment.c[gate]);
+    }
+    for j in 1..=4 {
+        r.add_term(-2 * n - j, E::Fr::random(&mut *rng));
+    }
+
+    let r_commitment = srs.commit(&worker, n, &r)?;
+
+    let mut transcript = super::transcript(&inputs);
+    transcript.absorb_point(b"r", &r_commitment);
+    let y = transcript.squeeze_challenge(b"y");
+
+    // t(X, y) = r(X, 1) (r(X, y) + s(X, y)) - k(y), whose constant term is
+    // zero exactly when the constraints are satisfied.
+    let r_prime = r.scale(y)?.add(&structure.s_poly_x(y)?);
+    let mut t = r.mul(&r_prime, &worker)?;
+    t.add_term(0, structure.k(&inputs, y)?.neg());
+
+    let t_commitment = srs.commit(&worker, d, &t)?;
+
+    transcript.absorb_point(b"t", &t_commitment);
+    let z = transcript.squeeze_challenge(b"z");
+
+    let (rz, z_opening) = srs.open(&worker, &r, z)?;
+    let (rzy, zy_opening) = srs.open(&worker, &r, y * &z)?;
+    let (_, t_opening) = srs.open(&worker, &t, z)?;
+
+    Ok(Proof {
+        r: r_commitment,
+        t: t_commitment,
+        rz,
+        rzy,
+        z_opening,
+        zy_opening,
+        t_opening,
+    })
+}
@@ -0,0 +1,525 @@
+use ff::{Field, PrimeField};
+use group::{
+    prime::{PrimeCurve, PrimeCurveAffine},
+    Curve, Group, GroupEncoding, UncompressedEncoding, Wnaf, WnafGroup,
+};
+use pairing::{Engine, MillerLoopResult, MultiMillerLoop};
+use rand_core::RngCore;
+use std::io::{self, Read, Write};
+use std::ops::{AddAssign, Neg};
+
+use super::poly::Laurent;
+use crate::multicore::Worker;
+use crate::multiexp::dense_multiexp;
+use crate::transcript::{Blake2sTranscript, Transcript};
+use crate::zeroize::{zeroize, Secret};
+use crate::{SynthesisError, VerificationError};
+
+/// The largest `d` that [`SRS::read`] accepts, so that the number of points
+/// it reads fits in a `u32`.
+const MAX_D: usize = (u32::MAX as usize - 1) / 2;
+
+/// The universal structured reference string of Sonic, supporting Laurent
+/// polynomials with degrees in `-d..=d`.
+///
+/// For secret `x` and `alpha` it contains `g^(x^i)` for `-d <= i <= d`,
+/// `g^(alpha x^i)` for `i != 0`, `h^(x^i)` for `-d <= i <= d`, `h^alpha` and
+/// `h^(alpha x)`. The missing `g^alpha` is what allows the verifier to check
+/// that a committed polynomial has no constant term.
+///
+/// Anyone can [`update`](SRS::update) the reference string with their own
+/// secrets, and publish an [`UpdateProof`] that others check with
+/// [`verify_update`](SRS::verify_update).
+#[derive(Clone)]
+pub struct SRS<E: Engine> {
+    d: usize,
+
+    // g^(x^i) for i in -d..=d, indexed by i + d.
+    g_x: Vec<E::G1Affine>,
+
+    // g^(alpha x^i) for i in -d..=d, indexed by i + d. The entry for i = 0 is
+    // the identity, and is never used.
+    g_alpha_x: Vec<E::G1Affine>,
+
+    // h^(x^i) for i in -d..=d, indexed by i + d.
+    h_x: Vec<E::G2Affine>,
+
+    h_alpha: E::G2Affine,
+    h_alpha_x: E::G2Affine,
+}
+
+impl<E: Engine> PartialEq for SRS<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.d == other.d
+            && self.g_x == other.g_x
+            && self.g_alpha_x == other.g_alpha_x
+            && self.h_x == other.h_x
+            && self.h_alpha == other.h_alpha
+            && self.h_alpha_x == other.h_alpha_x
+    }
+}
+
+impl<E> SRS<E>
+where
+    E: Engine,
+    E::G1: WnafGroup,
+    E::G2: WnafGroup,
+{
+    /// Generates a reference string of size `d` from the secrets `x` and
+    /// `alpha`. This is only suitable for testing; real deployments must
+    /// obtain the reference string from an updatable ceremony.
+    pub fn new(d: usize, x: E::Fr, alpha: E::Fr) -> Result<Self, SynthesisError> {
+        let x_inv = x.invert();
+        if x_inv.is_none().into() {
+            return Err(SynthesisError::UnexpectedIdentity);
+        }
+        let x_inv = x_inv.unwrap();
+
+        // Powers x^i for i in -d..=d.
+        let mut powers = vec![E::Fr::one(); 2 * d + 1];
+        for i in 1..=d {
+            powers[d + i] = powers[d + i - 1] * &x;
+            powers[d - i] = powers[d - i + 1] * &x_inv;
+        }
+
+        let mut g1_wnaf = Wnaf::new();
+        let mut g1_wnaf = g1_wnaf.base(E::G1::generator(), 2 * powers.len());
+        let mut g2_wnaf = Wnaf::new();
+        let mut g2_wnaf = g2_wnaf.base(E::G2::generator(), powers.len());
+
+        let g_x = powers.iter().map(|p| g1_wnaf.scalar(p)).collect::<Vec<_>>();
+        let g_alpha_x = powers
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                if i == d {
+                    E::G1::identity()
+                } else {
+                    g1_wnaf.scalar(&(*p * &alpha))
+                }
+            })
+            .collect::<Vec<_>>();
+        let h_x = powers.iter().map(|p| g2_wnaf.scalar(p)).collect::<Vec<_>>();
+
+        let h_alpha = g2_wnaf.scalar(&alpha).to_affine();
+        let h_alpha_x = g2_wnaf.scalar(&(alpha * &x)).to_affine();
+
+        let normalize_g1 = |points: &[E::G1]| {
+            let mut affine = vec![E::G1Affine::identity(); points.len()];
+            E::G1::batch_normalize(points, &mut affine);
+            affine
+        };
+        let g_x_affine = normalize_g1(&g_x);
+        let g_alpha_x_affine = normalize_g1(&g_alpha_x);
+        let mut h_x_affine = vec![E::G2Affine::identity(); h_x.len()];
+        E::G2::batch_normalize(&h_x, &mut h_x_affine);
+
+        Ok(SRS {
+            d,
+            g_x: g_x_affine,
+            g_alpha_x: g_alpha_x_affine,
+            h_x: h_x_affine,
+            h_alpha,
+            h_alpha_x,
+        })
+    }
+
+    /// Generates a reference string of size `d` from random secrets.
+    pub fn random<R: RngCore>(d: usize, rng: &mut R) -> Result<Self, SynthesisError> {
+        Self::new(d, E::Fr::random(&mut *rng), E::Fr::random(&mut *rng))
+    }
+
+    /// Updates the reference string with random secrets `x'` and `alpha'`,
+    /// so that it is the one for `x x'` and `alpha alpha'`, and returns it
+    /// with the proof to publish alongside. The secrets are erased before
+    /// this returns; as long as one updater erases theirs, nobody knows the
+    /// secrets of the result.
+    pub fn update<R: RngCore>(&self, rng: &mut R) -> (Self, UpdateProof<E>) {
+        let nonzero = |rng: &mut R| loop {
+            let t = Secret::new(E::Fr::random(&mut *rng), E::Fr::zero());
+            if !t.is_zero() {
+                break t;
+            }
+        };
+        let x = nonzero(rng);
+        let alpha = nonzero(rng);
+        let x_inv = Secret::new(x.invert().unwrap(), E::Fr::zero());
+        let alpha_x = Secret::new(*alpha * &*x, E::Fr::zero());
+
+        let d = self.d;
+        let mut powers = vec![E::Fr::one(); 2 * d + 1];
+        for i in 1..=d {
+            powers[d + i] = powers[d + i - 1] * &*x;
+            powers[d - i] = powers[d - i + 1] * &*x_inv;
+        }
+        let mut alpha_powers = powers.iter().map(|p| *p * &*alpha).collect::<Vec<_>>();
+
+        fn scale<G: PrimeCurve>(points: &[G::Affine], scalars: &[G::Scalar]) -> Vec<G::Affine> {
+            let projective = points
+                .iter()
+                .zip(scalars)
+                .map(|(p, s)| p.to_curve() * s)
+                .collect::<Vec<_>>();
+            let mut affine = vec![G::Affine::identity(); projective.len()];
+            G::batch_normalize(&projective, &mut affine);
+            affine
+        }
+        let next = SRS {
+            d,
+            g_x: scale::<E::G1>(&self.g_x, &powers),
+            g_alpha_x: scale::<E::G1>(&self.g_alpha_x, &alpha_powers),
+            h_x: scale::<E::G2>(&self.h_x, &powers),
+            h_alpha: (self.h_alpha * &*alpha).to_affine(),
+            h_alpha_x: (self.h_alpha_x * &*alpha_x).to_affine(),
+        };
+        zeroize(&mut powers, E::Fr::zero());
+        zeroize(&mut alpha_powers, E::Fr::zero());
+
+        let mut transcript = update_transcript(self, &next);
+        let proof = UpdateProof {
+            alpha: prove_knowledge::<E, _>(&mut transcript, self.h_alpha, &alpha, rng),
+            alpha_x: prove_knowledge::<E, _>(&mut transcript, self.h_alpha_x, &alpha_x, rng),
+        };
+        (next, proof)
+    }
+}
+
+impl<E: MultiMillerLoop> SRS<E> {
+    /// Verifies that `next` was derived from this reference string by
+    /// [`SRS::update`], with secrets that the updater knew, and that it is
+    /// well formed.
+    ///
+    /// The proof shows knowledge of `alpha'` and `alpha' x'`, the factors by
+    /// which `h^alpha` and `h^(alpha x)` were raised. The powers of `next`
+    /// are then checked against each other with random linear combinations,
+    /// which ties them to the same `x x'` and `alpha alpha'`.
+    pub fn verify_update<R: RngCore>(
+        &self,
+        next: &SRS<E>,
+        proof: &UpdateProof<E>,
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
+        let d = self.d;
+        if next.d != d
+            || next.g_x[d] != self.g_x[d]
+            || next.h_x[d] != self.h_x[d]
+            || bool::from(next.h_alpha.is_identity() | next.h_alpha_x.is_identity())
+        {
+            return Err(VerificationError::InvalidVerifyingKey);
+        }
+
+        let mut transcript = update_transcript(self, next);
+        if !verify_knowledge::<E>(&mut transcript, self.h_alpha, next.h_alpha, &proof.alpha)
+            || !verify_knowledge::<E>(
+                &mut transcript,
+                self.h_alpha_x,
+                next.h_alpha_x,
+                &proof.alpha_x,
+            )
+        {
+            return Err(VerificationError::InvalidProof);
+        }
+        if d == 0 {
+            // Only `h^alpha` and `h^(alpha x)` depend on the secrets.
+            return Ok(());
+        }
+
+        let worker = Worker::new();
+        let g = next.g_x[d];
+        let h = next.h_x[d];
+        let g_x = next.g_x[d + 1];
+        let h_x = next.h_x[d + 1];
+        let mut well_formed = || -> Result<bool, SynthesisError> {
+            let mut random =
+                |n: usize| (0..n).map(|_| E::Fr::random(&mut *rng)).collect::<Vec<_>>();
+            let g1 = |bases: &[E::G1Affine], coeffs: &[E::Fr]| {
+                dense_multiexp::<E::G1>(&worker, bases, coeffs).map(|p| p.to_affine())
+            };
+            let g2 = |bases: &[E::G2Affine], coeffs: &[E::Fr]| {
+                dense_multiexp::<E::G2>(&worker, bases, coeffs).map(|p| p.to_affine())
+            };
+            let pairs = |terms: &[(E::G1Affine, E::G2Affine)]| {
+                let prepared = terms
+                    .iter()
+                    .map(|(p, q)| (*p, E::G2Prepared::from(*q)))
+                    .collect::<Vec<_>>();
+                let terms = prepared.iter().map(|(p, q)| (p, q)).collect::<Vec<_>>();
+                bool::from(
+                    E::multi_miller_loop(&terms)
+                        .final_exponentiation()
+                        .is_identity(),
+                )
+            };
+
+            // Consecutive powers differ by x, in G1 and in G2.
+            let c = random(2 * d);
+            let (g1_low, g1_high) = (g1(&next.g_x[..2 * d], &c)?, g1(&next.g_x[1..], &c)?);
+            let (g2_low, g2_high) = (g2(&next.h_x[..2 * d], &c)?, g2(&next.h_x[1..], &c)?);
+
+            // Each power with alpha is the one without raised to alpha. The
+            // identity at `i = d` is on both sides.
+            let c = random(2 * d + 1);
+            let mut g_x_without = next.g_x.clone();
+            g_x_without[d] = E::G1Affine::identity();
+            let alpha_powers = g1(&next.g_alpha_x, &c)?;
+            let powers = g1(&g_x_without, &c)?;
+
+            Ok(pairs(&[(g1_high, h), (g1_low.neg(), h_x)])
+                && pairs(&[(g, g2_high), (g_x.neg(), g2_low)])
+                && pairs(&[(alpha_powers, h), (powers.neg(), next.h_alpha)])
+                && pairs(&[(g, next.h_alpha_x), (g_x.neg(), next.h_alpha)]))
+        };
+        if let Ok(true) = well_formed() {
+            Ok(())
+        } else {
+            Err(VerificationError::InvalidVerifyingKey)
+        }
+    }
+}
+
+impl<E: Engine> SRS<E> {
+    /// Returns the maximum absolute degree `d` supported by this reference
+    /// string.
+    pub fn d(&self) -> usize {
+        self.d
+    }
+
+    pub(crate) fn g(&self) -> E::G1Affine {
+        self.g_x[self.d]
+    }
+
+    pub(crate) fn h_alpha(&self) -> E::G2Affine {
+        self.h_alpha
+    }
+
+    pub(crate) fn h_alpha_x(&self) -> E::G2Affine {
+        self.h_alpha_x
+    }
+
+    /// Returns `h^(x^(max - d))`, against which commitments with maximum
+    /// degree `max` are checked.
+    pub(crate) fn h_for_max(&self, max: i64) -> E::G2Affine {
+        self.h_x[max as usize]
+    }
+
+    /// Commits to `f` as `g^(alpha x^(d - max) f(x))`.
+    ///
+    /// Fails with `PolynomialDegreeTooLarge` if `f` has a term of degree
+    /// above `max`, or below what the reference string supports, and with
+    /// `Unsatisfiable` if the shifted polynomial has a constant term.
+    pub(crate) fn commit(
+        &self,
+        worker: &Worker,
+        max: i64,
+        f: &Laurent<E::Fr>,
+    ) -> Result<E::G1Affine, SynthesisError> {
+        let d = self.d as i64;
+        let mut bases = Vec::with_capacity(f.coeffs.len());
+        let mut scalars = Vec::with_capacity(f.coeffs.len());
+
+        for (j, coeff) in f.coeffs.iter().enumerate() {
+            if coeff.is_zero() {
+                continue;
+            }
+
+            let degree = f.min + j as i64;
+            let index = d - max + degree;
+            if degree > max || index < -d || index > d {
+                return Err(SynthesisError::PolynomialDegreeTooLarge);
+            }
+            if index == 0 {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            bases.push(self.g_alpha_x[(index + d) as usize]);
+            scalars.push(*coeff);
+        }
+
+        Ok(dense_multiexp::<E::G1>(worker, &bases, &scalars)?.to_affine())
+    }
+
+    /// Opens `f` at `z`, returning `f(z)` and the witness `g^w(x)` for
+    /// `w(X) = (f(X) - f(z)) / (X - z)`.
+    pub(crate) fn open(
+        &self,
+        worker: &Worker,
+        f: &Laurent<E::Fr>,
+        z: E::Fr,
+    ) -> Result<(E::Fr, E::G1Affine), SynthesisError> {
+        let d = self.d as i64;
+        let value = f.evaluate(z)?;
+        let w = f.quotient(z)?;
+
+        if w.min < -d || w.max() > d {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+        let start = (w.min + d) as usize;
+        let bases = &self.g_x[start..(start + w.coeffs.len())];
+        let opening = dense_multiexp::<E::G1>(worker, bases, &w.coeffs)?.to_affine();
+
+        Ok((value, opening))
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.d as u32).to_be_bytes())?;
+        for g in self.g_x.iter() {
+            writer.write_all(g.to_uncompressed().as_ref())?;
+        }
+        for (i, g) in self.g_alpha_x.iter().enumerate() {
+            if i != self.d {
+                writer.write_all(g.to_uncompressed().as_ref())?;
+            }
+        }
+        for h in self.h_x.iter() {
+            writer.write_all(h.to_uncompressed().as_ref())?;
+        }
+        writer.write_all(self.h_alpha.to_uncompressed().as_ref())?;
+        writer.write_all(self.h_alpha_x.to_uncompressed().as_ref())?;
+
+        Ok(())
+    }
+
+    /// Reads a reference string written by [`SRS::write`]. Points are checked
+    /// to be on the curve and in the prime-order subgroup.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        fn read_point<G: UncompressedEncoding, R: Read>(reader: &mut R) -> io::Result<G> {
+            let mut repr = G::Uncompressed::default();
+            reader.read_exact(repr.as_mut())?;
+
+            let point = G::from_uncompressed(&repr);
+            if point.is_some().into() {
+                Ok(point.unwrap())
+            } else {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "invalid point"))
+            }
+        }
+
+        let mut d = [0u8; 4];
+        reader.read_exact(&mut d)?;
+        let d = u32::from_be_bytes(d) as usize;
+        if d > MAX_D {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported reference string size",
+            ));
+        }
+
+        let g_x = (0..(2 * d + 1))
+            .map(|_| read_point(&mut reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let g_alpha_x = (0..(2 * d + 1))
+            .map(|i| {
+                if i == d {
+                    Ok(E::G1Affine::identity())
+                } else {
+                    read_point(&mut reader)
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let h_x = (0..(2 * d + 1))
+            .map(|_| read_point(&mut reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let h_alpha = read_point(&mut reader)?;
+        let h_alpha_x = read_point(&mut reader)?;
+
+        Ok(SRS {
+            d,
+            g_x,
+            g_alpha_x,
+            h_x,
+            h_alpha,
+            h_alpha_x,
+        })
+    }
+}
+
+/// A proof published with a reference string updated by [`SRS::update`]:
+/// Schnorr proofs of knowledge of `alpha'` and of `alpha' x'`, with `h^alpha`
+/// and `h^(alpha x)` of the previous reference string as their bases.
+#[derive(Clone)]
+pub struct UpdateProof<E: Engine> {
+    alpha: (E::G2Affine, E::Fr),
+    alpha_x: (E::G2Affine, E::Fr),
+}
+
+impl<E: Engine> PartialEq for UpdateProof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.alpha == other.alpha && self.alpha_x == other.alpha_x
+    }
+}
+
+impl<E: Engine> UpdateProof<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (commitment, response) in &[self.alpha, self.alpha_x] {
+            writer.write_all(commitment.to_bytes().as_ref())?;
+            writer.write_all(response.to_repr().as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut read = || -> io::Result<(E::G2Affine, E::Fr)> {
+            let mut g2_repr = <E::G2Affine as GroupEncoding>::Repr::default();
+            reader.read_exact(g2_repr.as_mut())?;
+            let commitment = Option::from(E::G2Affine::from_bytes(&g2_repr))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid G2"))?;
+
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            reader.read_exact(repr.as_mut())?;
+            let response = E::Fr::from_repr(repr)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid scalar"))?;
+
+            Ok((commitment, response))
+        };
+
+        Ok(UpdateProof {
+            alpha: read()?,
+            alpha_x: read()?,
+        })
+    }
+}
+
+/// Starts the transcript of the proof that `next` updates `srs`.
+fn update_transcript<E: Engine>(srs: &SRS<E>, next: &SRS<E>) -> Blake2sTranscript<E::Fr> {
+    let mut transcript = Blake2sTranscript::new(b"bellman-sonic-update");
+    transcript.absorb_u64(b"d", srs.d as u64);
+    for s in &[srs, next] {
+        transcript.absorb_point(b"h_alpha", &s.h_alpha);
+        transcript.absorb_point(b"h_alpha_x", &s.h_alpha_x);
+    }
+    transcript
+}
+
+/// Proves knowledge of `secret`, the discrete logarithm of `base * secret`
+/// to `base`.
+fn prove_knowledge<E: Engine, R: RngCore>(
+    transcript: &mut Blake2sTranscript<E::Fr>,
+    base: E::G2Affine,
+    secret: &E::Fr,
+    rng: &mut R,
+) -> (E::G2Affine, E::Fr) {
+    let k = Secret::new(E::Fr::random(&mut *rng), E::Fr::zero());
+    let commitment = (base * &*k).to_affine();
+    transcript.absorb_point(b"commitment", &commitment);
+    let c = transcript.squeeze_challenge(b"challenge");
+
+    (commitment, *k + &(c * secret))
+}
+
+/// Checks a proof of [`prove_knowledge`] that `statement` is `base` raised
+/// to a known secret.
+fn verify_knowledge<E: Engine>(
+    transcript: &mut Blake2sTranscript<E::Fr>,
+    base: E::G2Affine,
+    statement: E::G2Affine,
+    proof: &(E::G2Affine, E::Fr),
+) -> bool {
+    let (commitment, response) = proof;
+    transcript.absorb_point(b"commitment", commitment);
+    let c = transcript.squeeze_challenge(b"challenge");
+
+    let mut rhs = statement * &c;
+    AddAssign::<&E::G2Affine>::add_assign(&mut rhs, commitment);
+    base * response == rhs
+}
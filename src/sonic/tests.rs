@@ -0,0 +1,280 @@
+use bls12_381::{Bls12, Scalar};
+use rand_core::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use std::io;
+
+use super::poly::Laurent;
+use super::*;
+use crate::multicore::Worker;
+use crate::{Circuit, ConstraintSystem, SynthesisError, VerificationError};
+
+/// Proves knowledge of `x` such that `x^3 + x + 5 = out`.
+#[derive(Clone)]
+struct CubeCircuit {
+    x: Option<Scalar>,
+    out: Option<Scalar>,
+}
+
+impl Circuit<Scalar> for CubeCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x_value = self.x;
+        let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+        let x2_value = x_value.map(|x| x.square());
+        let x2 = cs.alloc(
+            || "x2",
+            || x2_value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(|| "x2 = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+        let x3_value = x2_value.and_then(|x2| x_value.map(|x| x2 * x));
+        let x3 = cs.alloc(
+            || "x3",
+            || x3_value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(|| "x3 = x2 * x", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
+
+        let out = cs.alloc_input(
+            || "out",
+            || self.out.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        cs.enforce(
+            || "out = x3 + x + 5",
+            |lc| lc + x3 + x + (Scalar::from(5), CS::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + out,
+        );
+
+        Ok(())
+    }
+}
+
+fn cube(x: u64) -> CubeCircuit {
+    let x = Scalar::from(x);
+    CubeCircuit {
+        x: Some(x),
+        out: Some(x.square() * x + x + Scalar::from(5)),
+    }
+}
+
+fn setup() -> (XorShiftRng, AdaptedCircuit<Scalar>, SRS<Bls12>) {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let circuit = AdaptedCircuit::new(CubeCircuit { x: None, out: None }).unwrap();
+    let srs = SRS::random(circuit.required_srs_degree(), &mut rng).unwrap();
+
+    (rng, circuit, srs)
+}
+
+#[test]
+fn laurent_arithmetic() {
+    let worker = Worker::new();
+    let f = Laurent {
+        min: -2,
+        coeffs: vec![
+            Scalar::from(3),
+            Scalar::zero(),
+            Scalar::from(7),
+            Scalar::one(),
+        ],
+    };
+    let z = Scalar::from(11);
+    let x = Scalar::from(5);
+
+    // (f(x) - f(z)) = w(x) (x - z)
+    let w = f.quotient(z).unwrap();
+    assert_eq!(
+        f.evaluate(x).unwrap() - f.evaluate(z).unwrap(),
+        w.evaluate(x).unwrap() * (x - z)
+    );
+
+    let g = Laurent {
+        min: 1,
+        coeffs: vec![Scalar::from(2), Scalar::from(9)],
+    };
+    let w = g.quotient(z).unwrap();
+    assert_eq!(
+        g.evaluate(x).unwrap() - g.evaluate(z).unwrap(),
+        w.evaluate(x).unwrap() * (x - z)
+    );
+
+    let fg = f.mul(&g, &worker).unwrap();
+    assert_eq!(
+        fg.evaluate(x).unwrap(),
+        f.evaluate(x).unwrap() * g.evaluate(x).unwrap()
+    );
+    assert_eq!(
+        f.add(&g).evaluate(x).unwrap(),
+        f.evaluate(x).unwrap() + g.evaluate(x).unwrap()
+    );
+    assert_eq!(
+        f.scale(z).unwrap().evaluate(x).unwrap(),
+        f.evaluate(z * x).unwrap()
+    );
+}
+
+#[test]
+fn s_polynomial_is_consistent() {
+    let (_, circuit, _) = setup();
+    let x = Scalar::from(3);
+    let y = Scalar::from(7);
+
+    let s = circuit.s_eval(x, y).unwrap();
+    assert_eq!(circuit.s_poly_x(y).unwrap().evaluate(x).unwrap(), s);
+    assert_eq!(circuit.s_poly_y(x).unwrap().evaluate(y).unwrap(), s);
+}
+
+#[test]
+fn prove_and_verify() {
+    let (mut rng, circuit, srs) = setup();
+    let inputs = [cube(3).out.unwrap()];
+
+    let proof = create_proof(cube(3), &srs, &mut rng).unwrap();
+    assert!(verify_proof(&srs, &circuit, &proof, &inputs).is_ok());
+
+    let wrong = [inputs[0] + Scalar::one()];
+    assert!(matches!(
+        verify_proof(&srs, &circuit, &proof, &wrong),
+        Err(VerificationError::InvalidProof)
+    ));
+
+    let mut tampered = proof.clone();
+    tampered.rz += Scalar::one();
+    assert!(verify_proof(&srs, &circuit, &tampered, &inputs).is_err());
+
+    let mut bytes = vec![];
+    proof.write(&mut bytes).unwrap();
+    assert!(Proof::<Bls12>::read(&bytes[..]).unwrap() == proof);
+}
+
+#[test]
+fn unsatisfied_circuit_cannot_be_proven() {
+    let (mut rng, _, srs) = setup();
+    let mut circuit = cube(3);
+    circuit.out = Some(Scalar::from(4));
+
+    assert!(matches!(
+        create_proof(circuit, &srs, &mut rng),
+        Err(SynthesisError::Unsatisfiable)
+    ));
+}
+
+#[test]
+fn srs_too_small() {
+    let (mut rng, circuit, _) = setup();
+    let srs = SRS::<Bls12>::random(circuit.required_srs_degree() - 1, &mut rng).unwrap();
+
+    assert!(matches!(
+        create_proof(cube(3), &srs, &mut rng),
+        Err(SynthesisError::PolynomialDegreeTooLarge)
+    ));
+}
+
+#[test]
+fn srs_serialization() {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+    let srs = SRS::<Bls12>::random(4, &mut rng).unwrap();
+
+    let mut bytes = vec![];
+    srs.write(&mut bytes).unwrap();
+    assert!(SRS::<Bls12>::read(&bytes[..]).unwrap() == srs);
+
+    // A size whose points would not fit in a u32 is rejected before reading.
+    bytes[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert_eq!(
+        SRS::<Bls12>::read(&bytes[..]).err().unwrap().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn srs_update() {
+    let (mut rng, circuit, srs) = setup();
+
+    let (next, update) = srs.update(&mut rng);
+    assert!(next != srs);
+    assert!(srs.verify_update(&next, &update, &mut rng).is_ok());
+    let (last, second) = next.update(&mut rng);
+    assert!(next.verify_update(&last, &second, &mut rng).is_ok());
+
+    let mut bytes = vec![];
+    update.write(&mut bytes).unwrap();
+    assert!(UpdateProof::<Bls12>::read(&bytes[..]).unwrap() == update);
+
+    // Proofs with the updated reference string verify against it.
+    let c = cube(3);
+    let inputs = vec![c.out.unwrap()];
+    let proof = create_proof(c, &last, &mut rng).unwrap();
+    assert!(verify_proof(&last, &circuit, &proof, &inputs).is_ok());
+
+    // The proof is bound to the reference strings it relates.
+    assert_eq!(
+        srs.verify_update(&last, &update, &mut rng),
+        Err(VerificationError::InvalidProof)
+    );
+    assert_eq!(
+        next.verify_update(&last, &update, &mut rng),
+        Err(VerificationError::InvalidProof)
+    );
+    let other = SRS::<Bls12>::random(srs.d(), &mut rng).unwrap();
+    assert!(srs.verify_update(&other, &update, &mut rng).is_err());
+
+    // A reference string whose powers of x are inconsistent is rejected,
+    // even with a valid proof for its h^alpha and h^(alpha x).
+    let mut bytes = vec![];
+    next.write(&mut bytes).unwrap();
+    let size = 96;
+    let (first, second) = bytes[4..].split_at_mut(size);
+    first.copy_from_slice(&second[..size]);
+    let forged = SRS::<Bls12>::read(&bytes[..]).unwrap();
+    assert_eq!(
+        srs.verify_update(&forged, &update, &mut rng),
+        Err(VerificationError::InvalidVerifyingKey)
+    );
+
+    // Without powers of x, only h^alpha and h^(alpha x) are updated.
+    let empty = SRS::<Bls12>::random(0, &mut rng).unwrap();
+    let (next, update) = empty.update(&mut rng);
+    assert!(empty.verify_update(&next, &update, &mut rng).is_ok());
+}
+
+#[test]
+fn helped_batch_verification() {
+    let (mut rng, circuit, srs) = setup();
+
+    let statements = [3u64, 10]
+        .iter()
+        .map(|x| {
+            let c = cube(*x);
+            let inputs = vec![c.out.unwrap()];
+            let proof = create_proof(c, &srs, &mut rng).unwrap();
+            let advice = create_advice(&srs, &circuit, &proof, &inputs).unwrap();
+            (proof, inputs, advice)
+        })
+        .collect::<Vec<_>>();
+    let batch = statements
+        .iter()
+        .map(|(proof, inputs, advice)| (proof, &inputs[..], advice))
+        .collect::<Vec<_>>();
+
+    let aggregate = create_aggregate(&srs, &circuit, &batch).unwrap();
+    assert!(verify_aggregate(&srs, &circuit, &batch, &aggregate).is_ok());
+
+    // Advice that lies about s(z, y) is rejected.
+    let mut bad_advice = statements[0].2.clone();
+    bad_advice.szy += Scalar::one();
+    let mut bad_batch = batch.clone();
+    bad_batch[0].2 = &bad_advice;
+    assert!(verify_aggregate(&srs, &circuit, &bad_batch, &aggregate).is_err());
+
+    // So is an aggregate for a different batch.
+    assert!(verify_aggregate(&srs, &circuit, &batch[..1], &aggregate).is_err());
+
+    // Check the advice alone is consistent with a direct evaluation.
+    let (proof, inputs, advice) = &statements[1];
+    let (y, z) = proof.challenges(inputs);
+    assert_eq!(advice.szy, circuit.s_eval(z, y).unwrap());
+}
@@ -0,0 +1,168 @@
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Curve, Group};
+use pairing::{MillerLoopResult, MultiMillerLoop};
+use std::ops::{AddAssign, MulAssign, Neg};
+
+use super::{AdaptedCircuit, Proof, SRS};
+use crate::transcript::{Blake2sTranscript, Transcript};
+use crate::VerificationError;
+
+/// Verifies a Sonic proof without a helper, by evaluating `s(z, y)` directly.
+/// This takes time linear in the size of the circuit; batches of proofs can
+/// instead be checked with the help of an [`Aggregate`].
+///
+/// [`Aggregate`]: crate::sonic::Aggregate
+pub fn verify_proof<E: MultiMillerLoop>(
+    srs: &SRS<E>,
+    circuit: &AdaptedCircuit<E::Fr>,
+    proof: &Proof<E>,
+    inputs: &[E::Fr],
+) -> Result<(), VerificationError> {
+    let (y, z) = proof.challenges(inputs);
+    let szy = circuit
+        .s_eval(z, y)
+        .map_err(|_| VerificationError::InvalidProof)?;
+
+    let mut batch = PairingBatch::new(srs);
+    batch.add_proof(circuit, proof, inputs, y, z, szy)?;
+
+    batch.finalize()
+}
+
+/// A batch of polynomial commitment openings, checked together with a
+/// single multi-pairing.
+pub(crate) struct PairingBatch<'a, E: MultiMillerLoop> {
+    srs: &'a SRS<E>,
+    transcript: Blake2sTranscript<E::Fr>,
+    openings: Vec<Opening<E>>,
+}
+
+struct Opening<E: MultiMillerLoop> {
+    commitment: E::G1Affine,
+    max: i64,
+    point: E::Fr,
+    value: E::Fr,
+    opening: E::G1Affine,
+}
+
+impl<'a, E: MultiMillerLoop> PairingBatch<'a, E> {
+    pub fn new(srs: &'a SRS<E>) -> Self {
+        PairingBatch {
+            srs,
+            transcript: Blake2sTranscript::new(b"bellman-sonic-batch"),
+            openings: vec![],
+        }
+    }
+
+    /// Queues the check that `commitment` (to a polynomial with maximum
+    /// degree `max`) opens to `value` at `point`.
+    pub fn add_opening(
+        &mut self,
+        commitment: E::G1Affine,
+        max: i64,
+        point: E::Fr,
+        value: E::Fr,
+        opening: E::G1Affine,
+    ) {
+        self.transcript.absorb_point(b"commitment", &commitment);
+        self.transcript.absorb_u64(b"max", max as u64);
+        self.transcript.absorb_scalar(b"point", &point);
+        self.transcript.absorb_scalar(b"value", &value);
+        self.transcript.absorb_point(b"opening", &opening);
+
+        self.openings.push(Opening {
+            commitment,
+            max,
+            point,
+            value,
+            opening,
+        });
+    }
+
+    /// Queues the checks for a single proof, given `s(z, y)`.
+    pub fn add_proof(
+        &mut self,
+        circuit: &AdaptedCircuit<E::Fr>,
+        proof: &Proof<E>,
+        inputs: &[E::Fr],
+        y: E::Fr,
+        z: E::Fr,
+        szy: E::Fr,
+    ) -> Result<(), VerificationError> {
+        if self.srs.d() < circuit.required_srs_degree() {
+            return Err(VerificationError::InvalidVerifyingKey);
+        }
+        let n = circuit.n() as i64;
+        let d = self.srs.d() as i64;
+
+        let k = circuit
+            .k(inputs, y)
+            .map_err(|_| VerificationError::InvalidVerifyingKey)?;
+
+        // t(z, y) = r(z, 1) (r(z, y) + s(z, y)) - k(y)
+        let mut t = proof.rzy;
+        t.add_assign(&szy);
+        t.mul_assign(&proof.rz);
+        t.add_assign(&k.neg());
+
+        self.add_opening(proof.r, n, z, proof.rz, proof.z_opening);
+        self.add_opening(proof.r, n, y * &z, proof.rzy, proof.zy_opening);
+        self.add_opening(proof.t, d, z, t, proof.t_opening);
+
+        Ok(())
+    }
+
+    /// Checks every queued opening. Each opening of `F` at `z` to `v` with
+    /// witness `W` satisfies
+    ///
+    /// ```text
+    /// e(W, h^(alpha x)) e(g^v W^(-z), h^alpha) = e(F, h^(x^(max - d)))
+    /// ```
+    ///
+    /// and the equations are combined with powers of a random challenge.
+    pub fn finalize(mut self) -> Result<(), VerificationError> {
+        let weight = self.transcript.squeeze_challenge(b"weight");
+
+        let mut w_acc = E::G1::identity();
+        let mut v_acc = E::Fr::zero();
+        let mut zw_acc = E::G1::identity();
+        let mut f_acc: Vec<(i64, E::G1)> = vec![];
+
+        let mut power = E::Fr::one();
+        for o in self.openings.iter() {
+            let w = o.opening * &power;
+            AddAssign::<&E::G1>::add_assign(&mut w_acc, &w);
+            AddAssign::<&E::G1>::add_assign(&mut zw_acc, &(w * &o.point));
+            v_acc.add_assign(&(o.value * &power));
+
+            let f = o.commitment * &power;
+            match f_acc.iter_mut().find(|(max, _)| *max == o.max) {
+                Some((_, acc)) => AddAssign::<&E::G1>::add_assign(acc, &f),
+                None => f_acc.push((o.max, f)),
+            }
+
+            power.mul_assign(&weight);
+        }
+
+        let mut g_acc = self.srs.g() * &v_acc;
+        AddAssign::<&E::G1>::add_assign(&mut g_acc, &zw_acc.neg());
+
+        let mut g1 = vec![w_acc, g_acc];
+        let mut g2 = vec![self.srs.h_alpha_x(), self.srs.h_alpha()];
+        for (max, f) in f_acc {
+            g1.push(f.neg());
+            g2.push(self.srs.h_for_max(max));
+        }
+
+        let mut g1_affine = vec![E::G1Affine::identity(); g1.len()];
+        E::G1::batch_normalize(&g1, &mut g1_affine);
+        let g2_prepared = g2.into_iter().map(E::G2Prepared::from).collect::<Vec<_>>();
+        let terms = g1_affine.iter().zip(g2_prepared.iter()).collect::<Vec<_>>();
+
+        if E::multi_miller_loop(&terms).final_exponentiation() == E::Gt::identity() {
+            Ok(())
+        } else {
+            Err(VerificationError::InvalidProof)
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Timing spans for the phases of parameter generation and proving.
+//!
+//! With the `tracing` feature enabled, [`groth16::generate_parameters`],
+//! [`groth16::create_proof`] and the FFT and multiexponentiation internals
+//! open a span for each phase they go through, and report it to the
+//! `Subscriber` installed with `set_subscriber`. Each span carries a name,
+//! its duration, and a few structured fields such as the number of
+//! constraints or the size of an FFT, so that a subscriber can forward them
+//! to the `tracing` crate or any other observability tooling.
+//!
+//! Without the feature, spans compile to nothing.
+//!
+//! [`groth16::generate_parameters`]: crate::groth16::generate_parameters
+//! [`groth16::create_proof`]: crate::groth16::create_proof
+
+#[cfg(feature = "tracing")]
+use std::sync::RwLock;
+#[cfg(feature = "tracing")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tracing")]
+use crate::multicore::Global;
+
+/// Receives the spans opened by this crate. Spans nest, and are entered and
+/// exited on the same thread.
+#[cfg(feature = "tracing")]
+pub trait Subscriber: Send + Sync {
+    /// Called when the span `name` is entered.
+    fn enter(&self, _name: &'static str) {}
+
+    /// Called when the span `name` is exited, `elapsed` after it was
+    /// entered, with the fields recorded while it was open.
+    fn exit(&self, name: &'static str, fields: &[(&'static str, u64)], elapsed: Duration);
+}
+
+#[cfg(feature = "tracing")]
+static SUBSCRIBER: Global<RwLock<Option<Box<dyn Subscriber>>>> = Global::new();
+
+/// Installs the subscriber for all threads, replacing any previous one.
+#[cfg(feature = "tracing")]
+pub fn set_subscriber<S: Subscriber + 'static>(subscriber: S) {
+    *SUBSCRIBER.get().write().unwrap() = Some(Box::new(subscriber));
+}
+
+/// Removes the installed subscriber, if any.
+#[cfg(feature = "tracing")]
+pub fn clear_subscriber() {
+    *SUBSCRIBER.get().write().unwrap() = None;
+}
+
+#[cfg(feature = "tracing")]
+struct Entered {
+    name: &'static str,
+    start: Instant,
+    fields: Vec<(&'static str, u64)>,
+}
+
+/// An open span, which is exited when dropped.
+#[must_use]
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    entered: Option<Entered>,
+}
+
+/// Enters the span `name`.
+#[inline(always)]
+#[allow(unused_variables)]
+pub(crate) fn span(name: &'static str) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        let subscriber = SUBSCRIBER.get().read().unwrap();
+        if let Some(subscriber) = subscriber.as_ref() {
+            subscriber.enter(name);
+        }
+
+        // Spans are also timed for the phase histogram of the metrics layer.
+        let entered = if subscriber.is_some() || crate::metrics::enabled() {
+            Some(Entered {
+                name,
+                start: Instant::now(),
+                fields: vec![],
+            })
+        } else {
+            None
+        };
+
+        Span { entered }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    Span {}
+}
+
+impl Span {
+    /// Records a field, which is reported when the span is exited.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    pub fn record(&mut self, field: &'static str, value: usize) {
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(entered) = self.entered.as_mut() {
+                entered.fields.push((field, value as u64));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(entered) = self.entered.take() {
+            let elapsed = entered.start.elapsed();
+            if let Some(subscriber) = SUBSCRIBER.get().read().unwrap().as_ref() {
+                subscriber.exit(entered.name, &entered.fields, elapsed);
+            }
+            crate::metrics::record_phase(entered.name, elapsed);
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::exporter::{Assignment, RawCircuit, ReplayCircuit};
+    use crate::groth16::{create_random_proof, generate_random_parameters};
+    use crate::{Circuit, ConstraintSystem, SynthesisError};
+    use bls12_381::{Bls12, Scalar};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Enter(&'static str),
+        Exit(&'static str, Vec<(&'static str, u64)>),
+    }
+
+    // Other tests may run concurrently, so only events from the test's own
+    // thread are recorded. Worker threads are therefore not seen either.
+    struct Recorder {
+        thread: ThreadId,
+        events: Arc<Mutex<Vec<Event>>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enter(&self, name: &'static str) {
+            if thread::current().id() == self.thread {
+                self.events.lock().unwrap().push(Event::Enter(name));
+            }
+        }
+
+        fn exit(&self, name: &'static str, fields: &[(&'static str, u64)], _: Duration) {
+            if thread::current().id() == self.thread {
+                let event = Event::Exit(name, fields.to_vec());
+                self.events.lock().unwrap().push(event);
+            }
+        }
+    }
+
+    struct Square(Option<Scalar>);
+
+    impl Circuit<Scalar> for Square {
+        fn synthesize<CS: ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || self.0.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    self.0
+                        .map(|x| x.square())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn records_proving_phases() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let params = generate_random_parameters::<Bls12, _, _>(Square(None), &mut rng).unwrap();
+        let circuit = ReplayCircuit {
+            circuit: RawCircuit::synthesize(Square(None)).unwrap(),
+            assignment: Some(Assignment::synthesize(Square(Some(Scalar::from(3)))).unwrap()),
+        };
+
+        let events = Arc::new(Mutex::new(vec![]));
+        set_subscriber(Recorder {
+            thread: thread::current().id(),
+            events: events.clone(),
+        });
+        create_random_proof(circuit, &params, &mut rng).unwrap();
+        clear_subscriber();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.first(), Some(&Event::Enter("create_proof")));
+        assert_eq!(events.last(), Some(&Event::Exit("create_proof", vec![])));
+        assert!(events.contains(&Event::Exit(
+            "synthesize",
+            vec![("inputs", 2), ("aux", 1), ("constraints", 3)]
+        )));
+        assert!(events.contains(&Event::Exit("fft", vec![("size", 4)])));
+        assert!(events.contains(&Event::Exit("multiexp", vec![])));
+    }
+}
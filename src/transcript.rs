@@ -0,0 +1,319 @@
+//! Fiat-Shamir transcripts.
+//!
+//! A [`Transcript`] records everything a verifier would have seen in an
+//! interactive protocol, and derives the verifier's challenges from it. Every
+//! message is absorbed together with a label, and protocols begin by calling
+//! [`Transcript::domain_separate`], so that challenges from unrelated
+//! protocols (or different positions in the same protocol) can never collide.
+//!
+//! Three implementations are provided: [`Blake2bTranscript`] and
+//! [`Blake2sTranscript`], which are fast natively, the first on 64-bit
+//! platforms and the second on 32-bit ones, and [`PoseidonTranscript`], whose
+//! challenges can be recomputed cheaply inside a circuit.
+//!
+//! [`Transcript`]: crate::transcript::Transcript
+//! [`Transcript::domain_separate`]: crate::transcript::Transcript::domain_separate
+//! [`Blake2bTranscript`]: crate::transcript::Blake2bTranscript
+//! [`Blake2sTranscript`]: crate::transcript::Blake2sTranscript
+//! [`PoseidonTranscript`]: crate::transcript::PoseidonTranscript
+
+use blake2b_simd::{Params as Blake2bParams, State as Blake2bState};
+use blake2s_simd::{Params as Blake2sParams, State as Blake2sState};
+use ff::PrimeField;
+use group::GroupEncoding;
+use std::marker::PhantomData;
+
+use crate::poseidon::{PoseidonParams, Sponge};
+
+/// A transcript deriving challenges in the field `S`.
+pub trait Transcript<S: PrimeField> {
+    /// Separates the messages that follow from everything absorbed so far,
+    /// starting a new protocol or sub-protocol called `label`.
+    fn domain_separate(&mut self, label: &'static [u8]);
+
+    /// Absorbs an arbitrary byte string.
+    fn absorb_bytes(&mut self, label: &'static [u8], bytes: &[u8]);
+
+    /// Absorbs a scalar.
+    fn absorb_scalar(&mut self, label: &'static [u8], scalar: &S);
+
+    /// Absorbs a group element using its canonical encoding.
+    fn absorb_point<G: GroupEncoding>(&mut self, label: &'static [u8], point: &G) {
+        self.absorb_bytes(label, point.to_bytes().as_ref());
+    }
+
+    /// Absorbs an unsigned integer, such as the length of a vector.
+    fn absorb_u64(&mut self, label: &'static [u8], value: u64) {
+        self.absorb_bytes(label, &value.to_le_bytes());
+    }
+
+    /// Derives a challenge from everything absorbed so far. The challenge is
+    /// itself absorbed, so consecutive calls return independent challenges.
+    fn squeeze_challenge(&mut self, label: &'static [u8]) -> S;
+}
+
+/// A transcript over BLAKE2b.
+#[derive(Clone)]
+pub struct Blake2bTranscript<S: PrimeField> {
+    state: Blake2bState,
+    _marker: PhantomData<S>,
+}
+
+impl<S: PrimeField> Blake2bTranscript<S> {
+    /// Creates a transcript for the application or protocol `label`.
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut transcript = Blake2bTranscript {
+            state: Blake2bParams::new().personal(b"bellman_").to_state(),
+            _marker: PhantomData,
+        };
+        transcript.domain_separate(label);
+
+        transcript
+    }
+
+    fn append(&mut self, tag: u8, label: &[u8], bytes: &[u8]) {
+        self.state.update(&[tag]);
+        self.state.update(&(label.len() as u64).to_le_bytes());
+        self.state.update(label);
+        self.state.update(&(bytes.len() as u64).to_le_bytes());
+        self.state.update(bytes);
+    }
+}
+
+impl<S: PrimeField> Transcript<S> for Blake2bTranscript<S> {
+    fn domain_separate(&mut self, label: &'static [u8]) {
+        self.append(b'D', label, &[]);
+    }
+
+    fn absorb_bytes(&mut self, label: &'static [u8], bytes: &[u8]) {
+        self.append(b'B', label, bytes);
+    }
+
+    fn absorb_scalar(&mut self, label: &'static [u8], scalar: &S) {
+        self.append(b'S', label, scalar.to_repr().as_ref());
+    }
+
+    fn squeeze_challenge(&mut self, label: &'static [u8]) -> S {
+        self.append(b'C', label, &[]);
+
+        // A single output is 512 bits, enough for the challenge to be
+        // statistically close to uniform.
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(self.state.clone().finalize().as_bytes());
+        self.state.update(&bytes);
+
+        scalar_from_bytes_wide(&bytes)
+    }
+}
+
+/// A transcript over BLAKE2s.
+#[derive(Clone)]
+pub struct Blake2sTranscript<S: PrimeField> {
+    state: Blake2sState,
+    _marker: PhantomData<S>,
+}
+
+impl<S: PrimeField> Blake2sTranscript<S> {
+    /// Creates a transcript for the application or protocol `label`.
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut transcript = Blake2sTranscript {
+            state: Blake2sParams::new().personal(b"bellman_").to_state(),
+            _marker: PhantomData,
+        };
+        transcript.domain_separate(label);
+
+        transcript
+    }
+
+    fn append(&mut self, tag: u8, label: &[u8], bytes: &[u8]) {
+        self.state.update(&[tag]);
+        self.state.update(&(label.len() as u64).to_le_bytes());
+        self.state.update(label);
+        self.state.update(&(bytes.len() as u64).to_le_bytes());
+        self.state.update(bytes);
+    }
+}
+
+impl<S: PrimeField> Transcript<S> for Blake2sTranscript<S> {
+    fn domain_separate(&mut self, label: &'static [u8]) {
+        self.append(b'D', label, &[]);
+    }
+
+    fn absorb_bytes(&mut self, label: &'static [u8], bytes: &[u8]) {
+        self.append(b'B', label, bytes);
+    }
+
+    fn absorb_scalar(&mut self, label: &'static [u8], scalar: &S) {
+        self.append(b'S', label, scalar.to_repr().as_ref());
+    }
+
+    fn squeeze_challenge(&mut self, label: &'static [u8]) -> S {
+        self.append(b'C', label, &[]);
+
+        // Reduce 512 bits of output so that the challenge is statistically
+        // close to uniform.
+        let mut bytes = [0u8; 64];
+        for (i, chunk) in bytes.chunks_mut(32).enumerate() {
+            let mut state = self.state.clone();
+            state.update(&[i as u8]);
+            chunk.copy_from_slice(state.finalize().as_bytes());
+        }
+        self.state.update(&bytes);
+
+        scalar_from_bytes_wide(&bytes)
+    }
+}
+
+/// A transcript over a Poseidon sponge in the challenge field.
+///
+/// Scalars are absorbed directly. Byte strings (including labels and encoded
+/// group elements) are length-prefixed and packed into field elements of
+/// `CAPACITY / 8` bytes each.
+#[derive(Clone)]
+pub struct PoseidonTranscript<S: PrimeField> {
+    sponge: Sponge<S>,
+}
+
+impl<S: PrimeField> PoseidonTranscript<S> {
+    /// Creates a transcript for the application or protocol `label` using
+    /// the given permutation parameters.
+    pub fn new(params: PoseidonParams<S>, label: &'static [u8]) -> Self {
+        let mut transcript = PoseidonTranscript {
+            sponge: Sponge::new(params, S::from(u64::from_le_bytes(*b"bellman_"))),
+        };
+        transcript.domain_separate(label);
+
+        transcript
+    }
+
+    fn append_bytes(&mut self, bytes: &[u8]) {
+        let chunk_size = (S::CAPACITY / 8) as usize;
+        let base = S::from(256);
+
+        self.sponge.absorb(S::from(bytes.len() as u64));
+        for chunk in bytes.chunks(chunk_size) {
+            self.sponge.absorb(
+                chunk
+                    .iter()
+                    .fold(S::zero(), |acc, b| acc * base + S::from(u64::from(*b))),
+            );
+        }
+    }
+
+    fn append(&mut self, tag: u8, label: &[u8]) {
+        self.sponge.absorb(S::from(u64::from(tag)));
+        self.append_bytes(label);
+    }
+}
+
+impl<S: PrimeField> Transcript<S> for PoseidonTranscript<S> {
+    fn domain_separate(&mut self, label: &'static [u8]) {
+        self.append(b'D', label);
+    }
+
+    fn absorb_bytes(&mut self, label: &'static [u8], bytes: &[u8]) {
+        self.append(b'B', label);
+        self.append_bytes(bytes);
+    }
+
+    fn absorb_scalar(&mut self, label: &'static [u8], scalar: &S) {
+        self.append(b'S', label);
+        self.sponge.absorb(*scalar);
+    }
+
+    fn squeeze_challenge(&mut self, label: &'static [u8]) -> S {
+        self.append(b'C', label);
+        let challenge = self.sponge.squeeze();
+        self.sponge.absorb(challenge);
+
+        challenge
+    }
+}
+
+/// Interprets `bytes` as a big-endian integer and reduces it into the field.
+pub fn scalar_from_bytes_wide<S: PrimeField>(bytes: &[u8]) -> S {
+    let base = S::from(256);
+    bytes
+        .iter()
+        .fold(S::zero(), |acc, b| acc * base + S::from(u64::from(*b)))
+}
+
+#[cfg(feature = "pairing")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::{G1Affine, Scalar};
+
+    fn check_transcript<T: Transcript<Scalar> + Clone>(fresh: impl Fn() -> T) {
+        // Challenges are deterministic.
+        let mut a = fresh();
+        let mut b = fresh();
+        a.absorb_scalar(b"x", &Scalar::one());
+        b.absorb_scalar(b"x", &Scalar::one());
+        assert_eq!(a.squeeze_challenge(b"c"), b.squeeze_challenge(b"c"));
+
+        // Consecutive challenges differ.
+        assert!(a.squeeze_challenge(b"c") != a.squeeze_challenge(b"c"));
+
+        // Labels, values and message types all affect the challenge.
+        let challenge = |f: &dyn Fn(&mut T)| {
+            let mut t = fresh();
+            f(&mut t);
+            t.squeeze_challenge(b"c")
+        };
+        let base = challenge(&|t| t.absorb_scalar(b"x", &Scalar::one()));
+        assert!(base != challenge(&|t| t.absorb_scalar(b"y", &Scalar::one())));
+        assert!(base != challenge(&|t| t.absorb_scalar(b"x", &Scalar::zero())));
+        assert!(base != challenge(&|t| t.absorb_u64(b"x", 1)));
+        assert!(base != challenge(&|t| t.absorb_bytes(b"x", &[1])));
+        assert!(
+            challenge(&|t| t.absorb_point(b"p", &G1Affine::generator()))
+                != challenge(&|t| t.absorb_point(b"p", &G1Affine::identity()))
+        );
+
+        // Domain separation changes subsequent challenges.
+        let mut separated = fresh();
+        separated.domain_separate(b"sub-protocol");
+        separated.absorb_scalar(b"x", &Scalar::one());
+        assert!(base != separated.squeeze_challenge(b"c"));
+    }
+
+    #[test]
+    fn blake2b_transcript() {
+        check_transcript(|| Blake2bTranscript::<Scalar>::new(b"test"));
+
+        let mut a = Blake2bTranscript::<Scalar>::new(b"test");
+        let mut b = Blake2bTranscript::<Scalar>::new(b"other");
+        assert!(a.squeeze_challenge(b"c") != b.squeeze_challenge(b"c"));
+
+        // The hash is part of the transcript.
+        let mut s = Blake2sTranscript::<Scalar>::new(b"test");
+        let mut b = Blake2bTranscript::<Scalar>::new(b"test");
+        assert!(s.squeeze_challenge(b"c") != b.squeeze_challenge(b"c"));
+    }
+
+    #[test]
+    fn blake2s_transcript() {
+        check_transcript(|| Blake2sTranscript::<Scalar>::new(b"test"));
+
+        let mut a = Blake2sTranscript::<Scalar>::new(b"test");
+        let mut b = Blake2sTranscript::<Scalar>::new(b"other");
+        assert!(a.squeeze_challenge(b"c") != b.squeeze_challenge(b"c"));
+    }
+
+    #[test]
+    fn poseidon_transcript() {
+        let params = PoseidonParams::<Scalar>::for_width(3);
+        check_transcript(|| PoseidonTranscript::new(params.clone(), b"test"));
+
+        let mut a = PoseidonTranscript::new(params.clone(), b"test");
+        let mut b = PoseidonTranscript::new(params, b"other");
+        assert!(a.squeeze_challenge(b"c") != b.squeeze_challenge(b"c"));
+    }
+
+    #[test]
+    fn wide_reduction() {
+        assert_eq!(scalar_from_bytes_wide::<Scalar>(&[]), Scalar::zero());
+        assert_eq!(scalar_from_bytes_wide::<Scalar>(&[1, 0]), Scalar::from(256));
+    }
+}
@@ -0,0 +1,442 @@
+//! Erasure of secret values from memory.
+//!
+//! [`groth16::create_proof`] overwrites the witness, the polynomials derived
+//! from it, and the blinding factors of the proof with zeros before their
+//! memory is released, and [`groth16::generate_parameters`] does the same
+//! with the toxic waste. Buffers that grow are moved to a larger allocation
+//! by hand, so that the old one can be erased first. The writes are
+//! volatile, so that they are not optimized away. Copies that the compiler
+//! keeps in registers or on the stack are out of reach, so this limits how
+//! long secrets stay in memory rather than guaranteeing that they are gone.
+//!
+//! With the `mlock` feature, on Unix, `set_lock_memory` opts into locking
+//! these buffers into RAM for their whole lifetime, so that they are never
+//! written to swap. Locking counts against the `RLIMIT_MEMLOCK` limit of the
+//! process; proving fails with [`SynthesisError::IoError`] if it is reached.
+//!
+//! [`groth16::create_proof`]: crate::groth16::create_proof
+//! [`groth16::generate_parameters`]: crate::groth16::generate_parameters
+//! [`SynthesisError::IoError`]: crate::SynthesisError::IoError
+
+use std::sync::atomic::{compiler_fence, Ordering};
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "groth16")]
+use bitvec::{array::BitArray, order::Lsb0, view::BitView};
+#[cfg(feature = "groth16")]
+use ff::PrimeField;
+#[cfg(feature = "groth16")]
+use std::sync::Arc;
+
+#[cfg(feature = "groth16")]
+use crate::domain::{EvaluationDomain, Scalar};
+#[cfg(feature = "groth16")]
+use crate::SynthesisError;
+
+#[cfg(feature = "mlock")]
+use crate::multicore::Global;
+#[cfg(feature = "mlock")]
+use std::collections::HashMap;
+#[cfg(feature = "mlock")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "mlock")]
+use std::sync::Mutex;
+
+/// Overwrites every element of `values` with `zero`.
+pub fn zeroize<T: Copy>(values: &mut [T], zero: T) {
+    for value in values.iter_mut() {
+        // Safety: `value` is a valid, aligned reference, and `T` is `Copy`
+        // so there is nothing to drop.
+        unsafe { std::ptr::write_volatile(value, zero) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(feature = "mlock")]
+static LOCK_MEMORY: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether secret buffers are locked into RAM. Off by default.
+#[cfg(feature = "mlock")]
+pub fn set_lock_memory(enabled: bool) {
+    LOCK_MEMORY.store(enabled, Ordering::SeqCst);
+}
+
+/// The number of live [`Lock`]s on each locked page, by address. The kernel
+/// does not count locks, and unlocks a page on the first `munlock`, so a
+/// page shared by several allocations is only unlocked with the last.
+#[cfg(feature = "mlock")]
+static LOCKED_PAGES: Global<Mutex<HashMap<usize, usize>>> = Global::new();
+
+#[cfg(feature = "mlock")]
+fn page_size() -> usize {
+    // Safety: `sysconf` has no preconditions.
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Returns the addresses of the pages that `len` bytes from `ptr` span.
+#[cfg(feature = "mlock")]
+fn pages(ptr: usize, len: usize) -> impl Iterator<Item = usize> {
+    let size = page_size();
+    (ptr / size * size..ptr + len).step_by(size)
+}
+
+/// A range of memory locked into RAM, which is unlocked when dropped.
+#[cfg(feature = "groth16")]
+pub(crate) struct Lock {
+    #[cfg(feature = "mlock")]
+    range: Option<(usize, usize)>,
+}
+
+#[cfg(feature = "groth16")]
+impl Lock {
+    fn none() -> Self {
+        Lock {
+            #[cfg(feature = "mlock")]
+            range: None,
+        }
+    }
+
+    /// Locks the allocation of `capacity` values from `ptr`, if locking is
+    /// enabled.
+    #[allow(unused_variables)]
+    fn new<T>(ptr: *const T, capacity: usize) -> Result<Self, SynthesisError> {
+        #[cfg(feature = "mlock")]
+        {
+            let len = capacity * std::mem::size_of::<T>();
+            if LOCK_MEMORY.load(Ordering::SeqCst) && len > 0 {
+                let ptr = ptr as usize;
+                let mut locked = LOCKED_PAGES.get().lock().unwrap();
+                // Safety: the range is an allocation of `capacity` values.
+                if unsafe { libc::mlock(ptr as *const libc::c_void, len) } != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+                for page in pages(ptr, len) {
+                    *locked.entry(page).or_insert(0) += 1;
+                }
+                return Ok(Lock {
+                    range: Some((ptr, len)),
+                });
+            }
+        }
+
+        Ok(Lock::none())
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl Drop for Lock {
+    fn drop(&mut self) {
+        #[cfg(feature = "mlock")]
+        {
+            if let Some((ptr, len)) = self.range {
+                let mut locked = LOCKED_PAGES.get().lock().unwrap();
+                for page in pages(ptr, len) {
+                    let count = locked.get_mut(&page).expect("the page was locked");
+                    *count -= 1;
+                    if *count == 0 {
+                        locked.remove(&page);
+                        // Safety: the page was locked by `Lock::new`, and no
+                        // other lock is left on it.
+                        unsafe { libc::munlock(page as *const libc::c_void, page_size()) };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A secret value, which is overwritten when dropped.
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+pub(crate) struct Secret<T: Copy> {
+    value: T,
+    zero: T,
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+impl<T: Copy> Secret<T> {
+    pub(crate) fn new(value: T, zero: T) -> Self {
+        Secret { value, zero }
+    }
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+impl<T: Copy> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+impl<T: Copy> DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(any(feature = "groth16", feature = "sonic"))]
+impl<T: Copy> Drop for Secret<T> {
+    fn drop(&mut self) {
+        zeroize(std::slice::from_mut(&mut self.value), self.zero);
+    }
+}
+
+/// A growable buffer of secret values, which is overwritten when dropped,
+/// and locked while locking is enabled.
+#[cfg(feature = "groth16")]
+pub(crate) struct SecretVec<T: Copy> {
+    values: Vec<T>,
+    zero: T,
+    lock: Lock,
+    // The first error from locking a new allocation, reported by `check`.
+    error: Option<SynthesisError>,
+}
+
+#[cfg(feature = "groth16")]
+impl<T: Copy> SecretVec<T> {
+    pub(crate) fn new(zero: T) -> Self {
+        SecretVec {
+            values: vec![],
+            zero,
+            lock: Lock::none(),
+            error: None,
+        }
+    }
+
+    /// Takes ownership of `values`, whose allocation is locked from here on.
+    fn adopt(values: Vec<T>, zero: T, lock: Lock) -> Self {
+        SecretVec {
+            values,
+            zero,
+            lock,
+            error: None,
+        }
+    }
+
+    /// Moves the values to an allocation for `capacity` of them, erasing
+    /// the old one.
+    fn grow(&mut self, capacity: usize) {
+        let mut values = Vec::with_capacity(capacity);
+        let lock = match Lock::new(values.as_ptr(), values.capacity()) {
+            Ok(lock) => lock,
+            Err(e) => {
+                self.error.get_or_insert(e);
+                Lock::none()
+            }
+        };
+        values.extend_from_slice(&self.values);
+
+        zeroize(&mut self.values, self.zero);
+        self.values = values;
+        self.lock = lock;
+    }
+
+    pub(crate) fn push(&mut self, value: T) {
+        if self.values.len() == self.values.capacity() {
+            self.grow(std::cmp::max(2 * self.values.capacity(), 16));
+        }
+        self.values.push(value);
+    }
+
+    /// Overwrites and removes the values after the first `len`.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len < self.values.len() {
+            zeroize(&mut self.values[len..], self.zero);
+            self.values.truncate(len);
+        }
+    }
+
+    /// Returns the first error from locking memory, if any.
+    pub(crate) fn check(&mut self) -> Result<(), SynthesisError> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<T: Copy> Deref for SecretVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.values
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<T: Copy> DerefMut for SecretVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<T: Copy> Drop for SecretVec<T> {
+    fn drop(&mut self) {
+        zeroize(&mut self.values, self.zero);
+    }
+}
+
+/// An evaluation domain over secret values, which is overwritten when
+/// dropped, and locked while locking is enabled.
+#[cfg(feature = "groth16")]
+pub(crate) struct SecretDomain<S: PrimeField> {
+    domain: Option<EvaluationDomain<S, Scalar<S>>>,
+    lock: Lock,
+}
+
+#[cfg(feature = "groth16")]
+impl<S: PrimeField> SecretDomain<S> {
+    /// Copies `values` into a new domain of `size` points.
+    pub(crate) fn from_coeffs(values: &[Scalar<S>], size: usize) -> Result<Self, SynthesisError> {
+        // Allocate the padded size up front, so that the domain does not
+        // move the values.
+        let mut coeffs = Vec::with_capacity(std::cmp::max(values.len(), size));
+        let lock = Lock::new(coeffs.as_ptr(), coeffs.capacity())?;
+        coeffs.extend_from_slice(values);
+
+        Ok(SecretDomain {
+            domain: Some(EvaluationDomain::with_size(coeffs, size)?),
+            lock,
+        })
+    }
+
+    pub(crate) fn into_coeffs(mut self) -> SecretVec<Scalar<S>> {
+        let coeffs = self.domain.take().unwrap().into_coeffs();
+        let lock = std::mem::replace(&mut self.lock, Lock::none());
+
+        SecretVec::adopt(coeffs, Scalar(S::zero()), lock)
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<S: PrimeField> Deref for SecretDomain<S> {
+    type Target = EvaluationDomain<S, Scalar<S>>;
+
+    fn deref(&self) -> &Self::Target {
+        self.domain.as_ref().unwrap()
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<S: PrimeField> DerefMut for SecretDomain<S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.domain.as_mut().unwrap()
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<S: PrimeField> Drop for SecretDomain<S> {
+    fn drop(&mut self) {
+        if let Some(domain) = self.domain.as_mut() {
+            zeroize(domain.as_mut(), Scalar(S::zero()));
+        }
+    }
+}
+
+/// The little-endian bits of secret scalars, shared with the
+/// multiexponentiations that use them as exponents, and overwritten when
+/// dropped if they are no longer shared.
+#[cfg(feature = "groth16")]
+pub(crate) struct SecretBits<V: BitView> {
+    bits: Arc<Vec<BitArray<Lsb0, V>>>,
+    _lock: Lock,
+}
+
+#[cfg(feature = "groth16")]
+impl<V: BitView + Send + Sync> SecretBits<V> {
+    pub(crate) fn new<S, I>(values: I) -> Result<Self, SynthesisError>
+    where
+        S: PrimeField<ReprBits = V>,
+        I: ExactSizeIterator<Item = S>,
+    {
+        let mut bits = Vec::with_capacity(values.len());
+        let lock = Lock::new(bits.as_ptr(), bits.capacity())?;
+        bits.extend(values.map(|s| s.to_le_bits()));
+
+        Ok(SecretBits {
+            bits: Arc::new(bits),
+            _lock: lock,
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub(crate) fn shared(&self) -> Arc<Vec<BitArray<Lsb0, V>>> {
+        self.bits.clone()
+    }
+}
+
+#[cfg(feature = "groth16")]
+impl<V: BitView> Drop for SecretBits<V> {
+    fn drop(&mut self) {
+        if let Some(bits) = Arc::get_mut(&mut self.bits) {
+            for bits in bits.iter_mut() {
+                zeroize(bits.as_raw_mut_slice(), Default::default());
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "groth16"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_vec_grows_and_truncates() {
+        let mut values = SecretVec::new(0u64);
+        for i in 1..100 {
+            values.push(i);
+        }
+        assert_eq!(values.len(), 99);
+        assert_eq!(values[98], 99);
+        values.truncate(10);
+        assert_eq!(&values[..], &(1..11).collect::<Vec<_>>()[..]);
+        assert!(values.check().is_ok());
+
+        let mut secret = Secret::new(5u64, 0);
+        *secret += 1;
+        assert_eq!(*secret, 6);
+    }
+
+    #[cfg(feature = "mlock")]
+    #[test]
+    fn locked_domain() {
+        use bls12_381::Scalar as Fr;
+        use ff::Field;
+
+        set_lock_memory(true);
+        let values = (0..5u64).map(|i| Scalar(Fr::from(i))).collect::<Vec<_>>();
+        let domain = SecretDomain::from_coeffs(&values, 8).unwrap();
+        assert_eq!(domain.as_ref().len(), 8);
+        let coeffs = domain.into_coeffs();
+        assert_eq!(coeffs[4].0, Fr::from(4));
+        assert!(coeffs[5].0.is_zero());
+        set_lock_memory(false);
+    }
+
+    #[cfg(feature = "mlock")]
+    #[test]
+    fn shared_pages() {
+        set_lock_memory(true);
+        let buffer = vec![0u8; 64];
+        let first = Lock::new(buffer.as_ptr(), 32).unwrap();
+        let second = Lock::new(buffer.as_ptr(), buffer.capacity()).unwrap();
+        set_lock_memory(false);
+
+        // The pages of the first lock stay locked for the second.
+        let range = pages(buffer.as_ptr() as usize, 32).collect::<Vec<_>>();
+        drop(first);
+        {
+            let locked = LOCKED_PAGES.get().lock().unwrap();
+            assert!(range.iter().all(|page| locked.contains_key(page)));
+        }
+        drop(second);
+    }
+}
@@ -0,0 +1,220 @@
+//! Runs the full `bellman-cli` flow on a small circuit.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::Command;
+
+use bellman::groth16::ceremony::{contribute, initial_transcript};
+use bellman::groth16::exporter::{Assignment, Convention, Form, Negatives, RawCircuit};
+use bellman::groth16::vectors::read_vectors;
+use bellman::groth16::Parameters;
+use bellman::{Circuit, ConstraintSystem, SynthesisError};
+use bls12_381::{Bls12, Scalar};
+
+/// Proves knowledge of `x` such that `x^3 = y` for a public `y`.
+struct CubeCircuit {
+    x: Option<Scalar>,
+}
+
+impl Circuit<Scalar> for CubeCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x_value = self.x;
+        let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+        let x2_value = x_value.map(|x| x.square());
+        let x2 = cs.alloc(
+            || "x2",
+            || x2_value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let y = cs.alloc_input(
+            || "y",
+            || match (x2_value, x_value) {
+                (Some(x2), Some(x)) => Ok(x2 * x),
+                _ => Err(SynthesisError::AssignmentMissing),
+            },
+        )?;
+
+        cs.enforce(|| "x2", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
+        cs.enforce(|| "y", |lc| lc + x2, |lc| lc + x, |lc| lc + y);
+
+        Ok(())
+    }
+}
+
+fn path(name: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("bellman-cli-{}-{}", std::process::id(), name));
+    path
+}
+
+fn run(args: &[&PathBuf], command: &str) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_bellman-cli"))
+        .arg(command)
+        .args(args)
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[test]
+fn cli_flow() {
+    let circuit = path("circuit");
+    let witness = path("witness");
+    let bad_witness = path("bad-witness");
+    let params = path("params");
+    let vk = path("vk");
+    let proof = path("proof");
+    let public = path("public");
+
+    RawCircuit::synthesize(CubeCircuit { x: None })
+        .unwrap()
+        .write(File::create(&circuit).unwrap())
+        .unwrap();
+    let assignment = Assignment::synthesize(CubeCircuit {
+        x: Some(Scalar::from(3)),
+    })
+    .unwrap();
+    assignment.write(File::create(&witness).unwrap()).unwrap();
+    let mut bad = assignment.clone();
+    bad.inputs[1] = Scalar::from(28);
+    bad.write(File::create(&bad_witness).unwrap()).unwrap();
+
+    assert!(run(&[&circuit], "info"));
+    let json = path("json");
+    assert!(run(&[&PathBuf::from("--json"), &circuit, &json], "export"));
+    assert!(std::fs::read_to_string(&json)
+        .unwrap()
+        .contains("\"num_inputs\": 2"));
+    let _ = std::fs::remove_file(&json);
+    assert!(run(&[&circuit, &witness], "check"));
+    assert!(!run(&[&circuit, &bad_witness], "check"));
+    assert!(run(&[&circuit, &params, &vk], "keygen"));
+    assert!(run(
+        &[&params, &circuit, &witness, &proof, &public],
+        "prove"
+    ));
+    assert!(run(&[&vk, &proof, &public], "verify"));
+    assert!(run(&[&params, &circuit, &witness], "lint"));
+    assert!(!run(&[&vk, &circuit, &witness], "lint"));
+
+    // Keys for a circuit exported in another coefficient convention prove
+    // the same statements.
+    let foreign = path("foreign-circuit");
+    RawCircuit::synthesize(CubeCircuit { x: None })
+        .unwrap()
+        .write_with(
+            File::create(&foreign).unwrap(),
+            &Convention {
+                form: Form::Montgomery,
+                negatives: Negatives::SignBit,
+            },
+        )
+        .unwrap();
+    let (montgomery, sign_bit) = (PathBuf::from("--montgomery"), PathBuf::from("--sign-bit"));
+    let foreign_params = path("foreign-params");
+    let foreign_vk = path("foreign-vk");
+    assert!(run(
+        &[
+            &montgomery,
+            &sign_bit,
+            &foreign,
+            &foreign_params,
+            &foreign_vk
+        ],
+        "keygen"
+    ));
+    assert!(run(
+        &[&foreign_params, &circuit, &witness, &proof, &public],
+        "prove"
+    ));
+    assert!(run(&[&foreign_vk, &proof, &public], "verify"));
+    for file in [&foreign, &foreign_params, &foreign_vk].iter() {
+        let _ = std::fs::remove_file(file);
+    }
+    assert!(run(
+        &[&params, &circuit, &witness, &proof, &public],
+        "prove"
+    ));
+
+    // Batch proving writes a proof and public inputs next to each witness.
+    let batch = (0..3)
+        .map(|i| {
+            let witness = path(&format!("batch-{}", i));
+            Assignment::synthesize(CubeCircuit {
+                x: Some(Scalar::from(i + 2)),
+            })
+            .unwrap()
+            .write(File::create(&witness).unwrap())
+            .unwrap();
+            witness
+        })
+        .collect::<Vec<_>>();
+    let budget = PathBuf::from("1");
+    let mut args = vec![&params, &circuit, &budget];
+    args.extend(batch.iter());
+    assert!(run(&args, "prove-batch"));
+    for witness in &batch {
+        let proof = PathBuf::from(format!("{}.proof", witness.display()));
+        let public = PathBuf::from(format!("{}.public", witness.display()));
+        assert!(run(&[&vk, &proof, &public], "verify"));
+        for file in [witness, &proof, &public].iter() {
+            let _ = std::fs::remove_file(file);
+        }
+    }
+    let missing = path("missing");
+    assert!(!run(&[&params, &circuit, &budget, &missing], "prove-batch"));
+
+    // A proof does not verify against other public inputs.
+    Assignment {
+        inputs: bad.inputs.clone(),
+        aux: vec![],
+    }
+    .write(File::create(&public).unwrap())
+    .unwrap();
+    assert!(!run(&[&vk, &proof, &public], "verify"));
+
+    // A ceremony with one contribution on top of the generated parameters.
+    let final_params = path("final-params");
+    let contribution = path("contribution");
+    let initial = Parameters::<Bls12>::read(File::open(&params).unwrap(), true).unwrap();
+    let mut contributed = initial.clone();
+    contribute(
+        &mut contributed,
+        &initial_transcript(&initial),
+        &mut rand::thread_rng(),
+    )
+    .write(File::create(&contribution).unwrap())
+    .unwrap();
+    contributed
+        .write(File::create(&final_params).unwrap())
+        .unwrap();
+    assert!(run(
+        &[&circuit, &params, &final_params, &contribution],
+        "ceremony"
+    ));
+    assert!(!run(&[&circuit, &params, &final_params], "ceremony"));
+
+    let vectors = path("vectors");
+    assert!(run(&[&PathBuf::from("seed"), &vectors], "vectors"));
+    let vectors_file = File::open(&vectors).unwrap();
+    assert!(read_vectors::<Bls12, _>(vectors_file, true)
+        .unwrap()
+        .iter()
+        .all(|v| v.check()));
+
+    for file in [
+        circuit,
+        witness,
+        bad_witness,
+        params,
+        vk,
+        proof,
+        public,
+        vectors,
+        final_params,
+        contribution,
+    ]
+    .iter()
+    {
+        let _ = std::fs::remove_file(file);
+    }
+}